@@ -0,0 +1,28 @@
+//! Parsing and checking the programs of each workload, at each of its sizes.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use formality_bench::{check, parse, WORKLOADS};
+
+fn checks(c: &mut Criterion) {
+    for workload in WORKLOADS {
+        let mut group = c.benchmark_group(workload.name);
+        for &size in workload.sizes {
+            let text = (workload.generate)(size);
+            group.bench_with_input(BenchmarkId::new("parse", size), &text, |b, text| {
+                b.iter(|| parse(text))
+            });
+            let program = parse(&text);
+            group.bench_with_input(BenchmarkId::new("check", size), &program, |b, program| {
+                b.iter(|| check(program))
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = checks
+}
+criterion_main!(benches);
@@ -0,0 +1,133 @@
+//! Representative workloads for benchmarking the checks, so that changes to the prover
+//! and to folding can be evaluated for performance. Each workload generates a program
+//! of a given size, which the checks accept; the benchmarks are in `benches/` and run
+//! with
+//!
+//! ```text
+//! cargo bench -p formality-bench
+//! ```
+
+use std::fmt::Write;
+
+use formality_check::{check_all_crates_errors, CheckError, Options};
+use formality_rust::grammar::Program;
+use formality_types::parse::try_term;
+
+#[cfg(test)]
+mod test;
+
+/// A family of programs that stresses one part of the checks, generated at any size.
+#[derive(Copy, Clone, Debug)]
+pub struct Workload {
+    pub name: &'static str,
+
+    /// The program of the given size, in the formality grammar.
+    pub generate: fn(usize) -> String,
+
+    /// Sizes that run in reasonable time, from small to large.
+    pub sizes: &'static [usize],
+}
+
+/// The workloads that the benchmarks run.
+pub const WORKLOADS: &[Workload] = &[
+    Workload {
+        name: "wide_coherence",
+        generate: wide_coherence,
+        sizes: &[8, 16, 32],
+    },
+    Workload {
+        name: "deep_auto_traits",
+        generate: deep_auto_traits,
+        sizes: &[4, 8, 16],
+    },
+    Workload {
+        name: "many_where_clauses",
+        generate: many_where_clauses,
+        sizes: &[8, 16, 32],
+    },
+];
+
+/// `n` impls of one trait for distinct types, and a blanket impl over a bound that none
+/// of them meets, so that coherence checks all pairs of the impls for overlap.
+pub fn wide_coherence(n: usize) -> String {
+    let mut items = String::new();
+    for i in 0..n {
+        writeln!(items, "struct S{i}<> where [] {{}}").unwrap();
+        writeln!(items, "impl<> Foo<> for S{i}<> where [] {{}}").unwrap();
+    }
+    format!(
+        "[
+            crate core {{
+                trait Foo<> where [] {{}}
+                trait Bar<> where [] {{}}
+                impl<ty T> Foo<> for T where [T: Bar<>] {{}}
+                {items}
+            }}
+        ]"
+    )
+}
+
+/// A value whose type nests `depth` wrappers, each `Send` if what it wraps is, around a
+/// `PhantomData<u32>`, so that proving it `Send` recurses through all of them.
+pub fn deep_auto_traits(depth: usize) -> String {
+    let mut items = String::new();
+    let mut ty = "PhantomData<u32>".to_string();
+    for i in 0..depth {
+        writeln!(items, "struct W{i}<ty T> where [] {{ value: T }}").unwrap();
+        writeln!(
+            items,
+            "impl<ty T> Send<> for W{i}<T> where [T: Send<>] {{}}"
+        )
+        .unwrap();
+        ty = format!("W{i}<{ty}>");
+    }
+    format!(
+        "[
+            crate core {{
+                trait Send<> where [] {{}}
+                struct PhantomData<ty T> where [] {{}}
+                impl<> Send<> for u32 where [] {{}}
+                fn require_send<ty T>(T) -> () where [T: Send<>] {{trusted}}
+                {items}
+                fn deep<>({ty}) -> () where [] |x| {{ require_send(x) }}
+            }}
+        ]"
+    )
+}
+
+/// `n` traits implemented for `u32`, a trait with all of them as supertraits, and fns
+/// that require and assume all of them, so that the where-clauses are elaborated and
+/// proven many times.
+pub fn many_where_clauses(n: usize) -> String {
+    let mut items = String::new();
+    let mut bounds = vec![];
+    for i in 0..n {
+        writeln!(items, "trait T{i}<> where [] {{}}").unwrap();
+        writeln!(items, "impl<> T{i}<> for u32 where [] {{}}").unwrap();
+        bounds.push(format!("A: T{i}<>"));
+    }
+    let bounds = bounds.join(", ");
+    let supertraits = bounds.replace("A:", "Self:");
+    format!(
+        "[
+            crate core {{
+                {items}
+                trait All<> where [{supertraits}] {{}}
+                impl<> All<> for u32 where [] {{}}
+                fn require<ty A>(A) -> () where [{bounds}] {{trusted}}
+                fn assume<ty A>(A) -> () where [A: All<>] |a| {{ require(a) }}
+                fn concrete<>(u32) -> () where [] |x| {{ require(x) }}
+            }}
+        ]"
+    )
+}
+
+/// Parses a program generated by a workload.
+pub fn parse(program: &str) -> Program {
+    try_term(program).unwrap()
+}
+
+/// Runs all the checks on `program`.
+pub fn check(program: &Program) -> Vec<CheckError> {
+    check_all_crates_errors(program, &Options::default())
+}
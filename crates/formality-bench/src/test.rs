@@ -0,0 +1,12 @@
+use crate::{check, parse, WORKLOADS};
+
+/// The workloads measure the checks of programs that are accepted, not how fast an
+/// error is found.
+#[test]
+fn workloads_pass() {
+    for workload in WORKLOADS {
+        let program = parse(&(workload.generate)(workload.sizes[0]));
+        let errors = check(&program);
+        assert!(errors.is_empty(), "{}: {errors:?}", workload.name);
+    }
+}
@@ -1,25 +1,42 @@
 use formality_prove::Env;
-use formality_rust::grammar::{Adt, AdtBoundData, Field, Variant};
-use formality_types::grammar::Fallible;
+use formality_rust::{
+    grammar::{Adt, AdtBoundData, Field, Variant},
+    prove::ToWcs,
+};
+use formality_types::grammar::{Fallible, Wcs};
 
 impl super::Check<'_> {
     pub(super) fn check_adt(&self, adt: &Adt) -> Fallible<()> {
-        let Adt { id: _, binder } = adt;
+        let Adt {
+            visibility: _,
+            id: _,
+            binder,
+        } = adt;
 
         let mut env = Env::default();
 
+        let data = env.instantiate_universally(binder);
+
+        // As in rustc, the outlives bounds that the fields need, like `T: 'a` for a
+        // field of type `&'a T`, are implied rather than declared.
+        let assumptions: Wcs = (&data.where_clauses, data.implied_outlives()).to_wcs();
         let AdtBoundData {
             where_clauses,
             variants,
-        } = env.instantiate_universally(binder);
+        } = data;
 
-        self.prove_where_clauses_well_formed(&env, &where_clauses, &where_clauses)?;
+        self.prove_where_clauses_well_formed(&env, &assumptions, &where_clauses)?;
 
         // FIXME: check names are unique or integers from 0..n
 
         for Variant { name: _, fields } in &variants {
-            for Field { name: _, ty } in fields {
-                self.prove_goal(&env, &where_clauses, ty.well_formed())?;
+            for Field {
+                visibility: _,
+                name: _,
+                ty,
+            } in fields
+            {
+                self.prove_goal(&env, &assumptions, ty.well_formed())?;
             }
         }
 
@@ -0,0 +1,325 @@
+//! Borrow checking of MIR fn bodies.
+//!
+//! This is a simplified, location-insensitive take on Polonius:
+//!
+//! * each `ref` rvalue issues a *loan* of a place, tagged with the lifetime of the reference;
+//! * the outlives constraints from type checking form a subset graph: if `'a: 'b`,
+//!   then every loan in `'a` is also in `'b`;
+//! * a lifetime is *live* at a point if it appears in the type of a local that may
+//!   be used later; universal lifetimes (and `'static`) are live everywhere;
+//! * a loan is *active* at a point if it may have been issued on some path to that
+//!   point (and not killed by overwriting the borrowed local since), and it is
+//!   contained in a live lifetime.
+//!
+//! Accessing a place that overlaps the place of an active loan is an error unless
+//! both the loan and the access are shared.
+//!
+//! Two-phase borrows act like shared loans until they are activated by the first
+//! use of the reference; the activation itself is checked like a mutable borrow.
+
+use anyhow::bail;
+use formality_rust::grammar::mir::{LocalId, Projection};
+use formality_types::{
+    collections::{Map, Set},
+    grammar::{Fallible, Lt, LtData, ParameterKind, RefKind},
+    visit::Visit,
+};
+
+use crate::{
+    dataflow::{overlaps, AccessKind, Effects, Location, MirCfg},
+    mir::{MirTypeckResults, OutlivesConstraint},
+    Diagnostic,
+};
+
+pub(crate) fn borrow_check(body: &MirTypeckResults) -> Fallible<()> {
+    BorrowCheck {
+        cfg: MirCfg::new(&body.local_decls, &body.basic_block_decls),
+        outlives: &body.outlives,
+    }
+    .check()
+}
+
+impl AccessKind {
+    fn conflicts_with(self, loan: &RefKind) -> bool {
+        match loan {
+            RefKind::Mut => true,
+            RefKind::Shared => !matches!(self, AccessKind::Read | AccessKind::BorrowShared),
+        }
+    }
+
+    /// The rustc error code for this access conflicting with a loan of kind `loan`.
+    fn conflict_code(self, loan: &RefKind) -> &'static str {
+        match (self, loan) {
+            (AccessKind::Read, _) => "E0503",
+            (AccessKind::Write, _) => "E0506",
+            (AccessKind::Move | AccessKind::Drop, _) => "E0505",
+            (AccessKind::BorrowMut, RefKind::Mut) => "E0499",
+            (AccessKind::BorrowShared | AccessKind::BorrowMut, _) => "E0502",
+        }
+    }
+}
+
+struct BorrowCheck<'b> {
+    cfg: MirCfg<'b>,
+    outlives: &'b [OutlivesConstraint],
+}
+
+impl BorrowCheck<'_> {
+    fn check(&self) -> Fallible<()> {
+        let live_locals = self.live_locals();
+        let reaching_loans = self.propagate_loans(|_, effects| effects.loans.clone());
+        let activated_loans = self.propagate_loans(|location, effects| {
+            self.activations(&reaching_loans[&location], effects)
+        });
+        let loans_in = self.loans_in_lifetimes();
+
+        // Loans flowing into a universal lifetime must outlive the fn body.
+        let mut escaping_loans = Map::new();
+        for (lt, loans) in &loans_in {
+            if is_universal(lt) {
+                for &l in loans {
+                    escaping_loans.entry(l).or_insert(lt);
+                }
+            }
+        }
+        for (&l, &lt) in &escaping_loans {
+            let loan = &self.cfg.loans[l];
+            if !loan.place.projections.contains(&Projection::Deref) {
+                let mut message = format!(
+                    "`{:?}` borrowed at {} does not live long enough\n\
+                     the borrow must outlive `{lt:?}`, because",
+                    loan.place,
+                    self.cfg.describe(loan.location)
+                );
+                for c in self.outlives_path(&loan.lt, lt) {
+                    message += &format!("\n    `{:?}: {:?}` is required by {}", c.a, c.b, c.origin);
+                }
+                bail!(Diagnostic::new("E0597", message).with_primary(&loan.place));
+            }
+        }
+
+        for (location, effects) in self.cfg.locations() {
+            let live_lts: Set<Lt> = live_locals[&location]
+                .iter()
+                .flat_map(|local| self.lifetimes_of(local))
+                .collect();
+            let active_loans: Vec<usize> = reaching_loans[&location]
+                .iter()
+                .copied()
+                .filter(|l| {
+                    escaping_loans.contains_key(l)
+                        || live_lts
+                            .iter()
+                            .any(|lt| loans_in.get(lt).is_some_and(|loans| loans.contains(l)))
+                })
+                .collect();
+
+            // Until it is activated, a two-phase borrow acts like a shared one.
+            let loan_kind = |l: usize| {
+                let loan = &self.cfg.loans[l];
+                if loan.reserved_for.is_some() && !activated_loans[&location].contains(&l) {
+                    RefKind::Shared
+                } else {
+                    loan.kind.clone()
+                }
+            };
+
+            for &l in &active_loans {
+                let loan = &self.cfg.loans[l];
+                for (access, place) in &effects.accesses {
+                    if overlaps(place, &loan.place) && access.conflicts_with(&loan_kind(l)) {
+                        bail!(Diagnostic::new(
+                            access.conflict_code(&loan_kind(l)),
+                            format!(
+                                "cannot {} `{place:?}` at {} because `{:?}` is borrowed at {}",
+                                access.verb(),
+                                self.cfg.describe(location),
+                                loan.place,
+                                self.cfg.describe(loan.location),
+                            )
+                        )
+                        .with_primary(place)
+                        .with_related(&loan.place));
+                    }
+                }
+            }
+
+            // Activating a two-phase borrow is a mutable borrow of its place.
+            for a in self.activations(&reaching_loans[&location], effects) {
+                let activated = &self.cfg.loans[a];
+                for &l in active_loans.iter().filter(|&&l| l != a) {
+                    let loan = &self.cfg.loans[l];
+                    if overlaps(&activated.place, &loan.place)
+                        && AccessKind::BorrowMut.conflicts_with(&loan_kind(l))
+                    {
+                        bail!(Diagnostic::new(
+                            AccessKind::BorrowMut.conflict_code(&loan_kind(l)),
+                            format!(
+                                "cannot activate the two-phase borrow of `{:?}` from {} at {} because `{:?}` is borrowed at {}",
+                                activated.place,
+                                self.cfg.describe(activated.location),
+                                self.cfg.describe(location),
+                                loan.place,
+                                self.cfg.describe(loan.location),
+                            )
+                        )
+                        .with_primary(&activated.place)
+                        .with_related(&loan.place));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The lifetimes appearing in the type of `local`.
+    fn lifetimes_of(&self, local: &LocalId) -> Vec<Lt> {
+        self.cfg
+            .local_decls
+            .iter()
+            .filter(|decl| decl.name == *local)
+            .flat_map(|decl| decl.ty.free_variables())
+            .filter(|v| v.kind() == ParameterKind::Lt)
+            .map(|v| Lt::new(LtData::Variable(v)))
+            .collect()
+    }
+
+    /// For each location, the locals that may be used at or after it before being overwritten.
+    fn live_locals(&self) -> Map<Location, Set<LocalId>> {
+        let mut live_on_entry: Vec<Set<LocalId>> = vec![Set::new(); self.cfg.effects.len()];
+        let mut result = Map::new();
+        loop {
+            let mut changed = false;
+            for block in (0..self.cfg.effects.len()).rev() {
+                let mut live: Set<LocalId> = self.cfg.successors[block]
+                    .iter()
+                    .flat_map(|&s| live_on_entry[s].iter().cloned())
+                    .collect();
+                for (index, effects) in self.cfg.effects[block].iter().enumerate().rev() {
+                    for local in effects.defs() {
+                        live.remove(local);
+                    }
+                    live.extend(effects.uses().cloned());
+                    result.insert(Location { block, index }, live.clone());
+                }
+                if live != live_on_entry[block] {
+                    live_on_entry[block] = live;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return result;
+            }
+        }
+    }
+
+    /// The two-phase borrows among `reaching` that are activated by `effects`,
+    /// i.e., whose reference is used there.
+    fn activations(&self, reaching: &Set<usize>, effects: &Effects) -> Vec<usize> {
+        let uses: Set<&LocalId> = effects.uses().collect();
+        reaching
+            .iter()
+            .copied()
+            .filter(|&l| {
+                self.cfg.loans[l]
+                    .reserved_for
+                    .as_ref()
+                    .is_some_and(|local| uses.contains(local))
+            })
+            .collect()
+    }
+
+    /// For each location, the loans generated by `gen` on some path to it and
+    /// whose borrowed local has not been overwritten since.
+    fn propagate_loans(
+        &self,
+        gen: impl Fn(Location, &Effects) -> Vec<usize>,
+    ) -> Map<Location, Set<usize>> {
+        let mut reaching_on_entry: Vec<Set<usize>> = vec![Set::new(); self.cfg.effects.len()];
+        let mut result = Map::new();
+        loop {
+            let mut changed = false;
+            for block in 0..self.cfg.effects.len() {
+                let mut reaching = reaching_on_entry[block].clone();
+                for (index, effects) in self.cfg.effects[block].iter().enumerate() {
+                    let location = Location { block, index };
+                    result.insert(location, reaching.clone());
+                    let defs: Set<&LocalId> = effects.defs().collect();
+                    reaching.retain(|&l| !defs.contains(&self.cfg.loans[l].place.local_id));
+                    reaching.extend(gen(location, effects));
+                }
+                for &successor in &self.cfg.successors[block] {
+                    let len = reaching_on_entry[successor].len();
+                    reaching_on_entry[successor].extend(reaching.iter().copied());
+                    changed |= reaching_on_entry[successor].len() != len;
+                }
+            }
+            if !changed {
+                return result;
+            }
+        }
+    }
+
+    /// For each lifetime, the loans it may contain: the loans tagged with it,
+    /// plus those flowing into it through outlives constraints.
+    fn loans_in_lifetimes(&self) -> Map<Lt, Set<usize>> {
+        let mut loans_in: Map<Lt, Set<usize>> = Map::new();
+        for (l, loan) in self.cfg.loans.iter().enumerate() {
+            loans_in.entry(loan.lt.clone()).or_default().insert(l);
+        }
+        loop {
+            let mut changed = false;
+            for OutlivesConstraint { a, b, origin: _ } in self.outlives {
+                let Some(loans) = loans_in.get(a).cloned() else {
+                    continue;
+                };
+                let b_loans = loans_in.entry(b.clone()).or_default();
+                let len = b_loans.len();
+                b_loans.extend(loans);
+                changed |= b_loans.len() != len;
+            }
+            if !changed {
+                return loans_in;
+            }
+        }
+    }
+}
+
+impl BorrowCheck<'_> {
+    /// A shortest chain of outlives constraints from `from` to `to`, i.e., the reason
+    /// why the loans in `from` are also in `to`.
+    fn outlives_path(&self, from: &Lt, to: &Lt) -> Vec<&OutlivesConstraint> {
+        let mut reached: Map<&Lt, Option<&OutlivesConstraint>> = Map::new();
+        reached.insert(from, None);
+        let mut frontier = vec![from];
+        while !frontier.is_empty() && !reached.contains_key(to) {
+            let mut next = vec![];
+            for lt in frontier {
+                for c in self.outlives.iter().filter(|c| c.a == *lt) {
+                    if !reached.contains_key(&c.b) {
+                        reached.insert(&c.b, Some(c));
+                        next.push(&c.b);
+                    }
+                }
+            }
+            frontier = next;
+        }
+
+        let mut path = vec![];
+        let mut lt = to;
+        while let Some(Some(c)) = reached.get(lt) {
+            path.push(*c);
+            lt = &c.a;
+        }
+        path.reverse();
+        path
+    }
+}
+
+fn is_universal(lt: &Lt) -> bool {
+    match lt.data() {
+        LtData::Static => true,
+        LtData::Variable(v) => v.is_universal(),
+    }
+}
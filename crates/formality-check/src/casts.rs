@@ -0,0 +1,176 @@
+//! Checking of `as` casts.
+//!
+//! Following rustc, a cast `e as T` is valid if the type of `e` coerces to `T`,
+//! or if the pair of types falls into one of the [`CastKind`]s. As the type of
+//! `e` is often only known after inference, casts are checked once the
+//! inference variables have been solved.
+
+use anyhow::bail;
+use formality_prove::Env;
+use formality_rust::grammar::{AdtBoundData, FnBoundData, VariantId};
+use formality_types::{
+    cast::{Downcast, Upcast},
+    grammar::{Fallible, RefKind, Relation, RigidName, RigidTy, ScalarId, Ty, TyData, Wcs},
+};
+
+use crate::{Check, Diagnostic};
+
+/// The kinds of casts, named after the corresponding kinds in rustc.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum CastKind {
+    /// The source type coerces to the target type, e.g. `&T as *const T`,
+    /// or a fn item to a fn pointer with the same signature.
+    Coercion,
+    /// Between integer and float types, e.g. `u32 as u8` or `f64 as i32`.
+    Numeric,
+    /// From `bool` to an integer type.
+    PrimInt,
+    /// From an enum whose variants have no fields to an integer type.
+    Enum,
+    /// Between raw pointers, e.g. `*const T as *mut U`.
+    PtrPtr,
+    /// From a raw pointer to an integer type.
+    PtrAddr,
+    /// From an integer type to a raw pointer.
+    AddrPtr,
+    /// From a fn pointer to a raw pointer.
+    FnPtrPtr,
+    /// From a fn pointer to an integer type.
+    FnPtrAddr,
+}
+
+/// The types taking part in casts other than coercions.
+enum CastTy {
+    Int,
+    Float,
+    Bool,
+    Enum,
+    Ptr,
+    Ref(RefKind, Ty),
+    FnPtr,
+}
+
+impl Check<'_> {
+    /// Checks each cast from the source to the target type in `casts`,
+    /// whose inference variables have been solved in `env`.
+    pub(crate) fn check_casts(
+        &self,
+        env: &Env,
+        assumptions: &Wcs,
+        casts: &[(Ty, Ty)],
+    ) -> Fallible<()> {
+        for (source_ty, target_ty) in casts {
+            let (kind, obligations) = self.classify_cast(source_ty, target_ty)?;
+            let cs = formality_prove::prove(self.decls, env, assumptions, &obligations);
+            if !cs.iter().any(|c| c.known_true) {
+                bail!(Diagnostic::new(
+                    "E0606",
+                    format!("casting `{source_ty:?}` as `{target_ty:?}` is invalid")
+                )
+                .with_primary(source_ty));
+            }
+            tracing::debug!(?source_ty, ?target_ty, ?kind);
+        }
+        Ok(())
+    }
+
+    /// Determines the kind of the cast `source_ty as target_ty`, along with the
+    /// obligations that must hold for it to be valid.
+    fn classify_cast(&self, source_ty: &Ty, target_ty: &Ty) -> Fallible<(CastKind, Wcs)> {
+        if let TyData::Variable(_) = source_ty.data() {
+            bail!(Diagnostic::new(
+                "E0282",
+                format!("cannot cast `{source_ty:?}`, its type must be known at this point")
+            ));
+        }
+
+        // Casting a fn item to a fn pointer is a coercion; for other casts,
+        // the fn item is reified to a fn pointer first.
+        if let Some(RigidTy {
+            name: RigidName::FnDef(fn_id),
+            parameters,
+        }) = source_ty.downcast()
+        {
+            if !matches!(self.cast_ty(target_ty), Some(CastTy::FnPtr)) {
+                let f = self.program.fn_named(&fn_id)?;
+                let FnBoundData {
+                    defaults: _,
+                    self_param: _,
+                    input_tys,
+                    output_ty,
+                    where_clauses: _,
+                    body: _,
+                } = f.binder.instantiate_with(&parameters)?;
+                let abi = self.program.fn_abi(&fn_id);
+                return self.classify_cast(&Ty::abi_fn_ptr(abi, &input_tys, &output_ty), target_ty);
+            }
+        }
+
+        let kind = match (self.cast_ty(source_ty), self.cast_ty(target_ty)) {
+            (Some(CastTy::Int | CastTy::Float), Some(CastTy::Int | CastTy::Float)) => {
+                CastKind::Numeric
+            }
+            (Some(CastTy::Bool), Some(CastTy::Int)) => CastKind::PrimInt,
+            (Some(CastTy::Enum), Some(CastTy::Int)) => CastKind::Enum,
+            (Some(CastTy::Ptr), Some(CastTy::Ptr)) => CastKind::PtrPtr,
+            (Some(CastTy::Ptr), Some(CastTy::Int)) => CastKind::PtrAddr,
+            (Some(CastTy::Int), Some(CastTy::Ptr)) => CastKind::AddrPtr,
+            (Some(CastTy::FnPtr), Some(CastTy::Ptr)) => CastKind::FnPtrPtr,
+            (Some(CastTy::FnPtr), Some(CastTy::Int)) => CastKind::FnPtrAddr,
+
+            // `&mut T as *mut T`, `&mut T as *const T`, and `&T as *const T`.
+            (Some(CastTy::Ref(source_kind, referent)), Some(CastTy::Ptr)) => {
+                let (target_kind, pointee) = target_ty.as_raw_ptr_ty().unwrap();
+                if let (RefKind::Shared, RefKind::Mut) = (source_kind, target_kind) {
+                    bail!(Diagnostic::new(
+                        "E0606",
+                        format!("casting `{source_ty:?}` as `{target_ty:?}` is invalid")
+                    )
+                    .with_primary(source_ty));
+                }
+                return Ok((CastKind::Coercion, Relation::eq(referent, pointee).upcast()));
+            }
+
+            _ => {
+                return Ok((
+                    CastKind::Coercion,
+                    Relation::coerce(source_ty, target_ty).upcast(),
+                ))
+            }
+        };
+        Ok((kind, Wcs::t()))
+    }
+
+    fn cast_ty(&self, ty: &Ty) -> Option<CastTy> {
+        if let Some((kind, _, referent)) = ty.as_ref_ty() {
+            return Some(CastTy::Ref(kind, referent));
+        }
+        let RigidTy { name, parameters } = ty.downcast()?;
+        match name {
+            RigidName::ScalarId(ScalarId::Bool) => Some(CastTy::Bool),
+            RigidName::ScalarId(s) if s.is_float() => Some(CastTy::Float),
+            RigidName::ScalarId(_) => Some(CastTy::Int),
+            RigidName::RawPtr(_) => Some(CastTy::Ptr),
+            RigidName::FnPtr(..) => Some(CastTy::FnPtr),
+            RigidName::AdtId(adt_id) => {
+                let adt = self.program.adt_named(&adt_id).ok()?;
+                let AdtBoundData {
+                    where_clauses: _,
+                    variants,
+                } = adt.binder.instantiate_with(&parameters).ok()?;
+                let fieldless_enum = !variants.is_empty()
+                    && variants
+                        .iter()
+                        .all(|v| v.fields.is_empty() && v.name != VariantId::for_struct());
+                fieldless_enum.then_some(CastTy::Enum)
+            }
+            RigidName::Ref(_)
+            | RigidName::Tuple(_)
+            | RigidName::FnDef(_)
+            | RigidName::Never
+            | RigidName::Closure(_)
+            | RigidName::Coroutine
+            | RigidName::Dyn(_) => None,
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//! Capture analysis and closure kind inference.
+//!
+//! Like rustc, a closure captures each variable of the enclosing fn that its
+//! body uses, in the least demanding way that supports all of the uses: by
+//! reference if the body only reads it, by mutable reference if the body
+//! borrows it mutably, and by value if the body moves it. Moving a variable
+//! whose type is `Copy` only reads it. A `move` closure captures all variables
+//! by value, but its kind still depends on the uses: a closure that moves out of
+//! a captured variable is `FnOnce`, one that borrows one mutably is `FnMut`,
+//! and any other closure is `Fn`.
+//!
+//! As in the 2018 edition, closures capture whole variables rather than the
+//! places within them that the body uses.
+
+use formality_rust::grammar::expr::{Arm, Block, CaptureBy, Expr, ExprData, Stmt, ValueId};
+use formality_types::{
+    cast::{Upcast, UpcastFrom},
+    cast_impl,
+    collections::Map,
+    grammar::{lang_items::copy_trait, ClosureKind, FnId, Wcs},
+    judgment_fn,
+};
+
+use crate::typeck::Locals;
+
+/// How a closure body uses a variable, from the least to the most demanding.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CaptureKind {
+    Ref,
+    MutRef,
+    Value,
+}
+
+cast_impl!(CaptureKind);
+
+impl CaptureKind {
+    /// The use of a place that is dereferenced in a context with this use.
+    fn through_deref(self) -> CaptureKind {
+        match self {
+            CaptureKind::Value => CaptureKind::Ref,
+            kind => kind,
+        }
+    }
+
+    /// The use of a closure of kind `kind` when calling it.
+    fn for_call(kind: ClosureKind) -> CaptureKind {
+        match kind {
+            ClosureKind::Fn => CaptureKind::Ref,
+            ClosureKind::FnMut => CaptureKind::MutRef,
+            ClosureKind::FnOnce => CaptureKind::Value,
+        }
+    }
+}
+
+/// The variables a closure body uses, each with its most demanding use.
+#[derive(Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Captures {
+    uses: Map<ValueId, CaptureKind>,
+}
+
+cast_impl!(Captures);
+
+impl Captures {
+    fn one(id: &ValueId, kind: CaptureKind) -> Captures {
+        Captures {
+            uses: Some((id.clone(), kind)).into_iter().collect(),
+        }
+    }
+
+    /// The kind of a closure whose body has these uses.
+    pub fn closure_kind(&self) -> ClosureKind {
+        match self.uses.values().max() {
+            Some(CaptureKind::Value) => ClosureKind::FnOnce,
+            Some(CaptureKind::MutRef) => ClosureKind::FnMut,
+            Some(CaptureKind::Ref) | None => ClosureKind::Fn,
+        }
+    }
+
+    /// How a closure with these uses captures each variable, in order of the variable names.
+    pub fn capture_kinds(
+        &self,
+        capture_by: &CaptureBy,
+    ) -> impl Iterator<Item = (&ValueId, CaptureKind)> + '_ {
+        let by_value = matches!(capture_by, CaptureBy::Value);
+        self.uses.iter().map(move |(id, kind)| {
+            if by_value {
+                (id, CaptureKind::Value)
+            } else {
+                (id, *kind)
+            }
+        })
+    }
+
+    /// The uses by the enclosing body of the variables that a closure with these uses captures.
+    fn for_enclosing(&self, capture_by: &CaptureBy) -> Captures {
+        Captures {
+            uses: self
+                .capture_kinds(capture_by)
+                .map(|(id, kind)| (id.clone(), kind))
+                .collect(),
+        }
+    }
+}
+
+impl<A, B> UpcastFrom<(A, B)> for Captures
+where
+    A: Upcast<Captures>,
+    B: Upcast<Captures>,
+{
+    fn upcast_from((a, b): (A, B)) -> Self {
+        let Captures { mut uses } = a.upcast();
+        let b: Captures = b.upcast();
+        for (id, kind) in b.uses {
+            let entry = uses.entry(id).or_insert(kind);
+            *entry = (*entry).max(kind);
+        }
+        Captures { uses }
+    }
+}
+
+impl<A, B, C> UpcastFrom<(A, B, C)> for Captures
+where
+    A: Upcast<Captures>,
+    B: Upcast<Captures>,
+    C: Upcast<Captures>,
+{
+    fn upcast_from((a, b, c): (A, B, C)) -> Self {
+        (a, (b, c)).upcast()
+    }
+}
+
+judgment_fn! {
+    /// Computes the uses of the variables in `locals` by `expr`, when `expr`
+    /// is itself used as `kind` (e.g., `Ref` for the operand of `&`),
+    /// along with the obligations for those uses to be valid.
+    pub fn captures(
+        locals: Locals,
+        kind: CaptureKind,
+        expr: Expr,
+    ) => (Captures, Wcs) {
+        debug(expr, kind, locals)
+
+        (
+            (if let ExprData::Literal(_) | ExprData::Numeric(_) | ExprData::Unit = expr.data())
+            --- ("constant")
+            (captures(_locals, _kind, expr) => (Captures::default(), Wcs::t()))
+        )
+
+        (
+            (if locals.get(&id).is_some())
+            --- ("variable")
+            (captures(locals, kind, ExprData::Var(id)) => (Captures::one(&id, kind), Wcs::t()))
+        )
+
+        (
+            (if kind == CaptureKind::Value)
+            (if let Some(ty) = locals.get(&id))
+            --- ("copied variable")
+            (captures(locals, kind, ExprData::Var(id)) => (Captures::one(&id, CaptureKind::Ref), copy_trait().with(&ty, ())))
+        )
+
+        (
+            (if locals.get(&id).is_none())
+            --- ("item or shadowed variable")
+            (captures(locals, _kind, ExprData::Var(id)) => (Captures::default(), Wcs::t()))
+        )
+
+        (
+            (captures_exprs(&locals, &args) => (arg_captures, obligations))
+            (let callee = callee_captures(&locals, &id))
+            --- ("call")
+            (captures(locals, _kind, ExprData::Call(id, args)) => ((&arg_captures, &callee), &obligations))
+        )
+
+        (
+            (captures(locals, kind, base) => r)
+            --- ("field")
+            (captures(locals, kind, ExprData::Field(base, _field)) => r)
+        )
+
+        (
+            (captures(locals, CaptureKind::Ref, e) => r)
+            --- ("ref")
+            (captures(locals, _kind, ExprData::Ref(e)) => r)
+        )
+
+        (
+            (captures(locals, CaptureKind::MutRef, e) => r)
+            --- ("ref mut")
+            (captures(locals, _kind, ExprData::RefMut(e)) => r)
+        )
+
+        (
+            (captures(locals, kind.through_deref(), e) => r)
+            --- ("deref")
+            (captures(locals, kind, ExprData::Deref(e)) => r)
+        )
+
+        (
+            (captures_block(locals, block) => r)
+            --- ("block")
+            (captures(locals, _kind, ExprData::Block(block)) => r)
+        )
+
+        (
+            (captures_block(locals, block) => r)
+            --- ("unsafe block")
+            (captures(locals, _kind, ExprData::Unsafe(block)) => r)
+        )
+
+        (
+            (captures(&locals, CaptureKind::Value, cond) => (cond_captures, cond_obligations))
+            (captures_block(&locals, &then_block) => (then_captures, then_obligations))
+            (captures_block(&locals, &else_block) => (else_captures, else_obligations))
+            --- ("if")
+            (captures(locals, _kind, ExprData::If(cond, then_block, else_block)) => (
+                (&cond_captures, &then_captures, &else_captures),
+                (&cond_obligations, &then_obligations, &else_obligations),
+            ))
+        )
+
+        (
+            (captures(&locals, CaptureKind::Value, scrutinee) => (scrutinee_captures, scrutinee_obligations))
+            (captures_arms(&locals, &arms) => (arm_captures, arm_obligations))
+            --- ("match")
+            (captures(locals, _kind, ExprData::Match(scrutinee, arms)) => (
+                (&scrutinee_captures, &arm_captures),
+                (&scrutinee_obligations, &arm_obligations),
+            ))
+        )
+
+        (
+            (captures(locals, CaptureKind::Value, e) => r)
+            --- ("cast")
+            (captures(locals, _kind, ExprData::Cast(e, _ty)) => r)
+        )
+
+        (
+            (captures(locals.without(&params), CaptureKind::Value, &body) => (inner_captures, obligations))
+            --- ("closure")
+            (captures(locals, _kind, ExprData::Closure(capture_by, params, body)) => (inner_captures.for_enclosing(&capture_by), &obligations))
+        )
+
+        (
+            (captures_block(&locals, &block) => (inner_captures, obligations))
+            --- ("async block")
+            (captures(locals, _kind, ExprData::Async(capture_by, block)) => (inner_captures.for_enclosing(&capture_by), &obligations))
+        )
+
+        (
+            (captures(locals, CaptureKind::Value, e) => r)
+            --- ("await")
+            (captures(locals, _kind, ExprData::Await(e)) => r)
+        )
+
+        (
+            (captures(&locals, CaptureKind::Value, &lhs) => (lhs_captures, lhs_obligations))
+            (captures(&locals, CaptureKind::Value, &rhs) => (rhs_captures, rhs_obligations))
+            --- ("binary operator")
+            (captures(locals, _kind, ExprData::Binary(lhs, _op, rhs)) => ((&lhs_captures, &rhs_captures), (&lhs_obligations, &rhs_obligations)))
+        )
+
+        (
+            (captures(locals, CaptureKind::Value, e) => r)
+            --- ("unary operator")
+            (captures(locals, _kind, ExprData::Unary(_op, e)) => r)
+        )
+
+        (
+            (captures(&locals, kind.through_deref(), &base) => (base_captures, base_obligations))
+            (captures(&locals, CaptureKind::Value, &index) => (index_captures, index_obligations))
+            --- ("index")
+            (captures(locals, kind, ExprData::Index(base, index)) => ((&base_captures, &index_captures), (&base_obligations, &index_obligations)))
+        )
+    }
+}
+
+/// A call `id(...)` uses `id` if it is a closure in `locals`.
+fn callee_captures(locals: &Locals, id: &FnId) -> Captures {
+    let id = ValueId::new(id);
+    match locals.get(&id).and_then(|ty| ty.as_closure_ty()) {
+        Some((kind, _, _)) => Captures::one(&id, CaptureKind::for_call(kind)),
+        None => Captures::default(),
+    }
+}
+
+judgment_fn! {
+    /// The uses of the variables in `locals` by each of `exprs`, which are used by value.
+    fn captures_exprs(
+        locals: Locals,
+        exprs: Vec<Expr>,
+    ) => (Captures, Wcs) {
+        debug(exprs, locals)
+
+        (
+            (if exprs.is_empty())
+            --- ("nil")
+            (captures_exprs(_locals, exprs) => (Captures::default(), Wcs::t()))
+        )
+
+        (
+            (if let Some((head, tail)) = exprs.split_first())
+            (captures(&locals, CaptureKind::Value, head) => (head_captures, head_obligations))
+            (captures_exprs(&locals, tail) => (tail_captures, tail_obligations))
+            --- ("cons")
+            (captures_exprs(locals, exprs) => ((&head_captures, &tail_captures), (&head_obligations, &tail_obligations)))
+        )
+    }
+}
+
+judgment_fn! {
+    /// The uses of the variables in `locals` by the arms of a `match`.
+    fn captures_arms(
+        locals: Locals,
+        arms: Vec<Arm>,
+    ) => (Captures, Wcs) {
+        debug(arms, locals)
+
+        (
+            (if arms.is_empty())
+            --- ("nil")
+            (captures_arms(_locals, arms) => (Captures::default(), Wcs::t()))
+        )
+
+        (
+            (if let Some((Arm { pattern, body }, rest)) = arms.split_first())
+            (captures(locals.without(&pattern.bindings()), CaptureKind::Value, body) => (body_captures, body_obligations))
+            (captures_arms(&locals, rest) => (rest_captures, rest_obligations))
+            --- ("cons")
+            (captures_arms(locals, arms) => ((&body_captures, &rest_captures), (&body_obligations, &rest_obligations)))
+        )
+    }
+}
+
+judgment_fn! {
+    /// The uses of the variables in `locals` by the statements and tail expression of `block`.
+    fn captures_block(
+        locals: Locals,
+        block: Block,
+    ) => (Captures, Wcs) {
+        debug(block, locals)
+
+        (
+            (if block.stmts.is_empty())
+            (if let None = &block.tail)
+            --- ("empty")
+            (captures_block(_locals, block) => (Captures::default(), Wcs::t()))
+        )
+
+        (
+            (if block.stmts.is_empty())
+            (if let Some(tail) = &block.tail)
+            (captures(&locals, CaptureKind::Value, tail) => r)
+            --- ("tail")
+            (captures_block(locals, block) => r)
+        )
+
+        (
+            (if let Some((Stmt::Let(id, init) | Stmt::LetTy(id, _, init), rest)) = block.split_first())
+            (captures(&locals, CaptureKind::Value, &init) => (init_captures, init_obligations))
+            (captures_block(locals.without(&[id.clone()]), &rest) => (rest_captures, rest_obligations))
+            --- ("let")
+            (captures_block(locals, block) => ((&init_captures, &rest_captures), (&init_obligations, &rest_obligations)))
+        )
+
+        (
+            (if let Some((Stmt::Expr(e), rest)) = block.split_first())
+            (captures(&locals, CaptureKind::Value, &e) => (e_captures, e_obligations))
+            (captures_block(&locals, &rest) => (rest_captures, rest_obligations))
+            --- ("expr statement")
+            (captures_block(locals, block) => ((&e_captures, &rest_captures), (&e_obligations, &rest_obligations)))
+        )
+    }
+}
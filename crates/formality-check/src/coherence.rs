@@ -1,52 +1,73 @@
-use anyhow::bail;
-use fn_error_context::context;
-use formality_prove::Env;
+use anyhow::{bail, Context};
+use formality_prove::{Env, SolverFlag};
 use formality_rust::grammar::{Crate, NegTraitImpl, TraitImpl};
 use formality_types::{
-    cast::Downcasted,
-    grammar::{Fallible, Wc, Wcs},
+    cast::{Downcasted, DowncastedWith},
+    grammar::{CrateId, Fallible, Wc, Wcs},
 };
 use itertools::Itertools;
 
-use crate::Check;
+use crate::{incremental::Unit, Check, Diagnostic};
 
 impl Check<'_> {
-    pub(crate) fn check_coherence(&self, current_crate: &Crate) -> Fallible<()> {
-        let all_crate_impls: Vec<TraitImpl> =
-            self.program.items_from_all_crates().downcasted().collect();
+    pub(crate) fn check_coherence(&self, current_crate: &Crate) -> Vec<anyhow::Error> {
+        let all_crate_impls: Vec<(&CrateId, TraitImpl)> =
+            self.program.items_with_crates().downcasted_with().collect();
         let current_crate_impls: Vec<TraitImpl> = current_crate.items.iter().downcasted().collect();
         let current_crate_neg_impls: Vec<NegTraitImpl> =
             current_crate.items.iter().downcasted().collect();
 
-        for impl_a in &current_crate_impls {
-            self.orphan_check(impl_a)?;
-        }
+        let mut errors = vec![];
 
-        for impl_a in &current_crate_neg_impls {
-            self.orphan_check_neg(impl_a)?;
-        }
+        errors.extend(self.check_all(&current_crate_impls, |impl_a| {
+            self.memoized(
+                Unit::Orphan(current_crate.id.clone(), impl_a.clone()),
+                || self.orphan_check(impl_a),
+            )
+        }));
+
+        errors.extend(self.check_all(&current_crate_neg_impls, |impl_a| {
+            self.memoized(
+                Unit::OrphanNeg(current_crate.id.clone(), impl_a.clone()),
+                || self.orphan_check_neg(impl_a),
+            )
+        }));
 
         // check for duplicate impls in the current crate
         for (impl_a, i) in current_crate_impls.iter().zip(0..) {
             if current_crate_impls[i + 1..].contains(impl_a) {
-                bail!("duplicate impl in current crate: {:?}", impl_a)
+                errors.push(
+                    Diagnostic::new(
+                        "E0119",
+                        format!("duplicate impl in current crate: {impl_a}"),
+                    )
+                    .with_primary(impl_a)
+                    .into(),
+                );
             }
         }
 
-        // check each impl in current crate against impls in all other crates
-        for (impl_a, impl_b) in current_crate_impls
+        // check each impl in current crate against impls in all other crates,
+        // and each pair of impls in the current crate once
+        let pairs: Vec<(&TraitImpl, &TraitImpl)> = current_crate_impls
             .iter()
+            .enumerate()
             .cartesian_product(&all_crate_impls)
+            .filter(|((i, _), (crate_b, impl_b))| {
+                **crate_b != current_crate.id || !current_crate_impls[..*i].contains(impl_b)
+            })
+            .map(|((_, impl_a), (_, impl_b))| (impl_a, impl_b))
             .filter(|(impl_a, impl_b)| impl_a != impl_b)
             .filter(|(impl_a, impl_b)| impl_a.trait_id() == impl_b.trait_id())
-        {
-            self.overlap_check(impl_a, impl_b)?;
-        }
+            .collect();
+        errors.extend(self.check_all(&pairs, |&(impl_a, impl_b)| {
+            let unit = Unit::Overlap(current_crate.id.clone(), impl_a.clone(), impl_b.clone());
+            self.memoized(unit, || self.overlap_check(impl_a, impl_b))
+        }));
 
-        Ok(())
+        errors
     }
 
-    #[context("orphan_check({impl_a:?})")]
     fn orphan_check(&self, impl_a: &TraitImpl) -> Fallible<()> {
         let mut env = Env::default();
 
@@ -58,9 +79,11 @@ impl Check<'_> {
             &a.where_clauses,
             trait_ref.is_local(),
         )
+        .with_context(|| {
+            Diagnostic::new("E0117", format!("orphan_check({impl_a})")).with_primary(impl_a)
+        })
     }
 
-    #[context("orphan_check_neg({impl_a:?})")]
     fn orphan_check_neg(&self, impl_a: &NegTraitImpl) -> Fallible<()> {
         let mut env = Env::default();
 
@@ -72,10 +95,13 @@ impl Check<'_> {
             &a.where_clauses,
             trait_ref.is_local(),
         )
+        .with_context(|| {
+            Diagnostic::new("E0117", format!("orphan_check_neg({impl_a})")).with_primary(impl_a)
+        })
     }
 
     #[tracing::instrument(level = "Debug", skip(self))]
-    fn overlap_check(&self, impl_a: &TraitImpl, impl_b: &TraitImpl) -> Fallible<()> {
+    pub(crate) fn overlap_check(&self, impl_a: &TraitImpl, impl_b: &TraitImpl) -> Fallible<()> {
         let mut env = Env::default();
 
         // Example:
@@ -99,15 +125,12 @@ impl Check<'_> {
         // in coherence mode, then they do not overlap.
         //
         // ∀P_a, ∀P_b. ⌐ (coherence_mode => (Ts_a = Ts_b && WC_a && WC_b))
-        if let Ok(()) = self.prove_not_goal(
-            &env.with_coherence_mode(true),
-            (),
-            (
-                Wcs::all_eq(&trait_ref_a.parameters, &trait_ref_b.parameters),
-                &a.where_clauses,
-                &b.where_clauses,
-            ),
-        ) {
+        let overlap_goal = (
+            Wcs::all_eq(&trait_ref_a.parameters, &trait_ref_b.parameters),
+            &a.where_clauses,
+            &b.where_clauses,
+        );
+        if let Ok(()) = self.prove_not_goal(&env.with_coherence_mode(true), (), &overlap_goal) {
             tracing::debug!(
                 "proved not {:?}",
                 (
@@ -121,17 +144,26 @@ impl Check<'_> {
         }
 
         // If we can disprove the where clauses, then they do not overlap.
+        // This is negative coherence, which can be disabled (see `SolverFlag::NegativeCoherence`).
         //
         // Given some inverted where-clause Wc_i from (invert(Wc_a), invert(Wc_b))...e.g.
         // if `T: Debug` is in `Wc_a`, then `Wc_i` might be `T: !Debug`.
         //
         // If we can prove `∀P_a, ∀P_b, (T_a = T_b, Wc_a, Wc_b) => Wc_i`, then contradiction, no overlap.
-        let inverted: Vec<Wc> = a
-            .where_clauses
-            .iter()
-            .chain(&b.where_clauses)
-            .flat_map(|wc| wc.invert())
-            .collect();
+        let inverted: Vec<Wc> = if self
+            .decls
+            .config
+            .flags
+            .is_enabled(SolverFlag::NegativeCoherence)
+        {
+            a.where_clauses
+                .iter()
+                .chain(&b.where_clauses)
+                .flat_map(|wc| wc.invert())
+                .collect()
+        } else {
+            vec![]
+        };
         if let Some(inverted_wc) = inverted.iter().find(|inverted_wc| {
             self.prove_goal(
                 &env,
@@ -156,6 +188,17 @@ impl Check<'_> {
 
             return Ok(());
         }
-        bail!("impls may overlap:\n{impl_a:?}\n{impl_b:?}")
+
+        // As rustc does, explain which impls other crates may add.
+        let mut notes = String::new();
+        for unknowable in self.unknowable_goals(&env.with_coherence_mode(true), (), &overlap_goal) {
+            notes.push_str(&format!("\nnote: {unknowable}"));
+        }
+        bail!(Diagnostic::new(
+            "E0119",
+            format!("impls may overlap:\n{impl_a}\n{impl_b}{notes}")
+        )
+        .with_primary(impl_a)
+        .with_related(impl_b))
     }
 }
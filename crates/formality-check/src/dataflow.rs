@@ -0,0 +1,277 @@
+//! The control-flow graph of a MIR body, summarized for the dataflow analyses
+//! (borrow checking and move checking): which places each statement or
+//! terminator accesses, and which loans it issues.
+
+use formality_rust::grammar::mir::{
+    BasicBlockDecl, BasicBlockId, LocalDecl, LocalId, Operand, Place, Rvalue, Statement, Terminator,
+};
+use formality_types::{
+    collections::Map,
+    grammar::{Lt, RefKind},
+};
+
+/// A statement (`index < statements.len()`) or the terminator of a block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Location {
+    pub block: usize,
+    pub index: usize,
+}
+
+/// A borrow of `place`, issued by a `ref` rvalue at `location`.
+pub(crate) struct Loan {
+    pub kind: RefKind,
+    pub place: Place,
+    pub lt: Lt,
+    pub location: Location,
+
+    /// For two-phase borrows, the local holding the reference; the borrow
+    /// is activated when that local is first used.
+    pub reserved_for: Option<LocalId>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub(crate) enum AccessKind {
+    Read,
+    Write,
+    Move,
+    Drop,
+    BorrowShared,
+    BorrowMut,
+}
+
+impl AccessKind {
+    pub fn verb(self) -> &'static str {
+        match self {
+            AccessKind::Read => "use",
+            AccessKind::Write => "assign to",
+            AccessKind::Move => "move out of",
+            AccessKind::Drop => "drop",
+            AccessKind::BorrowShared => "borrow",
+            AccessKind::BorrowMut => "mutably borrow",
+        }
+    }
+}
+
+/// The places accessed and the loans issued by a statement or terminator.
+#[derive(Default)]
+pub(crate) struct Effects {
+    /// Accessed places, in evaluation order.
+    pub accesses: Vec<(AccessKind, Place)>,
+
+    /// Indices of the loans issued.
+    pub loans: Vec<usize>,
+}
+
+impl Effects {
+    /// A write to a whole local overwrites it without reading it.
+    pub fn defs(&self) -> impl Iterator<Item = &LocalId> {
+        self.accesses
+            .iter()
+            .filter(|(kind, place)| is_def(*kind, place))
+            .map(|(_, place)| &place.local_id)
+    }
+
+    pub fn uses(&self) -> impl Iterator<Item = &LocalId> {
+        self.accesses
+            .iter()
+            .filter(|(kind, place)| !is_def(*kind, place))
+            .map(|(_, place)| &place.local_id)
+    }
+
+    fn operand(&mut self, operand: &Operand) {
+        match operand {
+            Operand::Move(place) => self.accesses.push((AccessKind::Move, place.clone())),
+            Operand::Copy(place) => self.accesses.push((AccessKind::Read, place.clone())),
+            Operand::Const(_) => {}
+        }
+    }
+}
+
+fn is_def(kind: AccessKind, place: &Place) -> bool {
+    kind == AccessKind::Write && place.projections.is_empty()
+}
+
+pub(crate) struct MirCfg<'b> {
+    pub local_decls: &'b [LocalDecl],
+    pub basic_block_decls: &'b [BasicBlockDecl],
+
+    /// The successors of each block.
+    pub successors: Vec<Vec<usize>>,
+
+    /// The effects of each statement of each block, followed by those of its terminator.
+    pub effects: Vec<Vec<Effects>>,
+
+    pub loans: Vec<Loan>,
+}
+
+impl<'b> MirCfg<'b> {
+    pub fn new(local_decls: &'b [LocalDecl], basic_block_decls: &'b [BasicBlockDecl]) -> Self {
+        // Type checking has already verified that all jump targets exist.
+        let block_indices: Map<&BasicBlockId, usize> = basic_block_decls
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (&b.id, i))
+            .collect();
+
+        let mut cfg = MirCfg {
+            local_decls,
+            basic_block_decls,
+            successors: vec![],
+            effects: vec![],
+            loans: vec![],
+        };
+
+        for (block, decl) in basic_block_decls.iter().enumerate() {
+            let mut effects = vec![];
+            for (index, statement) in decl.statements.iter().enumerate() {
+                let location = Location { block, index };
+                effects.push(cfg.statement_effects(location, statement));
+            }
+            effects.push(cfg.terminator_effects(&decl.terminator));
+            cfg.effects.push(effects);
+
+            let successors = match &decl.terminator {
+                Terminator::Goto(target) => vec![block_indices[target]],
+                Terminator::Drop(_, targets)
+                | Terminator::DropAndReplace(_, targets)
+                | Terminator::Call(_, _, _, targets) => {
+                    targets.iter().map(|t| block_indices[t]).collect()
+                }
+                Terminator::Resume
+                | Terminator::Abort
+                | Terminator::Return
+                | Terminator::Unreachable => vec![],
+            };
+            cfg.successors.push(successors);
+        }
+
+        cfg
+    }
+
+    /// The locations of all statements and terminators, block by block.
+    pub fn locations(&self) -> impl Iterator<Item = (Location, &Effects)> {
+        self.effects
+            .iter()
+            .enumerate()
+            .flat_map(|(block, effects)| {
+                effects
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, e)| (Location { block, index }, e))
+            })
+    }
+
+    /// Formats `location` using the name of its block, e.g. `bb0[1]`.
+    pub fn describe(&self, location: Location) -> String {
+        let id = &self.basic_block_decls[location.block].id;
+        format!("{id:?}[{}]", location.index)
+    }
+
+    fn statement_effects(&mut self, location: Location, statement: &Statement) -> Effects {
+        let mut effects = Effects::default();
+        match statement {
+            Statement::Assign(place, rvalue) => {
+                self.rvalue_effects(location, place, rvalue, &mut effects);
+                effects.accesses.push((AccessKind::Write, place.clone()));
+            }
+            Statement::Noop => {}
+            Statement::FakeRead(place) => effects.accesses.push((AccessKind::Read, place.clone())),
+        }
+        effects
+    }
+
+    fn rvalue_effects(
+        &mut self,
+        location: Location,
+        destination: &Place,
+        rvalue: &Rvalue,
+        effects: &mut Effects,
+    ) {
+        match rvalue {
+            Rvalue::Use(operand) | Rvalue::Repeat(operand, _) | Rvalue::Cast(operand, _) => {
+                effects.operand(operand)
+            }
+            Rvalue::Ref(lt, kind, place) => {
+                effects.accesses.push((borrow_access(kind), place.clone()));
+                effects.loans.push(self.loans.len());
+                self.loans.push(Loan {
+                    kind: kind.clone(),
+                    place: place.clone(),
+                    lt: lt.clone(),
+                    location,
+                    reserved_for: None,
+                });
+            }
+            Rvalue::TwoPhaseRef(lt, place) => {
+                // Reserving the borrow only reads the place; activation is tracked by borrowck.
+                effects
+                    .accesses
+                    .push((AccessKind::BorrowShared, place.clone()));
+                effects.loans.push(self.loans.len());
+                self.loans.push(Loan {
+                    kind: RefKind::Mut,
+                    place: place.clone(),
+                    lt: lt.clone(),
+                    location,
+                    reserved_for: Some(destination.local_id.clone()),
+                });
+            }
+            Rvalue::AddrOf(kind, place) => {
+                effects.accesses.push((borrow_access(kind), place.clone()))
+            }
+            Rvalue::Len(place) => effects.accesses.push((AccessKind::Read, place.clone())),
+            Rvalue::Apply(a, _, b) | Rvalue::Checked(a, _, b) => {
+                effects.operand(a);
+                effects.operand(b);
+            }
+            Rvalue::Aggregate(_, operands) => operands.iter().for_each(|o| effects.operand(o)),
+        }
+    }
+
+    fn terminator_effects(&self, terminator: &Terminator) -> Effects {
+        let mut effects = Effects::default();
+        match terminator {
+            Terminator::Goto(_)
+            | Terminator::Resume
+            | Terminator::Abort
+            | Terminator::Unreachable => {}
+            Terminator::Return => {
+                let return_place = Place {
+                    local_id: self.local_decls[0].name.clone(),
+                    projections: vec![],
+                };
+                effects.accesses.push((AccessKind::Read, return_place));
+            }
+            Terminator::Drop(place, _) => effects.accesses.push((AccessKind::Drop, place.clone())),
+            Terminator::DropAndReplace(place, _) => {
+                effects.accesses.push((AccessKind::Drop, place.clone()));
+                effects.accesses.push((AccessKind::Write, place.clone()));
+            }
+            Terminator::Call(func, args, destination, _) => {
+                effects.operand(func);
+                args.iter().for_each(|a| effects.operand(a));
+                effects
+                    .accesses
+                    .push((AccessKind::Write, destination.clone()));
+            }
+        }
+        effects
+    }
+}
+
+fn borrow_access(kind: &RefKind) -> AccessKind {
+    match kind {
+        RefKind::Shared => AccessKind::BorrowShared,
+        RefKind::Mut => AccessKind::BorrowMut,
+    }
+}
+
+/// Two places overlap if one is a prefix of the other.
+pub(crate) fn overlaps(a: &Place, b: &Place) -> bool {
+    is_prefix(a, b) || is_prefix(b, a)
+}
+
+/// Whether `b` is `a` or a projection of `a`.
+pub(crate) fn is_prefix(a: &Place, b: &Place) -> bool {
+    a.local_id == b.local_id && b.projections.starts_with(&a.projections)
+}
@@ -0,0 +1,105 @@
+//! Structured diagnostics, so that tools can tell the errors of the checks apart.
+//!
+//! The checks report errors as [`anyhow::Error`]s. Those that rustc has an error code
+//! for are raised as [`Diagnostic`]s, either directly or as the context of a lower-level
+//! error (e.g., an orphan check that fails because a goal cannot be proven). The
+//! outermost diagnostic of an error is the one that describes it.
+
+use std::fmt::{self, Debug};
+
+use serde_json::json;
+
+use crate::CheckError;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The code of the error in rustc, e.g., `E0119` for overlapping impls.
+    pub code: Option<&'static str>,
+
+    pub message: String,
+
+    /// The term the error is about, e.g., the impl that is not well-formed.
+    pub primary: Option<String>,
+
+    /// Other terms involved, e.g., the impl that the primary one overlaps with.
+    pub related: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(code: &'static str, message: impl ToString) -> Self {
+        Diagnostic {
+            code: Some(code),
+            message: message.to_string(),
+            primary: None,
+            related: vec![],
+        }
+    }
+
+    pub fn with_primary(self, term: impl Debug) -> Self {
+        Diagnostic {
+            primary: Some(format!("{term:?}")),
+            ..self
+        }
+    }
+
+    pub fn with_related(mut self, term: impl Debug) -> Self {
+        self.related.push(format!("{term:?}"));
+        self
+    }
+
+    /// The outermost diagnostic of `error`, or one without a code if it has none.
+    pub fn of(error: &anyhow::Error) -> Diagnostic {
+        // `downcast_ref` looks through the contexts added with `anyhow::Context`,
+        // which the chain does not expose.
+        error
+            .downcast_ref::<Diagnostic>()
+            .or_else(|| error.chain().find_map(|e| e.downcast_ref::<Diagnostic>()))
+            .cloned()
+            .unwrap_or_else(|| Diagnostic {
+                code: None,
+                message: error.to_string(),
+                primary: None,
+                related: vec![],
+            })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// Debug-printing an error shows the message only, as it would for an error raised with
+// a plain `bail!`, so that adding a code does not change how the checks report errors.
+impl fmt::Debug for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.message, f)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+impl CheckError {
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::of(&self.error)
+    }
+
+    /// The error as a JSON object, with `rendered` being its human-readable form.
+    pub fn to_json(&self) -> serde_json::Value {
+        let Diagnostic {
+            code,
+            message,
+            primary,
+            related,
+        } = self.diagnostic();
+        json!({
+            "crate": format!("{:?}", self.crate_id),
+            "code": code,
+            "message": message,
+            "primary": primary,
+            "related": related,
+            "rendered": self.to_string(),
+        })
+    }
+}
@@ -0,0 +1,437 @@
+//! Exhaustiveness and reachability checking of `match` expressions.
+//!
+//! This follows the usefulness algorithm from Maranget's "Warnings for pattern
+//! matching". A pattern vector `q` is *useful* with respect to a matrix of
+//! pattern rows if some value matches `q` but none of the rows. A match is
+//! exhaustive if `_` is not useful after all its arms, and an arm is reachable
+//! if its pattern is useful with respect to the arms above it.
+//!
+//! Uninhabited types (`!` and enums without variants) have no constructors, so
+//! they need no arms at all. As in rustc, this does not apply behind references:
+//! the referent of a `&!` is not assumed to be a valid value.
+
+use anyhow::bail;
+use formality_rust::grammar::{
+    pattern::{AdtPattern, FieldPattern, Pattern, PatternData},
+    AdtBoundData, FieldName, VariantId,
+};
+use formality_types::{
+    cast::{Downcast, Upcast},
+    grammar::{AdtId, Bool, Const, Fallible, RefKind, RigidName, RigidTy, ScalarId, Ty},
+};
+
+use crate::{Check, Diagnostic};
+
+impl Check<'_> {
+    /// Checks that every arm of a match on `scrutinee_ty` with the given arm
+    /// `patterns` is reachable, and that together they cover every value.
+    pub(crate) fn check_match_exhaustive(
+        &self,
+        scrutinee_ty: &Ty,
+        patterns: &[Pattern],
+    ) -> Fallible<()> {
+        let column = Column {
+            ty: Some(scrutinee_ty.clone()),
+            behind_ref: false,
+        };
+        let mut rows: Vec<Vec<Pattern>> = vec![];
+        for pattern in patterns {
+            let row = vec![pattern.clone()];
+            if self.useful(&rows, &row, &[column.clone()]).is_none() {
+                bail!("unreachable pattern `{pattern:?}` in match on `{scrutinee_ty:?}`");
+            }
+            rows.push(row);
+        }
+
+        let wildcard: Pattern = PatternData::Wildcard.upcast();
+        if let Some(witness) = self.useful(&rows, &[wildcard], &[column]) {
+            bail!(Diagnostic::new(
+                "E0004",
+                format!(
+                    "non-exhaustive patterns in match on `{scrutinee_ty:?}`: `{:?}` not covered",
+                    witness[0]
+                )
+            )
+            .with_primary(scrutinee_ty)
+            .with_related(&witness[0]));
+        }
+        Ok(())
+    }
+
+    /// If `q` is useful with respect to `rows`, returns a witness: a vector of patterns
+    /// matching values that `q` matches but none of `rows` does.
+    fn useful(
+        &self,
+        rows: &[Vec<Pattern>],
+        q: &[Pattern],
+        columns: &[Column],
+    ) -> Option<Vec<Pattern>> {
+        let Some((q_head, q_tail)) = q.split_first() else {
+            return if rows.is_empty() { Some(vec![]) } else { None };
+        };
+        let (column, columns_tail) = columns.split_first().unwrap();
+
+        if let Some((ctor, fields)) = self.deconstruct(q_head) {
+            return self.useful_specialized(rows, &ctor, &fields, q_tail, column, columns_tail);
+        }
+
+        let heads: Vec<Constructor> = rows
+            .iter()
+            .filter_map(|row| self.deconstruct(&row[0]))
+            .map(|(ctor, _)| ctor)
+            .collect();
+        let all = self.inhabited_constructors(column, &heads);
+
+        // If the rows mention every constructor, `_` is useful iff it is useful
+        // for one of them.
+        if let Some(all) = &all {
+            if all.iter().all(|ctor| heads.contains(ctor)) {
+                return all.iter().find_map(|ctor| {
+                    let fields = vec![wildcard(); self.arity(ctor)];
+                    self.useful_specialized(rows, ctor, &fields, q_tail, column, columns_tail)
+                });
+            }
+        }
+
+        // Otherwise, it suffices to look at the rows starting with `_`, as those
+        // are the only ones matching the missing constructors.
+        let default_rows: Vec<Vec<Pattern>> = rows
+            .iter()
+            .filter(|row| self.deconstruct(&row[0]).is_none())
+            .map(|row| row[1..].to_vec())
+            .collect();
+        let witness = self.useful(&default_rows, q_tail, columns_tail)?;
+        let head = match all.iter().flatten().find(|ctor| !heads.contains(ctor)) {
+            Some(missing) => {
+                let fields = vec![wildcard(); self.arity(missing)];
+                self.construct(missing, fields)
+            }
+            None => wildcard(),
+        };
+        Some(std::iter::once(head).chain(witness).collect())
+    }
+
+    /// Usefulness of `ctor(fields), q_tail`, looking only at the rows that may start with `ctor`.
+    fn useful_specialized(
+        &self,
+        rows: &[Vec<Pattern>],
+        ctor: &Constructor,
+        fields: &[Pattern],
+        q_tail: &[Pattern],
+        column: &Column,
+        columns_tail: &[Column],
+    ) -> Option<Vec<Pattern>> {
+        let arity = fields.len();
+        let specialized: Vec<Vec<Pattern>> = rows
+            .iter()
+            .filter_map(|row| {
+                let row_fields = match self.deconstruct(&row[0]) {
+                    None => vec![wildcard(); arity],
+                    Some((c, row_fields)) if c == *ctor => row_fields,
+                    Some(_) => return None,
+                };
+                Some(
+                    row_fields
+                        .into_iter()
+                        .chain(row[1..].iter().cloned())
+                        .collect(),
+                )
+            })
+            .collect();
+        let q: Vec<Pattern> = fields.iter().chain(q_tail).cloned().collect();
+        let columns: Vec<Column> = self
+            .field_columns(ctor, column)
+            .into_iter()
+            .chain(columns_tail.iter().cloned())
+            .collect();
+
+        let mut witness = self.useful(&specialized, &q, &columns)?;
+        let rest = witness.split_off(arity);
+        let head = self.construct(ctor, witness);
+        Some(std::iter::once(head).chain(rest).collect())
+    }
+
+    /// Splits a pattern into its constructor and the patterns for the constructor's fields,
+    /// or returns `None` for patterns matching anything (wildcards and bindings).
+    fn deconstruct(&self, pattern: &Pattern) -> Option<(Constructor, Vec<Pattern>)> {
+        match pattern.data() {
+            PatternData::Wildcard | PatternData::Binding(_) => None,
+            PatternData::Literal(c) => {
+                if *c == Bool::True.upcast() {
+                    Some((Constructor::Bool(true), vec![]))
+                } else if *c == Bool::False.upcast() {
+                    Some((Constructor::Bool(false), vec![]))
+                } else {
+                    Some((Constructor::Literal(c.clone()), vec![]))
+                }
+            }
+            PatternData::Tuple(pats) => Some((Constructor::Tuple(pats.len()), pats.clone())),
+            PatternData::Adt(adt_pat) => {
+                let ctor = Constructor::Variant(adt_pat.adt_id.clone(), adt_pat.variant_id.clone());
+                let fields = self
+                    .variant_field_names(&adt_pat.adt_id, &adt_pat.variant_id)
+                    .and_then(|names| {
+                        names
+                            .iter()
+                            .map(|name| {
+                                adt_pat
+                                    .fields
+                                    .iter()
+                                    .find(|f| f.name == *name)
+                                    .map(|f| f.pattern.clone())
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_else(|| adt_pat.fields.iter().map(|f| f.pattern.clone()).collect());
+                Some((ctor, fields))
+            }
+            PatternData::Ref(p) => Some((Constructor::Ref(RefKind::Shared), vec![p.clone()])),
+            PatternData::RefMut(p) => Some((Constructor::Ref(RefKind::Mut), vec![p.clone()])),
+        }
+    }
+
+    /// Rebuilds a pattern from a constructor and its field patterns; the inverse of `deconstruct`.
+    fn construct(&self, ctor: &Constructor, fields: Vec<Pattern>) -> Pattern {
+        match ctor {
+            Constructor::Bool(true) => PatternData::Literal(Bool::True.upcast()).upcast(),
+            Constructor::Bool(false) => PatternData::Literal(Bool::False.upcast()).upcast(),
+            Constructor::Literal(c) => PatternData::Literal(c.clone()).upcast(),
+            Constructor::Tuple(_) => PatternData::Tuple(fields).upcast(),
+            Constructor::Ref(RefKind::Shared) => PatternData::Ref(fields[0].clone()).upcast(),
+            Constructor::Ref(RefKind::Mut) => PatternData::RefMut(fields[0].clone()).upcast(),
+            Constructor::Variant(adt_id, variant_id) => {
+                let names = self
+                    .variant_field_names(adt_id, variant_id)
+                    .unwrap_or_else(|| (0..self.arity(ctor)).map(FieldName::Index).collect());
+                AdtPattern {
+                    adt_id: adt_id.clone(),
+                    variant_id: variant_id.clone(),
+                    fields: names
+                        .into_iter()
+                        .zip(fields)
+                        .map(|(name, pattern)| FieldPattern { name, pattern })
+                        .collect(),
+                }
+                .upcast()
+            }
+        }
+    }
+
+    /// The number of fields of `ctor`.
+    fn arity(&self, ctor: &Constructor) -> usize {
+        match ctor {
+            Constructor::Bool(_) | Constructor::Literal(_) => 0,
+            Constructor::Tuple(n) => *n,
+            Constructor::Ref(_) => 1,
+            Constructor::Variant(adt_id, variant_id) => self
+                .variant_field_names(adt_id, variant_id)
+                .map_or(0, |names| names.len()),
+        }
+    }
+
+    /// The names of the fields of the given variant, in declaration order.
+    fn variant_field_names(
+        &self,
+        adt_id: &AdtId,
+        variant_id: &VariantId,
+    ) -> Option<Vec<FieldName>> {
+        let adt = self.program.adt_named(adt_id).ok()?;
+        let variant = adt
+            .binder
+            .peek()
+            .variants
+            .iter()
+            .find(|v| v.name == *variant_id)?
+            .clone();
+        Some(variant.fields.into_iter().map(|f| f.name).collect())
+    }
+
+    /// The columns for the fields of `ctor`, given the column of the value it constructs.
+    fn field_columns(&self, ctor: &Constructor, column: &Column) -> Vec<Column> {
+        let arity = self.arity(ctor);
+        let rigid: Option<RigidTy> = column.ty.as_ref().and_then(|ty| ty.downcast());
+        let tys: Option<Vec<Ty>> = match (ctor, rigid) {
+            (
+                Constructor::Tuple(_),
+                Some(RigidTy {
+                    name: RigidName::Tuple(_),
+                    parameters,
+                }),
+            ) => parameters.iter().map(|p| p.downcast()).collect(),
+            (
+                Constructor::Ref(_),
+                Some(RigidTy {
+                    name: RigidName::Ref(_),
+                    parameters,
+                }),
+            ) => parameters[1].downcast().map(|ty| vec![ty]),
+            (
+                Constructor::Variant(adt_id, variant_id),
+                Some(RigidTy {
+                    name: RigidName::AdtId(id),
+                    parameters,
+                }),
+            ) if *adt_id == id => {
+                let adt = self.program.adt_named(adt_id).ok();
+                adt.and_then(|adt| adt.binder.instantiate_with(&parameters).ok())
+                    .and_then(
+                        |AdtBoundData {
+                             where_clauses: _,
+                             variants,
+                         }| {
+                            let variant = variants.into_iter().find(|v| v.name == *variant_id)?;
+                            Some(variant.fields.into_iter().map(|f| f.ty).collect())
+                        },
+                    )
+            }
+            _ => None,
+        };
+        let behind_ref = column.behind_ref || matches!(ctor, Constructor::Ref(_));
+        match tys {
+            Some(tys) if tys.len() == arity => tys
+                .into_iter()
+                .map(|ty| Column {
+                    ty: Some(ty),
+                    behind_ref,
+                })
+                .collect(),
+            _ => vec![
+                Column {
+                    ty: None,
+                    behind_ref
+                };
+                arity
+            ],
+        }
+    }
+
+    /// All constructors of the values in `column`, or `None` if there are too many to list
+    /// (e.g., for integers). If the type of the column is not known, we go by the
+    /// constructors `heads` of the patterns in it.
+    fn all_constructors(&self, column: &Column, heads: &[Constructor]) -> Option<Vec<Constructor>> {
+        let rigid: Option<RigidTy> = column.ty.as_ref().and_then(|ty| ty.downcast());
+        let ctors = match rigid {
+            Some(RigidTy {
+                name,
+                parameters: _,
+            }) => match name {
+                RigidName::ScalarId(ScalarId::Bool) => bool_constructors(),
+                RigidName::Tuple(n) => vec![Constructor::Tuple(n)],
+                RigidName::Ref(kind) => vec![Constructor::Ref(kind)],
+                RigidName::AdtId(adt_id) => self.variant_constructors(&adt_id)?,
+                RigidName::Never => vec![],
+                RigidName::ScalarId(_)
+                | RigidName::RawPtr(_)
+                | RigidName::FnPtr(..)
+                | RigidName::FnDef(_)
+                | RigidName::Closure(_)
+                | RigidName::Coroutine
+                | RigidName::Dyn(_) => return None,
+            },
+            None => match heads.first()? {
+                Constructor::Bool(_) => bool_constructors(),
+                Constructor::Tuple(n) => vec![Constructor::Tuple(*n)],
+                Constructor::Ref(kind) => vec![Constructor::Ref(kind.clone())],
+                Constructor::Variant(adt_id, _) => self.variant_constructors(adt_id)?,
+                Constructor::Literal(_) => return None,
+            },
+        };
+
+        // The referent of a reference may not be a valid value, so a match on it
+        // needs an arm even if its type is uninhabited.
+        if ctors.is_empty() && column.behind_ref {
+            return None;
+        }
+        Some(ctors)
+    }
+
+    /// Like [`Self::all_constructors`], but leaves out the constructors with an uninhabited
+    /// field, like `Option::Some` for an `Option<!>`: no arm is needed for those.
+    fn inhabited_constructors(
+        &self,
+        column: &Column,
+        heads: &[Constructor],
+    ) -> Option<Vec<Constructor>> {
+        let ctors = self.all_constructors(column, heads)?;
+        Some(
+            ctors
+                .into_iter()
+                .filter(|ctor| !self.has_uninhabited_field(ctor, column, &mut vec![]))
+                .collect(),
+        )
+    }
+
+    fn has_uninhabited_field(
+        &self,
+        ctor: &Constructor,
+        column: &Column,
+        stack: &mut Vec<Ty>,
+    ) -> bool {
+        self.field_columns(ctor, column)
+            .iter()
+            .any(|field| self.is_uninhabited(field, stack))
+    }
+
+    /// Whether the values in `column` are known to have an uninhabited type.
+    /// `stack` holds the types being visited, so that recursive types are
+    /// (conservatively) considered inhabited.
+    fn is_uninhabited(&self, column: &Column, stack: &mut Vec<Ty>) -> bool {
+        let Some(ty) = &column.ty else {
+            return false;
+        };
+        if stack.contains(ty) {
+            return false;
+        }
+        let Some(ctors) = self.all_constructors(column, &[]) else {
+            return false;
+        };
+        stack.push(ty.clone());
+        let uninhabited = ctors
+            .iter()
+            .all(|ctor| self.has_uninhabited_field(ctor, column, stack));
+        stack.pop();
+        uninhabited
+    }
+
+    fn variant_constructors(&self, adt_id: &AdtId) -> Option<Vec<Constructor>> {
+        let adt = self.program.adt_named(adt_id).ok()?;
+        Some(
+            adt.binder
+                .peek()
+                .variants
+                .iter()
+                .map(|v| Constructor::Variant(adt_id.clone(), v.name.clone()))
+                .collect(),
+        )
+    }
+}
+
+/// The head of a pattern, i.e., what kind of value it matches, ignoring the fields.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Constructor {
+    /// An enum variant, or a struct (with [`VariantId::for_struct`]).
+    Variant(AdtId, VariantId),
+    Bool(bool),
+    Tuple(usize),
+    Ref(RefKind),
+    /// Any other constant, like an integer or a const generic.
+    Literal(Const),
+}
+
+/// What is known about the values matched by one column of a pattern matrix.
+#[derive(Clone, Debug)]
+struct Column {
+    /// The type of the values, if known.
+    ty: Option<Ty>,
+
+    /// Whether the values are behind a reference.
+    behind_ref: bool,
+}
+
+fn bool_constructors() -> Vec<Constructor> {
+    vec![Constructor::Bool(true), Constructor::Bool(false)]
+}
+
+fn wildcard() -> Pattern {
+    PatternData::Wildcard.upcast()
+}
@@ -0,0 +1,185 @@
+//! Fallback for inference variables that type checking leaves undetermined.
+//!
+//! Following rustc, the type of a numeric literal without a suffix is an
+//! inference variable that can only be solved to an integer (resp. float) type,
+//! and that falls back to `i32` (resp. `f64`) if nothing else determines it.
+//! Likewise, a variable that an expression of type `!` was coerced into falls
+//! back to `()`, or to `!` from Rust 2024 on. With the `type_param_default_fallback` option, the type parameters
+//! of fns fall back to their defaults; these fallbacks are applied first, so that a
+//! default wins over the fallback of a numeric literal it is unified with.
+//!
+//! The solver enumerates the possible solutions rather than committing to one,
+//! so a variable only counts as determined if all solutions agree on its value.
+//! For the others, we add the fallback and prove the obligations again.
+
+use anyhow::bail;
+use formality_prove::{Constraints, Env};
+use formality_rust::grammar::edition::Edition;
+use formality_types::{
+    cast::{Downcast, Upcast, Upcasted},
+    collections::Set,
+    grammar::{Fallible, FnId, Relation, RigidName, RigidTy, ScalarId, Ty, TyData, Variable, Wcs},
+};
+
+use crate::{Check, Diagnostic};
+
+/// What an inference variable falls back to if nothing determines it.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Fallback {
+    /// The type of an integer literal, falling back to `i32`.
+    Int,
+
+    /// The type of a float literal, falling back to `f64`.
+    Float,
+
+    /// A type that an expression of type `!` was coerced to. It falls back to `()`
+    /// in editions before Rust 2024, and to `!` from then on.
+    Diverging,
+
+    /// A type parameter of a fn with a default, falling back to that default.
+    Default(Ty),
+}
+
+impl Fallback {
+    /// The type to fall back to in a crate of `edition`.
+    fn ty(&self, edition: Edition) -> Ty {
+        match self {
+            Fallback::Int => ScalarId::I32.upcast(),
+            Fallback::Float => ScalarId::F64.upcast(),
+            Fallback::Diverging if edition >= Edition::Rust2024 => Ty::never(),
+            Fallback::Diverging => Ty::unit(),
+            Fallback::Default(ty) => ty.clone(),
+        }
+    }
+
+    /// Whether a variable with this fallback may be solved to `ty`.
+    fn admits(&self, ty: &Ty) -> bool {
+        let scalar = match ty.downcast() {
+            Some(RigidTy {
+                name: RigidName::ScalarId(s),
+                parameters: _,
+            }) => Some(s),
+            _ => None,
+        };
+        match self {
+            Fallback::Int => scalar.is_some_and(|s| s.is_integral()),
+            Fallback::Float => scalar.is_some_and(|s| s.is_float()),
+            Fallback::Diverging | Fallback::Default(_) => true,
+        }
+    }
+}
+
+/// Whether, in the solution `c`, each of `vars` is solved to a type its fallback admits
+/// or not solved at all.
+pub(crate) fn admits_all(c: &Constraints, vars: &[(Ty, Fallback)]) -> bool {
+    vars.iter().all(|(var, fallback)| {
+        let ty = c.substitution().apply(var);
+        is_variable(&ty) || fallback.admits(&ty)
+    })
+}
+
+fn is_variable(ty: &Ty) -> bool {
+    matches!(ty.data(), TyData::Variable(_))
+}
+
+impl Check<'_> {
+    /// Proves `goal`, first applying the fallback of each of `vars` whose value
+    /// the solutions do not determine. If enabled, the defaults of type parameters
+    /// are applied before the other fallbacks.
+    pub(crate) fn prove_with_fallback(
+        &self,
+        env: &Env,
+        assumptions: &Wcs,
+        goal: &Wcs,
+        vars: &[(Ty, Fallback)],
+    ) -> Set<Constraints> {
+        let (param_defaults, others): (Vec<_>, Vec<_>) = vars
+            .iter()
+            .cloned()
+            .partition(|(_, fallback)| matches!(fallback, Fallback::Default(_)));
+        let stages = if self.options.type_param_default_fallback {
+            vec![param_defaults, others]
+        } else {
+            vec![others]
+        };
+
+        let mut goal = goal.clone();
+        let mut cs = self.prove_obligations(env, assumptions, &goal);
+        for stage in stages {
+            let defaults = undetermined_defaults(&cs, vars, &stage, self.edition());
+            if defaults.is_empty() {
+                continue;
+            }
+
+            let defaults: Wcs = defaults.into_iter().upcasted().collect();
+
+            tracing::debug!(?defaults);
+            goal = (goal, defaults).upcast();
+            cs = self.prove_obligations(env, assumptions, &goal);
+        }
+        cs
+    }
+
+    /// Checks that in the solution `c`, each of `vars` is solved to a type its fallback admits.
+    pub(crate) fn check_fallback_vars(
+        &self,
+        c: &Constraints,
+        vars: &[(Ty, Fallback)],
+    ) -> Fallible<()> {
+        for (var, fallback) in vars {
+            let ty = c.substitution().apply(var);
+            if !fallback.admits(&ty) {
+                match fallback {
+                    Fallback::Int => bail!(Diagnostic::new(
+                        "E0308",
+                        format!("an integer literal cannot have type `{ty:?}`")
+                    )),
+                    Fallback::Float => bail!(Diagnostic::new(
+                        "E0308",
+                        format!("a float literal cannot have type `{ty:?}`")
+                    )),
+                    Fallback::Diverging | Fallback::Default(_) => unreachable!(),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the type parameters of the fns named in a body, solved to `generic_args`,
+    /// were determined by inference or fallback.
+    pub(crate) fn check_generic_args_inferred(&self, generic_args: &[(FnId, Ty)]) -> Fallible<()> {
+        for (id, ty) in generic_args {
+            if let TyData::Variable(Variable::ExistentialVar(_)) = ty.data() {
+                bail!(Diagnostic::new(
+                    "E0282",
+                    format!(
+                        "type annotations needed: cannot infer a type parameter of fn `{id:?}`"
+                    )
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The fallbacks among `stage` for the variables whose value the solutions `cs`
+/// do not determine, considering only the solutions that all of `vars` admit.
+fn undetermined_defaults(
+    cs: &Set<Constraints>,
+    vars: &[(Ty, Fallback)],
+    stage: &[(Ty, Fallback)],
+    edition: Edition,
+) -> Vec<Relation> {
+    let admitted: Vec<&Constraints> = cs.iter().filter(|c| admits_all(c, vars)).collect();
+    stage
+        .iter()
+        .filter(|(var, _)| {
+            let values: Set<Ty> = admitted
+                .iter()
+                .map(|c| c.substitution().apply(var))
+                .collect();
+            values.len() > 1 || values.iter().any(is_variable)
+        })
+        .map(|(var, fallback)| Relation::eq(var, fallback.ty(edition)))
+        .collect()
+}
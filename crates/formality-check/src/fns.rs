@@ -1,38 +1,75 @@
+use anyhow::bail;
+use fn_error_context::context;
 use formality_prove::Env;
 use formality_rust::{
-    grammar::{Fn, FnBoundData},
+    grammar::{
+        self_param::SelfParam,
+        ty_defaults::{TyDefault, TyDefaults},
+        ExternBlock, Fn, FnBody, FnBoundData, MaybeFnBody,
+    },
     prove::ToWcs,
 };
-use formality_types::grammar::{Fallible, Wcs};
+use formality_types::{
+    cast::Upcasted,
+    collections::Set,
+    grammar::{Fallible, FnId, ParameterKind, Relation, Ty, UniversalVar, Variable, Wcs},
+    visit::Visit,
+};
 
 use crate::Check;
 
 impl Check<'_> {
     pub(crate) fn check_free_fn(&self, f: &Fn) -> Fallible<()> {
-        self.check_fn(&Env::default(), Wcs::t(), f)
+        self.check_fn(&Env::default(), Wcs::t(), None, f)
+    }
+
+    /// The fns of an extern block are defined elsewhere, so they must not have bodies.
+    pub(crate) fn check_extern_block(&self, b: &ExternBlock) -> Fallible<()> {
+        for f in &b.fns {
+            if !matches!(f.binder.peek().body, MaybeFnBody::NoFnBody) {
+                bail!("fn `{:?}` in an extern block cannot have a body", f.id);
+            }
+            self.check_free_fn(f)?;
+        }
+        Ok(())
     }
 
+    /// Checks `f`, which is a method of `self_ty` if it is in a trait or impl.
     pub(crate) fn check_fn(
         &self,
         in_env: &Env,
         in_assumptions: impl ToWcs,
+        self_ty: Option<&Ty>,
         f: &Fn,
     ) -> Fallible<()> {
         let in_assumptions = in_assumptions.to_wcs();
         assert!(in_env.only_universal_variables() && in_env.encloses((&in_assumptions, f)));
 
-        let mut env = in_env.clone();
-
-        let Fn { id: _, binder } = f;
+        let Fn {
+            visibility: _,
+            safety: _,
+            id,
+            binder,
+        } = f;
 
+        let (env, fn_vars) = in_env.universal_substitution(binder);
         let FnBoundData {
+            defaults,
+            self_param,
             input_tys,
             output_ty,
             where_clauses,
-            body: _,
-        } = env.instantiate_universally(binder);
+            body,
+        } = binder.instantiate_with(&fn_vars).unwrap();
 
-        let fn_assumptions: Wcs = (in_assumptions, &where_clauses).to_wcs();
+        // As in rustc, the signature implies the outlives bounds that its types need
+        // to be well formed, like `T: 'a` for an input of type `&'a T`.
+        let implied: Vec<Relation> = input_tys
+            .iter()
+            .chain(Some(&output_ty))
+            .flat_map(|ty| self.decls.implied_outlives(ty))
+            .collect();
+        let fn_assumptions: Wcs = (in_assumptions, &where_clauses, implied).to_wcs();
 
         self.prove_where_clauses_well_formed(&env, &fn_assumptions, &where_clauses)?;
 
@@ -42,6 +79,98 @@ impl Check<'_> {
 
         self.prove_goal(&env, &fn_assumptions, output_ty.well_formed())?;
 
+        self.check_ty_defaults(&env, &fn_assumptions, id, &fn_vars, &defaults)?;
+
+        if !matches!(body, MaybeFnBody::NoFnBody) {
+            self.check_signature_sized(&env, &fn_assumptions, id, &input_tys, &output_ty)?;
+        }
+
+        match (self_param, self_ty) {
+            (SelfParam::NoSelf, _) => {}
+            (SelfParam::HasSelf, None) => {
+                bail!("fn `{id:?}` cannot have a `self` parameter outside of a trait or impl")
+            }
+            (SelfParam::HasSelf, Some(self_ty)) => {
+                let Some(receiver_ty) = input_tys.first() else {
+                    bail!("the `self` parameter of fn `{id:?}` has no type")
+                };
+                self.check_receiver(&env, &fn_assumptions, id, receiver_ty, self_ty)?;
+            }
+        }
+
+        match &body {
+            MaybeFnBody::NoFnBody | MaybeFnBody::FnBody(FnBody::TrustedFnBody) => {}
+            MaybeFnBody::FnBody(FnBody::ExprFnBody(b)) => {
+                self.check_expr_fn_body(&env, &fn_assumptions, &input_tys, &output_ty, b)?
+            }
+            MaybeFnBody::FnBody(FnBody::MirFnBody(b)) => {
+                self.check_mir_fn_body(&env, &fn_assumptions, &input_tys, &output_ty, b)?
+            }
+        }
+
         Ok(())
     }
+
+    /// Each default must belong to a different type parameter of the fn itself and,
+    /// as in rustc, may only refer to the parameters declared before that one.
+    #[context("check_ty_defaults({id:?})")]
+    fn check_ty_defaults(
+        &self,
+        env: &Env,
+        assumptions: &Wcs,
+        id: &FnId,
+        fn_vars: &[UniversalVar],
+        defaults: &TyDefaults,
+    ) -> Fallible<()> {
+        let mut defaulted = Set::new();
+        for TyDefault { param, default } in &defaults.defaults {
+            let Some(index) = fn_vars
+                .iter()
+                .position(|v| v.kind == ParameterKind::Ty && Ty::new(v) == *param)
+            else {
+                bail!("`{param:?}` is not a type parameter of fn `{id:?}`")
+            };
+            if !defaulted.insert(index) {
+                bail!("type parameter `{param:?}` has more than one default")
+            }
+            let later_vars: Vec<Variable> = fn_vars[index..].iter().upcasted().collect();
+            for v in default.free_variables() {
+                if later_vars.contains(&v) {
+                    bail!("the default of `{param:?}` refers to `{v:?}`, which is not declared before it")
+                }
+            }
+            self.prove_goal(env, assumptions, default.well_formed())?;
+        }
+        Ok(())
+    }
+
+    /// As in rustc, the inputs and output of a fn with a body must be `Sized`,
+    /// since the body stores them in local variables.
+    #[context("check_signature_sized({id:?})")]
+    fn check_signature_sized(
+        &self,
+        env: &Env,
+        assumptions: &Wcs,
+        id: &FnId,
+        input_tys: &[Ty],
+        output_ty: &Ty,
+    ) -> Fallible<()> {
+        for ty in input_tys.iter().chain(Some(output_ty)) {
+            self.prove_goal(env, assumptions, self.program.sized_goal(ty))?;
+        }
+        Ok(())
+    }
+
+    /// The type of `self` must be `Self` or deref to it, like `&'a Self` or `Rc<Self>`.
+    #[context("check_receiver({id:?})")]
+    fn check_receiver(
+        &self,
+        env: &Env,
+        assumptions: &Wcs,
+        id: &FnId,
+        receiver_ty: &Ty,
+        self_ty: &Ty,
+    ) -> Fallible<()> {
+        self.prove_goal(env, assumptions, Relation::receiver(receiver_ty, self_ty))
+    }
 }
@@ -0,0 +1,255 @@
+//! Differential fuzzing of the checks on random well-formed programs.
+//!
+//! Unlike the terms that `#[term]` generates, which name items that need not exist,
+//! [`generate_program`] only names traits and structs that the program declares,
+//! with as many parameters as they are declared with, so that the checks get past
+//! name resolution and into the prover. [`fuzz`] then looks for panics, checks that
+//! do not finish, and results that depend on what they should not: the order of
+//! the items of a crate, or whether the checks run in parallel.
+
+use std::{sync::mpsc, time::Duration};
+
+use formality_rust::grammar::Program;
+use formality_types::{generate::Generator, parse::term, pretty::to_string};
+
+use crate::{check_all_crates_errors, Options};
+
+/// How long checking a generated program may take before it counts as not terminating.
+const TIME_LIMIT: Duration = Duration::from_secs(30);
+
+/// The stack size of the threads that check a program, as the prover recurses deeply.
+const STACK_SIZE: usize = 64 << 20;
+
+/// The most traits, structs and impls each crate declares.
+const MAX_ITEMS: usize = 2;
+
+/// A trait or struct, with the number of type parameters it is declared with.
+#[derive(Clone)]
+struct Item {
+    name: String,
+    arity: usize,
+}
+
+/// Generates the text of a program and parses it.
+struct ProgramGenerator<'g> {
+    g: &'g mut Generator,
+    traits: Vec<Item>,
+    structs: Vec<Item>,
+}
+
+/// A random well-formed program of one or two crates, whose traits, structs and impls
+/// only name the traits and structs declared in their crate or upstream.
+pub fn generate_program(g: &mut Generator) -> Program {
+    let mut p = ProgramGenerator {
+        g,
+        traits: vec![],
+        structs: vec![],
+    };
+    let num_crates = 1 + p.g.below(2);
+    let crates: Vec<String> = (0..num_crates).map(|i| p.krate(i)).collect();
+    term(&format!("[{}]", crates.join(", ")))
+}
+
+impl ProgramGenerator<'_> {
+    fn krate(&mut self, index: usize) -> String {
+        let mut items = vec![];
+
+        // Declare the items first, so that where-clauses can refer to any of them.
+        let num_traits = self.g.below(MAX_ITEMS) + usize::from(index == 0);
+        let new_traits: Vec<Item> = (0..num_traits)
+            .map(|_| self.declare("Trait", true))
+            .collect();
+        let num_structs = self.g.below(MAX_ITEMS);
+        let new_structs: Vec<Item> = (0..num_structs)
+            .map(|_| self.declare("Struct", false))
+            .collect();
+
+        for t in &new_traits {
+            items.push(self.trait_decl(t));
+        }
+        for s in &new_structs {
+            items.push(self.struct_decl(s));
+        }
+        for _ in 0..self.g.below(MAX_ITEMS + 1) {
+            items.push(self.impl_decl());
+        }
+        format!("crate crate{index} {{ {} }}", items.join(" "))
+    }
+
+    fn declare(&mut self, prefix: &str, is_trait: bool) -> Item {
+        let items = if is_trait {
+            &mut self.traits
+        } else {
+            &mut self.structs
+        };
+        let item = Item {
+            name: format!("{prefix}{}", items.len()),
+            arity: self.g.below(3),
+        };
+        items.push(item.clone());
+        item
+    }
+
+    /// `<ty T0, ..>` binding `vars`.
+    fn binder(vars: &[String]) -> String {
+        let bindings: Vec<String> = vars.iter().map(|v| format!("ty {v}")).collect();
+        format!("<{}>", bindings.join(", "))
+    }
+
+    fn vars(arity: usize) -> Vec<String> {
+        (0..arity).map(|i| format!("T{i}")).collect()
+    }
+
+    fn trait_decl(&mut self, t: &Item) -> String {
+        let vars = Self::vars(t.arity);
+        let mut in_scope = vars.clone();
+        in_scope.push("Self".to_string());
+        format!(
+            "trait {}{} where {} {{}}",
+            t.name,
+            Self::binder(&vars),
+            self.where_clauses(&in_scope)
+        )
+    }
+
+    fn struct_decl(&mut self, s: &Item) -> String {
+        let vars = Self::vars(s.arity);
+        let fields: Vec<String> = (0..self.g.below(3))
+            .map(|i| format!("f{i}: {}", self.ty(&vars, 1)))
+            .collect();
+        format!(
+            "struct {}{} where {} {{ {} }}",
+            s.name,
+            Self::binder(&vars),
+            self.where_clauses(&vars),
+            fields.join(", ")
+        )
+    }
+
+    fn impl_decl(&mut self) -> String {
+        let vars = Self::vars(self.g.below(3));
+        let polarity = if self.g.below(4) == 0 { "!" } else { "" };
+        let trait_ref = self.trait_ref(&vars);
+        format!(
+            "impl{} {polarity}{trait_ref} for {} where {} {{}}",
+            Self::binder(&vars),
+            self.ty(&vars, 2),
+            self.where_clauses(&vars)
+        )
+    }
+
+    /// `[X: Trait<..>, ..]`, where `X` is a type with the variables in scope.
+    fn where_clauses(&mut self, vars: &[String]) -> String {
+        let wcs: Vec<String> = (0..self.g.below(3))
+            .map(|_| {
+                let ty = self.ty(vars, 1);
+                let trait_ref = self.trait_ref(vars);
+                format!("{ty}: {trait_ref}")
+            })
+            .collect();
+        format!("[{}]", wcs.join(", "))
+    }
+
+    /// `Trait<..>` for one of the traits declared so far.
+    fn trait_ref(&mut self, vars: &[String]) -> String {
+        let t = self.g.choose(&self.traits);
+        format!("{}{}", t.name, self.parameters(t.arity, vars))
+    }
+
+    fn parameters(&mut self, arity: usize, vars: &[String]) -> String {
+        let tys: Vec<String> = (0..arity).map(|_| self.ty(vars, 1)).collect();
+        format!("<{}>", tys.join(", "))
+    }
+
+    /// A type with the variables `vars` in scope, nesting at most `depth` structs.
+    fn ty(&mut self, vars: &[String], depth: usize) -> String {
+        match self.g.below(5) {
+            0 => self.g.choose(&["u32", "bool", "()"]).to_string(),
+            1 | 2 if !vars.is_empty() => self.g.choose(vars),
+            3 if depth > 0 && !self.structs.is_empty() => {
+                let s = self.g.choose(&self.structs);
+                format!("{}{}", s.name, self.parameters(s.arity, vars))
+            }
+            4 if depth > 0 => format!("&static {}", self.ty(vars, depth - 1)),
+            _ => "u32".to_string(),
+        }
+    }
+}
+
+/// Checks the program generated from `seed`, returning what went wrong: that checking
+/// panicked or took longer than [`TIME_LIMIT`], that the checks found errors in the
+/// program with the items of each crate in reverse order but not as generated (or vice
+/// versa), or that checking in parallel reported other errors than checking serially.
+pub fn fuzz(seed: u64) -> Result<(), String> {
+    let program = generate_program(&mut Generator::new(seed));
+    let failure = |message: String| Err(format!("{message}\nprogram: {}", to_string(&program)));
+
+    let serial = Options {
+        parallel: false,
+        ..Options::default()
+    };
+    let parallel = Options {
+        parallel: true,
+        ..Options::default()
+    };
+    let mut reversed = program.clone();
+    for c in &mut reversed.crates {
+        c.items.reverse();
+    }
+
+    let Some(errors) = check_within_time_limit(&program, &serial)? else {
+        return failure("checking panicked".to_string());
+    };
+    let Some(parallel_errors) = check_within_time_limit(&program, &parallel)? else {
+        return failure("checking in parallel panicked".to_string());
+    };
+    let Some(reversed_errors) = check_within_time_limit(&reversed, &serial)? else {
+        return failure("checking with the items reversed panicked".to_string());
+    };
+
+    if parallel_errors != errors {
+        return failure(format!(
+            "checking in parallel reports {parallel_errors:#?}\nbut serially {errors:#?}"
+        ));
+    }
+    if reversed_errors.is_empty() != errors.is_empty() {
+        return failure(format!(
+            "with the items reversed, checking reports {reversed_errors:#?}\nbut otherwise {errors:#?}"
+        ));
+    }
+    Ok(())
+}
+
+/// The errors that checking `program` reports, or `None` if checking panicked.
+fn check_within_time_limit(
+    program: &Program,
+    options: &Options,
+) -> Result<Option<Vec<String>>, String> {
+    let (sender, receiver) = mpsc::channel();
+    let (program1, options1) = (program.clone(), options.clone());
+    // Checking in parallel recurses on rayon's threads, so they need as large a stack.
+    let pool = rayon::ThreadPoolBuilder::new()
+        .stack_size(STACK_SIZE)
+        .build()
+        .map_err(|e| e.to_string())?;
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            let errors: Vec<String> = pool.install(|| {
+                check_all_crates_errors(&program1, &options1)
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect()
+            });
+            let _ = sender.send(errors);
+        })
+        .map_err(|e| e.to_string())?;
+    match receiver.recv_timeout(TIME_LIMIT) {
+        Ok(errors) => Ok(Some(errors)),
+        Err(mpsc::RecvTimeoutError::Disconnected) => Ok(None),
+        Err(mpsc::RecvTimeoutError::Timeout) => Err(format!(
+            "checking did not finish within {TIME_LIMIT:?}\nprogram: {}",
+            to_string(program)
+        )),
+    }
+}
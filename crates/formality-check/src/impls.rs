@@ -4,26 +4,28 @@ use fn_error_context::context;
 use formality_prove::Env;
 use formality_rust::{
     grammar::{
-        AssociatedTy, AssociatedTyBoundData, AssociatedTyValue, AssociatedTyValueBoundData, Fn,
-        FnBoundData, ImplItem, NegTraitImpl, NegTraitImplBoundData, TraitBoundData, TraitImpl,
-        TraitImplBoundData, TraitItem,
+        self_param::SelfParam, AssociatedTy, AssociatedTyBoundData, AssociatedTyValue,
+        AssociatedTyValueBoundData, Fn, FnBoundData, ImplItem, NegTraitImpl, NegTraitImplBoundData,
+        TraitBoundData, TraitImpl, TraitImplBoundData, TraitItem, WhereClause,
     },
     prove::ToWcs,
 };
 use formality_types::{
     cast::Downcasted,
-    grammar::{Binder, Fallible, Relation, Substitution, Wcs},
-    term::Term,
+    grammar::{Fallible, Relation, TraitRef, Ty, Wcs},
 };
 
+use crate::Diagnostic;
+
 impl super::Check<'_> {
-    #[context("check_trait_impl({v:?})")]
+    #[context("check_trait_impl({v})")]
     pub(super) fn check_trait_impl(&self, v: &TraitImpl) -> Fallible<()> {
-        let TraitImpl { binder } = v;
+        let TraitImpl { safety: _, binder } = v;
 
         let mut env = Env::default();
 
         let TraitImplBoundData {
+            constness: _,
             trait_id,
             self_ty,
             trait_parameters,
@@ -31,13 +33,17 @@ impl super::Check<'_> {
             impl_items,
         } = env.instantiate_universally(binder);
 
-        let trait_ref = trait_id.with(self_ty, trait_parameters);
+        let trait_ref = trait_id.with(&self_ty, trait_parameters);
+
+        let assumptions = self.impl_header_assumptions(&where_clauses, &trait_ref);
+
+        self.prove_where_clauses_well_formed(&env, &assumptions, &where_clauses)?;
 
-        self.prove_where_clauses_well_formed(&env, &where_clauses, &where_clauses)?;
+        self.prove_impl_header_well_formed(&env, &assumptions, &trait_ref)?;
 
-        self.prove_goal(&env, &where_clauses, trait_ref.is_implemented())?;
+        self.prove_goal(&env, &assumptions, trait_ref.is_implemented())?;
 
-        self.prove_not_goal(&env, &where_clauses, trait_ref.not_implemented())?;
+        self.prove_not_goal(&env, &assumptions, trait_ref.not_implemented())?;
 
         let trait_decl = self.program.trait_named(&trait_ref.trait_id)?;
         let TraitBoundData {
@@ -46,7 +52,7 @@ impl super::Check<'_> {
         } = trait_decl.binder.instantiate_with(&trait_ref.parameters)?;
 
         for impl_item in &impl_items {
-            self.check_trait_impl_item(&env, &where_clauses, &trait_items, impl_item)?;
+            self.check_trait_impl_item(&env, &assumptions, &self_ty, &trait_items, impl_item)?;
         }
 
         Ok(())
@@ -64,17 +70,50 @@ impl super::Check<'_> {
 
         let trait_ref = trait_id.with(self_ty, trait_parameters);
 
-        self.prove_where_clauses_well_formed(&env, &where_clauses, &where_clauses)?;
+        let assumptions = self.impl_header_assumptions(&where_clauses, &trait_ref);
 
-        self.prove_goal(&env, &where_clauses, trait_ref.not_implemented())?;
+        self.prove_where_clauses_well_formed(&env, &assumptions, &where_clauses)?;
+
+        self.prove_impl_header_well_formed(&env, &assumptions, &trait_ref)?;
+
+        self.prove_goal(&env, &assumptions, trait_ref.not_implemented())?;
 
         Ok(())
     }
 
+    /// The assumptions under which the items of an impl are checked: its where-clauses
+    /// and, as in rustc, the outlives bounds implied by the types of its header, like
+    /// `T: 'a` for `impl<'a, T> Foo for &'a T` (see `Decls::implied_outlives`).
+    fn impl_header_assumptions(&self, where_clauses: &[WhereClause], trait_ref: &TraitRef) -> Wcs {
+        let implied: Vec<Relation> = trait_ref
+            .parameters
+            .iter()
+            .downcasted::<Ty>()
+            .flat_map(|ty| self.decls.implied_outlives(&ty))
+            .collect();
+        (where_clauses, implied).to_wcs()
+    }
+
+    /// As rustc does, proves that the self type and the trait parameters of an impl
+    /// are well formed, and that the where-clauses of the trait hold for them.
+    #[context("prove_impl_header_well_formed({trait_ref:?})")]
+    fn prove_impl_header_well_formed(
+        &self,
+        env: &Env,
+        assumptions: &Wcs,
+        trait_ref: &TraitRef,
+    ) -> Fallible<()> {
+        for parameter in &trait_ref.parameters {
+            self.prove_goal(env, assumptions, parameter.well_formed())?;
+        }
+        self.prove_goal(env, assumptions, trait_ref.well_formed())
+    }
+
     fn check_trait_impl_item(
         &self,
         env: &Env,
         assumptions: impl ToWcs,
+        self_ty: &Ty,
         trait_items: &[TraitItem],
         impl_item: &ImplItem,
     ) -> Fallible<()> {
@@ -84,7 +123,7 @@ impl super::Check<'_> {
         );
 
         match impl_item {
-            ImplItem::Fn(v) => self.check_fn_in_impl(env, &assumptions, trait_items, v),
+            ImplItem::Fn(v) => self.check_fn_in_impl(env, &assumptions, self_ty, trait_items, v),
             ImplItem::AssociatedTyValue(v) => {
                 self.check_associated_ty_value(env, assumptions, trait_items, v)
             }
@@ -95,6 +134,7 @@ impl super::Check<'_> {
         &self,
         env: &Env,
         impl_assumptions: impl ToWcs,
+        self_ty: &Ty,
         trait_items: &[TraitItem],
         ii_fn: &Fn,
     ) -> Fallible<()> {
@@ -110,28 +150,35 @@ impl super::Check<'_> {
             .find(|trait_f| trait_f.id == ii_fn.id)
         {
             Some(trait_f) => trait_f,
-            None => bail!("no fn `{:?}` in the trait", ii_fn.id),
+            None => bail!(
+                Diagnostic::new("E0407", format!("no fn `{:?}` in the trait", ii_fn.id))
+                    .with_primary(&ii_fn.id)
+            ),
         };
 
         tracing::debug!(?ti_fn);
 
-        self.check_fn(env, &impl_assumptions, ii_fn)?;
+        self.check_fn(env, &impl_assumptions, Some(self_ty), ii_fn)?;
 
         let mut env = env.clone();
         let (
             FnBoundData {
+                defaults: _,
+                self_param: ii_self_param,
                 input_tys: ii_input_tys,
                 output_ty: ii_output_ty,
                 where_clauses: ii_where_clauses,
                 body: _,
             },
             FnBoundData {
+                defaults: _,
+                self_param: ti_self_param,
                 input_tys: ti_input_tys,
                 output_ty: ti_output_ty,
                 where_clauses: ti_where_clauses,
                 body: _,
             },
-        ) = env.instantiate_universally(&self.merge_binders(&ii_fn.binder, &ti_fn.binder)?);
+        ) = env.instantiate_universally(&ii_fn.binder.zip(&ti_fn.binder)?);
 
         self.prove_goal(
             &env,
@@ -139,12 +186,27 @@ impl super::Check<'_> {
             &ii_where_clauses,
         )?;
 
+        if ii_self_param != ti_self_param {
+            bail!(Diagnostic::new(
+                if ti_self_param == SelfParam::HasSelf { "E0186" } else { "E0185" },
+                format!(
+                    "fn `{:?}` must have a `self` parameter in the impl exactly if it has one in the trait",
+                    ii_fn.id
+                )
+            )
+            .with_primary(&ii_fn.id))
+        }
+
         if ii_input_tys.len() != ti_input_tys.len() {
-            bail!(
-                "impl has {} function arguments but trait has {} function arguments",
-                ii_input_tys.len(),
-                ti_input_tys.len()
+            bail!(Diagnostic::new(
+                "E0050",
+                format!(
+                    "impl has {} function arguments but trait has {} function arguments",
+                    ii_input_tys.len(),
+                    ti_input_tys.len()
+                )
             )
+            .with_primary(&ii_fn.id))
         }
 
         for (ii_input_ty, ti_input_ty) in ii_input_tys.iter().zip(&ti_input_tys) {
@@ -164,7 +226,7 @@ impl super::Check<'_> {
         Ok(())
     }
 
-    #[context("check_associated_ty_value({impl_value:?})")]
+    #[context("check_associated_ty_value({impl_value})")]
     fn check_associated_ty_value(
         &self,
         impl_env: &Env,
@@ -187,7 +249,11 @@ impl super::Check<'_> {
             .find(|trait_associated_ty| trait_associated_ty.id == *id)
         {
             Some(trait_associated_ty) => trait_associated_ty,
-            None => bail!("no associated type `{:?}` in the trait", id),
+            None => bail!(Diagnostic::new(
+                "E0437",
+                format!("no associated type `{:?}` in the trait", id)
+            )
+            .with_primary(id)),
         };
 
         let mut env = impl_env.clone();
@@ -201,7 +267,7 @@ impl super::Check<'_> {
                 ensures: ti_ensures,
                 where_clauses: ti_where_clauses,
             },
-        ) = env.instantiate_universally(&self.merge_binders(binder, &trait_associated_ty.binder)?);
+        ) = env.instantiate_universally(&binder.zip(&trait_associated_ty.binder)?);
 
         self.prove_where_clauses_well_formed(
             &env,
@@ -221,38 +287,12 @@ impl super::Check<'_> {
             ii_ty.well_formed(),
         )?;
 
+        // The bounds declared on the trait's associated type must hold for the value.
+        // Like rustc, we may assume the where-clauses declared in the trait here: the
+        // impl's own where-clauses were proven above to follow from them.
         let ensures: Wcs = ti_ensures.iter().map(|e| e.to_wc(&ii_ty)).collect();
-        self.prove_goal(&env, (&impl_assumptions, &ii_where_clauses), ensures)?;
+        self.prove_goal(&env, (&impl_assumptions, &ti_where_clauses), ensures)?;
 
         Ok(())
     }
-
-    /// Given a binder from some impl item `I` and a binder from the corresponding trait item `T`,
-    /// check that the binders have the same number/kinds of parameters, and then merge them
-    /// into a single binder over `(I, T)`
-    fn merge_binders<I: Term, T: Term>(
-        &self,
-        impl_binder: &Binder<I>,
-        trait_binder: &Binder<T>,
-    ) -> Fallible<Binder<(I, T)>> {
-        if impl_binder.kinds() != trait_binder.kinds() {
-            bail!(
-                "distinct binder kinds: impl {:?} vs trait {:?}",
-                impl_binder.kinds(),
-                trait_binder.kinds()
-            );
-        }
-
-        let (impl_names, impl_value) = impl_binder.open();
-
-        let (trait_names, trait_value) = trait_binder.open();
-
-        assert_eq!(impl_names.len(), trait_names.len());
-        let trait_to_impl_subst: Substitution = trait_names.iter().zip(impl_names.iter()).collect();
-
-        Ok(Binder::new(
-            &impl_names,
-            (impl_value, trait_to_impl_subst.apply(&trait_value)),
-        ))
-    }
 }
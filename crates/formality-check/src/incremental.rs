@@ -0,0 +1,306 @@
+//! Checking a program again after it changed, e.g., after adding or removing an impl,
+//! re-running only the checks that the change may affect (see [`IncrementalCheck`]).
+//!
+//! The checks that are re-run or not are those of the items, the orphan checks of the
+//! impls, and the overlap checks of the pairs of impls; the checks that span a crate
+//! (of duplicate items, privacy and unsafety) are always re-run. A check depends on
+//! the names it *mentions*: the ids of traits, ADTs, fns and so on that occur in the
+//! items it checks. Through those, it depends on the items that *define* the names,
+//! i.e., the declarations of the traits, ADTs and fns and the impls of the traits, and
+//! in turn on the names that those mention, and so on. A change to the program changes
+//! the names defined by the items it adds or removes, so that a check whose names
+//! reach one of those may no longer pass.
+//!
+//! Names are compared as text: anything that occurs in an item as a string, which
+//! includes the names of variables and fields, counts as a name that it mentions.
+//! That only makes checks depend on more than they do.
+//!
+//! The checks of a crate also depend on its header: a change to its edition re-runs all
+//! its checks, and a change to the crates it depends on, which decide the names visible
+//! to it, re-runs those and the checks of the crates depending on it, directly or not.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use formality_rust::grammar::{
+    CrateItem, Feature, ImplItem, NegTraitImpl, Program, TraitImpl, TraitItem,
+};
+use formality_types::{
+    collections::{Map, Set},
+    grammar::CrateId,
+    judgment::cancel,
+};
+use serde::Serialize;
+
+use crate::{check_all_crates_errors_with, lower, CheckError, Options};
+
+/// Checks a program again and again as it changes, re-running only the checks that the
+/// changes since the last [`IncrementalCheck::check`] may affect; see the module
+/// documentation. The checks that did not pass are always re-run, so that their errors
+/// are reported as usual.
+pub struct IncrementalCheck {
+    options: Options,
+
+    /// The program last checked, lowered, if it could be.
+    program: Option<Program>,
+
+    /// The checks that passed on the program last checked.
+    passes: Passes,
+
+    /// The number of checks that the last call to [`IncrementalCheck::check`] did not re-run.
+    reused: usize,
+}
+
+/// Checks that passed, each with the features that the program was checked with and
+/// the names that the check mentions.
+type Passes = Map<(Set<Feature>, Unit), Set<String>>;
+
+/// A check that can pass without re-running it.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) enum Unit {
+    /// The check of an item of a crate.
+    Item(CrateId, CrateItem),
+    /// The orphan check of an impl of a crate.
+    Orphan(CrateId, TraitImpl),
+    /// The orphan check of a negative impl of a crate.
+    OrphanNeg(CrateId, NegTraitImpl),
+    /// The overlap check of an impl of a crate with another impl.
+    Overlap(CrateId, TraitImpl, TraitImpl),
+}
+
+impl Unit {
+    /// The crate whose check it is.
+    fn crate_id(&self) -> &CrateId {
+        match self {
+            Unit::Item(crate_id, _)
+            | Unit::Orphan(crate_id, _)
+            | Unit::OrphanNeg(crate_id, _)
+            | Unit::Overlap(crate_id, _, _) => crate_id,
+        }
+    }
+
+    /// The names that the check mentions.
+    fn mentions(&self) -> Set<String> {
+        match self {
+            Unit::Item(_, item) => mentions(item),
+            Unit::Orphan(_, i) => mentions(i),
+            Unit::OrphanNeg(_, i) => mentions(i),
+            Unit::Overlap(_, a, b) => mentions(a).into_iter().chain(mentions(b)).collect(),
+        }
+    }
+}
+
+/// The checks that passed before and may be skipped, as the checks of one program see them.
+pub(crate) struct Memo {
+    previous: Passes,
+    current: Mutex<Passes>,
+    reused: AtomicUsize,
+}
+
+impl Memo {
+    /// Runs the check `op` of `unit` with `features`, unless it passed with those on the
+    /// program last checked and the changes since do not affect it.
+    pub(crate) fn check<E>(
+        &self,
+        features: &Set<Feature>,
+        unit: Unit,
+        op: impl FnOnce() -> Result<(), E>,
+    ) -> Result<(), E> {
+        let key = (features.clone(), unit);
+        if let Some(mentions) = self.previous.get(&key) {
+            self.reused.fetch_add(1, Ordering::Relaxed);
+            self.current.lock().unwrap().insert(key, mentions.clone());
+            return Ok(());
+        }
+        op()?;
+        // What passed once cancelled need not pass (see `cancel`).
+        if !cancel::is_cancelled() {
+            let mentions = key.1.mentions();
+            self.current.lock().unwrap().insert(key, mentions);
+        }
+        Ok(())
+    }
+}
+
+impl IncrementalCheck {
+    /// Checks with `options`, starting from no program, so that the first call to
+    /// [`IncrementalCheck::check`] runs all checks.
+    pub fn new(options: Options) -> Self {
+        IncrementalCheck {
+            options,
+            program: None,
+            passes: Map::new(),
+            reused: 0,
+        }
+    }
+
+    /// Checks all crates of `program` like [`crate::check_all_crates_errors`], re-running
+    /// only the checks that the changes since the program last checked may affect.
+    pub fn check(&mut self, program: &Program) -> Vec<CheckError> {
+        // The names are those of the items of all features, whichever are checked.
+        let lowered = lower(&program.configure(&program.features())).ok();
+        let mut passes = std::mem::take(&mut self.passes);
+        match (&self.program, &lowered) {
+            (Some(old), Some(new)) if crate_ids(old) == crate_ids(new) => {
+                let affected = affected_names(old, &changed_names(old, new));
+                let changed_crates = changed_crates(old, new);
+                passes.retain(|(_, unit), mentions| {
+                    !changed_crates.contains(unit.crate_id()) && mentions.is_disjoint(&affected)
+                });
+            }
+            _ => passes.clear(),
+        }
+
+        let memo = Memo {
+            previous: passes,
+            current: Default::default(),
+            reused: AtomicUsize::new(0),
+        };
+        let errors = check_all_crates_errors_with(program, &self.options, Some(&memo));
+        self.passes = memo.current.into_inner().unwrap();
+        self.reused = memo.reused.into_inner();
+        self.program = lowered;
+        errors
+    }
+
+    /// The number of checks that the last call to [`IncrementalCheck::check`] did not re-run.
+    pub fn reused(&self) -> usize {
+        self.reused
+    }
+}
+
+fn crate_ids(program: &Program) -> Vec<&CrateId> {
+    program.crates.iter().map(|c| &c.id).collect()
+}
+
+/// The crates of `new` whose edition or dependencies differ from those in `old`, which
+/// has the same crates, along with the crates that depend on those whose dependencies
+/// differ, directly or not.
+fn changed_crates(old: &Program, new: &Program) -> Set<CrateId> {
+    let edition_changed = (0..new.crates.len())
+        .filter(|&i| old.crates[i].edition != new.crates[i].edition)
+        .map(|i| new.crates[i].id.clone());
+    let mut changed: Set<usize> = (0..new.crates.len())
+        .filter(|&i| old.crates[i].dependencies != new.crates[i].dependencies)
+        .collect();
+    loop {
+        // A crate whose dependencies are not crates of the program does not check.
+        let dependents: Vec<usize> = (0..new.crates.len())
+            .filter(|i| !changed.contains(i))
+            .filter(|&i| {
+                new.direct_dependencies(i)
+                    .map_or(true, |ds| ds.iter().any(|d| changed.contains(d)))
+            })
+            .collect();
+        if dependents.is_empty() {
+            break;
+        }
+        changed.extend(dependents);
+    }
+    changed
+        .into_iter()
+        .map(|i| new.crates[i].id.clone())
+        .chain(edition_changed)
+        .collect()
+}
+
+/// The names defined by the items that are in one of the programs but not the other.
+fn changed_names(old: &Program, new: &Program) -> Set<String> {
+    let old_items: Set<(&CrateId, &CrateItem)> = old.items_with_crates().collect();
+    let new_items: Set<(&CrateId, &CrateItem)> = new.items_with_crates().collect();
+    old_items
+        .symmetric_difference(&new_items)
+        .flat_map(|(_, item)| defined_names(item))
+        .collect()
+}
+
+/// The names of `program` that reach one of `changed`, so that the checks mentioning
+/// them are affected by the change.
+fn affected_names(program: &Program, changed: &Set<String>) -> Set<String> {
+    // For each name, the names defined by the items that mention it.
+    let mut mentioned_by: Map<String, Set<String>> = Map::new();
+    for item in program.items_from_all_crates() {
+        let defined = defined_names(item);
+        for name in mentions(item) {
+            mentioned_by
+                .entry(name)
+                .or_default()
+                .extend(defined.iter().cloned());
+        }
+    }
+
+    let mut affected = changed.clone();
+    let mut queue: VecDeque<String> = changed.iter().cloned().collect();
+    while let Some(name) = queue.pop_front() {
+        for other in mentioned_by.get(&name).into_iter().flatten() {
+            if affected.insert(other.clone()) {
+                queue.push_back(other.clone());
+            }
+        }
+    }
+    affected
+}
+
+/// The names that `item` defines, for the items that mention them: the id of a trait,
+/// ADT or fn, or the trait of an impl, along with the ids of the items of a trait or
+/// impl, which method calls and associated types may refer to by those alone.
+fn defined_names(item: &CrateItem) -> Set<String> {
+    match item {
+        CrateItem::Struct(s) => mentions(&s.id),
+        CrateItem::Enum(e) => mentions(&e.id),
+        CrateItem::Trait(t) => {
+            let data = t.binder.explicit_binder.peek();
+            let items = data.trait_items.iter().map(|item| match item {
+                TraitItem::Fn(f) => mentions(&f.id),
+                TraitItem::AssociatedTy(a) => mentions(&a.id),
+            });
+            mentions(&t.id).into_iter().chain(items.flatten()).collect()
+        }
+        CrateItem::TraitImpl(i) => {
+            let data = i.binder.peek();
+            let items = data.impl_items.iter().map(|item| match item {
+                ImplItem::Fn(f) => mentions(&f.id),
+                ImplItem::AssociatedTyValue(a) => mentions(&a.id),
+            });
+            mentions(&data.trait_id)
+                .into_iter()
+                .chain(items.flatten())
+                .collect()
+        }
+        CrateItem::NegTraitImpl(i) => mentions(&i.binder.peek().trait_id),
+        CrateItem::Fn(f) => mentions(&f.id),
+        CrateItem::Static(s) => mentions(&s.id),
+        CrateItem::Const(c) => mentions(&c.id),
+        CrateItem::TypeAlias(t) => mentions(&t.id),
+        CrateItem::Module(m) => m.items.iter().flat_map(defined_names).collect(),
+        CrateItem::ExternBlock(b) => b.fns.iter().flat_map(|f| mentions(&f.id)).collect(),
+        CrateItem::Cfg(c) => defined_names(&c.item),
+    }
+}
+
+/// The strings that occur in `term`.
+fn mentions(term: &impl Serialize) -> Set<String> {
+    fn collect(value: serde_json::Value, strings: &mut Set<String>) {
+        match value {
+            serde_json::Value::String(s) => {
+                strings.insert(s);
+            }
+            serde_json::Value::Array(values) => {
+                values.into_iter().for_each(|v| collect(v, strings));
+            }
+            serde_json::Value::Object(fields) => {
+                fields.into_iter().for_each(|(_, v)| collect(v, strings));
+            }
+            serde_json::Value::Null | serde_json::Value::Bool(_) | serde_json::Value::Number(_) => {
+            }
+        }
+    }
+    let mut strings = Set::new();
+    collect(serde_json::to_value(term).unwrap(), &mut strings);
+    strings
+}
@@ -1,73 +1,478 @@
 #![allow(dead_code)]
 
-use std::{collections::VecDeque, fmt::Debug};
+use std::{fmt::Debug, sync::Arc, time::Duration};
 
 use anyhow::bail;
-use formality_prove::{Decls, Env};
+use formality_prove::{
+    active_query_cache, with_query_cache, AmbiguousConstraints, Decls, Env, ProofResult,
+    QueryCache, SolverFlags, Unknowable,
+};
 use formality_rust::{
-    grammar::{Crate, CrateItem, Program},
+    grammar::{edition::Edition, Crate, CrateItem, Feature, Program},
     prove::ToWcs,
 };
-use formality_types::grammar::{Fallible, Substitution, Wcs};
+use formality_types::{
+    collections::Set,
+    grammar::{CrateId, Fallible, Substitution, Wcs},
+    judgment::cancel::{self, CancellationToken, Cancelled},
+};
+use incremental::{Memo, Unit};
+use rayon::prelude::*;
 
-/// Check all crates in the program. The crates must be in dependency order
-/// such that any prefix of the crates is a complete program.
+pub use diagnostic::Diagnostic;
+pub use incremental::IncrementalCheck;
+pub use report::{check_all_crates_report, ItemReport, Report, Verdict};
+pub use semver::{check_semver, BreakingChange};
+
+/// Check all crates in the program. Each crate is checked after the crates it depends
+/// on, in a program of only those (see [`Program::crate_program`]), so that the crates
+/// it does not depend on are neither visible to it nor downstream of it.
 pub fn check_all_crates(program: &Program) -> Fallible<()> {
-    let Program { crates } = program;
-    let mut crates: VecDeque<_> = crates.iter().cloned().collect();
+    check_all_crates_with_options(program, &Options::default())
+}
 
-    let mut prefix_program = Program { crates: vec![] };
-    while let Some(c) = crates.pop_front() {
-        prefix_program.crates.push(c);
-        check_current_crate(&prefix_program)?;
+/// Like [`check_all_crates`], but with `options` selecting between alternative semantics.
+/// If there are several errors, they are reported together, see [`check_all_crates_errors`].
+pub fn check_all_crates_with_options(program: &Program, options: &Options) -> Fallible<()> {
+    combine_errors(check_all_crates_errors(program, options))
+}
+
+/// Reports `errors` as one error, listing them if there are several.
+pub fn combine_errors(mut errors: Vec<CheckError>) -> Fallible<()> {
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.pop().unwrap().error),
+        n => {
+            let mut message = format!("{n} errors:");
+            for e in &errors {
+                message.push_str(&format!("\n\n{e}"));
+            }
+            bail!(message)
+        }
     }
+}
 
-    Ok(())
+/// Like [`check_all_crates_with_options`], but returns all errors rather than the first.
+/// The crates are checked in dependency order up to the first one with errors, whose errors are
+/// all reported in the order of its items; the crates after it assume it is valid.
+///
+/// With a [time limit](`Options::time_limit`), a crate whose checks exceed it gets a
+/// single error saying so, in place of the errors found so far.
+pub fn check_all_crates_errors(program: &Program, options: &Options) -> Vec<CheckError> {
+    check_all_crates_errors_with(program, options, None)
 }
 
-/// Checks the current crate in the program, assuming all other crates are valid.
-fn check_current_crate(program: &Program) -> Fallible<()> {
-    let decls = program.to_prove_decls();
-    Check {
-        program,
-        decls: &decls,
+/// Like [`check_all_crates_errors`], skipping the checks that `memo` says still pass.
+/// With several sets of [features](`Options::features`), the program is checked with
+/// each in turn up to the first one with errors, which say which features they are with.
+fn check_all_crates_errors_with(
+    program: &Program,
+    options: &Options,
+    memo: Option<&Memo>,
+) -> Vec<CheckError> {
+    let token = options.time_limit.map(CancellationToken::with_time_limit);
+    let sets = options.features.sets(program);
+    for features in &sets {
+        let configured = program.configure(features);
+        let errors = check_configured(&configured, features, options, memo, token.as_ref());
+        if errors.is_empty() {
+            continue;
+        }
+        if sets.len() == 1 {
+            return errors;
+        }
+        return errors
+            .into_iter()
+            .map(|e| CheckError {
+                error: e
+                    .error
+                    .context(format!("with the features {features:?} enabled")),
+                ..e
+            })
+            .collect();
     }
-    .check()
+    vec![]
+}
+
+/// Checks the crates of `program`, configured with `features`, in dependency order.
+fn check_configured(
+    program: &Program,
+    features: &Set<Feature>,
+    options: &Options,
+    memo: Option<&Memo>,
+    token: Option<&CancellationToken>,
+) -> Vec<CheckError> {
+    let order = match program.dependency_order() {
+        Ok(order) => order,
+        Err(error) => {
+            return program
+                .crates
+                .last()
+                .map(|c| CheckError {
+                    crate_id: c.id.clone(),
+                    features: features.clone(),
+                    item: None,
+                    error,
+                })
+                .into_iter()
+                .collect()
+        }
+    };
+    for i in order {
+        let c = &program.crates[i];
+        let crate_program = match program.crate_program(i) {
+            Ok(crate_program) => crate_program,
+            Err(error) => {
+                return vec![CheckError {
+                    crate_id: c.id.clone(),
+                    features: features.clone(),
+                    item: None,
+                    error,
+                }]
+            }
+        };
+        let errors = match token {
+            None => check_current_crate(&crate_program, features, options, memo),
+            Some(token) => cancel::with_cancellation(token, || {
+                check_current_crate(&crate_program, features, options, memo)
+            })
+            .unwrap_or_else(|Cancelled| {
+                vec![CheckError {
+                    crate_id: c.id.clone(),
+                    features: features.clone(),
+                    item: None,
+                    error: Diagnostic::new(
+                        "E0275",
+                        format!(
+                            "checking crate `{:?}` timed out: exceeded the time limit of {:?}",
+                            c.id,
+                            options.time_limit.unwrap()
+                        ),
+                    )
+                    .into(),
+                }]
+            }),
+        };
+        if !errors.is_empty() {
+            return errors;
+        }
+    }
+    vec![]
+}
+
+/// An error found while checking the crate `crate_id`.
+#[derive(Debug)]
+pub struct CheckError {
+    pub crate_id: CrateId,
+
+    /// The features that the program was checked with (see [`Options::features`]).
+    pub features: Set<Feature>,
+
+    /// The index of the item the error is about, if known, among the items of the
+    /// crate after resolution (see [`SourceMap`](`formality_rust::source_map::SourceMap`)).
+    pub item: Option<usize>,
+
+    pub error: anyhow::Error,
+}
+
+impl std::fmt::Display for CheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The alternate form of `anyhow::Error` includes its context.
+        write!(f, "{:?}", self.error)
+    }
+}
+
+/// A hypothetical change to a program, to ask what it would break: e.g., whether
+/// adding an impl to a crate breaks the crates downstream of it.
+#[derive(Clone, Debug, Default)]
+pub struct WhatIf {
+    /// Items to add to the existing crates.
+    pub items: Vec<(CrateId, CrateItem)>,
+
+    /// Crates to add after the existing ones, e.g., a downstream crate that uses them.
+    pub crates: Vec<Crate>,
+}
+
+impl WhatIf {
+    /// The program as changed by this.
+    pub fn apply(&self, program: &Program) -> Fallible<Program> {
+        let mut program = program.clone();
+        for (crate_id, item) in &self.items {
+            program = program.with_items(crate_id, Some(item.clone()))?;
+        }
+        program.crates.extend(self.crates.iter().cloned());
+        Ok(program)
+    }
+}
+
+/// Checks all crates of `program` as changed by `what_if`. Errors that [`check_all_crates`]
+/// does not report for `program` itself are breakages due to the change.
+pub fn check_what_if(program: &Program, what_if: &WhatIf, options: &Options) -> Fallible<()> {
+    check_all_crates_with_options(&what_if.apply(program)?, options)
+}
+
+/// Flags for semantics that are unsettled in Rust, so that the alternatives
+/// can be compared on the same programs, and for how the checks run. The defaults
+/// follow stable Rust.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Whether type inference falls back to the default of a fn's type parameter
+    /// (see `TyDefaults`) if nothing else determines it, as in the unstable
+    /// `default_type_parameter_fallback` feature. The defaults take precedence over
+    /// the fallback of numeric literals. Otherwise, defaults are ignored.
+    pub type_param_default_fallback: bool,
+
+    /// Whether the items of a crate, and the pairs of impls for coherence, are checked
+    /// concurrently. The errors are reported in the same order either way.
+    pub parallel: bool,
+
+    /// Whether only coherence is checked: the orphan rules and the overlap of impls.
+    /// The items themselves are assumed to be well-formed.
+    pub coherence_only: bool,
+
+    /// How long the checks of each crate may take, after which the prover is cancelled
+    /// (see [`formality_types::judgment::cancel`]). There is no limit by default.
+    pub time_limit: Option<Duration>,
+
+    /// A cache of the responses to the goals that the checks prove, which can be saved
+    /// to be used again by later runs (see [`QueryCache`]). There is none by default.
+    pub query_cache: Option<Arc<QueryCache>>,
+
+    /// The features of the crates to check the program with. None are enabled by default.
+    pub features: Features,
+
+    /// The experimental rules of the prover that are enabled (see [`formality_prove::SolverFlag`]).
+    pub solver_flags: SolverFlags,
+}
+
+impl Options {
+    /// The decls that the checks of `program` prove their goals with.
+    fn prove_decls(&self, program: &Program) -> Decls {
+        let mut decls = program.to_prove_decls();
+        decls.config.flags = self.solver_flags.clone();
+        decls
+    }
+}
+
+/// Which of the features that the items of a program are gated on are enabled when
+/// checking it (see [`formality_rust::cfg`]).
+#[derive(Clone, Debug)]
+pub enum Features {
+    /// The program is checked once, with these features enabled.
+    Enabled(Set<Feature>),
+
+    /// The program is checked with every combination of its features, to find those
+    /// that are not additive. There are exponentially many combinations.
+    AllCombinations,
+
+    /// The program is checked with each of these sets of features enabled.
+    Combinations(Vec<Set<Feature>>),
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Features::Enabled(Set::new())
+    }
+}
+
+impl Features {
+    /// The sets of features to check `program` with, in order.
+    pub fn sets(&self, program: &Program) -> Vec<Set<Feature>> {
+        match self {
+            Features::Enabled(features) => vec![features.clone()],
+            Features::AllCombinations => program.feature_combinations(),
+            Features::Combinations(sets) => sets.clone(),
+        }
+    }
+}
+
+/// Checks the current crate in the program, assuming all other crates are valid.
+fn check_current_crate(
+    program: &Program,
+    features: &Set<Feature>,
+    options: &Options,
+    memo: Option<&Memo>,
+) -> Vec<CheckError> {
+    let crate_id = program.crates.last().unwrap().id.clone();
+    let errors = match lower(program) {
+        Ok(program) => {
+            let decls = options.prove_decls(&program);
+            let errors = with_query_cache(options.query_cache.clone(), || {
+                Check {
+                    program: &program,
+                    decls: &decls,
+                    features,
+                    options,
+                    memo,
+                }
+                .check()
+            });
+
+            // Errors of the checks that span items, e.g. coherence, are about their primary item.
+            let items: Vec<String> = program
+                .crates
+                .last()
+                .unwrap()
+                .items
+                .iter()
+                .map(|item| format!("{item:?}"))
+                .collect();
+            errors
+                .into_iter()
+                .map(|(item, error)| {
+                    let item = item.or_else(|| {
+                        let primary = Diagnostic::of(&error).primary?;
+                        items.iter().position(|i| *i == primary)
+                    });
+                    (item, error)
+                })
+                .collect()
+        }
+        Err(error) => vec![(None, error)],
+    };
+    errors
+        .into_iter()
+        .map(|(item, error)| CheckError {
+            crate_id: crate_id.clone(),
+            features: features.clone(),
+            item,
+            error,
+        })
+        .collect()
+}
+
+/// The program as the checks see it: resolved, without effects and with the implicit `Sized` bounds.
+fn lower(program: &Program) -> Fallible<Program> {
+    program
+        .resolve()?
+        .desugar_effects()?
+        .add_implicit_sized_bounds()
 }
 
 mod adts;
+mod borrowck;
+mod casts;
+mod closures;
 mod coherence;
+mod dataflow;
+mod diagnostic;
+mod exhaustiveness;
+mod fallback;
 mod fns;
+pub mod fuzz;
 mod impls;
+mod incremental;
+mod mir;
+pub mod minimize;
+mod moves;
+mod privacy;
+mod report;
+mod semver;
+mod statics;
 mod traits;
+mod type_aliases;
+mod typeck;
+mod unsafety;
 mod where_clauses;
 
 struct Check<'p> {
     program: &'p Program,
     decls: &'p Decls,
+
+    /// The features that the program was configured with.
+    features: &'p Set<Feature>,
+
+    options: &'p Options,
+
+    /// The checks that still pass since the program was last checked, if checking
+    /// incrementally (see [`IncrementalCheck`]).
+    memo: Option<&'p Memo>,
 }
 
 impl Check<'_> {
-    fn check(&self) -> Fallible<()> {
+    /// Checks the current crate, returning its errors along with the index of the item
+    /// they are about, for those that are about a single item.
+    fn check(&self) -> Vec<(Option<usize>, anyhow::Error)> {
         let Program { crates } = &self.program;
-        if let Some(current_crate) = crates.last() {
-            self.check_current_crate(current_crate)?;
+        match crates.last() {
+            Some(current_crate) => self.check_current_crate(current_crate),
+            None => vec![],
         }
-        Ok(())
     }
 
-    fn check_current_crate(&self, c: &Crate) -> Fallible<()> {
-        let Crate { id: _, items } = c;
+    fn check_current_crate(&self, c: &Crate) -> Vec<(Option<usize>, anyhow::Error)> {
+        let Crate {
+            id: _,
+            edition: _,
+            dependencies: _,
+            items,
+        } = c;
 
-        self.check_for_duplicate_items()?;
+        let mut errors = vec![];
 
-        for item in items {
-            self.check_crate_item(item)?;
+        if self.options.coherence_only {
+            errors.extend(self.check_coherence(c).into_iter().map(|e| (None, e)));
+            return errors;
         }
 
-        self.check_coherence(c)?;
+        errors.extend(self.check_for_duplicate_items().err().map(|e| (None, e)));
 
-        Ok(())
+        let indexed_items: Vec<(usize, &CrateItem)> = items.iter().enumerate().collect();
+        errors.extend(self.check_all(&indexed_items, |&(i, item)| {
+            self.memoized(Unit::Item(c.id.clone(), item.clone()), || {
+                self.check_crate_item(item)
+            })
+            .map_err(|e| (Some(i), e))
+        }));
+
+        errors.extend(self.check_coherence(c).into_iter().map(|e| (None, e)));
+
+        errors.extend(self.check_privacy(c).err().map(|e| (None, e)));
+
+        errors.extend(self.check_unsafety(c).err().map(|e| (None, e)));
+
+        errors
+    }
+
+    /// Applies the independent check `op` to each of `items`, concurrently if
+    /// [`Options::parallel`] is set, and returns the errors in the order of `items`.
+    fn check_all<T: Sync, E: Send>(
+        &self,
+        items: &[T],
+        op: impl Fn(&T) -> Result<(), E> + Sync + Send,
+    ) -> Vec<E> {
+        if self.options.parallel {
+            let active = cancel::active();
+            let query_cache = active_query_cache();
+            items
+                .par_iter()
+                .map(|item| {
+                    cancel::with_active(active.clone(), || {
+                        with_query_cache(query_cache.clone(), || op(item))
+                    })
+                })
+                .filter_map(Result::err)
+                .collect()
+        } else {
+            items.iter().map(op).filter_map(Result::err).collect()
+        }
+    }
+
+    /// The edition of the current crate, for the rules that differ by edition to branch on.
+    fn edition(&self) -> Edition {
+        self.program
+            .crates
+            .last()
+            .map(|c| c.edition)
+            .unwrap_or_default()
+    }
+
+    /// Runs the check `op` of `unit`, unless the [memo](`Check::memo`) says it still passes.
+    fn memoized<E>(&self, unit: Unit, op: impl FnOnce() -> Result<(), E>) -> Result<(), E> {
+        match self.memo {
+            Some(memo) => memo.check(self.features, unit, op),
+            None => op(),
+        }
     }
 
     fn check_for_duplicate_items(&self) -> Fallible<()> {
@@ -82,7 +487,17 @@ impl Check<'_> {
             CrateItem::Struct(s) => self.check_adt(&s.to_adt()),
             CrateItem::Enum(e) => self.check_adt(&e.to_adt()),
             CrateItem::Fn(f) => self.check_free_fn(f),
+            CrateItem::ExternBlock(b) => self.check_extern_block(b),
             CrateItem::NegTraitImpl(i) => self.check_neg_trait_impl(i),
+            CrateItem::Static(s) => self.check_static(s),
+            CrateItem::Const(c) => self.check_const_item(c),
+            CrateItem::TypeAlias(t) => self.check_type_alias(t),
+            CrateItem::Module(m) => m
+                .items
+                .iter()
+                .try_for_each(|item| self.check_crate_item(item)),
+            // Gated items left in the program are disabled (see `formality_rust::cfg`).
+            CrateItem::Cfg(_) => Ok(()),
         }
     }
 
@@ -98,12 +513,52 @@ impl Check<'_> {
         assert!(env.only_universal_variables());
         assert!(env.encloses((&assumptions, &goal)));
 
-        let cs = formality_prove::prove(self.decls, env, &assumptions, &goal);
-        if cs.iter().any(|c| c.unconditionally_true()) {
-            return Ok(());
+        let result = formality_prove::prove_result(self.decls, env, &assumptions, &goal);
+        match &result {
+            ProofResult::Yes(cs) if cs.iter().any(|c| c.unconditionally_true()) => {
+                report::record_proof_tree(self.decls, env, &assumptions, &goal);
+                return Ok(());
+            }
+            ProofResult::Ambiguous(AmbiguousConstraints { overflow: true, .. }) => {
+                bail!(Diagnostic::new(
+                    "E0275",
+                    format!("overflow evaluating {goal:?} given {assumptions:?}: exceeded the solver's max depth or max size")
+                )
+                .with_primary(&goal))
+            }
+            ProofResult::Ambiguous(AmbiguousConstraints { constraints, .. }) => {
+                bail!(Diagnostic::new(
+                    "E0283",
+                    format!("cannot decide whether {goal:?} holds given {assumptions:?}, got ambiguous {constraints:?}")
+                )
+                .with_primary(&goal))
+            }
+            ProofResult::TimedOut => {
+                bail!(Diagnostic::new(
+                    "E0275",
+                    format!("timed out proving {goal:?} given {assumptions:?}")
+                )
+                .with_primary(&goal))
+            }
+            ProofResult::Yes(_) | ProofResult::No(_) => {}
         }
 
-        bail!("failed to prove {goal:?} given {assumptions:?}, got {cs:?}")
+        let cs = result.constraints();
+        let trace = formality_prove::prove_failure_trace(self.decls, env, &assumptions, &goal);
+        let mut occurs_checks = String::new();
+        if let ProofResult::No(ocs) = &result {
+            for oc in ocs {
+                occurs_checks.push_str(&format!("\noccurs check failed: {oc}"));
+            }
+        }
+        bail!(Diagnostic::new(
+            "E0277",
+            format!(
+                "failed to prove {goal:?} given {assumptions:?}, got {cs:?}{occurs_checks}\n{}",
+                trace.to_string().trim_end()
+            )
+        )
+        .with_primary(&goal))
     }
 
     fn prove_not_goal(
@@ -124,31 +579,68 @@ impl Check<'_> {
         // we've proven the negation. (This is called the "negation as failure" property,
         // and it relies on our solver being complete -- i.e., if there is a solution,
         // we'll find it, or at least return ambiguous.)
-        let mut existential_env = Env::default().with_coherence_mode(env.is_in_coherence_mode());
-        let universal_to_existential: Substitution = env
-            .variables()
-            .iter()
-            .map(|v| {
-                assert!(v.is_universal());
-                let v1 = existential_env.fresh_existential(v.kind());
-                (v, v1)
-            })
-            .collect();
-
-        let existential_assumptions = universal_to_existential.apply(&assumptions);
-        let existential_goal = universal_to_existential.apply(&goal);
+        let (existential_env, existential_assumptions, existential_goal) =
+            existentially(env, &assumptions, &goal);
 
-        let cs = formality_prove::prove(
+        // Any solution will do to fail, so stop at the first one.
+        let solution = formality_prove::prove_lazily(
             self.decls,
-            &existential_env,
-            existential_assumptions.to_wcs(),
+            existential_env,
+            existential_assumptions,
             &existential_goal,
-        );
+        )
+        .next();
 
-        if cs.is_empty() {
-            return Ok(());
+        match solution {
+            None => Ok(()),
+            Some(c) if !c.known_true => bail!(
+                "failed to disprove\n    {goal:?}\ngiven\n    {assumptions:?}\nas it may hold, got\n{c:#?}"
+            ),
+            Some(c) => bail!(
+                "failed to disprove\n    {goal:?}\ngiven\n    {assumptions:?}\ngot\n{c:#?}"
+            ),
         }
+    }
 
-        bail!("failed to disprove\n    {goal:?}\ngiven\n    {assumptions:?}\ngot\n{cs:#?}")
+    /// In coherence mode, the goals that keep [`Self::prove_not_goal`] from disproving
+    /// `goal` because other crates may implement them.
+    fn unknowable_goals(
+        &self,
+        env: &Env,
+        assumptions: impl ToWcs,
+        goal: impl ToWcs,
+    ) -> Vec<Unknowable> {
+        let (existential_env, existential_assumptions, existential_goal) =
+            existentially(env, &assumptions.to_wcs(), &goal.to_wcs());
+        match formality_prove::prove_result(
+            self.decls,
+            existential_env,
+            existential_assumptions,
+            existential_goal,
+        ) {
+            ProofResult::Ambiguous(AmbiguousConstraints { unknowable, .. }) => unknowable,
+            ProofResult::Yes(_) | ProofResult::No(_) | ProofResult::TimedOut => vec![],
+        }
     }
 }
+
+/// Replaces the universal variables of `env`, which must have no others, with
+/// existential ones in `assumptions` and `goal`.
+fn existentially(env: &Env, assumptions: &Wcs, goal: &Wcs) -> (Env, Wcs, Wcs) {
+    let mut existential_env = Env::default().with_coherence_mode(env.is_in_coherence_mode());
+    let universal_to_existential: Substitution = env
+        .variables()
+        .iter()
+        .map(|v| {
+            assert!(v.is_universal());
+            let v1 = existential_env.fresh_existential(v.kind());
+            (v, v1)
+        })
+        .collect();
+
+    (
+        existential_env,
+        universal_to_existential.apply(assumptions),
+        universal_to_existential.apply(goal),
+    )
+}
@@ -0,0 +1,382 @@
+//! Minimizing a program that the checks go wrong on, to get a small reproducer.
+//!
+//! [`minimize`] removes what it can of a program while it still fails: whole crates,
+//! items (including those of modules, traits, impls and extern blocks), where-clauses,
+//! and the type and lifetime parameters of items, whose uses become `()` and `static`.
+//! Each kind of removal is tried on chunks that are halved down to single removals, as
+//! in delta debugging, and the kinds are repeated until nothing more can be removed.
+
+use std::{any::Any, ops::Range, sync::Arc};
+
+use formality_rust::grammar::{
+    AdtBoundData, AssociatedTy, AssociatedTyBoundData, AssociatedTyValue,
+    AssociatedTyValueBoundData, CfgItem, Crate, CrateItem, Enum, ExternBlock, Fn, FnBoundData,
+    ImplItem, Module, NegTraitImpl, NegTraitImplBoundData, Program, Struct, StructBoundData, Trait,
+    TraitBinder, TraitBoundData, TraitImpl, TraitImplBoundData, TraitItem, TypeAlias,
+    TypeAliasBoundData, WhereClause,
+};
+use formality_types::{
+    cast::Upcast,
+    fold::Fold,
+    grammar::{Binder, BoundVar, Lt, Parameter, ParameterKind, Ty, Variable},
+};
+
+use crate::{check_all_crates_errors, Options};
+
+/// The stack size of the thread that checks a program, as the prover recurses deeply.
+const STACK_SIZE: usize = 64 << 20;
+
+/// How the checks go wrong on a program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// The checks report an error whose message (with its context) contains this.
+    Error(String),
+
+    /// The checks report no errors, although they should.
+    Pass,
+
+    /// Checking panics, with a message that contains this.
+    Panic(String),
+}
+
+impl Failure {
+    /// Whether checking `program` goes wrong this way.
+    pub fn reproduces(&self, program: &Program) -> bool {
+        let errors = std::thread::scope(|scope| {
+            std::thread::Builder::new()
+                .stack_size(STACK_SIZE)
+                .spawn_scoped(scope, || {
+                    check_all_crates_errors(program, &Options::default())
+                        .iter()
+                        .map(|e| e.to_string())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap()
+                .join()
+        });
+        match (self, errors) {
+            (Failure::Error(pattern), Ok(errors)) => errors.iter().any(|e| e.contains(pattern)),
+            (Failure::Pass, Ok(errors)) => errors.is_empty(),
+            (Failure::Panic(pattern), Err(payload)) => panic_message(&payload).contains(pattern),
+            _ => false,
+        }
+    }
+}
+
+fn panic_message(payload: &Box<dyn Any + Send>) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s
+    } else {
+        ""
+    }
+}
+
+/// A smaller version of `program` that still `fails`, from which nothing more can be
+/// removed without it passing (see the module documentation). `fails` should only
+/// accept the failure of interest, e.g. with [`Failure::reproduces`], lest the program
+/// be reduced to one that fails for another reason.
+pub fn minimize(program: &Program, mut fails: impl FnMut(&Program) -> bool) -> Program {
+    let mut program = program.clone();
+    loop {
+        let before = program.clone();
+        for kind in [Kind::Crate, Kind::Item, Kind::WhereClause, Kind::Parameter] {
+            program = remove_chunks(program, kind, &mut fails);
+        }
+        if program == before {
+            return program;
+        }
+    }
+}
+
+/// Removes chunks of the things of kind `kind` while the program still fails, starting
+/// with all of them and halving the chunks down to single things.
+fn remove_chunks(
+    mut program: Program,
+    kind: Kind,
+    fails: &mut impl FnMut(&Program) -> bool,
+) -> Program {
+    let mut size = Removal::count(&program, kind);
+    while size > 0 {
+        let mut start = 0;
+        while start < Removal::count(&program, kind) {
+            let candidate = Removal::new(kind, start..start + size).program(&program);
+            if fails(&candidate) {
+                program = candidate;
+            } else {
+                start += size;
+            }
+        }
+        size /= 2;
+    }
+    program
+}
+
+/// The kinds of things that [`minimize`] removes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Kind {
+    Crate,
+    Item,
+    WhereClause,
+    Parameter,
+}
+
+/// Rebuilds a program without the things of kind `kind` whose index is in `range`,
+/// numbering them in the order they are visited. The things within a removed thing
+/// are visited all the same, so that the numbering does not depend on `range`.
+struct Removal {
+    kind: Kind,
+    range: Range<usize>,
+    next: usize,
+}
+
+impl Removal {
+    fn new(kind: Kind, range: Range<usize>) -> Self {
+        Removal {
+            kind,
+            range,
+            next: 0,
+        }
+    }
+
+    /// The number of things of kind `kind` in `program`.
+    fn count(program: &Program, kind: Kind) -> usize {
+        let mut removal = Removal::new(kind, 0..0);
+        removal.program(program);
+        removal.next
+    }
+
+    /// Whether to remove the next thing of kind `kind`.
+    fn removes(&mut self, kind: Kind) -> bool {
+        if kind != self.kind {
+            return false;
+        }
+        let index = self.next;
+        self.next += 1;
+        self.range.contains(&index)
+    }
+
+    fn retain<T>(
+        &mut self,
+        kind: Kind,
+        ts: &[T],
+        mut op: impl FnMut(&mut Self, &T) -> T,
+    ) -> Vec<T> {
+        ts.iter()
+            .filter_map(|t| {
+                let removed = self.removes(kind);
+                let t = op(self, t);
+                (!removed).then_some(t)
+            })
+            .collect()
+    }
+
+    fn program(&mut self, program: &Program) -> Program {
+        Program {
+            crates: self.retain(Kind::Crate, &program.crates, |this, c| Crate {
+                id: c.id.clone(),
+                edition: c.edition,
+                dependencies: c.dependencies.clone(),
+                items: this.crate_items(&c.items),
+            }),
+        }
+    }
+
+    fn crate_items(&mut self, items: &[CrateItem]) -> Vec<CrateItem> {
+        self.retain(Kind::Item, items, |this, item| this.crate_item(item))
+    }
+
+    fn crate_item(&mut self, item: &CrateItem) -> CrateItem {
+        match item {
+            CrateItem::Struct(s) => Struct {
+                binder: self.binder(
+                    &s.binder,
+                    0,
+                    |this,
+                     StructBoundData {
+                         where_clauses,
+                         fields,
+                     }| StructBoundData {
+                        where_clauses: this.where_clauses(&where_clauses),
+                        fields,
+                    },
+                ),
+                ..s.clone()
+            }
+            .upcast(),
+            CrateItem::Enum(e) => Enum {
+                binder: self.binder(
+                    &e.binder,
+                    0,
+                    |this,
+                     AdtBoundData {
+                         where_clauses,
+                         variants,
+                     }| AdtBoundData {
+                        where_clauses: this.where_clauses(&where_clauses),
+                        variants,
+                    },
+                ),
+                ..e.clone()
+            }
+            .upcast(),
+            CrateItem::Trait(t) => self.trait_(t).upcast(),
+            CrateItem::TraitImpl(i) => TraitImpl {
+                binder: self.binder(&i.binder, 0, |this, data| TraitImplBoundData {
+                    where_clauses: this.where_clauses(&data.where_clauses),
+                    impl_items: this.retain(Kind::Item, &data.impl_items, |this, impl_item| {
+                        this.impl_item(impl_item)
+                    }),
+                    ..data
+                }),
+                ..i.clone()
+            }
+            .upcast(),
+            CrateItem::NegTraitImpl(i) => NegTraitImpl {
+                binder: self.binder(&i.binder, 0, |this, data| NegTraitImplBoundData {
+                    where_clauses: this.where_clauses(&data.where_clauses),
+                    ..data
+                }),
+            }
+            .upcast(),
+            CrateItem::Fn(f) => self.fn_(f).upcast(),
+            CrateItem::Static(_) | CrateItem::Const(_) => item.clone(),
+            CrateItem::TypeAlias(t) => TypeAlias {
+                binder: self.binder(
+                    &t.binder,
+                    0,
+                    |this, TypeAliasBoundData { ty, where_clauses }| TypeAliasBoundData {
+                        ty,
+                        where_clauses: this.where_clauses(&where_clauses),
+                    },
+                ),
+                ..t.clone()
+            }
+            .upcast(),
+            CrateItem::Module(m) => Module {
+                items: self.crate_items(&m.items),
+                ..m.clone()
+            }
+            .upcast(),
+            CrateItem::ExternBlock(b) => ExternBlock {
+                abi: b.abi,
+                fns: self.retain(Kind::Item, &b.fns, |this, f| this.fn_(f)),
+            }
+            .upcast(),
+            CrateItem::Cfg(c) => CfgItem {
+                feature: c.feature.clone(),
+                item: Arc::new(self.crate_item(&c.item)),
+            }
+            .upcast(),
+        }
+    }
+
+    /// The first parameter of a trait is `Self`, which stays.
+    fn trait_(&mut self, t: &Trait) -> Trait {
+        Trait {
+            binder: TraitBinder {
+                explicit_binder: self.binder(
+                    &t.binder.explicit_binder,
+                    1,
+                    |this,
+                     TraitBoundData {
+                         where_clauses,
+                         trait_items,
+                     }| TraitBoundData {
+                        where_clauses: this.where_clauses(&where_clauses),
+                        trait_items: this.retain(Kind::Item, &trait_items, |this, trait_item| {
+                            this.trait_item(trait_item)
+                        }),
+                    },
+                ),
+            },
+            ..t.clone()
+        }
+    }
+
+    fn trait_item(&mut self, trait_item: &TraitItem) -> TraitItem {
+        match trait_item {
+            TraitItem::Fn(f) => self.fn_(f).upcast(),
+            TraitItem::AssociatedTy(AssociatedTy { id, binder }) => AssociatedTy {
+                id: id.clone(),
+                binder: self.binder(
+                    binder,
+                    0,
+                    |this,
+                     AssociatedTyBoundData {
+                         ensures,
+                         where_clauses,
+                     }| AssociatedTyBoundData {
+                        ensures,
+                        where_clauses: this.where_clauses(&where_clauses),
+                    },
+                ),
+            }
+            .upcast(),
+        }
+    }
+
+    fn impl_item(&mut self, impl_item: &ImplItem) -> ImplItem {
+        match impl_item {
+            ImplItem::Fn(f) => self.fn_(f).upcast(),
+            ImplItem::AssociatedTyValue(AssociatedTyValue { id, binder }) => AssociatedTyValue {
+                id: id.clone(),
+                binder: self.binder(
+                    binder,
+                    0,
+                    |this, AssociatedTyValueBoundData { where_clauses, ty }| {
+                        AssociatedTyValueBoundData {
+                            where_clauses: this.where_clauses(&where_clauses),
+                            ty,
+                        }
+                    },
+                ),
+            }
+            .upcast(),
+        }
+    }
+
+    fn fn_(&mut self, f: &Fn) -> Fn {
+        Fn {
+            binder: self.binder(&f.binder, 0, |this, data| FnBoundData {
+                where_clauses: this.where_clauses(&data.where_clauses),
+                ..data
+            }),
+            ..f.clone()
+        }
+    }
+
+    /// Removes parameters of `binder`, except for the first `fixed` ones and const
+    /// parameters, and rebuilds its contents with `op`.
+    fn binder<T: Fold>(
+        &mut self,
+        binder: &Binder<T>,
+        fixed: usize,
+        op: impl FnOnce(&mut Self, T) -> T,
+    ) -> Binder<T> {
+        let (vars, data) = binder.open();
+        let (mut kept, mut removed): (Vec<BoundVar>, Vec<BoundVar>) = (vec![], vec![]);
+        for (i, var) in vars.into_iter().enumerate() {
+            if i >= fixed && var.kind != ParameterKind::Const && self.removes(Kind::Parameter) {
+                removed.push(var);
+            } else {
+                kept.push(var);
+            }
+        }
+        let data = data.substitute(&mut |v: Variable| {
+            let var = removed.iter().find(|&&var| v == var.upcast())?;
+            let replacement: Parameter = match var.kind {
+                ParameterKind::Lt => Lt::static_().upcast(),
+                _ => Ty::unit().upcast(),
+            };
+            Some(replacement)
+        });
+        let data = op(self, data);
+        Binder::new(kept, data)
+    }
+
+    fn where_clauses(&mut self, where_clauses: &[WhereClause]) -> Vec<WhereClause> {
+        self.retain(Kind::WhereClause, where_clauses, |_, wc| wc.clone())
+    }
+}
@@ -0,0 +1,489 @@
+//! Type checking of MIR fn bodies.
+//!
+//! Unlike expressions, MIR is already fully typed: each local declares its type,
+//! and the only unknowns are the lifetimes bound by the body's binder (which we
+//! instantiate existentially). Type checking therefore just walks the body and
+//! generates obligations. Trait and equality goals are proven right away; outlives
+//! constraints between lifetimes are collected and handed to the borrow checker.
+
+use anyhow::bail;
+use formality_prove::Env;
+use formality_rust::{
+    grammar::{
+        mir::{
+            AggregateKind, BasicBlockDecl, BasicBlockId, BinaryOp, Constant, LocalDecl, LocalId,
+            LocalsAndBlocks, MirFnBody, Operand, Place, PlaceTy, Projection, Rvalue, Statement,
+            Terminator,
+        },
+        FieldName, FnBoundData, Program,
+    },
+    prove::ToWcs,
+};
+use formality_types::{
+    cast::{Downcast, Upcast},
+    collections::{Map, Set},
+    grammar::{
+        Fallible, Lt, ParameterKind, Predicate, RefKind, Relation, RigidName, RigidTy, Ty, Wc, Wcs,
+    },
+};
+
+use crate::{borrowck, Check};
+
+impl Check<'_> {
+    pub(crate) fn check_mir_fn_body(
+        &self,
+        env: &Env,
+        assumptions: impl ToWcs,
+        input_tys: &[Ty],
+        output_ty: &Ty,
+        body: &MirFnBody,
+    ) -> Fallible<()> {
+        let assumptions: Wcs = assumptions.to_wcs();
+        let results = self.typeck_mir_fn_body(env, &assumptions, input_tys, output_ty, body)?;
+        self.check_moves(&assumptions, input_tys.len(), &results)?;
+        borrowck::borrow_check(&results)
+    }
+
+    /// Type checks a MIR body, returning the instantiated body along with the
+    /// outlives constraints that must hold between its lifetimes.
+    pub(crate) fn typeck_mir_fn_body(
+        &self,
+        env: &Env,
+        assumptions: impl ToWcs,
+        input_tys: &[Ty],
+        output_ty: &Ty,
+        body: &MirFnBody,
+    ) -> Fallible<MirTypeckResults> {
+        let assumptions: Wcs = assumptions.to_wcs();
+        let MirFnBody { binder } = body;
+        let (env, subst) = env.existential_substitution(binder);
+        let LocalsAndBlocks {
+            local_decls,
+            basic_block_decls,
+        } = binder.instantiate_with(&subst)?;
+
+        let mut cx = MirTypeck::new(self.program, env, &local_decls, &basic_block_decls)?;
+
+        // `_0` is the return place, followed by the fn inputs.
+        if local_decls.len() < input_tys.len() + 1 {
+            bail!(
+                "MIR body must declare a return place and {} inputs",
+                input_tys.len()
+            );
+        }
+        cx.sub(&local_decls[0].ty, output_ty, "the return type");
+        for ((input_ty, local_decl), i) in input_tys.iter().zip(&local_decls[1..]).zip(1..) {
+            cx.sub(input_ty, &local_decl.ty, &format!("the type of input {i}"));
+        }
+
+        for block in &basic_block_decls {
+            cx.check_block(block)?;
+        }
+
+        let MirTypeck {
+            env,
+            goals,
+            outlives,
+            moves,
+            casts,
+            ..
+        } = cx;
+        let goals: Wcs = goals.into_iter().collect();
+        let cs = formality_prove::prove(self.decls, env, &assumptions, &goals);
+        match cs.iter().find(|c| c.known_true) {
+            Some(c) => {
+                let subst = c.substitution();
+                self.check_casts(c.env(), &assumptions, &subst.apply(&casts))?;
+                Ok(MirTypeckResults {
+                    local_decls: subst.apply(&local_decls),
+                    basic_block_decls: subst.apply(&basic_block_decls),
+                    outlives: outlives
+                        .into_iter()
+                        .map(|OutlivesConstraint { a, b, origin }| OutlivesConstraint {
+                            a: subst.apply(&a),
+                            b: subst.apply(&b),
+                            origin,
+                        })
+                        .collect(),
+                    moves: subst.apply(&moves),
+                    env: c.env().clone(),
+                })
+            }
+            None => bail!("failed to prove {goals:?} given {assumptions:?}, got {cs:?}"),
+        }
+    }
+}
+
+/// A type checked MIR body, with its lifetimes instantiated.
+pub(crate) struct MirTypeckResults {
+    pub local_decls: Vec<LocalDecl>,
+    pub basic_block_decls: Vec<BasicBlockDecl>,
+
+    /// The outlives constraints that must hold between the body's lifetimes.
+    pub outlives: Vec<OutlivesConstraint>,
+
+    /// The places moved out of by `move` operands, along with their types.
+    pub moves: Vec<(Place, Ty)>,
+
+    /// The environment in which the body was type checked.
+    pub env: Env,
+}
+
+/// A constraint `a: b` between lifetimes, along with what required it.
+pub(crate) struct OutlivesConstraint {
+    pub a: Lt,
+    pub b: Lt,
+
+    /// E.g., "the assignment at bb0[1]".
+    pub origin: String,
+}
+
+struct MirTypeck<'p> {
+    program: &'p Program,
+    env: Env,
+    local_tys: Map<LocalId, Ty>,
+    block_ids: Set<BasicBlockId>,
+
+    /// Trait and equality goals, proven once the whole body has been walked.
+    goals: Vec<Wc>,
+
+    /// Outlives constraints between lifetimes, left for borrow checking.
+    outlives: Vec<OutlivesConstraint>,
+
+    /// The statement or terminator being checked, for the origins of outlives constraints.
+    location: String,
+
+    /// Places moved out of, with their types.
+    moves: Vec<(Place, Ty)>,
+
+    /// The source and target types of casts, checked once the goals have been proven.
+    casts: Vec<(Ty, Ty)>,
+}
+
+impl<'p> MirTypeck<'p> {
+    fn new(
+        program: &'p Program,
+        env: Env,
+        local_decls: &[LocalDecl],
+        basic_block_decls: &[BasicBlockDecl],
+    ) -> Fallible<Self> {
+        let mut local_tys = Map::new();
+        let mut goals = vec![];
+        for LocalDecl { name, ty, .. } in local_decls {
+            if local_tys.insert(name.clone(), ty.clone()).is_some() {
+                bail!("local `{name:?}` declared twice");
+            }
+            goals.extend(program.sized_goal(ty));
+        }
+
+        let mut block_ids = Set::new();
+        for BasicBlockDecl { id, .. } in basic_block_decls {
+            if !block_ids.insert(id.clone()) {
+                bail!("basic block `{id:?}` declared twice");
+            }
+        }
+
+        Ok(MirTypeck {
+            program,
+            env,
+            local_tys,
+            block_ids,
+            goals,
+            outlives: vec![],
+            location: String::new(),
+            moves: vec![],
+            casts: vec![],
+        })
+    }
+
+    /// Requires that a value of type `a` can be stored in a place of type `b`.
+    ///
+    /// References are covariant in their lifetime (and, for shared references, their referent),
+    /// `PhantomData<T>` is covariant in `T`, and everything else is related invariantly.
+    /// `origin` describes what requires it, for error messages.
+    fn sub(&mut self, a: &Ty, b: &Ty, origin: &str) {
+        match (a.as_ref_ty(), b.as_ref_ty()) {
+            (Some((RefKind::Shared, lt_a, ty_a)), Some((RefKind::Shared, lt_b, ty_b))) => {
+                self.outlives(lt_a, lt_b, origin);
+                self.sub(&ty_a, &ty_b, origin);
+            }
+            (Some((RefKind::Mut, lt_a, ty_a)), Some((RefKind::Mut, lt_b, ty_b))) => {
+                self.outlives(lt_a, lt_b, origin);
+                self.goals.push(Relation::eq(ty_a, ty_b).upcast());
+            }
+            _ => match (a.as_phantom_data_ty(), b.as_phantom_data_ty()) {
+                (Some(ty_a), Some(ty_b)) => self.sub(&ty_a, &ty_b, origin),
+                _ => self.goals.push(Relation::eq(a, b).upcast()),
+            },
+        }
+    }
+
+    fn outlives(&mut self, a: Lt, b: Lt, origin: &str) {
+        self.outlives.push(OutlivesConstraint {
+            a,
+            b,
+            origin: origin.to_string(),
+        });
+    }
+
+    fn check_block(&mut self, block: &BasicBlockDecl) -> Fallible<()> {
+        let BasicBlockDecl {
+            id,
+            statements,
+            terminator,
+        } = block;
+        for (index, statement) in statements.iter().enumerate() {
+            self.location = format!("{id:?}[{index}]");
+            self.check_statement(statement)?;
+        }
+        self.location = format!("{id:?}[{}]", statements.len());
+        self.check_terminator(terminator)
+    }
+
+    fn check_statement(&mut self, statement: &Statement) -> Fallible<()> {
+        match statement {
+            Statement::Assign(place, rvalue) => {
+                let place_ty = self.place_ty(place)?;
+                let rvalue_ty = self.rvalue_ty(rvalue)?;
+                self.sub(
+                    &rvalue_ty,
+                    &place_ty,
+                    &format!("the assignment at {}", self.location),
+                );
+            }
+            Statement::Noop => {}
+            Statement::FakeRead(place) => {
+                self.place_ty(place)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_terminator(&mut self, terminator: &Terminator) -> Fallible<()> {
+        match terminator {
+            Terminator::Goto(target) => self.check_targets(std::slice::from_ref(target))?,
+            Terminator::Resume
+            | Terminator::Abort
+            | Terminator::Return
+            | Terminator::Unreachable => {}
+            Terminator::Drop(place, targets) | Terminator::DropAndReplace(place, targets) => {
+                self.place_ty(place)?;
+                self.check_targets(targets)?;
+            }
+            Terminator::Call(func, args, destination, targets) => {
+                let func_ty = self.operand_ty(func)?;
+                let Some(RigidTy {
+                    name: RigidName::FnDef(fn_id),
+                    parameters,
+                }) = func_ty.downcast()
+                else {
+                    bail!("cannot call a value of type `{func_ty:?}`")
+                };
+                let f = self.program.fn_named(&fn_id)?;
+                let FnBoundData {
+                    defaults: _,
+                    self_param: _,
+                    input_tys,
+                    output_ty,
+                    where_clauses,
+                    body: _,
+                } = f.binder.instantiate_with(&parameters)?;
+                if input_tys.len() != args.len() {
+                    bail!(
+                        "`{fn_id:?}` expects {} arguments, got {}",
+                        input_tys.len(),
+                        args.len()
+                    );
+                }
+                for ((arg, input_ty), i) in args.iter().zip(&input_tys).zip(1..) {
+                    let arg_ty = self.operand_ty(arg)?;
+                    let origin = format!("argument {i} of the call at {}", self.location);
+                    self.sub(&arg_ty, input_ty, &origin);
+                }
+                let destination_ty = self.place_ty(destination)?;
+                let origin = format!("the result of the call at {}", self.location);
+                self.sub(&output_ty, &destination_ty, &origin);
+                self.goals.extend(where_clauses.to_wcs());
+                self.check_targets(targets)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_targets(&self, targets: &[BasicBlockId]) -> Fallible<()> {
+        for target in targets {
+            if !self.block_ids.contains(target) {
+                bail!("no basic block named `{target:?}`");
+            }
+        }
+        Ok(())
+    }
+
+    fn place_ty(&self, place: &Place) -> Fallible<Ty> {
+        let Place {
+            local_id,
+            projections,
+        } = place;
+        let Some(ty) = self.local_tys.get(local_id) else {
+            bail!("no local named `{local_id:?}`")
+        };
+
+        let mut place_ty = PlaceTy::Ty(ty.clone());
+        for projection in projections {
+            place_ty = self.project(place_ty, projection)?;
+        }
+
+        match place_ty {
+            PlaceTy::Ty(ty) => Ok(ty),
+            PlaceTy::VariantTy(ty, variant) => {
+                bail!("place `{place:?}` has type `{ty:?}` downcast to `{variant:?}`")
+            }
+        }
+    }
+
+    fn project(&self, place_ty: PlaceTy, projection: &Projection) -> Fallible<PlaceTy> {
+        match (&place_ty, projection) {
+            (PlaceTy::Ty(ty), Projection::Deref) => match ty.as_ref_ty() {
+                Some((_, _, referent)) => Ok(referent.upcast()),
+                None => bail!("cannot dereference a value of type `{ty:?}`"),
+            },
+            (PlaceTy::Ty(ty), Projection::Field(field)) => {
+                let Some(RigidTy { name, parameters }) = ty.downcast() else {
+                    bail!("no field `{field:?}` on type `{ty:?}`")
+                };
+                let field_ty = match (&name, field) {
+                    (RigidName::Tuple(_), FieldName::Index(i)) => {
+                        parameters.get(*i).and_then(|p| p.downcast::<Ty>())
+                    }
+                    (RigidName::AdtId(adt_id), _) => self
+                        .program
+                        .adt_named(adt_id)?
+                        .struct_field_ty(&parameters, field)?,
+                    _ => None,
+                };
+                match field_ty {
+                    Some(field_ty) => Ok(field_ty.upcast()),
+                    None => bail!("no field `{field:?}` on type `{ty:?}`"),
+                }
+            }
+            (PlaceTy::VariantTy(ty, variant), Projection::Field(field)) => {
+                let Some(RigidTy {
+                    name: RigidName::AdtId(adt_id),
+                    parameters,
+                }) = ty.downcast()
+                else {
+                    bail!("no variant `{variant:?}` on type `{ty:?}`")
+                };
+                match self.program.adt_named(&adt_id)?.variant_field_ty(
+                    &parameters,
+                    variant,
+                    field,
+                )? {
+                    Some(field_ty) => Ok(field_ty.upcast()),
+                    None => bail!("no field `{field:?}` on variant `{variant:?}` of `{ty:?}`"),
+                }
+            }
+            (PlaceTy::Ty(ty), Projection::Downcast(variant)) => {
+                Ok(PlaceTy::VariantTy(ty.clone(), variant.clone()))
+            }
+            (_, Projection::Index(_)) => bail!("indexing is not supported"),
+            (PlaceTy::VariantTy(..), _) => {
+                bail!("cannot apply `{projection:?}` to `{place_ty:?}`")
+            }
+        }
+    }
+
+    fn operand_ty(&mut self, operand: &Operand) -> Fallible<Ty> {
+        match operand {
+            Operand::Move(place) => {
+                let ty = self.place_ty(place)?;
+                self.moves.push((place.clone(), ty.clone()));
+                Ok(ty)
+            }
+            Operand::Copy(place) => self.place_ty(place),
+            Operand::Const(constant) => Ok(self.constant_ty(constant)),
+        }
+    }
+
+    fn constant_ty(&mut self, constant: &Constant) -> Ty {
+        match constant {
+            Constant::Value(c) => match c.as_value() {
+                Some((_, ty)) => ty,
+                None => {
+                    let ty: Ty = self.env.fresh_existential(ParameterKind::Ty).upcast();
+                    self.goals
+                        .push(Predicate::ConstHasType(c.clone(), ty.clone()).upcast());
+                    ty
+                }
+            },
+            Constant::FnPtr(fn_id, parameters) => {
+                Ty::rigid(RigidName::FnDef(fn_id.clone()), parameters)
+            }
+            Constant::Tuple(constants) => {
+                let tys: Vec<Ty> = constants.iter().map(|c| self.constant_ty(c)).collect();
+                Ty::rigid(RigidName::Tuple(tys.len()), tys)
+            }
+        }
+    }
+
+    fn rvalue_ty(&mut self, rvalue: &Rvalue) -> Fallible<Ty> {
+        match rvalue {
+            Rvalue::Use(operand) => self.operand_ty(operand),
+            Rvalue::Ref(lt, RefKind::Shared, place) => Ok(self.place_ty(place)?.ref_ty(lt)),
+            Rvalue::Ref(lt, RefKind::Mut, place) | Rvalue::TwoPhaseRef(lt, place) => {
+                Ok(self.place_ty(place)?.ref_mut_ty(lt))
+            }
+            Rvalue::Apply(a, op, b) => self.binary_op_ty(a, op, b),
+            Rvalue::Checked(a, op, b) => {
+                let ty = self.binary_op_ty(a, op, b)?;
+                Ok(Ty::rigid(RigidName::Tuple(2), vec![ty, Ty::bool()]))
+            }
+            Rvalue::Aggregate(AggregateKind::Tuple, operands) => {
+                let tys = operands
+                    .iter()
+                    .map(|o| self.operand_ty(o))
+                    .collect::<Fallible<Vec<Ty>>>()?;
+                Ok(Ty::rigid(RigidName::Tuple(tys.len()), tys))
+            }
+            Rvalue::Aggregate(AggregateKind::Adt(adt_id, variant, parameters), operands) => {
+                let Some(field_tys) = self
+                    .program
+                    .adt_named(adt_id)?
+                    .variant_field_tys(parameters, variant)?
+                else {
+                    bail!("no variant `{variant:?}` in `{adt_id:?}`")
+                };
+                if field_tys.len() != operands.len() {
+                    bail!(
+                        "variant `{variant:?}` of `{adt_id:?}` has {} fields, got {} operands",
+                        field_tys.len(),
+                        operands.len()
+                    );
+                }
+                for ((operand, field_ty), i) in operands.iter().zip(&field_tys).zip(0..) {
+                    let operand_ty = self.operand_ty(operand)?;
+                    let origin = format!("field {i} of the aggregate at {}", self.location);
+                    self.sub(&operand_ty, field_ty, &origin);
+                }
+                Ok(Ty::rigid(adt_id.clone(), parameters))
+            }
+            Rvalue::Cast(operand, ty) => {
+                let operand_ty = self.operand_ty(operand)?;
+                self.casts.push((operand_ty, ty.clone()));
+                Ok(ty.clone())
+            }
+            Rvalue::Repeat(..) | Rvalue::Len(_) => bail!("arrays are not supported"),
+            Rvalue::AddrOf(kind, place) => Ok(self.place_ty(place)?.raw_ptr_ty(kind.clone())),
+        }
+    }
+
+    fn binary_op_ty(&mut self, a: &Operand, op: &BinaryOp, b: &Operand) -> Fallible<Ty> {
+        let a_ty = self.operand_ty(a)?;
+        let b_ty = self.operand_ty(b)?;
+        self.goals.push(Relation::eq(&a_ty, &b_ty).upcast());
+        match op {
+            BinaryOp::Math(_) => Ok(a_ty),
+            BinaryOp::Comparison(_) => Ok(Ty::bool()),
+        }
+    }
+}
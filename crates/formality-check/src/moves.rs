@@ -0,0 +1,191 @@
+//! Move and initialization checking of MIR fn bodies.
+//!
+//! A forward dataflow analysis computes, at each point, the places that may be
+//! uninitialized: locals that have not been assigned yet (everything but the fn
+//! inputs on entry) and places that have been moved out of. Using a place that
+//! may be uninitialized, or one with a part that may be, is an error.
+//!
+//! Moving out of a place whose type is `Copy` leaves it initialized; drops are
+//! treated as no-ops, as they would be after drop elaboration.
+
+use anyhow::bail;
+use formality_prove::Env;
+use formality_rust::grammar::mir::{Place, Projection};
+use formality_types::{
+    collections::Set,
+    grammar::{lang_items::copy_trait, Fallible, Ty, Wcs},
+};
+
+use crate::{
+    dataflow::{is_prefix, AccessKind, Effects, Location, MirCfg},
+    mir::MirTypeckResults,
+    Check, Diagnostic,
+};
+
+/// A place that may be uninitialized, along with the location where it was
+/// moved out of (or `None` if it may never have been initialized).
+type Uninit = (Place, Option<Location>);
+
+impl Check<'_> {
+    pub(crate) fn check_moves(
+        &self,
+        assumptions: &Wcs,
+        num_inputs: usize,
+        body: &MirTypeckResults,
+    ) -> Fallible<()> {
+        let copies = body
+            .moves
+            .iter()
+            .filter(|(_, ty)| self.is_copy(&body.env, assumptions, ty))
+            .map(|(place, _)| place.clone())
+            .collect();
+
+        MoveCheck {
+            cfg: MirCfg::new(&body.local_decls, &body.basic_block_decls),
+            copies,
+        }
+        .check(num_inputs)
+    }
+
+    fn is_copy(&self, env: &Env, assumptions: &Wcs, ty: &Ty) -> bool {
+        let goal = copy_trait().with(ty, ());
+        formality_prove::prove(self.decls, env, assumptions, goal)
+            .iter()
+            .any(|c| c.unconditionally_true())
+    }
+}
+
+struct MoveCheck<'b> {
+    cfg: MirCfg<'b>,
+
+    /// Places whose `move`s are really copies.
+    copies: Set<Place>,
+}
+
+impl MoveCheck<'_> {
+    fn check(&self, num_inputs: usize) -> Fallible<()> {
+        // On entry, only the fn inputs (which follow the return place) are initialized.
+        let mut uninit_on_entry: Vec<Set<Uninit>> = vec![Set::new(); self.cfg.effects.len()];
+        if let Some(entry) = uninit_on_entry.first_mut() {
+            for (i, decl) in self.cfg.local_decls.iter().enumerate() {
+                if i == 0 || i > num_inputs {
+                    let place = Place {
+                        local_id: decl.name.clone(),
+                        projections: vec![],
+                    };
+                    entry.insert((place, None));
+                }
+            }
+        }
+
+        // The sets only grow, so any error found before reaching the fixed point
+        // is also an error at the fixed point.
+        loop {
+            let mut changed = false;
+            for block in 0..self.cfg.effects.len() {
+                let mut uninit = uninit_on_entry[block].clone();
+                for (index, effects) in self.cfg.effects[block].iter().enumerate() {
+                    self.apply(Location { block, index }, effects, &mut uninit)?;
+                }
+                for &successor in &self.cfg.successors[block] {
+                    let len = uninit_on_entry[successor].len();
+                    uninit_on_entry[successor].extend(uninit.iter().cloned());
+                    changed |= uninit_on_entry[successor].len() != len;
+                }
+            }
+            if !changed {
+                return Ok(());
+            }
+        }
+    }
+
+    fn apply(
+        &self,
+        location: Location,
+        effects: &Effects,
+        uninit: &mut Set<Uninit>,
+    ) -> Fallible<()> {
+        for (access, place) in &effects.accesses {
+            match access {
+                AccessKind::Read | AccessKind::BorrowShared | AccessKind::BorrowMut => {
+                    self.require_init(location, place, uninit)?
+                }
+                AccessKind::Move if self.copies.contains(place) => {
+                    self.require_init(location, place, uninit)?
+                }
+                AccessKind::Move => {
+                    self.require_init(location, place, uninit)?;
+                    if place.projections.contains(&Projection::Deref) {
+                        bail!(Diagnostic::new(
+                            "E0507",
+                            format!(
+                                "cannot move out of `{place:?}` at {}, which is behind a reference",
+                                self.cfg.describe(location)
+                            )
+                        )
+                        .with_primary(place));
+                    }
+                    uninit.insert((place.clone(), Some(location)));
+                }
+                AccessKind::Drop => {}
+                AccessKind::Write => {
+                    if let Some((prefix, _)) = uninit
+                        .iter()
+                        .find(|(u, _)| u != place && is_prefix(u, place))
+                    {
+                        bail!(Diagnostic::new(
+                            "E0381",
+                            format!(
+                                "cannot assign to `{place:?}` at {} because `{prefix:?}` is not initialized",
+                                self.cfg.describe(location)
+                            )
+                        )
+                        .with_primary(place)
+                        .with_related(prefix));
+                    }
+                    uninit.retain(|(u, _)| !is_prefix(place, u));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Requires that `place` and all of its parts are initialized.
+    fn require_init(
+        &self,
+        location: Location,
+        place: &Place,
+        uninit: &Set<Uninit>,
+    ) -> Fallible<()> {
+        for (u, moved_at) in uninit {
+            let what = if is_prefix(u, place) {
+                "moved value"
+            } else if is_prefix(place, u) {
+                "partially moved value"
+            } else {
+                continue;
+            };
+            match moved_at {
+                Some(moved_at) => bail!(Diagnostic::new(
+                    "E0382",
+                    format!(
+                        "use of {what} `{place:?}` at {}: `{u:?}` was moved at {}",
+                        self.cfg.describe(location),
+                        self.cfg.describe(*moved_at)
+                    )
+                )
+                .with_primary(place)
+                .with_related(u)),
+                None => bail!(Diagnostic::new(
+                    "E0381",
+                    format!(
+                        "use of possibly uninitialized `{place:?}` at {}",
+                        self.cfg.describe(location)
+                    )
+                )
+                .with_primary(place)),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,510 @@
+//! Privacy checking: items and struct fields without `pub` can only be
+//! referred to from within the crate that defines them.
+//!
+//! Most references can be found syntactically. Field accesses are the exception,
+//! as the struct being accessed is only known after type checking; those are
+//! collected by `type_expr` and checked with [`Check::check_fields_accessible`].
+
+use anyhow::bail;
+use formality_rust::grammar::{
+    expr::{Block, Expr, ExprData, Stmt, ValueId},
+    pattern::{AdtPattern, Pattern, PatternData},
+    AssociatedTy, AssociatedTyBoundData, AssociatedTyValue, AssociatedTyValueBoundData, ConstItem,
+    Crate, CrateItem, Enum, Field, Fn, FnBody, FnBoundData, ImplItem, MaybeFnBody, Module,
+    NegTraitImpl, NegTraitImplBoundData, Program, Static, Struct, StructBoundData, Trait,
+    TraitBoundData, TraitImpl, TraitImplBoundData, TraitItem, TypeAlias, TypeAliasBoundData,
+    WhereBound, WhereBoundData, WhereClause, WhereClauseData,
+};
+use formality_rust::grammar::{visibility::Visibility, AdtBoundData, FieldName, Variant};
+use formality_types::{
+    collections::Set,
+    grammar::{
+        AdtId, AliasName, ConstData, Fallible, FnId, Parameter, PredicateTy, RigidName, TraitId,
+        Ty, TyData, TypeAliasId,
+    },
+};
+
+use crate::{Check, Diagnostic};
+
+/// A reference to an item or a struct field, which may be defined in another crate.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum ItemRef {
+    Adt(AdtId),
+    Trait(TraitId),
+    Fn(FnId),
+    /// A `static` or `const` item.
+    Value(ValueId),
+    TypeAlias(TypeAliasId),
+    Field(AdtId, FieldName),
+}
+
+impl std::fmt::Display for ItemRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ItemRef::Adt(id) => write!(f, "type `{id:?}`"),
+            ItemRef::Trait(id) => write!(f, "trait `{id:?}`"),
+            ItemRef::Fn(id) => write!(f, "fn `{id:?}`"),
+            ItemRef::Value(id) => write!(f, "item `{id:?}`"),
+            ItemRef::TypeAlias(id) => write!(f, "type alias `{id:?}`"),
+            ItemRef::Field(id, name) => write!(f, "field `{name:?}` of `{id:?}`"),
+        }
+    }
+}
+
+impl ItemRef {
+    /// If `item` is the item referred to (or the struct that has the field referred to),
+    /// returns the visibility of the item (or field).
+    fn visibility_in(&self, item: &CrateItem) -> Option<Visibility> {
+        match (self, item) {
+            (ItemRef::Adt(id), CrateItem::Struct(s)) if s.id == *id => Some(s.visibility),
+            (ItemRef::Adt(id), CrateItem::Enum(e)) if e.id == *id => Some(e.visibility),
+            (ItemRef::Trait(id), CrateItem::Trait(t)) if t.id == *id => Some(t.visibility),
+            (ItemRef::Fn(id), CrateItem::Fn(f)) if f.id == *id => Some(f.visibility),
+            (ItemRef::Fn(id), CrateItem::ExternBlock(b)) => {
+                b.fns.iter().find(|f| f.id == *id).map(|f| f.visibility)
+            }
+            (ItemRef::Value(id), CrateItem::Static(s)) if s.id == *id => Some(s.visibility),
+            (ItemRef::Value(id), CrateItem::Const(c)) if c.id == *id => Some(c.visibility),
+            (ItemRef::TypeAlias(id), CrateItem::TypeAlias(t)) if t.id == *id => Some(t.visibility),
+            (ItemRef::Field(id, name), CrateItem::Struct(s)) if s.id == *id => {
+                let StructBoundData {
+                    where_clauses: _,
+                    fields,
+                } = s.binder.peek();
+                fields
+                    .iter()
+                    .find(|f| f.name == *name)
+                    .map(|f| f.visibility)
+            }
+            // The fields of enum variants are as visible as the enum itself.
+            (ItemRef::Field(id, _), CrateItem::Enum(e)) if e.id == *id => Some(Visibility::Public),
+            _ => None,
+        }
+    }
+}
+
+impl Check<'_> {
+    /// Checks that the current crate only refers to items (and fields) of other crates that are `pub`.
+    pub(crate) fn check_privacy(&self, c: &Crate) -> Fallible<()> {
+        let mut references = References::default();
+        for item in &c.items {
+            references.crate_item(item);
+        }
+
+        for r in &references.refs {
+            self.check_accessible(r)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that each of the struct `fields`, accessed from the current crate, is accessible.
+    pub(crate) fn check_fields_accessible(&self, fields: &[(AdtId, FieldName)]) -> Fallible<()> {
+        for (adt_id, field) in fields {
+            self.check_accessible(&ItemRef::Field(adt_id.clone(), field.clone()))?;
+        }
+        Ok(())
+    }
+
+    fn check_accessible(&self, r: &ItemRef) -> Fallible<()> {
+        let Program { crates } = self.program;
+        let Some((current_crate, other_crates)) = crates.split_last() else {
+            return Ok(());
+        };
+
+        if current_crate
+            .items
+            .iter()
+            .any(|item| r.visibility_in(item).is_some())
+        {
+            return Ok(());
+        }
+
+        for c in other_crates {
+            for item in &c.items {
+                if let Some(Visibility::Private) = r.visibility_in(item) {
+                    bail!(Diagnostic::new(
+                        "E0603",
+                        format!("{r} is private to crate `{:?}`", c.id)
+                    ))
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The items referred to by the items of a crate.
+#[derive(Default)]
+struct References {
+    refs: Set<ItemRef>,
+}
+
+impl References {
+    fn crate_item(&mut self, item: &CrateItem) {
+        match item {
+            CrateItem::Struct(Struct {
+                visibility: _,
+                id: _,
+                binder,
+            }) => {
+                let StructBoundData {
+                    where_clauses,
+                    fields,
+                } = binder.peek();
+                self.where_clauses(where_clauses);
+                self.fields(fields);
+            }
+            CrateItem::Enum(Enum {
+                visibility: _,
+                id: _,
+                binder,
+            }) => {
+                let AdtBoundData {
+                    where_clauses,
+                    variants,
+                } = binder.peek();
+                self.where_clauses(where_clauses);
+                for Variant { name: _, fields } in variants {
+                    self.fields(fields);
+                }
+            }
+            CrateItem::Trait(Trait {
+                visibility: _,
+                safety: _,
+                constness: _,
+                id: _,
+                binder,
+            }) => {
+                let TraitBoundData {
+                    where_clauses,
+                    trait_items,
+                } = binder.explicit_binder.peek();
+                self.where_clauses(where_clauses);
+                for trait_item in trait_items {
+                    match trait_item {
+                        TraitItem::Fn(f) => self.fn_(f),
+                        TraitItem::AssociatedTy(AssociatedTy { id: _, binder }) => {
+                            let AssociatedTyBoundData {
+                                ensures,
+                                where_clauses,
+                            } = binder.peek();
+                            for ensure in ensures {
+                                self.where_bound(ensure);
+                            }
+                            self.where_clauses(where_clauses);
+                        }
+                    }
+                }
+            }
+            CrateItem::TraitImpl(TraitImpl { safety: _, binder }) => {
+                let TraitImplBoundData {
+                    constness: _,
+                    trait_id,
+                    self_ty,
+                    trait_parameters,
+                    where_clauses,
+                    impl_items,
+                } = binder.peek();
+                self.refs.insert(ItemRef::Trait(trait_id.clone()));
+                self.ty(self_ty);
+                self.parameters(trait_parameters);
+                self.where_clauses(where_clauses);
+                for impl_item in impl_items {
+                    match impl_item {
+                        ImplItem::Fn(f) => self.fn_(f),
+                        ImplItem::AssociatedTyValue(AssociatedTyValue { id: _, binder }) => {
+                            let AssociatedTyValueBoundData { where_clauses, ty } = binder.peek();
+                            self.where_clauses(where_clauses);
+                            self.ty(ty);
+                        }
+                    }
+                }
+            }
+            CrateItem::NegTraitImpl(NegTraitImpl { binder }) => {
+                let NegTraitImplBoundData {
+                    trait_id,
+                    self_ty,
+                    trait_parameters,
+                    where_clauses,
+                } = binder.peek();
+                self.refs.insert(ItemRef::Trait(trait_id.clone()));
+                self.ty(self_ty);
+                self.parameters(trait_parameters);
+                self.where_clauses(where_clauses);
+            }
+            CrateItem::Fn(f) => self.fn_(f),
+            CrateItem::ExternBlock(b) => b.fns.iter().for_each(|f| self.fn_(f)),
+            CrateItem::Static(Static {
+                visibility: _,
+                kind: _,
+                id: _,
+                ty,
+                value,
+            })
+            | CrateItem::Const(ConstItem {
+                visibility: _,
+                id: _,
+                ty,
+                value,
+            }) => {
+                self.ty(ty);
+                self.expr(&Set::new(), value);
+            }
+            CrateItem::TypeAlias(TypeAlias {
+                visibility: _,
+                id: _,
+                binder,
+            }) => {
+                let TypeAliasBoundData { ty, where_clauses } = binder.peek();
+                self.ty(ty);
+                self.where_clauses(where_clauses);
+            }
+            CrateItem::Module(Module {
+                visibility: _,
+                id: _,
+                items,
+            }) => {
+                for item in items {
+                    self.crate_item(item);
+                }
+            }
+            CrateItem::Cfg(_) => {}
+        }
+    }
+
+    fn fields(&mut self, fields: &[Field]) {
+        for Field {
+            visibility: _,
+            name: _,
+            ty,
+        } in fields
+        {
+            self.ty(ty);
+        }
+    }
+
+    fn fn_(&mut self, f: &Fn) {
+        let Fn {
+            visibility: _,
+            safety: _,
+            id: _,
+            binder,
+        } = f;
+        let FnBoundData {
+            defaults,
+            self_param: _,
+            input_tys,
+            output_ty,
+            where_clauses,
+            body,
+        } = binder.peek();
+        for d in &defaults.defaults {
+            self.ty(&d.default);
+        }
+        for ty in input_tys {
+            self.ty(ty);
+        }
+        self.ty(output_ty);
+        self.where_clauses(where_clauses);
+        match body {
+            MaybeFnBody::NoFnBody | MaybeFnBody::FnBody(FnBody::TrustedFnBody) => {}
+            MaybeFnBody::FnBody(FnBody::ExprFnBody(b)) => {
+                self.block(&b.params.iter().cloned().collect(), &b.body)
+            }
+            // FIXME: check the references in MIR bodies
+            MaybeFnBody::FnBody(FnBody::MirFnBody(_)) => {}
+        }
+    }
+
+    fn where_clauses(&mut self, where_clauses: &[WhereClause]) {
+        for where_clause in where_clauses {
+            match where_clause.data() {
+                WhereClauseData::IsImplemented(self_ty, trait_id, parameters)
+                | WhereClauseData::ConstIsImplemented(self_ty, _, trait_id, parameters) => {
+                    self.ty(self_ty);
+                    self.refs.insert(ItemRef::Trait(trait_id.clone()));
+                    self.parameters(parameters);
+                }
+                WhereClauseData::AliasEq(alias_ty, ty) => {
+                    self.ty(&Ty::new(alias_ty.clone()));
+                    self.ty(ty);
+                }
+                WhereClauseData::Outlives(parameter, _) => self.parameters([parameter]),
+                WhereClauseData::ForAll(binder) => {
+                    self.where_clauses(std::slice::from_ref(binder.peek()))
+                }
+                WhereClauseData::TypeOfConst(c, ty) => {
+                    self.parameters([&Parameter::Const(c.clone())]);
+                    self.ty(ty);
+                }
+                WhereClauseData::MaybeSized(ty) => self.ty(ty),
+            }
+        }
+    }
+
+    fn where_bound(&mut self, where_bound: &WhereBound) {
+        match where_bound.data() {
+            WhereBoundData::IsImplemented(trait_id, parameters) => {
+                self.refs.insert(ItemRef::Trait(trait_id.clone()));
+                self.parameters(parameters);
+            }
+            WhereBoundData::Outlives(_) => {}
+            WhereBoundData::ForAll(binder) => self.where_bound(binder.peek()),
+        }
+    }
+
+    fn parameters<'p>(&mut self, parameters: impl IntoIterator<Item = &'p Parameter>) {
+        for parameter in parameters {
+            match parameter {
+                Parameter::Ty(ty) => self.ty(ty),
+                Parameter::Lt(_) => {}
+                Parameter::Const(c) => match c.data() {
+                    ConstData::Value(_, ty) => self.ty(ty),
+                    ConstData::Variable(_) => {}
+                },
+            }
+        }
+    }
+
+    fn ty(&mut self, ty: &Ty) {
+        match ty.data() {
+            TyData::RigidTy(rigid_ty) => {
+                match &rigid_ty.name {
+                    RigidName::AdtId(id) => {
+                        self.refs.insert(ItemRef::Adt(id.clone()));
+                    }
+                    RigidName::FnDef(id) => {
+                        self.refs.insert(ItemRef::Fn(id.clone()));
+                    }
+                    RigidName::Dyn(id) => {
+                        self.refs.insert(ItemRef::Trait(id.clone()));
+                    }
+                    _ => {}
+                }
+                self.parameters(&rigid_ty.parameters);
+            }
+            TyData::AliasTy(alias_ty) => {
+                match &alias_ty.name {
+                    AliasName::AssociatedTyId(name) => {
+                        self.refs.insert(ItemRef::Trait(name.trait_id.clone()));
+                    }
+                    AliasName::TypeAliasId(id) => {
+                        self.refs.insert(ItemRef::TypeAlias(id.clone()));
+                    }
+                }
+                self.parameters(&alias_ty.parameters);
+            }
+            TyData::PredicateTy(PredicateTy::ForAll(binder)) => self.ty(binder.peek()),
+            TyData::Variable(_) => {}
+        }
+    }
+
+    /// Collects the references in `block`, where the variables `locals` are in scope.
+    fn block(&mut self, locals: &Set<ValueId>, block: &Block) {
+        let Block { stmts, tail } = block;
+        let mut locals = locals.clone();
+        for stmt in stmts {
+            match stmt {
+                Stmt::Let(id, init) => {
+                    self.expr(&locals, init);
+                    locals.insert(id.clone());
+                }
+                Stmt::LetTy(id, ty, init) => {
+                    self.ty(ty);
+                    self.expr(&locals, init);
+                    locals.insert(id.clone());
+                }
+                Stmt::Expr(e) => self.expr(&locals, e),
+            }
+        }
+        if let Some(tail) = tail {
+            self.expr(&locals, tail);
+        }
+    }
+
+    /// Collects the references in `expr`, where the variables `locals` are in scope.
+    /// A name that is not a local variable may refer to a fn, a static or a const.
+    fn expr(&mut self, locals: &Set<ValueId>, expr: &Expr) {
+        match expr.data() {
+            ExprData::Literal(c) => self.parameters([&Parameter::Const(c.clone())]),
+            ExprData::Numeric(_) | ExprData::Unit => {}
+            ExprData::Var(id) => {
+                if !locals.contains(id) {
+                    self.refs.insert(ItemRef::Fn(FnId::new(id)));
+                    self.refs.insert(ItemRef::Value(id.clone()));
+                }
+            }
+            ExprData::Call(id, args) => {
+                if !locals.contains(&ValueId::new(id)) {
+                    self.refs.insert(ItemRef::Fn(id.clone()));
+                }
+                for arg in args {
+                    self.expr(locals, arg);
+                }
+            }
+            ExprData::Field(e, _)
+            | ExprData::Ref(e)
+            | ExprData::RefMut(e)
+            | ExprData::Deref(e)
+            | ExprData::Await(e)
+            | ExprData::Unary(_, e) => self.expr(locals, e),
+            ExprData::Binary(lhs, _, rhs) | ExprData::Index(lhs, rhs) => {
+                self.expr(locals, lhs);
+                self.expr(locals, rhs);
+            }
+            ExprData::Block(block) | ExprData::Unsafe(block) | ExprData::Async(_, block) => {
+                self.block(locals, block)
+            }
+            ExprData::If(cond, then_block, else_block) => {
+                self.expr(locals, cond);
+                self.block(locals, then_block);
+                self.block(locals, else_block);
+            }
+            ExprData::Match(scrutinee, arms) => {
+                self.expr(locals, scrutinee);
+                for arm in arms {
+                    self.pattern(&arm.pattern);
+                    let mut locals = locals.clone();
+                    locals.extend(arm.pattern.bindings());
+                    self.expr(&locals, &arm.body);
+                }
+            }
+            ExprData::Cast(e, ty) => {
+                self.expr(locals, e);
+                self.ty(ty);
+            }
+            ExprData::Closure(_, params, body) => {
+                let mut locals = locals.clone();
+                locals.extend(params.iter().cloned());
+                self.expr(&locals, body);
+            }
+        }
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern.data() {
+            PatternData::Wildcard | PatternData::Binding(_) => {}
+            PatternData::Literal(c) => self.parameters([&Parameter::Const(c.clone())]),
+            PatternData::Tuple(pats) => {
+                for pat in pats {
+                    self.pattern(pat);
+                }
+            }
+            PatternData::Adt(AdtPattern {
+                adt_id,
+                variant_id: _,
+                fields,
+            }) => {
+                self.refs.insert(ItemRef::Adt(adt_id.clone()));
+                for field in fields {
+                    self.refs
+                        .insert(ItemRef::Field(adt_id.clone(), field.name.clone()));
+                    self.pattern(&field.pattern);
+                }
+            }
+            PatternData::Ref(p) | PatternData::RefMut(p) => self.pattern(p),
+        }
+    }
+}
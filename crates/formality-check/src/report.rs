@@ -0,0 +1,201 @@
+//! A machine-readable account of checking a program, for tools such as dashboards and
+//! the differential fuzzer: the verdict on each item, the errors, and optionally the
+//! proof trees that show why the items that pass do.
+//!
+//! Proof trees are recorded by checking the items that pass once more: while recording,
+//! [`Check::prove_goal`] proves each goal that holds again to derive its proof tree.
+
+use std::cell::RefCell;
+
+use formality_prove::{Decls, Env, ProofTree};
+use formality_rust::grammar::{CrateItem, Feature, Program};
+use formality_types::{
+    collections::Set,
+    grammar::{CrateId, Wcs},
+    pretty::to_string,
+};
+use serde_json::json;
+
+use crate::{check_all_crates_errors, lower, Check, CheckError, Options};
+
+/// The outcome of checking a program, see [`check_all_crates_report`].
+#[derive(Debug)]
+pub struct Report {
+    pub items: Vec<ItemReport>,
+
+    /// The features that the program is configured with for [`Report::items`].
+    pub features: Set<Feature>,
+
+    /// All errors, in the order that [`check_all_crates_errors`] returns them.
+    pub errors: Vec<CheckError>,
+}
+
+#[derive(Debug)]
+pub struct ItemReport {
+    pub crate_id: CrateId,
+
+    /// The index of the item among the items of its crate after resolution, as in [`CheckError::item`].
+    pub index: usize,
+
+    /// The item as the checks see it.
+    pub item: CrateItem,
+
+    pub verdict: Verdict,
+
+    /// The indices in [`Report::errors`] of the errors about this item.
+    pub errors: Vec<usize>,
+
+    /// The proof trees of the goals proven to check the item, if requested and it passes.
+    pub proof_trees: Vec<ProofTree>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail,
+
+    /// The item is in a crate after the first one with errors, which is not checked.
+    Unchecked,
+}
+
+/// Checks all crates of `program` like [`check_all_crates_errors`], reporting the verdict
+/// on each item. If `proof_trees` is set, the items that pass come with proof trees,
+/// unless only coherence is checked (see [`Options::coherence_only`]). The items are
+/// those of the program configured with the features of the errors, if any, or else
+/// with the first set of [features](`Options::features`).
+pub fn check_all_crates_report(program: &Program, options: &Options, proof_trees: bool) -> Report {
+    let errors = check_all_crates_errors(program, options);
+    let failed_crate = errors.first().map(|e| &e.crate_id);
+    let features = match errors.first() {
+        Some(e) => e.features.clone(),
+        None => options
+            .features
+            .sets(program)
+            .into_iter()
+            .next()
+            .unwrap_or_default(),
+    };
+    let program = &program.configure(&features);
+
+    let mut items = vec![];
+    let mut checked = true;
+    // The crates are reported in the order they are checked in.
+    for i in program.dependency_order().unwrap_or_default() {
+        let crate_id = &program.crates[i].id;
+        // A crate that cannot be lowered has an error about no item in particular.
+        let Ok(lowered) = program.crate_program(i).and_then(|p| lower(&p)) else {
+            checked = false;
+            continue;
+        };
+        let decls = options.prove_decls(&lowered);
+        let check = Check {
+            program: &lowered,
+            decls: &decls,
+            features: &features,
+            options,
+            memo: None,
+        };
+        for (index, item) in lowered.crates.last().unwrap().items.iter().enumerate() {
+            let item_errors: Vec<usize> = errors
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| e.crate_id == *crate_id && e.item == Some(index))
+                .map(|(i, _)| i)
+                .collect();
+            let verdict = if !checked {
+                Verdict::Unchecked
+            } else if item_errors.is_empty() {
+                Verdict::Pass
+            } else {
+                Verdict::Fail
+            };
+            let proof_trees = if proof_trees && verdict == Verdict::Pass && !options.coherence_only
+            {
+                record_proof_trees(|| check.check_crate_item(item)).1
+            } else {
+                vec![]
+            };
+            items.push(ItemReport {
+                crate_id: crate_id.clone(),
+                index,
+                item: item.clone(),
+                verdict,
+                errors: item_errors,
+                proof_trees,
+            });
+        }
+        if failed_crate == Some(crate_id) {
+            checked = false;
+        }
+    }
+
+    Report {
+        items,
+        features,
+        errors,
+    }
+}
+
+impl Report {
+    /// The report as a JSON object, with the errors as in [`CheckError::to_json`].
+    pub fn to_json(&self) -> serde_json::Value {
+        let items: Vec<serde_json::Value> = self
+            .items
+            .iter()
+            .map(|item| {
+                json!({
+                    "crate": format!("{:?}", item.crate_id),
+                    "index": item.index,
+                    "item": to_string(&item.item),
+                    "verdict": match item.verdict {
+                        Verdict::Pass => "pass",
+                        Verdict::Fail => "fail",
+                        Verdict::Unchecked => "unchecked",
+                    },
+                    "errors": item.errors,
+                    "proof_trees": item.proof_trees,
+                })
+            })
+            .collect();
+        let errors: Vec<serde_json::Value> = self
+            .errors
+            .iter()
+            .map(|error| {
+                let mut json = error.to_json();
+                json["item"] = json!(error.item);
+                json
+            })
+            .collect();
+        json!({
+            "items": items,
+            "errors": errors,
+        })
+    }
+}
+
+thread_local! {
+    /// The proof trees of the goals proven by [`Check::prove_goal`], if they are being recorded.
+    static PROOF_TREES: RefCell<Option<Vec<ProofTree>>> = const { RefCell::new(None) }
+}
+
+/// Runs `op`, recording the proof trees of the goals that it proves.
+fn record_proof_trees<R>(op: impl FnOnce() -> R) -> (R, Vec<ProofTree>) {
+    let outer = PROOF_TREES.with(|t| t.replace(Some(vec![])));
+    let result = op();
+    let proof_trees = PROOF_TREES.with(|t| t.replace(outer)).unwrap();
+    (result, proof_trees)
+}
+
+/// If proof trees are being recorded, proves `goal`, which holds unconditionally,
+/// again to record the proof tree of a solution that shows it.
+pub(crate) fn record_proof_tree(decls: &Decls, env: &Env, assumptions: &Wcs, goal: &Wcs) {
+    if PROOF_TREES.with(|t| t.borrow().is_none()) {
+        return;
+    }
+    let proofs = formality_prove::prove_with_proof_trees(decls, env, assumptions, goal);
+    let tree = proofs
+        .into_iter()
+        .find(|(c, _)| c.unconditionally_true())
+        .map(|(_, tree)| tree);
+    PROOF_TREES.with(|t| t.borrow_mut().as_mut().unwrap().extend(tree));
+}
@@ -0,0 +1,180 @@
+//! Semver checking: which changes between two versions of a crate may break the crates
+//! that depend on it, as far as the model can tell. Reported are
+//!
+//! * impls of the old version that no longer hold,
+//! * new impls of existing public traits that overlap with an impl a downstream crate
+//!   may have written for its own type, e.g., a new blanket impl,
+//! * public fns whose where-clauses are not implied by those of the old version.
+
+use std::fmt;
+
+use formality_prove::Env;
+use formality_rust::grammar::{
+    dependencies::Dependencies, edition::Edition, visibility::Visibility, Crate, CrateItem, Fn,
+    Program, Struct, StructBoundData, Trait, TraitImpl, TraitImplBoundData,
+};
+use formality_types::{
+    cast::Downcasted,
+    collections::Set,
+    grammar::{AdtId, Binder, CrateId, Fallible, FnId, RigidName, Ty},
+};
+
+use crate::{lower, Check, Options};
+
+/// A change between two versions of a crate that may break the crates depending on it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BreakingChange {
+    /// An impl of the old version that the new version does not provide.
+    RemovedImpl(TraitImpl),
+
+    /// An impl of the new version that overlaps with an impl a downstream crate may have.
+    OverlappingImpl(TraitImpl),
+
+    /// A public fn whose where-clauses the old version's do not imply.
+    StrengthenedWhereClauses(FnId),
+}
+
+impl fmt::Display for BreakingChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakingChange::RemovedImpl(i) => {
+                write!(f, "impl removed: {i}")
+            }
+            BreakingChange::OverlappingImpl(i) => {
+                write!(f, "impl may overlap with downstream impls: {i}")
+            }
+            BreakingChange::StrengthenedWhereClauses(id) => {
+                write!(f, "where-clauses of fn `{id:?}` strengthened")
+            }
+        }
+    }
+}
+
+/// Compares the versions `old` and `new` of a crate that depends on the crates of
+/// `dependencies`, returning the changes that may break downstream crates.
+pub fn check_semver(
+    dependencies: &Program,
+    old: &Crate,
+    new: &Crate,
+    options: &Options,
+) -> Fallible<Vec<BreakingChange>> {
+    let old_program = lower(&with_crate(dependencies, old))?;
+    let new_program = lower(&with_crate(dependencies, new))?;
+    let old_items = &old_program.crates.last().unwrap().items;
+    let new_items = &new_program.crates.last().unwrap().items;
+
+    let new_decls = options.prove_decls(&new_program);
+    let check = Check {
+        program: &new_program,
+        decls: &new_decls,
+        features: &Set::new(),
+        options,
+        memo: None,
+    };
+
+    let mut changes = vec![];
+
+    let old_impls: Vec<TraitImpl> = old_items.iter().downcasted().collect();
+    for old_impl in &old_impls {
+        if !check.impl_holds(old_impl) {
+            changes.push(BreakingChange::RemovedImpl(old_impl.clone()));
+        }
+    }
+
+    let old_traits: Vec<Trait> = old_items.iter().downcasted().collect();
+    let new_impls: Vec<TraitImpl> = new_items.iter().downcasted().collect();
+    for new_impl in new_impls.iter().filter(|i| !old_impls.contains(i)) {
+        let existed = old_traits
+            .iter()
+            .any(|t| t.id == *new_impl.trait_id() && t.visibility == Visibility::Public);
+        if existed && overlaps_downstream(&new_program, new_impl, options) {
+            changes.push(BreakingChange::OverlappingImpl(new_impl.clone()));
+        }
+    }
+
+    let old_fns: Vec<Fn> = old_items.iter().downcasted().collect();
+    let new_fns: Vec<Fn> = new_items.iter().downcasted().collect();
+    for new_fn in new_fns
+        .iter()
+        .filter(|f| f.visibility == Visibility::Public)
+    {
+        let Some(old_fn) = old_fns.iter().find(|f| f.id == new_fn.id) else {
+            continue;
+        };
+        if old_fn.binder.kinds() != new_fn.binder.kinds() {
+            continue;
+        }
+        if !check.where_clauses_implied(old_fn, new_fn) {
+            changes.push(BreakingChange::StrengthenedWhereClauses(new_fn.id.clone()));
+        }
+    }
+
+    Ok(changes)
+}
+
+fn with_crate(dependencies: &Program, c: &Crate) -> Program {
+    let mut program = dependencies.clone();
+    program.crates.push(c.clone());
+    program
+}
+
+/// Whether `new_impl` overlaps with an impl of its trait for a type local to a downstream crate.
+fn overlaps_downstream(new_program: &Program, new_impl: &TraitImpl, options: &Options) -> bool {
+    let local_id = AdtId::new("SemverLocal");
+    let local_struct = Struct {
+        visibility: Visibility::Public,
+        id: local_id.clone(),
+        binder: Binder::dummy(StructBoundData {
+            where_clauses: vec![],
+            fields: vec![],
+        }),
+    };
+    let local_impl = TraitImpl {
+        safety: new_impl.safety,
+        binder: new_impl.binder.map(|data| TraitImplBoundData {
+            self_ty: Ty::rigid(RigidName::AdtId(local_id), ()),
+            where_clauses: vec![],
+            impl_items: vec![],
+            ..data
+        }),
+    };
+
+    let mut program = new_program.clone();
+    program.crates.push(Crate {
+        id: CrateId::new("semver_downstream"),
+        edition: Edition::default(),
+        dependencies: Dependencies::Preceding,
+        items: vec![
+            CrateItem::Struct(local_struct),
+            CrateItem::TraitImpl(local_impl.clone()),
+        ],
+    });
+    let decls = options.prove_decls(&program);
+    let check = Check {
+        program: &program,
+        decls: &decls,
+        features: &Set::new(),
+        options,
+        memo: None,
+    };
+    check.overlap_check(&local_impl, new_impl).is_err()
+}
+
+impl Check<'_> {
+    /// Whether the trait ref of `i` holds whenever its where-clauses do.
+    fn impl_holds(&self, i: &TraitImpl) -> bool {
+        let mut env = Env::default();
+        let i = env.instantiate_universally(&i.binder);
+        self.prove_goal(&env, &i.where_clauses, i.trait_ref().is_implemented())
+            .is_ok()
+    }
+
+    /// Whether the where-clauses of `old_fn` imply those of `new_fn`, which has the same generic parameters.
+    fn where_clauses_implied(&self, old_fn: &Fn, new_fn: &Fn) -> bool {
+        let (env, vars) = Env::default().universal_substitution(&new_fn.binder);
+        let old_data = old_fn.binder.instantiate_with(&vars).unwrap();
+        let new_data = new_fn.binder.instantiate_with(&vars).unwrap();
+        self.prove_goal(&env, &old_data.where_clauses, &new_data.where_clauses)
+            .is_ok()
+    }
+}
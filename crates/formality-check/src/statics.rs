@@ -0,0 +1,72 @@
+use formality_prove::Env;
+use formality_rust::grammar::{
+    expr::{Block, Expr},
+    ConstItem, Static, StaticKind,
+};
+use formality_types::grammar::{
+    lang_items::{sized_trait, sync_trait},
+    Fallible, Ty, Wcs,
+};
+
+use crate::{typeck::Locals, Check};
+
+impl Check<'_> {
+    pub(crate) fn check_static(&self, s: &Static) -> Fallible<()> {
+        let Static {
+            visibility: _,
+            kind,
+            id,
+            ty,
+            value,
+        } = s;
+
+        self.check_value_item_ty(ty)?;
+
+        // Other threads may access a shared static at the same time.
+        if let StaticKind::Global = kind {
+            self.prove_goal(
+                &Env::default(),
+                Wcs::t(),
+                sync_trait().with(ty, ()).is_implemented(),
+            )?;
+        }
+
+        self.check_value_item_initializer(ty, value, &format!("static `{id:?}`"))
+    }
+
+    pub(crate) fn check_const_item(&self, c: &ConstItem) -> Fallible<()> {
+        let ConstItem {
+            visibility: _,
+            id,
+            ty,
+            value,
+        } = c;
+
+        self.check_value_item_ty(ty)?;
+
+        self.check_value_item_initializer(ty, value, &format!("const `{id:?}`"))
+    }
+
+    /// The type of a static or const must be well formed and sized, as its value is stored
+    /// in memory (or copied into each place that uses it).
+    fn check_value_item_ty(&self, ty: &Ty) -> Fallible<()> {
+        let env = Env::default();
+        self.prove_goal(&env, Wcs::t(), ty.well_formed())?;
+        self.prove_goal(&env, Wcs::t(), sized_trait().with(ty, ()).is_implemented())
+    }
+
+    fn check_value_item_initializer(&self, ty: &Ty, value: &Expr, what: &str) -> Fallible<()> {
+        let body = Block {
+            stmts: vec![],
+            tail: Some(value.clone()),
+        };
+        self.check_expr_body(
+            &Env::default(),
+            &Wcs::t(),
+            Locals::default(),
+            &body,
+            ty,
+            what,
+        )
+    }
+}
@@ -3,25 +3,36 @@ use formality_prove::Env;
 use formality_rust::grammar::{
     AssociatedTy, AssociatedTyBoundData, Fn, Trait, TraitBoundData, TraitItem, WhereClause,
 };
-use formality_types::grammar::Fallible;
+use formality_types::{
+    cast::Upcast,
+    grammar::{Fallible, Ty},
+};
 
 impl super::Check<'_> {
     #[context("check_trait({:?})", t.id)]
     pub(super) fn check_trait(&self, t: &Trait) -> Fallible<()> {
-        let Trait { id: _, binder } = t;
-        let mut env = Env::default();
-
+        let Trait {
+            visibility: _,
+            safety: _,
+            constness: _,
+            id: _,
+            binder,
+        } = t;
+        let (env, vars) = Env::default().universal_substitution(&binder.explicit_binder);
         let TraitBoundData {
             where_clauses,
             trait_items,
-        } = env.instantiate_universally(&binder.explicit_binder);
+        } = binder.explicit_binder.instantiate_with(&vars)?;
+
+        // The `Self` type is bound first, see `TraitBinder`.
+        let self_ty: Ty = vars[0].upcast();
 
         self.check_trait_items_have_unique_names(&trait_items)?;
 
         self.prove_where_clauses_well_formed(&env, &where_clauses, &where_clauses)?;
 
         for trait_item in &trait_items {
-            self.check_trait_item(&env, &where_clauses, trait_item)?;
+            self.check_trait_item(&env, &where_clauses, &self_ty, trait_item)?;
         }
 
         Ok(())
@@ -36,16 +47,25 @@ impl super::Check<'_> {
         &self,
         env: &Env,
         where_clauses: &[WhereClause],
+        self_ty: &Ty,
         trait_item: &TraitItem,
     ) -> Fallible<()> {
         match trait_item {
-            TraitItem::Fn(v) => self.check_fn_in_trait(env, where_clauses, v),
+            TraitItem::Fn(v) => self.check_fn_in_trait(env, where_clauses, self_ty, v),
             TraitItem::AssociatedTy(v) => self.check_associated_ty(env, where_clauses, v),
         }
     }
 
-    fn check_fn_in_trait(&self, env: &Env, where_clauses: &[WhereClause], f: &Fn) -> Fallible<()> {
-        self.check_fn(env, where_clauses, f)
+    fn check_fn_in_trait(
+        &self,
+        env: &Env,
+        where_clauses: &[WhereClause],
+        self_ty: &Ty,
+        f: &Fn,
+    ) -> Fallible<()> {
+        // Methods do not have to be dyn compatible: if one is not, `dyn Trait` types are
+        // not well formed (see `formality_rust::dyn_compat`), but the trait is fine.
+        self.check_fn(env, where_clauses, Some(self_ty), f)
     }
 
     fn check_associated_ty(
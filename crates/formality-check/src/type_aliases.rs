@@ -0,0 +1,24 @@
+use fn_error_context::context;
+use formality_prove::Env;
+use formality_rust::grammar::{TypeAlias, TypeAliasBoundData};
+use formality_types::grammar::Fallible;
+
+impl super::Check<'_> {
+    #[context("check_type_alias({:?})", t.id)]
+    pub(super) fn check_type_alias(&self, t: &TypeAlias) -> Fallible<()> {
+        let TypeAlias {
+            visibility: _,
+            id: _,
+            binder,
+        } = t;
+        let mut env = Env::default();
+
+        let TypeAliasBoundData { ty, where_clauses } = env.instantiate_universally(binder);
+
+        self.prove_where_clauses_well_formed(&env, &where_clauses, &where_clauses)?;
+
+        self.prove_goal(&env, &where_clauses, ty.well_formed())?;
+
+        Ok(())
+    }
+}
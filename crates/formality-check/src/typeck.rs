@@ -0,0 +1,984 @@
+//! Type checking of expression fn bodies.
+//!
+//! Type checking is split in two phases. The `type_expr` judgment walks the
+//! expression and computes its type, creating existential (inference) variables
+//! where types are not known syntactically (e.g., the generic arguments of a
+//! call). Rather than solving anything itself, it accumulates the obligations
+//! that must hold for the expression to be well typed. Those are then handed
+//! to `formality_prove` all at once, which also finds values for the inference
+//! variables.
+
+use std::fmt::Debug;
+
+use anyhow::bail;
+use formality_prove::{Constraints, Env};
+use formality_rust::{
+    grammar::{
+        expr::{Arm, Block, CaptureBy, Expr, ExprData, NumericLit, Stmt, ValueId},
+        pattern::{Pattern, PatternData},
+        ty_defaults::TyDefaults,
+        AdtBoundData, ExprFnBody, FieldName, FnBoundData, Program,
+    },
+    prove::ToWcs,
+};
+use formality_types::{
+    cast::{Downcast, Upcast, UpcastFrom, Upcasted},
+    cast_impl,
+    collections::{Map, Set},
+    grammar::{
+        lang_items::{
+            deref_target, deref_trait, future_output, future_trait, index_trait, op_output,
+        },
+        AdtId, AliasTy, ExistentialVar, Fallible, FnId, ParameterKind, Predicate, Relation,
+        RigidName, RigidTy, Ty, Wc, WcData, Wcs, PR,
+    },
+    judgment_fn,
+};
+
+use crate::{
+    closures::{captures, CaptureKind, Captures},
+    fallback::{self, Fallback},
+    Check,
+};
+
+/// Types of the local variables in scope.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Locals {
+    tys: Map<ValueId, Ty>,
+
+    /// Inside an async block, the locals in scope that it declares. The coroutine
+    /// holds these across an `.await`. `None` outside of async blocks.
+    coroutine_locals: Option<Set<ValueId>>,
+}
+
+cast_impl!(Locals);
+
+impl Locals {
+    pub fn get(&self, id: &ValueId) -> Option<Ty> {
+        self.tys.get(id).cloned()
+    }
+
+    /// Returns a new set of locals where `id` has type `ty`, shadowing any previous `id`.
+    pub fn with(&self, id: &ValueId, ty: &Ty) -> Locals {
+        let mut locals = self.clone();
+        locals.tys.insert(id.clone(), ty.clone());
+        if let Some(coroutine_locals) = &mut locals.coroutine_locals {
+            coroutine_locals.insert(id.clone());
+        }
+        locals
+    }
+
+    /// Returns a new set of locals without `ids`.
+    pub fn without(&self, ids: &[ValueId]) -> Locals {
+        let mut locals = self.clone();
+        for id in ids {
+            locals.tys.remove(id);
+            if let Some(coroutine_locals) = &mut locals.coroutine_locals {
+                coroutine_locals.remove(id);
+            }
+        }
+        locals
+    }
+
+    /// The locals in the body of an async block, where `.await` is allowed.
+    fn in_coroutine(&self) -> Locals {
+        Locals {
+            tys: self.tys.clone(),
+            coroutine_locals: Some(Set::new()),
+        }
+    }
+
+    /// The locals in the body of a closure, where `.await` is not allowed
+    /// even if the closure is inside an async block.
+    fn outside_coroutine(&self) -> Locals {
+        Locals {
+            tys: self.tys.clone(),
+            coroutine_locals: None,
+        }
+    }
+
+    /// The types of the locals that an `.await` here holds across, or `None`
+    /// outside of async blocks.
+    fn held_across_await(&self) -> Option<Vec<Ty>> {
+        let coroutine_locals = self.coroutine_locals.as_ref()?;
+        Some(
+            coroutine_locals
+                .iter()
+                .map(|id| self.tys[id].clone())
+                .collect(),
+        )
+    }
+}
+
+impl Debug for Locals {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(&self.tys).finish()
+    }
+}
+
+/// Checks that can only be done once the types involved are known, i.e.,
+/// after the inference variables have been solved, along with the witness
+/// types that an enclosing async block collects from its `.await`s.
+#[derive(Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Deferred {
+    /// Each `match`, with the type of its scrutinee and the patterns of its arms,
+    /// to be checked for exhaustiveness.
+    matches: Vec<(Ty, Vec<Pattern>)>,
+
+    /// Each `as` cast, with the source and target type.
+    casts: Vec<(Ty, Ty)>,
+
+    /// Types that fall back to a default if inference does not determine them.
+    fallbacks: Vec<(Ty, Fallback)>,
+
+    /// The types held across each `.await`: the awaited future and the
+    /// locals of the enclosing async block that are in scope.
+    witness: Vec<Ty>,
+
+    /// Each struct field accessed, to be checked for privacy.
+    fields: Vec<(AdtId, FieldName)>,
+
+    /// The types of the type parameters of each fn named, with the fn, which
+    /// inference (including fallback) must determine.
+    generic_args: Vec<(FnId, Ty)>,
+}
+
+cast_impl!(Deferred);
+
+impl Deferred {
+    fn for_match(scrutinee_ty: &Ty, arms: &[Arm]) -> Deferred {
+        let patterns = arms.iter().map(|arm| arm.pattern.clone()).collect();
+        Deferred {
+            matches: vec![(scrutinee_ty.clone(), patterns)],
+            ..Deferred::default()
+        }
+    }
+
+    fn for_field(adt_id: &AdtId, field: &FieldName) -> Deferred {
+        Deferred {
+            fields: vec![(adt_id.clone(), field.clone())],
+            ..Deferred::default()
+        }
+    }
+
+    fn for_cast(source_ty: &Ty, target_ty: &Ty) -> Deferred {
+        Deferred {
+            casts: vec![(source_ty.clone(), target_ty.clone())],
+            ..Deferred::default()
+        }
+    }
+
+    fn for_literal(ty: &Ty, n: &NumericLit) -> Deferred {
+        let fallback = match n {
+            NumericLit::Int(_) => Fallback::Int,
+            NumericLit::Float(_) => Fallback::Float,
+        };
+        Deferred {
+            fallbacks: vec![(ty.clone(), fallback)],
+            ..Deferred::default()
+        }
+    }
+
+    fn for_await(future_ty: &Ty, held_tys: &[Ty]) -> Deferred {
+        Deferred {
+            witness: std::iter::once(future_ty)
+                .chain(held_tys)
+                .cloned()
+                .collect(),
+            ..Deferred::default()
+        }
+    }
+
+    /// Separates the witness types of an async block from the rest.
+    fn split_witness(&self) -> (Vec<Ty>, Deferred) {
+        let deferred = Deferred {
+            witness: vec![],
+            ..self.clone()
+        };
+        (self.witness.clone(), deferred)
+    }
+
+    /// The type parameters of the fn `id` are instantiated with `args`. Those
+    /// with `defaults` fall back to them.
+    fn for_generic_args(id: &FnId, args: &[ExistentialVar], defaults: &TyDefaults) -> Deferred {
+        let tys: Vec<Ty> = args
+            .iter()
+            .filter(|v| v.kind == ParameterKind::Ty)
+            .map(Ty::new)
+            .collect();
+        let fallbacks = tys
+            .iter()
+            .filter_map(|ty| {
+                let default = defaults.default_of(ty)?;
+                Some((ty.clone(), Fallback::Default(default.clone())))
+            })
+            .collect();
+        Deferred {
+            fallbacks,
+            generic_args: tys.into_iter().map(|ty| (id.clone(), ty)).collect(),
+            ..Deferred::default()
+        }
+    }
+
+    /// For the arguments of type `!` in a call, the corresponding input types diverge.
+    fn for_args(arg_tys: &[Ty], input_tys: &[Ty]) -> Deferred {
+        let fallbacks = arg_tys
+            .iter()
+            .zip(input_tys)
+            .filter(|(arg_ty, _)| **arg_ty == Ty::never())
+            .map(|(_, input_ty)| (input_ty.clone(), Fallback::Diverging))
+            .collect();
+        Deferred {
+            fallbacks,
+            ..Deferred::default()
+        }
+    }
+}
+
+impl<A, B> UpcastFrom<(A, B)> for Deferred
+where
+    A: Upcast<Deferred>,
+    B: Upcast<Deferred>,
+{
+    fn upcast_from((a, b): (A, B)) -> Self {
+        let Deferred {
+            mut matches,
+            mut casts,
+            mut fallbacks,
+            mut witness,
+            mut fields,
+            mut generic_args,
+        } = a.upcast();
+        let b: Deferred = b.upcast();
+        matches.extend(b.matches);
+        casts.extend(b.casts);
+        fallbacks.extend(b.fallbacks);
+        witness.extend(b.witness);
+        fields.extend(b.fields);
+        generic_args.extend(b.generic_args);
+        Deferred {
+            matches,
+            casts,
+            fallbacks,
+            witness,
+            fields,
+            generic_args,
+        }
+    }
+}
+
+impl<A, B, C> UpcastFrom<(A, B, C)> for Deferred
+where
+    A: Upcast<Deferred>,
+    B: Upcast<Deferred>,
+    C: Upcast<Deferred>,
+{
+    fn upcast_from((a, b, c): (A, B, C)) -> Self {
+        (a, (b, c)).upcast()
+    }
+}
+
+impl Check<'_> {
+    /// Type checks `body` given that the fn has the (already instantiated) signature
+    /// `input_tys -> output_ty`, in an environment where `assumptions` hold.
+    pub(crate) fn check_expr_fn_body(
+        &self,
+        env: &Env,
+        assumptions: impl ToWcs,
+        input_tys: &[Ty],
+        output_ty: &Ty,
+        body: &ExprFnBody,
+    ) -> Fallible<()> {
+        let assumptions: Wcs = assumptions.to_wcs();
+        let ExprFnBody { params, body } = body;
+
+        if params.len() != input_tys.len() {
+            bail!(
+                "fn body has {} parameters but the signature has {} inputs",
+                params.len(),
+                input_tys.len()
+            );
+        }
+
+        let locals = params
+            .iter()
+            .zip(input_tys)
+            .fold(Locals::default(), |locals, (id, ty)| locals.with(id, ty));
+
+        self.check_expr_body(env, &assumptions, locals, body, output_ty, "fn body")
+    }
+
+    /// Type checks `body`, which has the (already instantiated) type `output_ty` and may
+    /// refer to `locals`, in an environment where `assumptions` hold. `what` describes the
+    /// body in error messages.
+    pub(crate) fn check_expr_body(
+        &self,
+        env: &Env,
+        assumptions: &Wcs,
+        locals: Locals,
+        body: &Block,
+        output_ty: &Ty,
+        what: &str,
+    ) -> Fallible<()> {
+        let typings = type_block(self.program, env, locals, body);
+        if typings.is_empty() {
+            bail!("failed to type {what} {body:?}");
+        }
+
+        for (env, ty, obligations, deferred) in &typings {
+            let goal: Wcs = (obligations, Relation::coerce(ty, output_ty)).to_wcs();
+            let fallbacks = &deferred.fallbacks;
+            let cs = self.prove_with_fallback(env, assumptions, &goal, fallbacks);
+            let solutions = || cs.iter().filter(|c| c.known_true);
+            if let Some(c) = solutions()
+                .find(|c| fallback::admits_all(c, fallbacks))
+                .or_else(|| solutions().next())
+            {
+                self.check_fallback_vars(c, fallbacks)?;
+                let subst = c.substitution();
+                self.check_generic_args_inferred(&subst.apply(&deferred.generic_args))?;
+                for (scrutinee_ty, patterns) in &subst.apply(&deferred.matches) {
+                    self.check_match_exhaustive(scrutinee_ty, patterns)?;
+                }
+                self.check_casts(c.env(), assumptions, &subst.apply(&deferred.casts))?;
+                self.check_fields_accessible(&deferred.fields)?;
+                return Ok(());
+            }
+        }
+
+        let typings: Set<_> = typings
+            .iter()
+            .map(|(env, ty, obligations, _)| (env, ty, obligations))
+            .collect();
+        bail!("{what} {body:?} is not well typed, obligations were {typings:?}")
+    }
+}
+
+impl Check<'_> {
+    /// Proves the obligations `goal` of a fn body, solving its relations (e.g., coercions)
+    /// before its other predicates. Like rustc, which unifies types while type checking
+    /// and solves trait obligations afterwards, this means the self types of the trait
+    /// predicates are known when proving them. This matters for closure types, which
+    /// (unlike the self types of impls) the solver cannot guess.
+    pub(crate) fn prove_obligations(
+        &self,
+        env: &Env,
+        assumptions: &Wcs,
+        goal: &Wcs,
+    ) -> Set<Constraints> {
+        let (relations, predicates): (Vec<Wc>, Vec<Wc>) = goal
+            .into_iter()
+            .partition(|wc| matches!(wc.data(), WcData::PR(PR::Relation(_))));
+        let relations: Wcs = relations.into_iter().collect();
+        let predicates: Wcs = predicates.into_iter().collect();
+
+        formality_prove::prove(self.decls, env, assumptions, relations)
+            .into_iter()
+            .flat_map(|c1| {
+                let (assumptions, predicates) = c1
+                    .substitution()
+                    .apply(&(assumptions.clone(), predicates.clone()));
+                formality_prove::prove(self.decls, c1.env(), assumptions, predicates)
+                    .into_iter()
+                    .map(move |c2| c1.seq(c2))
+            })
+            .collect()
+    }
+}
+
+/// Creates a fresh type inference variable.
+fn fresh_ty_var(env: &Env) -> (Env, Ty) {
+    let mut env = env.clone();
+    let v = env.fresh_existential(ParameterKind::Ty);
+    (env, Ty::new(v))
+}
+
+/// Creates `n` fresh type inference variables.
+fn fresh_ty_vars(env: &Env, n: usize) -> (Env, Vec<Ty>) {
+    let mut env = env.clone();
+    let tys = (0..n)
+        .map(|_| Ty::new(env.fresh_existential(ParameterKind::Ty)))
+        .collect();
+    (env, tys)
+}
+
+judgment_fn! {
+    /// Computes the type of `expr`, along with the obligations that must be proven
+    /// for that type to be valid. The returned env extends `env` with any inference variables.
+    pub fn type_expr(
+        program: Program,
+        env: Env,
+        locals: Locals,
+        expr: Expr,
+    ) => (Env, Ty, Wcs, Deferred) {
+        debug(expr, locals, env, program)
+
+        (
+            (if let Some((_, ty)) = c.as_value())
+            --- ("literal")
+            (type_expr(_program, env, _locals, ExprData::Literal(c)) => (env, ty, Wcs::t(), ()))
+        )
+
+        (
+            (let (env, ty) = fresh_ty_var(&env))
+            (let this_literal = Deferred::for_literal(&ty, &n))
+            --- ("numeric literal")
+            (type_expr(_program, env, _locals, ExprData::Numeric(n)) => (&env, &ty, Wcs::t(), &this_literal))
+        )
+
+        (
+            (if c.as_variable().is_some())
+            (let (env, ty) = fresh_ty_var(&env))
+            --- ("const generic")
+            (type_expr(_program, env, _locals, ExprData::Literal(c)) => (&env, &ty, Predicate::ConstHasType(c.clone(), ty.clone()), ()))
+        )
+
+        (
+            (if let Some(ty) = locals.get(&id))
+            --- ("variable")
+            (type_expr(_program, env, locals, ExprData::Var(id)) => (env, ty, Wcs::t(), ()))
+        )
+
+        (
+            (if locals.get(&id).is_none())
+            (if let Ok(f) = program.fn_named(&FnId::new(&id)))
+            (let (env, subst) = env.existential_substitution(&f.binder))
+            (let FnBoundData { defaults, self_param: _, input_tys: _, output_ty: _, where_clauses, body: _ } = f.binder.instantiate_with(&subst).unwrap())
+            (let generic_args = Deferred::for_generic_args(&f.id, &subst, &defaults))
+            --- ("fn item")
+            (type_expr(program, env, locals, ExprData::Var(id)) => (&env, Ty::rigid(RigidName::FnDef(f.id.clone()), &subst), where_clauses.to_wcs(), &generic_args))
+        )
+
+        (
+            (if locals.get(&id).is_none())
+            (if let Ok(ty) = program.value_item_ty(&id))
+            --- ("static or const")
+            (type_expr(program, env, locals, ExprData::Var(id)) => (env, ty, Wcs::t(), ()))
+        )
+
+        (
+            --- ("unit")
+            (type_expr(_program, env, _locals, ExprData::Unit) => (env, Ty::unit(), Wcs::t(), ()))
+        )
+
+        (
+            (if locals.get(&ValueId::new(&id)).is_none())
+            (if let Ok(f) = program.fn_named(&id))
+            (let (env, subst) = env.existential_substitution(&f.binder))
+            (let FnBoundData { defaults, self_param: _, input_tys, output_ty, where_clauses, body: _ } = f.binder.instantiate_with(&subst).unwrap())
+            (if input_tys.len() == args.len())
+            (type_exprs(&program, env, &locals, &args) => (env, arg_tys, obligations, deferred))
+            (let coercions = coercions_to_each(&arg_tys, &input_tys))
+            (let diverging = Deferred::for_args(&arg_tys, &input_tys))
+            (let generic_args = Deferred::for_generic_args(&id, &subst, &defaults))
+            --- ("call")
+            (type_expr(program, env, locals, ExprData::Call(id, args)) => (&env, &output_ty, (&obligations, &coercions, where_clauses.to_wcs()), (&deferred, &diverging, &generic_args)))
+        )
+
+        (
+            (if let Some(callee_ty) = locals.get(&ValueId::new(&id)))
+            (if let Some((_, sig, _)) = callee_ty.as_closure_ty())
+            (if let Some((input_tys, output_ty)) = sig.as_fn_ptr_ty())
+            (if input_tys.len() == args.len())
+            (type_exprs(&program, env, &locals, &args) => (env, arg_tys, obligations, deferred))
+            (let coercions = coercions_to_each(&arg_tys, &input_tys))
+            (let diverging = Deferred::for_args(&arg_tys, &input_tys))
+            --- ("call closure")
+            (type_expr(program, env, locals, ExprData::Call(id, args)) => (&env, &output_ty, (&obligations, &coercions), (&deferred, &diverging)))
+        )
+
+        (
+            (type_expr(&program, env, &locals, &base) => (env, base_ty, obligations, deferred))
+            (if let Some(RigidTy { name: RigidName::AdtId(adt_id), parameters }) = strip_refs(&program, &base_ty).downcast())
+            (if let Ok(adt) = program.adt_named(&adt_id))
+            (if let Ok(Some(field_ty)) = adt.struct_field_ty(&parameters, &field))
+            --- ("field")
+            (type_expr(program, env, locals, ExprData::Field(base, field)) => (&env, &field_ty, &obligations, (&deferred, Deferred::for_field(&adt_id, &field))))
+        )
+
+        (
+            (type_expr(&program, env, &locals, &e) => (env, ty, obligations, deferred))
+            (let mut env = env)
+            (let lt = env.fresh_existential(ParameterKind::Lt))
+            --- ("ref")
+            (type_expr(program, env, locals, ExprData::Ref(e)) => (&env, ty.ref_ty(lt), &obligations, &deferred))
+        )
+
+        (
+            (type_expr(&program, env, &locals, &e) => (env, ty, obligations, deferred))
+            (let mut env = env)
+            (let lt = env.fresh_existential(ParameterKind::Lt))
+            --- ("ref mut")
+            (type_expr(program, env, locals, ExprData::RefMut(e)) => (&env, ty.ref_mut_ty(lt), &obligations, &deferred))
+        )
+
+        (
+            (type_expr(&program, env, &locals, &e) => (env, ty, obligations, deferred))
+            (if let Some((_, _, referent)) = ty.as_ref_ty())
+            --- ("deref builtin")
+            (type_expr(program, env, locals, ExprData::Deref(e)) => (&env, &referent, &obligations, &deferred))
+        )
+
+        (
+            (type_expr(&program, env, &locals, &e) => (env, ty, obligations, deferred))
+            (if ty.as_ref_ty().is_none())
+            (let target = AliasTy::associated_ty(deref_trait(), deref_target(), vec![ty.clone()]))
+            --- ("deref overloaded")
+            (type_expr(program, env, locals, ExprData::Deref(e)) => (&env, Ty::new(target.clone()), (&obligations, deref_trait().with(&ty, ())), &deferred))
+        )
+
+        (
+            (type_expr(&program, env, &locals, &lhs) => (env, lhs_ty, lhs_obligations, lhs_deferred))
+            (type_expr(&program, env, &locals, &rhs) => (env, rhs_ty, rhs_obligations, rhs_deferred))
+            (let output = AliasTy::associated_ty(op.trait_id(), op_output(), vec![lhs_ty.clone(), rhs_ty.clone()]))
+            --- ("binary operator")
+            (type_expr(program, env, locals, ExprData::Binary(lhs, op, rhs)) => (
+                &env,
+                Ty::new(output.clone()),
+                (&lhs_obligations, &rhs_obligations, op.trait_id().with(&lhs_ty, vec![&rhs_ty])),
+                (&lhs_deferred, &rhs_deferred),
+            ))
+        )
+
+        (
+            (type_expr(&program, env, &locals, &e) => (env, ty, obligations, deferred))
+            (let output = AliasTy::associated_ty(op.trait_id(), op_output(), vec![ty.clone()]))
+            --- ("unary operator")
+            (type_expr(program, env, locals, ExprData::Unary(op, e)) => (&env, Ty::new(output.clone()), (&obligations, op.trait_id().with(&ty, ())), &deferred))
+        )
+
+        (
+            (type_expr(&program, env, &locals, &base) => (env, base_ty, base_obligations, base_deferred))
+            (type_expr(&program, env, &locals, &index) => (env, index_ty, index_obligations, index_deferred))
+            (let output = AliasTy::associated_ty(index_trait(), op_output(), vec![base_ty.clone(), index_ty.clone()]))
+            --- ("index")
+            (type_expr(program, env, locals, ExprData::Index(base, index)) => (
+                &env,
+                Ty::new(output.clone()),
+                (&base_obligations, &index_obligations, index_trait().with(&base_ty, vec![&index_ty])),
+                (&base_deferred, &index_deferred),
+            ))
+        )
+
+        (
+            (type_block(program, env, locals, block) => r)
+            --- ("block")
+            (type_expr(program, env, locals, ExprData::Block(block)) => r)
+        )
+
+        (
+            (type_block(program, env, locals, block) => r)
+            --- ("unsafe block")
+            (type_expr(program, env, locals, ExprData::Unsafe(block)) => r)
+        )
+
+        (
+            (type_expr(&program, env, &locals, &scrutinee) => (env, scrutinee_ty, scrutinee_obligations, scrutinee_deferred))
+            (type_arms(&program, env, &locals, &scrutinee_ty, &arms) => (env, arm_tys, arm_obligations, arm_deferred))
+            (lub(&program, env, arm_tys) => (env, ty, lub_obligations))
+            (let this_match = Deferred::for_match(&scrutinee_ty, &arms))
+            --- ("match")
+            (type_expr(program, env, locals, ExprData::Match(scrutinee, arms)) => (
+                &env,
+                &ty,
+                (&scrutinee_obligations, &arm_obligations, &lub_obligations),
+                (&scrutinee_deferred, &arm_deferred, &this_match),
+            ))
+        )
+
+        (
+            (type_expr(&program, env, &locals, &e) => (env, source_ty, obligations, deferred))
+            (let this_cast = Deferred::for_cast(&source_ty, &target_ty))
+            --- ("cast")
+            (type_expr(program, env, locals, ExprData::Cast(e, target_ty)) => (
+                &env,
+                &target_ty,
+                (&obligations, target_ty.well_formed()),
+                (&deferred, &this_cast),
+            ))
+        )
+
+        (
+            (let (env, param_tys) = fresh_ty_vars(&env, params.len()))
+            (let body_locals = params.iter().zip(&param_tys).fold(locals.outside_coroutine(), |l, (id, ty)| l.with(id, ty)))
+            (type_expr(&program, env, body_locals, &body) => (env, body_ty, obligations, deferred))
+            (captures(locals.without(&params), CaptureKind::Value, &body) => (captures, capture_obligations))
+            (let (env, upvar_tys) = upvar_tys(&env, &locals, &captures, &capture_by))
+            (let closure_ty = Ty::closure(captures.closure_kind(), &Ty::fn_ptr(&param_tys, &body_ty), &upvar_tys))
+            --- ("closure")
+            (type_expr(program, env, locals, ExprData::Closure(capture_by, params, body)) => (
+                &env,
+                &closure_ty,
+                (&obligations, &capture_obligations),
+                &deferred,
+            ))
+        )
+
+        (
+            (type_block(&program, env, locals.in_coroutine(), &block) => (env, output_ty, obligations, deferred))
+            (captures(&locals, CaptureKind::Value, &block) => (captures, capture_obligations))
+            (let (env, upvar_tys) = upvar_tys(&env, &locals, &captures, &capture_by))
+            (let (witness, deferred) = deferred.split_witness())
+            (let coroutine_ty = Ty::coroutine(&output_ty, &upvar_tys, &witness))
+            --- ("async block")
+            (type_expr(program, env, locals, ExprData::Async(capture_by, block)) => (
+                &env,
+                &coroutine_ty,
+                (&obligations, &capture_obligations),
+                &deferred,
+            ))
+        )
+
+        (
+            (type_expr(&program, env, &locals, &e) => (env, future_ty, obligations, deferred))
+            (if let Some(held_tys) = locals.held_across_await())
+            (let output = AliasTy::associated_ty(future_trait(), future_output(), vec![future_ty.clone()]))
+            (let this_await = Deferred::for_await(&future_ty, &held_tys))
+            --- ("await")
+            (type_expr(program, env, locals, ExprData::Await(e)) => (
+                &env,
+                Ty::new(output.clone()),
+                (&obligations, future_trait().with(&future_ty, ())),
+                (&deferred, &this_await),
+            ))
+        )
+
+        (
+            (type_expr(&program, env, &locals, &cond) => (env, cond_ty, cond_obligations, cond_deferred))
+            (type_block(&program, env, &locals, &then_block) => (env, then_ty, then_obligations, then_deferred))
+            (type_block(&program, env, &locals, &else_block) => (env, else_ty, else_obligations, else_deferred))
+            (lub(&program, env, vec![then_ty.clone(), else_ty.clone()]) => (env, ty, lub_obligations))
+            --- ("if")
+            (type_expr(program, env, locals, ExprData::If(cond, then_block, else_block)) => (
+                &env,
+                &ty,
+                (
+                    (&cond_obligations, &then_obligations, &else_obligations),
+                    Relation::eq(&cond_ty, Ty::bool()),
+                    &lub_obligations,
+                ),
+                (&cond_deferred, &then_deferred, &else_deferred),
+            ))
+        )
+    }
+}
+
+judgment_fn! {
+    /// Types each of `exprs` in order, threading the env through.
+    fn type_exprs(
+        program: Program,
+        env: Env,
+        locals: Locals,
+        exprs: Vec<Expr>,
+    ) => (Env, Vec<Ty>, Wcs, Deferred) {
+        debug(exprs, locals, env, program)
+
+        (
+            (if exprs.is_empty())
+            --- ("nil")
+            (type_exprs(_program, env, _locals, exprs) => (env, Vec::<Ty>::new(), Wcs::t(), ()))
+        )
+
+        (
+            (if let Some((head, tail)) = exprs.split_first())
+            (type_expr(&program, env, &locals, head) => (env, head_ty, head_obligations, head_deferred))
+            (type_exprs(&program, env, &locals, tail) => (env, tail_tys, tail_obligations, tail_deferred))
+            --- ("cons")
+            (type_exprs(program, env, locals, exprs) => (
+                &env,
+                std::iter::once(head_ty.clone()).chain(tail_tys.iter().cloned()).collect::<Vec<Ty>>(),
+                (&head_obligations, &tail_obligations),
+                (&head_deferred, &tail_deferred),
+            ))
+        )
+    }
+}
+
+judgment_fn! {
+    /// Types each arm of a `match` whose scrutinee has type `scrutinee_ty`,
+    /// returning the types of the arm bodies.
+    fn type_arms(
+        program: Program,
+        env: Env,
+        locals: Locals,
+        scrutinee_ty: Ty,
+        arms: Vec<Arm>,
+    ) => (Env, Vec<Ty>, Wcs, Deferred) {
+        debug(arms, scrutinee_ty, locals, env, program)
+
+        (
+            (if arms.is_empty())
+            --- ("nil")
+            (type_arms(_program, env, _locals, _scrutinee_ty, arms) => (env, Vec::<Ty>::new(), Wcs::t(), ()))
+        )
+
+        (
+            (if let Some((Arm { pattern, body }, rest)) = arms.split_first())
+            (type_pattern(&program, env, &locals, pattern, &scrutinee_ty) => (env, arm_locals, pattern_obligations))
+            (type_expr(&program, env, arm_locals, body) => (env, body_ty, body_obligations, body_deferred))
+            (type_arms(&program, env, &locals, &scrutinee_ty, rest) => (env, rest_tys, rest_obligations, rest_deferred))
+            --- ("cons")
+            (type_arms(program, env, locals, scrutinee_ty, arms) => (
+                &env,
+                std::iter::once(body_ty.clone()).chain(rest_tys.iter().cloned()).collect::<Vec<Ty>>(),
+                (&pattern_obligations, &body_obligations, &rest_obligations),
+                (&body_deferred, &rest_deferred),
+            ))
+        )
+    }
+}
+
+judgment_fn! {
+    /// Computes the type of an `if` or `match` whose branches have types `tys`,
+    /// along with the obligations for each branch to coerce to it.
+    ///
+    /// Like rustc, we only consider the types of the branches themselves as candidates
+    /// (e.g., `&u32` for branches of type `&mut u32` and `&u32`), except that distinct
+    /// fn items are coerced to a fn pointer. Without branches, the result has type `!`.
+    fn lub(
+        program: Program,
+        env: Env,
+        tys: Vec<Ty>,
+    ) => (Env, Ty, Wcs) {
+        debug(tys, env, program)
+
+        (
+            (if tys.is_empty())
+            --- ("no branches")
+            (lub(_program, env, tys) => (env, Ty::never(), Wcs::t()))
+        )
+
+        (
+            (tys.iter() => candidate)
+            (let coercions = coercions_to(&tys, candidate))
+            --- ("branch")
+            (lub(_program, env, tys) => (&env, candidate, coercions))
+        )
+
+        (
+            (tys.iter() => ty)
+            (if let Some(RigidTy { name: RigidName::FnDef(fn_id), parameters }) = ty.downcast())
+            (if let Ok(f) = program.fn_named(&fn_id))
+            (let FnBoundData { defaults: _, self_param: _, input_tys, output_ty, where_clauses: _, body: _ } = f.binder.instantiate_with(&parameters).unwrap())
+            (let candidate = Ty::abi_fn_ptr(program.fn_abi(&fn_id), &input_tys, &output_ty))
+            (let coercions = coercions_to(&tys, &candidate))
+            --- ("fn pointer")
+            (lub(program, env, tys) => (&env, &candidate, coercions))
+        )
+    }
+}
+
+/// The obligations for each of `tys` to coerce to the corresponding type in `targets`.
+fn coercions_to_each(tys: &[Ty], targets: &[Ty]) -> Wcs {
+    tys.iter()
+        .zip(targets)
+        .map(|(ty, target)| Relation::coerce(ty, target))
+        .upcasted()
+        .collect()
+}
+
+/// The types of the variables captured by a closure with `captures`, as stored in the closure.
+fn upvar_tys(
+    env: &Env,
+    locals: &Locals,
+    captures: &Captures,
+    capture_by: &CaptureBy,
+) -> (Env, Vec<Ty>) {
+    let mut env = env.clone();
+    let tys = captures
+        .capture_kinds(capture_by)
+        .map(|(id, kind)| {
+            let ty = locals.get(id).unwrap();
+            match kind {
+                CaptureKind::Ref => ty.ref_ty(env.fresh_existential(ParameterKind::Lt)),
+                CaptureKind::MutRef => ty.ref_mut_ty(env.fresh_existential(ParameterKind::Lt)),
+                CaptureKind::Value => ty,
+            }
+        })
+        .collect();
+    (env, tys)
+}
+
+/// The obligations for each of `tys` to coerce to `target`.
+fn coercions_to(tys: &[Ty], target: &Ty) -> Wcs {
+    tys.iter()
+        .map(|ty| Relation::coerce(ty, target))
+        .upcasted()
+        .collect()
+}
+
+judgment_fn! {
+    /// Checks that `pattern` can match values of type `ty`, returning `locals`
+    /// extended with the bindings it introduces.
+    pub fn type_pattern(
+        program: Program,
+        env: Env,
+        locals: Locals,
+        pattern: Pattern,
+        ty: Ty,
+    ) => (Env, Locals, Wcs) {
+        debug(pattern, ty, locals, env, program)
+
+        (
+            --- ("wildcard")
+            (type_pattern(_program, env, locals, PatternData::Wildcard, _ty) => (env, locals, Wcs::t()))
+        )
+
+        (
+            --- ("binding")
+            (type_pattern(_program, env, locals, PatternData::Binding(id), ty) => (&env, locals.with(&id, &ty), Wcs::t()))
+        )
+
+        (
+            (if let Some((_, c_ty)) = c.as_value())
+            --- ("literal")
+            (type_pattern(_program, env, locals, PatternData::Literal(c), ty) => (env, locals, Relation::eq(&ty, &c_ty)))
+        )
+
+        (
+            (if c.as_variable().is_some())
+            --- ("const generic")
+            (type_pattern(_program, env, locals, PatternData::Literal(c), ty) => (env, locals, Predicate::ConstHasType(c.clone(), ty.clone())))
+        )
+
+        (
+            (let (env, tys) = fresh_ty_vars(&env, pats.len()))
+            (type_patterns(&program, env, &locals, &pats, &tys) => (env, locals, obligations))
+            --- ("tuple")
+            (type_pattern(program, env, locals, PatternData::Tuple(pats), ty) => (
+                &env,
+                &locals,
+                (&obligations, Relation::eq(&ty, Ty::rigid(RigidName::Tuple(tys.len()), &tys))),
+            ))
+        )
+
+        (
+            (if let Ok(adt) = program.adt_named(&adt_pat.adt_id))
+            (let (env, subst) = env.existential_substitution(&adt.binder))
+            (let AdtBoundData { where_clauses, variants } = adt.binder.instantiate_with(&subst).unwrap())
+            (if let Some(variant) = variants.iter().find(|v| v.name == adt_pat.variant_id))
+            (if let Some(pats) = adt_pat.fields_in_order(variant))
+            (let tys = variant.fields.iter().map(|f| f.ty.clone()).collect::<Vec<Ty>>())
+            (type_patterns(&program, env, &locals, &pats, &tys) => (env, locals, obligations))
+            --- ("adt")
+            (type_pattern(program, env, locals, PatternData::Adt(adt_pat), ty) => (
+                &env,
+                &locals,
+                (&obligations, Relation::eq(&ty, Ty::rigid(&adt_pat.adt_id, &subst)), where_clauses.to_wcs()),
+            ))
+        )
+
+        (
+            (let (env, referent) = fresh_ty_var(&env))
+            (let mut env = env)
+            (let lt = env.fresh_existential(ParameterKind::Lt))
+            (type_pattern(&program, &env, &locals, &p, &referent) => (env, locals, obligations))
+            --- ("ref")
+            (type_pattern(program, env, locals, PatternData::Ref(p), ty) => (&env, &locals, (&obligations, Relation::eq(&ty, referent.ref_ty(lt)))))
+        )
+
+        (
+            (let (env, referent) = fresh_ty_var(&env))
+            (let mut env = env)
+            (let lt = env.fresh_existential(ParameterKind::Lt))
+            (type_pattern(&program, &env, &locals, &p, &referent) => (env, locals, obligations))
+            --- ("ref mut")
+            (type_pattern(program, env, locals, PatternData::RefMut(p), ty) => (&env, &locals, (&obligations, Relation::eq(&ty, referent.ref_mut_ty(lt)))))
+        )
+    }
+}
+
+judgment_fn! {
+    /// Types each of `pats` against the corresponding type in `tys`.
+    fn type_patterns(
+        program: Program,
+        env: Env,
+        locals: Locals,
+        pats: Vec<Pattern>,
+        tys: Vec<Ty>,
+    ) => (Env, Locals, Wcs) {
+        debug(pats, tys, locals, env, program)
+
+        (
+            (if pats.is_empty() && tys.is_empty())
+            --- ("nil")
+            (type_patterns(_program, env, locals, pats, tys) => (env, locals, Wcs::t()))
+        )
+
+        (
+            (if let (Some((pat, pats_tail)), Some((ty, tys_tail))) = (pats.split_first(), tys.split_first()))
+            (type_pattern(&program, env, &locals, pat, ty) => (env, locals, head_obligations))
+            (type_patterns(&program, env, locals, pats_tail, tys_tail) => (env, locals, tail_obligations))
+            --- ("cons")
+            (type_patterns(program, env, locals, pats, tys) => (&env, &locals, (&head_obligations, &tail_obligations)))
+        )
+    }
+}
+
+judgment_fn! {
+    /// Types the statements of `block` in order, followed by its tail expression.
+    pub fn type_block(
+        program: Program,
+        env: Env,
+        locals: Locals,
+        block: Block,
+    ) => (Env, Ty, Wcs, Deferred) {
+        debug(block, locals, env, program)
+
+        (
+            (if block.stmts.is_empty())
+            (if let None = &block.tail)
+            --- ("empty")
+            (type_block(_program, env, _locals, block) => (env, Ty::unit(), Wcs::t(), ()))
+        )
+
+        (
+            (if block.stmts.is_empty())
+            (if let Some(tail) = &block.tail)
+            (type_expr(&program, env, &locals, tail) => r)
+            --- ("tail")
+            (type_block(program, env, locals, block) => r)
+        )
+
+        (
+            (if let Some((Stmt::Let(id, init), rest)) = block.split_first())
+            (type_expr(&program, env, &locals, &init) => (env, init_ty, init_obligations, init_deferred))
+            (type_block(&program, env, locals.with(&id, &init_ty), &rest) => (env, ty, obligations, deferred))
+            --- ("let")
+            (type_block(program, env, locals, block) => (&env, &ty, (&init_obligations, &obligations, program.sized_goal(&init_ty)), (&init_deferred, &deferred)))
+        )
+
+        (
+            (if let Some((Stmt::LetTy(id, let_ty, init), rest)) = block.split_first())
+            (type_expr(&program, env, &locals, &init) => (env, init_ty, init_obligations, init_deferred))
+            (type_block(&program, env, locals.with(&id, &let_ty), &rest) => (env, ty, obligations, deferred))
+            --- ("let with type")
+            (type_block(program, env, locals, block) => (
+                &env,
+                &ty,
+                (&init_obligations, &obligations, (Relation::coerce(&init_ty, &let_ty), let_ty.well_formed(), program.sized_goal(&let_ty))),
+                (&init_deferred, &deferred),
+            ))
+        )
+
+        (
+            (if let Some((Stmt::Expr(e), rest)) = block.split_first())
+            (type_expr(&program, env, &locals, &e) => (env, _e_ty, e_obligations, e_deferred))
+            (type_block(&program, env, &locals, &rest) => (env, ty, obligations, deferred))
+            --- ("expr statement")
+            (type_block(program, env, locals, block) => (&env, &ty, (&e_obligations, &obligations), (&e_deferred, &deferred)))
+        )
+    }
+}
+
+/// Peels off any references, for use with auto-deref'ing field accesses.
+/// Type aliases are expanded along the way, so that their fields can be accessed.
+fn strip_refs(program: &Program, ty: &Ty) -> Ty {
+    if let Some((_, _, referent)) = ty.as_ref_ty() {
+        strip_refs(program, &referent)
+    } else if let Some(expanded) = program.expand_type_alias(ty) {
+        strip_refs(program, &expanded)
+    } else {
+        ty.clone()
+    }
+}
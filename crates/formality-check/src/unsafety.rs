@@ -0,0 +1,212 @@
+//! Unsafety checking: calling an `unsafe fn` (or a fn declared in an extern block)
+//! is only allowed in unsafe code, i.e. in an `unsafe { ... }` block or in the body
+//! of an `unsafe fn`, and an `unsafe trait` can only be implemented by an `unsafe impl`.
+//!
+//! FIXME: fn pointer types do not record whether the fn is unsafe, so an `unsafe fn`
+//! coerced to a fn pointer can be called from safe code.
+
+use anyhow::bail;
+use formality_rust::grammar::{
+    expr::{Block, Expr, ExprData, Stmt, ValueId},
+    safety::Safety,
+    ConstItem, Crate, CrateItem, Fn, FnBody, ImplItem, MaybeFnBody, Module, Static, Trait,
+    TraitBoundData, TraitImpl, TraitItem,
+};
+use formality_types::{collections::Set, grammar::Fallible};
+
+use crate::{Check, Diagnostic};
+
+impl Check<'_> {
+    /// Checks that the current crate only performs unsafe operations in unsafe code.
+    pub(crate) fn check_unsafety(&self, c: &Crate) -> Fallible<()> {
+        for item in &c.items {
+            self.check_item_unsafety(item)?;
+        }
+        Ok(())
+    }
+
+    fn check_item_unsafety(&self, item: &CrateItem) -> Fallible<()> {
+        match item {
+            CrateItem::Fn(f) => self.check_fn_unsafety(f),
+            CrateItem::Trait(Trait {
+                visibility: _,
+                safety: _,
+                constness: _,
+                id: _,
+                binder,
+            }) => {
+                let TraitBoundData {
+                    where_clauses: _,
+                    trait_items,
+                } = binder.explicit_binder.peek();
+                for trait_item in trait_items {
+                    match trait_item {
+                        TraitItem::Fn(f) => self.check_fn_unsafety(f)?,
+                        TraitItem::AssociatedTy(_) => {}
+                    }
+                }
+                Ok(())
+            }
+            CrateItem::TraitImpl(trait_impl) => self.check_trait_impl_unsafety(trait_impl),
+            CrateItem::Static(Static {
+                visibility: _,
+                kind: _,
+                id: _,
+                ty: _,
+                value,
+            })
+            | CrateItem::Const(ConstItem {
+                visibility: _,
+                id: _,
+                ty: _,
+                value,
+            }) => self.check_expr_unsafety(Safety::Safe, &Set::new(), value),
+            CrateItem::Module(Module {
+                visibility: _,
+                id: _,
+                items,
+            }) => items
+                .iter()
+                .try_for_each(|item| self.check_item_unsafety(item)),
+            CrateItem::Struct(_)
+            | CrateItem::Enum(_)
+            | CrateItem::NegTraitImpl(_)
+            | CrateItem::TypeAlias(_)
+            | CrateItem::ExternBlock(_)
+            | CrateItem::Cfg(_) => Ok(()),
+        }
+    }
+
+    /// Checks that `trait_impl` is an `unsafe impl` exactly if it implements an `unsafe trait`.
+    fn check_trait_impl_unsafety(&self, trait_impl: &TraitImpl) -> Fallible<()> {
+        let TraitImpl { safety, binder } = trait_impl;
+        let trait_id = trait_impl.trait_id();
+        let trait_decl = self.program.trait_named(trait_id)?;
+        match (trait_decl.safety, safety) {
+            (Safety::Unsafe, Safety::Safe) => {
+                bail!(Diagnostic::new(
+                    "E0200",
+                    format!(
+                        "implementing the unsafe trait `{trait_id:?}` requires an `unsafe impl`"
+                    )
+                )
+                .with_primary(trait_impl))
+            }
+            (Safety::Safe, Safety::Unsafe) => {
+                bail!(Diagnostic::new(
+                    "E0199",
+                    format!("the trait `{trait_id:?}` is not unsafe, so it cannot have an `unsafe impl`")
+                )
+                .with_primary(trait_impl))
+            }
+            (Safety::Safe, Safety::Safe) | (Safety::Unsafe, Safety::Unsafe) => {}
+        }
+
+        for impl_item in &binder.peek().impl_items {
+            match impl_item {
+                ImplItem::Fn(f) => self.check_fn_unsafety(f)?,
+                ImplItem::AssociatedTyValue(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// The body of an `unsafe fn` is unsafe code; the body of any other fn is not.
+    fn check_fn_unsafety(&self, f: &Fn) -> Fallible<()> {
+        match &f.binder.peek().body {
+            MaybeFnBody::NoFnBody | MaybeFnBody::FnBody(FnBody::TrustedFnBody) => Ok(()),
+            MaybeFnBody::FnBody(FnBody::ExprFnBody(b)) => {
+                let locals = b.params.iter().cloned().collect();
+                self.check_block_unsafety(f.safety, &locals, &b.body)
+            }
+            // MIR bodies are assumed to have been checked before they were lowered to MIR.
+            MaybeFnBody::FnBody(FnBody::MirFnBody(_)) => Ok(()),
+        }
+    }
+
+    /// Checks `block`, which is unsafe code if `context` is [`Safety::Unsafe`]
+    /// and where the variables `locals` are in scope.
+    fn check_block_unsafety(
+        &self,
+        context: Safety,
+        locals: &Set<ValueId>,
+        block: &Block,
+    ) -> Fallible<()> {
+        let Block { stmts, tail } = block;
+        let mut locals = locals.clone();
+        for stmt in stmts {
+            match stmt {
+                Stmt::Let(id, init) | Stmt::LetTy(id, _, init) => {
+                    self.check_expr_unsafety(context, &locals, init)?;
+                    locals.insert(id.clone());
+                }
+                Stmt::Expr(e) => self.check_expr_unsafety(context, &locals, e)?,
+            }
+        }
+        if let Some(tail) = tail {
+            self.check_expr_unsafety(context, &locals, tail)?;
+        }
+        Ok(())
+    }
+
+    /// Like [`Self::check_block_unsafety`], but for an expression. Closures and async
+    /// blocks are unsafe code if they are written in unsafe code.
+    fn check_expr_unsafety(
+        &self,
+        context: Safety,
+        locals: &Set<ValueId>,
+        expr: &Expr,
+    ) -> Fallible<()> {
+        match expr.data() {
+            ExprData::Literal(_) | ExprData::Numeric(_) | ExprData::Var(_) | ExprData::Unit => {}
+            ExprData::Call(id, args) => {
+                if context == Safety::Safe
+                    && !locals.contains(&ValueId::new(id))
+                    && self.program.fn_safety(id) == Safety::Unsafe
+                {
+                    bail!(Diagnostic::new(
+                        "E0133",
+                        format!("call to unsafe fn `{id:?}` requires an unsafe block")
+                    ))
+                }
+                for arg in args {
+                    self.check_expr_unsafety(context, locals, arg)?;
+                }
+            }
+            ExprData::Field(e, _)
+            | ExprData::Ref(e)
+            | ExprData::RefMut(e)
+            | ExprData::Deref(e)
+            | ExprData::Cast(e, _)
+            | ExprData::Await(e)
+            | ExprData::Unary(_, e) => self.check_expr_unsafety(context, locals, e)?,
+            ExprData::Binary(lhs, _, rhs) | ExprData::Index(lhs, rhs) => {
+                self.check_expr_unsafety(context, locals, lhs)?;
+                self.check_expr_unsafety(context, locals, rhs)?;
+            }
+            ExprData::Block(block) | ExprData::Async(_, block) => {
+                self.check_block_unsafety(context, locals, block)?
+            }
+            ExprData::Unsafe(block) => self.check_block_unsafety(Safety::Unsafe, locals, block)?,
+            ExprData::If(cond, then_block, else_block) => {
+                self.check_expr_unsafety(context, locals, cond)?;
+                self.check_block_unsafety(context, locals, then_block)?;
+                self.check_block_unsafety(context, locals, else_block)?;
+            }
+            ExprData::Match(scrutinee, arms) => {
+                self.check_expr_unsafety(context, locals, scrutinee)?;
+                for arm in arms {
+                    let mut locals = locals.clone();
+                    locals.extend(arm.pattern.bindings());
+                    self.check_expr_unsafety(context, &locals, &arm.body)?;
+                }
+            }
+            ExprData::Closure(_, params, body) => {
+                let mut locals = locals.clone();
+                locals.extend(params.iter().cloned());
+                self.check_expr_unsafety(context, &locals, body)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -22,7 +22,7 @@ impl super::Check<'_> {
         Ok(())
     }
 
-    #[context("prove_where_clause_well_formed({where_clause:?})")]
+    #[context("prove_where_clause_well_formed({where_clause})")]
     // FIXME(oli-obk): figure out why is this a function and not a `judgment_fn`.
     fn prove_where_clause_well_formed(
         &self,
@@ -31,7 +31,8 @@ impl super::Check<'_> {
         where_clause: &WhereClause,
     ) -> Fallible<()> {
         match where_clause.data() {
-            WhereClauseData::IsImplemented(self_ty, trait_id, parameters) => self
+            WhereClauseData::IsImplemented(self_ty, trait_id, parameters)
+            | WhereClauseData::ConstIsImplemented(self_ty, _, trait_id, parameters) => self
                 .prove_trait_ref_well_formed(
                     in_env,
                     assumptions,
@@ -61,6 +62,9 @@ impl super::Check<'_> {
                 self.prove_parameter_well_formed(in_env, &assumptions, ct.clone())?;
                 self.prove_parameter_well_formed(in_env, assumptions, ty.clone())
             }
+            WhereClauseData::MaybeSized(ty) => {
+                self.prove_parameter_well_formed(in_env, assumptions, ty.clone())
+            }
         }
     }
 
@@ -0,0 +1,54 @@
+//! Minimizes a program that the checks go wrong on (see `formality_check::minimize`),
+//! printing the smallest program that still goes wrong the same way, e.g.
+//!
+//! ```text
+//! cargo run -p formality-cli --bin minimize -- --error "impls may overlap" program.formality
+//! ```
+
+use std::path::PathBuf;
+
+use clap::{ArgGroup, Parser};
+use formality_check::minimize::{minimize, Failure};
+use formality_types::pretty::to_string;
+
+#[derive(Parser, Debug)]
+#[command(name = "minimize", version, about, long_about = None)]
+#[command(group(ArgGroup::new("failure").required(true).args(["error", "pass", "panic"])))]
+struct Args {
+    /// The checks report an error whose message contains this.
+    #[arg(long, value_name = "PATTERN")]
+    error: Option<String>,
+
+    /// The checks pass, although they should not.
+    #[arg(long)]
+    pass: bool,
+
+    /// Checking panics, with a message that contains this.
+    #[arg(long, value_name = "PATTERN")]
+    panic: Option<String>,
+
+    /// The files of the program, as for `formality-cli`.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let failure = match (args.error, args.pass, args.panic) {
+        (Some(pattern), _, _) => Failure::Error(pattern),
+        (_, true, _) => Failure::Pass,
+        (_, _, pattern) => Failure::Panic(pattern.unwrap_or_default()),
+    };
+    let program = formality_cli::read_program(&args.files)?;
+    if !failure.reproduces(&program) {
+        anyhow::bail!("the program does not fail as given: {failure:?}");
+    }
+
+    // The candidates that panic would otherwise each print their panic.
+    std::panic::set_hook(Box::new(|_| {}));
+    let minimized = minimize(&program, |p| failure.reproduces(p));
+    let _ = std::panic::take_hook();
+
+    println!("{}", to_string(&minimized));
+    Ok(())
+}
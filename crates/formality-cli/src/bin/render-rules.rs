@@ -0,0 +1,48 @@
+//! Prints the rules of the judgments as inference rules, e.g. for an appendix of the
+//! specification:
+//!
+//! ```text
+//! cargo run -p formality-cli --bin render-rules -- --format latex > rules.tex
+//! ```
+
+use clap::{Parser, ValueEnum};
+use formality_types::judgment::rules::{judgments, to_latex, to_markdown};
+
+#[derive(Parser, Debug)]
+#[command(name = "render-rules", version, about, long_about = None)]
+struct Args {
+    #[arg(long, value_enum, default_value_t = Format::Markdown)]
+    format: Format,
+
+    /// Only the judgments of modules whose path starts with this, e.g. `formality_prove`.
+    #[arg(long)]
+    module: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// LaTeX, to be `\input` into a document that uses `amsmath`.
+    Latex,
+
+    /// Markdown, with the rules as MathJax formulas.
+    Markdown,
+}
+
+fn main() {
+    // The judgments are registered by the crates that declare them, which are only
+    // linked in if the program can reach them.
+    std::hint::black_box(formality_check::check_all_crates_errors as fn(_, _) -> _);
+
+    let args = Args::parse();
+    let judgments: Vec<_> = judgments()
+        .into_iter()
+        .filter(|j| match &args.module {
+            Some(module) => j.module.starts_with(&module[..]),
+            None => true,
+        })
+        .collect();
+    match args.format {
+        Format::Latex => print!("{}", to_latex(&judgments)),
+        Format::Markdown => print!("{}", to_markdown(&judgments)),
+    }
+}
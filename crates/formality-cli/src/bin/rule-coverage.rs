@@ -0,0 +1,32 @@
+//! Reports the rules of the judgments that never fired in the runs that recorded
+//! their coverage to the given file (see `formality_types::judgment::coverage`), e.g.
+//!
+//! ```text
+//! FORMALITY_RULE_COVERAGE=$PWD/target/rule-coverage cargo test --all
+//! cargo run -p formality-cli --bin rule-coverage target/rule-coverage
+//! ```
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use clap::Parser;
+use formality_types::judgment::coverage::Coverage;
+
+#[derive(Parser, Debug)]
+#[command(name = "rule-coverage", version, about, long_about = None)]
+struct Args {
+    /// The file that `FORMALITY_RULE_COVERAGE` pointed to.
+    file: PathBuf,
+}
+
+fn main() -> anyhow::Result<()> {
+    // The judgments are registered by the crates that declare them, which are only
+    // linked in if the program can reach them.
+    std::hint::black_box(formality_check::check_all_crates_errors as fn(_, _) -> _);
+
+    let args = Args::parse();
+    let coverage =
+        Coverage::read(&args.file).with_context(|| format!("reading `{}`", args.file.display()))?;
+    print!("{coverage}");
+    Ok(())
+}
@@ -0,0 +1,283 @@
+//! A command-line driver for the model: checks programs written in the formality
+//! grammar and proves goals against them, e.g.
+//!
+//! ```text
+//! formality-cli core.formality foo.formality
+//! formality-cli --prove-goal "exists<ty T> {} => {Foo(T)}" --emit=proof-tree core.formality
+//! ```
+//!
+//! The crates of the files are concatenated in order, so that a file can build on the
+//! crates of those before it. With `--repl`, goals are read interactively instead (see [`repl`]).
+
+use std::{io::Write, path::PathBuf, sync::Arc, time::Duration};
+
+use anyhow::{bail, Context};
+use clap::{Parser, ValueEnum};
+use formality_check::{
+    check_all_crates_errors, check_all_crates_report, combine_errors, Features, Options, Verdict,
+};
+use formality_prove::{
+    test_util::{test_prove, test_prove_with_proof_trees, TestAssertion},
+    QueryCache, SolverFlag, SolverFlags,
+};
+use formality_rust::grammar::Program;
+use formality_types::{judgment::stats, parse::try_term, pretty::to_string};
+use repl::Repl;
+
+pub mod repl;
+
+#[derive(Parser, Debug)]
+#[command(name = "formality-cli", version, about, long_about = None)]
+pub struct Args {
+    /// Check the crates of the program. This is what happens if no goal is given.
+    #[arg(long)]
+    check: bool,
+
+    /// Only check coherence: the orphan rules and the overlap of impls.
+    #[arg(long)]
+    coherence_only: bool,
+
+    /// Prove a goal against the program, written like `forall<ty T> {assumptions} => {goals}`,
+    /// with `exists<..>` and `coherence_mode` as further prefixes.
+    #[arg(long, value_name = "GOAL")]
+    prove_goal: Option<String>,
+
+    /// Read goals and commands from stdin after loading the program; see `:help`.
+    #[arg(long)]
+    repl: bool,
+
+    /// How to print the results.
+    #[arg(long, value_enum, default_value_t = Emit::Text)]
+    emit: Emit,
+
+    #[command(flatten)]
+    check_options: CheckOptions,
+
+    /// Take the responses to the goals that the checks prove from the query cache in this
+    /// file, if they are there, and save the others to it (see `formality_prove::QueryCache`).
+    #[arg(long, value_name = "FILE")]
+    query_cache: Option<PathBuf>,
+
+    /// Print statistics of the judgments after the results: their calls, memo hits,
+    /// iterations, rule firings and time (see `formality_types::judgment::stats`).
+    #[arg(long)]
+    stats: bool,
+
+    /// The files of the program, each a list of crates `[crate foo { ... }, ...]`.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+/// The arguments that configure the checks, which the driver of the ui tests takes too.
+#[derive(clap::Args, Debug)]
+#[command(about = None, long_about = None)]
+pub struct CheckOptions {
+    /// Fall back to the defaults of fn type parameters during type inference.
+    #[arg(long)]
+    type_param_default_fallback: bool,
+
+    /// Check items and pairs of impls concurrently.
+    #[arg(long)]
+    parallel: bool,
+
+    /// Give up on checking a crate after this many seconds, reporting that it timed out.
+    #[arg(long, value_name = "SECONDS")]
+    time_limit: Option<f64>,
+
+    /// Enable a feature of a crate, written `crate/feature`, for the items gated on it
+    /// with `#[cfg(feature = ...)]`. The goals are proven with these features too.
+    #[arg(long = "feature", value_name = "CRATE/FEATURE")]
+    features: Vec<String>,
+
+    /// Check the program with every combination of its features instead, to find the
+    /// features that are not additive.
+    #[arg(long)]
+    all_feature_combinations: bool,
+
+    /// Enable an experimental rule set of the prover, e.g. `negative_coherence` (see
+    /// `formality_prove::SolverFlag`), for the checks and `--prove-goal`.
+    #[arg(long = "solver-flag", value_name = "FLAG")]
+    solver_flags: Vec<String>,
+
+    /// Disable a rule set of the prover that is enabled by default, to compare the
+    /// results without it.
+    #[arg(long = "no-solver-flag", value_name = "FLAG")]
+    no_solver_flags: Vec<String>,
+}
+
+impl CheckOptions {
+    /// The options of the checks that these arguments ask for.
+    pub fn to_options(&self) -> anyhow::Result<Options> {
+        let enabled = self
+            .features
+            .iter()
+            .map(|f| try_term(f).with_context(|| format!("parsing the feature `{f}`")))
+            .collect::<anyhow::Result<_>>()?;
+        let mut solver_flags = SolverFlags::default();
+        for (names, enabled) in [(&self.solver_flags, true), (&self.no_solver_flags, false)] {
+            for name in names {
+                let flag: SolverFlag =
+                    try_term(name).with_context(|| format!("parsing the solver flag `{name}`"))?;
+                solver_flags = solver_flags.with(flag, enabled);
+            }
+        }
+        Ok(Options {
+            type_param_default_fallback: self.type_param_default_fallback,
+            parallel: self.parallel,
+            time_limit: self.time_limit.map(Duration::from_secs_f64),
+            features: if self.all_feature_combinations {
+                Features::AllCombinations
+            } else {
+                Features::Enabled(enabled)
+            },
+            solver_flags,
+            ..Options::default()
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Emit {
+    /// The errors, or the solutions of the goal.
+    Text,
+
+    /// The report of the checks (see `formality_check::Report`), or the solutions of the goal, as JSON.
+    Json,
+
+    /// The proof trees of the goals proven for the items that pass, or of the solutions of the goal.
+    ProofTree,
+}
+
+/// Runs the driver with the command-line arguments `args`, writing the results to `out`.
+/// Fails if the program has errors or the goal cannot be proven.
+pub fn run(args: &Args, out: &mut dyn Write) -> anyhow::Result<()> {
+    let program = read_program(&args.files)?;
+    let query_cache = match &args.query_cache {
+        Some(path) => Some(Arc::new(QueryCache::open(path)?)),
+        None => None,
+    };
+    let options = Options {
+        coherence_only: args.coherence_only,
+        query_cache: query_cache.clone(),
+        ..args.check_options.to_options()?
+    };
+
+    let result = if args.stats {
+        let (result, stats) = stats::record(|| run_program(args, &program, &options, out));
+        write!(out, "{stats}")?;
+        result
+    } else {
+        run_program(args, &program, &options, out)
+    };
+    if let Some(query_cache) = &query_cache {
+        query_cache.save()?;
+    }
+    result
+}
+
+fn run_program(
+    args: &Args,
+    program: &Program,
+    options: &Options,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    if args.check || args.coherence_only || (args.prove_goal.is_none() && !args.repl) {
+        check(program, options, args.emit, out)?;
+    }
+
+    // The goals are proven against the program with the features enabled, if they are.
+    let program = &match &options.features {
+        Features::Enabled(features) => program.configure(features),
+        _ => program.clone(),
+    };
+
+    if let Some(goal) = &args.prove_goal {
+        prove_goal(program, &options.solver_flags, goal, args.emit, out)?;
+    }
+
+    if args.repl {
+        Repl::new(program).run(std::io::stdin().lock(), out)?;
+    }
+
+    Ok(())
+}
+
+/// Parses the files, concatenating their crates.
+pub fn read_program(files: &[PathBuf]) -> anyhow::Result<Program> {
+    let mut program = Program { crates: vec![] };
+    for file in files {
+        let text = std::fs::read_to_string(file)
+            .with_context(|| format!("reading `{}`", file.display()))?;
+        let Program { crates } =
+            try_term(&text).with_context(|| format!("parsing `{}`", file.display()))?;
+        program.crates.extend(crates);
+    }
+    Ok(program)
+}
+
+fn check(
+    program: &Program,
+    options: &Options,
+    emit: Emit,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    let errors = match emit {
+        Emit::Text => check_all_crates_errors(program, options),
+        Emit::Json => {
+            let report = check_all_crates_report(program, options, false);
+            writeln!(out, "{:#}", report.to_json())?;
+            report.errors
+        }
+        Emit::ProofTree => {
+            let report = check_all_crates_report(program, options, true);
+            for item in &report.items {
+                if item.verdict == Verdict::Pass && !item.proof_trees.is_empty() {
+                    writeln!(out, "{}", to_string(&item.item))?;
+                    for tree in &item.proof_trees {
+                        writeln!(out, "{tree}")?;
+                    }
+                }
+            }
+            report.errors
+        }
+    };
+    combine_errors(errors)
+}
+
+fn prove_goal(
+    program: &Program,
+    solver_flags: &SolverFlags,
+    goal: &str,
+    emit: Emit,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    let assertion: Arc<TestAssertion> =
+        try_term(goal).with_context(|| format!("parsing the goal `{goal}`"))?;
+    let mut decls = program.to_prove_decls();
+    decls.config.flags = solver_flags.clone();
+    let solutions = match emit {
+        Emit::Text => {
+            let constraints = test_prove(decls, assertion);
+            for c in &constraints {
+                writeln!(out, "{c:?}")?;
+            }
+            constraints.len()
+        }
+        Emit::Json => {
+            let constraints = test_prove(decls, assertion);
+            writeln!(out, "{}", serde_json::to_string_pretty(&constraints)?)?;
+            constraints.len()
+        }
+        Emit::ProofTree => {
+            let proofs = test_prove_with_proof_trees(decls, assertion);
+            for (_, tree) in &proofs {
+                writeln!(out, "{tree}")?;
+            }
+            proofs.len()
+        }
+    };
+    if solutions == 0 {
+        bail!("failed to prove `{goal}`");
+    }
+    Ok(())
+}
@@ -0,0 +1,7 @@
+use clap::Parser;
+use formality_cli::Args;
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    formality_core::with_tracing_logs(|| formality_cli::run(&args, &mut std::io::stdout()))
+}
@@ -0,0 +1,150 @@
+//! An interactive loop for posing goals against a program, see [`Repl`].
+
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+
+use formality_prove::{prove, test_util::TestAssertion, Decls, Env};
+use formality_rust::{
+    grammar::{Program, WhereClause},
+    prove::ToWcs,
+};
+use formality_types::{fixed_point, parse::try_term, pretty::to_string};
+
+const HELP: &str = "\
+Goals are where-clauses like `Vec<u32>: Debug<>` or `for<ty T> Vec<T>: Sized<>`, which are
+proven given the assumptions, or assertions like `exists<ty T> {} => {Debug(T)}`.
+
+Commands:
+  :assume WHERE-CLAUSE  add an assumption
+  :reset                drop all assumptions
+  :coherence            toggle coherence mode
+  :env                  show the coherence mode and the assumptions
+  :decls                show the declarations of the program
+  :help                 show this message
+  :quit                 leave";
+
+/// The state of the loop: the program that goals are proven against, and the
+/// environment that the user has set up for them.
+pub struct Repl {
+    decls: Decls,
+    coherence_mode: bool,
+    assumptions: Vec<WhereClause>,
+}
+
+impl Repl {
+    pub fn new(program: &Program) -> Self {
+        Repl {
+            decls: program.to_prove_decls(),
+            coherence_mode: false,
+            assumptions: vec![],
+        }
+    }
+
+    /// Reads lines from `input` and evaluates them until the input ends or the user quits.
+    pub fn run(&mut self, mut input: impl BufRead, out: &mut dyn Write) -> anyhow::Result<()> {
+        writeln!(out, "Type a goal, or `:help` for the commands.")?;
+        loop {
+            write!(out, "> ")?;
+            out.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            match self.eval(&line) {
+                Some(output) if output.is_empty() => {}
+                Some(output) => writeln!(out, "{output}")?,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Evaluates one line of input, returning what to print, or `None` if the user quits.
+    pub fn eval(&mut self, line: &str) -> Option<String> {
+        let line = line.trim();
+        let (command, argument) = match line.split_once(char::is_whitespace) {
+            Some((command, argument)) => (command, argument.trim()),
+            None => (line, ""),
+        };
+        let output = match command {
+            "" => String::new(),
+            ":quit" | ":q" => return None,
+            ":help" => HELP.to_string(),
+            ":assume" => match try_term::<WhereClause>(argument) {
+                Ok(wc) => {
+                    self.assumptions.push(wc);
+                    String::new()
+                }
+                Err(e) => format!("error: {e:?}"),
+            },
+            ":reset" => {
+                self.assumptions.clear();
+                String::new()
+            }
+            ":coherence" => {
+                self.coherence_mode = !self.coherence_mode;
+                format!("coherence mode: {}", on_off(self.coherence_mode))
+            }
+            ":env" => {
+                let assumptions: Vec<String> = self.assumptions.iter().map(to_string).collect();
+                format!(
+                    "coherence mode: {}\nassumptions: [{}]",
+                    on_off(self.coherence_mode),
+                    assumptions.join(", ")
+                )
+            }
+            ":decls" => to_string(&self.decls),
+            _ if command.starts_with(':') => {
+                format!("error: unknown command `{command}`, see `:help`")
+            }
+            _ => self.prove(line),
+        };
+        Some(output)
+    }
+
+    /// Proves `goal`, returning its solutions. The memoized outputs of the judgments are
+    /// dropped first, so that they don't pile up over a long session.
+    fn prove(&self, goal: &str) -> String {
+        fixed_point::clear_memo_tables();
+        // Assertions bring their own assumptions; where-clauses use those of the user.
+        let is_assertion = ["{", "exists", "forall", "coherence_mode"]
+            .iter()
+            .any(|prefix| goal.starts_with(prefix));
+        let solutions = if is_assertion {
+            let assertion: Arc<TestAssertion> = match try_term(goal) {
+                Ok(assertion) => assertion,
+                Err(e) => return format!("error: {e:?}"),
+            };
+            let assertion = match self.coherence_mode {
+                true => Arc::new(TestAssertion::CoherenceMode(assertion)),
+                false => assertion,
+            };
+            formality_prove::test_util::test_prove(self.decls.clone(), assertion)
+        } else {
+            let wc: WhereClause = match try_term(goal) {
+                Ok(wc) => wc,
+                Err(e) => return format!("error: {e:?}"),
+            };
+            let env = Env::default().with_coherence_mode(self.coherence_mode);
+            prove(&self.decls, &env, self.assumptions.to_wcs(), wc.to_wcs())
+                .iter()
+                .map(|c| c.compress(&env))
+                .collect()
+        };
+        if solutions.is_empty() {
+            return "no solutions".to_string();
+        }
+        solutions
+            .iter()
+            .map(|c| format!("{c:?}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn on_off(b: bool) -> &'static str {
+    if b {
+        "on"
+    } else {
+        "off"
+    }
+}
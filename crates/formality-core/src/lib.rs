@@ -1,15 +1,21 @@
+pub use trace_filter::TraceFilter;
 pub use tracing::debug;
 pub use tracing::instrument;
 pub use tracing::trace;
 
+mod trace_filter;
+
 /// Run an action with a tracing log subscriber. The logging level is loaded
-/// from `RUST_LOG`.
+/// from `RUST_LOG`, and the output can be restricted to particular judgments
+/// and rules with `FORMALITY_TRACE` (see [`TraceFilter`]).
 pub fn with_tracing_logs<T>(action: impl FnOnce() -> T) -> T {
-    use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Registry};
-    use tracing_tree::HierarchicalLayer;
-    let filter = EnvFilter::from_env("RUST_LOG");
-    let subscriber = Registry::default()
-        .with(filter)
-        .with(HierarchicalLayer::new(2).with_writer(std::io::stdout));
+    with_filtered_tracing_logs(TraceFilter::from_env(), action)
+}
+
+/// Run an action with a tracing log subscriber that only shows what `filter` selects.
+/// The logging level is loaded from `RUST_LOG`.
+pub fn with_filtered_tracing_logs<T>(filter: TraceFilter, action: impl FnOnce() -> T) -> T {
+    let directives = std::env::var("RUST_LOG").unwrap_or_default();
+    let subscriber = filter.subscriber(&directives, std::io::stdout);
     tracing::subscriber::with_default(subscriber, action)
 }
@@ -0,0 +1,152 @@
+use std::{cell::RefCell, str::FromStr};
+
+use tracing::{span, subscriber::Interest, Metadata, Subscriber};
+use tracing_subscriber::{
+    fmt::MakeWriter,
+    layer::{Context, Filter, Layer, SubscriberExt},
+    registry::LookupSpan,
+    EnvFilter, Registry,
+};
+use tracing_tree::HierarchicalLayer;
+
+/// Restricts the tracing output to what happens within particular judgments, or
+/// particular rules of a judgment, when debugging a proof.
+///
+/// This relies on the spans that `judgment_fn!` emits: the span of a judgment is
+/// named after the judgment (e.g. `prove_wc`), and the span of a rule after the rule
+/// (e.g. `positive impl`), with the judgment as its `judgment` field. Everything
+/// nested within a selected span is shown, including other judgments. An empty
+/// filter shows everything.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TraceFilter {
+    judgments: Vec<String>,
+    rules: Vec<(String, String)>,
+}
+
+impl TraceFilter {
+    /// Also show what happens within the judgment `judgment`.
+    pub fn judgment(mut self, judgment: &str) -> Self {
+        self.judgments.push(judgment.to_string());
+        self
+    }
+
+    /// Also show what happens within the rule `rule` of the judgment `judgment`.
+    pub fn rule(mut self, judgment: &str, rule: &str) -> Self {
+        self.rules.push((judgment.to_string(), rule.to_string()));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.judgments.is_empty() && self.rules.is_empty()
+    }
+
+    /// The filter in the environment variable `FORMALITY_TRACE`, if it is set
+    /// (see [`TraceFilter::from_str`]), or else an empty one.
+    pub fn from_env() -> Self {
+        std::env::var("FORMALITY_TRACE")
+            .map(|s| s.parse().unwrap())
+            .unwrap_or_default()
+    }
+
+    /// A subscriber that writes the tracing output selected by this filter to
+    /// `writer`, at the levels given by `directives` (in the syntax of `RUST_LOG`).
+    pub fn subscriber<W>(self, directives: &str, writer: W) -> impl Subscriber + Send + Sync
+    where
+        W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+    {
+        Registry::default()
+            .with(EnvFilter::new(directives))
+            .with(ScopeTracker)
+            .with(
+                HierarchicalLayer::new(2)
+                    .with_writer(writer)
+                    .with_filter(self),
+            )
+    }
+
+    /// Whether to show a span or event nested within `scopes`, from the outermost
+    /// one to itself.
+    fn shows(&self, scopes: &[&Metadata<'_>]) -> bool {
+        self.is_empty()
+            || scopes.iter().enumerate().any(|(i, metadata)| {
+                if is_rule(metadata) {
+                    // The span of a rule is entered within that of its judgment.
+                    i > 0
+                        && self.rules.iter().any(|(judgment, rule)| {
+                            rule == metadata.name() && judgment == scopes[i - 1].name()
+                        })
+                } else {
+                    self.judgments.iter().any(|j| j == metadata.name())
+                }
+            })
+    }
+}
+
+/// Parses a comma-separated list of judgments, like `prove_wc`, and rules of
+/// judgments, like `prove_wc:positive impl`.
+impl FromStr for TraceFilter {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.split(',').map(str::trim).filter(|s| !s.is_empty()).fold(
+            TraceFilter::default(),
+            |filter, s| match s.split_once(':') {
+                Some((judgment, rule)) => filter.rule(judgment.trim(), rule.trim()),
+                None => filter.judgment(s),
+            },
+        ))
+    }
+}
+
+fn is_rule(metadata: &Metadata<'_>) -> bool {
+    metadata.is_span() && metadata.fields().field("judgment").is_some()
+}
+
+impl<S> Filter<S> for TraceFilter {
+    fn enabled(&self, metadata: &Metadata<'_>, _: &Context<'_, S>) -> bool {
+        SCOPES.with(|scopes| {
+            let scopes = scopes.borrow();
+            let mut scopes: Vec<&Metadata<'_>> = scopes.iter().map(|(_, m)| *m).collect();
+            scopes.push(metadata);
+            self.shows(&scopes)
+        })
+    }
+
+    fn callsite_enabled(&self, _: &'static Metadata<'static>) -> Interest {
+        // Whether a callsite is enabled depends on where it is reached from.
+        if self.is_empty() {
+            Interest::always()
+        } else {
+            Interest::sometimes()
+        }
+    }
+}
+
+thread_local! {
+    /// The spans that the current thread is in, from the outermost one.
+    static SCOPES: RefCell<Vec<(span::Id, &'static Metadata<'static>)>> = RefCell::new(vec![])
+}
+
+/// Keeps [`SCOPES`] up to date. The filter does not see the spans it disables, so
+/// this layer is not filtered by it.
+struct ScopeTracker;
+
+impl<S> Layer<S> for ScopeTracker
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            SCOPES.with(|scopes| scopes.borrow_mut().push((id.clone(), span.metadata())));
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, _: Context<'_, S>) {
+        SCOPES.with(|scopes| {
+            let mut scopes = scopes.borrow_mut();
+            if let Some(i) = scopes.iter().rposition(|(s, _)| s == id) {
+                scopes.remove(i);
+            }
+        })
+    }
+}
@@ -0,0 +1,1084 @@
+//! Importing real Rust code into the formality grammar.
+//!
+//! [`import_crate`] parses Rust source with `syn` and lowers the subset of it that
+//! formality models: structs, enums, traits, impls of traits, fns, type aliases and
+//! inline modules, with their generics and where-clauses. Fn bodies are not imported;
+//! fns that have one get a `{trusted}` body instead. Any other item is an error, except
+//! for `use` and `extern crate` declarations, which are dropped: names are taken as
+//! written, without resolving them.
+//!
+//! Lifetimes elided in the signature of a fn or the header of an impl are named after
+//! fresh lifetime parameters of the fn or impl, following the rules of rustc.
+
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use formality_rust::grammar::{
+    constness::Constness, dependencies::Dependencies, edition::Edition, safety::Safety,
+    self_param::SelfParam, ty_defaults::TyDefaults, visibility::Visibility, AdtBoundData,
+    AssociatedTy, AssociatedTyBoundData, AssociatedTyValue, AssociatedTyValueBoundData, Crate,
+    CrateItem, Enum, Field, FieldName, Fn, FnBody, FnBoundData, ImplItem, MaybeFnBody, Module,
+    ModuleId, NegTraitImpl, NegTraitImplBoundData, Program, Struct, StructBoundData, Trait,
+    TraitBinder, TraitBoundData, TraitImpl, TraitImplBoundData, TraitItem, TypeAlias,
+    TypeAliasBoundData, Variant, VariantId, WhereBound, WhereBoundData, WhereClause,
+    WhereClauseData,
+};
+use formality_types::{
+    cast::{Downcast, Upcast},
+    grammar::{
+        fresh_bound_var, Abi, AdtId, AliasTy, AssociatedItemId, Binder, BoundVar, CrateId,
+        Fallible, FieldId, FnId, Lt, Parameter, ParameterKind, RefKind, RigidName, RigidTy,
+        ScalarId, TraitId, Ty, TyData, TypeAliasId,
+    },
+};
+use quote::ToTokens;
+use syn::{
+    visit_mut::{self, VisitMut},
+    FnArg, GenericArgument, GenericParam, Generics, Item, Lifetime, LifetimeDef, Pat, Path,
+    PathArguments, ReturnType, Signature, TraitBound, TraitBoundModifier, Type, TypeParamBound,
+    WherePredicate,
+};
+
+/// Imports the Rust source `text` as a program of one crate, named `crate_id`.
+pub fn import_program(crate_id: &str, text: &str) -> Fallible<Program> {
+    Ok(Program {
+        crates: vec![import_crate(crate_id, text)?],
+    })
+}
+
+/// Imports the Rust source `text` as the crate `crate_id`.
+pub fn import_crate(crate_id: &str, text: &str) -> Fallible<Crate> {
+    let file = syn::parse_file(text)?;
+    let mut type_aliases = vec![];
+    collect_type_aliases("", &file.items, &mut type_aliases);
+    let scope = Scope {
+        type_aliases: Arc::new(type_aliases),
+        ..Scope::default()
+    };
+    Ok(Crate {
+        id: CrateId::new(crate_id),
+        edition: Edition::default(),
+        dependencies: Dependencies::Preceding,
+        items: scope.items(&file.items)?,
+    })
+}
+
+/// Collects the paths of the type aliases declared in `items`, which are within the
+/// module at `prefix`, as uses of a type alias are written differently from other types.
+fn collect_type_aliases(prefix: &str, items: &[Item], type_aliases: &mut Vec<String>) {
+    for item in items {
+        match item {
+            Item::Type(t) => type_aliases.push(format!("{prefix}{}", t.ident)),
+            Item::Mod(m) => {
+                if let Some((_, items)) = &m.content {
+                    collect_type_aliases(&format!("{prefix}{}::", m.ident), items, type_aliases)
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The values of the associated types bound in a trait path, like `Item` in
+/// `Iterator<Item = u32>`.
+type Bindings = Vec<(AssociatedItemId, Ty)>;
+
+/// What the names in the item being lowered refer to.
+#[derive(Clone, Default)]
+struct Scope {
+    /// The generic parameters of the enclosing items, by name; those of lifetimes
+    /// include the `'`. `Self` is here too, in traits and impls.
+    names: Vec<(String, Parameter)>,
+
+    /// The trait whose associated types `Self::Item` refers to, with its parameters,
+    /// starting with `Self`.
+    self_trait: Option<(TraitId, Vec<Parameter>)>,
+
+    /// The path of the enclosing module, e.g. `m::`.
+    module: String,
+
+    /// See [`collect_type_aliases`].
+    type_aliases: Arc<Vec<String>>,
+}
+
+impl Scope {
+    fn items(&self, items: &[Item]) -> Fallible<Vec<CrateItem>> {
+        let mut lowered = vec![];
+        for item in items {
+            let item: CrateItem = match item {
+                Item::Use(_) | Item::ExternCrate(_) => continue,
+                Item::Struct(s) => self
+                    .struct_item(s)
+                    .with_context(|| format!("importing struct `{}`", s.ident))?
+                    .upcast(),
+                Item::Enum(e) => self
+                    .enum_item(e)
+                    .with_context(|| format!("importing enum `{}`", e.ident))?
+                    .upcast(),
+                Item::Trait(t) => self
+                    .trait_item(t)
+                    .with_context(|| format!("importing trait `{}`", t.ident))?
+                    .upcast(),
+                Item::Impl(i) => self
+                    .impl_item(i)
+                    .with_context(|| format!("importing `{}`", header(i)))?,
+                Item::Fn(f) => self
+                    .fn_item(visibility(&f.vis), &f.sig, true)
+                    .with_context(|| format!("importing fn `{}`", f.sig.ident))?
+                    .upcast(),
+                Item::Type(t) => self
+                    .type_alias_item(t)
+                    .with_context(|| format!("importing type alias `{}`", t.ident))?
+                    .upcast(),
+                Item::Mod(m) => {
+                    let Some((_, items)) = &m.content else {
+                        bail!("module `{}` is not inline, which is not supported", m.ident)
+                    };
+                    let scope = Scope {
+                        module: format!("{}{}::", self.module, m.ident),
+                        ..self.clone()
+                    };
+                    Module {
+                        visibility: visibility(&m.vis),
+                        id: ModuleId::new(&m.ident.to_string()),
+                        items: scope.items(items)?,
+                    }
+                    .upcast()
+                }
+                _ => bail!("unsupported item `{}`", tokens(item)),
+            };
+            lowered.push(item);
+        }
+        Ok(lowered)
+    }
+
+    fn struct_item(&self, s: &syn::ItemStruct) -> Fallible<Struct> {
+        let (scope, vars) = self.bind(&s.generics)?;
+        let fields = scope.fields(&s.fields)?;
+        let mut where_clauses = scope.where_clauses(&s.generics)?;
+        for field in &fields {
+            add_implied_bounds(&mut where_clauses, &field.ty);
+        }
+        Ok(Struct {
+            visibility: visibility(&s.vis),
+            id: AdtId::new(&s.ident.to_string()),
+            binder: Binder::new(
+                vars,
+                StructBoundData {
+                    where_clauses,
+                    fields,
+                },
+            ),
+        })
+    }
+
+    fn enum_item(&self, e: &syn::ItemEnum) -> Fallible<Enum> {
+        let (scope, vars) = self.bind(&e.generics)?;
+        let variants = e
+            .variants
+            .iter()
+            .map(|v| {
+                if v.discriminant.is_some() {
+                    bail!("the discriminant of variant `{}` is not supported", v.ident)
+                }
+                Ok(Variant {
+                    name: VariantId::new(&v.ident.to_string()),
+                    fields: scope.fields(&v.fields)?,
+                })
+            })
+            .collect::<Fallible<Vec<Variant>>>()?;
+        let mut where_clauses = scope.where_clauses(&e.generics)?;
+        for field in variants.iter().flat_map(|v| &v.fields) {
+            add_implied_bounds(&mut where_clauses, &field.ty);
+        }
+        Ok(Enum {
+            visibility: visibility(&e.vis),
+            id: AdtId::new(&e.ident.to_string()),
+            binder: Binder::new(
+                vars,
+                AdtBoundData {
+                    where_clauses,
+                    variants,
+                },
+            ),
+        })
+    }
+
+    fn fields(&self, fields: &syn::Fields) -> Fallible<Vec<Field>> {
+        fields
+            .iter()
+            .enumerate()
+            .map(|(index, f)| {
+                Ok(Field {
+                    visibility: visibility(&f.vis),
+                    name: match &f.ident {
+                        Some(id) => FieldName::Id(FieldId::new(&id.to_string())),
+                        None => FieldName::Index(index),
+                    },
+                    ty: self.ty(&f.ty)?,
+                })
+            })
+            .collect()
+    }
+
+    fn trait_item(&self, t: &syn::ItemTrait) -> Fallible<Trait> {
+        if t.auto_token.is_some() {
+            bail!("auto traits are not supported")
+        }
+        let self_var = fresh_bound_var(ParameterKind::Ty);
+        let mut scope = self.clone();
+        scope.names.push(("Self".to_string(), self_var.upcast()));
+        let (mut scope, vars) = scope.bind(&t.generics)?;
+
+        let id = TraitId::new(&t.ident.to_string());
+        let vars: Vec<BoundVar> = std::iter::once(self_var).chain(vars).collect();
+        scope.self_trait = Some((id.clone(), vars.iter().map(|v| v.upcast()).collect()));
+
+        let mut where_clauses = scope.bounds(&self_var.ty(), &t.supertraits)?;
+        where_clauses.extend(scope.where_clauses(&t.generics)?);
+        let trait_items = t
+            .items
+            .iter()
+            .map(|item| match item {
+                syn::TraitItem::Method(m) => Ok(scope
+                    .fn_item(Visibility::Private, &m.sig, m.default.is_some())
+                    .with_context(|| format!("importing fn `{}`", m.sig.ident))?
+                    .upcast()),
+                syn::TraitItem::Type(ty) => Ok(scope
+                    .associated_ty(ty)
+                    .with_context(|| format!("importing associated type `{}`", ty.ident))?
+                    .upcast()),
+                _ => bail!("unsupported trait item `{}`", tokens(item)),
+            })
+            .collect::<Fallible<Vec<TraitItem>>>()?;
+
+        Ok(Trait {
+            visibility: visibility(&t.vis),
+            safety: safety(&t.unsafety),
+            constness: Constness::NotConst,
+            id,
+            binder: TraitBinder {
+                explicit_binder: Binder::new(
+                    vars,
+                    TraitBoundData {
+                        where_clauses,
+                        trait_items,
+                    },
+                ),
+            },
+        })
+    }
+
+    fn associated_ty(&self, ty: &syn::TraitItemType) -> Fallible<AssociatedTy> {
+        if ty.default.is_some() {
+            bail!("defaults of associated types are not supported")
+        }
+        let (scope, vars) = self.bind(&ty.generics)?;
+        let ensures = ty
+            .bounds
+            .iter()
+            .map(|bound| scope.where_bound(bound))
+            .collect::<Fallible<_>>()?;
+        Ok(AssociatedTy {
+            id: AssociatedItemId::new(&ty.ident.to_string()),
+            binder: Binder::new(
+                vars,
+                AssociatedTyBoundData {
+                    ensures,
+                    where_clauses: scope.where_clauses(&ty.generics)?,
+                },
+            ),
+        })
+    }
+
+    /// A bound of an associated type, which holds for any value of the type.
+    fn where_bound(&self, bound: &TypeParamBound) -> Fallible<WhereBound> {
+        let data = match bound {
+            TypeParamBound::Lifetime(lt) => WhereBoundData::Outlives(self.lt(lt)?),
+            TypeParamBound::Trait(TraitBound {
+                modifier: TraitBoundModifier::Maybe(_),
+                ..
+            }) => bail!(
+                "`{}` bounds on associated types are not supported",
+                tokens(bound)
+            ),
+            TypeParamBound::Trait(b) => {
+                let (scope, vars) = self.bind_lifetimes(&b.lifetimes);
+                let (trait_id, parameters, bindings) = scope.trait_path(&b.path)?;
+                if !bindings.is_empty() {
+                    bail!(
+                        "associated type bindings in `{}` are not supported",
+                        tokens(bound)
+                    )
+                }
+                let is_implemented = WhereBoundData::IsImplemented(trait_id, parameters);
+                if vars.is_empty() {
+                    is_implemented
+                } else {
+                    let bound = WhereBound {
+                        data: Arc::new(is_implemented),
+                    };
+                    WhereBoundData::ForAll(Binder::new(vars, bound))
+                }
+            }
+        };
+        Ok(WhereBound {
+            data: Arc::new(data),
+        })
+    }
+
+    fn impl_item(&self, i: &syn::ItemImpl) -> Fallible<CrateItem> {
+        let mut i = i.clone();
+        let Some((bang, trait_path, _)) = &mut i.trait_ else {
+            bail!("inherent impls are not supported")
+        };
+        if i.defaultness.is_some() {
+            bail!("`default` impls are not supported")
+        }
+
+        let mut elided = ElidedLifetimes::default();
+        elided.visit_type_mut(&mut i.self_ty);
+        elided.visit_path_mut(trait_path);
+        elided.declare_in(&mut i.generics);
+
+        let (mut scope, vars) = self.bind(&i.generics)?;
+        let self_ty = scope.ty(&i.self_ty)?;
+        scope
+            .names
+            .push(("Self".to_string(), self_ty.to_parameter()));
+        let (trait_id, trait_parameters, bindings) = scope.trait_path(trait_path)?;
+        if !bindings.is_empty() {
+            bail!("associated type bindings in the trait of an impl are not supported")
+        }
+        scope.self_trait = Some((
+            trait_id.clone(),
+            std::iter::once(self_ty.to_parameter())
+                .chain(trait_parameters.iter().cloned())
+                .collect(),
+        ));
+        let mut where_clauses = scope.where_clauses(&i.generics)?;
+        add_implied_bounds(&mut where_clauses, &self_ty);
+        for parameter in &trait_parameters {
+            if let Parameter::Ty(ty) = parameter {
+                add_implied_bounds(&mut where_clauses, ty);
+            }
+        }
+
+        if bang.is_some() {
+            if !i.items.is_empty() {
+                bail!("negative impls cannot have items")
+            }
+            return Ok(NegTraitImpl {
+                binder: Binder::new(
+                    vars,
+                    NegTraitImplBoundData {
+                        trait_id,
+                        self_ty,
+                        trait_parameters,
+                        where_clauses,
+                    },
+                ),
+            }
+            .upcast());
+        }
+
+        let impl_items = i
+            .items
+            .iter()
+            .map(|item| match item {
+                syn::ImplItem::Method(m) => Ok(scope
+                    .fn_item(visibility(&m.vis), &m.sig, true)
+                    .with_context(|| format!("importing fn `{}`", m.sig.ident))?
+                    .upcast()),
+                syn::ImplItem::Type(ty) => Ok(scope
+                    .associated_ty_value(ty)
+                    .with_context(|| format!("importing associated type `{}`", ty.ident))?
+                    .upcast()),
+                _ => bail!("unsupported impl item `{}`", tokens(item)),
+            })
+            .collect::<Fallible<Vec<ImplItem>>>()?;
+        Ok(TraitImpl {
+            safety: safety(&i.unsafety),
+            binder: Binder::new(
+                vars,
+                TraitImplBoundData {
+                    constness: Constness::NotConst,
+                    trait_id,
+                    self_ty,
+                    trait_parameters,
+                    where_clauses,
+                    impl_items,
+                },
+            ),
+        }
+        .upcast())
+    }
+
+    fn associated_ty_value(&self, ty: &syn::ImplItemType) -> Fallible<AssociatedTyValue> {
+        let (scope, vars) = self.bind(&ty.generics)?;
+        Ok(AssociatedTyValue {
+            id: AssociatedItemId::new(&ty.ident.to_string()),
+            binder: Binder::new(
+                vars,
+                AssociatedTyValueBoundData {
+                    where_clauses: scope.where_clauses(&ty.generics)?,
+                    ty: scope.ty(&ty.ty)?,
+                },
+            ),
+        })
+    }
+
+    fn type_alias_item(&self, t: &syn::ItemType) -> Fallible<TypeAlias> {
+        let (scope, vars) = self.bind(&t.generics)?;
+        Ok(TypeAlias {
+            visibility: visibility(&t.vis),
+            id: TypeAliasId::new(&t.ident.to_string()),
+            binder: Binder::new(
+                vars,
+                TypeAliasBoundData {
+                    ty: scope.ty(&t.ty)?,
+                    where_clauses: scope.where_clauses(&t.generics)?,
+                },
+            ),
+        })
+    }
+
+    /// A fn with the signature `sig`, with a trusted body if `has_body`.
+    fn fn_item(&self, visibility: Visibility, sig: &Signature, has_body: bool) -> Fallible<Fn> {
+        if sig.asyncness.is_some() {
+            bail!("async fns are not supported")
+        }
+        if sig.abi.is_some() || sig.variadic.is_some() {
+            bail!("fns with an ABI are not supported")
+        }
+
+        let mut sig = sig.clone();
+        let mut elided = ElidedLifetimes::default();
+        for input in &mut sig.inputs {
+            elided.visit_fn_arg_mut(input);
+        }
+        let output_lt = sig
+            .receiver()
+            .and_then(|r| match r {
+                FnArg::Receiver(r) => r.reference.as_ref().and_then(|(_, lt)| lt.clone()),
+                FnArg::Typed(_) => None,
+            })
+            .or_else(|| match &elided.lifetimes[..] {
+                [lt] => Some(lt.clone()),
+                _ => None,
+            });
+        if let ReturnType::Type(_, ty) = &mut sig.output {
+            let mut output = OutputLifetimes {
+                lt: output_lt,
+                missing: false,
+            };
+            output.visit_type_mut(ty);
+            if output.missing {
+                bail!("missing lifetime in the return type `{}`", tokens(ty))
+            }
+        }
+        elided.declare_in(&mut sig.generics);
+
+        let (scope, vars) = self.bind(&sig.generics)?;
+        let mut self_param = SelfParam::NoSelf;
+        let mut input_tys = vec![];
+        for input in &sig.inputs {
+            match input {
+                FnArg::Receiver(r) => {
+                    self_param = SelfParam::HasSelf;
+                    let self_ty = scope.self_ty()?;
+                    input_tys.push(match &r.reference {
+                        None => self_ty,
+                        Some((_, lt)) => {
+                            let lt = scope.lt(lt.as_ref().unwrap())?;
+                            match r.mutability {
+                                Some(_) => self_ty.ref_mut_ty(lt),
+                                None => self_ty.ref_ty(lt),
+                            }
+                        }
+                    });
+                }
+                FnArg::Typed(arg) => {
+                    if matches!(&*arg.pat, Pat::Ident(p) if p.ident == "self") {
+                        self_param = SelfParam::HasSelf;
+                    }
+                    input_tys.push(scope.ty(&arg.ty)?);
+                }
+            }
+        }
+        let output_ty = match &sig.output {
+            ReturnType::Default => Ty::unit(),
+            ReturnType::Type(_, ty) => scope.ty(ty)?,
+        };
+        let mut where_clauses = scope.where_clauses(&sig.generics)?;
+        for ty in input_tys.iter().chain(Some(&output_ty)) {
+            add_implied_bounds(&mut where_clauses, ty);
+        }
+
+        Ok(Fn {
+            visibility,
+            safety: safety(&sig.unsafety),
+            id: FnId::new(&sig.ident.to_string()),
+            binder: Binder::new(
+                vars,
+                FnBoundData {
+                    defaults: TyDefaults::default(),
+                    self_param,
+                    input_tys,
+                    output_ty,
+                    where_clauses,
+                    body: if has_body {
+                        MaybeFnBody::FnBody(FnBody::TrustedFnBody)
+                    } else {
+                        MaybeFnBody::NoFnBody
+                    },
+                },
+            ),
+        })
+    }
+
+    /// Brings the parameters of `generics` into scope, returning the variables
+    /// they are bound to.
+    fn bind(&self, generics: &Generics) -> Fallible<(Scope, Vec<BoundVar>)> {
+        let mut scope = self.clone();
+        let mut vars = vec![];
+        for param in &generics.params {
+            let (name, kind) = match param {
+                GenericParam::Type(p) => {
+                    if p.default.is_some() {
+                        bail!(
+                            "the default of type parameter `{}` is not supported",
+                            p.ident
+                        )
+                    }
+                    (p.ident.to_string(), ParameterKind::Ty)
+                }
+                GenericParam::Lifetime(p) => (p.lifetime.to_string(), ParameterKind::Lt),
+                GenericParam::Const(p) => {
+                    bail!("const parameter `{}` is not supported", p.ident)
+                }
+            };
+            let var = fresh_bound_var(kind);
+            scope.names.push((name, var.upcast()));
+            vars.push(var);
+        }
+        Ok((scope, vars))
+    }
+
+    /// Brings the lifetimes of `for<'a, ..>` into scope, returning the variables
+    /// they are bound to.
+    fn bind_lifetimes(&self, lifetimes: &Option<syn::BoundLifetimes>) -> (Scope, Vec<BoundVar>) {
+        let mut scope = self.clone();
+        let mut vars = vec![];
+        for def in lifetimes.iter().flat_map(|l| &l.lifetimes) {
+            let var = fresh_bound_var(ParameterKind::Lt);
+            scope.names.push((def.lifetime.to_string(), var.upcast()));
+            vars.push(var);
+        }
+        (scope, vars)
+    }
+
+    /// The where-clauses of `generics`: the bounds declared with its parameters,
+    /// followed by its `where` clause.
+    fn where_clauses(&self, generics: &Generics) -> Fallible<Vec<WhereClause>> {
+        let mut where_clauses = vec![];
+        for param in &generics.params {
+            match param {
+                GenericParam::Type(p) => {
+                    let ty = self.path_ty(&p.ident.clone().into())?;
+                    where_clauses.extend(self.bounds(&ty, &p.bounds)?);
+                }
+                GenericParam::Lifetime(p) => {
+                    let lt = self.lt(&p.lifetime)?;
+                    for bound in &p.bounds {
+                        where_clauses.push(outlives(lt.clone(), self.lt(bound)?));
+                    }
+                }
+                GenericParam::Const(_) => {}
+            }
+        }
+        for predicate in generics.where_clause.iter().flat_map(|w| &w.predicates) {
+            match predicate {
+                WherePredicate::Type(p) => {
+                    let (scope, vars) = self.bind_lifetimes(&p.lifetimes);
+                    let ty = scope.ty(&p.bounded_ty)?;
+                    where_clauses.extend(
+                        scope
+                            .bounds(&ty, &p.bounds)?
+                            .into_iter()
+                            .map(|wc| for_all(&vars, wc)),
+                    );
+                }
+                WherePredicate::Lifetime(p) => {
+                    let lt = self.lt(&p.lifetime)?;
+                    for bound in &p.bounds {
+                        where_clauses.push(outlives(lt.clone(), self.lt(bound)?));
+                    }
+                }
+                WherePredicate::Eq(_) => {
+                    bail!("unsupported where clause `{}`", tokens(predicate))
+                }
+            }
+        }
+        Ok(where_clauses)
+    }
+
+    /// The where-clauses `ty: bound` for each of `bounds`.
+    fn bounds<'b>(
+        &self,
+        ty: &Ty,
+        bounds: impl IntoIterator<Item = &'b TypeParamBound>,
+    ) -> Fallible<Vec<WhereClause>> {
+        let mut where_clauses = vec![];
+        for bound in bounds {
+            match bound {
+                TypeParamBound::Lifetime(lt) => {
+                    where_clauses.push(outlives(ty.clone(), self.lt(lt)?));
+                }
+                TypeParamBound::Trait(TraitBound {
+                    modifier: TraitBoundModifier::Maybe(_),
+                    path,
+                    ..
+                }) => {
+                    if !path.is_ident("Sized") {
+                        bail!("unsupported bound `{}`", tokens(bound))
+                    }
+                    where_clauses.push(where_clause(WhereClauseData::MaybeSized(ty.clone())));
+                }
+                TypeParamBound::Trait(b) => {
+                    let (scope, vars) = self.bind_lifetimes(&b.lifetimes);
+                    let (trait_id, parameters, bindings) = scope.trait_path(&b.path)?;
+
+                    // `T: Iterator<Item = U>` is `T: Iterator` and `<T as Iterator>::Item == U`.
+                    let alias_parameters: Vec<Parameter> = std::iter::once(ty.upcast())
+                        .chain(parameters.iter().cloned())
+                        .collect();
+                    let mut wcs = vec![where_clause(WhereClauseData::IsImplemented(
+                        ty.clone(),
+                        trait_id.clone(),
+                        parameters,
+                    ))];
+                    for (item, value) in bindings {
+                        let alias = AliasTy::associated_ty(&trait_id, item, &alias_parameters);
+                        wcs.push(where_clause(WhereClauseData::AliasEq(alias, value)));
+                    }
+                    where_clauses.extend(wcs.into_iter().map(|wc| for_all(&vars, wc)));
+                }
+            }
+        }
+        Ok(where_clauses)
+    }
+
+    /// The trait at `path`, with its parameters and the values of the associated types
+    /// bound in it, like `Item` in `Iterator<Item = u32>`.
+    fn trait_path(
+        &self,
+        path: &Path,
+    ) -> Fallible<(TraitId, Vec<Parameter>, Bindings)> {
+        let (name, args) = self.path(path)?;
+        let mut parameters = vec![];
+        let mut bindings = vec![];
+        for arg in args {
+            match arg {
+                GenericArgument::Binding(b) => {
+                    bindings.push((AssociatedItemId::new(&b.ident.to_string()), self.ty(&b.ty)?))
+                }
+                _ => parameters.push(self.parameter(arg)?),
+            }
+        }
+        Ok((TraitId::new(&name), parameters, bindings))
+    }
+
+    /// The name of the item at `path`, e.g. `m::Foo`, with the generic arguments
+    /// of its last segment.
+    fn path<'p>(&self, path: &'p Path) -> Fallible<(String, Vec<&'p GenericArgument>)> {
+        let mut segments: Vec<&syn::PathSegment> = path.segments.iter().collect();
+        if let Some(first) = segments.first() {
+            if first.ident == "crate" || first.ident == "self" {
+                segments.remove(0);
+            }
+        }
+        let Some((last, init)) = segments.split_last() else {
+            bail!("unsupported path `{}`", tokens(path))
+        };
+        if init.iter().any(|s| !s.arguments.is_empty()) {
+            bail!("unsupported path `{}`", tokens(path))
+        }
+        let args = match &last.arguments {
+            PathArguments::None => vec![],
+            PathArguments::AngleBracketed(args) => args.args.iter().collect(),
+            PathArguments::Parenthesized(_) => bail!("unsupported path `{}`", tokens(path)),
+        };
+        let name: Vec<String> = segments.iter().map(|s| s.ident.to_string()).collect();
+        Ok((name.join("::"), args))
+    }
+
+    fn parameter(&self, arg: &GenericArgument) -> Fallible<Parameter> {
+        match arg {
+            GenericArgument::Type(ty) => Ok(self.ty(ty)?.upcast()),
+            GenericArgument::Lifetime(lt) => Ok(self.lt(lt)?.upcast()),
+            _ => bail!("unsupported generic argument `{}`", tokens(arg)),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Parameter> {
+        self.names
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, p)| p)
+    }
+
+    fn self_ty(&self) -> Fallible<Ty> {
+        match self.lookup("Self").and_then(|p| p.downcast()) {
+            Some(ty) => Ok(ty),
+            None => bail!("`Self` is only supported in traits and impls"),
+        }
+    }
+
+    fn lt(&self, lt: &Lifetime) -> Fallible<Lt> {
+        if lt.ident == "static" {
+            return Ok(Lt::static_());
+        }
+        match self.lookup(&lt.to_string()).and_then(|p| p.downcast()) {
+            Some(lt) => Ok(lt),
+            None => bail!("undeclared lifetime `{lt}`"),
+        }
+    }
+
+    fn ty(&self, ty: &Type) -> Fallible<Ty> {
+        match ty {
+            Type::Path(syn::TypePath { qself: None, path }) => self.path_ty(path),
+
+            // `<T as Trait<..>>::Item`
+            Type::Path(syn::TypePath {
+                qself: Some(qself),
+                path,
+            }) => {
+                if qself.position == 0 || path.segments.len() != qself.position + 1 {
+                    bail!("unsupported type `{}`", tokens(ty))
+                }
+                let item = path.segments.last().unwrap();
+                if !item.arguments.is_empty() {
+                    bail!("unsupported type `{}`", tokens(ty))
+                }
+                let trait_path = Path {
+                    leading_colon: None,
+                    segments: path.segments.iter().take(qself.position).cloned().collect(),
+                };
+                let (trait_id, parameters, bindings) = self.trait_path(&trait_path)?;
+                if !bindings.is_empty() {
+                    bail!("unsupported type `{}`", tokens(ty))
+                }
+                let parameters: Vec<Parameter> = std::iter::once(self.ty(&qself.ty)?.upcast())
+                    .chain(parameters)
+                    .collect();
+                Ok(AliasTy::associated_ty(
+                    trait_id,
+                    AssociatedItemId::new(&item.ident.to_string()),
+                    parameters,
+                )
+                .upcast())
+            }
+
+            Type::Reference(r) => {
+                let Some(lt) = &r.lifetime else {
+                    bail!("missing lifetime in `{}`", tokens(ty))
+                };
+                let lt = self.lt(lt)?;
+                let referent = self.ty(&r.elem)?;
+                Ok(match r.mutability {
+                    Some(_) => referent.ref_mut_ty(lt),
+                    None => referent.ref_ty(lt),
+                })
+            }
+
+            Type::Ptr(p) => Ok(self.ty(&p.elem)?.raw_ptr_ty(match p.mutability {
+                Some(_) => RefKind::Mut,
+                None => RefKind::Shared,
+            })),
+
+            Type::Tuple(t) => {
+                let tys = t
+                    .elems
+                    .iter()
+                    .map(|ty| self.ty(ty))
+                    .collect::<Fallible<Vec<Ty>>>()?;
+                Ok(Ty::tuple(&tys))
+            }
+
+            Type::Never(_) => Ok(Ty::never()),
+
+            Type::BareFn(f) => {
+                if f.lifetimes.is_some() || f.unsafety.is_some() || f.variadic.is_some() {
+                    bail!("unsupported type `{}`", tokens(ty))
+                }
+                let abi = match f.abi.as_ref().and_then(|abi| abi.name.as_ref()) {
+                    None => Abi::Rust,
+                    Some(name) if name.value() == "Rust" => Abi::Rust,
+                    Some(name) if name.value() == "C" => Abi::C,
+                    Some(_) => bail!("unsupported type `{}`", tokens(ty)),
+                };
+                let input_tys = f
+                    .inputs
+                    .iter()
+                    .map(|arg| self.ty(&arg.ty))
+                    .collect::<Fallible<Vec<Ty>>>()?;
+                let output_ty = match &f.output {
+                    ReturnType::Default => Ty::unit(),
+                    ReturnType::Type(_, ty) => self.ty(ty)?,
+                };
+                Ok(Ty::abi_fn_ptr(abi, &input_tys, &output_ty))
+            }
+
+            Type::Paren(p) => self.ty(&p.elem),
+            Type::Group(g) => self.ty(&g.elem),
+
+            _ => bail!("unsupported type `{}`", tokens(ty)),
+        }
+    }
+
+    /// A type written as a path without a `<T as Trait>` qualifier: a generic parameter,
+    /// a scalar, an associated type of `Self`, a type alias or an ADT.
+    fn path_ty(&self, path: &Path) -> Fallible<Ty> {
+        if let Some(ident) = path.get_ident() {
+            let name = ident.to_string();
+            if let Some(parameter) = self.lookup(&name) {
+                return match parameter.downcast() {
+                    Some(ty) => Ok(ty),
+                    None => bail!("`{name}` is not a type"),
+                };
+            }
+            if let Some(scalar) = scalar(&name) {
+                return Ok(Ty::rigid(scalar, Vec::<Parameter>::new()));
+            }
+            if ["char", "str", "u128", "i128"].contains(&name.as_str()) {
+                bail!("the type `{name}` is not supported")
+            }
+        }
+
+        // `Self::Item` or `T::Item`
+        if let [first, item] = &path.segments.iter().collect::<Vec<_>>()[..] {
+            if first.ident == "Self" && first.arguments.is_empty() && item.arguments.is_empty() {
+                let Some((trait_id, parameters)) = &self.self_trait else {
+                    bail!("`{}` is only supported in traits and impls", tokens(path))
+                };
+                return Ok(AliasTy::associated_ty(
+                    trait_id,
+                    AssociatedItemId::new(&item.ident.to_string()),
+                    parameters,
+                )
+                .upcast());
+            }
+            if self.lookup(&first.ident.to_string()).is_some() {
+                bail!(
+                    "ambiguous associated type `{}`, write `<{} as Trait>::{}` instead",
+                    tokens(path),
+                    first.ident,
+                    item.ident
+                )
+            }
+        }
+
+        let (name, args) = self.path(path)?;
+        let parameters = args
+            .into_iter()
+            .map(|arg| self.parameter(arg))
+            .collect::<Fallible<Vec<Parameter>>>()?;
+        let is_type_alias = [name.clone(), format!("{}{name}", self.module)]
+            .iter()
+            .any(|n| self.type_aliases.contains(n));
+        if is_type_alias {
+            Ok(AliasTy::type_alias(TypeAliasId::new(&name), parameters).upcast())
+        } else {
+            Ok(Ty::rigid(AdtId::new(&name), parameters))
+        }
+    }
+}
+
+/// Adds the bound `T: 'a` for each reference `&'a T` within `ty` to `where_clauses`.
+/// rustc implies these bounds for the types in the signature of a fn or the header of
+/// an impl, and infers them for the fields of ADTs, but formality needs them written out.
+fn add_implied_bounds(where_clauses: &mut Vec<WhereClause>, ty: &Ty) {
+    if let Some((_, lt, referent)) = ty.as_ref_ty() {
+        let bound = outlives(referent, lt);
+        if !where_clauses.contains(&bound) {
+            where_clauses.push(bound);
+        }
+    }
+    match ty.data() {
+        // The lifetimes within a fn pointer type are its own.
+        TyData::RigidTy(RigidTy {
+            name: RigidName::FnPtr(..),
+            ..
+        }) => {}
+        TyData::RigidTy(RigidTy { parameters, .. }) => {
+            for parameter in parameters {
+                if let Parameter::Ty(ty) = parameter {
+                    add_implied_bounds(where_clauses, ty);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Gives the lifetimes elided in the inputs of a fn, or in the header of an impl,
+/// the names of fresh lifetime parameters, which [`Self::declare_in`] declares.
+#[derive(Default)]
+struct ElidedLifetimes {
+    /// All lifetimes that the inputs mention, named or elided.
+    lifetimes: Vec<Lifetime>,
+
+    /// The names given to the elided lifetimes.
+    fresh: Vec<Lifetime>,
+}
+
+impl ElidedLifetimes {
+    fn fresh(&mut self) -> Lifetime {
+        let lt = Lifetime::new(
+            &format!("'_{}", self.fresh.len()),
+            proc_macro2::Span::call_site(),
+        );
+        self.fresh.push(lt.clone());
+        lt
+    }
+
+    fn declare_in(self, generics: &mut Generics) {
+        for lt in self.fresh {
+            generics
+                .params
+                .push(GenericParam::Lifetime(LifetimeDef::new(lt)));
+        }
+    }
+}
+
+impl VisitMut for ElidedLifetimes {
+    fn visit_lifetime_mut(&mut self, lt: &mut Lifetime) {
+        if lt.ident == "_" {
+            *lt = self.fresh();
+        }
+        self.lifetimes.push(lt.clone());
+    }
+
+    fn visit_type_reference_mut(&mut self, r: &mut syn::TypeReference) {
+        if r.lifetime.is_none() {
+            r.lifetime = Some(self.fresh());
+        }
+        visit_mut::visit_type_reference_mut(self, r);
+    }
+
+    fn visit_receiver_mut(&mut self, r: &mut syn::Receiver) {
+        if let Some((_, lt @ None)) = &mut r.reference {
+            *lt = Some(self.fresh());
+        }
+        visit_mut::visit_receiver_mut(self, r);
+    }
+
+    // Lifetimes elided in fn pointer types are bound by the fn pointer.
+    fn visit_type_bare_fn_mut(&mut self, _: &mut syn::TypeBareFn) {}
+}
+
+/// Gives the lifetimes elided in the output of a fn the lifetime `lt`: that of `self`
+/// if it is a reference, or else the only lifetime in the inputs, if there is one.
+struct OutputLifetimes {
+    lt: Option<Lifetime>,
+
+    /// Whether there was an elided lifetime but no `lt` to give it.
+    missing: bool,
+}
+
+impl OutputLifetimes {
+    fn elided(&mut self) -> Option<Lifetime> {
+        self.missing |= self.lt.is_none();
+        self.lt.clone()
+    }
+}
+
+impl VisitMut for OutputLifetimes {
+    fn visit_lifetime_mut(&mut self, lt: &mut Lifetime) {
+        if lt.ident == "_" {
+            if let Some(elided) = self.elided() {
+                *lt = elided;
+            }
+        }
+    }
+
+    fn visit_type_reference_mut(&mut self, r: &mut syn::TypeReference) {
+        if r.lifetime.is_none() {
+            r.lifetime = self.elided();
+        }
+        visit_mut::visit_type_reference_mut(self, r);
+    }
+
+    fn visit_type_bare_fn_mut(&mut self, _: &mut syn::TypeBareFn) {}
+}
+
+fn scalar(name: &str) -> Option<ScalarId> {
+    Some(match name {
+        "u8" => ScalarId::U8,
+        "u16" => ScalarId::U16,
+        "u32" => ScalarId::U32,
+        "u64" => ScalarId::U64,
+        "i8" => ScalarId::I8,
+        "i16" => ScalarId::I16,
+        "i32" => ScalarId::I32,
+        "i64" => ScalarId::I64,
+        "bool" => ScalarId::Bool,
+        "usize" => ScalarId::Usize,
+        "isize" => ScalarId::Isize,
+        "f32" => ScalarId::F32,
+        "f64" => ScalarId::F64,
+        _ => return None,
+    })
+}
+
+fn visibility(vis: &syn::Visibility) -> Visibility {
+    match vis {
+        syn::Visibility::Inherited => Visibility::Private,
+        _ => Visibility::Public,
+    }
+}
+
+fn safety(unsafety: &Option<syn::token::Unsafe>) -> Safety {
+    match unsafety {
+        Some(_) => Safety::Unsafe,
+        None => Safety::Safe,
+    }
+}
+
+fn where_clause(data: WhereClauseData) -> WhereClause {
+    WhereClause {
+        data: Arc::new(data),
+    }
+}
+
+fn outlives(a: impl Upcast<Parameter>, b: Lt) -> WhereClause {
+    where_clause(WhereClauseData::Outlives(a.upcast(), b))
+}
+
+/// `for<vars> wc`, or just `wc` if there are no `vars`.
+fn for_all(vars: &[BoundVar], wc: WhereClause) -> WhereClause {
+    if vars.is_empty() {
+        wc
+    } else {
+        where_clause(WhereClauseData::ForAll(Binder::new(vars, wc)))
+    }
+}
+
+/// The header of an impl, to name it in errors.
+fn header(i: &syn::ItemImpl) -> String {
+    let self_ty = tokens(&i.self_ty);
+    match &i.trait_ {
+        Some((bang, path, _)) => format!(
+            "impl {}{} for {self_ty}",
+            if bang.is_some() { "!" } else { "" },
+            tokens(path)
+        ),
+        None => format!("impl {self_ty}"),
+    }
+}
+
+fn tokens(t: &impl ToTokens) -> String {
+    t.to_token_stream().to_string()
+}
@@ -0,0 +1,154 @@
+//! What the server knows about a document, computed from its text alone.
+//!
+//! Positions within the text are byte offsets, as in the [`Span`]s of the parser;
+//! the server converts them to and from the positions of the protocol.
+
+use formality_prove::{elaborate, Decls};
+use formality_rust::{
+    grammar::{CrateItem, Program},
+    prove::ToWcs,
+};
+use formality_types::{
+    collections::Set,
+    grammar::{Binder, Wcs},
+    parse::{try_term_with_spans, Span, Spans, SyntaxError},
+    pretty::to_string,
+};
+
+/// The item keywords that introduce the names [`Analysis::definition`] knows about.
+const KEYWORDS: &[(&str, &str)] = &[("Struct", "struct"), ("Enum", "enum"), ("Trait", "trait")];
+
+pub struct Analysis<'t> {
+    text: &'t str,
+    parsed: Result<(Program, Spans), Vec<SyntaxError>>,
+}
+
+impl<'t> Analysis<'t> {
+    pub fn new(text: &'t str) -> Self {
+        Analysis {
+            text,
+            parsed: try_term_with_spans(text),
+        }
+    }
+
+    /// The syntax errors in the text.
+    pub fn syntax_errors(&self) -> &[SyntaxError] {
+        match &self.parsed {
+            Ok(_) => &[],
+            Err(errors) => errors,
+        }
+    }
+
+    /// The spans of the names of the ADTs and traits named by the word at `offset`.
+    pub fn definition(&self, offset: usize) -> Vec<Span> {
+        let Some(name) = word_at(self.text, offset) else {
+            return vec![];
+        };
+        self.definitions()
+            .into_iter()
+            .filter(|(n, _)| *n == name)
+            .map(|(_, span)| span)
+            .collect()
+    }
+
+    /// For the ADT or trait named by the word at `offset`, its where-clauses together
+    /// with those they imply, e.g., the supertraits of its bounds (see [`elaborate`]).
+    pub fn hover(&self, offset: usize) -> Option<String> {
+        let name = word_at(self.text, offset)?;
+        let (program, _) = self.parsed.as_ref().ok()?;
+        let program = program
+            .resolve()
+            .and_then(|p| p.desugar_effects())
+            .and_then(|p| p.add_implicit_sized_bounds())
+            .ok()?;
+        let decls = program.to_prove_decls();
+        let (header, where_clauses) = program
+            .items_from_all_crates()
+            .find_map(|item| where_clauses(&decls, item, name))?;
+        Some(format!(
+            "```\n{header}\n```\nwhere-clauses, elaborated: `{}`",
+            to_string(&where_clauses)
+        ))
+    }
+
+    /// The ADTs and traits defined in the text, with the spans of their names.
+    fn definitions(&self) -> Set<(&'t str, Span)> {
+        let Ok((_, spans)) = &self.parsed else {
+            return Set::new();
+        };
+        KEYWORDS
+            .iter()
+            .flat_map(|&(type_name, keyword)| {
+                spans
+                    .of(type_name)
+                    .filter_map(move |span| name_after(self.text, span, keyword))
+            })
+            .collect()
+    }
+}
+
+/// If `item` is the ADT or trait `name` (or `m::name`, after resolution), a header
+/// describing it and its elaborated where-clauses, under the binder of its generics.
+fn where_clauses(decls: &Decls, item: &CrateItem, name: &str) -> Option<(String, Binder<Wcs>)> {
+    let is_named = |id: String| id == name || id.ends_with(&format!("::{name}"));
+    let (header, vars, wcs) = match item {
+        CrateItem::Struct(s) if is_named(format!("{:?}", s.id)) => {
+            let (vars, data) = s.to_adt().binder.open();
+            (
+                format!("struct {:?}", s.id),
+                vars,
+                data.where_clauses.to_wcs(),
+            )
+        }
+        CrateItem::Enum(e) if is_named(format!("{:?}", e.id)) => {
+            let (vars, data) = e.to_adt().binder.open();
+            (
+                format!("enum {:?}", e.id),
+                vars,
+                data.where_clauses.to_wcs(),
+            )
+        }
+        CrateItem::Trait(t) if is_named(format!("{:?}", t.id)) => {
+            let decl = decls.trait_decls().iter().find(|d| d.id == t.id)?;
+            let (vars, data) = decl.binder.open();
+            (format!("trait {:?}", t.id), vars, data.where_clause)
+        }
+        _ => return None,
+    };
+    Some((header, Binder::new(vars, elaborate(decls, wcs))))
+}
+
+/// The name after `keyword` in the item at `span`, with the span of that name.
+fn name_after<'t>(text: &'t str, span: Span, keyword: &str) -> Option<(&'t str, Span)> {
+    let mut item_words = words(&text[span.start..span.end]);
+    item_words.find(|&(_, w)| w == keyword)?;
+    let (start, name) = item_words.next()?;
+    let start = span.start + start;
+    Some((
+        name,
+        Span {
+            start,
+            end: start + name.len(),
+        },
+    ))
+}
+
+/// The identifier that `offset` is in or right after.
+fn word_at(text: &str, offset: usize) -> Option<&str> {
+    words(text)
+        .find(|&(start, w)| start <= offset && offset <= start + w.len())
+        .map(|(_, w)| w)
+}
+
+/// The identifiers in `text`, with their offsets.
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    text.char_indices()
+        .filter(move |&(i, c)| is_word(c) && !text[..i].ends_with(is_word))
+        .map(move |(start, _)| {
+            let len = text[start..]
+                .find(|c| !is_word(c))
+                .unwrap_or(text.len() - start);
+            (start, &text[start..start + len])
+        })
+}
@@ -0,0 +1,197 @@
+//! A language server for programs written in the formality grammar. It reports syntax
+//! errors, goes to the definitions of ADTs and traits, and shows the elaborated
+//! where-clauses of an ADT or trait on hover. Documents are analyzed from scratch on
+//! every change, see [`analysis`].
+
+use std::collections::HashMap;
+
+use anyhow::bail;
+use formality_types::{fixed_point, parse::Span};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{GotoDefinition, HoverRequest, Request as _},
+    Diagnostic, DiagnosticSeverity, GotoDefinitionParams, GotoDefinitionResponse, Hover,
+    HoverContents, HoverParams, HoverProviderCapability, Location, MarkupContent, MarkupKind,
+    OneOf, Position, PublishDiagnosticsParams, Range, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+
+use crate::analysis::Analysis;
+
+pub mod analysis;
+
+/// Serves the client at the other end of `connection` until it shuts the server down.
+pub fn run(connection: &Connection) -> anyhow::Result<()> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        ..ServerCapabilities::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut documents: HashMap<Url, String> = HashMap::new();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                let response = respond(&documents, request);
+                // Each request analyses the document afresh, with decls of its own, so
+                // the memoized outputs of the judgments are of no use to the next one.
+                fixed_point::clear_memo_tables();
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                let uri = match notification.method.as_str() {
+                    DidOpenTextDocument::METHOD => {
+                        let params = notification.extract::<lsp_types::DidOpenTextDocumentParams>(
+                            DidOpenTextDocument::METHOD,
+                        )?;
+                        documents
+                            .insert(params.text_document.uri.clone(), params.text_document.text);
+                        params.text_document.uri
+                    }
+                    DidChangeTextDocument::METHOD => {
+                        let mut params = notification
+                            .extract::<lsp_types::DidChangeTextDocumentParams>(
+                                DidChangeTextDocument::METHOD,
+                            )?;
+                        // With full sync, the last change has the whole text.
+                        let Some(change) = params.content_changes.pop() else {
+                            continue;
+                        };
+                        documents.insert(params.text_document.uri.clone(), change.text);
+                        params.text_document.uri
+                    }
+                    DidCloseTextDocument::METHOD => {
+                        let params = notification
+                            .extract::<lsp_types::DidCloseTextDocumentParams>(
+                                DidCloseTextDocument::METHOD,
+                            )?;
+                        documents.remove(&params.text_document.uri);
+                        params.text_document.uri
+                    }
+                    _ => continue,
+                };
+                let diagnostics = match documents.get(&uri) {
+                    Some(text) => diagnostics(text),
+                    None => vec![],
+                };
+                let params = PublishDiagnosticsParams {
+                    uri,
+                    diagnostics,
+                    version: None,
+                };
+                connection
+                    .sender
+                    .send(Message::Notification(Notification::new(
+                        PublishDiagnostics::METHOD.to_string(),
+                        params,
+                    )))?;
+            }
+            Message::Response(_) => {}
+        }
+    }
+    bail!("the client closed the connection without shutting down the server")
+}
+
+fn respond(documents: &HashMap<Url, String>, request: Request) -> Response {
+    let id = request.id.clone();
+    let result = match request.method.as_str() {
+        GotoDefinition::METHOD => request
+            .extract::<GotoDefinitionParams>(GotoDefinition::METHOD)
+            .map(|(_, params)| {
+                let position = params.text_document_position_params;
+                let uri = position.text_document.uri;
+                let locations: Vec<Location> = match documents.get(&uri) {
+                    Some(text) => Analysis::new(text)
+                        .definition(offset(text, position.position))
+                        .into_iter()
+                        .map(|span| Location::new(uri.clone(), range(text, span)))
+                        .collect(),
+                    None => vec![],
+                };
+                serde_json::to_value(GotoDefinitionResponse::Array(locations)).unwrap()
+            }),
+        HoverRequest::METHOD => {
+            request
+                .extract::<HoverParams>(HoverRequest::METHOD)
+                .map(|(_, params)| {
+                    let position = params.text_document_position_params;
+                    let hover = documents.get(&position.text_document.uri).and_then(|text| {
+                        let value = Analysis::new(text).hover(offset(text, position.position))?;
+                        Some(Hover {
+                            contents: HoverContents::Markup(MarkupContent {
+                                kind: MarkupKind::Markdown,
+                                value,
+                            }),
+                            range: None,
+                        })
+                    });
+                    serde_json::to_value(hover).unwrap()
+                })
+        }
+        method => {
+            return Response::new_err(
+                id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request `{method}`"),
+            )
+        }
+    };
+    match result {
+        Ok(result) => Response::new_ok(id, result),
+        Err(e) => Response::new_err(id, ErrorCode::InvalidParams as i32, format!("{e:?}")),
+    }
+}
+
+/// The syntax errors of `text` as diagnostics.
+pub fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    Analysis::new(text)
+        .syntax_errors()
+        .iter()
+        .map(|e| Diagnostic {
+            range: range(text, e.span),
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some("formality".to_string()),
+            message: e.message.clone(),
+            ..Diagnostic::default()
+        })
+        .collect()
+}
+
+/// The byte offset in `text` of `position`, whose character counts UTF-16 code units.
+fn offset(text: &str, position: Position) -> usize {
+    let line_start: usize = text
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum();
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= position.character as usize || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// The position in `text` of the byte offset `offset`.
+fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    Position::new(line as u32, character as u32)
+}
+
+fn range(text: &str, span: Span) -> Range {
+    Range::new(position(text, span.start), position(text, span.end))
+}
@@ -1,8 +1,29 @@
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{Attribute, Type};
+use syn::{parse::ParseStream, punctuated::Punctuated, Attribute, Token, Type};
 use synstructure::VariantInfo;
 
+/// `#[derive(Cast)]`: the casts of a type that is not a term, between the type and
+/// itself, the fields of its `#[cast]` variants, and the types of its `#[cast(..)]`
+/// chains (see [`via_impls`]).
+pub(crate) fn derive_cast(s: synstructure::Structure) -> TokenStream {
+    let variants: &[VariantInfo] = match s.ast().data {
+        syn::Data::Enum(_) => s.variants(),
+        syn::Data::Struct(_) | syn::Data::Union(_) => &[],
+    };
+    let via_impls = match via_impls(&s) {
+        Ok(impls) => impls,
+        Err(e) => return e.into_compile_error(),
+    };
+    variants
+        .iter()
+        .filter(|v| has_cast_attr(v.ast().attrs))
+        .flat_map(|v| [upcast_to_variant(&s, v), downcast_to_variant(&s, v)])
+        .chain([self_upcast(&s), self_downcast(&s)])
+        .chain(via_impls)
+        .collect()
+}
+
 pub(crate) fn upcast_impls(s: synstructure::Structure) -> Vec<TokenStream> {
     let num_variants = s.variants().len();
     s.variants()
@@ -90,6 +111,70 @@ fn downcast_to_variant(s: &synstructure::Structure, v: &VariantInfo) -> TokenStr
     })
 }
 
+/// Whether `attrs` has a `#[cast]`, without arguments, for a variant.
 pub(crate) fn has_cast_attr(attrs: &[Attribute]) -> bool {
-    attrs.iter().any(|a| a.path.is_ident("cast"))
+    attrs
+        .iter()
+        .any(|a| a.path.is_ident("cast") && a.tokens.is_empty())
+}
+
+/// A chain `A <: B <: C` of a `#[cast(..)]` on a type `T`: each type upcasts to the
+/// next, and the last one to `T`.
+struct CastChain {
+    tys: Vec<syn::Path>,
+}
+
+impl syn::parse::Parse for CastChain {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // Types are paths without generic arguments, as `<:` would be taken for them.
+        let mut tys = vec![input.call(syn::Path::parse_mod_style)?];
+        while input.peek(Token![<]) && input.peek2(Token![:]) {
+            input.parse::<Token![<]>()?;
+            input.parse::<Token![:]>()?;
+            tys.push(input.call(syn::Path::parse_mod_style)?);
+        }
+        if tys.len() < 2 {
+            return Err(input.error("expected `<:` and the type to upcast to"));
+        }
+        Ok(CastChain { tys })
+    }
+}
+
+/// The casts of the chains in the `#[cast(..)]` on the type `s` itself. For
+/// `#[cast(A <: B <: C)]` on `T`, `A` upcasts to `T` by way of `B` and `C`, and `T`
+/// downcasts to `A` by way of `C` and `B`.
+pub(crate) fn via_impls(s: &synstructure::Structure) -> syn::Result<Vec<TokenStream>> {
+    let mut impls = vec![];
+    for attr in &s.ast().attrs {
+        if !attr.path.is_ident("cast") || attr.tokens.is_empty() {
+            continue;
+        }
+        let chains = attr.parse_args_with(Punctuated::<CastChain, Token![,]>::parse_terminated)?;
+        for CastChain { tys } in chains {
+            let (bottom, mids) = tys.split_first().unwrap();
+            let rev_mids = mids.iter().rev();
+            impls.push(s.gen_impl(quote! {
+                use crate::derive_links::{Upcast, UpcastFrom};
+
+                gen impl UpcastFrom<#bottom> for @Self {
+                    fn upcast_from(term: #bottom) -> Self {
+                        #(let term: #mids = Upcast::upcast(term);)*
+                        Upcast::upcast(term)
+                    }
+                }
+            }));
+            impls.push(s.gen_impl(quote! {
+                use crate::derive_links::{DowncastFrom, DowncastTo};
+
+                gen impl DowncastTo<#bottom> for @Self {
+                    fn downcast_to(&self) -> Option<#bottom> {
+                        let term: &Self = self;
+                        #(let term: &#rev_mids = &DowncastFrom::downcast_from(term)?;)*
+                        DowncastFrom::downcast_from(term)
+                    }
+                }
+            }));
+        }
+    }
+    Ok(impls)
 }
@@ -119,6 +119,11 @@ fn debug_variant_with_attr(
 
     stream.extend(quote!(let mut sep = "";));
 
+    let operand_levels = match crate::precedence::operand_levels(variant, spec) {
+        Ok(levels) => levels,
+        Err(err) => return err.into_compile_error(),
+    };
+
     let mut prev_op: Option<&FormalitySpecOp> = None;
     for op in &spec.ops {
         // insert whitespace if needed
@@ -150,14 +155,25 @@ fn debug_variant_with_attr(
             spec::FormalitySpecOp::Field {
                 name,
                 mode: FieldMode::Single,
-            } => {
-                quote_spanned! {
+            } => match operand_levels.get(&name.to_string()) {
+                // An operand that binds less tightly than required goes in parentheses.
+                Some(level) => quote_spanned! {
+                    name.span() =>
+                        write!(fmt, "{}", sep)?;
+                        if #name.__precedence() < #level {
+                            write!(fmt, "({:?})", #name)?;
+                        } else {
+                            write!(fmt, "{:?}", #name)?;
+                        }
+                        sep = " ";
+                },
+                None => quote_spanned! {
                     name.span() =>
                         write!(fmt, "{}", sep)?;
                         write!(fmt, "{:?}", #name)?;
                         sep = " ";
-                }
-            }
+                },
+            },
 
             spec::FormalitySpecOp::Field {
                 name,
@@ -236,7 +252,7 @@ fn debug_variant_with_attr(
     stream
 }
 
-fn get_grammar_attr(attrs: &[Attribute]) -> Option<syn::Result<FormalitySpec>> {
+pub(crate) fn get_grammar_attr(attrs: &[Attribute]) -> Option<syn::Result<FormalitySpec>> {
     let attr = attrs.iter().find(|a| a.path.is_ident("grammar"))?;
     Some(attr.parse_args())
 }
@@ -248,11 +264,11 @@ fn as_literal(ident: &Ident) -> Literal {
 /// Convert a name like `Foo` into the name we expect to parse (`foo`).
 ///
 /// Ideally we'd do `snake_case` conversion but I can't figure out best library for that.
-fn to_parse_ident(ident: &Ident) -> String {
+pub(crate) fn to_parse_ident(ident: &Ident) -> String {
     ident.to_string().to_case(Case::Snake)
 }
 
-fn field_ident(field: &syn::Field, index: usize) -> syn::Ident {
+pub(crate) fn field_ident(field: &syn::Field, index: usize) -> syn::Ident {
     match &field.ident {
         Some(field_name) => field_name.clone(),
         None => syn::Ident::new(&format!("v{}", index), field.span()),
@@ -0,0 +1,30 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Derive `Generate`, which picks one of the variants at random and generates its fields
+/// one level deeper.
+pub(crate) fn derive_generate(s: synstructure::Structure) -> TokenStream {
+    if let syn::Data::Union(v) = &s.ast().data {
+        return syn::Error::new(v.union_token.span, "unions are not supported")
+            .into_compile_error();
+    }
+
+    let num_variants = s.variants().len();
+    let arms = s.variants().iter().zip(0_usize..).map(|(v, i)| {
+        let construct = v.construct(|_, _| quote!(Generate::generate(g)));
+        quote!(#i => g.nested(|g| #construct),)
+    });
+
+    s.gen_impl(quote! {
+        use crate::derive_links::generate::{Generate, Generator};
+
+        gen impl Generate for @Self {
+            fn generate(g: &mut Generator) -> Self {
+                match g.variant(#num_variants) {
+                    #(#arms)*
+                    _ => unreachable!(),
+                }
+            }
+        }
+    })
+}
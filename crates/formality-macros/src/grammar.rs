@@ -0,0 +1,333 @@
+//! The grammar table of a term: one production per variant, as the generated parser
+//! parses it, so that conflicts between the productions can be reported when the term
+//! is declared rather than when some input happens to hit them.
+//!
+//! Only the productions of one type are known to the macro, so the checks are limited
+//! to what can be decided from those:
+//!
+//! * two variants with the same production, or where one production is a prefix of
+//!   another, are ambiguous: the parser tries every variant and panics if more than
+//!   one succeeds;
+//! * a production that starts with the type itself is left-recursive: the parser
+//!   would recurse without consuming any input, unless the variant has a precedence
+//!   (see [`crate::precedence`]), in which case the production is parsed after the
+//!   operand, and only conflicts with the productions doing the same;
+//! * with precedences, a production that ends with the type itself needs a precedence
+//!   as well, and the parentheses around a term are one more production.
+
+use std::fmt;
+
+use convert_case::{Case, Casing};
+use quote::ToTokens;
+
+use crate::{
+    precedence::{get_precedence_attr, has_precedence},
+    spec::{FieldMode, FormalitySpec, FormalitySpecOp},
+};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Clone, PartialEq, Eq)]
+enum Symbol {
+    Keyword(String),
+    Char(char),
+    /// A field, with its name as written in the grammar and its type, which is what
+    /// matters for conflicts.
+    Field {
+        name: String,
+        mode: FieldModeKind,
+        ty: String,
+    },
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum FieldModeKind {
+    Single,
+    Many,
+    Comma,
+}
+
+impl Symbol {
+    /// Whether parsing the symbol starts with parsing `type_name`.
+    fn refers_to(&self, type_name: &syn::Ident) -> bool {
+        match self {
+            Symbol::Field { mode, ty, .. } => {
+                refers_to(ty, type_name, *mode != FieldModeKind::Single)
+            }
+            Symbol::Keyword(_) | Symbol::Char(_) => false,
+        }
+    }
+
+    fn same_as(&self, other: &Symbol) -> bool {
+        match (self, other) {
+            (
+                Symbol::Field { mode, ty, .. },
+                Symbol::Field {
+                    mode: other_mode,
+                    ty: other_ty,
+                    ..
+                },
+            ) => mode == other_mode && ty == other_ty,
+            _ => self == other,
+        }
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Symbol::Keyword(k) => write!(f, "{k}"),
+            Symbol::Char(c) => write!(f, "{c}"),
+            Symbol::Field { name, mode, .. } => match mode {
+                FieldModeKind::Single => write!(f, "${name}"),
+                FieldModeKind::Many => write!(f, "$*{name}"),
+                FieldModeKind::Comma => write!(f, "$,{name}"),
+            },
+        }
+    }
+}
+
+struct Production {
+    /// The name of the variant, or `parentheses` for the parentheses around a term.
+    variant: String,
+    span: proc_macro2::Span,
+    symbols: Vec<Symbol>,
+    precedence: bool,
+}
+
+impl Production {
+    /// Whether the production starts with the type `type_name` itself.
+    fn left_recursive(&self, type_name: &syn::Ident) -> bool {
+        self.symbols.first().is_some_and(|s| s.refers_to(type_name))
+    }
+
+    /// Whether the production ends with the type `type_name` itself.
+    fn right_recursive(&self, type_name: &syn::Ident) -> bool {
+        self.symbols.last().is_some_and(|s| s.refers_to(type_name))
+    }
+}
+
+impl fmt::Display for Production {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbols: Vec<String> = self.symbols.iter().map(|s| s.to_string()).collect();
+        write!(f, "`{}` (`{}`)", self.variant, symbols.join(" "))
+    }
+}
+
+/// Reports the conflicts in the grammar of the term `s` (see the module documentation),
+/// whose grammar is `external_spec` if it was given to `#[term]`.
+pub(crate) fn check_grammar(
+    s: &synstructure::Structure,
+    external_spec: Option<&FormalitySpec>,
+) -> syn::Result<()> {
+    let type_name = &s.ast().ident;
+    let mut productions = vec![];
+    for variant in s.variants() {
+        productions.push(production(variant, external_spec)?);
+    }
+    let precedence = has_precedence(s);
+    if precedence {
+        productions.push(Production {
+            variant: "parentheses".to_string(),
+            span: type_name.span(),
+            symbols: vec![
+                Symbol::Char('('),
+                Symbol::Field {
+                    name: "self".to_string(),
+                    mode: FieldModeKind::Single,
+                    ty: type_name.to_string(),
+                },
+                Symbol::Char(')'),
+            ],
+            precedence: false,
+        });
+    }
+
+    let mut errors: Option<syn::Error> = None;
+    let mut report = |span: proc_macro2::Span, message: String| {
+        let error = syn::Error::new(span, message);
+        match &mut errors {
+            Some(errors) => errors.combine(error),
+            None => errors = Some(error),
+        }
+    };
+
+    for production in &productions {
+        if production.precedence {
+            continue;
+        }
+        if production.left_recursive(type_name) {
+            report(
+                production.span,
+                format!("left-recursive grammar for `{type_name}`: {production} starts with `{type_name}` itself"),
+            );
+        } else if precedence && production.right_recursive(type_name) {
+            report(
+                production.span,
+                format!("grammar for `{type_name}` with precedences: {production} ends with `{type_name}` itself but has no `#[precedence]`"),
+            );
+        }
+    }
+
+    for (i, a) in productions.iter().enumerate() {
+        for b in &productions[i + 1..] {
+            if a.left_recursive(type_name) != b.left_recursive(type_name) {
+                continue;
+            }
+            let common = a
+                .symbols
+                .iter()
+                .zip(&b.symbols)
+                .take_while(|(a, b)| a.same_as(b))
+                .count();
+            if common == a.symbols.len() && common == b.symbols.len() {
+                report(
+                    b.span,
+                    format!("ambiguous grammar for `{type_name}`: {b} is the same as {a}"),
+                );
+            } else if common == a.symbols.len() || common == b.symbols.len() {
+                let (prefix, longer) = if common == a.symbols.len() {
+                    (a, b)
+                } else {
+                    (b, a)
+                };
+                report(
+                    b.span,
+                    format!(
+                        "ambiguous grammar for `{type_name}`: {prefix} is a prefix of {longer}"
+                    ),
+                );
+            }
+        }
+    }
+
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(()),
+    }
+}
+
+/// The production of `variant`, following `parse_variant`.
+fn production(
+    variant: &synstructure::VariantInfo,
+    external_spec: Option<&FormalitySpec>,
+) -> syn::Result<Production> {
+    let ast = variant.ast();
+    let fields: Vec<(String, String)> = variant
+        .bindings()
+        .iter()
+        .zip(0..)
+        .map(|(b, index)| {
+            let name = match &b.ast().ident {
+                Some(name) => name.to_string(),
+                None => format!("v{index}"),
+            };
+            (name, normalize(&b.ast().ty))
+        })
+        .collect();
+    let field = |name: String, mode: FieldModeKind| {
+        let ty = fields
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, ty)| ty.clone())
+            .unwrap_or_default();
+        Symbol::Field { name, mode, ty }
+    };
+
+    let attr_spec = match external_spec {
+        Some(_) => None,
+        None => ast
+            .attrs
+            .iter()
+            .find(|a| a.path.is_ident("grammar"))
+            .map(|attr| attr.parse_args::<FormalitySpec>())
+            .transpose()?,
+    };
+    let spec = external_spec.or(attr_spec.as_ref());
+
+    let mut symbols = vec![];
+    if let Some(spec) = spec {
+        for op in &spec.ops {
+            symbols.push(match op {
+                FormalitySpecOp::Field { name, mode } => {
+                    let mode = match mode {
+                        FieldMode::Single => FieldModeKind::Single,
+                        FieldMode::Many => FieldModeKind::Many,
+                        FieldMode::Comma => FieldModeKind::Comma,
+                    };
+                    field(name.to_string(), mode)
+                }
+                FormalitySpecOp::Keyword { ident } => Symbol::Keyword(ident.to_string()),
+                FormalitySpecOp::Char { punct } => Symbol::Char(punct.as_char()),
+                FormalitySpecOp::Delimeter { text } => Symbol::Char(*text),
+            });
+        }
+    } else if fields.is_empty() {
+        symbols.push(Symbol::Keyword(ast.ident.to_string().to_case(Case::Snake)));
+    } else {
+        let cast = crate::cast::has_cast_attr(ast.attrs);
+        if !cast {
+            symbols.push(Symbol::Keyword(ast.ident.to_string().to_case(Case::Snake)));
+            symbols.push(Symbol::Char('('));
+        }
+        for (index, (name, _)) in fields.iter().enumerate() {
+            if index > 0 {
+                symbols.push(Symbol::Char(','));
+            }
+            symbols.push(field(name.clone(), FieldModeKind::Single));
+        }
+        if !cast {
+            symbols.push(Symbol::Char(')'));
+        }
+    }
+
+    Ok(Production {
+        variant: ast.ident.to_string(),
+        span: ast.ident.span(),
+        symbols,
+        precedence: get_precedence_attr(ast.attrs).transpose()?.is_some(),
+    })
+}
+
+/// The type as written, without spaces, for comparing types. The `Arc`s and `Box`es
+/// around it are left out, as they parse as what they contain.
+pub(crate) fn normalize(ty: &syn::Type) -> String {
+    let mut ty: String = ty
+        .to_token_stream()
+        .to_string()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    while let Some(inner) = ["Arc<", "Box<"]
+        .iter()
+        .find_map(|wrapper| ty.strip_prefix(wrapper)?.strip_suffix('>'))
+    {
+        ty = inner.to_string();
+    }
+    ty
+}
+
+/// Whether parsing `ty` starts with parsing `type_name`, e.g., for `Arc<Ty>` and `Ty`,
+/// or with `many`, as for the fields parsed with `$*` or `$,`, for `Vec<Ty>` as well.
+pub(crate) fn refers_to(ty: &str, type_name: &syn::Ident, many: bool) -> bool {
+    let type_name = type_name.to_string();
+    let wrappers: &[&str] = if many {
+        &["Arc<", "Box<", "Vec<"]
+    } else {
+        &["Arc<", "Box<"]
+    };
+    let mut ty = ty;
+    loop {
+        if ty == type_name || ty == "Self" {
+            return true;
+        }
+        match wrappers
+            .iter()
+            .find_map(|wrapper| ty.strip_prefix(wrapper)?.strip_suffix('>'))
+        {
+            Some(inner) => ty = inner,
+            None => return false,
+        }
+    }
+}
@@ -0,0 +1,114 @@
+#![cfg(test)]
+
+use syn::DeriveInput;
+
+use super::check_grammar;
+
+fn check(input: DeriveInput) -> String {
+    match check_grammar(&synstructure::Structure::new(&input), None) {
+        Ok(()) => "ok".to_string(),
+        Err(errors) => errors
+            .into_iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+#[test]
+fn unambiguous() {
+    expect_test::expect!["ok"].assert_eq(&check(syn::parse_quote! {
+        enum Expr {
+            #[grammar(($v0 + $v1))]
+            Add(Var, Arc<Expr>),
+            #[grammar(- $v0)]
+            Neg(Arc<Expr>),
+            #[cast]
+            Var(Var),
+            Unit,
+        }
+    }));
+}
+
+#[test]
+fn same_production() {
+    expect_test::expect![
+        "ambiguous grammar for `Expr`: `Sub` (`$v0 - $v1`) is the same as `Minus` (`$v0 - $v1`)"
+    ]
+    .assert_eq(&check(syn::parse_quote! {
+        enum Expr {
+            #[grammar($v0 - $v1)]
+            Minus(Var, Var),
+            #[grammar($v0 - $v1)]
+            Sub(Var, Var),
+        }
+    }));
+}
+
+#[test]
+fn prefix() {
+    expect_test::expect![
+        "ambiguous grammar for `Expr`: `Var` (`$v0`) is a prefix of `Call` (`$v0 ( $,v1 )`)"
+    ]
+    .assert_eq(&check(syn::parse_quote! {
+        enum Expr {
+            #[grammar($v0 ( $,v1 ))]
+            Call(Var, Vec<Var>),
+            #[cast]
+            Var(Var),
+        }
+    }));
+}
+
+#[test]
+fn left_recursive() {
+    expect_test::expect![
+        "left-recursive grammar for `Expr`: `Add` (`$v0 + $v1`) starts with `Expr` itself"
+    ]
+    .assert_eq(&check(syn::parse_quote! {
+        enum Expr {
+            #[grammar($v0 + $v1)]
+            Add(Arc<Expr>, Var),
+            #[cast]
+            Var(Var),
+        }
+    }));
+}
+
+#[test]
+fn precedence() {
+    expect_test::expect!["ok"].assert_eq(&check(syn::parse_quote! {
+        enum Expr {
+            #[grammar($v0 + $v1)]
+            #[precedence(1)]
+            Add(Arc<Expr>, Arc<Expr>),
+            #[grammar($v0 * $v1)]
+            #[precedence(2)]
+            Mul(Arc<Expr>, Arc<Expr>),
+            #[grammar(- $v0)]
+            #[precedence(3)]
+            Neg(Arc<Expr>),
+            #[cast]
+            Var(Var),
+        }
+    }));
+}
+
+#[test]
+fn precedence_conflicts() {
+    expect_test::expect![[r#"
+        grammar for `Expr` with precedences: `Neg` (`- $v0`) ends with `Expr` itself but has no `#[precedence]`
+        ambiguous grammar for `Expr`: `parentheses` (`( $self )`) is the same as `Paren` (`( $v0 )`)"#]].assert_eq(&check(syn::parse_quote! {
+        enum Expr {
+            #[grammar($v0 + $v1)]
+            #[precedence(1)]
+            Add(Arc<Expr>, Arc<Expr>),
+            #[grammar(- $v0)]
+            Neg(Arc<Expr>),
+            #[grammar(($v0))]
+            Paren(Arc<Expr>),
+            #[cast]
+            Var(Var),
+        }
+    }));
+}
@@ -9,7 +9,11 @@ mod cast;
 mod debug;
 mod fixed_point;
 mod fold;
+mod generate;
+mod grammar;
 mod parse;
+mod precedence;
+mod pretty;
 mod spec;
 mod term;
 mod test;
@@ -34,6 +38,7 @@ pub fn term(args: TokenStream, input: TokenStream) -> TokenStream {
 }
 
 synstructure::decl_derive!([Visit] => visit::derive_visit);
+synstructure::decl_derive!([Cast, attributes(cast)] => cast::derive_cast);
 
 #[proc_macro_attribute]
 pub fn fixed_point(args: TokenStream, input: TokenStream) -> TokenStream {
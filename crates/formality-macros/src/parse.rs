@@ -5,13 +5,17 @@ use quote::{quote, quote_spanned};
 use syn::{spanned::Spanned, Attribute};
 use synstructure::BindingInfo;
 
-use crate::spec::{self, FieldMode, FormalitySpec, FormalitySpecOp};
+use crate::{
+    precedence::{self, get_precedence_attr, has_precedence, is_left_recursive, Precedence},
+    spec::{self, FieldMode, FormalitySpec, FormalitySpecOp},
+};
 
 /// Derive the `Parse` impl, using an optional grammar supplied "from the outside".
 /// This is used by the `#[term(G)]` macro, which supplies the grammar `G`.
 pub(crate) fn derive_parse_with_spec(
     s: synstructure::Structure,
     external_spec: Option<&FormalitySpec>,
+    recovery_point: bool,
 ) -> syn::Result<TokenStream> {
     if let syn::Data::Union(v) = &s.ast().data {
         return Err(syn::Error::new(
@@ -24,7 +28,10 @@ pub(crate) fn derive_parse_with_spec(
 
     let type_name = Literal::string(&format!("`{}`", s.ast().ident));
 
-    if s.variants().len() == 1 {
+    let precedence = has_precedence(&s);
+    if precedence {
+        stream.extend(parse_with_precedence(&s, &type_name)?);
+    } else if s.variants().len() == 1 {
         stream.extend(parse_variant(&s.variants()[0], external_spec));
     } else {
         stream.extend(quote! {
@@ -45,15 +52,40 @@ pub(crate) fn derive_parse_with_spec(
     }
 
     let type_name = as_literal(&s.ast().ident);
+    let signature = if precedence {
+        quote! {
+            fn parse<'t>(scope: &parse::Scope, text: &'t str) -> parse::ParseResult<'t, Self>
+            {
+                Self::parse_with_precedence(scope, text, 0)
+            }
+
+            fn parse_with_precedence<'t>(
+                scope: &parse::Scope,
+                text: &'t str,
+                __precedence: usize,
+            ) -> parse::ParseResult<'t, Self>
+        }
+    } else {
+        quote! {
+            fn parse<'t>(scope: &parse::Scope, text: &'t str) -> parse::ParseResult<'t, Self>
+        }
+    };
     Ok(s.gen_impl(quote! {
         use crate::derive_links::{parse};
 
         gen impl parse::Parse for @Self {
-            fn parse<'t>(scope: &parse::Scope, text: &'t str) -> parse::ParseResult<'t, Self>
+            const RECOVERY_POINT: bool = #recovery_point;
+
+            #signature
             {
                 let __span = tracing::span!(tracing::Level::TRACE, "parse", type_name = #type_name, ?scope, ?text);
                 let __guard = __span.enter();
+                let __recovered = scope.recovered();
                 let __result = { #stream };
+                match &__result {
+                    Ok((_, __remainder)) => scope.record_span(#type_name, text, __remainder),
+                    Err(_) => scope.discard_recovered(__recovered),
+                }
                 tracing::trace!("result = {:?}", __result);
                 __result
             }
@@ -61,6 +93,72 @@ pub(crate) fn derive_parse_with_spec(
     }))
 }
 
+/// The body of `parse_with_precedence` for an enum with precedences (see
+/// [`crate::precedence`]): parses one of the variants that do not start with an operand,
+/// or a term in parentheses, and then, as long as one does, one of the variants that
+/// start with an operand, with what was parsed so far as the operand.
+fn parse_with_precedence(
+    s: &synstructure::Structure,
+    type_name: &Literal,
+) -> syn::Result<TokenStream> {
+    let mut prefix = TokenStream::new();
+    let mut infix = TokenStream::new();
+    for variant in s.variants() {
+        let variant_name = as_literal(variant.ast().ident);
+        let v = parse_variant(variant, None)?;
+        let push = quote! {
+            __results.push({
+                let __span = tracing::span!(tracing::Level::TRACE, "parse", variant_name = #variant_name);
+                let __guard = __span.enter();
+                parse::try_parse(|| { #v })
+            });
+        };
+        // Variants without a precedence bind most tightly.
+        let push = match get_precedence_attr(variant.ast().attrs).transpose()? {
+            Some(Precedence { level, .. }) => quote!(if #level >= __precedence { #push }),
+            None => push,
+        };
+        if is_left_recursive(variant)? {
+            infix.extend(push);
+        } else {
+            prefix.extend(push);
+        }
+    }
+
+    let parse_prefix = quote! {
+        let mut __results = vec![];
+        #prefix
+        __results.push(parse::try_parse(|| {
+            let ((), text) = parse::expect_char('(', text)?;
+            let (__term, text) = <Self as parse::Parse>::parse(scope, text)?;
+            let ((), text) = parse::expect_char(')', text)?;
+            Ok((__term, text))
+        }));
+    };
+    if infix.is_empty() {
+        return Ok(quote! {
+            #parse_prefix
+            parse::require_unambiguous(text, __results, #type_name)
+        });
+    }
+    Ok(quote! {
+        #parse_prefix
+        let (mut __lhs, mut text) = parse::require_unambiguous(text, __results, #type_name)?;
+        loop {
+            let mut __results = vec![];
+            #infix
+            match parse::require_unambiguous(text, __results, #type_name) {
+                Ok((__term, __text)) => {
+                    __lhs = __term;
+                    text = __text;
+                }
+                Err(_) => break,
+            }
+        }
+        Ok((__lhs, text))
+    })
+}
+
 fn parse_variant(
     variant: &synstructure::VariantInfo,
     external_spec: Option<&FormalitySpec>,
@@ -132,20 +230,34 @@ fn parse_variant_with_attr(
     spec: &FormalitySpec,
 ) -> syn::Result<TokenStream> {
     let mut stream = TokenStream::new();
+    let operand_levels = precedence::operand_levels(variant, spec)?;
 
     for i in 0..spec.ops.len() {
         let op = &spec.ops[i];
         let next_op = spec.ops.get(i + 1);
         stream.extend(match op {
+            // The operand that the variant starts with has been parsed already.
             spec::FormalitySpecOp::Field {
                 name,
                 mode: FieldMode::Single,
-            } => {
+            } if i == 0 && operand_levels.contains_key(&name.to_string()) => {
                 quote_spanned! {
-                    name.span() => let (#name, text) = parse::Parse::parse(scope, text)?;
+                    name.span() => let #name = ::std::convert::From::from(::std::clone::Clone::clone(&__lhs));
                 }
             }
 
+            spec::FormalitySpecOp::Field {
+                name,
+                mode: FieldMode::Single,
+            } => match operand_levels.get(&name.to_string()) {
+                Some(level) => quote_spanned! {
+                    name.span() => let (#name, text) = parse::Parse::parse_with_precedence(scope, text, #level)?;
+                },
+                None => quote_spanned! {
+                    name.span() => let (#name, text) = parse::Parse::parse(scope, text)?;
+                },
+            },
+
             spec::FormalitySpecOp::Field {
                 name,
                 mode: FieldMode::Many,
@@ -0,0 +1,197 @@
+//! Precedence of the variants of a term, so that operators can be written without
+//! parentheses around every operand:
+//!
+//! ```rust,ignore
+//! #[term]
+//! enum Ty {
+//!     #[grammar($v0 -> $v1)]
+//!     #[precedence(1, right)]
+//!     Fn(Arc<Ty>, Arc<Ty>),
+//!
+//!     #[grammar(& $v0)]
+//!     #[precedence(2)]
+//!     Ref(Arc<Ty>),
+//!
+//!     #[grammar(u32)]
+//!     U32,
+//! }
+//! ```
+//!
+//! The *operands* of a variant are its fields of the type itself (possibly in an `Arc`)
+//! that start or end its grammar. A variant with `#[precedence(level)]` requires its
+//! operands to bind at least as tightly as `level`, and one level more tightly on the
+//! side that does not associate (the left side with `right`, the right side otherwise):
+//! above, `&u32 -> u32 -> u32` is `(&u32) -> (u32 -> u32)`. Variants without
+//! `#[precedence]` bind most tightly, and cannot start or end with an operand.
+//!
+//! Parsing an operand parses only the variants that bind tightly enough (by precedence
+//! climbing, for the variants starting with an operand), and any variant in parentheses.
+//! `Debug` and `Pretty` print an operand in parentheses if it does not bind tightly enough.
+
+use std::collections::BTreeMap;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Attribute;
+
+use crate::{
+    debug::{field_ident, get_grammar_attr},
+    spec::{FieldMode, FormalitySpec, FormalitySpecOp},
+};
+
+/// The argument of `#[precedence(..)]`.
+#[derive(Copy, Clone)]
+pub(crate) struct Precedence {
+    pub level: usize,
+    pub right: bool,
+}
+
+impl syn::parse::Parse for Precedence {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let level: syn::LitInt = input.parse()?;
+        let level = level.base10_parse()?;
+        let mut right = false;
+        if input.parse::<Option<syn::Token![,]>>()?.is_some() {
+            let associativity: syn::Ident = input.parse()?;
+            if associativity != "right" {
+                return Err(syn::Error::new(
+                    associativity.span(),
+                    "expected `right`, the only associativity besides the default",
+                ));
+            }
+            right = true;
+        }
+        Ok(Precedence { level, right })
+    }
+}
+
+pub(crate) fn get_precedence_attr(attrs: &[Attribute]) -> Option<syn::Result<Precedence>> {
+    let attr = attrs.iter().find(|a| a.path.is_ident("precedence"))?;
+    Some(attr.parse_args())
+}
+
+/// Whether some variant of `s` has a `#[precedence]`.
+pub(crate) fn has_precedence(s: &synstructure::Structure) -> bool {
+    s.variants()
+        .iter()
+        .any(|v| get_precedence_attr(v.ast().attrs).is_some())
+}
+
+/// Where an operand of a variant is.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Operand {
+    /// At the start of the grammar: the variant is parsed after the operand.
+    Left,
+    /// At the end of the grammar.
+    Right,
+}
+
+/// The operand at the op with index `index` of `spec`, the grammar of the enum variant
+/// `variant`, if any.
+fn operand(
+    variant: &synstructure::VariantInfo,
+    spec: &FormalitySpec,
+    index: usize,
+) -> Option<Operand> {
+    let FormalitySpecOp::Field {
+        name,
+        mode: FieldMode::Single,
+    } = &spec.ops[index]
+    else {
+        return None;
+    };
+    let binding = variant
+        .bindings()
+        .iter()
+        .zip(0..)
+        .find(|(b, i)| field_ident(b.ast(), *i) == *name)?
+        .0;
+    let type_name = variant.prefix?;
+    let ty = crate::grammar::normalize(&binding.ast().ty);
+    if !crate::grammar::refers_to(&ty, type_name, false) {
+        None
+    } else if index == 0 {
+        Some(Operand::Left)
+    } else if index == spec.ops.len() - 1 {
+        Some(Operand::Right)
+    } else {
+        None
+    }
+}
+
+impl Precedence {
+    /// The level that `operand` must bind at least as tightly as.
+    fn operand_level(&self, operand: Operand, infix: bool) -> usize {
+        match operand {
+            Operand::Left if self.right => self.level + 1,
+            Operand::Right if infix && !self.right => self.level + 1,
+            Operand::Left | Operand::Right => self.level,
+        }
+    }
+}
+
+/// The level that each operand of `variant` with grammar `spec` must bind at least as
+/// tightly as, by the name of its field. Empty if `variant` has no `#[precedence]`.
+pub(crate) fn operand_levels(
+    variant: &synstructure::VariantInfo,
+    spec: &FormalitySpec,
+) -> syn::Result<BTreeMap<String, usize>> {
+    let Some(precedence) = get_precedence_attr(variant.ast().attrs).transpose()? else {
+        return Ok(BTreeMap::new());
+    };
+    let infix = operand(variant, spec, 0) == Some(Operand::Left);
+    Ok(spec
+        .ops
+        .iter()
+        .zip(0..)
+        .filter_map(|(op, index)| {
+            let operand = operand(variant, spec, index)?;
+            let FormalitySpecOp::Field { name, .. } = op else {
+                unreachable!()
+            };
+            Some((name.to_string(), precedence.operand_level(operand, infix)))
+        })
+        .collect())
+}
+
+/// Whether `variant` has a `#[precedence]` and a grammar starting with an operand, so
+/// that it is parsed after the operand.
+pub(crate) fn is_left_recursive(variant: &synstructure::VariantInfo) -> syn::Result<bool> {
+    if get_precedence_attr(variant.ast().attrs).is_none() {
+        return Ok(false);
+    }
+    match get_grammar_attr(variant.ast().attrs).transpose()? {
+        Some(spec) => Ok(operand(variant, &spec, 0) == Some(Operand::Left)),
+        None => Ok(false),
+    }
+}
+
+/// The inherent `__precedence` method of the enum `s`, which gives the level of a value.
+pub(crate) fn derive_precedence(s: &synstructure::Structure) -> syn::Result<TokenStream> {
+    if !has_precedence(s) {
+        return Ok(TokenStream::new());
+    }
+    let mut arms = TokenStream::new();
+    for variant in s.variants() {
+        let pat = variant.pat();
+        let level = match get_precedence_attr(variant.ast().attrs).transpose()? {
+            Some(Precedence { level, .. }) => quote!(#level),
+            None => quote!(usize::MAX),
+        };
+        arms.extend(quote!(#pat => #level,));
+    }
+    let ast = s.ast();
+    let ident = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// The precedence level of `self`, see `#[precedence]`.
+            #[doc(hidden)]
+            fn __precedence(&self) -> usize {
+                match self {
+                    #arms
+                }
+            }
+        }
+    })
+}
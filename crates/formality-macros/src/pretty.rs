@@ -0,0 +1,276 @@
+use std::collections::BTreeMap;
+
+use proc_macro2::{Literal, TokenStream};
+use quote::{quote, quote_spanned};
+
+use crate::{
+    debug::{field_ident, get_grammar_attr, to_parse_ident},
+    spec::{FieldMode, FormalitySpec, FormalitySpecOp},
+};
+
+/// Derive the `Pretty` impl from the same grammar as the `Parse` and `Debug` impls,
+/// grouping the contents of delimiters so that they can be broken over several lines.
+pub(crate) fn derive_pretty_with_spec(
+    s: synstructure::Structure,
+    external_spec: Option<&FormalitySpec>,
+) -> TokenStream {
+    if let syn::Data::Union(v) = &s.ast().data {
+        return syn::Error::new(v.union_token.span, "unions are not supported")
+            .into_compile_error();
+    }
+
+    let pretty_arms = s.each_variant(|v| pretty_variant(v, external_spec));
+
+    s.gen_impl(quote! {
+        use crate::derive_links::pretty::{Doc, DocBuilder, Pretty, PrettyCx};
+
+        gen impl Pretty for @Self {
+            fn pretty(&self, cx: &PrettyCx) -> Doc {
+                match self {
+                    #pretty_arms
+                }
+            }
+        }
+    })
+}
+
+/// `Display` prints the term with `Pretty`. Generic terms get none, as `Display`
+/// would need bounds on the parameters that the macro cannot tell.
+pub(crate) fn derive_display(s: synstructure::Structure) -> TokenStream {
+    let ast = s.ast();
+    if !ast.generics.params.is_empty() {
+        return TokenStream::new();
+    }
+    let ident = &ast.ident;
+    quote! {
+        impl std::fmt::Display for #ident {
+            fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                fmt.write_str(&crate::derive_links::pretty::to_string(self))
+            }
+        }
+    }
+}
+
+fn pretty_variant(
+    variant: &synstructure::VariantInfo,
+    external_spec: Option<&FormalitySpec>,
+) -> TokenStream {
+    let ast = variant.ast();
+
+    // When invoked like `#[term(foo)]`, use the spec from `foo`
+    if let Some(spec) = external_spec {
+        return pretty_variant_with_attr(variant, spec);
+    }
+
+    // Else, look for a `#[grammar]` attribute on the variant
+    if let Some(attr) = get_grammar_attr(ast.attrs) {
+        return match attr {
+            Ok(spec) => pretty_variant_with_attr(variant, &spec),
+            Err(err) => err.into_compile_error(),
+        };
+    }
+
+    // If no `#[grammar(...)]` attribute is provided, then we print as `Debug` does.
+
+    if variant.bindings().is_empty() {
+        let literal = Literal::string(&to_parse_ident(ast.ident));
+        quote! {
+            Doc::text(#literal)
+        }
+    } else if crate::cast::has_cast_attr(variant.ast().attrs) {
+        let binding_names: Vec<_> = variant.bindings().iter().map(|b| &b.binding).collect();
+        quote! {
+            let mut b = DocBuilder::default();
+            for doc in Doc::comma_separated([#(Pretty::pretty(#binding_names, cx)),*]) {
+                b.push(doc);
+            }
+            b.group()
+        }
+    } else {
+        let literal = Literal::string(&to_parse_ident(ast.ident));
+        let binding_names: Vec<_> = variant.bindings().iter().map(|b| &b.binding).collect();
+        quote! {
+            let mut b = DocBuilder::default();
+            b.text(#literal);
+            b.push(Doc::delimited(
+                '(',
+                Doc::comma_separated([#(Pretty::pretty(#binding_names, cx)),*]),
+                ')',
+            ));
+            b.group()
+        }
+    }
+}
+
+fn pretty_variant_with_attr(
+    variant: &synstructure::VariantInfo,
+    spec: &FormalitySpec,
+) -> TokenStream {
+    let mut stream = TokenStream::new();
+
+    for (b, index) in variant.bindings().iter().zip(0..) {
+        let binding_field = &b.binding;
+        let spec_field = field_ident(b.ast(), index);
+        stream.extend(quote!(let #spec_field = #binding_field;));
+    }
+
+    let operand_levels = match crate::precedence::operand_levels(variant, spec) {
+        Ok(levels) => levels,
+        Err(err) => return err.into_compile_error(),
+    };
+    let ops = pretty_ops(&spec.ops, &operand_levels);
+    stream.extend(quote! {
+        let mut b = DocBuilder::default();
+        #ops
+        b.group()
+    });
+
+    stream
+}
+
+/// Pushes the docs for `ops` onto the builder `b`. The elements of a list that
+/// makes up the whole contents of delimiters go on lines of their own if the contents
+/// do not fit; other lists are grouped so that they break on their own. The operands
+/// in `operand_levels` go in parentheses if they bind less tightly than their level.
+fn pretty_ops(ops: &[FormalitySpecOp], operand_levels: &BTreeMap<String, usize>) -> TokenStream {
+    let mut stream = TokenStream::new();
+    let whole_contents = ops.len() == 1;
+
+    let mut i = 0;
+    while i < ops.len() {
+        match &ops[i] {
+            FormalitySpecOp::Field {
+                name,
+                mode: FieldMode::Single,
+            } => stream.extend(match operand_levels.get(&name.to_string()) {
+                Some(level) => quote_spanned! {
+                    name.span() =>
+                        if #name.__precedence() < #level {
+                            b.push(Doc::delimited('(', vec![Pretty::pretty(#name, cx)], ')'));
+                        } else {
+                            b.push(Pretty::pretty(#name, cx));
+                        }
+                },
+                None => quote_spanned! {
+                    name.span() =>
+                        b.push(Pretty::pretty(#name, cx));
+                },
+            }),
+
+            FormalitySpecOp::Field {
+                name,
+                mode: FieldMode::Many,
+            } => {
+                let push_docs = if whole_contents {
+                    quote!(for doc in docs {
+                        b.push(doc);
+                    })
+                } else {
+                    quote!(b.push(Doc::Group(vec![Doc::Nest(docs)]));)
+                };
+                stream.extend(quote_spanned! {
+                    name.span() =>
+                        let mut docs = vec![];
+                        for (e, i) in #name.iter().zip(0..) {
+                            if i > 0 {
+                                docs.push(Doc::Line);
+                            }
+                            docs.push(Pretty::pretty(e, cx));
+                        }
+                        #push_docs
+                })
+            }
+
+            FormalitySpecOp::Field {
+                name,
+                mode: FieldMode::Comma,
+            } => {
+                let push_docs = if whole_contents {
+                    quote!(for doc in Doc::comma_separated(docs) {
+                        b.push(doc);
+                    })
+                } else {
+                    quote!(b.push(Doc::list(docs));)
+                };
+                stream.extend(quote_spanned! {
+                    name.span() =>
+                        let docs = #name.iter().map(|e| Pretty::pretty(e, cx));
+                        #push_docs
+                })
+            }
+
+            FormalitySpecOp::Keyword { ident } => {
+                let literal = Literal::string(&ident.to_string());
+                stream.extend(quote_spanned!(ident.span() => b.text(#literal);));
+            }
+
+            // Consecutive characters are printed without spaces, e.g., `::` or `->`.
+            // Characters around a comma-separated list hug it, as in `|x, y|` or
+            // `Foo<u32>`, and so does a `!` what it negates.
+            FormalitySpecOp::Char { .. } => {
+                let mut text = String::new();
+                while let Some(FormalitySpecOp::Char { punct }) = ops.get(i) {
+                    text.push(punct.as_char());
+                    i += 1;
+                }
+                let literal = Literal::string(&text);
+                let after_list = i > text.len() && is_comma_field(&ops[i - text.len() - 1]);
+                if after_list || text == "<" {
+                    stream.extend(quote!(b.glue();));
+                }
+                stream.extend(quote!(b.text(#literal);));
+                if ops.get(i).is_some_and(is_comma_field) || text == "!" {
+                    stream.extend(quote!(b.glue();));
+                }
+                continue;
+            }
+
+            FormalitySpecOp::Delimeter { text: open } => {
+                let close = closing_delimeter(ops, i);
+                let contents = pretty_ops(&ops[i + 1..close], operand_levels);
+                let FormalitySpecOp::Delimeter { text: close_text } = &ops[close] else {
+                    unreachable!()
+                };
+                stream.extend(quote! {
+                    let docs = {
+                        let mut b = DocBuilder::default();
+                        #contents
+                        b.into_docs()
+                    };
+                    b.push(Doc::delimited(#open, docs, #close_text));
+                });
+                i = close;
+            }
+        }
+        i += 1;
+    }
+
+    stream
+}
+
+fn is_comma_field(op: &FormalitySpecOp) -> bool {
+    matches!(
+        op,
+        FormalitySpecOp::Field {
+            mode: FieldMode::Comma,
+            ..
+        }
+    )
+}
+
+/// The index of the delimeter closing the one at `open`.
+fn closing_delimeter(ops: &[FormalitySpecOp], open: usize) -> usize {
+    let mut depth = 0;
+    for (op, i) in ops.iter().zip(0..).skip(open) {
+        if let FormalitySpecOp::Delimeter { text } = op {
+            match text {
+                '(' | '[' | '{' => depth += 1,
+                _ => depth -= 1,
+            }
+            if depth == 0 {
+                return i;
+            }
+        }
+    }
+    panic!("unbalanced delimeters in grammar")
+}
@@ -8,6 +8,9 @@ use proc_macro2::{Ident, Punct, TokenStream, TokenTree};
 /// * a field like `$foo` parses the type of the declared field
 ///     * you can also do `$*foo` to use the `parse_many` option
 /// * a character like `<` is parsed as is; a group like `[..]` parses a `[`, the contents, and then `]`
+///
+/// Variants that start or end with the type itself, like the operators of expressions,
+/// can be given a precedence with `#[precedence]`, see [`crate::precedence`].
 pub struct FormalitySpec {
     pub ops: Vec<FormalitySpecOp>,
 }
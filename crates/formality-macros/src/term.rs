@@ -3,44 +3,78 @@ use quote::quote;
 use syn::DeriveInput;
 
 use crate::{
-    cast::{downcast_impls, upcast_impls},
+    cast::{downcast_impls, upcast_impls, via_impls},
     debug::derive_debug_with_spec,
     fold::derive_fold,
+    generate::derive_generate,
+    grammar::check_grammar,
     parse::derive_parse_with_spec,
+    precedence::derive_precedence,
+    pretty::{derive_display, derive_pretty_with_spec},
     spec::FormalitySpec,
     visit::derive_visit,
 };
 
 pub fn term(spec: Option<FormalitySpec>, mut input: DeriveInput) -> syn::Result<TokenStream> {
+    // `#[recovery_point]`: see `Parse::RECOVERY_POINT`
+    let recovery_point = input
+        .attrs
+        .iter()
+        .any(|attr| attr.path.is_ident("recovery_point"));
+    input
+        .attrs
+        .retain(|attr| !attr.path.is_ident("recovery_point"));
+
+    check_grammar(&synstructure::Structure::new(&input), spec.as_ref())?;
+
     let fold_impl = derive_fold(synstructure::Structure::new(&input));
     let visit_impl = derive_visit(synstructure::Structure::new(&input));
-    let parse_impl = derive_parse_with_spec(synstructure::Structure::new(&input), spec.as_ref())?;
+    let parse_impl = derive_parse_with_spec(
+        synstructure::Structure::new(&input),
+        spec.as_ref(),
+        recovery_point,
+    )?;
     let debug_impl = derive_debug_with_spec(synstructure::Structure::new(&input), spec.as_ref());
+    let pretty_impl = derive_pretty_with_spec(synstructure::Structure::new(&input), spec.as_ref());
+    let display_impl = derive_display(synstructure::Structure::new(&input));
+    let generate_impl = derive_generate(synstructure::Structure::new(&input));
     let term_impl = derive_term(synstructure::Structure::new(&input));
+    let precedence_impl = derive_precedence(&synstructure::Structure::new(&input))?;
     let downcast_impls = downcast_impls(synstructure::Structure::new(&input));
     let upcast_impls = upcast_impls(synstructure::Structure::new(&input));
+    let via_impls = via_impls(&synstructure::Structure::new(&input))?;
     remove_formality_attributes(&mut input);
 
     Ok(quote! {
         #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[derive(crate::derive_links::serde::Serialize, crate::derive_links::serde::Deserialize)]
+        #[serde(crate = "crate::derive_links::serde")]
         #input
 
         #fold_impl
         #visit_impl
         #parse_impl
         #debug_impl
+        #pretty_impl
+        #display_impl
+        #generate_impl
         #term_impl
+        #precedence_impl
         #(#downcast_impls)*
         #(#upcast_impls)*
+        #(#via_impls)*
     })
 }
 
 fn remove_formality_attributes(input: &mut DeriveInput) {
+    input.attrs.retain(|attr| !attr.path.is_ident("cast"));
     if let syn::Data::Enum(v) = &mut input.data {
         for variant in &mut v.variants {
-            variant
-                .attrs
-                .retain(|attr| !attr.path.is_ident("grammar") && !attr.path.is_ident("cast"));
+            variant.attrs.retain(|attr| {
+                !attr.path.is_ident("grammar")
+                    && !attr.path.is_ident("cast")
+                    && !attr.path.is_ident("precedence")
+            });
         }
     }
 }
@@ -9,13 +9,15 @@ pub(crate) fn derive_visit(mut s: synstructure::Structure) -> TokenStream {
 
     let free_variables_body = s.each(|field| quote!(output.extend(Visit::free_variables(#field))));
 
+    let var_flags_body = s.each(|field| quote!(__flags |= Visit::var_flags(#field)));
+
     let size_body = s.each(|field| quote!(__sum += Visit::size(#field)));
 
     let assert_valid_body = s.each(|field| quote!(Visit::assert_valid(#field)));
 
     // s.add_bounds(synstructure::AddBounds::None);
     s.gen_impl(quote! {
-        use crate::derive_links::{Visit, Variable};
+        use crate::derive_links::{Visit, Variable, VarFlags};
 
         gen impl Visit for @Self {
             fn free_variables(&self) -> Vec<Variable> {
@@ -26,6 +28,14 @@ pub(crate) fn derive_visit(mut s: synstructure::Structure) -> TokenStream {
                 output
             }
 
+            fn var_flags(&self) -> VarFlags {
+                let mut __flags = VarFlags::NONE;
+                match self {
+                    #var_flags_body
+                }
+                __flags
+            }
+
             fn size(&self) -> usize {
                 let mut __sum = 0;
                 __sum += 1;
@@ -0,0 +1,53 @@
+//! Times equating deep types that are identical in whole or in part; run with
+//! `cargo bench -p formality-prove`.
+
+use std::time::{Duration, Instant};
+
+use formality_prove::{prove, Decls, Env, SolverConfig};
+use formality_types::{
+    cast::Upcast,
+    grammar::{ParameterKind, Relation, Ty, Wcs},
+    parse::term,
+};
+
+const ITERATIONS: u32 = 20;
+
+/// `Vec<Vec<...<u32>>>`, with `depth` `Vec`s.
+fn nested(depth: usize) -> String {
+    (0..depth).fold("u32".to_string(), |ty, _| format!("Vec<{ty}>"))
+}
+
+fn time(decls: &Decls, env: &Env, goal: &Wcs) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        let constraints = prove(decls, env, (), goal);
+        assert!(constraints.iter().any(|c| c.known_true && !c.overflow));
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let decls = Decls::empty().with_config(SolverConfig {
+        max_size: usize::MAX,
+        ..SolverConfig::default()
+    });
+    let mut env = Env::default();
+    let x = env.fresh_existential(ParameterKind::Ty);
+
+    for depth in [8, 16, 32, 64, 128] {
+        let a: Ty = term(&nested(depth));
+
+        // `A = A`
+        let identical: Wcs = Relation::eq(&a, &a).upcast();
+
+        // `(A, u32) = (A, ?X)`, where only the second halves need to be equated
+        let pair = |ty: Ty| Ty::tuple(&[a.clone(), ty]);
+        let pairs: Wcs = Relation::eq(pair(term("u32")), pair(Ty::new(x))).upcast();
+
+        println!(
+            "depth {depth:>3}: `A = A` {:>12?}, `(A, u32) = (A, ?X)` {:>12?}",
+            time(&decls, &env, &identical),
+            time(&decls, &env, &pairs),
+        );
+    }
+}
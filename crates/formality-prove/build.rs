@@ -0,0 +1,44 @@
+//! Hashes the sources the prover is built from, so that the query cache (see
+//! `src/db.rs`) can tell whether it was saved by the same prover.
+
+use std::path::{Path, PathBuf};
+
+/// The crates whose sources decide the responses of the prover: this one, and the
+/// judgment engine, terms and macros it is built on.
+const CRATES: &[&str] = &[
+    "formality-prove",
+    "formality-types",
+    "formality-core",
+    "formality-macros",
+];
+
+fn main() {
+    let crates_dir = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for name in CRATES {
+        let src = crates_dir.join(name).join("src");
+        println!("cargo:rerun-if-changed={}", src.display());
+        let mut files = vec![];
+        collect_files(&src, &mut files);
+        files.sort();
+        for file in files {
+            let path = file.strip_prefix(crates_dir).unwrap().to_string_lossy();
+            let contents = std::fs::read(&file).unwrap();
+            for byte in path.bytes().chain(contents) {
+                hash = (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3);
+            }
+        }
+    }
+    println!("cargo:rustc-env=FORMALITY_PROVE_SOURCE_HASH={hash:016x}");
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
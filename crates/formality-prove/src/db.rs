@@ -0,0 +1,252 @@
+//! A cache of the responses to goals that persists on disk, so that running the checks
+//! again on the same programs, e.g., on a large corpus, does not prove the same goals
+//! anew.
+//!
+//! The entries are keyed by a hash of the [`Decls`] and the [`CanonicalGoal`], and
+//! only the outermost calls to [`prove`] use the cache: the goals proven within them
+//! may be part of a cycle that is still being computed. Responses that overflowed, or
+//! that were computed after a cancellation, are not cached. Neither are goals proven
+//! while recording proof trees, whose derivations the cache does not keep.
+//!
+//! The responses are only as good as the prover that computed them: a cache saved by
+//! another version of the prover, or one built from other sources, is discarded when
+//! opened. The file starts with the version of this crate and a hash of the sources of
+//! the prover and the crates it is built on for that reason, which the build script
+//! computes. Other hashes are FNV-1a hashes of the serialized terms, which do not
+//! change from one build to the next.
+//!
+//! Saving writes a temporary file next to the cache and renames it into place, so that
+//! runs that save the same cache concurrently do not corrupt it: the last one wins.
+
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::Context;
+use formality_types::{
+    collections::{Map, Set},
+    grammar::{Fallible, Wcs},
+    judgment::cancel,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    decls::Decls,
+    prove::{
+        canonicalize, instantiate_response, prove, CanonicalGoal, CanonicalResponse, Constraints,
+        Env,
+    },
+};
+
+/// The responses to the canonical goals proven so far, by the hash of the decls they
+/// were proven with. Loaded from a file with [`QueryCache::open`], and written back
+/// with [`QueryCache::save`]; the goals proven under [`with_query_cache`] use it.
+pub struct QueryCache {
+    path: PathBuf,
+    entries: Mutex<Map<(u64, CanonicalGoal), Vec<CanonicalResponse>>>,
+    hits: AtomicUsize,
+}
+
+/// An entry as saved: the hash of the decls, the goal, and its responses.
+type Entry = (u64, CanonicalGoal, Vec<CanonicalResponse>);
+
+/// What identifies the prover that saved a cache.
+#[derive(PartialEq, Eq, Serialize, Deserialize, Debug)]
+struct Stamp {
+    /// The version of this crate.
+    version: String,
+
+    /// The hash of the sources of this crate and of the crates it is built on, not
+    /// just of its rules: the helpers they call and the judgment engine matter too.
+    sources: String,
+}
+
+impl Stamp {
+    fn current() -> Stamp {
+        Stamp {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            sources: env!("FORMALITY_PROVE_SOURCE_HASH").to_string(),
+        }
+    }
+}
+
+/// The file of a cache, as saved.
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    stamp: Stamp,
+    entries: Vec<Entry>,
+}
+
+/// The start of the file of a cache, which is read first to tell whether the rest is
+/// worth reading. Older caches have none.
+#[derive(Deserialize)]
+struct CacheHeader {
+    stamp: Stamp,
+}
+
+impl QueryCache {
+    /// The cache saved at `path`, or an empty one if there is no file at `path` yet or
+    /// it was saved by another version of the prover.
+    pub fn open(path: impl Into<PathBuf>) -> Fallible<Self> {
+        let path = path.into();
+        let entries: Vec<Entry> = if path.exists() {
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("reading the query cache `{}`", path.display()))?;
+            match serde_json::from_str::<CacheHeader>(&text) {
+                Ok(header) if header.stamp == Stamp::current() => {
+                    let file: CacheFile = serde_json::from_str(&text)
+                        .with_context(|| format!("parsing the query cache `{}`", path.display()))?;
+                    file.entries
+                }
+                _ => {
+                    tracing::debug!("discarding the query cache `{}`", path.display());
+                    vec![]
+                }
+            }
+        } else {
+            vec![]
+        };
+        Ok(QueryCache {
+            path,
+            entries: Mutex::new(
+                entries
+                    .into_iter()
+                    .map(|(hash, goal, responses)| ((hash, goal), responses))
+                    .collect(),
+            ),
+            hits: AtomicUsize::new(0),
+        })
+    }
+
+    /// Writes the entries to the file the cache was opened from, replacing it at once.
+    pub fn save(&self) -> Fallible<()> {
+        let entries: Vec<Entry> = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((hash, goal), responses)| (*hash, goal.clone(), responses.clone()))
+            .collect();
+        let text = serde_json::to_string(&CacheFile {
+            stamp: Stamp::current(),
+            entries,
+        })?;
+
+        let mut temp_name = self.path.file_name().unwrap_or_default().to_owned();
+        temp_name.push(format!(".{}.tmp", std::process::id()));
+        let temp_path = self.path.with_file_name(temp_name);
+        std::fs::write(&temp_path, text)
+            .with_context(|| format!("writing the query cache `{}`", temp_path.display()))?;
+        std::fs::rename(&temp_path, &self.path).with_context(|| {
+            let _ = std::fs::remove_file(&temp_path);
+            format!("writing the query cache `{}`", self.path.display())
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The number of goals with cached responses.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of goals whose responses were taken from the cache since it was opened.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Proves `goal` under `assumptions` in `env` like [`prove`], with the cached
+    /// responses if there are any, and caching them otherwise.
+    fn prove(&self, decls: &Decls, env: &Env, assumptions: &Wcs, goal: &Wcs) -> Set<Constraints> {
+        let (canonical_goal, variables) = canonicalize(env, assumptions, goal);
+        let key = (decls_hash(decls), canonical_goal);
+
+        let cached = self.entries.lock().unwrap().get(&key).cloned();
+        if let Some(responses) = cached {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return responses
+                .iter()
+                .map(|r| instantiate_response(env, &variables, r))
+                .collect();
+        }
+
+        let result = with_query_cache(None, || prove(decls, env, assumptions, goal));
+        if !cancel::is_cancelled() && result.iter().all(|c| !c.overflow) {
+            let responses: Set<CanonicalResponse> = result
+                .iter()
+                .map(|c| CanonicalResponse::new(&variables, c))
+                .collect();
+            self.entries
+                .lock()
+                .unwrap()
+                .insert(key, responses.into_iter().collect());
+        }
+        result
+    }
+}
+
+impl std::fmt::Debug for QueryCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryCache")
+            .field("path", &self.path)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// The hash of `decls` that the entries are keyed by. The decls themselves are
+/// hashed once, see [`Decls::stable_hash`], but their config may change between calls.
+fn decls_hash(decls: &Decls) -> u64 {
+    stable_hash(&(decls.stable_hash(), &decls.config))
+}
+
+/// The 64-bit FNV-1a hash of `value` serialized as JSON, which unlike the hashes of
+/// [`std::hash::Hash`] is the same in every build.
+pub(crate) fn stable_hash(value: &impl Serialize) -> u64 {
+    let text = serde_json::to_string(value).unwrap();
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+thread_local! {
+    /// The cache that the outermost calls to [`prove`] on this thread use, if any.
+    static ACTIVE: RefCell<Option<Arc<QueryCache>>> = RefCell::new(None)
+}
+
+/// Runs `op` with the outermost calls to [`prove`] on this thread using `cache`, or no
+/// cache at all. Operations that hand work over to other threads should carry the
+/// [`active_query_cache`] over to them, as they do for cancellation.
+pub fn with_query_cache<R>(cache: Option<Arc<QueryCache>>, op: impl FnOnce() -> R) -> R {
+    let previous = ACTIVE.with(|active| active.replace(cache));
+    let result = op();
+    ACTIVE.with(|active| active.replace(previous));
+    result
+}
+
+/// The cache that the outermost calls to [`prove`] on this thread use, if any.
+pub fn active_query_cache() -> Option<Arc<QueryCache>> {
+    ACTIVE.with(|active| active.borrow().clone())
+}
+
+/// Proves `goal` with the [active](`active_query_cache`) cache, if there is one.
+pub(crate) fn prove_with_active_cache(
+    decls: &Decls,
+    env: &Env,
+    assumptions: &Wcs,
+    goal: &Wcs,
+) -> Option<Set<Constraints>> {
+    let cache = active_query_cache()?;
+    Some(cache.prove(decls, env, assumptions, goal))
+}
@@ -3,29 +3,199 @@ use formality_types::{
     cast::Upcast,
     collections::Set,
     grammar::{
-        AdtId, AliasName, AliasTy, Binder, Parameter, Predicate, Relation, TraitId, TraitRef, Ty,
-        Wc, Wcs, PR,
+        Abi, AdtId, AliasName, AliasTy, Binder, FnId, Parameter, Predicate, Relation, RigidName,
+        RigidTy, TraitId, TraitRef, Ty, TyData, TypeAliasId, Wc, WcData, Wcs, PR,
     },
     set,
 };
 
+use crate::db::stable_hash;
+use index::{decls_at, IndexTables};
+
+mod index;
+
+pub use index::DeclsIndex;
+
+/// Limits on the search of the prover, and the mode it runs in. Exceeding the
+/// limits yields an overflow result, see [`Constraints::overflow`](`crate::Constraints::overflow`).
 #[term]
-pub struct Decls {
+pub struct SolverConfig {
+    /// The maximum size of a goal, to catch goals that keep growing.
     pub max_size: usize,
 
-    /// Each trait in the program
-    pub trait_decls: Vec<TraitDecl>,
-    pub impl_decls: Vec<ImplDecl>,
-    pub neg_impl_decls: Vec<NegImplDecl>,
-    pub alias_eq_decls: Vec<AliasEqDecl>,
-    pub alias_bound_decls: Vec<AliasBoundDecl>,
+    /// The maximum number of nested calls to `prove`, to catch goals that keep
+    /// recursing without growing.
+    pub max_depth: usize,
+
+    /// Compare types modulo lifetimes, as codegen does once regions are erased:
+    /// any two lifetimes are equal, and everything outlives every lifetime.
+    pub erase_regions: bool,
+
+    /// How the search is bounded by `max_depth`.
+    pub search_strategy: BuiltinSearchStrategy,
+
+    /// Evaluate the candidates for a goal in parallel, see [`formality_types::judgment::parallel`].
+    pub parallel: bool,
+
+    /// The experimental rules that are enabled.
+    pub flags: SolverFlags,
+}
+
+/// A rule set that can be switched on or off, so that the alternatives can be compared
+/// on the same programs in one build. The rules of each flag are gated on
+/// [`SolverFlags::is_enabled`].
+#[term]
+#[derive(Copy)]
+pub enum SolverFlag {
+    /// Impls do not overlap if the where-clauses of one contradict those of the other,
+    /// e.g., `T: Foo` and `T: !Foo` (see `overlap_check` in `formality-check`), as in
+    /// rustc's unstable `with_negative_coherence` feature.
+    NegativeCoherence,
+
+    /// The orphan rules of [RFC 2451], in which type parameters may appear before the
+    /// first local type of an impl if they are covered, e.g., in `impl<T> ForeignTrait<LocalType>
+    /// for Vec<T>`. Otherwise, as in [RFC 1023], they may not appear there at all.
+    ///
+    /// [RFC 2451]: https://rust-lang.github.io/rfcs/2451-re-rebalancing-coherence.html
+    /// [RFC 1023]: https://rust-lang.github.io/rfcs/1023-rebalancing-coherence.html
+    ReRebalancingCoherence,
+}
+
+impl SolverFlag {
+    pub const ALL: [SolverFlag; 2] = [
+        SolverFlag::NegativeCoherence,
+        SolverFlag::ReRebalancingCoherence,
+    ];
+}
+
+/// The set of enabled [`SolverFlag`]s. By default, the flags of the rules that the
+/// model has always had are enabled.
+#[term]
+pub struct SolverFlags {
+    pub enabled: Set<SolverFlag>,
+}
+
+impl SolverFlags {
+    pub fn is_enabled(&self, flag: SolverFlag) -> bool {
+        self.enabled.contains(&flag)
+    }
+
+    /// These flags, with `flag` enabled or disabled.
+    pub fn with(&self, flag: SolverFlag, enabled: bool) -> SolverFlags {
+        let mut flags = self.clone();
+        if enabled {
+            flags.enabled.insert(flag);
+        } else {
+            flags.enabled.remove(&flag);
+        }
+        flags
+    }
+}
+
+impl Default for SolverFlags {
+    fn default() -> Self {
+        SolverFlags {
+            enabled: set![
+                SolverFlag::NegativeCoherence,
+                SolverFlag::ReRebalancingCoherence,
+            ],
+        }
+    }
+}
+
+/// How the prover bounds the depth of its search. Each strategy is an experiment in
+/// trading completeness for termination: the bounded ones commit to the shallowest
+/// proofs they find, dropping the overflows of deeper branches that might still hold
+/// other solutions.
+///
+/// The strategy of a [`SolverConfig`] is one of the [`BuiltinSearchStrategy`]s; others
+/// can be run on the same rules with [`prove_with_search_strategy`](`crate::prove_with_search_strategy`).
+pub trait SearchStrategy {
+    /// The depth bounds of the successive depth-first searches, the last one being
+    /// `max_depth`.
+    fn depth_bounds(&self, max_depth: usize) -> Vec<usize>;
+}
+
+/// The search strategies that can be selected by [`SolverConfig::search_strategy`].
+#[term]
+#[derive(Copy)]
+pub enum BuiltinSearchStrategy {
+    /// One search bounded by `max_depth`, as rustc does.
+    DepthFirst,
+
+    /// Searches bounded by `step`, `2 * step`, `3 * step`, ... up to `max_depth`, so
+    /// that, with a `step` of 1, the proofs of least depth are found first.
+    IterativeDeepening { step: usize },
+
+    /// Searches bounded by 1, 2, 4, ... up to `max_depth`.
+    Doubling,
+}
+
+impl SearchStrategy for BuiltinSearchStrategy {
+    fn depth_bounds(&self, max_depth: usize) -> Vec<usize> {
+        let shallower: Vec<usize> = match *self {
+            BuiltinSearchStrategy::DepthFirst => vec![],
+            BuiltinSearchStrategy::IterativeDeepening { step } => {
+                (step.max(1)..max_depth).step_by(step.max(1)).collect()
+            }
+            BuiltinSearchStrategy::Doubling => std::iter::successors(Some(1), |d| Some(d * 2))
+                .take_while(|&d| d < max_depth)
+                .collect(),
+        };
+        shallower.into_iter().chain(Some(max_depth)).collect()
+    }
+}
+
+impl SolverConfig {
+    /// Max size used in unit tests that are not stress testing maximum size.
+    pub const DEFAULT_MAX_SIZE: usize = 222;
+
+    /// Like rustc's default `recursion_limit`.
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            max_size: SolverConfig::DEFAULT_MAX_SIZE,
+            max_depth: SolverConfig::DEFAULT_MAX_DEPTH,
+            erase_regions: false,
+            search_strategy: BuiltinSearchStrategy::DepthFirst,
+            parallel: false,
+            flags: SolverFlags::default(),
+        }
+    }
+}
+
+#[term]
+pub struct Decls {
+    pub config: SolverConfig,
+
+    /// Each trait in the program. These and the other indexed decls below are only
+    /// set through the `with_*` methods, which build the index anew.
+    trait_decls: Vec<TraitDecl>,
+    impl_decls: Vec<ImplDecl>,
+    neg_impl_decls: Vec<NegImplDecl>,
+    alias_eq_decls: Vec<AliasEqDecl>,
+    alias_bound_decls: Vec<AliasBoundDecl>,
+    type_alias_decls: Vec<TypeAliasDecl>,
+    fn_decls: Vec<FnDecl>,
+    adt_decls: Vec<AdtDecl>,
     pub local_trait_ids: Set<TraitId>,
     pub local_adt_ids: Set<AdtId>,
+
+    /// The traits whose `dyn Trait` types are well formed.
+    pub dyn_compatible_trait_ids: Set<TraitId>,
+
+    /// The lookups into the decls above, built on first use and shared by the clones
+    /// of these decls.
+    index: DeclsIndex,
 }
 
 impl Decls {
-    /// Max size used in unit tests that are not stress testing maximum size.
-    pub const DEFAULT_MAX_SIZE: usize = 222;
+    fn tables(&self) -> &IndexTables {
+        self.index.tables(self)
+    }
 
     pub fn is_local_trait_id(&self, trait_id: &TraitId) -> bool {
         self.local_trait_ids.contains(trait_id)
@@ -35,64 +205,233 @@ impl Decls {
         self.local_adt_ids.contains(adt_id)
     }
 
-    pub fn impl_decls<'s>(&'s self, trait_id: &'s TraitId) -> impl Iterator<Item = &'s ImplDecl> {
-        self.impl_decls
-            .iter()
-            .filter(move |i| i.binder.peek().trait_ref.trait_id == *trait_id)
+    pub fn is_dyn_compatible(&self, trait_id: &TraitId) -> bool {
+        self.dyn_compatible_trait_ids.contains(trait_id)
+    }
+
+    pub fn impl_decls<'s>(&'s self, trait_id: &TraitId) -> impl Iterator<Item = &'s ImplDecl> {
+        decls_at(&self.impl_decls, &self.tables().impl_decls, trait_id)
     }
 
     pub fn neg_impl_decls<'s>(
         &'s self,
-        trait_id: &'s TraitId,
+        trait_id: &TraitId,
     ) -> impl Iterator<Item = &'s NegImplDecl> {
-        self.neg_impl_decls
-            .iter()
-            .filter(move |i| i.binder.peek().trait_ref.trait_id == *trait_id)
+        decls_at(
+            &self.neg_impl_decls,
+            &self.tables().neg_impl_decls,
+            trait_id,
+        )
     }
 
     pub fn trait_decl(&self, trait_id: &TraitId) -> &TraitDecl {
-        let mut v: Vec<_> = self
-            .trait_decls
-            .iter()
-            .filter(|t| t.id == *trait_id)
-            .collect();
+        let mut v: Vec<_> =
+            decls_at(&self.trait_decls, &self.tables().trait_decls, trait_id).collect();
         assert!(!v.is_empty(), "no traits named `{trait_id:?}`");
         assert!(v.len() <= 1, "multiple traits named `{trait_id:?}`");
         v.pop().unwrap()
     }
 
-    pub fn alias_eq_decls<'s>(
+    pub fn fn_decl(&self, fn_id: &FnId) -> &FnDecl {
+        let mut v: Vec<_> = decls_at(&self.fn_decls, &self.tables().fn_decls, fn_id).collect();
+        assert!(!v.is_empty(), "no fns named `{fn_id:?}`");
+        assert!(v.len() <= 1, "multiple fns named `{fn_id:?}`");
+        v.pop().unwrap()
+    }
+
+    pub fn alias_eq_decls<'s>(&'s self, name: &AliasName) -> impl Iterator<Item = &'s AliasEqDecl> {
+        decls_at(&self.alias_eq_decls, &self.tables().alias_eq_decls, name)
+    }
+
+    pub fn alias_bound_decls<'s>(
         &'s self,
-        name: &'s AliasName,
-    ) -> impl Iterator<Item = &'s AliasEqDecl> {
-        self.alias_eq_decls
-            .iter()
-            .filter(move |a| a.alias_name() == *name)
+        name: &AliasName,
+    ) -> impl Iterator<Item = &'s AliasBoundDecl> {
+        decls_at(
+            &self.alias_bound_decls,
+            &self.tables().alias_bound_decls,
+            name,
+        )
+    }
+
+    pub fn type_alias_decl(&self, id: &TypeAliasId) -> &TypeAliasDecl {
+        let mut v: Vec<_> =
+            decls_at(&self.type_alias_decls, &self.tables().type_alias_decls, id).collect();
+        assert!(!v.is_empty(), "no type aliases named `{id:?}`");
+        assert!(v.len() <= 1, "multiple type aliases named `{id:?}`");
+        v.pop().unwrap()
     }
 
-    pub fn alias_bound_decls(&self) -> &[AliasBoundDecl] {
-        &self.alias_bound_decls
+    /// The decl of the ADT `id`, if it is declared. The prover's own tests use ADTs
+    /// like `Vec<T>` without declaring them, which are not well formed (see `prove_wf`).
+    pub fn adt_decl(&self, id: &AdtId) -> Option<&AdtDecl> {
+        let mut v: Vec<_> = decls_at(&self.adt_decls, &self.tables().adt_decls, id).collect();
+        assert!(v.len() <= 1, "multiple ADTs named `{id:?}`");
+        v.pop()
+    }
+
+    /// The outlives bounds that `ty` being well formed implies, as in rustc's implied
+    /// bounds: those of [`Ty::implied_outlives`], and the outlives where-clauses of the
+    /// ADTs in `ty`, like `T: 'a` for `Ref<'a, T>` given `struct Ref<'a, T> { r: &'a T }`.
+    pub fn implied_outlives(&self, ty: &Ty) -> Vec<Relation> {
+        let mut bounds = vec![];
+        let parameters = match ty.data() {
+            TyData::RigidTy(RigidTy { name, parameters }) => {
+                match (name, &parameters[..]) {
+                    (RigidName::Ref(_), [lt, ty]) => bounds.push(ty.outlives(lt)),
+                    (RigidName::AdtId(id), _) => {
+                        let where_clause = self
+                            .adt_decl(id)
+                            .and_then(|decl| decl.binder.instantiate_with(parameters).ok())
+                            .map(|data| data.where_clause)
+                            .unwrap_or_else(Wcs::t);
+                        bounds.extend(where_clause.into_iter().filter_map(|wc| match wc.data() {
+                            WcData::PR(PR::Relation(r @ Relation::Outlives(..))) => Some(r.clone()),
+                            _ => None,
+                        }));
+                    }
+                    _ => {}
+                }
+                parameters
+            }
+            TyData::AliasTy(AliasTy {
+                name: _,
+                parameters,
+            }) => parameters,
+            TyData::PredicateTy(_) | TyData::Variable(_) => return bounds,
+        };
+        for p in parameters {
+            if let Parameter::Ty(ty) = p {
+                bounds.extend(self.implied_outlives(ty));
+            }
+        }
+        bounds
     }
 
     /// Return the set of "trait invariants" for all traits.
     /// See [`TraitDecl::trait_invariants`].
-    pub fn trait_invariants(&self) -> Set<TraitInvariant> {
-        self.trait_decls
-            .iter()
-            .flat_map(|td| td.trait_invariants())
-            .collect()
+    pub fn trait_invariants(&self) -> &Set<TraitInvariant> {
+        &self.tables().trait_invariants
+    }
+
+    /// The hash of these decls that the query cache of [`crate::db`] is keyed by,
+    /// computed once and shared by their clones. Their config is left out, as it may
+    /// still be changed after cloning.
+    pub(crate) fn stable_hash(&self) -> u64 {
+        self.index.stable_hash(|| {
+            stable_hash(&Decls {
+                config: SolverConfig::default(),
+                ..self.clone()
+            })
+        })
+    }
+
+    /// These decls with `config` instead of their config, which the index does not
+    /// depend on.
+    pub fn with_config(self, config: SolverConfig) -> Self {
+        Decls { config, ..self }
+    }
+
+    /// Each trait in the program, in the order they are declared in.
+    pub fn trait_decls(&self) -> &[TraitDecl] {
+        &self.trait_decls
+    }
+
+    /// These decls with their traits replaced by `trait_decls`.
+    pub fn with_trait_decls(self, trait_decls: Vec<TraitDecl>) -> Self {
+        Decls {
+            trait_decls,
+            ..self.unindexed()
+        }
+    }
+
+    /// These decls with their impls replaced by `impl_decls`.
+    pub fn with_impl_decls(self, impl_decls: Vec<ImplDecl>) -> Self {
+        Decls {
+            impl_decls,
+            ..self.unindexed()
+        }
+    }
+
+    /// These decls with their negative impls replaced by `neg_impl_decls`.
+    pub fn with_neg_impl_decls(self, neg_impl_decls: Vec<NegImplDecl>) -> Self {
+        Decls {
+            neg_impl_decls,
+            ..self.unindexed()
+        }
+    }
+
+    /// These decls with their alias rules replaced by `alias_eq_decls`.
+    pub fn with_alias_eq_decls(self, alias_eq_decls: Vec<AliasEqDecl>) -> Self {
+        Decls {
+            alias_eq_decls,
+            ..self.unindexed()
+        }
+    }
+
+    /// These decls with their alias bounds replaced by `alias_bound_decls`.
+    pub fn with_alias_bound_decls(self, alias_bound_decls: Vec<AliasBoundDecl>) -> Self {
+        Decls {
+            alias_bound_decls,
+            ..self.unindexed()
+        }
+    }
+
+    /// These decls with their type aliases replaced by `type_alias_decls`.
+    pub fn with_type_alias_decls(self, type_alias_decls: Vec<TypeAliasDecl>) -> Self {
+        Decls {
+            type_alias_decls,
+            ..self.unindexed()
+        }
+    }
+
+    /// These decls with their fns replaced by `fn_decls`.
+    pub fn with_fn_decls(self, fn_decls: Vec<FnDecl>) -> Self {
+        Decls {
+            fn_decls,
+            ..self.unindexed()
+        }
+    }
+
+    /// These decls with their ADTs replaced by `adt_decls`.
+    pub fn with_adt_decls(self, adt_decls: Vec<AdtDecl>) -> Self {
+        Decls {
+            adt_decls,
+            ..self.unindexed()
+        }
+    }
+
+    /// These decls without the index they share with their clones, to change them.
+    fn unindexed(self) -> Self {
+        Decls {
+            index: DeclsIndex::default(),
+            ..self
+        }
+    }
+
+    /// These decls with `impl_decls` added, to ask what would hold if some crate
+    /// added those impls.
+    pub fn with_hypothetical_impls(&self, impl_decls: impl IntoIterator<Item = ImplDecl>) -> Self {
+        let mut decls = self.clone().unindexed();
+        decls.impl_decls.extend(impl_decls);
+        decls
     }
 
     pub fn empty() -> Self {
         Self {
-            max_size: Decls::DEFAULT_MAX_SIZE,
+            config: SolverConfig::default(),
             trait_decls: vec![],
             impl_decls: vec![],
             neg_impl_decls: vec![],
             alias_eq_decls: vec![],
             alias_bound_decls: vec![],
+            type_alias_decls: vec![],
+            fn_decls: vec![],
+            adt_decls: vec![],
             local_trait_ids: set![],
             local_adt_ids: set![],
+            dyn_compatible_trait_ids: set![],
+            index: DeclsIndex::default(),
         }
     }
 }
@@ -261,3 +600,64 @@ pub struct AliasBoundDeclBoundData {
     pub ensures: Binder<Wc>,
     pub where_clause: Wcs,
 }
+
+/// A "type alias decl" declares a free type alias like `type Foo<T> = Vec<T> where T: Clone`.
+/// The alias `(alias Foo X)` is equal to `Vec<X>`, provided that `X: Clone`.
+#[term(type $id $binder)]
+pub struct TypeAliasDecl {
+    /// The name of the type alias
+    pub id: TypeAliasId,
+
+    /// The binder captures the generics of the type alias.
+    pub binder: Binder<TypeAliasDeclBoundData>,
+}
+
+/// The "bound data" for a [`TypeAliasDecl`][].
+#[term(= $ty where $where_clause)]
+pub struct TypeAliasDeclBoundData {
+    /// The type the alias expands to
+    pub ty: Ty,
+
+    /// The where-clauses that must hold for the alias to be well formed
+    pub where_clause: Wcs,
+}
+
+/// An "ADT decl" declares a struct or enum and the where-clauses that must hold for
+/// its types to be well formed, like `struct Foo<T: Copy>`. Its fields are not needed.
+#[term(adt $id $binder)]
+pub struct AdtDecl {
+    /// The name of the ADT
+    pub id: AdtId,
+
+    /// The binder captures the generics of the ADT.
+    pub binder: Binder<AdtDeclBoundData>,
+}
+
+/// The "bound data" for an [`AdtDecl`][].
+#[term(where $where_clause)]
+pub struct AdtDeclBoundData {
+    /// The where-clauses declared on the ADT, along with the outlives bounds implied by
+    /// its fields (see [`Ty::implied_outlives`]), as rustc infers them.
+    pub where_clause: Wcs,
+}
+
+/// A "fn decl" declares the signature of a fn item, which is needed to coerce
+/// the fn item to a fn pointer.
+#[term(extern $abi fn $id $binder)]
+pub struct FnDecl {
+    /// The ABI the fn is called with
+    pub abi: Abi,
+
+    /// The name of the fn
+    pub id: FnId,
+
+    /// The binder captures the generics of the fn.
+    pub binder: Binder<FnDeclBoundData>,
+}
+
+/// The "bound data" for a [`FnDecl`][] -- i.e., the signature of the fn.
+#[term(($,input_tys) -> $output_ty)]
+pub struct FnDeclBoundData {
+    pub input_tys: Vec<Ty>,
+    pub output_ty: Ty,
+}
@@ -0,0 +1,173 @@
+use std::sync::{Arc, OnceLock};
+
+use formality_macros::Cast;
+use formality_types::{
+    collections::{Map, Set},
+    fold::{Fold, SubstitutionFn},
+    generate::{Generate, Generator},
+    grammar::{AdtId, AliasName, FnId, TraitId, TypeAliasId, Variable},
+    parse::{expect_char, Parse, ParseResult, Scope},
+    pretty::Pretty,
+    term::Term,
+    visit::{VarFlags, Visit},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{Decls, TraitInvariant};
+
+/// The decls of a [`Decls`] by what the prover looks them up by: impls by the trait
+/// they implement, alias rules by the alias they are about, and so on. Built on first
+/// use, from the decls it is part of, and shared by their clones.
+///
+/// The [stable hash](`Decls::stable_hash`) of the decls is computed once and shared
+/// the same way.
+///
+/// Being a function of the decls, an index is equal to any other, and written `..`.
+#[derive(Clone, Default, Serialize, Deserialize, Cast)]
+pub struct DeclsIndex {
+    #[serde(skip)]
+    tables: Arc<OnceLock<IndexTables>>,
+
+    #[serde(skip)]
+    stable_hash: Arc<OnceLock<u64>>,
+}
+
+/// The positions of the decls in the vectors of [`Decls`] by key, in the order of
+/// the vectors, so that lookups find the decls in the same order as a scan would.
+pub(super) struct IndexTables {
+    pub trait_decls: Map<TraitId, Vec<usize>>,
+    pub impl_decls: Map<TraitId, Vec<usize>>,
+    pub neg_impl_decls: Map<TraitId, Vec<usize>>,
+    pub alias_eq_decls: Map<AliasName, Vec<usize>>,
+    pub alias_bound_decls: Map<AliasName, Vec<usize>>,
+    pub type_alias_decls: Map<TypeAliasId, Vec<usize>>,
+    pub fn_decls: Map<FnId, Vec<usize>>,
+    pub adt_decls: Map<AdtId, Vec<usize>>,
+    pub trait_invariants: Set<TraitInvariant>,
+}
+
+impl DeclsIndex {
+    pub(super) fn tables(&self, decls: &Decls) -> &IndexTables {
+        self.tables.get_or_init(|| IndexTables::new(decls))
+    }
+
+    pub(super) fn stable_hash(&self, hash: impl FnOnce() -> u64) -> u64 {
+        *self.stable_hash.get_or_init(hash)
+    }
+}
+
+impl IndexTables {
+    fn new(decls: &Decls) -> Self {
+        IndexTables {
+            trait_decls: positions_by(&decls.trait_decls, |t| t.id.clone()),
+            impl_decls: positions_by(&decls.impl_decls, |i| {
+                i.binder.peek().trait_ref.trait_id.clone()
+            }),
+            neg_impl_decls: positions_by(&decls.neg_impl_decls, |i| {
+                i.binder.peek().trait_ref.trait_id.clone()
+            }),
+            alias_eq_decls: positions_by(&decls.alias_eq_decls, |a| a.alias_name()),
+            alias_bound_decls: positions_by(&decls.alias_bound_decls, |a| a.alias_name()),
+            type_alias_decls: positions_by(&decls.type_alias_decls, |t| t.id.clone()),
+            fn_decls: positions_by(&decls.fn_decls, |f| f.id.clone()),
+            adt_decls: positions_by(&decls.adt_decls, |a| a.id.clone()),
+            trait_invariants: decls
+                .trait_decls
+                .iter()
+                .flat_map(|td| td.trait_invariants())
+                .collect(),
+        }
+    }
+}
+
+fn positions_by<D, K: Ord>(decls: &[D], key: impl Fn(&D) -> K) -> Map<K, Vec<usize>> {
+    let mut positions: Map<K, Vec<usize>> = Map::new();
+    for (position, decl) in decls.iter().enumerate() {
+        positions.entry(key(decl)).or_default().push(position);
+    }
+    positions
+}
+
+/// The decls among `decls` whose key is `key`, given their `positions` by key.
+pub(super) fn decls_at<'s, D, K: Ord>(
+    decls: &'s [D],
+    positions: &'s Map<K, Vec<usize>>,
+    key: &K,
+) -> impl Iterator<Item = &'s D> {
+    positions
+        .get(key)
+        .into_iter()
+        .flatten()
+        .map(move |&position| &decls[position])
+}
+
+impl PartialEq for DeclsIndex {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for DeclsIndex {}
+
+impl PartialOrd for DeclsIndex {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DeclsIndex {
+    fn cmp(&self, _other: &Self) -> std::cmp::Ordering {
+        std::cmp::Ordering::Equal
+    }
+}
+
+impl std::hash::Hash for DeclsIndex {
+    fn hash<H: std::hash::Hasher>(&self, _state: &mut H) {}
+}
+
+impl std::fmt::Debug for DeclsIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "..")
+    }
+}
+
+impl Term for DeclsIndex {}
+
+impl Pretty for DeclsIndex {}
+
+/// The index of the decls it is part of, once they are folded, is built anew.
+impl Fold for DeclsIndex {
+    fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {
+        DeclsIndex::default()
+    }
+}
+
+impl Visit for DeclsIndex {
+    fn free_variables(&self) -> Vec<Variable> {
+        vec![]
+    }
+
+    fn var_flags(&self) -> VarFlags {
+        VarFlags::NONE
+    }
+
+    fn size(&self) -> usize {
+        0
+    }
+
+    fn assert_valid(&self) {}
+}
+
+impl Parse for DeclsIndex {
+    fn parse<'t>(_scope: &Scope, text: &'t str) -> ParseResult<'t, Self> {
+        let ((), text) = expect_char('.', text)?;
+        let ((), text) = expect_char('.', text)?;
+        Ok((DeclsIndex::default(), text))
+    }
+}
+
+impl Generate for DeclsIndex {
+    fn generate(_g: &mut Generator) -> Self {
+        DeclsIndex::default()
+    }
+}
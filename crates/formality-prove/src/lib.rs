@@ -4,10 +4,30 @@ mod db;
 mod decls;
 mod prove;
 
+pub use db::{active_query_cache, with_query_cache, QueryCache};
 pub use decls::*;
+pub use formality_types::judgment::proof_tree::{self, FailureTrace, ProofTree};
+pub use prove::canonicalize;
+pub use prove::elaborate;
+pub use prove::instantiate_response;
 pub use prove::prove;
+pub use prove::prove_canonical_goal;
+pub use prove::prove_failure_trace;
+pub use prove::prove_lazily;
+pub use prove::prove_result;
+pub use prove::prove_with_proof_trees;
+pub use prove::prove_with_search_strategy;
+pub use prove::AmbiguousConstraints;
+pub use prove::CanonicalGoal;
+pub use prove::CanonicalResponse;
 pub use prove::Constraints;
 pub use prove::Env;
+pub use prove::OccursCheck;
+pub use prove::ProofResult;
+pub use prove::RegionConstraint;
+pub use prove::Quantifier;
+pub use prove::Solutions;
+pub use prove::Unknowable;
 
 #[cfg(test)]
 mod test;
@@ -1,25 +1,81 @@
+mod candidates;
+mod canonical;
+mod combinators;
 mod constraints;
+mod elaborate;
 mod env;
 mod is_local;
 mod minimize;
+mod proof_result;
 mod prove_after;
+mod prove_coerce;
 mod prove_eq;
 mod prove_normalize;
+mod prove_outlives;
+mod prove_receiver;
 mod prove_via;
 mod prove_wc;
 mod prove_wc_list;
 mod prove_wf;
-mod combinators;
+mod solutions;
+
+pub use canonical::{
+    canonicalize, instantiate_response, prove_canonical_goal, CanonicalGoal, CanonicalResponse,
+    Quantifier,
+};
+pub use constraints::{Constraints, RegionConstraint};
+pub use elaborate::elaborate;
+pub use proof_result::{AmbiguousConstraints, OccursCheck, ProofResult, Unknowable};
+pub use solutions::{prove_lazily, Solutions};
+use std::cell::Cell;
 
-pub use constraints::Constraints;
-use formality_types::{cast::Upcast, collections::Set, grammar::Wcs, set, visit::Visit};
+use formality_types::{
+    cast::Upcast,
+    collections::Set,
+    fixed_point,
+    grammar::{validate_debruijn, Relation, Wc, WcData, Wcs, PR},
+    judgment::{
+        cancel, parallel,
+        proof_tree::{self, Derivations, FailureTrace, ProofTree},
+    },
+    set,
+    visit::Visit,
+};
 use tracing::Level;
 
-use crate::decls::Decls;
+use crate::{
+    db,
+    decls::{BuiltinSearchStrategy, Decls, SearchStrategy, SolverConfig},
+};
 
 pub use self::env::Env;
 use self::prove_wc_list::prove_wc_list;
 
+thread_local! {
+    /// The number of nested calls to [`prove`], limited by [`SolverConfig::max_depth`](`crate::SolverConfig::max_depth`).
+    ///
+    /// The depth is not part of the inputs of the judgments, so the outputs of those that
+    /// exceeded it are not memoized (see [`fixed_point::mark_context_dependent`]): the
+    /// same goals may well be proven at a shallower depth in another proof.
+    static DEPTH: Cell<usize> = Cell::new(0)
+}
+
+/// Restores [`DEPTH`] to its previous value when dropped, even when unwinding.
+struct RestoreDepth(usize);
+
+impl RestoreDepth {
+    /// Sets [`DEPTH`] to `depth` until the returned guard is dropped.
+    fn set(depth: usize) -> RestoreDepth {
+        RestoreDepth(DEPTH.with(|d| d.replace(depth)))
+    }
+}
+
+impl Drop for RestoreDepth {
+    fn drop(&mut self) {
+        DEPTH.with(|d| d.set(self.0));
+    }
+}
+
 /// Top-level entry point for proving things; other rules recurse to this one.
 pub fn prove(
     decls: impl Upcast<Decls>,
@@ -32,33 +88,261 @@ pub fn prove(
     let assumptions: Wcs = assumptions.upcast();
     let goal: Wcs = goal.upcast();
 
+    // The outermost calls use the query cache, if there is one (see `crate::db`).
+    let depth = DEPTH.with(|depth| depth.get());
+    if depth == 0 && !proof_tree::is_recording() {
+        if let Some(result) = db::prove_with_active_cache(&decls, &env, &assumptions, &goal) {
+            proof_tree::set_last_call(None);
+            return result;
+        }
+    }
+
+    // The strategy only bounds the outermost call; the calls it makes search depth-first.
+    let search_strategy = decls.config.search_strategy;
+    if depth == 0 && search_strategy != BuiltinSearchStrategy::DepthFirst {
+        return prove_with_search_strategy(&decls, &env, &assumptions, &goal, &search_strategy);
+    }
+
+    // Goals `a = a` hold whatever `a` is. Dropping them up front spares walking `a`
+    // through the rules of `prove_eq`, and keeps identical terms that exceed the
+    // max size from overflowing.
+    let goal: Wcs = goal.into_iter().filter(|wc| !is_trivial_eq(wc)).collect();
+
+    // Minimizing also canonicalizes the goal: its variables are renumbered in order of
+    // appearance and the rest of the env is dropped. Goals that only differ in that
+    // respect thus share the entries of the judgments' memo tables (see `fixed_point`).
     let (env, (assumptions, goal), min) = minimize::minimize(env, (assumptions, goal));
 
     let span = tracing::span!(Level::DEBUG, "prove", ?goal, ?assumptions, ?env, ?decls);
     let _guard = span.enter();
 
+    // When recording proof trees, `prove` shows up as a node of its own, with the
+    // (minimized) goal as input and the derivation of `prove_wc_list` as its premise.
+    let inputs = proof_tree::is_recording().then(|| {
+        vec![
+            ("goal", format!("{goal:?}")),
+            ("assumptions", format!("{assumptions:?}")),
+            ("env", format!("{env:?}")),
+        ]
+    });
+
     let term_in = (&assumptions, &goal);
-    if term_in.size() > decls.max_size {
+    if term_in.size() > decls.config.max_size || depth >= decls.config.max_depth {
         tracing::debug!(
-            "term has size {} which exceeds max size of {}",
+            "term has size {} and depth {}, which exceeds max size {} or max depth {}",
             term_in.size(),
-            decls.max_size
+            depth,
+            decls.config.max_size,
+            decls.config.max_depth,
         );
-        return set![Constraints::none(env).ambiguous()];
+        if depth >= decls.config.max_depth {
+            fixed_point::mark_context_dependent();
+        }
+        let result = min.reconstitute(Constraints::none(env).overflow());
+        proof_tree::set_last_call(inputs.map(|inputs| {
+            let mut derivations = Derivations::default();
+            derivations.insert(
+                &result,
+                vec![prove_node(inputs, "overflow", &result, vec![])],
+                true,
+            );
+            derivations
+        }));
+        return set![result];
     }
 
     assert!(env.encloses(term_in));
 
-    let result_set = prove_wc_list(decls, &env, assumptions, goal);
+    // Bound variables that escape the goal would silently give wrong results.
+    if cfg!(debug_assertions) {
+        for term in [&assumptions, &goal] {
+            if let Err(e) = validate_debruijn(term) {
+                panic!("prove: {e} in {term:?}");
+            }
+        }
+    }
+
+    let assumptions = elaborate::elaborate(&decls, &assumptions);
+
+    let result_set = {
+        let _depth = RestoreDepth::set(depth + 1);
+        prove_wc_list(decls, &env, assumptions, goal)
+    };
+    let inner_derivations = proof_tree::take_last_call().unwrap_or_default();
 
     result_set.iter().for_each(|constraints1| {
         assert!(constraints1.is_valid_extension_of(&env));
+        if cfg!(debug_assertions) {
+            if let Err(e) = validate_debruijn(constraints1.substitution()) {
+                panic!("prove: {e} in the result {constraints1:?}");
+            }
+        }
     });
 
     tracing::debug!(?result_set);
 
-    result_set
+    let mut derivations = Derivations::default();
+    let result = result_set
         .into_iter()
-        .map(|r| min.reconstitute(r))
+        .map(|r| {
+            let c = min.reconstitute(r.clone());
+            if let Some(inputs) = &inputs {
+                let premises = inner_derivations.premises(&r).unwrap_or_default().to_vec();
+                derivations.insert(
+                    &c,
+                    vec![prove_node(inputs.clone(), "prove", &c, premises)],
+                    inner_derivations.is_complete(&r),
+                );
+            }
+            c
+        })
+        .collect();
+    proof_tree::set_last_call(inputs.map(|_| derivations));
+    result
+}
+
+/// Proves `goal` depth-first within each of the depth bounds of `search_strategy` in
+/// turn, instead of the strategy of `decls`. Commits to the first bound within which
+/// some solution is found without overflow, dropping the overflows of that search.
+pub fn prove_with_search_strategy(
+    decls: impl Upcast<Decls>,
+    env: impl Upcast<Env>,
+    assumptions: impl Upcast<Wcs>,
+    goal: impl Upcast<Wcs>,
+    search_strategy: &dyn SearchStrategy,
+) -> Set<Constraints> {
+    let decls: Decls = decls.upcast();
+    let env: Env = env.upcast();
+    let assumptions: Wcs = assumptions.upcast();
+    let goal: Wcs = goal.upcast();
+
+    let config = &decls.config;
+    let prove_within = |max_depth: usize| {
+        let decls = decls.clone().with_config(SolverConfig {
+            max_depth,
+            search_strategy: BuiltinSearchStrategy::DepthFirst,
+            ..config.clone()
+        });
+        prove(decls, &env, &assumptions, &goal)
+    };
+
+    let bounds = search_strategy.depth_bounds(config.max_depth);
+    let (last, shallower) = bounds.split_last().unwrap();
+    for &max_depth in shallower {
+        let result = prove_within(max_depth);
+        if result.iter().any(|c| !c.overflow) {
+            return result.into_iter().filter(|c| !c.overflow).collect();
+        }
+    }
+    prove_within(*last)
+}
+
+fn prove_node(
+    inputs: Vec<(&'static str, String)>,
+    rule: &'static str,
+    output: &Constraints,
+    children: Vec<ProofTree>,
+) -> ProofTree {
+    ProofTree {
+        judgment: "prove",
+        inputs,
+        rule,
+        output: format!("{output:?}"),
+        children,
+    }
+}
+
+/// Maps `op` over `items` with [`parallel::map`], in parallel if [`SolverConfig::parallel`]
+/// is set. `op` runs at the depth of the caller, whichever thread it runs on.
+fn map_in_parallel<T, R>(decls: &Decls, items: &[T], op: impl Fn(&T) -> R + Sync + Send) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    let depth = DEPTH.with(|depth| depth.get());
+    parallel::map(decls.config.parallel, items, |item| {
+        let _depth = RestoreDepth::set(depth);
+        op(item)
+    })
+}
+
+/// True if `wc` is `a = a` for some `a`.
+fn is_trivial_eq(wc: &Wc) -> bool {
+    matches!(wc.data(), WcData::PR(PR::Relation(Relation::Equals(a, b))) if a == b)
+}
+
+/// Like [`prove`], but classifies the result as [`ProofResult::Yes`],
+/// [`ProofResult::No`] or [`ProofResult::Ambiguous`], or as [`ProofResult::TimedOut`]
+/// if it is proven under a [cancellation token](`cancel::with_cancellation`) that is
+/// cancelled before the proof finishes.
+///
+/// If the goal does not hold, it is proven again, bypassing the memo tables, to
+/// find the occurs check failures that [`ProofResult::No`] lists. Likewise, if it is
+/// ambiguous in coherence mode, to find the goals that make it unknowable.
+pub fn prove_result(
+    decls: impl Upcast<Decls>,
+    env: impl Upcast<Env>,
+    assumptions: impl Upcast<Wcs>,
+    goal: impl Upcast<Wcs>,
+) -> ProofResult {
+    let decls: Decls = decls.upcast();
+    let env: Env = env.upcast();
+    let assumptions: Wcs = assumptions.upcast();
+    let goal: Wcs = goal.upcast();
+
+    let reprove = || proof_tree::record(|| prove(&decls, &env, &assumptions, &goal));
+    let result = ProofResult::from_constraints(prove(&decls, &env, &assumptions, &goal));
+    if cancel::is_cancelled() {
+        return ProofResult::TimedOut;
+    }
+    let result = match result {
+        ProofResult::No(_) => {
+            let (_, occurs_checks) = prove_eq::record_occurs_checks(reprove);
+            ProofResult::No(occurs_checks.into_iter().collect())
+        }
+        ProofResult::Ambiguous(a) if env.is_in_coherence_mode() => {
+            let (_, unknowable) = is_local::record_unknowables(reprove);
+            ProofResult::Ambiguous(AmbiguousConstraints {
+                unknowable: unknowable.into_iter().collect(),
+                ..a
+            })
+        }
+        result => result,
+    };
+    if cancel::is_cancelled() {
+        return ProofResult::TimedOut;
+    }
+    result
+}
+
+/// Like [`prove`], but also records why each result holds: each set of constraints
+/// comes with the proof tree that derived it.
+pub fn prove_with_proof_trees(
+    decls: impl Upcast<Decls>,
+    env: impl Upcast<Env>,
+    assumptions: impl Upcast<Wcs>,
+    goal: impl Upcast<Wcs>,
+) -> Vec<(Constraints, ProofTree)> {
+    let (result, recording) = proof_tree::record(|| prove(decls, env, assumptions, goal));
+    let derivations = recording.derivations.unwrap_or_default();
+    result
+        .into_iter()
+        .map(|c| {
+            let tree = derivations.premises(&c).unwrap()[0].clone();
+            (c, tree)
+        })
         .collect()
 }
+
+/// Proves `goal` again while recording failures, to explain why it could not be proven.
+/// The root goals are the outermost `prove_wc` goals that failed, or the region
+/// constraints, which name the goals they come from.
+pub fn prove_failure_trace(
+    decls: impl Upcast<Decls>,
+    env: impl Upcast<Env>,
+    assumptions: impl Upcast<Wcs>,
+    goal: impl Upcast<Wcs>,
+) -> FailureTrace {
+    let (_, recording) = proof_tree::record(|| prove(decls, env, assumptions, goal));
+    FailureTrace::new(&recording.failures, &["prove_wc", "prove_region_constraint"])
+}
@@ -0,0 +1,180 @@
+//! Candidate selection for trait goals, after rustc: where-clause candidates are
+//! preferred over impl candidates, and several impls that apply in different ways
+//! make the goal ambiguous rather than each yielding its own solution.
+
+use formality_types::{
+    cast::Downcast,
+    collections::Set,
+    grammar::{Parameter, Predicate, Relation, RigidName, RigidTy, TraitRef, WcData, Wcs, PR},
+    judgment::proof_tree::{self, Derivations},
+    judgment_fn,
+};
+
+use crate::{
+    decls::{Decls, ImplDecl},
+    prove::{map_in_parallel, prove, prove_after::prove_after},
+};
+
+use super::{constraints::Constraints, env::Env};
+
+/// True if some where-clause in `assumptions` may prove `trait_ref` directly, i.e.,
+/// names the same trait with parameters that can be equated to those of `trait_ref`.
+/// Whether its own conditions hold is not considered.
+pub fn where_clause_applies(
+    decls: &Decls,
+    env: &Env,
+    assumptions: &Wcs,
+    trait_ref: &TraitRef,
+) -> bool {
+    assumptions
+        .into_iter()
+        .any(|wc| where_clause_head_applies(decls, env, assumptions, wc.data(), trait_ref))
+}
+
+fn where_clause_head_applies(
+    decls: &Decls,
+    env: &Env,
+    assumptions: &Wcs,
+    wc: &WcData,
+    trait_ref: &TraitRef,
+) -> bool {
+    match wc {
+        WcData::PR(PR::Predicate(Predicate::IsImplemented(tr))) => {
+            tr.trait_id == trait_ref.trait_id
+                && !prove(
+                    decls,
+                    env,
+                    assumptions,
+                    Wcs::all_eq(&tr.parameters, &trait_ref.parameters),
+                )
+                .is_empty()
+        }
+        WcData::PR(_) => false,
+        WcData::ForAll(binder) => {
+            let (env, subst) = env.existential_substitution(binder);
+            let wc = binder.instantiate_with(&subst).unwrap();
+            where_clause_head_applies(decls, &env, assumptions, wc.data(), trait_ref)
+        }
+        WcData::Implies(_, consequence) => {
+            where_clause_head_applies(decls, env, assumptions, consequence.data(), trait_ref)
+        }
+    }
+}
+
+/// Proves `trait_ref` with each of the impls of its trait. If more than one impl
+/// applies and they do not all yield the same solution, every solution is ambiguous.
+///
+/// The impls are tried in parallel if [`SolverConfig::parallel`](`crate::SolverConfig::parallel`) is set.
+///
+/// The impls whose parameters cannot be equated to those of `trait_ref`, judging by
+/// their rigid heads alone (see [`impl_may_apply`]), are skipped without being tried.
+///
+/// When recording proof trees, each solution is derived through the [`prove_impl`]
+/// call of the impl it came from, which thus shows which candidate was chosen.
+pub fn impl_candidates(
+    decls: &Decls,
+    env: &Env,
+    assumptions: &Wcs,
+    trait_ref: &TraitRef,
+) -> Set<Constraints> {
+    let reject = !assumes_equalities(assumptions);
+    let impls: Vec<&ImplDecl> = decls
+        .impl_decls(&trait_ref.trait_id)
+        .filter(|i| !reject || impl_may_apply(trait_ref, i))
+        .collect();
+    let candidates: Vec<(Set<Constraints>, Option<Derivations>)> =
+        map_in_parallel(decls, &impls, |i| {
+            let cs = prove_impl(decls, env, assumptions, trait_ref, *i);
+            (cs, proof_tree::take_last_call())
+        })
+        .into_iter()
+        .filter(|(cs, _)| !cs.is_empty())
+        .collect();
+
+    let solutions: Set<&Constraints> = candidates.iter().flat_map(|(cs, _)| cs).collect();
+    let ambiguous = candidates.len() > 1 && solutions.len() > 1;
+
+    let mut result = Set::new();
+    let mut derivations = Derivations::default();
+    for (cs, candidate_derivations) in candidates {
+        for c in cs {
+            let c1 = if ambiguous {
+                c.clone().ambiguous()
+            } else {
+                c.clone()
+            };
+            if let Some(d) = &candidate_derivations {
+                let premises = d.premises(&c).unwrap_or_default().to_vec();
+                derivations.insert(&c1, premises, d.is_complete(&c));
+            }
+            result.insert(c1);
+        }
+    }
+    proof_tree::set_last_call(proof_tree::is_recording().then_some(derivations));
+    result
+}
+
+/// False if the impl `i` cannot prove `trait_ref`, because some parameter of its trait
+/// ref and the corresponding one of `trait_ref` are rigid types with different names,
+/// as `Vec<T>` and `u32`: the names of rigid types must be equal for the types to be.
+/// This is rustc's `fast_reject`, looking at the heads of the types only; variables,
+/// aliases and any other parameters may be equal to anything.
+pub fn impl_may_apply(trait_ref: &TraitRef, i: &ImplDecl) -> bool {
+    let impl_trait_ref = &i.binder.peek().trait_ref;
+    trait_ref
+        .parameters
+        .iter()
+        .zip(&impl_trait_ref.parameters)
+        .all(|(a, b)| match (simplify(a), simplify(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        })
+}
+
+/// The name of `p`, if it is a rigid type.
+fn simplify(p: &Parameter) -> Option<RigidName> {
+    let RigidTy { name, .. } = p.downcast()?;
+    Some(name)
+}
+
+/// True if some where-clause in `assumptions` assumes two parameters equal, which might
+/// equate rigid types with different names.
+fn assumes_equalities(assumptions: &Wcs) -> bool {
+    assumptions
+        .into_iter()
+        .any(|wc| wc_assumes_equality(wc.data()))
+}
+
+fn wc_assumes_equality(wc: &WcData) -> bool {
+    match wc {
+        WcData::PR(PR::Relation(Relation::Equals(..))) => true,
+        WcData::PR(_) => false,
+        WcData::ForAll(binder) => wc_assumes_equality(binder.peek().data()),
+        WcData::Implies(_, consequence) => wc_assumes_equality(consequence.data()),
+    }
+}
+
+judgment_fn! {
+    /// Proves `trait_ref` using the impl `i`.
+    pub fn prove_impl(
+        decls: Decls,
+        env: Env,
+        assumptions: Wcs,
+        trait_ref: TraitRef,
+        i: ImplDecl,
+    ) => Constraints {
+        debug(trait_ref, i, assumptions, env, decls)
+
+        (
+            (let (env, subst) = env.existential_substitution(&i.binder))
+            (let i = i.binder.instantiate_with(&subst).unwrap())
+            (let t = decls.trait_decl(&i.trait_ref.trait_id).binder.instantiate_with(&i.trait_ref.parameters).unwrap())
+            (let co_assumptions = (&assumptions, &trait_ref))
+            (prove(&decls, env, co_assumptions, Wcs::all_eq(&trait_ref.parameters, &i.trait_ref.parameters)) => c)
+            (prove_after(&decls, c, co_assumptions, &i.where_clause) => c)
+            (prove_after(&decls, c, &assumptions, &t.where_clause) => c)
+            ----------------------------- ("impl")
+            (prove_impl(decls, env, assumptions, trait_ref, i) => c.pop_subst(&subst))
+        )
+    }
+}
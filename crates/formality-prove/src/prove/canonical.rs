@@ -0,0 +1,199 @@
+use formality_types::{
+    cast::{To, Upcast},
+    collections::{Deduplicate, Set},
+    grammar::{Binder, Parameter, ParameterKind, Variable, Wcs},
+    visit::Visit,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::decls::Decls;
+
+use super::{constraints::RegionConstraint, prove, Constraints, Env};
+
+/// A goal that stands on its own, without an [`Env`]: its free variables are bound
+/// by `binder`, in the order of their universes.
+///
+/// Create one from a goal in some env with [`canonicalize`], or directly.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+pub struct CanonicalGoal {
+    /// Whether each variable bound by `binder` is universal or existential.
+    pub quantifiers: Vec<Quantifier>,
+    pub coherence_mode: bool,
+    /// The assumptions and the goal.
+    pub binder: Binder<(Wcs, Wcs)>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+pub enum Quantifier {
+    ForAll,
+    Exists,
+}
+
+/// One way in which a [`CanonicalGoal`] may hold, in terms of its variables.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+pub struct CanonicalResponse {
+    pub known_true: bool,
+    pub overflow: bool,
+    /// Whether each variable bound by `var_values` is an existential variable that
+    /// the prover created. The others are the variables of the goal, in order.
+    /// All are in the order of their universes.
+    pub fresh: Vec<bool>,
+    /// The value of each variable of the goal; universal variables and unconstrained
+    /// existential ones are their own value.
+    pub var_values: Binder<Vec<Parameter>>,
+    /// The region constraints on those values, bound like them.
+    pub region_constraints: Binder<Vec<RegionConstraint>>,
+}
+
+impl CanonicalGoal {
+    /// Creates an env with fresh variables for those of the goal, returning it along
+    /// with those variables and the instantiated assumptions and goal.
+    pub fn instantiate(&self) -> (Env, Vec<Variable>, (Wcs, Wcs)) {
+        assert_eq!(self.quantifiers.len(), self.binder.len());
+
+        let mut env = Env::default().with_coherence_mode(self.coherence_mode);
+        let variables: Vec<Variable> = self
+            .quantifiers
+            .iter()
+            .zip(self.binder.kinds())
+            .map(|(quantifier, &kind)| match quantifier {
+                Quantifier::ForAll => env.fresh_universal(kind).upcast(),
+                Quantifier::Exists => env.fresh_existential(kind).upcast(),
+            })
+            .collect();
+        let term = self.binder.instantiate_with(&variables).unwrap();
+        (env, variables, term)
+    }
+}
+
+impl CanonicalResponse {
+    /// Expresses `constraints`, which resulted from proving a goal whose variables
+    /// are `variables`, in terms of those variables.
+    pub fn new(variables: &[Variable], constraints: &Constraints) -> Self {
+        let var_values: Vec<Parameter> = variables
+            .iter()
+            .map(|v| constraints.substitution().apply(&v.to::<Parameter>()))
+            .collect();
+
+        let region_constraints: Vec<RegionConstraint> =
+            constraints.region_constraints.iter().cloned().collect();
+
+        // Keep only the fresh variables that show up in the values.
+        let fv = (&var_values, &region_constraints).free_variables();
+        let bound: Vec<Variable> = constraints
+            .env()
+            .variables()
+            .iter()
+            .copied()
+            .filter(|v| variables.contains(v) || fv.contains(v))
+            .collect();
+
+        CanonicalResponse {
+            known_true: constraints.known_true,
+            overflow: constraints.overflow,
+            fresh: bound.iter().map(|v| !variables.contains(v)).collect(),
+            var_values: Binder::new(&bound, var_values),
+            region_constraints: Binder::new(bound, region_constraints),
+        }
+    }
+}
+
+/// Canonicalizes proving `goal` under `assumptions` in `env`. Returns the canonical
+/// goal along with the variables of `env` it binds, which [`instantiate_response`]
+/// needs to map the responses back into `env`.
+pub fn canonicalize(
+    env: &Env,
+    assumptions: impl Upcast<Wcs>,
+    goal: impl Upcast<Wcs>,
+) -> (CanonicalGoal, Vec<Variable>) {
+    let term: (Wcs, Wcs) = (assumptions.upcast(), goal.upcast());
+    let mut variables = term.free_variables().deduplicate();
+    variables.sort_by_key(|&v| env.universe(v));
+
+    let goal = CanonicalGoal {
+        quantifiers: variables
+            .iter()
+            .map(|v| {
+                if v.is_universal() {
+                    Quantifier::ForAll
+                } else {
+                    Quantifier::Exists
+                }
+            })
+            .collect(),
+        coherence_mode: env.is_in_coherence_mode(),
+        binder: Binder::new(variables.clone(), term),
+    };
+    (goal, variables)
+}
+
+/// Maps `response` back into the `env` that a goal was canonicalized from, where
+/// `variables` are those returned by [`canonicalize`]. The resulting constraints
+/// extend `env` with the fresh variables of the response.
+pub fn instantiate_response(
+    env: &Env,
+    variables: &[Variable],
+    response: &CanonicalResponse,
+) -> Constraints {
+    let mut env = env.clone();
+    let mut variables_iter = variables.iter();
+    let mut parameters: Vec<Option<Parameter>> = vec![None; response.fresh.len()];
+
+    // Fresh variables are placed right before the next variable of the goal, so they
+    // keep their universes relative to the goal's variables.
+    let mut pending: Vec<(usize, ParameterKind)> = vec![];
+    for (index, (&fresh, &kind)) in response
+        .fresh
+        .iter()
+        .zip(response.var_values.kinds())
+        .enumerate()
+    {
+        if fresh {
+            pending.push((index, kind));
+        } else {
+            let v = *variables_iter.next().unwrap();
+            for (fresh_index, kind) in pending.drain(..) {
+                let fresh_var = env.insert_fresh_before(kind, env.universe(v));
+                parameters[fresh_index] = Some(fresh_var.upcast());
+            }
+            parameters[index] = Some(v.upcast());
+        }
+    }
+    assert!(variables_iter.next().is_none());
+    for (fresh_index, kind) in pending {
+        parameters[fresh_index] = Some(env.fresh_existential(kind).upcast());
+    }
+
+    let parameters: Vec<Parameter> = parameters.into_iter().map(Option::unwrap).collect();
+    let var_values = response.var_values.instantiate_with(&parameters).unwrap();
+    let region_constraints = response
+        .region_constraints
+        .instantiate_with(&parameters)
+        .unwrap();
+    let substitution: Vec<(Variable, Parameter)> = variables
+        .iter()
+        .copied()
+        .zip(var_values)
+        .filter(|(v, p)| v.to::<Parameter>() != *p)
+        .collect();
+
+    Constraints {
+        known_true: response.known_true,
+        overflow: response.overflow,
+        region_constraints: region_constraints.into_iter().collect(),
+        ..Constraints::from(env, substitution)
+    }
+}
+
+/// Proves a canonical goal, for callers that have no [`Env`] of their own.
+pub fn prove_canonical_goal(
+    decls: impl Upcast<Decls>,
+    goal: &CanonicalGoal,
+) -> Vec<CanonicalResponse> {
+    let (env, variables, (assumptions, goal)) = goal.instantiate();
+    let responses: Set<CanonicalResponse> = prove(decls, env, assumptions, goal)
+        .iter()
+        .map(|c| CanonicalResponse::new(&variables, c))
+        .collect();
+    responses.into_iter().collect()
+}
@@ -1,4 +1,11 @@
-use formality_types::{cast::Upcast, collections::Set, set, term::Term};
+use formality_types::{
+    cast::Upcast,
+    collections::Set,
+    fold::Fold,
+    judgment::proof_tree::{self, Derivations},
+    set,
+    term::Term,
+};
 
 use crate::decls::Decls;
 
@@ -20,22 +27,21 @@ where
     assert_eq!(a.len(), b.len());
 
     if a.is_empty() && b.is_empty() {
-        return set![Constraints::none(env.upcast())];
+        return nothing_to_prove(env);
     }
 
     let a0 = a.remove(0);
     let b0 = b.remove(0);
-    op(decls.clone(), env.clone(), context.clone(), a0, b0)
-        .into_iter()
-        .flat_map(|c1| {
-            let context = c1.substitution().apply(context);
-            let a = c1.substitution().apply(&a);
-            let b = c1.substitution().apply(&b);
-            zip(decls, c1.env(), &context, a, b, op)
-                .into_iter()
-                .map(move |c2| c1.seq(c2))
-        })
-        .collect()
+    let cs1 = op(decls.clone(), env.clone(), context.clone(), a0, b0);
+    let mut seq = Seq::new();
+    for c1 in cs1 {
+        let context = c1.substitution().apply(context);
+        let a = c1.substitution().apply(&a);
+        let b = c1.substitution().apply(&b);
+        let cs2 = zip(decls, c1.env(), &context, a, b, op);
+        seq.extend(&c1, cs2);
+    }
+    seq.finish()
 }
 
 pub fn for_all<A, C>(
@@ -46,23 +52,75 @@ pub fn for_all<A, C>(
     op: &impl Fn(Decls, Env, C, A) -> Set<Constraints>,
 ) -> Set<Constraints>
 where
-    A: Term,
+    A: Fold + Clone,
     C: Term,
 {
     if a.is_empty() {
-        return set![Constraints::none(env.upcast())];
+        return nothing_to_prove(env);
     }
 
     let a0 = a[0].clone();
     let a_remaining: Vec<A> = a[1..].to_vec();
-    op(decls.clone(), env.clone(), context.clone(), a0)
-        .into_iter()
-        .flat_map(|c1| {
-            let context = c1.substitution().apply(context);
-            let a_remaining = c1.substitution().apply(&a_remaining);
-            for_all(decls, c1.env(), &context, &a_remaining, op)
-                .into_iter()
-                .map(move |c2| c1.seq(c2))
-        })
-        .collect()
+    let cs1 = op(decls.clone(), env.clone(), context.clone(), a0);
+    let mut seq = Seq::new();
+    for c1 in cs1 {
+        let context = c1.substitution().apply(context);
+        let a_remaining = c1.substitution().apply(&a_remaining);
+        let cs2 = for_all(decls, c1.env(), &context, &a_remaining, op);
+        seq.extend(&c1, cs2);
+    }
+    seq.finish()
+}
+
+fn nothing_to_prove(env: &Env) -> Set<Constraints> {
+    let c = Constraints::none(env.upcast());
+    if proof_tree::is_recording() {
+        let mut derivations = Derivations::default();
+        derivations.insert(&c, vec![], true);
+        proof_tree::set_last_call(Some(derivations));
+    }
+    set![c]
+}
+
+/// Sequences the results of a first judgment call with the results of the calls
+/// that follow each of them. When recording proof trees, the premises of each
+/// sequenced result are those of both of its parts.
+struct Seq {
+    derivations1: Option<Derivations>,
+    derivations: Derivations,
+    result: Set<Constraints>,
+}
+
+impl Seq {
+    /// Must be created right after the first judgment call.
+    fn new() -> Self {
+        Seq {
+            derivations1: proof_tree::take_last_call(),
+            derivations: Derivations::default(),
+            result: Set::new(),
+        }
+    }
+
+    /// Must be called right after the call that produced `cs2`.
+    fn extend(&mut self, c1: &Constraints, cs2: Set<Constraints>) {
+        let derivations2 = proof_tree::take_last_call();
+        for c2 in cs2 {
+            let c = c1.seq(&c2);
+            if let (Some(derivations1), Some(derivations2)) = (&self.derivations1, &derivations2) {
+                let premises = [derivations1.premises(c1), derivations2.premises(&c2)]
+                    .into_iter()
+                    .flat_map(|premises| premises.unwrap_or_default())
+                    .cloned()
+                    .collect();
+                let complete = derivations1.is_complete(c1) && derivations2.is_complete(&c2);
+                self.derivations.insert(&c, premises, complete);
+            }
+            self.result.insert(c);
+        }
+    }
+
+    fn finish(self) -> Set<Constraints> {
+        proof_tree::set_last_call(self.derivations1.map(|_| self.derivations));
+        self.result
+    }
 }
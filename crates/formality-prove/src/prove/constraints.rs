@@ -1,21 +1,102 @@
+use formality_macros::Cast;
 use formality_types::{
     cast::{Downcast, Upcast},
-    cast_impl,
+    collections::Set,
     derive_links::UpcastFrom,
+    fold::{Fold, SubstitutionFn},
     grammar::{ExistentialVar, Parameter, Substitution, Variable},
-    visit::Visit,
+    visit::{VarFlags, Visit},
 };
 
+use serde::{Deserialize, Serialize};
+
 use super::env::Env;
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize, Cast)]
 pub struct Constraints {
     pub env: Env,
     pub known_true: bool,
+
+    /// True if the prover gave up on some subgoal because it exceeded the limits
+    /// of its [`SolverConfig`](`crate::SolverConfig`); implies `!known_true`.
+    pub overflow: bool,
+
     pub substitution: Substitution,
+
+    /// The outlives relations between lifetimes that could not be decided yet, as they
+    /// involve existential variables; see [`RegionConstraint`]. The substitution has
+    /// been applied to them.
+    pub region_constraints: Set<RegionConstraint>,
+}
+
+/// An outlives relation `a: b` between lifetimes that involves existential variables,
+/// recorded by the "existential" rule of `prove_outlives` rather than decided then and
+/// there. It is proven once its variables are known (see `prove_after`), and dropped
+/// along with its variables if they never are, as they can always be chosen so that it
+/// holds on its own (see [`Constraints::pop_subst`]).
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Cast)]
+pub struct RegionConstraint {
+    pub a: Parameter,
+    pub b: Parameter,
+
+    /// The goal that introduced the constraint, i.e., the innermost goal other than an
+    /// outlives relation that it was found proving, for reporting. `None` until that
+    /// goal is proven.
+    pub origin: Option<String>,
+}
+
+impl RegionConstraint {
+    pub fn new(a: impl Upcast<Parameter>, b: impl Upcast<Parameter>) -> Self {
+        RegionConstraint {
+            a: a.upcast(),
+            b: b.upcast(),
+            origin: None,
+        }
+    }
+
+    /// Whether the constraint mentions no existential variables, so it can be proven.
+    fn is_ready(&self) -> bool {
+        !self.var_flags().contains(VarFlags::EXISTENTIAL)
+    }
+}
+
+impl std::fmt::Debug for RegionConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} : {:?}", self.a, self.b)?;
+        if let Some(origin) = &self.origin {
+            write!(f, " from {origin}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Visit for RegionConstraint {
+    fn free_variables(&self) -> Vec<Variable> {
+        (&self.a, &self.b).free_variables()
+    }
+
+    fn var_flags(&self) -> VarFlags {
+        (&self.a, &self.b).var_flags()
+    }
+
+    fn size(&self) -> usize {
+        (&self.a, &self.b).size()
+    }
+
+    fn assert_valid(&self) {
+        (&self.a, &self.b).assert_valid()
+    }
 }
 
-cast_impl!(Constraints);
+impl Fold for RegionConstraint {
+    fn substitute(&self, substitution_fn: SubstitutionFn<'_>) -> Self {
+        RegionConstraint {
+            a: self.a.substitute(substitution_fn),
+            b: self.b.substitute(substitution_fn),
+            origin: self.origin.clone(),
+        }
+    }
+}
 
 impl<A, B> UpcastFrom<(Env, (A, B))> for Constraints
 where
@@ -41,7 +122,7 @@ impl Constraints {
     }
 
     pub fn unconditionally_true(&self) -> bool {
-        self.known_true && self.substitution.is_empty()
+        self.known_true && self.substitution.is_empty() && self.region_constraints.is_empty()
     }
 
     pub fn from(
@@ -55,6 +136,8 @@ impl Constraints {
             env,
             substitution,
             known_true: true,
+            overflow: false,
+            region_constraints: Set::new(),
         };
         c2.assert_valid();
         c2
@@ -68,6 +151,68 @@ impl Constraints {
         &self.substitution
     }
 
+    /// Adds the region constraint `a: b`, see [`RegionConstraint`].
+    pub fn with_region_constraint(
+        mut self,
+        a: impl Upcast<Parameter>,
+        b: impl Upcast<Parameter>,
+    ) -> Constraints {
+        let constraint = self.substitution.apply(&RegionConstraint::new(a, b));
+        self.region_constraints.insert(constraint);
+        self
+    }
+
+    /// Records `origin` as the origin of the region constraints that have none yet.
+    pub fn with_region_origin(mut self, origin: &impl std::fmt::Debug) -> Constraints {
+        if self.region_constraints.iter().all(|rc| rc.origin.is_some()) {
+            return self;
+        }
+        let origin = format!("{origin:?}");
+        self.region_constraints = std::mem::take(&mut self.region_constraints)
+            .into_iter()
+            .map(|rc| RegionConstraint {
+                origin: rc.origin.or_else(|| Some(origin.clone())),
+                ..rc
+            })
+            .collect();
+        self
+    }
+
+    /// Removes the region constraints that mention no existential variables, which can
+    /// now be proven, and returns them.
+    pub fn take_ready_region_constraints(mut self) -> (Constraints, Vec<RegionConstraint>) {
+        let (ready, pending): (Set<_>, Set<_>) = std::mem::take(&mut self.region_constraints)
+            .into_iter()
+            .partition(|rc| rc.is_ready());
+        self.region_constraints = pending;
+        (self, ready.into_iter().collect())
+    }
+
+    /// Drops the region constraints that mention the existential variable `v`, adding
+    /// `a: b` for each `a: v` and `v: b` among them: if those hold, `v` can be chosen to
+    /// be `a` (or `b`), and if `v` has no bound on one side, it can be chosen to be
+    /// whatever the other bounds are. The constraints added are ready, if `v` was the
+    /// only existential variable of theirs, see [`Self::take_ready_region_constraints`].
+    fn eliminate_region_var(&mut self, v: Variable) {
+        let v: Parameter = v.upcast();
+        let (mention, rest): (Set<RegionConstraint>, Set<RegionConstraint>) =
+            std::mem::take(&mut self.region_constraints)
+                .into_iter()
+                .partition(|rc| rc.a == v || rc.b == v);
+        self.region_constraints = rest;
+        for below in mention.iter().filter(|rc| rc.b == v) {
+            for above in mention.iter().filter(|rc| rc.a == v) {
+                if below.a != above.b {
+                    self.region_constraints.insert(RegionConstraint {
+                        a: below.a.clone(),
+                        b: above.b.clone(),
+                        origin: above.origin.clone().or_else(|| below.origin.clone()),
+                    });
+                }
+            }
+        }
+    }
+
     pub fn ambiguous(self) -> Constraints {
         Self {
             known_true: false,
@@ -75,6 +220,16 @@ impl Constraints {
         }
     }
 
+    /// Like [`Self::ambiguous`], but the goal could not be decided because the
+    /// prover exceeded its limits.
+    pub fn overflow(self) -> Constraints {
+        Self {
+            known_true: false,
+            overflow: true,
+            ..self
+        }
+    }
+
     /// Given constraings from solving the subparts of `(A /\ B)`, yield combined constraints.
     ///
     /// # Parameters
@@ -105,12 +260,22 @@ impl Constraints {
 
         // Apply c2's substitution to our substitution (since it may have bound
         // existential variables that we reference)
-        let c1_substitution = c2.substitution.apply(&self.substitution);
+        let substitution = self.substitution.compose(&c2.substitution);
+
+        // Likewise for our region constraints, which are then combined with c2's.
+        let region_constraints = c2
+            .substitution
+            .apply(&self.region_constraints)
+            .into_iter()
+            .chain(c2.region_constraints)
+            .collect();
 
         Constraints {
             env: c2.env,
             known_true: self.known_true && c2.known_true,
-            substitution: c1_substitution.into_iter().chain(c2.substitution).collect(),
+            overflow: self.overflow || c2.overflow,
+            substitution,
+            region_constraints,
         }
     }
 
@@ -122,15 +287,103 @@ impl Constraints {
             return self;
         }
 
+        self = self.eliminate_region_vars(v);
         let vars = self.env.pop_vars(v);
+        assert!(
+            self.region_constraints
+                .free_variables()
+                .iter()
+                .all(|v| !vars.contains(v)),
+            "region constraints escape the scope of their variables: {:?}",
+            self.region_constraints
+        );
+
         self.substitution -= vars;
 
         self
     }
 
+    /// Eliminates the existential variables that [`Self::pop_subst`] would pop along
+    /// with `v` from the region constraints, see [`Self::eliminate_region_var`].
+    pub fn eliminate_region_vars<V>(mut self, v: &[V]) -> Self
+    where
+        V: Upcast<Variable> + Copy,
+    {
+        let Some(&v0) = v.first() else {
+            return self;
+        };
+        let v0: Variable = v0.upcast();
+        let start = self
+            .env
+            .variables()
+            .iter()
+            .position(|&var| var == v0)
+            .unwrap();
+        let popped: Vec<Variable> = self.env.variables()[start..].to_vec();
+
+        // Innermost first, as the constraints added may mention the outer ones.
+        for var in popped.into_iter().rev() {
+            if var.is_a::<ExistentialVar>() && self.substitution.get(var).is_none() {
+                self.eliminate_region_var(var);
+            }
+        }
+        self
+    }
+
+    /// Whether some region constraint mentions one of `vars`, which would escape their
+    /// scope if they were popped.
+    pub fn region_constraints_mention<V>(&self, vars: &[V]) -> bool
+    where
+        V: Upcast<Variable> + Copy,
+    {
+        let free_variables = self.region_constraints.free_variables();
+        vars.iter().any(|&v| free_variables.contains(&v.upcast()))
+    }
+
     pub fn is_valid_extension_of(&self, env0: &Env) -> bool {
         self.env.is_valid_extension_of(env0)
     }
+
+    /// Puts constraints that resulted from proving a goal in `env0` in a canonical
+    /// form, for reporting and comparing them: the variables that the prover created
+    /// and that the values of the variables of `env0` do not refer to are dropped,
+    /// along with their values, and the rest are renumbered by [`Env::compress`].
+    pub fn compress(&self, env0: &Env) -> Constraints {
+        assert!(self.is_valid_extension_of(env0));
+
+        let relevant: Vec<Variable> = env0
+            .variables()
+            .iter()
+            .copied()
+            .chain(
+                self.substitution
+                    .iter()
+                    .filter(|&(v, _)| env0.defines(v))
+                    .flat_map(|(_, p)| p.free_variables()),
+            )
+            .chain(self.region_constraints.free_variables())
+            .collect();
+        let mut env = self.env.clone();
+        let irrelevant = env.remove_variables_unless_within(&relevant);
+        let substitution = self.substitution.clone().remove_vars(irrelevant);
+
+        let (env, renaming) = env.compress();
+        let substitution: Substitution = substitution
+            .iter()
+            .map(|(v, p)| -> (Variable, Parameter) {
+                (renaming.map_var(v).unwrap(), renaming.apply(&p))
+            })
+            .collect();
+        let region_constraints = renaming.apply(&self.region_constraints);
+        let c = Constraints {
+            env,
+            substitution,
+            region_constraints,
+            ..*self
+        };
+        c.assert_valid();
+        c
+    }
 }
 
 impl Visit for Constraints {
@@ -138,7 +391,9 @@ impl Visit for Constraints {
         let Constraints {
             env,
             known_true: _,
+            overflow: _,
             substitution,
+            region_constraints,
         } = self;
 
         // Debatable if `env.free_variables()` should be considered
@@ -147,38 +402,56 @@ impl Visit for Constraints {
         env.free_variables()
             .into_iter()
             .chain(substitution.free_variables())
+            .chain(region_constraints.free_variables())
             .collect()
     }
 
+    fn var_flags(&self) -> VarFlags {
+        let Constraints {
+            env,
+            known_true: _,
+            overflow: _,
+            substitution,
+            region_constraints,
+        } = self;
+        env.var_flags() | substitution.var_flags() | region_constraints.var_flags()
+    }
+
     fn size(&self) -> usize {
         let Constraints {
             env,
             known_true: _,
+            overflow: _,
             substitution,
+            region_constraints,
         } = self;
-        env.size() + substitution.size()
+        env.size() + substitution.size() + region_constraints.size()
     }
 
     fn assert_valid(&self) {
         let Constraints {
             env,
             known_true: _,
+            overflow: _,
             substitution,
+            region_constraints,
         } = self;
 
-        let domain = substitution.domain();
-        let range = substitution.range();
+        assert!(env.encloses(substitution.domain()));
+        assert!(env.encloses(substitution.range()));
+        assert!(env.encloses(region_constraints));
 
-        assert!(env.encloses(&domain));
-        assert!(env.encloses(&range));
+        // The substitution has been applied to the region constraints.
+        assert!(substitution
+            .domain()
+            .iter()
+            .all(|v| !occurs_in(v, region_constraints)));
 
         // No variable in the domain appears in any part of the range;
         // this prevents the obvious occurs check violations like `X = Vec<X>`
         // but also indirect ones like `X = Vec<Y>, Y = X`; it also implies that
         // the substitution has been fully applied, so we don't have `X = Vec<Y>, Y = u32`.
-        range
-            .iter()
-            .for_each(|p| assert!(domain.iter().all(|&v| !occurs_in(v, p))));
+        assert!(substitution.is_idempotent());
 
         // Each variable `x` is only bound to a term of strictly lower universe.
         // This implies that `x` does not appear in `p`.
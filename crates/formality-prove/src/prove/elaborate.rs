@@ -0,0 +1,79 @@
+use formality_types::{
+    cast::{Downcast, Upcast},
+    collections::Set,
+    grammar::{Parameter, Predicate, Relation, Ty, TyData, Wc, WcData, Wcs, PR},
+    visit::Visit,
+};
+
+use crate::decls::Decls;
+
+/// Closes `assumptions` under what they imply:
+///
+/// * `T: Trait` implies the supertraits of `Trait` (more generally, its [trait invariants](`crate::TraitInvariant`)),
+/// * `a: b` and `b: c` imply `a: c`,
+/// * a rigid type outliving `b` implies that its components do.
+///
+/// Assumptions under binders are left as they are. Implied where-clauses larger than
+/// [`SolverConfig::max_size`](`crate::SolverConfig::max_size`) are dropped, so that
+/// traits like `trait Foo<ty Self> where {Foo(Vec<Self>)}` do not elaborate forever.
+pub fn elaborate(decls: &Decls, assumptions: impl Upcast<Wcs>) -> Wcs {
+    let assumptions: Wcs = assumptions.upcast();
+    let mut elaborated: Set<Wc> = assumptions.into_iter().collect();
+    let mut stack: Vec<Wc> = elaborated.iter().cloned().collect();
+    while let Some(wc) = stack.pop() {
+        for implied in implied_by(decls, &elaborated, &wc) {
+            if implied.size() <= decls.config.max_size && elaborated.insert(implied.clone()) {
+                stack.push(implied);
+            }
+        }
+    }
+    elaborated.into_iter().collect()
+}
+
+/// The where-clauses directly implied by `wc`, possibly together with other `elaborated` ones.
+fn implied_by(decls: &Decls, elaborated: &Set<Wc>, wc: &Wc) -> Vec<Wc> {
+    match wc.data() {
+        WcData::PR(PR::Predicate(Predicate::IsImplemented(trait_ref))) => decls
+            .trait_invariants()
+            .iter()
+            .filter(|ti| ti.binder.peek().trait_ref.trait_id == trait_ref.trait_id)
+            .map(|ti| {
+                ti.binder
+                    .instantiate_with(&trait_ref.parameters)
+                    .unwrap()
+                    .where_clause
+            })
+            .collect(),
+
+        WcData::PR(PR::Relation(Relation::Outlives(a, b))) => {
+            let mut implied: Vec<Wc> = outlives_components(a)
+                .into_iter()
+                .map(|p| Relation::outlives(p, b).upcast())
+                .collect();
+            for other in elaborated {
+                if let Some(Relation::Outlives(c, d)) = other.downcast::<Relation>() {
+                    if c == *b {
+                        implied.push(Relation::outlives(a, &d).upcast());
+                    }
+                    if d == *a {
+                        implied.push(Relation::outlives(c, b).upcast());
+                    }
+                }
+            }
+            implied
+        }
+
+        WcData::PR(_) | WcData::ForAll(_) | WcData::Implies(..) => vec![],
+    }
+}
+
+/// The parameters that must outlive whatever `p` outlives; see the "rigid" rule of `prove_outlives`.
+fn outlives_components(p: &Parameter) -> Vec<Parameter> {
+    match p.downcast::<Ty>() {
+        Some(ty) => match ty.data() {
+            TyData::RigidTy(rigid) => rigid.parameters.clone(),
+            TyData::AliasTy(_) | TyData::PredicateTy(_) | TyData::Variable(_) => vec![],
+        },
+        None => vec![],
+    }
+}
@@ -1,16 +1,18 @@
-use formality_macros::term;
+use formality_macros::{term, Cast};
 use formality_types::{
     cast::{To, Upcast},
-    cast_impl,
     collections::Set,
     fold::Fold,
     grammar::{
         Binder, ExistentialVar, ParameterKind, UniversalVar, VarIndex, VarSubstitution, Variable,
     },
-    visit::Visit,
+    visit::{VarFlags, Visit},
 };
+use serde::{Deserialize, Serialize};
 
-#[derive(Default, Debug, Clone, Hash, Ord, Eq, PartialEq, PartialOrd)]
+#[derive(
+    Default, Debug, Clone, Hash, Ord, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Cast,
+)]
 pub struct Env {
     variables: Vec<Variable>,
     coherence_mode: bool,
@@ -33,8 +35,6 @@ impl Env {
     }
 }
 
-cast_impl!(Env);
-
 #[term(U($index))]
 #[derive(Copy)]
 pub struct Universe {
@@ -78,6 +78,13 @@ impl Env {
         v
     }
 
+    pub fn fresh_universal(&mut self, kind: ParameterKind) -> UniversalVar {
+        let var_index = self.fresh_index();
+        let v = UniversalVar { kind, var_index };
+        self.variables.push(v.upcast());
+        v
+    }
+
     pub fn insert_fresh_before(&mut self, kind: ParameterKind, rank: Universe) -> ExistentialVar {
         let var_index = self.fresh_index();
         let v = ExistentialVar { kind, var_index };
@@ -176,6 +183,9 @@ impl Env {
     /// `existential_substitution` or `universal_substitution`,
     /// removes those variables from `self` along with anything created afterwards.
     /// Returns the list of variables created since the universal subst.
+    ///
+    /// The variables need not be adjacent: [`Env::insert_fresh_before`] may have
+    /// placed fresh variables between them, which are removed as well.
     pub(crate) fn pop_vars<V>(&mut self, v: &[V]) -> Vec<Variable>
     where
         V: Upcast<Variable> + Copy,
@@ -184,10 +194,9 @@ impl Env {
             return vec![];
         }
 
-        let p0 = v[0];
-        let universe_p0 = self.universe(p0);
+        let universe_p0 = self.universe(v[0]);
         for (i, item) in v.iter().enumerate().skip(1) {
-            assert_eq!(self.universe(item).index, universe_p0.index + i);
+            assert!(self.universe(item).index >= universe_p0.index + i);
         }
 
         self.variables.drain(universe_p0.index..).collect()
@@ -218,6 +227,31 @@ impl Env {
     pub fn defines(&self, v: Variable) -> bool {
         self.variables.contains(&v)
     }
+
+    /// Renumbers the variables after their universes, from 0, so that envs which only
+    /// differ in how many variables were created along the way become equal. Returns
+    /// the renumbered env along with the renaming.
+    pub fn compress(&self) -> (Env, VarSubstitution) {
+        let renaming: VarSubstitution = self
+            .variables
+            .iter()
+            .zip(0..)
+            .map(|(&v, index)| {
+                let var_index = VarIndex { index };
+                let renamed: Variable = match v {
+                    Variable::UniversalVar(UniversalVar { kind, .. }) => {
+                        UniversalVar { kind, var_index }.upcast()
+                    }
+                    Variable::ExistentialVar(ExistentialVar { kind, .. }) => {
+                        ExistentialVar { kind, var_index }.upcast()
+                    }
+                    Variable::BoundVar(_) => panic!("unexpected bound variable {v:?}"),
+                };
+                (v, renamed)
+            })
+            .collect();
+        (self.substitute(&renaming), renaming)
+    }
 }
 
 impl Visit for Env {
@@ -225,6 +259,10 @@ impl Visit for Env {
         self.variables.clone()
     }
 
+    fn var_flags(&self) -> VarFlags {
+        self.variables.iter().map(VarFlags::of).collect()
+    }
+
     fn size(&self) -> usize {
         0
     }
@@ -1,3 +1,5 @@
+use std::cell::RefCell;
+
 use formality_types::{
     collections::Set,
     grammar::{Lt, Parameter, RigidName, RigidTy, TraitRef, TyData, Variable, Wcs},
@@ -5,8 +7,11 @@ use formality_types::{
 };
 
 use crate::{
-    decls::Decls,
-    prove::{combinators::for_all, prove_normalize::prove_normalize, Constraints},
+    decls::{Decls, SolverFlag},
+    prove::{
+        combinators::for_all, proof_result::Unknowable, prove_normalize::prove_normalize,
+        Constraints,
+    },
     Env,
 };
 
@@ -42,10 +47,11 @@ use crate::{
 pub fn may_be_remote(decls: Decls, env: Env, assumptions: Wcs, goal: TraitRef) -> Set<Constraints> {
     assert!(env.is_in_coherence_mode());
 
-    let c = is_local_trait_ref(decls, &env, assumptions, goal);
+    let c = is_local_trait_ref(decls, &env, assumptions, &goal);
 
     if c.is_empty() {
         // Cannot possibly be local, so always remote.
+        record_unknowable(Unknowable::Upstream(goal));
         return set![Constraints::none(env)];
     }
 
@@ -54,10 +60,35 @@ pub fn may_be_remote(decls: Decls, env: Env, assumptions: Wcs, goal: TraitRef) -
         return set![];
     }
 
-    // Otherwise it is ambiguous
+    // Otherwise it is ambiguous: it is local unless its inference variables are
+    // instantiated with types of a downstream crate.
+    record_unknowable(Unknowable::Downstream(goal));
     set![Constraints::none(env).ambiguous()]
 }
 
+thread_local! {
+    /// The unknowable goals met by [`may_be_remote`], if they are being recorded.
+    static UNKNOWABLE: RefCell<Option<Vec<Unknowable>>> = const { RefCell::new(None) }
+}
+
+/// Runs `op`, recording the goals it finds unknowable. Like
+/// [`record_occurs_checks`](`super::prove_eq::record_occurs_checks`), `op` should
+/// bypass the memo tables.
+pub(crate) fn record_unknowables<R>(op: impl FnOnce() -> R) -> (R, Set<Unknowable>) {
+    let outer = UNKNOWABLE.with(|u| u.replace(Some(vec![])));
+    let result = op();
+    let unknowables = UNKNOWABLE.with(|u| u.replace(outer)).unwrap();
+    (result, unknowables.into_iter().collect())
+}
+
+fn record_unknowable(unknowable: Unknowable) {
+    UNKNOWABLE.with(|unknowables| {
+        if let Some(unknowables) = &mut *unknowables.borrow_mut() {
+            unknowables.push(unknowable);
+        }
+    });
+}
+
 judgment_fn! {
     pub fn is_local_trait_ref(
         decls: Decls,
@@ -104,8 +135,17 @@ judgment_fn! {
         (
             // Since https://rust-lang.github.io/rfcs/2451-re-rebalancing-coherence.html,
             // any rigid type is adequate.
+            (if decls.config.flags.is_enabled(SolverFlag::ReRebalancingCoherence))
             --- ("rigid")
-            (not_downstream(_decls, env, _assumptions, RigidTy { .. }) => Constraints::none(env))
+            (not_downstream(decls, env, _assumptions, RigidTy { .. }) => Constraints::none(env))
+        )
+
+        (
+            // Before RFC 2451, type parameters could not appear at all, not even covered.
+            (if !decls.config.flags.is_enabled(SolverFlag::ReRebalancingCoherence))
+            (for_all(&decls, &env, &assumptions, &parameters, &not_downstream) => c)
+            --- ("rigid (RFC 1023)")
+            (not_downstream(decls, env, assumptions, RigidTy { name: _, parameters }) => c)
         )
 
         (
@@ -159,6 +199,13 @@ judgment_fn! {
             (is_local_parameter(decls, env, _assumptions, RigidTy { name: RigidName::AdtId(a), parameters: _ }) => Constraints::none(env))
         )
 
+        // Trait objects are local if their trait was declared in this crate.
+        (
+            (if decls.is_local_trait_id(&t))
+            --- ("local trait object")
+            (is_local_parameter(decls, env, _assumptions, RigidTy { name: RigidName::Dyn(t), parameters: _ }) => Constraints::none(env))
+        )
+
         // existential variables might or might not be local, depending on how they are instantiated.
         (
             --- ("existential variable")
@@ -182,8 +229,13 @@ fn is_fundamental(_decls: &Decls, name: &RigidName) -> bool {
         RigidName::Ref(_) => true,
 
         RigidName::ScalarId(_)
+        | RigidName::RawPtr(_)
         | RigidName::Tuple(_)
-        | RigidName::FnPtr(_)
-        | RigidName::FnDef(_) => false,
+        | RigidName::FnPtr(..)
+        | RigidName::FnDef(_)
+        | RigidName::Never
+        | RigidName::Closure(_)
+        | RigidName::Coroutine
+        | RigidName::Dyn(_) => false,
     }
 }
@@ -117,7 +117,9 @@ impl Minimization {
         let Constraints {
             env: _,
             known_true,
+            overflow,
             substitution,
+            region_constraints,
         } = constraints;
         let substitution: Substitution = substitution
             .iter()
@@ -125,10 +127,13 @@ impl Minimization {
                 (env2out_subst.map_var(x).unwrap(), env2out_subst.apply(&p))
             })
             .collect();
+        let region_constraints = env2out_subst.apply(&region_constraints);
         Constraints {
             env: env_out,
             known_true,
+            overflow,
             substitution,
+            region_constraints,
         }
     }
 }
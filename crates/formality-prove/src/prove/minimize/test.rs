@@ -64,9 +64,11 @@ fn minimize_a() {
     let c_min = Constraints {
         env: env_min,
         known_true: true,
+        overflow: false,
         substitution: vec![(ty1, ty2.to::<Ty>()), (ty0, ScalarId::U32.to::<Ty>())]
             .into_iter()
             .collect(),
+        region_constraints: Default::default(),
     };
     let c = m.reconstitute(c_min);
 
@@ -82,10 +84,12 @@ fn minimize_a() {
                 coherence_mode: false,
             },
             known_true: true,
+            overflow: false,
             substitution: {
                 ?ty_1 => (rigid (scalar u32)),
                 ?ty_3 => ?ty_4,
             },
+            region_constraints: {},
         }
     "#]]
     .assert_debug_eq(&c);
@@ -0,0 +1,127 @@
+use formality_types::{
+    collections::Set,
+    grammar::{ExistentialVar, Parameter, TraitRef},
+};
+
+use super::Constraints;
+
+/// The outcome of proving a goal, distinguishing definite failure from goals that
+/// the prover cannot decide.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum ProofResult {
+    /// The goal holds, under any of these constraints.
+    Yes(Set<Constraints>),
+
+    /// The goal may hold, but the prover cannot decide, e.g. because of unresolved
+    /// inference variables or because in coherence mode, a downstream or future crate
+    /// might add an impl. These are the constraints under which it may hold.
+    Ambiguous(AmbiguousConstraints),
+
+    /// The goal definitely does not hold. Lists the occurs check failures met along
+    /// the way, if known: these explain goals that could only hold with an infinite type.
+    No(Vec<OccursCheck>),
+
+    /// The prover was cancelled before it finished, e.g. because it exceeded its
+    /// time limit (see [`formality_types::judgment::cancel`]). Nothing is known.
+    TimedOut,
+}
+
+/// The constraints under which an ambiguous goal may hold.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct AmbiguousConstraints {
+    pub constraints: Set<Constraints>,
+
+    /// True if the ambiguity is (also) due to the prover exceeding its limits.
+    pub overflow: bool,
+
+    /// In coherence mode, the goals that are unknowable because another crate may
+    /// implement them, if known. These are rustc's intercrate ambiguity causes.
+    pub unknowable: Vec<Unknowable>,
+}
+
+impl ProofResult {
+    /// Classifies the constraints returned by [`prove`](`crate::prove`). Like rustc,
+    /// a goal holds as soon as one solution is known to be true, even if others
+    /// are ambiguous.
+    pub fn from_constraints(cs: Set<Constraints>) -> Self {
+        if cs.is_empty() {
+            return ProofResult::No(vec![]);
+        }
+
+        let (known_true, ambiguous): (Set<_>, Set<_>) = cs.into_iter().partition(|c| c.known_true);
+        if !known_true.is_empty() {
+            return ProofResult::Yes(known_true);
+        }
+
+        ProofResult::Ambiguous(AmbiguousConstraints {
+            overflow: ambiguous.iter().any(|c| c.overflow),
+            constraints: ambiguous,
+            unknowable: vec![],
+        })
+    }
+
+    pub fn is_yes(&self) -> bool {
+        matches!(self, ProofResult::Yes(_))
+    }
+
+    pub fn is_no(&self) -> bool {
+        matches!(self, ProofResult::No(_))
+    }
+
+    /// All the constraints, known to be true or not.
+    pub fn constraints(&self) -> Set<Constraints> {
+        match self {
+            ProofResult::Yes(cs) => cs.clone(),
+            ProofResult::Ambiguous(a) => a.constraints.clone(),
+            ProofResult::No(_) | ProofResult::TimedOut => Set::new(),
+        }
+    }
+}
+
+/// Unifying `var` with `ty` failed because `var` occurs in `ty`, so its value would
+/// have to be an infinite type.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub struct OccursCheck {
+    pub var: ExistentialVar,
+    pub ty: Parameter,
+}
+
+impl std::fmt::Display for OccursCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{:?}` cannot be `{:?}`, as it would be an infinite type",
+            self.var, self.ty
+        )
+    }
+}
+
+/// A trait goal that coherence cannot take to be false, because a crate other than
+/// the current one may implement it.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum Unknowable {
+    /// A downstream crate may implement the trait for one of its own types, which
+    /// an inference variable of the goal could be.
+    Downstream(TraitRef),
+
+    /// An upstream crate may add an impl of the trait in a future version, as the
+    /// current crate could not write it.
+    Upstream(TraitRef),
+}
+
+impl std::fmt::Display for Unknowable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Unknowable::Downstream(trait_ref) => write!(
+                f,
+                "downstream crates may implement trait `{:?}` for type `{:?}`",
+                trait_ref.trait_id, trait_ref.parameters[0]
+            ),
+            Unknowable::Upstream(trait_ref) => write!(
+                f,
+                "upstream crates may add a new impl of trait `{:?}` for type `{:?}` in future versions",
+                trait_ref.trait_id, trait_ref.parameters[0]
+            ),
+        }
+    }
+}
@@ -2,9 +2,13 @@ use formality_types::{grammar::Wcs, judgment_fn};
 
 use crate::{decls::Decls, prove::prove};
 
-use super::constraints::Constraints;
+use super::{
+    combinators::for_all, constraints::Constraints, prove_outlives::prove_region_constraint,
+};
 
 judgment_fn! {
+    /// Proves `goal` with what `constraints` inferred, then proves the region constraints
+    /// whose variables that inferred, see [`RegionConstraint`](`super::constraints::RegionConstraint`).
     pub fn prove_after(
         decls: Decls,
         constraints: Constraints,
@@ -15,9 +19,11 @@ judgment_fn! {
 
         (
             (let (assumptions, goal) = c1.substitution().apply(&(assumptions, goal)))
-            (prove(decls, c1.env(), assumptions, goal) => c2)
+            (prove(&decls, c1.env(), &assumptions, goal) => c2)
+            (let (c, ready) = c1.seq(c2).take_ready_region_constraints())
+            (for_all(&decls, c.env(), &assumptions, &ready, &prove_region_constraint) => c3)
             --- ("prove_after")
-            (prove_after(decls, c1, assumptions, goal) => c1.seq(c2))
+            (prove_after(decls, c1, assumptions, goal) => c.seq(c3))
         )
     }
 }
@@ -0,0 +1,161 @@
+use formality_types::{
+    cast::Downcast,
+    grammar::{
+        lang_items::{deref_mut_trait, deref_target, deref_trait, unsize_trait},
+        Abi, AliasTy, RefKind, Relation, RigidName, RigidTy, Ty, Wcs,
+    },
+    judgment_fn,
+};
+
+use crate::{
+    decls::{Decls, FnDeclBoundData},
+    prove::{
+        prove_after::prove_after, prove_eq::prove_eq, prove_normalize::prove_normalize,
+        prove_wc_list::prove_wc_list,
+    },
+};
+
+use super::{constraints::Constraints, env::Env};
+
+judgment_fn! {
+    /// Coercions that can be applied implicitly at coercion sites,
+    /// e.g. function arguments or `let` initializers with a type annotation.
+    pub fn prove_coerce(
+        decls: Decls,
+        env: Env,
+        assumptions: Wcs,
+        a: Ty,
+        b: Ty,
+    ) => Constraints {
+        debug(a, b, assumptions, env, decls)
+
+        (
+            (prove_eq(decls, env, assumptions, a, b) => c)
+            --- ("identity")
+            (prove_coerce(decls, env, assumptions, a, b) => c)
+        )
+
+        (
+            (if let Some(RigidTy { name: RigidName::Never, .. }) = a.downcast())
+            --- ("never")
+            (prove_coerce(_decls, env, _assumptions, a, _b) => Constraints::none(env))
+        )
+
+        (
+            (if let Some((RefKind::Shared, _, _)) = b.as_ref_ty())
+            (if let Some((RefKind::Mut, lt_a, ty_a)) = a.as_ref_ty())
+            (prove_coerce(&decls, &env, &assumptions, ty_a.ref_ty(&lt_a), &b) => c)
+            --- ("mut to shared")
+            (prove_coerce(decls, env, assumptions, a, b) => c)
+        )
+
+        // FIXME: as for deref coercions, the lifetimes must currently be equal.
+        (
+            (if let Some((kind_b, lt_b, ty_b)) = b.as_ref_ty())
+            (if let Some((kind_a, lt_a, ty_a)) = a.as_ref_ty())
+            (if kind_a == kind_b || kind_b == RefKind::Shared)
+            (prove_wc_list(&decls, &env, &assumptions, (Relation::eq(&lt_a, &lt_b), unsize_trait().with(&ty_a, vec![&ty_b]))) => c)
+            --- ("unsize")
+            (prove_coerce(decls, env, assumptions, a, b) => c)
+        )
+
+        (
+            (if let Some(RigidTy { name: RigidName::FnDef(fn_id), parameters }) = a.downcast())
+            (if let Some(RigidTy { name: RigidName::FnPtr(abi, _), .. }) = b.downcast())
+            (let fn_decl = decls.fn_decl(&fn_id))
+            (if fn_decl.abi == abi)
+            (let FnDeclBoundData { input_tys, output_ty } = fn_decl.binder.instantiate_with(&parameters).unwrap())
+            (prove_eq(&decls, &env, &assumptions, Ty::abi_fn_ptr(abi, &input_tys, &output_ty), &b) => c)
+            --- ("reify fn item")
+            (prove_coerce(decls, env, assumptions, a, b) => c)
+        )
+
+        (
+            (if let Some((_, sig, upvar_tys)) = a.as_closure_ty())
+            (if upvar_tys.is_empty())
+            (if let Some(RigidTy { name: RigidName::FnPtr(Abi::Rust, _), .. }) = b.downcast())
+            (prove_eq(&decls, &env, &assumptions, sig, &b) => c)
+            --- ("non-capturing closure")
+            (prove_coerce(decls, env, assumptions, a, b) => c)
+        )
+
+        // `&'a A` (or `&'a mut A`) coerces to `&'b B` if `A` derefs to some `A1` with `&'a A1 ~> &'b B`.
+        //
+        // FIXME: the lifetimes must currently be equal, rather than requiring `'a: 'b`.
+        (
+            (if let Some((RefKind::Shared, _, _)) = b.as_ref_ty())
+            (if let Some((_, lt_a, ty_a)) = a.as_ref_ty())
+            (prove_deref(&decls, &env, &assumptions, ty_a) => (c, ty_a1))
+            (prove_after(&decls, c, &assumptions, Relation::coerce(ty_a1.ref_ty(&lt_a), &b)) => c)
+            --- ("deref")
+            (prove_coerce(decls, env, assumptions, a, b) => c)
+        )
+
+        (
+            (if let Some((RefKind::Mut, _, _)) = b.as_ref_ty())
+            (if let Some((RefKind::Mut, lt_a, ty_a)) = a.as_ref_ty())
+            (prove_deref_mut(&decls, &env, &assumptions, ty_a) => (c, ty_a1))
+            (prove_after(&decls, c, &assumptions, Relation::coerce(ty_a1.ref_mut_ty(&lt_a), &b)) => c)
+            --- ("deref mut")
+            (prove_coerce(decls, env, assumptions, a, b) => c)
+        )
+    }
+}
+
+judgment_fn! {
+    /// One step of autoderef: `*ty` has type `target`.
+    /// References deref built-in; everything else goes through `<ty as Deref>::Target`.
+    pub fn prove_deref(
+        decls: Decls,
+        env: Env,
+        assumptions: Wcs,
+        ty: Ty,
+    ) => (Constraints, Ty) {
+        debug(ty, assumptions, env, decls)
+
+        (
+            (if let Some((_, _, referent)) = ty.as_ref_ty())
+            --- ("builtin ref")
+            (prove_deref(_decls, env, _assumptions, ty) => (Constraints::none(env), referent))
+        )
+
+        (
+            (if ty.as_ref_ty().is_none())
+            (let alias = AliasTy::associated_ty(deref_trait(), deref_target(), vec![ty.clone()]))
+            (prove_normalize(&decls, &env, &assumptions, alias) => (c, target))
+            (prove_after(&decls, c, &assumptions, deref_trait().with(&ty, ())) => c)
+            (let target = c.substitution().apply(&target))
+            (if let Some(target) = target.downcast::<Ty>())
+            --- ("Deref impl")
+            (prove_deref(decls, env, assumptions, ty) => (c, target))
+        )
+    }
+}
+
+judgment_fn! {
+    /// Like [`prove_deref`], but for a mutable place: only `&mut` derefs built-in,
+    /// other types additionally have to implement `DerefMut`.
+    pub fn prove_deref_mut(
+        decls: Decls,
+        env: Env,
+        assumptions: Wcs,
+        ty: Ty,
+    ) => (Constraints, Ty) {
+        debug(ty, assumptions, env, decls)
+
+        (
+            (if let Some((RefKind::Mut, _, referent)) = ty.as_ref_ty())
+            --- ("builtin ref mut")
+            (prove_deref_mut(_decls, env, _assumptions, ty) => (Constraints::none(env), referent))
+        )
+
+        (
+            (if ty.as_ref_ty().is_none())
+            (prove_deref(&decls, &env, &assumptions, &ty) => (c, target))
+            (prove_after(&decls, c, &assumptions, deref_mut_trait().with(&ty, ())) => c)
+            (let target = c.substitution().apply(&target))
+            --- ("DerefMut impl")
+            (prove_deref_mut(decls, env, assumptions, ty) => (c, target))
+        )
+    }
+}
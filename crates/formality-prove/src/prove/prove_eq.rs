@@ -1,22 +1,24 @@
+use std::cell::RefCell;
+
 use formality_types::{
     cast::{Downcast, Upcast, Upcasted},
     collections::{Deduplicate, Set},
     grammar::{
-        AliasTy, ExistentialVar, Parameter, Relation, RigidTy, Substitution, TyData, UniversalVar,
-        Variable, Wcs,
+        AliasName, AliasTy, ExistentialVar, Parameter, ParameterKind, Relation, RigidTy,
+        Substitution, Ty, TyData, UniversalVar, Variable, Wcs,
     },
     judgment_fn, set,
     visit::Visit,
 };
 
 use crate::{
-    decls::Decls,
+    decls::{Decls, TypeAliasDeclBoundData},
     prove::{
         constraints::occurs_in, prove, prove_after::prove_after, prove_normalize::prove_normalize,
     },
 };
 
-use super::{constraints::Constraints, env::Env};
+use super::{constraints::Constraints, env::Env, proof_result::OccursCheck};
 
 /// Goal(s) to prove `a` and `b` are equal
 pub fn eq(a: impl Upcast<Parameter>, b: impl Upcast<Parameter>) -> Relation {
@@ -37,6 +39,8 @@ judgment_fn! {
 
         trivial(a == b => Constraints::none(env))
 
+        trivial(decls.config.erase_regions && a.kind() == ParameterKind::Lt => Constraints::none(env))
+
         (
             (prove_eq(decls, env, assumptions, r, l) => env_c)
             ----------------------------- ("symmetric")
@@ -61,6 +65,16 @@ judgment_fn! {
             (prove_eq(decls, env, assumptions, TyData::AliasTy(a), TyData::AliasTy(b)) => env_c)
         )
 
+        // Type aliases are expanded lazily, only once they are compared with another type.
+        // The expansion must satisfy the where-clauses of the alias.
+        (
+            (if let AliasName::TypeAliasId(id) = &a.name)
+            (ok decls.type_alias_decl(id).binder.instantiate_with(&a.parameters) => TypeAliasDeclBoundData { ty, where_clause })
+            (prove(&decls, env, &assumptions, (where_clause, eq(ty, &b))) => c)
+            ----------------------------- ("expand type alias")
+            (prove_eq(decls, env, assumptions, TyData::AliasTy(a), b) => c)
+        )
+
         (
             (prove_existential_var_eq(decls, env, assumptions, v, r) => c)
             ----------------------------- ("existential")
@@ -108,6 +122,21 @@ judgment_fn! {
     }
 }
 
+thread_local! {
+    /// The occurs check failures met by [`equate_variable`], if they are being recorded.
+    static OCCURS_CHECKS: RefCell<Option<Vec<OccursCheck>>> = const { RefCell::new(None) }
+}
+
+/// Runs `op`, recording the occurs check failures it meets. Judgments whose outputs
+/// are memoized are not re-evaluated, so `op` should bypass the memo tables, e.g.,
+/// by recording proof trees as well.
+pub(crate) fn record_occurs_checks<R>(op: impl FnOnce() -> R) -> (R, Set<OccursCheck>) {
+    let outer = OCCURS_CHECKS.with(|o| o.replace(Some(vec![])));
+    let result = op();
+    let occurs_checks = OCCURS_CHECKS.with(|o| o.replace(outer)).unwrap();
+    (result, occurs_checks.into_iter().collect())
+}
+
 fn equate_variable(
     decls: Decls,
     mut env: Env,
@@ -127,10 +156,21 @@ fn equate_variable(
     assert!(env.encloses((x, (&assumptions, &p))));
     assert!(!p.is_a::<Variable>());
 
+    // Replace the aliases nested in `p` that `x` cannot be equated with by fresh
+    // variables, to be equated with those aliases once `x` is known.
+    let universe_x = env.universe(x);
+    let mut nested_aliases = vec![];
+    let p = generalize_nested_aliases(&mut env, x, &p, &mut nested_aliases);
+
     let fvs = p.free_variables().deduplicate();
 
     // Ensure that `x` passes the occurs check for the free variables in `p`.
     if occurs_in(x, &fvs) {
+        OCCURS_CHECKS.with(|occurs_checks| {
+            if let Some(occurs_checks) = &mut *occurs_checks.borrow_mut() {
+                occurs_checks.push(OccursCheck { var: x, ty: p });
+            }
+        });
         return set![];
     }
 
@@ -140,7 +180,6 @@ fn equate_variable(
     // e.g., in an environment `[X, Y]`, if we have `X = Vec<Y>`:
     // * we would create `Z` before `X` (so new env is `[Z, X, Y]`)
     // * and map `Y` to `Z`
-    let universe_x = env.universe(x);
     let universe_subst: Substitution = fvs
         .iter()
         .flat_map(|fv| {
@@ -169,10 +208,13 @@ fn equate_variable(
     // above, we now have to prove that goal. e.g., if we had `X = Vec<!Y>`, we would replace `!Y` with `?Z`
     // (where `?Z` is in a lower universe than `X`), but now we must prove that `!Y = ?Z`
     // (this may be posible due to assumptions).
+    //
+    // Finally, each nested alias that we replaced must be equal to its variable.
     let goals: Wcs = universe_subst
         .iter()
         .filter(|(v, _)| v.is_a::<UniversalVar>())
         .map(|(v, p)| eq(v, p))
+        .chain(nested_aliases.iter().map(|(y, alias)| eq(y, alias)))
         .upcasted()
         .collect();
 
@@ -180,3 +222,54 @@ fn equate_variable(
 
     prove_after(decls, constraints, assumptions, goals)
 }
+
+/// Generalizes the aliases nested in the rigid types of `p` for equating `p` with `x`,
+/// as the generalizer of rustc's new solver does: an alias that `x` occurs in, or
+/// that names a universal variable `x` cannot name, may still normalize to a type
+/// that `x` can be equated with. Such an alias is replaced by a fresh variable `y`
+/// in the universe of `x`, and pushed to `aliases` along with `y`.
+///
+/// e.g., equating `X` with `Vec<<X as Id>::Out>` does not fail the occurs check,
+/// but equates `X` with `Vec<Y>` where `Y = <X as Id>::Out`.
+///
+/// An alias that is all of `p` is left as it is: equating `x` with it is what
+/// proving `y` equal to a nested alias does.
+fn generalize_nested_aliases(
+    env: &mut Env,
+    x: ExistentialVar,
+    p: &Parameter,
+    aliases: &mut Vec<(ExistentialVar, AliasTy)>,
+) -> Parameter {
+    let Some(ty) = p.downcast::<Ty>() else {
+        return p.clone();
+    };
+    let TyData::RigidTy(RigidTy { name, parameters }) = ty.data() else {
+        return p.clone();
+    };
+    let parameters: Vec<Parameter> = parameters
+        .iter()
+        .map(|parameter| match parameter.downcast::<Ty>() {
+            Some(ty) => match ty.data() {
+                TyData::AliasTy(alias) if !can_equate_with_alias(env, x, alias) => {
+                    let y = env.insert_fresh_before(ParameterKind::Ty, env.universe(x));
+                    aliases.push((y, alias.clone()));
+                    y.upcast()
+                }
+                _ => generalize_nested_aliases(env, x, parameter, aliases),
+            },
+            None => parameter.clone(),
+        })
+        .collect();
+    Ty::rigid(name, parameters).upcast()
+}
+
+/// True if `x` can be equated with `alias` as it is, i.e., `x` does not occur in it
+/// and every universal variable in it is nameable from the universe of `x`.
+fn can_equate_with_alias(env: &Env, x: ExistentialVar, alias: &AliasTy) -> bool {
+    let universe_x = env.universe(x);
+    alias.free_variables().into_iter().all(|v| match v {
+        Variable::ExistentialVar(_) => v != x.upcast(),
+        Variable::UniversalVar(_) => env.universe(v) < universe_x,
+        Variable::BoundVar(_) => true,
+    })
+}
@@ -1,7 +1,9 @@
 use formality_types::{
     cast::Downcast,
     grammar::{
-        AliasTy, ExistentialVar, Parameter, Relation, RigidTy, TyData, Variable, Wc, WcData, Wcs,
+        lang_items::{builtin_operator_impls, future_output, future_trait, op_output},
+        AliasName, AliasTy, ExistentialVar, Parameter, Predicate, Relation, RigidTy, Ty, TyData,
+        Variable, Wc, WcData, Wcs,
     },
     judgment_fn,
 };
@@ -46,6 +48,25 @@ judgment_fn! {
             ----------------------------- ("normalize-via-impl")
             (prove_normalize(decls, env, assumptions, TyData::AliasTy(a)) => (c, ty))
         )
+
+        (
+            (if let AliasName::AssociatedTyId(name) = &a.name)
+            (if name.trait_id == future_trait() && name.item_id == future_output())
+            (if let [Parameter::Ty(self_ty)] = &a.parameters[..])
+            (if let Some((output_ty, _, _)) = self_ty.as_coroutine_ty())
+            ----------------------------- ("normalize-coroutine-output")
+            (prove_normalize(_decls, env, _assumptions, TyData::AliasTy(a)) => (Constraints::none(env), output_ty))
+        )
+
+        (
+            (if let AliasName::AssociatedTyId(name) = &a.name)
+            (if name.item_id == op_output())
+            (builtin_operator_impls(&name.trait_id) => s)
+            (let s = Ty::rigid(s, ()))
+            (prove(&decls, &env, &assumptions, Wcs::all_eq(&a.parameters, vec![&s; a.parameters.len()])) => c)
+            ----------------------------- ("normalize-builtin-operator-output")
+            (prove_normalize(decls, env, assumptions, TyData::AliasTy(a)) => (c, &s))
+        )
     }
 }
 
@@ -105,6 +126,16 @@ judgment_fn! {
             (prove_normalize_via(decls, env, assumptions, Relation::Equals(b, a), goal) => (c, b))
         )
 
+        // Likewise, an assumption `<X as Trait>::Item => Y` (e.g., from a where clause)
+        // normalizes the alias to `Y`.
+
+        (
+            (prove_syntactically_eq(decls, env, assumptions, a, goal) => c)
+            (let b = c.substitution().apply(&b))
+            ----------------------------- ("alias-eq")
+            (prove_normalize_via(decls, env, assumptions, Predicate::AliasEq(a, b), goal) => (c, b))
+        )
+
         // These rules handle the the ∀ and ⇒ cases.
 
         (
@@ -0,0 +1,123 @@
+use formality_types::{
+    cast::{Downcast, Upcast, Upcasted},
+    grammar::{
+        AliasTy, ExistentialVar, Lt, LtData, Parameter, Relation, RigidTy, TyData, Variable, Wcs,
+    },
+    judgment_fn,
+    visit::Visit,
+};
+
+use crate::{decls::Decls, prove::prove};
+
+use super::{
+    constraints::{Constraints, RegionConstraint},
+    env::Env,
+};
+
+/// Goal(s) to prove that each of `parameters` outlives `b`
+fn all_outlive(parameters: &[Parameter], b: &Parameter) -> Wcs {
+    parameters
+        .iter()
+        .map(|p| Relation::outlives(p, b))
+        .upcasted()
+        .collect()
+}
+
+judgment_fn! {
+    /// Proves `a: b`, where `a` is a type or a lifetime and `b` is a lifetime.
+    /// Outlives goals that are literally among the assumptions are proven by `prove_wc`.
+    pub fn prove_outlives(
+        decls: Decls,
+        env: Env,
+        assumptions: Wcs,
+        a: Parameter,
+        b: Parameter,
+    ) => Constraints {
+        debug(a, b, assumptions, env, decls)
+
+        trivial(a == b => Constraints::none(env))
+
+        trivial(decls.config.erase_regions => Constraints::none(env))
+
+        (
+            (if a == Lt::static_().upcast())
+            ----------------------------- ("static")
+            (prove_outlives(_decls, env, _assumptions, a, _b) => Constraints::none(env))
+        )
+
+        // A type outlives `b` if all of its components do; for `&'a T` that is `'a` and `T`.
+        (
+            (prove(decls, env, assumptions, all_outlive(&parameters, &b)) => c)
+            ----------------------------- ("rigid")
+            (prove_outlives(decls, env, assumptions, TyData::RigidTy(RigidTy { name: _, parameters }), b) => c)
+        )
+
+        // Whatever an alias normalizes to, it can only be made up of its parameters
+        // (and of things that outlive everything), so it suffices that those outlive `b`.
+        (
+            (prove(decls, env, assumptions, all_outlive(&parameters, &b)) => c)
+            ----------------------------- ("alias components")
+            (prove_outlives(decls, env, assumptions, TyData::AliasTy(AliasTy { name: _, parameters }), b) => c)
+        )
+
+        // Which lifetimes existential variables stand for is not known yet, so `a: b`
+        // is recorded as a region constraint, to be proven once it is.
+        (
+            (if is_deferrable(&env, &a, &b))
+            ----------------------------- ("existential")
+            (prove_outlives(_decls, env, _assumptions, a, b) => Constraints::none(env).with_region_constraint(&a, &b))
+        )
+
+        // From `a: c` and `c: b`, we know `a: b`.
+        (
+            (&assumptions => assumption)
+            (if let Some(Relation::Outlives(x, c)) = assumption.downcast::<Relation>())
+            (if x == a)
+            (prove(&decls, &env, &assumptions, Relation::outlives(c, &b)) => c)
+            ----------------------------- ("transitive")
+            (prove_outlives(decls, env, assumptions, a, b) => c)
+        )
+    }
+}
+
+/// Whether `a: b` relates lifetimes, one of which is an existential variable that can
+/// name all the variables of the other (see [`RegionConstraint`]). `'static: b` always
+/// holds, by the "static" rule.
+fn is_deferrable(env: &Env, a: &Parameter, b: &Parameter) -> bool {
+    let (Some(lt_a), Some(lt_b)) = (a.downcast::<Lt>(), b.downcast::<Lt>()) else {
+        return false;
+    };
+    if lt_a == Lt::static_() {
+        return false;
+    }
+    let existential_universes = [&lt_a, &lt_b].into_iter().filter_map(|lt| match lt.data() {
+        LtData::Variable(v @ Variable::ExistentialVar(_)) => Some(env.universe(v)),
+        _ => None,
+    });
+    let Some(universe) = existential_universes.max() else {
+        return false;
+    };
+    (&lt_a, &lt_b)
+        .free_variables()
+        .iter()
+        .all(|&v| v.is_a::<ExistentialVar>() || env.universe(v) < universe)
+}
+
+judgment_fn! {
+    /// Proves a region constraint whose variables are known, see [`RegionConstraint`].
+    /// Its origin shows up in the failures.
+    pub fn prove_region_constraint(
+        decls: Decls,
+        env: Env,
+        assumptions: Wcs,
+        constraint: RegionConstraint,
+    ) => Constraints {
+        debug(constraint, assumptions, env, decls)
+
+        (
+            (prove(decls, env, assumptions, Relation::outlives(&constraint.a, &constraint.b)) => c)
+            ----------------------------- ("region constraint")
+            (prove_region_constraint(decls, env, assumptions, constraint) => c)
+        )
+    }
+}
@@ -0,0 +1,57 @@
+use formality_types::{
+    cast::Downcast,
+    grammar::{
+        lang_items::{receiver_target, receiver_trait},
+        AliasTy, Relation, Ty, Wcs,
+    },
+    judgment_fn,
+};
+
+use crate::{
+    decls::Decls,
+    prove::{
+        prove_after::prove_after, prove_coerce::prove_deref, prove_eq::prove_eq,
+        prove_normalize::prove_normalize,
+    },
+};
+
+use super::{constraints::Constraints, env::Env};
+
+judgment_fn! {
+    /// `receiver` can be the type of `self` in a method whose `Self` type is `self_ty`,
+    /// like `Self`, `&'a Self` or `Rc<Self>`: following `Receiver` impls or autoderef
+    /// from `receiver` eventually reaches `self_ty`.
+    pub fn prove_receiver(
+        decls: Decls,
+        env: Env,
+        assumptions: Wcs,
+        receiver: Ty,
+        self_ty: Ty,
+    ) => Constraints {
+        debug(receiver, self_ty, assumptions, env, decls)
+
+        (
+            (prove_eq(decls, env, assumptions, receiver, self_ty) => c)
+            --- ("self")
+            (prove_receiver(decls, env, assumptions, receiver, self_ty) => c)
+        )
+
+        (
+            (prove_deref(&decls, &env, &assumptions, &receiver) => (c, target))
+            (prove_after(&decls, c, &assumptions, Relation::receiver(target, &self_ty)) => c)
+            --- ("deref")
+            (prove_receiver(decls, env, assumptions, receiver, self_ty) => c)
+        )
+
+        (
+            (let alias = AliasTy::associated_ty(receiver_trait(), receiver_target(), vec![receiver.clone()]))
+            (prove_normalize(&decls, &env, &assumptions, alias) => (c, target))
+            (prove_after(&decls, c, &assumptions, receiver_trait().with(&receiver, ())) => c)
+            (let target = c.substitution().apply(&target))
+            (if let Some(target) = target.downcast::<Ty>())
+            (prove_after(&decls, c, &assumptions, Relation::receiver(target, &self_ty)) => c)
+            --- ("Receiver impl")
+            (prove_receiver(decls, env, assumptions, receiver, self_ty) => c)
+        )
+    }
+}
@@ -1,16 +1,28 @@
 use formality_types::{
-    grammar::{Predicate, Relation, Wc, WcData, Wcs},
+    cast::{Downcast, Upcasted},
+    grammar::{
+        lang_items::{
+            auto_traits, builtin_operator_impls, fn_trait_kind, future_trait, sized_trait,
+        },
+        Parameter, Predicate, Relation, Ty, TyData, Wc, WcData, Wcs,
+    },
     judgment_fn,
 };
 
 use crate::{
     decls::Decls,
     prove::{
+        candidates::{impl_candidates, where_clause_applies},
+        combinators::for_all,
+        elaborate::elaborate,
         env::Env,
         is_local::{is_local_trait_ref, may_be_remote},
         prove,
         prove_after::prove_after,
+        prove_coerce::prove_coerce,
         prove_eq::prove_eq,
+        prove_outlives::{prove_outlives, prove_region_constraint},
+        prove_receiver::prove_receiver,
         prove_via::prove_via,
         prove_wf::prove_wf,
     },
@@ -30,13 +42,19 @@ judgment_fn! {
         (
             (let (env, subst) = env.universal_substitution(&binder))
             (let p1 = binder.instantiate_with(&subst).unwrap())
-            (prove_wc(decls, env, &assumptions, p1) => c)
+            (prove_wc(&decls, env, &assumptions, p1) => c)
+            // The region constraints on the placeholders are proven while they are in
+            // scope, and must not escape it, as nothing is known of them outside.
+            (let (c, ready) = c.eliminate_region_vars(&subst).take_ready_region_constraints())
+            (for_all(&decls, c.env(), &assumptions, &ready, &prove_region_constraint) => c2)
+            (let c = c.seq(c2))
+            (if !c.region_constraints_mention(&subst))
             --- ("forall")
             (prove_wc(decls, env, assumptions, WcData::ForAll(binder)) => c.pop_subst(&subst))
         )
 
         (
-            (prove_wc(decls, env, (assumptions, p1), p2) => c)
+            (prove_wc(&decls, env, elaborate(&decls, (assumptions, p1)), p2) => c)
             --- ("implies")
             (prove_wc(decls, env, assumptions, WcData::Implies(p1, p2)) => c)
         )
@@ -48,17 +66,12 @@ judgment_fn! {
             (prove_wc(decls, env, assumptions, WcData::PR(goal)) => c)
         )
 
+        // As in rustc, impls are only candidates if no where-clause is.
         (
-            (decls.impl_decls(&trait_ref.trait_id) => i)
-            (let (env, subst) = env.existential_substitution(&i.binder))
-            (let i = i.binder.instantiate_with(&subst).unwrap())
-            (let t = decls.trait_decl(&i.trait_ref.trait_id).binder.instantiate_with(&i.trait_ref.parameters).unwrap())
-            (let co_assumptions = (&assumptions, &trait_ref))
-            (prove(&decls, env, co_assumptions, Wcs::all_eq(&trait_ref.parameters, &i.trait_ref.parameters)) => c)
-            (prove_after(&decls, c, co_assumptions, &i.where_clause) => c)
-            (prove_after(&decls, c, &assumptions, &t.where_clause) => c)
+            (if !where_clause_applies(&decls, &env, &assumptions, &trait_ref))
+            (impl_candidates(&decls, &env, &assumptions, &trait_ref) => c)
             ----------------------------- ("positive impl")
-            (prove_wc(decls, env, assumptions, Predicate::IsImplemented(trait_ref)) => c.pop_subst(&subst))
+            (prove_wc(decls, env, assumptions, Predicate::IsImplemented(trait_ref)) => c)
         )
 
         (
@@ -78,6 +91,99 @@ judgment_fn! {
             (prove_wc(decls, env, assumptions, Predicate::NotImplemented(trait_ref)) => c.pop_subst(&subst))
         )
 
+        // All rigid types have a size known at compile time.
+        (
+            (if trait_ref.trait_id == sized_trait())
+            (if let [Parameter::Ty(self_ty)] = &trait_ref.parameters[..])
+            (if self_ty.is_rigid() && self_ty.as_dyn_ty().is_none())
+            ----------------------------- ("sized")
+            (prove_wc(_decls, env, _assumptions, Predicate::IsImplemented(trait_ref)) => Constraints::none(env))
+        )
+
+        // `dyn Trait<..>` implements `Trait<..>`, through its vtable.
+        (
+            (if let [Parameter::Ty(self_ty), parameters @ ..] = &trait_ref.parameters[..])
+            (if let Some((trait_id, dyn_parameters)) = self_ty.as_dyn_ty())
+            (if trait_id == trait_ref.trait_id && parameters.len() == dyn_parameters.len())
+            (prove(decls, env, assumptions, Wcs::all_eq(parameters.to_vec(), dyn_parameters)) => c)
+            ----------------------------- ("dyn")
+            (prove_wc(decls, env, assumptions, Predicate::IsImplemented(trait_ref)) => c)
+        )
+
+        (
+            (if let Some(trait_kind) = fn_trait_kind(&trait_ref.trait_id))
+            (if let [Parameter::Ty(self_ty), args] = &trait_ref.parameters[..])
+            (if let Some((kind, sig, _)) = self_ty.as_closure_ty())
+            (if kind <= trait_kind)
+            (if let Some((input_tys, _)) = sig.as_fn_ptr_ty())
+            (prove_eq(decls, env, assumptions, args, Ty::tuple(&input_tys)) => c)
+            ----------------------------- ("closure")
+            (prove_wc(decls, env, assumptions, Predicate::IsImplemented(trait_ref)) => c)
+        )
+
+        (
+            (if auto_traits().contains(&trait_ref.trait_id))
+            (if let [Parameter::Ty(self_ty)] = &trait_ref.parameters[..])
+            (if let Some((_, _, upvar_tys)) = self_ty.as_closure_ty())
+            (let goals = upvar_tys.iter().map(|ty| trait_ref.trait_id.with(ty, ()).is_implemented()).upcasted().collect::<Wcs>())
+            (prove(decls, env, assumptions, goals) => c)
+            ----------------------------- ("closure captures")
+            (prove_wc(decls, env, assumptions, Predicate::IsImplemented(trait_ref)) => c)
+        )
+
+        // `PhantomData<T>` has no fields, but it implements the auto traits as if it owned a `T`.
+        (
+            (if auto_traits().contains(&trait_ref.trait_id))
+            (if let [Parameter::Ty(self_ty)] = &trait_ref.parameters[..])
+            (if let Some(ty) = self_ty.as_phantom_data_ty())
+            (prove(decls, env, assumptions, trait_ref.trait_id.with(ty, ()).is_implemented()) => c)
+            ----------------------------- ("phantom data")
+            (prove_wc(decls, env, assumptions, Predicate::IsImplemented(trait_ref)) => c)
+        )
+
+        (
+            (if trait_ref.trait_id == future_trait())
+            (if let [Parameter::Ty(self_ty)] = &trait_ref.parameters[..])
+            (if self_ty.as_coroutine_ty().is_some())
+            ----------------------------- ("coroutine")
+            (prove_wc(_decls, env, _assumptions, Predicate::IsImplemented(trait_ref)) => Constraints::none(env))
+        )
+
+        (
+            (if auto_traits().contains(&trait_ref.trait_id))
+            (if let [Parameter::Ty(self_ty)] = &trait_ref.parameters[..])
+            (if let Some((_, upvar_tys, witness_tys)) = self_ty.as_coroutine_ty())
+            (let goals = upvar_tys.iter().chain(&witness_tys).map(|ty| trait_ref.trait_id.with(ty, ()).is_implemented()).upcasted().collect::<Wcs>())
+            (prove(decls, env, assumptions, goals) => c)
+            ----------------------------- ("coroutine components")
+            (prove_wc(decls, env, assumptions, Predicate::IsImplemented(trait_ref)) => c)
+        )
+
+        // The scalar types implement the operator traits as if there were an impl like
+        // `impl Add<i32> for i32` for each of them.
+        (
+            (builtin_operator_impls(&trait_ref.trait_id) => s)
+            (let s = Ty::rigid(s, ()))
+            (prove(&decls, &env, &assumptions, Wcs::all_eq(&trait_ref.parameters, vec![&s; trait_ref.parameters.len()])) => c)
+            ----------------------------- ("builtin operator")
+            (prove_wc(decls, env, assumptions, Predicate::IsImplemented(trait_ref)) => c)
+        )
+
+        // The bounds declared on an associated type, like `type Item: Debug`, hold for
+        // the alias itself, whatever it normalizes to.
+        (
+            (if let Some(Parameter::Ty(self_ty)) = trait_ref.parameters.first())
+            (if let TyData::AliasTy(alias) = self_ty.data())
+            (decls.alias_bound_decls(&alias.name) => b)
+            (let (env, subst) = env.existential_substitution(&b.binder))
+            (let b = b.binder.instantiate_with(&subst).unwrap())
+            (let ensures = b.ensures.instantiate_with(&[Ty::new(&b.alias)]).unwrap())
+            (prove_via(&decls, env, &assumptions, ensures, &trait_ref) => c)
+            (prove_after(&decls, c, &assumptions, &b.where_clause) => c)
+            ----------------------------- ("alias bound")
+            (prove_wc(decls, env, assumptions, Predicate::IsImplemented(trait_ref)) => c.pop_subst(&subst))
+        )
+
         (
             (prove_eq(decls, env, assumptions, alias_ty, ty) => c)
             ----------------------------- ("alias eq")
@@ -100,6 +206,33 @@ judgment_fn! {
             (prove_wc(decls, env, assumptions, Relation::Equals(a, b)) => c)
         )
 
+        // FIXME: there is no variance yet, so subtyping is equality.
+        (
+            (prove_eq(decls, env, assumptions, a, b) => c)
+            ----------------------------- ("sub")
+            (prove_wc(decls, env, assumptions, Relation::Sub(a, b)) => c)
+        )
+
+        (
+            (prove_outlives(decls, env, assumptions, a, b) => c)
+            ----------------------------- ("outlives")
+            (prove_wc(decls, env, assumptions, Relation::Outlives(a, b)) => c)
+        )
+
+        (
+            (if let (Some(a), Some(b)) = (a.downcast::<Ty>(), b.downcast::<Ty>()))
+            (prove_coerce(&decls, &env, &assumptions, a, b) => c)
+            ----------------------------- ("coerce")
+            (prove_wc(decls, env, assumptions, Relation::Coerce(a, b)) => c)
+        )
+
+        (
+            (if let (Some(a), Some(b)) = (a.downcast::<Ty>(), b.downcast::<Ty>()))
+            (prove_receiver(&decls, &env, &assumptions, a, b) => c)
+            ----------------------------- ("receiver")
+            (prove_wc(decls, env, assumptions, Relation::Receiver(a, b)) => c)
+        )
+
         (
             (let t = decls.trait_decl(&trait_ref.trait_id))
             (let t = t.binder.instantiate_with(&trait_ref.parameters).unwrap())
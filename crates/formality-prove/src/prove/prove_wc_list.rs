@@ -1,8 +1,13 @@
-use formality_types::{grammar::Wcs, judgment_fn};
+use formality_types::{
+    collections::Set,
+    grammar::{Relation, Wc, WcData, Wcs, PR},
+    judgment::proof_tree::{self, Derivations},
+    judgment_fn,
+};
 
 use crate::{
     decls::Decls,
-    prove::{constraints::Constraints, prove_after::prove_after},
+    prove::{constraints::Constraints, minimize, prove_after::prove_after},
 };
 
 use super::{env::Env, prove_wc::prove_wc};
@@ -24,10 +29,67 @@ judgment_fn! {
         )
 
         (
-            (prove_wc(&decls, env, &assumptions, wc0) => c)
+            (if let Some((wc0, wcs1)) = split_first_goal(&goal))
+            (prove_wc_minimized(&decls, &env, &assumptions, wc0) => c)
             (prove_after(&decls, c, &assumptions, &wcs1) => c)
             --- ("some")
-            (prove_wc_list(decls, env, assumptions, (wc0, wcs1)) => c)
+            (prove_wc_list(decls, env, assumptions, goal) => c)
         )
     }
 }
+
+/// Proves `goal` with [`prove_wc`] in the minimized env of `goal` and `assumptions`,
+/// like [`super::prove`] does: `env` grows as the goals before `goal` are proven, and
+/// `goal` is canonicalized so that it shares the entries of the memo tables with the
+/// goals that only differ in the variables of `env` it does not mention.
+fn prove_wc_minimized(decls: &Decls, env: &Env, assumptions: &Wcs, goal: Wc) -> Set<Constraints> {
+    let goal0 = goal.clone();
+    let (env, (assumptions, goal), min) =
+        minimize::minimize(env.clone(), (assumptions.clone(), goal));
+    let result_set = prove_wc(decls, env, assumptions, goal);
+    let inner_derivations = proof_tree::take_last_call();
+
+    let mut derivations = Derivations::default();
+    let result = result_set
+        .into_iter()
+        .map(|r| {
+            let c = with_origin(min.reconstitute(r.clone()), &goal0);
+            if let Some(inner) = &inner_derivations {
+                let premises = inner.premises(&r).unwrap_or_default().to_vec();
+                derivations.insert(&c, premises, inner.is_complete(&r));
+            }
+            c
+        })
+        .collect();
+    proof_tree::set_last_call(inner_derivations.map(|_| derivations));
+    result
+}
+
+/// Records `goal` as the origin of the region constraints that proving it introduced,
+/// unless it is itself an outlives relation, which says nothing of where those came from.
+fn with_origin(c: Constraints, goal: &Wc) -> Constraints {
+    if matches!(
+        goal.data(),
+        WcData::PR(PR::Relation(Relation::Outlives(..)))
+    ) {
+        return c;
+    }
+    let goal = c.substitution().apply(goal);
+    c.with_region_origin(&goal)
+}
+
+/// Splits off the goal to prove first; see [`in_proof_order`].
+fn split_first_goal(goal: &Wcs) -> Option<(Wc, Wcs)> {
+    let mut goals = in_proof_order(goal).into_iter();
+    let first = goals.next()?;
+    Some((first, goals.collect()))
+}
+
+/// The where-clauses of `goal` in the order to prove them. Equalities come first, as
+/// rustc equates impl headers before it proves their where-clauses, so that the
+/// where-clauses are proven with what the equalities infer.
+pub(super) fn in_proof_order(goal: &Wcs) -> Vec<Wc> {
+    let mut goals: Vec<Wc> = goal.into_iter().collect();
+    goals.sort_by_key(|wc| !matches!(wc.data(), WcData::PR(PR::Relation(Relation::Equals(..)))));
+    goals
+}
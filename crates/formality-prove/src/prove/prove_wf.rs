@@ -1,9 +1,16 @@
 use formality_types::{
-    grammar::{ConstData, Parameter, RigidName, RigidTy, UniversalVar, Wcs, AliasTy, Parameters, AliasName},
-    judgment_fn, collections::Set,
+    collections::Set,
+    grammar::{
+        AdtId, AliasName, AliasTy, ConstData, LtData, Parameter, Parameters, RigidName, RigidTy,
+        UniversalVar, Wcs,
+    },
+    judgment_fn,
 };
 
-use crate::{decls::Decls, prove::combinators::for_all};
+use crate::{
+    decls::{AdtDeclBoundData, Decls, TypeAliasDeclBoundData},
+    prove::{combinators::for_all, prove_after::prove_after},
+};
 
 use super::{constraints::Constraints, env::Env};
 
@@ -27,6 +34,11 @@ judgment_fn! {
             (prove_wf(_decls, env, _assumptions, UniversalVar { .. }) => Constraints::none(env))
         )
 
+        (
+            --- ("static")
+            (prove_wf(_decls, env, _assumptions, LtData::Static) => Constraints::none(env))
+        )
+
         (
             (for_all(&decls, &env, &assumptions, &parameters, &prove_wf) => c)
             --- ("tuples")
@@ -41,8 +53,53 @@ judgment_fn! {
 
         (
             (for_all(&decls, &env, &assumptions, &parameters, &prove_wf) => c)
+            --- ("raw pointers")
+            (prove_wf(decls, env, assumptions, RigidTy { name: RigidName::RawPtr(_), parameters }) => c)
+        )
+
+        (
+            (for_all(&decls, &env, &assumptions, &parameters, &prove_wf) => c)
+            --- ("fn pointers")
+            (prove_wf(decls, env, assumptions, RigidTy { name: RigidName::FnPtr(..), parameters }) => c)
+        )
+
+        (
+            (for_all(&decls, &env, &assumptions, &parameters, &prove_wf) => c)
+            --- ("closures")
+            (prove_wf(decls, env, assumptions, RigidTy { name: RigidName::Closure(_), parameters }) => c)
+        )
+
+        (
+            (for_all(&decls, &env, &assumptions, &parameters, &prove_wf) => c)
+            --- ("coroutines")
+            (prove_wf(decls, env, assumptions, RigidTy { name: RigidName::Coroutine, parameters }) => c)
+        )
+
+        (
+            --- ("never")
+            (prove_wf(_decls, env, _assumptions, RigidTy { name: RigidName::Never, .. }) => Constraints::none(env))
+        )
+
+        (
+            (if decls.is_dyn_compatible(&trait_id))
+            (for_all(&decls, &env, &assumptions, &parameters, &prove_wf) => c)
+            --- ("dyn")
+            (prove_wf(decls, env, assumptions, RigidTy { name: RigidName::Dyn(trait_id), parameters }) => c)
+        )
+
+        (
+            (prove_adt_wf(&decls, &env, &assumptions, id, parameters) => c)
             --- ("ADT")
-            (prove_wf(decls, env, assumptions, RigidTy { name: RigidName::AdtId(_), parameters }) => c)
+            (prove_wf(decls, env, assumptions, RigidTy { name: RigidName::AdtId(id), parameters }) => c)
+        )
+
+        // `&'a T` is well formed if `T` is and outlives `'a`.
+        (
+            (if let [lt, ty] = &parameters[..])
+            (prove_wf(&decls, &env, &assumptions, ty) => c)
+            (prove_after(&decls, c, &assumptions, ty.outlives(lt)) => c)
+            --- ("references")
+            (prove_wf(decls, env, assumptions, RigidTy { name: RigidName::Ref(_), parameters }) => c)
         )
 
         (
@@ -59,13 +116,55 @@ judgment_fn! {
     }
 }
 
+/// An ADT is well formed if it is declared, its parameters are well formed and its
+/// where-clauses hold.
+pub fn prove_adt_wf(
+    decls: &Decls,
+    env: &Env,
+    assumptions: &Wcs,
+    id: AdtId,
+    parameters: Parameters,
+) -> Set<Constraints> {
+    let Some(adt_decl) = decls.adt_decl(&id) else {
+        return Set::new();
+    };
+    let cs = for_all(decls, env, assumptions, &parameters, &prove_wf);
+    let Ok(AdtDeclBoundData { where_clause }) = adt_decl.binder.instantiate_with(&parameters)
+    else {
+        return Set::new();
+    };
+    cs.into_iter()
+        .flat_map(|c| prove_after(decls, c, assumptions, &where_clause))
+        .collect()
+}
+
 pub fn prove_alias_wf(
     decls: &Decls,
     env: &Env,
     assumptions: &Wcs,
-    _name: AliasName,
+    name: AliasName,
     parameters: Parameters,
 ) -> Set<Constraints> {
-    // FIXME: verify self type implements trait
-    for_all(decls, env, assumptions, &parameters, &prove_wf)
+    let cs = for_all(decls, env, assumptions, &parameters, &prove_wf);
+    match name {
+        // FIXME: verify self type implements trait
+        AliasName::AssociatedTyId(_) => cs,
+
+        // A type alias is well formed if its where-clauses hold.
+        AliasName::TypeAliasId(id) => {
+            let Ok(TypeAliasDeclBoundData {
+                ty: _,
+                where_clause,
+            }) = decls
+                .type_alias_decl(&id)
+                .binder
+                .instantiate_with(&parameters)
+            else {
+                return Set::new();
+            };
+            cs.into_iter()
+                .flat_map(|c| prove_after(decls, c, assumptions, &where_clause))
+                .collect()
+        }
+    }
 }
@@ -0,0 +1,63 @@
+use formality_types::{
+    cast::Upcast,
+    grammar::{Wc, Wcs},
+};
+
+use crate::decls::Decls;
+
+use super::{prove, prove_wc_list::in_proof_order, Constraints, Env};
+
+/// The solutions of a goal, enumerated lazily; see [`prove_lazily`].
+pub struct Solutions {
+    iter: Box<dyn Iterator<Item = Constraints>>,
+}
+
+impl Iterator for Solutions {
+    type Item = Constraints;
+
+    fn next(&mut self) -> Option<Constraints> {
+        self.iter.next()
+    }
+}
+
+/// Like [`prove`], but yields the solutions one at a time. Each where-clause of `goal`
+/// is only proven once those before it (in [`in_proof_order`]) have yielded a solution, so callers that stop
+/// early (e.g., because they only need to know whether there is any solution) skip
+/// the work for the remaining ones.
+///
+/// Unlike [`prove`], the same solution may be yielded more than once.
+pub fn prove_lazily(
+    decls: impl Upcast<Decls>,
+    env: impl Upcast<Env>,
+    assumptions: impl Upcast<Wcs>,
+    goal: impl Upcast<Wcs>,
+) -> Solutions {
+    let goal: Wcs = goal.upcast();
+    Solutions {
+        iter: solutions(
+            decls.upcast(),
+            env.upcast(),
+            assumptions.upcast(),
+            in_proof_order(&goal),
+        ),
+    }
+}
+
+fn solutions(
+    decls: Decls,
+    env: Env,
+    assumptions: Wcs,
+    goal: Vec<Wc>,
+) -> Box<dyn Iterator<Item = Constraints>> {
+    let Some((wc0, wcs1)) = goal.split_first() else {
+        return Box::new(std::iter::once(Constraints::none(env)));
+    };
+
+    let wcs1 = wcs1.to_vec();
+    let cs1 = prove(&decls, &env, &assumptions, wc0);
+    Box::new(cs1.into_iter().flat_map(move |c1| {
+        let assumptions = c1.substitution().apply(&assumptions);
+        let wcs1 = c1.substitution().apply(&wcs1);
+        solutions(decls.clone(), c1.env().clone(), assumptions, wcs1).map(move |c2| c1.seq(c2))
+    }))
+}
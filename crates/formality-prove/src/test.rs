@@ -1,9 +1,25 @@
+mod candidates;
+mod canonical;
+mod decls_index;
+mod elaborate;
 mod eq_assumptions;
 mod eq_partial_eq;
+mod erase_regions;
 mod exists_constraints;
 mod expanding;
+mod generalize;
 mod is_local;
 mod magic_copy;
+mod memo;
 mod occurs_check;
+mod outlives;
+mod overflow;
+mod parallel;
+mod proof_result;
+mod proof_tree;
+mod properties;
+mod query_cache;
+mod search_strategy;
 mod simple_impl;
+mod solutions;
 mod universes;
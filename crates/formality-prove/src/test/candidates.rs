@@ -0,0 +1,94 @@
+use expect_test::expect;
+use formality_macros::test;
+use formality_types::parse::term;
+
+use crate::{
+    decls::Decls,
+    test_util::{test_prove, test_prove_result},
+    ProofResult,
+};
+
+fn decls() -> Decls {
+    Decls::empty()
+        .with_trait_decls(vec![term("trait Debug<ty Self> where {}")])
+        .with_impl_decls(vec![
+            term("impl<ty T> Debug(Vec<T>) where {Debug(T)}"),
+            term("impl<> Debug(u32) where {}"),
+        ])
+}
+
+/// The where-clause `Debug(U)` applies, so the impls are not considered and `T = U`
+/// is the only solution.
+#[test]
+fn where_clause_preferred_over_impls() {
+    let constraints = test_prove(
+        decls(),
+        term("forall<ty U> exists<ty T> {Debug(U)} => {Debug(T)}"),
+    );
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [
+                        !ty_0,
+                        ?ty_1,
+                    ],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {
+                    ?ty_1 => !ty_0,
+                },
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+/// Both impls apply, in different ways, so neither is chosen.
+#[test]
+fn multiple_impls_ambiguous() {
+    let result =
+        ProofResult::from_constraints(test_prove(decls(), term("exists<ty T> {} => {Debug(T)}")));
+    assert!(matches!(result, ProofResult::Ambiguous(_)));
+}
+
+/// Only the impl for `Vec<T>` applies, so it is chosen.
+#[test]
+fn single_impl_chosen() {
+    let result =
+        ProofResult::from_constraints(test_prove(decls(), term("{} => {Debug(Vec<u32>)}")));
+    assert!(result.is_yes());
+}
+
+/// Impls for other rigid types than `u32` are skipped, and the impls that remain give
+/// the same result as when all impls are tried.
+#[test]
+fn impls_for_other_types_skipped() {
+    let decls = Decls::empty()
+        .with_trait_decls(vec![term("trait Debug<ty Self> where {}")])
+        .with_impl_decls(
+            (0..32)
+                .map(|i| term(&format!("impl<> Debug(S{i}) where {{}}")))
+                .chain([term("impl<> Debug(u32) where {}")])
+                .collect(),
+        );
+    assert!(test_prove_result(decls.clone(), term("{} => {Debug(u32)}")).is_yes());
+    assert!(test_prove_result(decls.clone(), term("{} => {Debug(bool)}")).is_no());
+    let result =
+        ProofResult::from_constraints(test_prove(decls, term("exists<ty T> {} => {Debug(T)}")));
+    assert!(matches!(result, ProofResult::Ambiguous(_)));
+}
+
+/// An assumption may equate rigid types with different names, so the impls are not
+/// skipped by their names then.
+#[test]
+fn impls_not_skipped_under_equalities() {
+    let decls = Decls::empty()
+        .with_trait_decls(vec![term("trait Debug<ty Self> where {}")])
+        .with_impl_decls(vec![term("impl<> Debug(Vec<u32>) where {}")]);
+    assert!(test_prove_result(decls.clone(), term("{} => {Debug(u32)}")).is_no());
+    assert!(test_prove_result(decls, term("{u32 = Vec<u32>} => {Debug(u32)}")).is_yes());
+}
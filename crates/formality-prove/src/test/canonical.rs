@@ -0,0 +1,93 @@
+use expect_test::expect;
+use formality_macros::test;
+use formality_types::{
+    collections::Set,
+    grammar::{Binder, ParameterKind, Wcs},
+    parse::term,
+};
+
+use crate::{canonicalize, decls::Decls, instantiate_response, prove, prove_canonical_goal, Env};
+
+fn decls() -> Decls {
+    Decls::empty()
+        .with_trait_decls(vec![
+            term("trait Debug<ty Self> where {}"),
+            term("trait Display<ty Self> where {}"),
+        ])
+        .with_impl_decls(vec![
+            term("impl<ty T> Debug(Vec<T>) where {}"),
+            term("impl<> Debug(u32) where {}"),
+        ])
+}
+
+/// Proving `Debug(T)` assuming `Display(U)`, where the env also has a variable that
+/// the goal does not mention.
+fn env_and_goal() -> (Env, Wcs, Wcs) {
+    let mut env = Env::default();
+    env.fresh_existential(ParameterKind::Ty);
+    let u = env.fresh_universal(ParameterKind::Ty);
+    let t = env.fresh_existential(ParameterKind::Ty);
+    let display: Binder<Wcs> = term("<ty X> {Display(X)}");
+    let debug: Binder<Wcs> = term("<ty X> {Debug(X)}");
+    let assumptions = display.instantiate_with(&[u]).unwrap();
+    let goal = debug.instantiate_with(&[t]).unwrap();
+    (env, assumptions, goal)
+}
+
+#[test]
+fn canonical_responses() {
+    let (env, assumptions, goal) = env_and_goal();
+    let (canonical_goal, _) = canonicalize(&env, assumptions, goal);
+    expect![[r#"
+        (
+            CanonicalGoal {
+                quantifiers: [
+                    ForAll,
+                    Exists,
+                ],
+                coherence_mode: false,
+                binder: <ty, ty> ({Display(^ty0_0)}, {Debug(^ty0_1)}),
+            },
+            [
+                CanonicalResponse {
+                    known_true: false,
+                    overflow: false,
+                    fresh: [
+                        false,
+                        false,
+                    ],
+                    var_values: <ty, ty> [^ty0_0, (rigid (scalar u32))],
+                    region_constraints: <ty, ty> [],
+                },
+                CanonicalResponse {
+                    known_true: false,
+                    overflow: false,
+                    fresh: [
+                        false,
+                        true,
+                        false,
+                    ],
+                    var_values: <ty, ty, ty> [^ty0_0, (rigid (adt Vec) ^ty0_1)],
+                    region_constraints: <ty, ty, ty> [],
+                },
+            ],
+        )
+    "#]]
+    .assert_debug_eq(&(
+        &canonical_goal,
+        prove_canonical_goal(decls(), &canonical_goal),
+    ));
+}
+
+/// Instantiating the responses yields the same constraints as proving the goal directly.
+#[test]
+fn instantiated_responses_match_prove() {
+    let (env, assumptions, goal) = env_and_goal();
+    let (canonical_goal, variables) = canonicalize(&env, &assumptions, &goal);
+    let instantiated: Set<_> = prove_canonical_goal(decls(), &canonical_goal)
+        .iter()
+        .map(|response| instantiate_response(&env, &variables, response))
+        .collect();
+    let direct = prove(decls(), env, assumptions, goal);
+    assert_eq!(instantiated, direct);
+}
@@ -0,0 +1,80 @@
+use expect_test::expect;
+use formality_macros::test;
+use formality_types::{grammar::TraitId, parse::term};
+
+use crate::Decls;
+
+fn decls() -> Decls {
+    Decls::empty()
+        .with_trait_decls(vec![
+            term("trait Debug<ty Self> where {}"),
+            term("trait Eq<ty Self> where {PartialEq(Self)}"),
+            term("trait PartialEq<ty Self> where {}"),
+        ])
+        .with_impl_decls(vec![
+            term("impl<> Debug(u32) where {}"),
+            term("impl<> Eq(u32) where {}"),
+            term("impl<ty T> Debug(Vec<T>) where {Debug(T)}"),
+        ])
+}
+
+/// The impls of a trait are found in the order they are declared in.
+#[test]
+fn impls_by_trait() {
+    let decls = decls();
+    let debug_impls: Vec<String> = decls
+        .impl_decls(&TraitId::new("Debug"))
+        .map(|i| format!("{i:?}"))
+        .collect();
+    expect![[r#"
+        [
+            "impl <> Debug((rigid (scalar u32))) where {}",
+            "impl <ty> Debug((rigid (adt Vec) ^ty0_0)) where {Debug(^ty0_0)}",
+        ]
+    "#]]
+    .assert_debug_eq(&debug_impls);
+    assert_eq!(decls.impl_decls(&TraitId::new("Clone")).count(), 0);
+    assert_eq!(decls.trait_invariants().len(), 1);
+}
+
+/// Adding impls to decls whose index is built indexes them as well, and leaves the
+/// index of the original decls as it was.
+#[test]
+fn hypothetical_impls() {
+    let decls = decls();
+    let eq = TraitId::new("Eq");
+    assert_eq!(decls.impl_decls(&eq).count(), 1);
+    let more = decls.with_hypothetical_impls([term("impl<> Eq(bool) where {}")]);
+    assert_eq!(more.impl_decls(&eq).count(), 2);
+    assert_eq!(decls.impl_decls(&eq).count(), 1);
+    assert_ne!(more, decls);
+}
+
+/// Replacing the decls of a clone whose index is built indexes the new decls, and
+/// leaves the index of the original decls as it was.
+#[test]
+fn replaced_decls() {
+    let decls = decls();
+    let eq = TraitId::new("Eq");
+    assert_eq!(decls.impl_decls(&eq).count(), 1);
+    let replaced = decls.clone().with_impl_decls(vec![
+        term("impl<> Eq(bool) where {}"),
+        term("impl<> Eq(i32) where {}"),
+    ]);
+    assert_eq!(replaced.impl_decls(&eq).count(), 2);
+    assert_eq!(replaced.impl_decls(&TraitId::new("Debug")).count(), 0);
+    assert_eq!(decls.impl_decls(&eq).count(), 1);
+}
+
+/// The index is not part of what the decls are: it is written `..`, whether or not
+/// it is built.
+#[test]
+fn index_is_not_a_decl() {
+    let empty: Decls = term(
+        "decls(solver_config(222, 128, false, depth_first, false, solver_flags({negative_coherence, re_rebalancing_coherence})), [], [], [], [], [], [], [], [], {}, {}, {}, ..)",
+    );
+    assert_eq!(empty, Decls::empty());
+    let decls = decls();
+    decls.impl_decls(&TraitId::new("Debug")).count();
+    assert!(format!("{decls:?}").ends_with("{}, {}, {}, ..)"));
+}
@@ -0,0 +1,46 @@
+use formality_macros::test;
+use formality_types::parse::term;
+
+use crate::{decls::Decls, test_util::test_prove, ProofResult};
+
+fn decls() -> Decls {
+    Decls::empty().with_trait_decls(vec![
+        term("trait Eq<ty Self> where {PartialEq(Self)}"),
+        term("trait PartialEq<ty Self> where {}"),
+        term("trait Static<ty Self> where {Self : static}"),
+    ])
+}
+
+fn is_yes(assertion: &str) -> bool {
+    ProofResult::from_constraints(test_prove(decls(), term(assertion))).is_yes()
+}
+
+/// `Eq(T)` implies its supertrait `PartialEq(T)`.
+#[test]
+fn supertrait() {
+    assert!(is_yes("{} => {for<ty T> if {Eq(T)} PartialEq(T)}"));
+}
+
+/// `Static(T)` implies `T : static`, which outlives every lifetime.
+#[test]
+fn supertrait_outlives() {
+    assert!(is_yes("{} => {for<ty T, lt a> if {Static(T)} T : a}"));
+}
+
+/// A type outlives `a` only if its components do.
+#[test]
+fn outlives_components() {
+    assert!(is_yes("{} => {for<ty T, lt a> if {Vec<T> : a} T : a}"));
+}
+
+#[test]
+fn outlives_transitive_through_components() {
+    assert!(is_yes(
+        "{} => {for<ty T, lt a, lt b> if {Vec<T> : a, a : b} T : b}"
+    ));
+}
+
+#[test]
+fn not_implied() {
+    assert!(!is_yes("{} => {for<ty T, lt a> if {PartialEq(T)} T : a}"));
+}
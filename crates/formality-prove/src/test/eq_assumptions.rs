@@ -20,7 +20,9 @@ fn test_a() {
                     coherence_mode: false,
                 },
                 known_true: true,
+                overflow: false,
                 substitution: {},
+                region_constraints: {},
             },
         }
     "#]]
@@ -38,16 +40,16 @@ fn test_b() {
             Constraints {
                 env: Env {
                     variables: [
-                        ?ty_2,
-                        ?ty_1,
+                        ?ty_0,
                     ],
                     coherence_mode: false,
                 },
                 known_true: true,
+                overflow: false,
                 substitution: {
-                    ?ty_1 => (rigid (adt Vec) (rigid (scalar u32))),
-                    ?ty_2 => (rigid (scalar u32)),
+                    ?ty_0 => (rigid (adt Vec) (rigid (scalar u32))),
                 },
+                region_constraints: {},
             },
         }
     "#]]
@@ -68,7 +70,9 @@ fn test_normalize_assoc_ty() {
                     coherence_mode: false,
                 },
                 known_true: true,
+                overflow: false,
                 substitution: {},
+                region_constraints: {},
             },
         }
     "#]]
@@ -103,15 +107,17 @@ fn test_normalize_assoc_ty_existential1() {
             Constraints {
                 env: Env {
                     variables: [
-                        !ty_1,
-                        ?ty_2,
+                        !ty_0,
+                        ?ty_1,
                     ],
                     coherence_mode: false,
                 },
                 known_true: true,
+                overflow: false,
                 substitution: {
-                    ?ty_2 => !ty_1,
+                    ?ty_1 => !ty_0,
                 },
+                region_constraints: {},
             },
         }
     "#]]
@@ -9,13 +9,10 @@ use crate::{decls::Decls, prove::prove};
 
 /// Simple example decls consisting only of two trait declarations.
 fn decls() -> Decls {
-    Decls {
-        trait_decls: vec![
-            term("trait Eq<ty Self> where {PartialEq(Self)}"),
-            term("trait PartialEq<ty Self> where {}"),
-        ],
-        ..Decls::empty()
-    }
+    Decls::empty().with_trait_decls(vec![
+        term("trait Eq<ty Self> where {PartialEq(Self)}"),
+        term("trait PartialEq<ty Self> where {}"),
+    ])
 }
 
 #[test]
@@ -31,7 +28,9 @@ fn eq_implies_partial_eq() {
                     coherence_mode: false,
                 },
                 known_true: true,
+                overflow: false,
                 substitution: {},
+                region_constraints: {},
             },
         }
     "#]]
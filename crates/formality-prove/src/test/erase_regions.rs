@@ -0,0 +1,102 @@
+use expect_test::expect;
+use formality_macros::test;
+use formality_types::parse::term;
+
+use crate::{decls::Decls, test_util::test_prove, SolverConfig};
+
+fn decls(erase_regions: bool) -> Decls {
+    Decls::empty().with_config(SolverConfig {
+        erase_regions,
+        ..SolverConfig::default()
+    })
+}
+
+#[test]
+fn distinct_lifetimes_differ() {
+    let constraints = test_prove(
+        decls(false),
+        term("{} => {for<lt a, lt b> &a u32 = &b u32}"),
+    );
+    expect![[r#"
+        {}
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+/// Modulo lifetimes, `&'a u32` and `&'b u32` are the same type.
+#[test]
+fn distinct_lifetimes_erased() {
+    let constraints = test_prove(decls(true), term("{} => {for<lt a, lt b> &a u32 = &b u32}"));
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {},
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+/// Erasing regions does not make distinct types equal.
+#[test]
+fn distinct_types_erased() {
+    let constraints = test_prove(decls(true), term("{} => {for<lt a, lt b> &a u32 = &b i32}"));
+    expect![[r#"
+        {}
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+#[test]
+fn outlives_erased() {
+    let constraints = test_prove(decls(true), term("{} => {for<lt a, lt b> a : b}"));
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {},
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+/// Modulo lifetimes, `x` need not name `a`.
+#[test]
+fn unnameable_lifetime_erased() {
+    let constraints = test_prove(
+        decls(true),
+        term("exists<lt x> forall<lt a> {} => {&x u32 = &a u32}"),
+    );
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [
+                        ?lt_0,
+                        !lt_1,
+                    ],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {},
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
@@ -8,11 +8,9 @@ use crate::test_util::test_prove;
 
 /// Simple example decls consisting only of two trait declarations.
 fn decls() -> Decls {
-    Decls {
-        trait_decls: vec![term("trait Foo<ty Self> where {}")],
-        impl_decls: vec![term("impl<ty T> Foo(Vec<T>) where {}")],
-        ..Decls::empty()
-    }
+    Decls::empty()
+        .with_trait_decls(vec![term("trait Foo<ty Self> where {}")])
+        .with_impl_decls(vec![term("impl<ty T> Foo(Vec<T>) where {}")])
 }
 
 /// Test that `exists<T> Foo(U)` yields `U = Vec<X>` for some fresh `X`
@@ -24,15 +22,17 @@ fn exists_u_for_t() {
             Constraints {
                 env: Env {
                     variables: [
-                        ?ty_2,
+                        ?ty_0,
                         ?ty_1,
                     ],
                     coherence_mode: false,
                 },
                 known_true: true,
+                overflow: false,
                 substitution: {
-                    ?ty_1 => (rigid (adt Vec) ?ty_2),
+                    ?ty_1 => (rigid (adt Vec) ?ty_0),
                 },
+                region_constraints: {},
             },
         }
     "#]]
@@ -2,16 +2,17 @@ use expect_test::expect;
 use formality_macros::test;
 use formality_types::parse::term;
 
-use crate::{test_util::test_prove, Decls};
+use crate::{test_util::test_prove, Decls, SolverConfig};
 
 /// Simple example decls consisting only of two trait declarations.
 fn decls() -> Decls {
-    Decls {
-        max_size: 10,
-        trait_decls: vec![term("trait Debug<ty Self> where {}")],
-        impl_decls: vec![term("impl<ty T> Debug(Vec<T>) where {Debug(T)}")],
-        ..Decls::empty()
-    }
+    Decls::empty()
+        .with_config(SolverConfig {
+            max_size: 10,
+            ..SolverConfig::default()
+        })
+        .with_trait_decls(vec![term("trait Debug<ty Self> where {}")])
+        .with_impl_decls(vec![term("impl<ty T> Debug(Vec<T>) where {Debug(T)}")])
 }
 
 /// There is no U that is equal to all T.
@@ -28,7 +29,9 @@ fn expanding() {
                     coherence_mode: false,
                 },
                 known_true: false,
+                overflow: true,
                 substitution: {},
+                region_constraints: {},
             },
         }
     "#]]
@@ -0,0 +1,103 @@
+use expect_test::expect;
+use formality_macros::test;
+use formality_types::parse::term;
+
+use crate::decls::Decls;
+
+use crate::test_util::test_prove;
+
+/// `Id::Out` normalizes to `u32` for every `T`.
+fn decls() -> Decls {
+    Decls::empty()
+        .with_trait_decls(vec![term("trait Id<ty Self> where {}")])
+        .with_impl_decls(vec![term("impl<ty T> Id(T) where {}")])
+        .with_alias_eq_decls(vec![term(
+            "alias <ty T> (alias (Id::Out) T) = u32 where {Id(T)}",
+        )])
+}
+
+/// `X` occurs in `Vec<<X as Id>::Out>` only within an alias, which normalizes to `u32`.
+#[test]
+fn occurs_in_nested_alias() {
+    let constraints = test_prove(
+        decls(),
+        term("exists<ty X> {} => {X = Vec<(alias (Id::Out) X)>}"),
+    );
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [
+                        ?ty_0,
+                    ],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {
+                    ?ty_0 => (rigid (adt Vec) (rigid (scalar u32))),
+                },
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+/// Here `<X as Id>::Out` normalizes to `X`, so `X = Vec<X>` fails the occurs check.
+#[test]
+fn occurs_in_nested_alias_infinite() {
+    let decls = decls().with_alias_eq_decls(vec![term(
+        "alias <ty T> (alias (Id::Out) T) = T where {Id(T)}",
+    )]);
+    let constraints = test_prove(
+        decls,
+        term("exists<ty X> {} => {X = Vec<(alias (Id::Out) X)>}"),
+    );
+    expect![[r#"
+        {}
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+/// `X` cannot name `T`, but `<T as Id>::Out` normalizes to `u32`, which it can.
+#[test]
+fn universal_in_nested_alias() {
+    let constraints = test_prove(
+        decls(),
+        term("exists<ty X> forall<ty T> {} => {X = Vec<(alias (Id::Out) T)>}"),
+    );
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [
+                        ?ty_0,
+                        !ty_1,
+                    ],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {
+                    ?ty_0 => (rigid (adt Vec) (rigid (scalar u32))),
+                },
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+/// `X` cannot name `T` outside of an alias.
+#[test]
+fn universal_outside_alias() {
+    let constraints = test_prove(
+        decls(),
+        term("exists<ty X> forall<ty T> {} => {X = Vec<T>}"),
+    );
+    expect![[r#"
+        {}
+    "#]]
+    .assert_debug_eq(&constraints);
+}
@@ -29,12 +29,14 @@ fn test_exists_not_local() {
             Constraints {
                 env: Env {
                     variables: [
-                        ?ty_1,
+                        ?ty_0,
                     ],
                     coherence_mode: true,
                 },
                 known_true: false,
+                overflow: false,
                 substitution: {},
+                region_constraints: {},
             },
         }
     "#]] // FIXME: really this should be ambiguous, not sure if it matters
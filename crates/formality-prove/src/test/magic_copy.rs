@@ -8,17 +8,15 @@ use crate::test_util::test_prove;
 
 /// Simple example decls consisting only of two trait declarations.
 fn decls() -> Decls {
-    Decls {
-        trait_decls: vec![
+    Decls::empty()
+        .with_trait_decls(vec![
             term("trait Copy<ty Self> where {}"),
             term("trait Magic<ty Self> where {Copy(Self)}"),
-        ],
-        impl_decls: vec![
+        ])
+        .with_impl_decls(vec![
             term("impl<ty T> Magic(T) where {Magic(T)}"),
             term("impl<> Copy(u32) where {}"),
-        ],
-        ..Decls::empty()
-    }
+        ])
 }
 
 #[test]
@@ -0,0 +1,30 @@
+use formality_macros::test;
+use formality_types::{grammar::Wc, judgment::stats, parse::term};
+
+use crate::{decls::Decls, prove::prove, test_util::test_prove};
+
+fn decls() -> Decls {
+    Decls::empty()
+        .with_trait_decls(vec![
+            term("trait Debug<ty Self> where {}"),
+            term("trait Zeta<ty Self> where {}"),
+        ])
+        .with_impl_decls(vec![
+            term("impl<> Debug(u32) where {}"),
+            term("impl<ty T> Zeta(T) where {}"),
+        ])
+}
+
+/// `Debug(u32)` is proven first in the env of both goals, which has the variable `T`.
+/// Goals are canonicalized before the memo tables are consulted, so that proof is
+/// memoized for `Debug(u32)` in an env without `T` as well.
+#[test]
+fn goals_share_memo_across_envs() {
+    let constraints = test_prove(decls(), term("exists<ty T> {} => {Debug(u32), Zeta(T)}"));
+    assert!(constraints.iter().any(|c| c.known_true));
+
+    let goal: Wc = term("Debug(u32)");
+    let (constraints, stats) = stats::record(|| prove(decls(), (), (), goal));
+    assert!(constraints.iter().any(|c| c.known_true));
+    assert_eq!(stats.judgments["prove_wc"].memo_hits, 1);
+}
@@ -4,15 +4,13 @@ use formality_types::parse::term;
 
 use crate::decls::Decls;
 
-use crate::test_util::test_prove;
+use crate::test_util::{test_prove, test_prove_result};
 
 /// Simple example decls consisting only of two trait declarations.
 fn decls() -> Decls {
-    Decls {
-        trait_decls: vec![term("trait Foo<ty Self> where {}")],
-        impl_decls: vec![term("impl<ty T> Foo(Vec<T>) where {}")],
-        ..Decls::empty()
-    }
+    Decls::empty()
+        .with_trait_decls(vec![term("trait Foo<ty Self> where {}")])
+        .with_impl_decls(vec![term("impl<ty T> Foo(Vec<T>) where {}")])
 }
 
 /// Test that `X = Vec<X>` cannot be solved
@@ -25,6 +23,49 @@ fn direct_cycle() {
     .assert_debug_eq(&constraints);
 }
 
+/// Test that the failure of `X = Vec<X>` reports the occurs check
+#[test]
+fn direct_cycle_result() {
+    let result = test_prove_result(decls(), term("exists<ty A> {} => {A = Vec<A>}"));
+    expect![[r#"
+        No(
+            [
+                OccursCheck {
+                    var: ?ty_0,
+                    ty: (rigid (adt Vec) ?ty_0),
+                },
+            ],
+        )
+    "#]]
+    .assert_debug_eq(&result);
+}
+
+/// Test that an occurs check behind an impl is reported too
+#[test]
+fn indirect_cycle_result() {
+    let decls = Decls::empty()
+        .with_trait_decls(vec![
+            term("trait Foo<ty Self> where {}"),
+            term("trait Bar<ty Self, ty T> where {}"),
+        ])
+        .with_impl_decls(vec![
+            term("impl<ty T> Foo(Vec<T>) where {Bar(T, Vec<T>)}"),
+            term("impl<ty T> Bar(T, T) where {}"),
+        ]);
+    let result = test_prove_result(decls, term("exists<ty A> {} => {Foo(Vec<A>)}"));
+    expect![[r#"
+        No(
+            [
+                OccursCheck {
+                    var: ?ty_0,
+                    ty: (rigid (adt Vec) ?ty_0),
+                },
+            ],
+        )
+    "#]]
+    .assert_debug_eq(&result);
+}
+
 /// Test that `X = Vec<Y>` can be solved
 #[test]
 fn eq_variable_to_rigid() {
@@ -34,17 +75,19 @@ fn eq_variable_to_rigid() {
             Constraints {
                 env: Env {
                     variables: [
-                        ?ty_3,
+                        ?ty_0,
                         ?ty_1,
                         ?ty_2,
                     ],
                     coherence_mode: false,
                 },
                 known_true: true,
+                overflow: false,
                 substitution: {
-                    ?ty_1 => (rigid (adt Vec) ?ty_3),
-                    ?ty_2 => ?ty_3,
+                    ?ty_1 => (rigid (adt Vec) ?ty_0),
+                    ?ty_2 => ?ty_0,
                 },
+                region_constraints: {},
             },
         }
     "#]]
@@ -60,17 +103,19 @@ fn eq_rigid_to_variable() {
             Constraints {
                 env: Env {
                     variables: [
-                        ?ty_3,
+                        ?ty_0,
                         ?ty_1,
                         ?ty_2,
                     ],
                     coherence_mode: false,
                 },
                 known_true: true,
+                overflow: false,
                 substitution: {
-                    ?ty_1 => (rigid (adt Vec) ?ty_3),
-                    ?ty_2 => ?ty_3,
+                    ?ty_1 => (rigid (adt Vec) ?ty_0),
+                    ?ty_2 => ?ty_0,
                 },
+                region_constraints: {},
             },
         }
     "#]]
@@ -0,0 +1,195 @@
+use expect_test::expect;
+use formality_macros::test;
+use formality_types::parse::term;
+
+use crate::decls::Decls;
+
+use crate::test_util::{test_prove, test_prove_failure_trace};
+
+#[test]
+fn static_outlives_all() {
+    let constraints = test_prove(Decls::empty(), term("{} => {for<lt a> static : a}"));
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {},
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+#[test]
+fn unrelated_lifetimes() {
+    let constraints = test_prove(Decls::empty(), term("{} => {for<lt a, lt b> a : b}"));
+    expect![[r#"
+        {}
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+#[test]
+fn transitive() {
+    let constraints = test_prove(
+        Decls::empty(),
+        term("{} => {for<lt a, lt b, lt c> if {a : b, b : c} a : c}"),
+    );
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {},
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+#[test]
+fn scalar_outlives_all() {
+    let constraints = test_prove(Decls::empty(), term("{} => {for<lt a> u32 : a}"));
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {},
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+#[test]
+fn components() {
+    let constraints = test_prove(
+        Decls::empty(),
+        term("{} => {for<lt a, ty T> if {T : a} Vec<T> : a}"),
+    );
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {},
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+#[test]
+fn component_does_not_outlive() {
+    let constraints = test_prove(Decls::empty(), term("{} => {for<lt a, ty T> Vec<T> : a}"));
+    expect![[r#"
+        {}
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+#[test]
+fn reference_components() {
+    let constraints = test_prove(
+        Decls::empty(),
+        term("{} => {for<lt a, lt b, ty T> if {T : a, b : a} &b T : a}"),
+    );
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {},
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+#[test]
+fn reference_lifetime_does_not_outlive() {
+    let constraints = test_prove(
+        Decls::empty(),
+        term("{} => {for<lt a, lt b, ty T> if {T : a} &b T : a}"),
+    );
+    expect![[r#"
+        {}
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+#[test]
+fn alias_components() {
+    let constraints = test_prove(
+        Decls::empty(),
+        term("{} => {for<lt a, ty T> if {T : a} <T as Iterator>::Item : a}"),
+    );
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {},
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+/// The check of `x : b` is deferred until `x` is known, the failure then names
+/// the goal that required it.
+#[test]
+fn deferred_outlives_failure_names_origin() {
+    let decls = Decls::empty()
+        .with_trait_decls(vec![
+            term("trait Outlives<ty Self, ty T> where {}"),
+            term("trait Same<ty Self, ty T> where {}"),
+        ])
+        .with_impl_decls(vec![
+            term("impl<lt p, lt q> Outlives(&p u32, &q u32) where {p : q}"),
+            term("impl<ty T> Same(T, T) where {}"),
+        ]);
+    let trace = test_prove_failure_trace(
+        decls,
+        term("forall<lt a, lt b> exists<lt x> {} => {Outlives(&x u32, &b u32), Same(&x u32, &a u32)}"),
+    );
+    expect![[r#"
+        failed goals:
+          prove_region_constraint(constraint: !lt_2 : !lt_1 from Outlives((rigid &(shared) ?lt_0 (rigid (scalar u32))), (rigid &(shared) !lt_1 (rigid (scalar u32))))), rules tried: ["region constraint"]
+        deepest failed subgoals:
+          prove_wc(goal: !lt_0 : !lt_1), rules tried: ["assumption", "outlives"]
+    "#]]
+    .assert_eq(&trace.to_string());
+}
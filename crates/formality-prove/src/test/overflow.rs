@@ -0,0 +1,127 @@
+use expect_test::expect;
+use formality_macros::test;
+use formality_types::{
+    grammar::{Wc, Wcs},
+    parse::term,
+};
+
+use crate::{decls::Decls, prove::prove, SolverConfig};
+
+/// A chain of traits, each of which is implemented for `u32` if the next one is.
+fn decls(max_depth: usize) -> Decls {
+    Decls::empty()
+        .with_config(SolverConfig {
+            max_depth,
+            ..SolverConfig::default()
+        })
+        .with_trait_decls(vec![
+            term("trait A<ty Self> where {}"),
+            term("trait B<ty Self> where {}"),
+            term("trait C<ty Self> where {}"),
+            term("trait D<ty Self> where {}"),
+        ])
+        .with_impl_decls(vec![
+            term("impl<> A(u32) where {B(u32)}"),
+            term("impl<> B(u32) where {C(u32)}"),
+            term("impl<> C(u32) where {D(u32)}"),
+            term("impl<> D(u32) where {}"),
+        ])
+}
+
+#[test]
+fn chain_within_max_depth() {
+    let goal: Wc = term("A(u32)");
+    let constraints = prove(decls(SolverConfig::DEFAULT_MAX_DEPTH), (), (), goal);
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {},
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+/// Exceeding the max depth is neither a proof nor a failure.
+#[test]
+fn chain_exceeds_max_depth() {
+    let goal: Wc = term("A(u32)");
+    let constraints = prove(decls(4), (), (), goal);
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [],
+                    coherence_mode: false,
+                },
+                known_true: false,
+                overflow: true,
+                substitution: {},
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+/// Equating a term with itself holds even if the term exceeds the max size.
+#[test]
+fn identical_terms_exceed_max_size() {
+    let decls = Decls::empty().with_config(SolverConfig {
+        max_size: 10,
+        ..SolverConfig::default()
+    });
+    let goal: Wc = term("Vec<Vec<Vec<Vec<Vec<Vec<u32>>>>>> = Vec<Vec<Vec<Vec<Vec<Vec<u32>>>>>>");
+    let constraints = prove(decls, (), (), goal);
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {},
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+/// An overflow deep in one proof does not carry over to another that proves the same
+/// goal at a shallower depth, as the memo tables do not keep outputs that overflowed.
+/// Proving `A(u32)` overflows on `D(u32)`, with the goals above it as assumptions.
+#[test]
+fn overflow_not_memoized() {
+    let goal: Wc = term("A(u32)");
+    let constraints = prove(decls(4), (), (), goal);
+    assert!(constraints.iter().all(|c| c.overflow));
+
+    let assumptions: Wcs = term("{A(u32), B(u32), C(u32)}");
+    let goal: Wc = term("D(u32)");
+    let constraints = prove(decls(4), (), assumptions, goal);
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [],
+                    coherence_mode: false,
+                },
+                known_true: true,
+                overflow: false,
+                substitution: {},
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
@@ -0,0 +1,40 @@
+use formality_macros::test;
+use formality_types::parse::term;
+
+use crate::{test_util::test_prove, Decls, SolverConfig};
+
+fn decls(parallel: bool) -> Decls {
+    Decls::empty()
+        .with_config(SolverConfig {
+            max_depth: 5,
+            parallel,
+            ..SolverConfig::default()
+        })
+        .with_trait_decls(vec![
+            term("trait Into<ty Self, ty T> where {}"),
+            term("trait Debug<ty Self> where {}"),
+        ])
+        .with_impl_decls(vec![
+            term("impl<> Into(u32, u32) where {}"),
+            term("impl<> Into(u32, i32) where {}"),
+            term("impl<ty T> Into(u32, Vec<T>) where {Into(u32, T), Debug(T)}"),
+            term("impl<> Debug(u32) where {}"),
+            term("impl<ty T> Debug(Vec<T>) where {Debug(T)}"),
+        ])
+}
+
+/// Trying the impls in parallel yields the same solutions as trying them in order.
+#[test]
+fn parallel_candidates_match_sequential() {
+    for goal in [
+        "exists<ty T> {} => {Into(u32, T)}",
+        "{} => {Into(u32, Vec<Vec<u32>>)}",
+        "{} => {Into(u32, Vec<i32>)}",
+    ] {
+        assert_eq!(
+            test_prove(decls(true), term(goal)),
+            test_prove(decls(false), term(goal)),
+            "{goal}"
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use formality_macros::test;
+use formality_types::{
+    judgment::cancel::{with_cancellation, CancellationToken},
+    parse::term,
+};
+
+use crate::{
+    decls::Decls,
+    test_util::{test_prove, test_prove_result},
+    ProofResult, SolverConfig, Unknowable,
+};
+
+fn decls() -> Decls {
+    Decls::empty()
+        .with_trait_decls(vec![term("trait Debug<ty Self> where {}")])
+        .with_impl_decls(vec![
+            term("impl<ty T> Debug(Vec<T>) where {Debug(T)}"),
+            term("impl<> Debug(u32) where {}"),
+        ])
+}
+
+fn prove_result(decls: Decls, assertion: &str) -> ProofResult {
+    ProofResult::from_constraints(test_prove(decls, term(assertion)))
+}
+
+#[test]
+fn yes() {
+    assert!(prove_result(decls(), "{} => {Debug(Vec<u32>)}").is_yes());
+}
+
+#[test]
+fn no() {
+    assert!(prove_result(decls(), "{} => {Debug(i32)}").is_no());
+}
+
+/// A downstream crate may implement `Debug` for its own type.
+#[test]
+fn ambiguous_in_coherence_mode() {
+    let ProofResult::Ambiguous(a) = prove_result(
+        Decls::empty(),
+        "coherence_mode exists<ty T> {} => {@IsLocal(Debug(T))}",
+    ) else {
+        panic!("expected an ambiguous result")
+    };
+    assert!(!a.overflow);
+}
+
+#[test]
+fn ambiguous_by_overflow() {
+    let decls = decls().with_config(SolverConfig {
+        max_size: 10,
+        ..SolverConfig::default()
+    });
+    let ProofResult::Ambiguous(a) = prove_result(decls, "exists<ty T> {} => {Debug(T)}") else {
+        panic!("expected an ambiguous result")
+    };
+    assert!(a.overflow);
+}
+
+/// In coherence mode, a downstream crate may implement `Debug` for its own type,
+/// and an upstream crate may add an impl for `i32`.
+#[test]
+fn unknowable_in_coherence_mode() {
+    let ProofResult::Ambiguous(a) = test_prove_result(
+        decls(),
+        term("coherence_mode exists<ty T> {} => {Debug(T)}"),
+    ) else {
+        panic!("expected an ambiguous result")
+    };
+    assert!(matches!(&a.unknowable[..], [Unknowable::Downstream(_)]));
+
+    let ProofResult::Ambiguous(a) =
+        test_prove_result(decls(), term("coherence_mode {} => {Debug(i32)}"))
+    else {
+        panic!("expected an ambiguous result")
+    };
+    assert!(matches!(&a.unknowable[..], [Unknowable::Upstream(_)]));
+}
+
+/// A proof that exceeds its time limit times out, rather than failing.
+#[test]
+fn timed_out() {
+    let token = CancellationToken::with_time_limit(std::time::Duration::ZERO);
+    let mut result = None;
+    let _ = with_cancellation(&token, || {
+        result = Some(test_prove_result(decls(), term("{} => {Debug(Vec<u32>)}")));
+    });
+    assert_eq!(result, Some(ProofResult::TimedOut));
+
+    assert!(test_prove_result(decls(), term("{} => {Debug(Vec<u32>)}")).is_yes());
+}
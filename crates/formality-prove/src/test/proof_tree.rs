@@ -0,0 +1,112 @@
+use expect_test::expect;
+use formality_macros::test;
+use formality_types::{grammar::Wc, parse::term};
+
+use crate::{
+    decls::Decls,
+    proof_tree,
+    prove::{prove_failure_trace, prove_with_proof_trees},
+    ProofTree,
+};
+
+/// Simple example decls consisting only of two trait declarations.
+fn decls() -> Decls {
+    Decls::empty()
+        .with_trait_decls(vec![term("trait Debug<ty Self> where {}")])
+        .with_impl_decls(vec![
+            term("impl<ty T> Debug(Vec<T>) where {Debug(T)}"),
+            term("impl<> Debug(u32) where {}"),
+        ])
+}
+
+#[test]
+fn u32_debug() {
+    let goal: Wc = term("Debug(u32)");
+    let proofs = prove_with_proof_trees(decls(), (), (), goal);
+    assert_eq!(proofs.len(), 1);
+    expect![[r#"
+        prove(goal: {Debug((rigid (scalar u32)))}, assumptions: {}, env: Env { variables: [], coherence_mode: false }) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "prove"
+          prove_wc_list(goal: {Debug((rigid (scalar u32)))}, assumptions: {}, env: Env { variables: [], coherence_mode: false }) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "some"
+            prove_wc(goal: Debug((rigid (scalar u32))), assumptions: {}, env: Env { variables: [], coherence_mode: false }) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "positive impl"
+              prove_impl(trait_ref: Debug((rigid (scalar u32))), i: impl <> Debug((rigid (scalar u32))) where {}, assumptions: {}, env: Env { variables: [], coherence_mode: false }) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "impl"
+                prove(goal: {}, assumptions: {Debug((rigid (scalar u32)))}, env: Env { variables: [], coherence_mode: false }) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "prove"
+                  prove_wc_list(goal: {}, assumptions: {Debug((rigid (scalar u32)))}, env: Env { variables: [], coherence_mode: false }) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "none"
+                prove_after(constraints: Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} }, goal: {}, assumptions: {Debug((rigid (scalar u32)))}) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "prove_after"
+                  prove(goal: {}, assumptions: {Debug((rigid (scalar u32)))}, env: Env { variables: [], coherence_mode: false }) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "prove"
+                    prove_wc_list(goal: {}, assumptions: {Debug((rigid (scalar u32)))}, env: Env { variables: [], coherence_mode: false }) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "none"
+                prove_after(constraints: Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} }, goal: {}, assumptions: {}) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "prove_after"
+                  prove(goal: {}, assumptions: {}, env: Env { variables: [], coherence_mode: false }) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "prove"
+                    prove_wc_list(goal: {}, assumptions: {}, env: Env { variables: [], coherence_mode: false }) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "none"
+            prove_after(constraints: Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} }, goal: {}, assumptions: {}) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "prove_after"
+              prove(goal: {}, assumptions: {}, env: Env { variables: [], coherence_mode: false }) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "prove"
+                prove_wc_list(goal: {}, assumptions: {}, env: Env { variables: [], coherence_mode: false }) => Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} } by "none"
+    "#]].assert_eq(&proofs[0].1.to_string());
+}
+
+/// Only the `prove_wc` nodes, to keep the expectation readable.
+#[test]
+fn vec_u32_debug_rules() {
+    let goal: Wc = term("Debug(Vec<u32>)");
+    let proofs = prove_with_proof_trees(decls(), (), (), goal);
+    assert_eq!(proofs.len(), 1);
+    let mut rules = vec![];
+    collect_rules(&proofs[0].1, "prove_wc", &mut rules);
+    assert!(proof_tree::to_dot(&proofs[0].1).contains(r#"by \"positive impl\""#));
+    expect![[r#"
+        [
+            "Debug((rigid (adt Vec) (rigid (scalar u32)))) by \"positive impl\"",
+            "(rigid (adt Vec) (rigid (scalar u32))) = (rigid (adt Vec) ?ty_0) by \"eq\"",
+            "Debug((rigid (scalar u32))) by \"positive impl\"",
+        ]
+    "#]]
+    .assert_debug_eq(&rules);
+}
+
+fn collect_rules(tree: &ProofTree, judgment: &str, rules: &mut Vec<String>) {
+    if tree.judgment == judgment {
+        rules.push(format!("{} by {:?}", tree.inputs[0].1, tree.rule));
+    }
+    for child in &tree.children {
+        collect_rules(child, judgment, rules);
+    }
+}
+
+#[test]
+fn vec_i32_debug_unproven() {
+    let goal: Wc = term("Debug(Vec<i32>)");
+    let proofs = prove_with_proof_trees(decls(), (), (), goal);
+    assert!(proofs.is_empty());
+}
+
+#[test]
+fn vec_i32_debug_failure_trace() {
+    let goal: Wc = term("Debug(Vec<i32>)");
+    let trace = prove_failure_trace(decls(), (), (), goal);
+    expect![[r#"
+        failed goals:
+          prove_wc(goal: Debug((rigid (adt Vec) (rigid (scalar i32))))), rules tried: ["assumption", "positive impl", "coherence / remote impl", "sized", "dyn", "closure", "closure captures", "phantom data", "coroutine", "coroutine components", "builtin operator", "alias bound", "trait implied bound"]
+        deepest failed subgoals:
+          prove_wc(goal: (rigid (adt Vec) (rigid (scalar i32))) = (rigid (scalar i32))), rules tried: ["assumption", "eq"]
+    "#]].assert_eq(&trace.to_string());
+}
+
+#[test]
+fn vec_i32_debug_failure_trace_graphs() {
+    let goal: Wc = term("Debug(Vec<i32>)");
+    let trace = prove_failure_trace(decls(), (), (), goal);
+    expect![[r#"
+        digraph proof {
+            node [shape=box];
+            n0 [label="prove_wc(goal: Debug((rigid (adt Vec) (rigid (scalar i32)))))\nrules tried: [\"assumption\", \"positive impl\", \"coherence / remote impl\", \"sized\", \"dyn\", \"closure\", \"closure captures\", \"phantom data\", \"coroutine\", \"coroutine components\", \"builtin operator\", \"alias bound\", \"trait implied bound\"]", tooltip="goal: Debug((rigid (adt Vec) (rigid (scalar i32))))\nassumptions: {}\nenv: Env { variables: [], coherence_mode: false }", color=red];
+            n1 [label="prove_wc(goal: (rigid (adt Vec) (rigid (scalar i32))) = (rigid (scalar i32)))\nrules tried: [\"assumption\", \"eq\"]", tooltip="goal: (rigid (adt Vec) (rigid (scalar i32))) = (rigid (scalar i32))\nassumptions: {Debug((rigid (adt Vec) (rigid (scalar i32))))}\nenv: Env { variables: [], coherence_mode: false }", color=red];
+            n0 -> n1 [style=dashed];
+        }
+    "#]].assert_eq(&proof_tree::to_dot(&trace));
+    expect![[r#"
+        graph TD
+            n0["prove_wc(goal: Debug((rigid (adt Vec) (rigid (scalar i32)))))<br/>rules tried: [#quot;assumption#quot;, #quot;positive impl#quot;, #quot;coherence / remote impl#quot;, #quot;sized#quot;, #quot;dyn#quot;, #quot;closure#quot;, #quot;closure captures#quot;, #quot;phantom data#quot;, #quot;coroutine#quot;, #quot;coroutine components#quot;, #quot;builtin operator#quot;, #quot;alias bound#quot;, #quot;trait implied bound#quot;]"]:::failed
+            n1["prove_wc(goal: (rigid (adt Vec) (rigid (scalar i32))) = (rigid (scalar i32)))<br/>rules tried: [#quot;assumption#quot;, #quot;eq#quot;]"]:::failed
+            n0 -.-> n1
+            classDef failed stroke:red
+    "#]].assert_eq(&proof_tree::to_mermaid(&trace));
+}
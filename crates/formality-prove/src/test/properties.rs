@@ -0,0 +1,65 @@
+//! Properties of the prover checked against generated terms (see `formality_types::generate`).
+
+use std::sync::Arc;
+
+use formality_macros::test;
+use formality_types::{
+    cast::Upcast,
+    generate::check_for_all,
+    grammar::{
+        AliasName, AliasTy, Binder, Parameter, PredicateTy, Relation, RigidTy, Ty, TyData, Wcs,
+    },
+};
+
+use crate::{
+    decls::Decls,
+    test_util::{test_prove, TestAssertion},
+};
+
+/// The number of terms to check each property against.
+const COUNT: u64 = 200;
+
+/// Proving `a = b` succeeds just when proving `b = a` does, where the variables bound
+/// in `a` and `b` are existential.
+#[test]
+fn eq_is_symmetric() {
+    check_for_all(COUNT, |binder: &Binder<(Ty, Ty)>| {
+        let (a, b) = binder.peek();
+        if mentions_type_alias(a) || mentions_type_alias(b) {
+            return Ok(());
+        }
+        let provable = |swap: bool| {
+            let assertion = binder.map(|(a, b)| {
+                let goal = if swap {
+                    Relation::eq(b, a)
+                } else {
+                    Relation::eq(a, b)
+                };
+                Arc::new(TestAssertion::Prove(Wcs::t(), goal.upcast()))
+            });
+            !test_prove(Decls::empty(), Arc::new(TestAssertion::Exists(assertion))).is_empty()
+        };
+        match (provable(false), provable(true)) {
+            (a_b, b_a) if a_b == b_a => Ok(()),
+            (a_b, b_a) => Err(format!("`a = b` provable: {a_b}, `b = a` provable: {b_a}")),
+        }
+    });
+}
+
+/// Whether `ty` mentions a type alias, which the prover expects to be declared.
+fn mentions_type_alias(ty: &Ty) -> bool {
+    let in_parameters = |parameters: &[Parameter]| {
+        parameters.iter().any(|p| match p {
+            Parameter::Ty(ty) => mentions_type_alias(ty),
+            Parameter::Lt(_) | Parameter::Const(_) => false,
+        })
+    };
+    match ty.data() {
+        TyData::RigidTy(RigidTy { parameters, .. }) => in_parameters(parameters),
+        TyData::AliasTy(AliasTy { name, parameters }) => {
+            matches!(name, AliasName::TypeAliasId(_)) || in_parameters(parameters)
+        }
+        TyData::PredicateTy(PredicateTy::ForAll(binder)) => mentions_type_alias(binder.peek()),
+        TyData::Variable(_) => false,
+    }
+}
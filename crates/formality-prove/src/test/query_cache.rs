@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use formality_macros::test;
+use formality_types::parse::term;
+
+use crate::{decls::Decls, test_util::test_prove, with_query_cache, QueryCache};
+
+fn decls() -> Decls {
+    Decls::empty()
+        .with_trait_decls(vec![term("trait Debug<ty Self> where {}")])
+        .with_impl_decls(vec![
+            term("impl<ty T> Debug(Vec<T>) where {Debug(T)}"),
+            term("impl<> Debug(u32) where {}"),
+        ])
+}
+
+/// A query cache in a file of its own, which is removed when dropped.
+struct TempCache(std::path::PathBuf);
+
+impl TempCache {
+    fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("query-cache-{name}-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        TempCache(path)
+    }
+
+    fn open(&self) -> Arc<QueryCache> {
+        Arc::new(QueryCache::open(&self.0).unwrap())
+    }
+}
+
+impl Drop for TempCache {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// The responses are taken from the cache once the goal has been proven, also after
+/// the cache is saved and opened again, and are the same as without the cache.
+#[test]
+fn responses_reused() {
+    let file = TempCache::new("reused");
+    let goal = "exists<ty T> {} => {T = Vec<u32>, Debug(T)}";
+    let expected = test_prove(decls(), term(goal));
+
+    let cache = file.open();
+    assert!(cache.is_empty());
+    let prove = |cache: &Arc<QueryCache>| {
+        with_query_cache(Some(cache.clone()), || test_prove(decls(), term(goal)))
+    };
+    assert_eq!(prove(&cache), expected);
+    assert_eq!((cache.len(), cache.hits()), (1, 0));
+    assert_eq!(prove(&cache), expected);
+    assert_eq!((cache.len(), cache.hits()), (1, 1));
+    cache.save().unwrap();
+
+    let cache = file.open();
+    assert_eq!(cache.len(), 1);
+    assert_eq!(prove(&cache), expected);
+    assert_eq!((cache.len(), cache.hits()), (1, 1));
+}
+
+/// The same goal with other decls is proven anew, and goals that overflow are not cached.
+#[test]
+fn responses_keyed_by_decls() {
+    let file = TempCache::new("keyed");
+    let cache = file.open();
+    let goal = "{} => {Debug(u32)}";
+    let with_cache = |decls: Decls, goal: &str| {
+        with_query_cache(Some(cache.clone()), || test_prove(decls, term(goal)))
+    };
+
+    assert_eq!(with_cache(decls(), goal).len(), 1);
+    assert_eq!(with_cache(Decls::empty(), goal).len(), 0);
+    assert_eq!((cache.len(), cache.hits()), (2, 0));
+
+    // The hash of the decls is shared by their clones, but their config is not part of it.
+    let hashed = decls();
+    assert_eq!(with_cache(hashed.clone(), goal).len(), 1);
+    let mut reconfigured = hashed.clone();
+    reconfigured.config.erase_regions = true;
+    assert_eq!(with_cache(reconfigured, goal).len(), 1);
+    assert_eq!((cache.len(), cache.hits()), (3, 1));
+
+    let overflowing =
+        decls().with_impl_decls(vec![term("impl<ty T> Debug(T) where {Debug(Vec<T>)}")]);
+    let result = with_cache(overflowing, "{} => {Debug(bool)}");
+    assert!(result.iter().all(|c| c.overflow) && !result.is_empty());
+    assert_eq!((cache.len(), cache.hits()), (3, 1));
+}
+
+/// A cache saved by another version of the prover, or by one built from other
+/// sources, or in an older format, is discarded,
+/// and saving leaves no other file behind.
+#[test]
+fn other_versions_discarded() {
+    let file = TempCache::new("versions");
+    let cache = file.open();
+    with_query_cache(Some(cache.clone()), || {
+        test_prove(decls(), term("{} => {Debug(u32)}"))
+    });
+    cache.save().unwrap();
+    assert_eq!(file.open().len(), 1);
+    let siblings = std::fs::read_dir(std::env::temp_dir())
+        .unwrap()
+        .filter(|e| {
+            let name = e.as_ref().unwrap().file_name();
+            let file_name = file.0.file_name().unwrap();
+            name != file_name
+                && name
+                    .to_string_lossy()
+                    .starts_with(&*file_name.to_string_lossy())
+        })
+        .count();
+    assert_eq!(siblings, 0);
+
+    let text = std::fs::read_to_string(&file.0).unwrap();
+    let mut saved: serde_json::Value = serde_json::from_str(&text).unwrap();
+    let mut other_sources = saved.clone();
+    other_sources["stamp"]["sources"] = "0000000000000000".into();
+    std::fs::write(&file.0, other_sources.to_string()).unwrap();
+    assert!(file.open().is_empty());
+
+    saved["stamp"]["version"] = "0.0.0-other".into();
+    std::fs::write(&file.0, saved.to_string()).unwrap();
+    assert!(file.open().is_empty());
+
+    std::fs::write(&file.0, saved["entries"].to_string()).unwrap();
+    assert!(file.open().is_empty());
+}
@@ -0,0 +1,245 @@
+use expect_test::expect;
+use formality_macros::test;
+use formality_types::parse::term;
+
+use crate::{
+    test_util::{test_prove, test_prove_with_search_strategy},
+    BuiltinSearchStrategy, Decls, SearchStrategy, SolverConfig,
+};
+
+/// `u32` converts into `u32` and, one impl deeper each time, into `Vec<u32>`, `Vec<Vec<u32>>`, ...
+fn decls(search_strategy: BuiltinSearchStrategy) -> Decls {
+    Decls::empty()
+        .with_config(SolverConfig {
+            max_depth: 5,
+            search_strategy,
+            ..SolverConfig::default()
+        })
+        .with_trait_decls(vec![term("trait Into<ty Self, ty T> where {}")])
+        .with_impl_decls(vec![
+            term("impl<> Into(u32, u32) where {}"),
+            term("impl<ty T> Into(u32, Vec<T>) where {Into(u32, T)}"),
+        ])
+}
+
+/// Finds the conversions within the max depth, and overflows on the deeper ones.
+#[test]
+fn depth_first() {
+    let constraints = test_prove(
+        decls(BuiltinSearchStrategy::DepthFirst),
+        term("exists<ty T> {} => {Into(u32, T)}"),
+    );
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [
+                        ?ty_0,
+                    ],
+                    coherence_mode: false,
+                },
+                known_true: false,
+                overflow: false,
+                substitution: {
+                    ?ty_0 => (rigid (adt Vec) (rigid (adt Vec) (rigid (scalar u32)))),
+                },
+                region_constraints: {},
+            },
+            Constraints {
+                env: Env {
+                    variables: [
+                        ?ty_0,
+                    ],
+                    coherence_mode: false,
+                },
+                known_true: false,
+                overflow: false,
+                substitution: {
+                    ?ty_0 => (rigid (adt Vec) (rigid (scalar u32))),
+                },
+                region_constraints: {},
+            },
+            Constraints {
+                env: Env {
+                    variables: [
+                        ?ty_0,
+                    ],
+                    coherence_mode: false,
+                },
+                known_true: false,
+                overflow: false,
+                substitution: {
+                    ?ty_0 => (rigid (scalar u32)),
+                },
+                region_constraints: {},
+            },
+            Constraints {
+                env: Env {
+                    variables: [
+                        ?ty_0,
+                    ],
+                    coherence_mode: false,
+                },
+                known_true: false,
+                overflow: true,
+                substitution: {
+                    ?ty_0 => (rigid (adt Vec) (rigid (adt Vec) (rigid (adt Vec) (rigid (scalar u32))))),
+                },
+                region_constraints: {},
+            },
+            Constraints {
+                env: Env {
+                    variables: [
+                        ?ty_0,
+                        ?ty_1,
+                    ],
+                    coherence_mode: false,
+                },
+                known_true: false,
+                overflow: true,
+                substitution: {
+                    ?ty_1 => (rigid (adt Vec) (rigid (adt Vec) (rigid (adt Vec) (rigid (adt Vec) ?ty_0)))),
+                },
+                region_constraints: {},
+            },
+        }
+    "#]].assert_debug_eq(&constraints);
+}
+
+/// Commits to the shallowest conversion, found within depth 3.
+#[test]
+fn iterative_deepening() {
+    let constraints = test_prove(
+        decls(BuiltinSearchStrategy::IterativeDeepening { step: 1 }),
+        term("exists<ty T> {} => {Into(u32, T)}"),
+    );
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [
+                        ?ty_0,
+                    ],
+                    coherence_mode: false,
+                },
+                known_true: false,
+                overflow: false,
+                substitution: {
+                    ?ty_0 => (rigid (scalar u32)),
+                },
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+/// Commits to the conversions found within depth 4, having found none within depth 2.
+#[test]
+fn doubling() {
+    let constraints = test_prove(
+        decls(BuiltinSearchStrategy::Doubling),
+        term("exists<ty T> {} => {Into(u32, T)}"),
+    );
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [
+                        ?ty_0,
+                    ],
+                    coherence_mode: false,
+                },
+                known_true: false,
+                overflow: false,
+                substitution: {
+                    ?ty_0 => (rigid (adt Vec) (rigid (scalar u32))),
+                },
+                region_constraints: {},
+            },
+            Constraints {
+                env: Env {
+                    variables: [
+                        ?ty_0,
+                    ],
+                    coherence_mode: false,
+                },
+                known_true: false,
+                overflow: false,
+                substitution: {
+                    ?ty_0 => (rigid (scalar u32)),
+                },
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+/// Searches within depth 4 only, then within the max depth.
+struct Depth4;
+
+impl SearchStrategy for Depth4 {
+    fn depth_bounds(&self, max_depth: usize) -> Vec<usize> {
+        vec![4, max_depth]
+    }
+}
+
+/// A strategy that is not built in commits to the conversions found within depth 4, as
+/// [`doubling`] does.
+#[test]
+fn custom() {
+    let constraints = test_prove_with_search_strategy(
+        decls(BuiltinSearchStrategy::DepthFirst),
+        term("exists<ty T> {} => {Into(u32, T)}"),
+        &Depth4,
+    );
+    expect![[r#"
+        {
+            Constraints {
+                env: Env {
+                    variables: [
+                        ?ty_0,
+                    ],
+                    coherence_mode: false,
+                },
+                known_true: false,
+                overflow: false,
+                substitution: {
+                    ?ty_0 => (rigid (adt Vec) (rigid (scalar u32))),
+                },
+                region_constraints: {},
+            },
+            Constraints {
+                env: Env {
+                    variables: [
+                        ?ty_0,
+                    ],
+                    coherence_mode: false,
+                },
+                known_true: false,
+                overflow: false,
+                substitution: {
+                    ?ty_0 => (rigid (scalar u32)),
+                },
+                region_constraints: {},
+            },
+        }
+    "#]]
+    .assert_debug_eq(&constraints);
+}
+
+#[test]
+fn depth_bounds() {
+    let bounds = |strategy: BuiltinSearchStrategy| strategy.depth_bounds(9);
+    assert_eq!(bounds(BuiltinSearchStrategy::DepthFirst), vec![9]);
+    assert_eq!(
+        bounds(BuiltinSearchStrategy::IterativeDeepening { step: 1 }),
+        vec![1, 2, 3, 4, 5, 6, 7, 8, 9]
+    );
+    assert_eq!(
+        bounds(BuiltinSearchStrategy::IterativeDeepening { step: 3 }),
+        vec![3, 6, 9]
+    );
+    assert_eq!(bounds(BuiltinSearchStrategy::Doubling), vec![1, 2, 4, 8, 9]);
+}
@@ -6,14 +6,12 @@ use crate::{decls::Decls, prove::prove};
 
 /// Simple example decls consisting only of two trait declarations.
 fn decls() -> Decls {
-    Decls {
-        trait_decls: vec![term("trait Debug<ty Self> where {}")],
-        impl_decls: vec![
+    Decls::empty()
+        .with_trait_decls(vec![term("trait Debug<ty Self> where {}")])
+        .with_impl_decls(vec![
             term("impl<ty T> Debug(Vec<T>) where {Debug(T)}"),
             term("impl<> Debug(u32) where {}"),
-        ],
-        ..Decls::empty()
-    }
+        ])
 }
 
 #[test]
@@ -28,7 +26,9 @@ fn vec_u32_debug() {
                     coherence_mode: false,
                 },
                 known_true: true,
+                overflow: false,
                 substitution: {},
+                region_constraints: {},
             },
         }
     "#]]
@@ -47,7 +47,9 @@ fn vec_vec_u32_debug() {
                     coherence_mode: false,
                 },
                 known_true: true,
+                overflow: false,
                 substitution: {},
+                region_constraints: {},
             },
         }
     "#]]
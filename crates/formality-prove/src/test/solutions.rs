@@ -0,0 +1,45 @@
+use formality_macros::test;
+use formality_types::{
+    collections::Set,
+    grammar::{Binder, ParameterKind, Wcs},
+    parse::term,
+};
+
+use crate::{decls::Decls, prove, prove_lazily, Env};
+
+fn decls() -> Decls {
+    Decls::empty()
+        .with_trait_decls(vec![
+            term("trait Debug<ty Self> where {}"),
+            term("trait Display<ty Self> where {}"),
+        ])
+        .with_impl_decls(vec![
+            term("impl<ty T> Debug(Vec<T>) where {Debug(T)}"),
+            term("impl<> Debug(u32) where {}"),
+            term("impl<> Debug(i32) where {}"),
+            term("impl<> Display(u32) where {}"),
+        ])
+}
+
+/// Instantiates `goal` with a fresh existential variable.
+fn env_and_goal(goal: &str) -> (Env, Wcs) {
+    let mut env = Env::default();
+    let t = env.fresh_existential(ParameterKind::Ty);
+    let goal: Binder<Wcs> = term(goal);
+    let goal = goal.instantiate_with(&[t]).unwrap();
+    (env, goal)
+}
+
+/// Collecting the lazily enumerated solutions gives the same result as `prove`.
+#[test]
+fn lazy_solutions_match_prove() {
+    let (env, goal) = env_and_goal("<ty T> {Debug(T), Display(T)}");
+    let lazy: Set<_> = prove_lazily(decls(), &env, (), &goal).collect();
+    assert_eq!(lazy, prove(decls(), &env, (), &goal));
+}
+
+#[test]
+fn no_solutions() {
+    let (env, goal) = env_and_goal("<ty T> {Display(i32), Debug(T)}");
+    assert!(prove_lazily(decls(), env, (), &goal).next().is_none());
+}
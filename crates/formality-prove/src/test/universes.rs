@@ -1,8 +1,12 @@
 use expect_test::expect;
 use formality_macros::test;
-use formality_types::parse::term;
+use formality_types::{
+    cast::To,
+    grammar::{AdtId, Parameter, ParameterKind, Ty},
+    parse::term,
+};
 
-use crate::decls::Decls;
+use crate::{decls::Decls, Constraints, Env};
 
 use crate::test_util::test_prove;
 
@@ -20,11 +24,9 @@ fn exists_u_for_t() {
 /// There is U that is equal to some T.
 #[test]
 fn for_t_exists_u() {
-    let decls = Decls {
-        trait_decls: vec![term("trait Test<ty Self, ty T> where {}")],
-        impl_decls: vec![term("impl<ty X, ty Y> Test(X, Y) where {X = Y}")],
-        ..Decls::empty()
-    };
+    let decls = Decls::empty()
+        .with_trait_decls(vec![term("trait Test<ty Self, ty T> where {}")])
+        .with_impl_decls(vec![term("impl<ty X, ty Y> Test(X, Y) where {X = Y}")]);
 
     let constraints = test_prove(decls, term("{} => {for<ty T> Test(T, T)}"));
     expect![[r#"
@@ -35,9 +37,50 @@ fn for_t_exists_u() {
                     coherence_mode: false,
                 },
                 known_true: true,
+                overflow: false,
                 substitution: {},
+                region_constraints: {},
             },
         }
     "#]]
     .assert_debug_eq(&constraints);
 }
+
+/// Compressing drops the fresh variables that the values of the goal's variables do not
+/// refer to, and renumbers the others after their universes.
+#[test]
+fn compress() {
+    let mut env0 = Env::default();
+    let t = env0.fresh_universal(ParameterKind::Ty);
+    let x = env0.fresh_existential(ParameterKind::Ty);
+
+    let mut env = env0.clone();
+    let y = env.insert_fresh_before(ParameterKind::Ty, env.universe(x));
+    let z = env.fresh_existential(ParameterKind::Ty);
+    let constraints = Constraints::from(
+        env,
+        vec![
+            (x, Ty::rigid(AdtId::new("Vec"), vec![y.to::<Parameter>()])),
+            (z, t.to::<Ty>()),
+        ],
+    );
+    expect![[r#"
+        Constraints {
+            env: Env {
+                variables: [
+                    !ty_0,
+                    ?ty_1,
+                    ?ty_2,
+                ],
+                coherence_mode: false,
+            },
+            known_true: true,
+            overflow: false,
+            substitution: {
+                ?ty_2 => (rigid (adt Vec) ?ty_1),
+            },
+            region_constraints: {},
+        }
+    "#]]
+    .assert_debug_eq(&constraints.compress(&env0));
+}
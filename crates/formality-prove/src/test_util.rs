@@ -7,8 +7,12 @@ use formality_types::{
 };
 
 use crate::{
-    decls::Decls,
-    prove::{prove, Constraints, Env},
+    decls::{Decls, SearchStrategy},
+    prove::{
+        prove, prove_failure_trace, prove_result, prove_with_proof_trees,
+        prove_with_search_strategy, Constraints, Env, ProofResult,
+    },
+    FailureTrace, ProofTree,
 };
 
 /// Useful assertions for use in tests.
@@ -27,8 +31,62 @@ pub enum TestAssertion {
 /// `t` represents some set of existential bindings combined with (assumptions, goals).
 /// Returns the constraints that result from proving assumptions/goals. These will reference
 /// existential variables created for the bindings, so they're really just suitable for
-/// using with expect.
-pub fn test_prove(decls: Decls, mut assertion: Arc<TestAssertion>) -> Set<Constraints> {
+/// using with expect. They are [compressed](`Constraints::compress`), so that their
+/// variables are numbered after their universes.
+pub fn test_prove(decls: Decls, assertion: Arc<TestAssertion>) -> Set<Constraints> {
+    test_prove_with(decls, assertion, |decls, env, assumptions, goals| {
+        prove(decls, &env, assumptions, goals)
+            .iter()
+            .map(|c| c.compress(&env))
+            .collect()
+    })
+}
+
+/// Like [`test_prove`], but searches with `search_strategy`, see [`prove_with_search_strategy`].
+pub fn test_prove_with_search_strategy(
+    decls: Decls,
+    assertion: Arc<TestAssertion>,
+    search_strategy: &dyn SearchStrategy,
+) -> Set<Constraints> {
+    test_prove_with(decls, assertion, |decls, env, assumptions, goals| {
+        prove_with_search_strategy(decls, &env, assumptions, goals, search_strategy)
+            .iter()
+            .map(|c| c.compress(&env))
+            .collect()
+    })
+}
+
+/// Like [`test_prove`], but returns the [`ProofResult`] of [`prove_result`].
+pub fn test_prove_result(decls: Decls, assertion: Arc<TestAssertion>) -> ProofResult {
+    test_prove_with(decls, assertion, |decls, env, assumptions, goals| {
+        prove_result(decls, env, assumptions, goals)
+    })
+}
+
+/// Like [`test_prove`], but each set of constraints comes with its proof tree,
+/// see [`prove_with_proof_trees`].
+pub fn test_prove_with_proof_trees(
+    decls: Decls,
+    assertion: Arc<TestAssertion>,
+) -> Vec<(Constraints, ProofTree)> {
+    test_prove_with(decls, assertion, |decls, env, assumptions, goals| {
+        prove_with_proof_trees(decls, env, assumptions, goals)
+    })
+}
+
+/// Like [`test_prove`], but explains why the goals could not be proven,
+/// see [`prove_failure_trace`].
+pub fn test_prove_failure_trace(decls: Decls, assertion: Arc<TestAssertion>) -> FailureTrace {
+    test_prove_with(decls, assertion, |decls, env, assumptions, goals| {
+        prove_failure_trace(decls, env, assumptions, goals)
+    })
+}
+
+fn test_prove_with<R>(
+    decls: Decls,
+    mut assertion: Arc<TestAssertion>,
+    op: impl FnOnce(Decls, Env, &Wcs, &Wcs) -> R,
+) -> R {
     let mut env = Env::default();
 
     loop {
@@ -48,7 +106,7 @@ pub fn test_prove(decls: Decls, mut assertion: Arc<TestAssertion>) -> Set<Constr
             }
 
             TestAssertion::Prove(assumptions, goals) => {
-                return prove(decls, env, assumptions, goals);
+                return op(decls, env, assumptions, goals);
             }
 
             TestAssertion::CoherenceMode(assertion1) => {
@@ -0,0 +1,93 @@
+//! Feature-gated items.
+//!
+//! An item `#[cfg(feature = f)] item` is only in its crate if the feature `f` of the
+//! crate is enabled. [`Program::configure`] keeps the gated items of the enabled
+//! features and drops the others; the passes after it, starting with name resolution,
+//! take the gated items that are left in a program as disabled.
+//!
+//! As in Cargo, features should be *additive*: enabling a feature should not break a
+//! program that works without it. Checking the program with every combination of its
+//! features (see [`Program::feature_combinations`]) finds the features that are not,
+//! e.g., two features whose impls overlap.
+
+use formality_types::{cast::Upcast, collections::Set};
+
+use crate::grammar::{Crate, CrateItem, Feature, Module, Program};
+
+impl Program {
+    /// The program with the features `enabled`: the items gated on those are in their
+    /// crate, in place of the `#[cfg]`, and the items gated on other features are not.
+    pub fn configure(&self, enabled: &Set<Feature>) -> Program {
+        let crates = self
+            .crates
+            .iter()
+            .map(|c| Crate {
+                items: configure_items(&c.items, &|item: &CrateItem| match item {
+                    CrateItem::Cfg(cfg) => enabled.contains(&Feature {
+                        crate_id: c.id.clone(),
+                        feature_id: cfg.feature.clone(),
+                    }),
+                    _ => true,
+                }),
+                ..c.clone()
+            })
+            .collect();
+        Program { crates }
+    }
+
+    /// The features that the items of the program are gated on.
+    pub fn features(&self) -> Set<Feature> {
+        let mut features = Set::new();
+        for c in &self.crates {
+            let mut items: Vec<&CrateItem> = c.items.iter().collect();
+            while let Some(item) = items.pop() {
+                match item {
+                    CrateItem::Cfg(cfg) => {
+                        features.insert(Feature {
+                            crate_id: c.id.clone(),
+                            feature_id: cfg.feature.clone(),
+                        });
+                        items.push(&cfg.item);
+                    }
+                    CrateItem::Module(m) => items.extend(&m.items),
+                    _ => {}
+                }
+            }
+        }
+        features
+    }
+
+    /// Every combination of the features of the program, starting with none of them
+    /// and ending with all of them.
+    pub fn feature_combinations(&self) -> Vec<Set<Feature>> {
+        let features: Vec<Feature> = self.features().into_iter().collect();
+        (0..1_usize << features.len())
+            .map(|bits| {
+                features
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| bits & (1 << i) != 0)
+                    .map(|(_, f)| f.clone())
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// The items with those gated on a disabled feature dropped, as `enabled` says of each
+/// `#[cfg]`, and the others in place of their `#[cfg]`.
+fn configure_items(items: &[CrateItem], enabled: &impl Fn(&CrateItem) -> bool) -> Vec<CrateItem> {
+    items
+        .iter()
+        .filter(|item| enabled(item))
+        .flat_map(|item| match item {
+            CrateItem::Cfg(cfg) => configure_items(std::slice::from_ref(&*cfg.item), enabled),
+            CrateItem::Module(m) => vec![Module {
+                items: configure_items(&m.items, enabled),
+                ..m.clone()
+            }
+            .upcast()],
+            _ => vec![item.clone()],
+        })
+        .collect()
+}
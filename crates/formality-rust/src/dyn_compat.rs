@@ -0,0 +1,159 @@
+//! Dyn compatibility (formerly "object safety").
+//!
+//! A `dyn Trait` type is only well formed if `Trait` is dyn compatible, i.e., if each of
+//! its methods can be called through a vtable. As in rustc, methods with a
+//! `where Self: Sized` clause are exempt: they cannot be called on `dyn Trait`, whose
+//! size is not known, so they need no entry in its vtable. A trait that requires
+//! `Self: Sized` itself is not dyn compatible.
+//!
+//! Of the valid receivers (see `check_receiver` in formality-check), only those that the
+//! vtable can be found through are dispatchable: `Self`, `&Self`, `&mut Self`, and the
+//! ADTs that implement `DispatchFromDyn` wrapping a dispatchable receiver, like `Rc<Self>`
+//! or `Pin<&Self>`. Unlike rustc, we do not check the `DispatchFromDyn` impls themselves.
+//!
+//! FIXME: rustc also requires the supertraits to be dyn compatible.
+
+use formality_types::{
+    cast::{Downcast, Upcast},
+    collections::Set,
+    grammar::{
+        lang_items::{dispatch_from_dyn_trait, sized_trait},
+        AdtId, ParameterKind, RigidName, RigidTy, TraitId, Ty, Variable,
+    },
+    visit::Visit,
+};
+
+use crate::grammar::{
+    self_param::SelfParam, AssociatedTy, CrateItem, Fn, FnBoundData, Program, Trait,
+    TraitBoundData, TraitImpl, TraitItem, WhereClause, WhereClauseData,
+};
+
+impl Program {
+    /// The traits of the program whose `dyn Trait` types are well formed.
+    pub(crate) fn dyn_compatible_trait_ids(&self) -> Set<TraitId> {
+        let dispatch_adt_ids = self.dispatch_from_dyn_adt_ids();
+        self.items_from_all_crates()
+            .filter_map(|item| match item {
+                CrateItem::Trait(t) if t.is_dyn_compatible(&dispatch_adt_ids) => Some(t.id.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The ADTs that implement `DispatchFromDyn`.
+    fn dispatch_from_dyn_adt_ids(&self) -> Set<AdtId> {
+        self.items_from_all_crates()
+            .filter_map(|item| match item {
+                CrateItem::TraitImpl(TraitImpl { safety: _, binder }) => {
+                    let data = binder.peek();
+                    if data.trait_id != dispatch_from_dyn_trait() {
+                        return None;
+                    }
+                    match data.self_ty.downcast() {
+                        Some(RigidTy {
+                            name: RigidName::AdtId(id),
+                            parameters: _,
+                        }) => Some(id),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Trait {
+    fn is_dyn_compatible(&self, dispatch_adt_ids: &Set<AdtId>) -> bool {
+        let (
+            vars,
+            TraitBoundData {
+                where_clauses,
+                trait_items,
+            },
+        ) = self.binder.explicit_binder.open();
+
+        // The `Self` type is bound first, see `TraitBinder`.
+        let self_var: Variable = vars[0].upcast();
+
+        !requires_sized(&where_clauses, &self_var)
+            && trait_items.iter().all(|item| match item {
+                TraitItem::Fn(f) => is_dispatchable(f, &self_var, dispatch_adt_ids),
+                TraitItem::AssociatedTy(AssociatedTy { id: _, binder }) => binder.is_empty(),
+            })
+    }
+}
+
+/// Whether `f` can be called on `dyn Trait` through its vtable, or cannot be called
+/// on it at all.
+fn is_dispatchable(f: &Fn, self_var: &Variable, dispatch_adt_ids: &Set<AdtId>) -> bool {
+    let (
+        vars,
+        FnBoundData {
+            defaults: _,
+            self_param,
+            input_tys,
+            output_ty,
+            where_clauses,
+            body: _,
+        },
+    ) = f.binder.open();
+
+    if requires_sized(&where_clauses, self_var) {
+        return true;
+    }
+
+    // Without a receiver, there is no vtable to call the method through.
+    let (SelfParam::HasSelf, Some(receiver_ty)) = (self_param, input_tys.first()) else {
+        return false;
+    };
+    let self_ty: Ty = self_var.upcast();
+    if !is_dispatchable_receiver(receiver_ty, &self_ty, dispatch_adt_ids) {
+        return false;
+    }
+
+    // The vtable has one entry per method, so methods cannot be generic over types.
+    if vars.iter().any(|v| v.kind != ParameterKind::Lt) {
+        return false;
+    }
+
+    // The erased self type may only be mentioned by the receiver.
+    input_tys
+        .iter()
+        .skip(1)
+        .chain(Some(&output_ty))
+        .all(|ty| !ty.free_variables().contains(self_var))
+}
+
+/// Whether the vtable of `dyn Trait` can be found through `receiver_ty` when it is
+/// called with `self_ty` erased to `dyn Trait`.
+fn is_dispatchable_receiver(receiver_ty: &Ty, self_ty: &Ty, dispatch_adt_ids: &Set<AdtId>) -> bool {
+    if receiver_ty == self_ty {
+        return true;
+    }
+    match receiver_ty.downcast() {
+        Some(RigidTy {
+            name: RigidName::Ref(_),
+            parameters,
+        }) => parameters.last() == Some(&self_ty.upcast()),
+        Some(RigidTy {
+            name: RigidName::AdtId(id),
+            parameters,
+        }) if dispatch_adt_ids.contains(&id) => parameters.iter().any(|p| {
+            p.downcast::<Ty>()
+                .is_some_and(|ty| is_dispatchable_receiver(&ty, self_ty, dispatch_adt_ids))
+        }),
+        _ => false,
+    }
+}
+
+/// Whether `where_clauses` include `Self: Sized`.
+fn requires_sized(where_clauses: &[WhereClause], self_var: &Variable) -> bool {
+    let self_ty: Ty = self_var.upcast();
+    where_clauses.iter().any(|wc| match wc.data() {
+        WhereClauseData::IsImplemented(ty, trait_id, _) => {
+            *ty == self_ty && *trait_id == sized_trait()
+        }
+        _ => false,
+    })
+}
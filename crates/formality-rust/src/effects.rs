@@ -0,0 +1,450 @@
+//! Desugaring of const traits into an *effect* parameter.
+//!
+//! Each `const trait` gets an extra `const` parameter of type `bool` after its other
+//! parameters: its host effect, which is `true` when the trait is used at runtime and
+//! `false` when it is used in a const context. So:
+//!
+//! * A bound `T: Trait<>` becomes `T: Trait<true>`, and `T: const Trait<>` becomes `T: Trait<false>`.
+//! * `impl Trait<> for X` becomes `impl Trait<true> for X`, which can only be used at runtime,
+//!   whereas `impl const Trait<> for X` becomes `impl<const host> Trait<host> for X`.
+//! * `T: ~const Trait<>` in a const trait or an `impl const` becomes `T: Trait<host>`,
+//!   where `host` is the effect of the enclosing trait or impl.
+//!
+//! The prover thus distinguishes const from runtime-only impls by their effect parameter,
+//! without knowing about const traits at all.
+
+use std::sync::Arc;
+
+use anyhow::bail;
+use formality_types::{
+    cast::Upcast,
+    collections::Set,
+    grammar::{
+        fresh_bound_var, Binder, Bool, BoundVar, Const, Fallible, Parameter, ParameterKind,
+        TraitId, Ty, Variable,
+    },
+};
+
+use crate::grammar::{
+    constness::{BoundConstness, Constness},
+    AdtBoundData, AssociatedTy, AssociatedTyBoundData, AssociatedTyValue,
+    AssociatedTyValueBoundData, CfgItem, Crate, CrateItem, Enum, ExternBlock, Fn, FnBoundData,
+    ImplItem, Module, NegTraitImpl, NegTraitImplBoundData, Program, Struct, StructBoundData, Trait,
+    TraitBinder, TraitBoundData, TraitImpl, TraitImplBoundData, TraitItem, TypeAlias,
+    TypeAliasBoundData, WhereBound, WhereBoundData, WhereClause, WhereClauseData,
+};
+
+impl Program {
+    /// Gives each const trait its effect parameter, returning a program without
+    /// `const` and `~const` bounds.
+    pub fn desugar_effects(&self) -> Fallible<Program> {
+        let desugarer = Desugarer {
+            const_traits: self
+                .items_from_all_crates()
+                .filter_map(|item| match item {
+                    CrateItem::Trait(t) if t.constness == Constness::Const => Some(t.id.clone()),
+                    _ => None,
+                })
+                .collect(),
+        };
+        let crates = self
+            .crates
+            .iter()
+            .map(|c| {
+                Ok(Crate {
+                    id: c.id.clone(),
+                    edition: c.edition,
+                    dependencies: c.dependencies.clone(),
+                    items: desugarer.crate_items(&c.items)?,
+                })
+            })
+            .collect::<Fallible<_>>()?;
+        Ok(Program { crates })
+    }
+}
+
+struct Desugarer {
+    const_traits: Set<TraitId>,
+}
+
+/// A fresh `const` variable for an effect, along with the where-clause giving it type `bool`.
+fn fresh_effect_var() -> (BoundVar, WhereClause) {
+    let var = fresh_bound_var(ParameterKind::Const);
+    let v: Variable = var.upcast();
+    let ct = Const::new(v);
+    let where_clause = WhereClause {
+        data: Arc::new(WhereClauseData::TypeOfConst(ct, Ty::bool())),
+    };
+    (var, where_clause)
+}
+
+fn runtime_effect() -> Parameter {
+    let effect: Const = Bool::True.upcast();
+    effect.upcast()
+}
+
+fn const_effect() -> Parameter {
+    let effect: Const = Bool::False.upcast();
+    effect.upcast()
+}
+
+impl Desugarer {
+    fn crate_items(&self, items: &[CrateItem]) -> Fallible<Vec<CrateItem>> {
+        items.iter().map(|item| self.crate_item(item)).collect()
+    }
+
+    fn crate_item(&self, item: &CrateItem) -> Fallible<CrateItem> {
+        Ok(match item {
+            CrateItem::Struct(s) => Struct {
+                binder: self.binder(
+                    &s.binder,
+                    |StructBoundData {
+                         where_clauses,
+                         fields,
+                     }| {
+                        Ok(StructBoundData {
+                            where_clauses: self.where_clauses(None, &where_clauses)?,
+                            fields,
+                        })
+                    },
+                )?,
+                ..s.clone()
+            }
+            .upcast(),
+            CrateItem::Enum(e) => Enum {
+                binder: self.binder(
+                    &e.binder,
+                    |AdtBoundData {
+                         where_clauses,
+                         variants,
+                     }| {
+                        Ok(AdtBoundData {
+                            where_clauses: self.where_clauses(None, &where_clauses)?,
+                            variants,
+                        })
+                    },
+                )?,
+                ..e.clone()
+            }
+            .upcast(),
+            CrateItem::Trait(t) => self.trait_(t)?.upcast(),
+            CrateItem::TraitImpl(i) => self.trait_impl(i)?.upcast(),
+            CrateItem::NegTraitImpl(i) => self.neg_trait_impl(i)?.upcast(),
+            CrateItem::Fn(f) => self.fn_(None, f)?.upcast(),
+            CrateItem::Static(_) | CrateItem::Const(_) => item.clone(),
+            CrateItem::TypeAlias(t) => TypeAlias {
+                binder: self.binder(&t.binder, |TypeAliasBoundData { ty, where_clauses }| {
+                    Ok(TypeAliasBoundData {
+                        ty,
+                        where_clauses: self.where_clauses(None, &where_clauses)?,
+                    })
+                })?,
+                ..t.clone()
+            }
+            .upcast(),
+            CrateItem::Module(m) => Module {
+                items: self.crate_items(&m.items)?,
+                ..m.clone()
+            }
+            .upcast(),
+            CrateItem::ExternBlock(b) => ExternBlock {
+                abi: b.abi,
+                fns: b
+                    .fns
+                    .iter()
+                    .map(|f| self.fn_(None, f))
+                    .collect::<Fallible<_>>()?,
+            }
+            .upcast(),
+            CrateItem::Cfg(c) => CfgItem {
+                feature: c.feature.clone(),
+                item: Arc::new(self.crate_item(&c.item)?),
+            }
+            .upcast(),
+        })
+    }
+
+    /// A const trait binds its effect after its other parameters.
+    fn trait_(&self, t: &Trait) -> Fallible<Trait> {
+        let (
+            mut vars,
+            TraitBoundData {
+                mut where_clauses,
+                trait_items,
+            },
+        ) = t.binder.explicit_binder.open();
+
+        let host = match t.constness {
+            Constness::NotConst => None,
+            Constness::Const => {
+                if trait_items
+                    .iter()
+                    .any(|item| matches!(item, TraitItem::AssociatedTy(_)))
+                {
+                    bail!(
+                        "the const trait `{:?}` cannot have associated types (not yet supported)",
+                        t.id
+                    )
+                }
+                let (var, where_clause) = fresh_effect_var();
+                vars.push(var);
+                where_clauses.push(where_clause);
+                Some(var.upcast())
+            }
+        };
+
+        let data = TraitBoundData {
+            where_clauses: self.where_clauses(host.as_ref(), &where_clauses)?,
+            trait_items: trait_items
+                .iter()
+                .map(|trait_item| self.trait_item(host.as_ref(), trait_item))
+                .collect::<Fallible<_>>()?,
+        };
+        Ok(Trait {
+            binder: TraitBinder {
+                explicit_binder: Binder::new(vars, data),
+            },
+            ..t.clone()
+        })
+    }
+
+    /// An `impl const` binds an effect and passes it on to the trait; other impls
+    /// of const traits only implement them for runtime use.
+    fn trait_impl(&self, i: &TraitImpl) -> Fallible<TraitImpl> {
+        let (
+            mut vars,
+            TraitImplBoundData {
+                constness,
+                trait_id,
+                self_ty,
+                mut trait_parameters,
+                mut where_clauses,
+                impl_items,
+            },
+        ) = i.binder.open();
+
+        let host = match constness {
+            Constness::NotConst => {
+                if self.const_traits.contains(&trait_id) {
+                    trait_parameters.push(runtime_effect());
+                }
+                None
+            }
+            Constness::Const => {
+                if !self.const_traits.contains(&trait_id) {
+                    bail!("`impl const` requires `{trait_id:?}` to be a const trait")
+                }
+                let (var, where_clause) = fresh_effect_var();
+                vars.push(var);
+                where_clauses.push(where_clause);
+                trait_parameters.push(var.upcast());
+                Some(var.upcast())
+            }
+        };
+
+        let data = TraitImplBoundData {
+            constness,
+            trait_id,
+            self_ty,
+            trait_parameters,
+            where_clauses: self.where_clauses(host.as_ref(), &where_clauses)?,
+            impl_items: impl_items
+                .iter()
+                .map(|impl_item| self.impl_item(host.as_ref(), impl_item))
+                .collect::<Fallible<_>>()?,
+        };
+        Ok(TraitImpl {
+            safety: i.safety,
+            binder: Binder::new(vars, data),
+        })
+    }
+
+    /// A negative impl of a const trait rules out impls for either effect.
+    fn neg_trait_impl(&self, i: &NegTraitImpl) -> Fallible<NegTraitImpl> {
+        let (
+            mut vars,
+            NegTraitImplBoundData {
+                trait_id,
+                self_ty,
+                mut trait_parameters,
+                mut where_clauses,
+            },
+        ) = i.binder.open();
+
+        if self.const_traits.contains(&trait_id) {
+            let (var, where_clause) = fresh_effect_var();
+            vars.push(var);
+            where_clauses.push(where_clause);
+            trait_parameters.push(var.upcast());
+        }
+
+        let data = NegTraitImplBoundData {
+            trait_id,
+            self_ty,
+            trait_parameters,
+            where_clauses: self.where_clauses(None, &where_clauses)?,
+        };
+        Ok(NegTraitImpl {
+            binder: Binder::new(vars, data),
+        })
+    }
+
+    fn trait_item(&self, host: Option<&Parameter>, trait_item: &TraitItem) -> Fallible<TraitItem> {
+        Ok(match trait_item {
+            TraitItem::Fn(f) => self.fn_(host, f)?.upcast(),
+            TraitItem::AssociatedTy(AssociatedTy { id, binder }) => AssociatedTy {
+                id: id.clone(),
+                binder: self.binder(
+                    binder,
+                    |AssociatedTyBoundData {
+                         ensures,
+                         where_clauses,
+                     }| {
+                        Ok(AssociatedTyBoundData {
+                            ensures: ensures.iter().map(|e| self.where_bound(e)).collect(),
+                            where_clauses: self.where_clauses(host, &where_clauses)?,
+                        })
+                    },
+                )?,
+            }
+            .upcast(),
+        })
+    }
+
+    fn impl_item(&self, host: Option<&Parameter>, impl_item: &ImplItem) -> Fallible<ImplItem> {
+        Ok(match impl_item {
+            ImplItem::Fn(f) => self.fn_(host, f)?.upcast(),
+            ImplItem::AssociatedTyValue(AssociatedTyValue { id, binder }) => AssociatedTyValue {
+                id: id.clone(),
+                binder: self.binder(
+                    binder,
+                    |AssociatedTyValueBoundData { where_clauses, ty }| {
+                        Ok(AssociatedTyValueBoundData {
+                            where_clauses: self.where_clauses(host, &where_clauses)?,
+                            ty,
+                        })
+                    },
+                )?,
+            }
+            .upcast(),
+        })
+    }
+
+    fn fn_(&self, host: Option<&Parameter>, f: &Fn) -> Fallible<Fn> {
+        Ok(Fn {
+            binder: self.binder(
+                &f.binder,
+                |FnBoundData {
+                     defaults,
+                     self_param,
+                     input_tys,
+                     output_ty,
+                     where_clauses,
+                     body,
+                 }| {
+                    Ok(FnBoundData {
+                        defaults,
+                        self_param,
+                        input_tys,
+                        output_ty,
+                        where_clauses: self.where_clauses(host, &where_clauses)?,
+                        body,
+                    })
+                },
+            )?,
+            ..f.clone()
+        })
+    }
+
+    fn binder<T: formality_types::fold::Fold>(
+        &self,
+        binder: &Binder<T>,
+        op: impl FnOnce(T) -> Fallible<T>,
+    ) -> Fallible<Binder<T>> {
+        let (vars, data) = binder.open();
+        Ok(Binder::new(vars, op(data)?))
+    }
+
+    /// Desugars `where_clauses`, in which `~const` bounds refer to the effect `host`
+    /// of the enclosing const trait or `impl const`, if any.
+    fn where_clauses(
+        &self,
+        host: Option<&Parameter>,
+        where_clauses: &[WhereClause],
+    ) -> Fallible<Vec<WhereClause>> {
+        where_clauses
+            .iter()
+            .map(|where_clause| self.where_clause(host, where_clause))
+            .collect()
+    }
+
+    fn where_clause(
+        &self,
+        host: Option<&Parameter>,
+        where_clause: &WhereClause,
+    ) -> Fallible<WhereClause> {
+        let data = match where_clause.data() {
+            WhereClauseData::IsImplemented(self_ty, trait_id, parameters) => {
+                WhereClauseData::IsImplemented(
+                    self_ty.clone(),
+                    trait_id.clone(),
+                    self.runtime_parameters(trait_id, parameters),
+                )
+            }
+            WhereClauseData::ConstIsImplemented(self_ty, constness, trait_id, parameters) => {
+                if !self.const_traits.contains(trait_id) {
+                    bail!("`{constness:?}` bounds require `{trait_id:?}` to be a const trait")
+                }
+                let effect = match constness {
+                    BoundConstness::Const => const_effect(),
+                    BoundConstness::MaybeConst => {
+                        match host {
+                            Some(host) => host.clone(),
+                            None => {
+                                bail!("`~const` bounds are only allowed in const traits and `impl const`")
+                            }
+                        }
+                    }
+                };
+                let parameters = parameters.iter().cloned().chain(Some(effect)).collect();
+                WhereClauseData::IsImplemented(self_ty.clone(), trait_id.clone(), parameters)
+            }
+            WhereClauseData::ForAll(binder) => {
+                WhereClauseData::ForAll(self.binder(binder, |wc| self.where_clause(host, &wc))?)
+            }
+            WhereClauseData::AliasEq(..)
+            | WhereClauseData::Outlives(..)
+            | WhereClauseData::TypeOfConst(..)
+            | WhereClauseData::MaybeSized(..) => return Ok(where_clause.clone()),
+        };
+        Ok(WhereClause {
+            data: Arc::new(data),
+        })
+    }
+
+    fn where_bound(&self, where_bound: &WhereBound) -> WhereBound {
+        let data = match where_bound.data() {
+            WhereBoundData::IsImplemented(trait_id, parameters) => WhereBoundData::IsImplemented(
+                trait_id.clone(),
+                self.runtime_parameters(trait_id, parameters),
+            ),
+            WhereBoundData::Outlives(_) => return where_bound.clone(),
+            WhereBoundData::ForAll(binder) => {
+                let (vars, bound) = binder.open();
+                WhereBoundData::ForAll(Binder::new(vars, self.where_bound(&bound)))
+            }
+        };
+        WhereBound {
+            data: Arc::new(data),
+        }
+    }
+
+    /// The parameters of a plain bound on `trait_id`, which is used at runtime.
+    fn runtime_parameters(&self, trait_id: &TraitId, parameters: &[Parameter]) -> Vec<Parameter> {
+        let mut parameters = parameters.to_vec();
+        if self.const_traits.contains(trait_id) {
+            parameters.push(runtime_effect());
+        }
+        parameters
+    }
+}
@@ -1,23 +1,45 @@
 use std::sync::Arc;
 
 use formality_macros::term;
+use serde::{Deserialize, Serialize};
 use formality_types::{
-    cast::Upcast,
+    cast::{Downcast, Upcast},
+    collections::Set,
     grammar::{
-        AdtId, AssociatedItemId, Binder, Const, CrateId, Fallible, FieldId, FnId, Lt, Parameter,
-        TraitId, TraitRef, Ty, Wc, AliasTy,
+        Abi, AdtId, AliasName, AliasTy, AssociatedItemId, Binder, Const, CrateId, Fallible,
+        FieldId, FnId, Lt, Parameter, Relation, TraitId, TraitRef, Ty, TypeAliasId, Wc,
     },
     term::Term,
 };
 
-use crate::grammar::mir::MirFnBody;
+use crate::grammar::{
+    constness::{BoundConstness, Constness},
+    dependencies::Dependencies,
+    edition::Edition,
+    expr::{Block, Expr, ValueId},
+    mir::MirFnBody,
+    safety::Safety,
+    self_param::SelfParam,
+    ty_defaults::TyDefaults,
+    visibility::Visibility,
+};
 
+pub mod constness;
+pub mod dependencies;
+pub mod edition;
+pub mod expr;
 pub mod mir;
+pub mod pattern;
+pub mod safety;
+pub mod self_param;
+pub mod ty_defaults;
+pub mod visibility;
 
 #[term($crates)]
 pub struct Program {
     /// List of all crates.
     /// The last crate in the list is the current crate.
+    /// Each crate depends on some of the others (see [`Dependencies`]).
     pub crates: Vec<Crate>,
 }
 
@@ -26,6 +48,215 @@ impl Program {
         self.crates.iter().flat_map(|c| &c.items)
     }
 
+    /// The items of all crates, each with the id of the crate that defines it.
+    pub fn items_with_crates(&self) -> impl Iterator<Item = (&CrateId, &CrateItem)> {
+        self.crates
+            .iter()
+            .flat_map(|c| c.items.iter().map(move |item| (&c.id, item)))
+    }
+
+    /// The program with `items` added to the crate `crate_id`.
+    pub fn with_items(
+        &self,
+        crate_id: &CrateId,
+        items: impl IntoIterator<Item = CrateItem>,
+    ) -> Fallible<Program> {
+        let mut program = self.clone();
+        let Some(c) = program.crates.iter_mut().find(|c| c.id == *crate_id) else {
+            anyhow::bail!("no crate named `{crate_id:?}`")
+        };
+        c.items.extend(items);
+        Ok(program)
+    }
+
+    /// The indices of the crates that the crate at `index` depends on directly.
+    pub fn direct_dependencies(&self, index: usize) -> Fallible<Vec<usize>> {
+        let c = &self.crates[index];
+        match &c.dependencies {
+            Dependencies::Preceding => Ok((0..index).collect()),
+            Dependencies::Explicit(crate_ids) => crate_ids
+                .iter()
+                .map(|crate_id| match self.crates.iter().position(|d| d.id == *crate_id) {
+                    Some(i) => Ok(i),
+                    None => anyhow::bail!(
+                        "crate `{:?}` depends on `{crate_id:?}`, which is not a crate of the program",
+                        c.id
+                    ),
+                })
+                .collect(),
+        }
+    }
+
+    /// The indices of all crates, each after the crates it depends on. Crates keep
+    /// their order in the program where the dependencies allow, so that the order is
+    /// that of the program if each crate comes after its dependencies.
+    pub fn dependency_order(&self) -> Fallible<Vec<usize>> {
+        let dependencies = (0..self.crates.len())
+            .map(|i| self.direct_dependencies(i))
+            .collect::<Fallible<Vec<_>>>()?;
+        let mut done = vec![false; self.crates.len()];
+        let mut order = vec![];
+        while order.len() < self.crates.len() {
+            let next = (0..self.crates.len())
+                .find(|&i| !done[i] && dependencies[i].iter().all(|&d| done[d]));
+            let Some(i) = next else {
+                let crate_ids: Vec<&CrateId> = (0..self.crates.len())
+                    .filter(|&i| !done[i])
+                    .map(|i| &self.crates[i].id)
+                    .collect();
+                anyhow::bail!("cyclic dependencies among the crates {crate_ids:?}")
+            };
+            done[i] = true;
+            order.push(i);
+        }
+        Ok(order)
+    }
+
+    /// The program to check the crate at `index` in: the crates it depends on, directly
+    /// or not, in dependency order, followed by the crate itself as the current crate.
+    pub fn crate_program(&self, index: usize) -> Fallible<Program> {
+        let mut needed = Set::new();
+        let mut stack = vec![index];
+        while let Some(i) = stack.pop() {
+            for d in self.direct_dependencies(i)? {
+                if needed.insert(d) {
+                    stack.push(d);
+                }
+            }
+        }
+        let indices: Vec<usize> = self
+            .dependency_order()?
+            .into_iter()
+            .filter(|i| needed.contains(i))
+            .chain(Some(index))
+            .collect();
+
+        // The crates that depend on those before them keep doing so, unless some of
+        // those are left out.
+        let crates = indices
+            .iter()
+            .enumerate()
+            .map(|(position, &i)| {
+                let c = &self.crates[i];
+                let dependencies = self.direct_dependencies(i)?;
+                if c.dependencies == Dependencies::Preceding && dependencies == indices[..position]
+                {
+                    return Ok(c.clone());
+                }
+                Ok(Crate {
+                    dependencies: Dependencies::Explicit(
+                        dependencies
+                            .iter()
+                            .map(|&d| self.crates[d].id.clone())
+                            .collect(),
+                    ),
+                    ..c.clone()
+                })
+            })
+            .collect::<Fallible<_>>()?;
+        Ok(Program { crates })
+    }
+
+    pub fn fn_named(&self, fn_id: &FnId) -> Fallible<&Fn> {
+        let mut fns: Vec<&Fn> = self
+            .items_from_all_crates()
+            .flat_map(|crate_item| match crate_item {
+                CrateItem::Fn(f) => std::slice::from_ref(f),
+                CrateItem::ExternBlock(b) => &b.fns[..],
+                _ => &[],
+            })
+            .filter(|f| f.id == *fn_id)
+            .collect();
+        if fns.is_empty() {
+            anyhow::bail!("no fn named `{fn_id:?}`")
+        } else if fns.len() > 1 {
+            anyhow::bail!("multiple fns named `{fn_id:?}`")
+        } else {
+            Ok(fns.pop().unwrap())
+        }
+    }
+
+    /// The ABI that the fn `fn_id` is called with: the ABI of its extern block,
+    /// if it is declared in one, and the Rust ABI otherwise.
+    pub fn fn_abi(&self, fn_id: &FnId) -> Abi {
+        self.items_from_all_crates()
+            .find_map(|crate_item| match crate_item {
+                CrateItem::ExternBlock(b) if b.fns.iter().any(|f| f.id == *fn_id) => Some(b.abi),
+                _ => None,
+            })
+            .unwrap_or(Abi::Rust)
+    }
+
+    /// Whether calling the fn `fn_id` requires an unsafe context: it is an `unsafe fn`,
+    /// or declared in an extern block, whose fns the compiler knows nothing about.
+    pub fn fn_safety(&self, fn_id: &FnId) -> Safety {
+        self.items_from_all_crates()
+            .find_map(|crate_item| match crate_item {
+                CrateItem::Fn(f) if f.id == *fn_id => Some(f.safety),
+                CrateItem::ExternBlock(b) if b.fns.iter().any(|f| f.id == *fn_id) => {
+                    Some(Safety::Unsafe)
+                }
+                _ => None,
+            })
+            .unwrap_or(Safety::Safe)
+    }
+
+    pub fn adt_named(&self, adt_id: &AdtId) -> Fallible<Adt> {
+        let mut adts: Vec<Adt> = self
+            .items_from_all_crates()
+            .filter_map(|crate_item| match crate_item {
+                CrateItem::Struct(s) if s.id == *adt_id => Some(s.to_adt()),
+                CrateItem::Enum(e) if e.id == *adt_id => Some(e.to_adt()),
+                _ => None,
+            })
+            .collect();
+        if adts.is_empty() {
+            anyhow::bail!("no ADT named `{adt_id:?}`")
+        } else if adts.len() > 1 {
+            anyhow::bail!("multiple ADTs named `{adt_id:?}`")
+        } else {
+            Ok(adts.pop().unwrap())
+        }
+    }
+
+    /// Returns the type of the `static` or `const` item named `id`.
+    pub fn value_item_ty(&self, id: &ValueId) -> Fallible<Ty> {
+        let mut tys: Vec<Ty> = self
+            .items_from_all_crates()
+            .filter_map(|crate_item| match crate_item {
+                CrateItem::Static(s) if s.id == *id => Some(s.ty.clone()),
+                CrateItem::Const(c) if c.id == *id => Some(c.ty.clone()),
+                _ => None,
+            })
+            .collect();
+        if tys.is_empty() {
+            anyhow::bail!("no static or const named `{id:?}`")
+        } else if tys.len() > 1 {
+            anyhow::bail!("multiple statics or consts named `{id:?}`")
+        } else {
+            Ok(tys.pop().unwrap())
+        }
+    }
+
+    /// If `ty` is a use of a type alias, returns its expansion (without checking the
+    /// where-clauses of the alias).
+    pub fn expand_type_alias(&self, ty: &Ty) -> Option<Ty> {
+        let AliasTy {
+            name: AliasName::TypeAliasId(id),
+            parameters,
+        } = ty.downcast()?
+        else {
+            return None;
+        };
+        self.items_from_all_crates()
+            .find_map(|crate_item| match crate_item {
+                CrateItem::TypeAlias(t) if t.id == id => {
+                    Some(t.binder.instantiate_with(&parameters).ok()?.ty)
+                }
+                _ => None,
+            })
+    }
+
     pub fn trait_named(&self, trait_id: &TraitId) -> Fallible<&Trait> {
         let mut traits: Vec<&Trait> = self
             .items_from_all_crates()
@@ -44,13 +275,16 @@ impl Program {
     }
 }
 
-#[term(crate $id { $*items })]
+#[term(crate $id $edition $dependencies { $*items })]
 pub struct Crate {
     pub id: CrateId,
+    pub edition: Edition,
+    pub dependencies: Dependencies,
     pub items: Vec<CrateItem>,
 }
 
 #[term]
+#[recovery_point]
 pub enum CrateItem {
     #[cast]
     Struct(Struct),
@@ -64,10 +298,104 @@ pub enum CrateItem {
     NegTraitImpl(NegTraitImpl),
     #[cast]
     Fn(Fn),
+    #[cast]
+    Static(Static),
+    #[cast]
+    Const(ConstItem),
+    #[cast]
+    TypeAlias(TypeAlias),
+    #[cast]
+    Module(Module),
+    #[cast]
+    ExternBlock(ExternBlock),
+    #[cast]
+    Cfg(CfgItem),
+}
+
+formality_types::id!(ModuleId);
+
+/// A module `mod m { ... }`. Name resolution (see [`crate::resolve`]) flattens modules,
+/// giving each item inside the path of its module as a prefix, e.g. `m::Foo`.
+#[term($visibility mod $id { $*items })]
+pub struct Module {
+    pub visibility: Visibility,
+    pub id: ModuleId,
+    pub items: Vec<CrateItem>,
+}
+
+/// An `extern "C" { ... }` block, declaring fns that are defined outside of the program
+/// and are called with the given ABI. The fns do not have bodies.
+#[term(extern $abi { $*fns })]
+pub struct ExternBlock {
+    pub abi: Abi,
+    pub fns: Vec<Fn>,
+}
+
+formality_types::id!(FeatureId);
+
+/// An item that is only in its crate if the crate's feature `feature` is enabled,
+/// e.g. `#[cfg(feature = std)] impl<> ...` (see [`crate::cfg`]).
+#[term(#[cfg(feature = $feature)] $item)]
+pub struct CfgItem {
+    pub feature: FeatureId,
+    pub item: Arc<CrateItem>,
+}
+
+/// The feature `feature_id` of the crate `crate_id`, written `crate_id/feature_id`.
+#[term($crate_id / $feature_id)]
+pub struct Feature {
+    pub crate_id: CrateId,
+    pub feature_id: FeatureId,
+}
+
+/// A `static` item, a value that lives at a fixed address for the whole run of the program.
+#[term($visibility $kind $id : $ty = $value ;)]
+pub struct Static {
+    pub visibility: Visibility,
+    pub kind: StaticKind,
+    pub id: ValueId,
+    pub ty: Ty,
+    pub value: Expr,
+}
+
+#[term]
+pub enum StaticKind {
+    /// Statics shared between all threads, whose type must therefore be `Sync`.
+    #[grammar(static)]
+    Global,
+
+    /// Statics of which each thread has its own copy.
+    #[grammar(#[thread_local] static)]
+    ThreadLocal,
+}
+
+/// A `const` item, a value that is copied into each place that uses it.
+#[term($visibility const $id : $ty = $value ;)]
+pub struct ConstItem {
+    pub visibility: Visibility,
+    pub id: ValueId,
+    pub ty: Ty,
+    pub value: Expr,
 }
 
-#[term(struct $id $binder)]
+/// A free type alias, e.g. `type Foo<ty T> = Vec<T> where [T: Clone<>];`.
+/// Uses of the alias are written `(alias Foo X)`.
+#[term($visibility type $id $binder ;)]
+pub struct TypeAlias {
+    pub visibility: Visibility,
+    pub id: TypeAliasId,
+    pub binder: Binder<TypeAliasBoundData>,
+}
+
+#[term(= $ty where $where_clauses)]
+pub struct TypeAliasBoundData {
+    pub ty: Ty,
+    pub where_clauses: Vec<WhereClause>,
+}
+
+#[term($visibility struct $id $binder)]
 pub struct Struct {
+    pub visibility: Visibility,
     pub id: AdtId,
     pub binder: Binder<StructBoundData>,
 }
@@ -82,6 +410,7 @@ impl Struct {
             },
         ) = self.binder.open();
         Adt {
+            visibility: self.visibility,
             id: self.id.clone(),
             binder: Binder::new(
                 vars,
@@ -103,8 +432,9 @@ pub struct StructBoundData {
     pub fields: Vec<Field>,
 }
 
-#[term($name : $ty)]
+#[term($visibility $name : $ty)]
 pub struct Field {
+    pub visibility: Visibility,
     pub name: FieldName,
     pub ty: Ty,
 }
@@ -126,8 +456,9 @@ impl VariantId {
     }
 }
 
-#[term(enum $id $binder)]
+#[term($visibility enum $id $binder)]
 pub struct Enum {
+    pub visibility: Visibility,
     pub id: AdtId,
     pub binder: Binder<AdtBoundData>,
 }
@@ -135,6 +466,7 @@ pub struct Enum {
 impl Enum {
     pub fn to_adt(&self) -> Adt {
         Adt {
+            visibility: self.visibility,
             id: self.id.clone(),
             binder: self.binder.clone(),
         }
@@ -143,33 +475,113 @@ impl Enum {
 
 /// Not directly part of the grammar, but structs/enums
 /// can be converted to this.
-#[term(adt $id $binder)]
+#[term($visibility adt $id $binder)]
 pub struct Adt {
+    pub visibility: Visibility,
     pub id: AdtId,
     pub binder: Binder<AdtBoundData>,
 }
 
+impl Adt {
+    /// If this ADT has a single variant (i.e., it is a struct), returns the type of `field`
+    /// with the ADT's generic parameters instantiated to `parameters`.
+    pub fn struct_field_ty(
+        &self,
+        parameters: &[Parameter],
+        field: &FieldName,
+    ) -> Fallible<Option<Ty>> {
+        let AdtBoundData {
+            where_clauses: _,
+            variants,
+        } = self.binder.instantiate_with(parameters)?;
+        let [variant] = &variants[..] else {
+            return Ok(None);
+        };
+        Ok(variant.field_ty(field))
+    }
+
+    /// Returns the types of the fields of `variant`, with the ADT's generic parameters
+    /// instantiated to `parameters`.
+    pub fn variant_field_tys(
+        &self,
+        parameters: &[Parameter],
+        variant: &VariantId,
+    ) -> Fallible<Option<Vec<Ty>>> {
+        let AdtBoundData {
+            where_clauses: _,
+            variants,
+        } = self.binder.instantiate_with(parameters)?;
+        Ok(variants
+            .iter()
+            .find(|v| v.name == *variant)
+            .map(|v| v.fields.iter().map(|f| f.ty.clone()).collect()))
+    }
+
+    /// Returns the type of `field` in `variant`, with the ADT's generic parameters
+    /// instantiated to `parameters`.
+    pub fn variant_field_ty(
+        &self,
+        parameters: &[Parameter],
+        variant: &VariantId,
+        field: &FieldName,
+    ) -> Fallible<Option<Ty>> {
+        let AdtBoundData {
+            where_clauses: _,
+            variants,
+        } = self.binder.instantiate_with(parameters)?;
+        Ok(variants
+            .iter()
+            .find(|v| v.name == *variant)
+            .and_then(|v| v.field_ty(field)))
+    }
+}
+
 #[term(where $where_clauses { $,variants })]
 pub struct AdtBoundData {
     pub where_clauses: Vec<WhereClause>,
     pub variants: Vec<Variant>,
 }
 
+impl AdtBoundData {
+    /// The outlives bounds implied by the types of the fields, e.g. `T: 'a` for a field
+    /// of type `&'a T`, which hold of the ADT without being declared (RFC 2093).
+    pub fn implied_outlives(&self) -> Vec<Relation> {
+        self.variants
+            .iter()
+            .flat_map(|v| &v.fields)
+            .flat_map(|f| f.ty.implied_outlives())
+            .collect()
+    }
+}
+
 #[term($name { $,fields })]
 pub struct Variant {
     pub name: VariantId,
     pub fields: Vec<Field>,
 }
 
-#[term(trait $id $binder)]
+impl Variant {
+    pub fn field_ty(&self, field: &FieldName) -> Option<Ty> {
+        self.fields
+            .iter()
+            .find(|f| f.name == *field)
+            .map(|f| f.ty.clone())
+    }
+}
+
+#[term($visibility $safety $constness trait $id $binder)]
 pub struct Trait {
+    pub visibility: Visibility,
+    pub safety: Safety,
+    pub constness: Constness,
     pub id: TraitId,
     pub binder: TraitBinder<TraitBoundData>,
 }
 
 // NB: TraitBinder is a manually implemented Term
 // that binds the `Self` variable.
-#[derive(Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(bound = "")] // `T: Term` implies the serde traits
 pub struct TraitBinder<T: Term> {
     pub explicit_binder: Binder<T>,
 }
@@ -194,14 +606,18 @@ pub enum TraitItem {
     AssociatedTy(AssociatedTy),
 }
 
-#[term(fn $id $binder)]
+#[term($visibility $safety fn $id $binder)]
 pub struct Fn {
+    pub visibility: Visibility,
+    pub safety: Safety,
     pub id: FnId,
     pub binder: Binder<FnBoundData>,
 }
 
-#[term(($,input_tys) -> $output_ty where $where_clauses $body)]
+#[term($defaults ($self_param $,input_tys) -> $output_ty where $where_clauses $body)]
 pub struct FnBoundData {
+    pub defaults: TyDefaults,
+    pub self_param: SelfParam,
     pub input_tys: Vec<Ty>,
     pub output_ty: Ty,
     pub where_clauses: Vec<WhereClause>,
@@ -225,6 +641,17 @@ pub enum FnBody {
     #[cast]
     #[grammar(= $v0 ;)]
     MirFnBody(MirFnBody),
+
+    #[cast]
+    ExprFnBody(ExprFnBody),
+}
+
+/// A body written as an expression, e.g. `|x, y| { x }`.
+/// The parameters name the fn inputs, in order.
+#[term(|$,params| $body)]
+pub struct ExprFnBody {
+    pub params: Vec<ValueId>,
+    pub body: Block,
 }
 
 #[term(type $id $binder ;)]
@@ -242,8 +669,9 @@ pub struct AssociatedTyBoundData {
     pub where_clauses: Vec<WhereClause>,
 }
 
-#[term(impl $binder)]
+#[term($safety impl $binder)]
 pub struct TraitImpl {
+    pub safety: Safety,
     pub binder: Binder<TraitImplBoundData>,
 }
 
@@ -253,8 +681,9 @@ impl TraitImpl {
     }
 }
 
-#[term($trait_id < $,trait_parameters > for $self_ty where $where_clauses { $*impl_items })]
+#[term($constness $trait_id < $,trait_parameters > for $self_ty where $where_clauses { $*impl_items })]
 pub struct TraitImplBoundData {
+    pub constness: Constness,
     pub trait_id: TraitId,
     pub self_ty: Ty,
     pub trait_parameters: Vec<Parameter>,
@@ -308,6 +737,7 @@ pub struct AssociatedTyValueBoundData {
 }
 
 #[term($data)]
+#[recovery_point]
 pub struct WhereClause {
     pub data: Arc<WhereClauseData>,
 }
@@ -325,6 +755,7 @@ impl WhereClause {
                     .not_implemented()
                     .upcast(),
             ),
+            WhereClauseData::ConstIsImplemented(..) => None,
             WhereClauseData::AliasEq(_, _) => None,
             WhereClauseData::Outlives(_, _) => None,
             WhereClauseData::ForAll(binder) => {
@@ -333,6 +764,7 @@ impl WhereClause {
                 Some(Wc::for_all(&vars, wc))
             }
             WhereClauseData::TypeOfConst(_, _) => None,
+            WhereClauseData::MaybeSized(_) => None,
         }
     }
 }
@@ -342,6 +774,11 @@ pub enum WhereClauseData {
     #[grammar($v0 : $v1 < $,v2 >)]
     IsImplemented(Ty, TraitId, Vec<Parameter>),
 
+    /// Bounds `T: const Trait<>` and `T: ~const Trait<>` on a const trait.
+    /// These are desugared into plain bounds by [`crate::effects`].
+    #[grammar($v0 : $v1 $v2 < $,v3 >)]
+    ConstIsImplemented(Ty, BoundConstness, TraitId, Vec<Parameter>),
+
     #[grammar($v0 => $v1)]
     AliasEq(AliasTy, Ty),
 
@@ -353,6 +790,11 @@ pub enum WhereClauseData {
 
     #[grammar(type_of_const $v0 is $v1)]
     TypeOfConst(Const, Ty),
+
+    /// `T: ?Sized` removes the implicit `T: Sized` bound of the type parameter `T`
+    /// (see [`crate::sized`]). It is not a where clause itself.
+    #[grammar($v0 : ?Sized)]
+    MaybeSized(Ty),
 }
 
 #[term($data)]
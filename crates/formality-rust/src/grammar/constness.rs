@@ -0,0 +1,112 @@
+//! Const traits: `const trait`, `impl const` and the `const` and `~const` modifiers on bounds.
+//!
+//! The markers get handwritten parsers, as items without a `const` are not const
+//! and so the marker may be omitted entirely.
+
+use formality_macros::{Cast, Visit};
+use serde::{Deserialize, Serialize};
+use formality_types::{
+    fold::{Fold, SubstitutionFn},
+    generate::{Generate, Generator},
+    parse::{expect_char, expect_keyword, Parse, ParseResult, Scope},
+    pretty::Pretty,
+    term::Term,
+};
+
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast)]
+pub enum Constness {
+    /// Traits and impls without a `const`, which can only be used at runtime.
+    #[default]
+    NotConst,
+
+    /// A `const trait` can be implemented by an `impl const`, which can also be
+    /// used in const contexts.
+    Const,
+}
+
+impl Term for Constness {}
+
+impl Pretty for Constness {}
+
+impl Generate for Constness {
+    fn generate(g: &mut Generator) -> Self {
+        g.choose(&[Constness::NotConst, Constness::Const])
+    }
+}
+
+impl Fold for Constness {
+    fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {
+        *self
+    }
+}
+
+impl Parse for Constness {
+    #[tracing::instrument(level = "trace", ret)]
+    fn parse<'t>(_scope: &Scope, text: &'t str) -> ParseResult<'t, Self> {
+        match expect_keyword("const", text) {
+            Ok(((), text)) => Ok((Constness::Const, text)),
+            Err(_) => Ok((Constness::NotConst, text)),
+        }
+    }
+}
+
+impl std::fmt::Debug for Constness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Constness::NotConst => Ok(()),
+            Constness::Const => write!(f, "const"),
+        }
+    }
+}
+
+/// The modifier of a bound on a const trait.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast)]
+pub enum BoundConstness {
+    /// `T: const Trait<>` requires an `impl const`.
+    Const,
+
+    /// `T: ~const Trait<>`, in a const trait or `impl const`, requires an `impl const`
+    /// only if the enclosing trait or impl is itself used in a const context.
+    MaybeConst,
+}
+
+impl Term for BoundConstness {}
+
+impl Pretty for BoundConstness {}
+
+impl Generate for BoundConstness {
+    fn generate(g: &mut Generator) -> Self {
+        g.choose(&[BoundConstness::Const, BoundConstness::MaybeConst])
+    }
+}
+
+impl Fold for BoundConstness {
+    fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {
+        *self
+    }
+}
+
+impl Parse for BoundConstness {
+    #[tracing::instrument(level = "trace", ret)]
+    fn parse<'t>(_scope: &Scope, text: &'t str) -> ParseResult<'t, Self> {
+        match expect_char('~', text) {
+            Ok(((), text)) => {
+                let ((), text) = expect_keyword("const", text)?;
+                Ok((BoundConstness::MaybeConst, text))
+            }
+            Err(_) => {
+                let ((), text) = expect_keyword("const", text)?;
+                Ok((BoundConstness::Const, text))
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for BoundConstness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoundConstness::Const => write!(f, "const"),
+            BoundConstness::MaybeConst => write!(f, "~const"),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! The crates that a crate depends on.
+//!
+//! A crate can list its dependencies, as in `crate c depends [a, b] { ... }`, and can
+//! then only refer to the items of those and of itself. The list gets a handwritten
+//! parser, as a crate without one depends on all the crates before it in the program.
+
+use formality_macros::{Cast, Visit};
+use serde::{Deserialize, Serialize};
+use formality_types::{
+    fold::{Fold, SubstitutionFn},
+    generate::{Generate, Generator},
+    grammar::CrateId,
+    parse::{expect_keyword, Parse, ParseResult, Scope},
+    pretty::Pretty,
+    term::Term,
+};
+
+#[derive(
+    Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast,
+)]
+pub enum Dependencies {
+    /// Crates without a `depends` depend on all the crates before them.
+    #[default]
+    Preceding,
+
+    /// `depends [a, b]`: the crates depended on, in any order.
+    Explicit(Vec<CrateId>),
+}
+
+impl Term for Dependencies {}
+
+impl Pretty for Dependencies {}
+
+/// Generated crates depend on the crates before them, as the ids of other crates are
+/// unlikely to be generated.
+impl Generate for Dependencies {
+    fn generate(_g: &mut Generator) -> Self {
+        Dependencies::Preceding
+    }
+}
+
+impl Fold for Dependencies {
+    fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {
+        self.clone()
+    }
+}
+
+impl Parse for Dependencies {
+    #[tracing::instrument(level = "trace", ret)]
+    fn parse<'t>(scope: &Scope, text: &'t str) -> ParseResult<'t, Self> {
+        let Ok(((), text1)) = expect_keyword("depends", text) else {
+            return Ok((Dependencies::Preceding, text));
+        };
+        let (crate_ids, text1) = Vec::<CrateId>::parse(scope, text1)?;
+        Ok((Dependencies::Explicit(crate_ids), text1))
+    }
+}
+
+impl std::fmt::Debug for Dependencies {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dependencies::Preceding => Ok(()),
+            Dependencies::Explicit(crate_ids) => write!(f, "depends {crate_ids:?}"),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! The edition of a crate, which selects between the rules that differ by edition.
+//!
+//! A crate can name its edition, as in `crate foo edition 2024 { ... }`. The edition
+//! gets a handwritten parser, as crates without one are of the default edition. Rules
+//! branch on the edition of the crate being checked by comparing it, e.g.
+//! `edition >= Edition::Rust2024`, so that the rule of an edition carries on to those after it.
+
+use formality_macros::{Cast, Visit};
+use serde::{Deserialize, Serialize};
+use formality_types::{
+    fold::{Fold, SubstitutionFn},
+    generate::{Generate, Generator},
+    parse::{expect_keyword, number, Parse, ParseError, ParseResult, Scope},
+    pretty::Pretty,
+    term::Term,
+};
+
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast)]
+pub enum Edition {
+    Rust2015,
+    Rust2018,
+
+    /// Crates without an `edition`.
+    #[default]
+    Rust2021,
+
+    /// Diverging expressions fall back to `!` rather than `()` (see `Fallback::Diverging`
+    /// in `formality-check`).
+    Rust2024,
+}
+
+impl Edition {
+    pub const ALL: [Edition; 4] = [
+        Edition::Rust2015,
+        Edition::Rust2018,
+        Edition::Rust2021,
+        Edition::Rust2024,
+    ];
+
+    /// The year that names the edition.
+    pub fn year(self) -> u32 {
+        match self {
+            Edition::Rust2015 => 2015,
+            Edition::Rust2018 => 2018,
+            Edition::Rust2021 => 2021,
+            Edition::Rust2024 => 2024,
+        }
+    }
+}
+
+impl Term for Edition {}
+
+impl Pretty for Edition {}
+
+impl Generate for Edition {
+    fn generate(g: &mut Generator) -> Self {
+        g.choose(&Edition::ALL)
+    }
+}
+
+impl Fold for Edition {
+    fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {
+        *self
+    }
+}
+
+impl Parse for Edition {
+    #[tracing::instrument(level = "trace", ret)]
+    fn parse<'t>(_scope: &Scope, text: &'t str) -> ParseResult<'t, Self> {
+        let Ok(((), text1)) = expect_keyword("edition", text) else {
+            return Ok((Edition::default(), text));
+        };
+        let (year, text2) = number::<u32>(text1)?;
+        match Edition::ALL.into_iter().find(|e| e.year() == year) {
+            Some(edition) => Ok((edition, text2)),
+            None => Err(ParseError::at(text1, format!("unknown edition `{year}`"))),
+        }
+    }
+}
+
+/// The default edition is not written out, like a crate without `depends`.
+impl std::fmt::Debug for Edition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if *self == Edition::default() {
+            Ok(())
+        } else {
+            write!(f, "edition {}", self.year())
+        }
+    }
+}
@@ -0,0 +1,848 @@
+//! Expressions, used to write fn bodies in a Rust-like surface syntax.
+//!
+//! Like types, expressions get a handwritten parser so that tests can
+//! be written in something close to Rust syntax (e.g., `&x.f`, `foo(x, 22_u32)`).
+//! Unlike in types, numeric literals may omit their suffix (e.g., `22` or `1.5`).
+
+use std::sync::Arc;
+
+use formality_macros::{term, Cast, Visit};
+use serde::{Deserialize, Serialize};
+use formality_types::{
+    cast::{DowncastTo, Upcast, UpcastFrom},
+    fold::{Fold, SubstitutionFn},
+    generate::{Generate, Generator},
+    grammar::{
+        lang_items::{add_trait, div_trait, mul_trait, neg_trait, not_trait, rem_trait, sub_trait},
+        Const, FnId, Parameter, TraitId, Ty,
+    },
+    parse::{
+        expect_char, expect_keyword, identifier, number, path, skip_whitespace, Parse, ParseError,
+        ParseResult, Scope,
+    },
+    pretty::{Doc, DocBuilder, Pretty, PrettyCx},
+    term::Term,
+};
+
+use super::{pattern::Pattern, FieldName};
+
+formality_types::id!(ValueId);
+
+/// Identifiers with a special meaning in expressions; these cannot be used as variable names.
+pub(crate) const KEYWORDS: &[&str] = &[
+    "let", "if", "else", "match", "true", "false", "mut", "as", "move", "async", "await", "unsafe",
+];
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast)]
+#[serde(transparent)]
+#[cast(Block <: ExprData, ValueId <: ExprData, Const <: ExprData, NumericLit <: ExprData)]
+pub struct Expr {
+    data: Arc<ExprData>,
+}
+
+impl Expr {
+    pub fn new(data: impl Upcast<ExprData>) -> Self {
+        Expr {
+            data: Arc::new(data.upcast()),
+        }
+    }
+
+    pub fn data(&self) -> &ExprData {
+        &self.data
+    }
+
+    /// Whether `self` needs parentheses as the operand of a field access, indexing or `.await`:
+    /// prefix and binary operators, casts and closures bind less tightly than these, and
+    /// an integer like `1` would run together with a field like `.0` into a float literal.
+    fn needs_parens_as_postfix_operand(&self) -> bool {
+        matches!(
+            self.data(),
+            ExprData::Ref(_)
+                | ExprData::RefMut(_)
+                | ExprData::Deref(_)
+                | ExprData::Unary(..)
+                | ExprData::Binary(..)
+                | ExprData::Cast(..)
+                | ExprData::Closure(..)
+                | ExprData::Numeric(NumericLit::Int(_))
+        )
+    }
+
+    /// Whether `self` needs parentheses as the operand of a prefix operator, which binds
+    /// more tightly than `as`, binary operators and closures.
+    fn needs_parens_as_prefix_operand(&self) -> bool {
+        matches!(
+            self.data(),
+            ExprData::Cast(..) | ExprData::Binary(..) | ExprData::Closure(..)
+        )
+    }
+
+    /// Whether `self` needs parentheses as the operand of `as`, which binds more tightly
+    /// than binary operators and closures.
+    fn needs_parens_as_cast_operand(&self) -> bool {
+        matches!(self.data(), ExprData::Binary(..) | ExprData::Closure(..))
+    }
+
+    /// Whether `self` needs parentheses as an operand of `op`: if it binds less tightly,
+    /// or, on the `right`, as tightly, as the operators are left-associative.
+    fn needs_parens_as_operand_of(&self, op: BinOp, right: bool) -> bool {
+        match self.data() {
+            ExprData::Binary(_, op1, _) => {
+                op1.precedence() < op.precedence() || (right && op1.precedence() == op.precedence())
+            }
+            ExprData::Closure(..) => true,
+            _ => false,
+        }
+    }
+}
+
+#[term]
+pub enum ExprData {
+    /// Constants like `22_u32` or `true`, as well as const generics in scope.
+    #[cast]
+    Literal(Const),
+
+    /// Numeric literals without a suffix, like `22` or `1.5`, whose type is inferred.
+    #[cast]
+    Numeric(NumericLit),
+
+    #[cast]
+    Var(ValueId),
+
+    #[grammar($v0 ($,v1))]
+    Call(FnId, Vec<Expr>),
+
+    #[grammar($v0 . $v1)]
+    Field(Expr, FieldName),
+
+    #[grammar(& $v0)]
+    Ref(Expr),
+
+    #[grammar(&mut $v0)]
+    RefMut(Expr),
+
+    #[grammar(* $v0)]
+    Deref(Expr),
+
+    /// Arithmetic like `a + b`, which is `Add::add(a, b)`; see [`BinOp`].
+    #[grammar($v0 $v1 $v2)]
+    Binary(Expr, BinOp, Expr),
+
+    /// Negation `-a` and `!a`, which are `Neg::neg(a)` and `Not::not(a)`.
+    #[grammar($v0 $v1)]
+    Unary(UnOp, Expr),
+
+    /// Indexing `a[i]`, which is `*Index::index(&a, i)`.
+    #[grammar($v0 [$v1])]
+    Index(Expr, Expr),
+
+    #[grammar(())]
+    Unit,
+
+    #[cast]
+    Block(Block),
+
+    /// Unsafe blocks `unsafe { ... }`, in which unsafe operations like calling
+    /// an `unsafe fn` are allowed.
+    #[grammar(unsafe $v0)]
+    Unsafe(Block),
+
+    #[grammar(if $v0 $v1 else $v2)]
+    If(Expr, Block, Block),
+
+    #[grammar(match $v0 { $,v1 })]
+    Match(Expr, Vec<Arm>),
+
+    #[grammar($v0 as $v1)]
+    Cast(Expr, Ty),
+
+    /// Closures `|x, y| body`, whose parameter types are inferred.
+    #[grammar($v0 |$,v1| $v2)]
+    Closure(CaptureBy, Vec<ValueId>, Expr),
+
+    /// Async blocks `async { ... }`, which evaluate to a coroutine that runs the
+    /// block when polled. They capture variables like closures do.
+    #[grammar(async $v0 $v1)]
+    Async(CaptureBy, Block),
+
+    #[grammar($v0 . await)]
+    Await(Expr),
+}
+
+/// Binary operators, each of which calls the method of an operator trait, like `Add`.
+/// These are implemented built-in for the numeric types.
+#[term]
+#[derive(Copy)]
+pub enum BinOp {
+    #[grammar(+)]
+    Add,
+    #[grammar(-)]
+    Sub,
+    #[grammar(*)]
+    Mul,
+    #[grammar(/)]
+    Div,
+    #[grammar(%)]
+    Rem,
+}
+
+impl BinOp {
+    /// The operator trait, e.g. `Add` for `+`, which takes the type of the right operand
+    /// as a parameter.
+    pub fn trait_id(self) -> TraitId {
+        match self {
+            BinOp::Add => add_trait(),
+            BinOp::Sub => sub_trait(),
+            BinOp::Mul => mul_trait(),
+            BinOp::Div => div_trait(),
+            BinOp::Rem => rem_trait(),
+        }
+    }
+
+    const MAX_PRECEDENCE: u8 = 1;
+
+    /// Operators with a higher precedence bind more tightly; all are left-associative.
+    fn precedence(self) -> u8 {
+        match self {
+            BinOp::Add | BinOp::Sub => 0,
+            BinOp::Mul | BinOp::Div | BinOp::Rem => 1,
+        }
+    }
+}
+
+/// Unary operators, which call the method of an operator trait like binary operators do.
+#[term]
+#[derive(Copy)]
+pub enum UnOp {
+    #[grammar(-)]
+    Neg,
+    #[grammar(!)]
+    Not,
+}
+
+impl UnOp {
+    /// The operator trait, e.g. `Neg` for `-`.
+    pub fn trait_id(self) -> TraitId {
+        match self {
+            UnOp::Neg => neg_trait(),
+            UnOp::Not => not_trait(),
+        }
+    }
+}
+
+/// How a closure or async block captures the variables it uses.
+#[term]
+pub enum CaptureBy {
+    /// `move` closures and async blocks capture each variable by value.
+    #[grammar(move)]
+    Value,
+
+    /// Others capture each variable by reference, by mutable reference
+    /// or by value, depending on how their body uses it.
+    #[grammar(ref)]
+    Ref,
+}
+
+/// The value of a numeric literal without a type suffix.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast)]
+pub enum NumericLit {
+    Int(u128),
+
+    /// The bits of an `f64`, so that literals can be compared and hashed.
+    Float(u64),
+}
+
+impl Term for NumericLit {}
+
+impl Pretty for NumericLit {}
+
+/// Floats are picked among ones whose `Debug` output parses back to the same bits.
+impl Generate for NumericLit {
+    fn generate(g: &mut Generator) -> Self {
+        match g.below(2) {
+            0 => NumericLit::Int(g.below(100) as u128),
+            _ => NumericLit::Float(g.choose(&[0.5, 1.25, 2.0_f64]).to_bits()),
+        }
+    }
+}
+
+impl Fold for NumericLit {
+    fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {
+        *self
+    }
+}
+
+impl Parse for NumericLit {
+    #[tracing::instrument(level = "trace", ret)]
+    fn parse<'t>(_scope: &Scope, text: &'t str) -> ParseResult<'t, Self> {
+        let text = skip_whitespace(text);
+        let (int, text1): (u128, _) = number(text)?;
+
+        // A `.` only makes a float literal if digits follow, so that `x.0.1` stays a field access.
+        let Some(fraction) = text1.strip_prefix('.') else {
+            return Ok((NumericLit::Int(int), text1));
+        };
+        let Ok((_, text2)) = number::<u128>(fraction) else {
+            return Ok((NumericLit::Int(int), text1));
+        };
+        let digits = &text[..text.len() - text2.len()];
+        match digits.parse::<f64>() {
+            Ok(f) => Ok((NumericLit::Float(f.to_bits()), text2)),
+            Err(err) => Err(ParseError::at(
+                text,
+                format!("could not parse number: {err}"),
+            )),
+        }
+    }
+}
+
+impl std::fmt::Debug for NumericLit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NumericLit::Int(n) => write!(f, "{n}"),
+            NumericLit::Float(bits) => write!(f, "{:?}", f64::from_bits(*bits)),
+        }
+    }
+}
+
+#[term($pattern => $body)]
+pub struct Arm {
+    pub pattern: Pattern,
+    pub body: Expr,
+}
+
+/// A block `{ stmt* tail? }`. Without a tail expression the block has type `()`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub tail: Option<Expr>,
+}
+
+impl Block {
+    /// Splits off the first statement, returning it along with the remainder of the block.
+    pub fn split_first(&self) -> Option<(Stmt, Block)> {
+        let (first, rest) = self.stmts.split_first()?;
+        Some((
+            first.clone(),
+            Block {
+                stmts: rest.to_vec(),
+                tail: self.tail.clone(),
+            },
+        ))
+    }
+}
+
+#[term]
+pub enum Stmt {
+    #[grammar(let $v0 = $v1 ;)]
+    Let(ValueId, Expr),
+
+    #[grammar(let $v0 : $v1 = $v2 ;)]
+    LetTy(ValueId, Ty, Expr),
+
+    #[grammar($v0 ;)]
+    Expr(Expr),
+}
+
+impl Term for Expr {}
+impl Term for Block {}
+
+/// Expressions as in `Debug`, with the variables bound outside of them named.
+impl Pretty for Expr {
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        let operand = |e: &Expr, parens: bool| {
+            if parens {
+                Doc::delimited('(', vec![e.pretty(cx)], ')')
+            } else {
+                e.pretty(cx)
+            }
+        };
+        let mut b = DocBuilder::default();
+        match self.data() {
+            ExprData::Literal(c) => b.push(c.pretty(cx)),
+            ExprData::Numeric(n) => b.push(n.pretty(cx)),
+            ExprData::Var(v) => b.push(v.pretty(cx)),
+            ExprData::Call(id, args) => {
+                b.push(id.pretty(cx));
+                b.push(Doc::delimited(
+                    '(',
+                    Doc::comma_separated(args.iter().map(|a| a.pretty(cx))),
+                    ')',
+                ));
+            }
+            ExprData::Field(base, field) => {
+                b.push(operand(base, base.needs_parens_as_postfix_operand()));
+                b.text(".");
+                b.push(field.pretty(cx));
+            }
+            ExprData::Ref(e) => {
+                b.text("&");
+                b.push(operand(e, e.needs_parens_as_prefix_operand()));
+            }
+            ExprData::RefMut(e) => {
+                b.text("&mut");
+                b.push(operand(e, e.needs_parens_as_prefix_operand()));
+            }
+            ExprData::Deref(e) => {
+                b.text("*");
+                b.push(operand(e, e.needs_parens_as_prefix_operand()));
+            }
+            ExprData::Binary(lhs, op, rhs) => {
+                b.push(operand(lhs, lhs.needs_parens_as_operand_of(*op, false)));
+                b.push(op.pretty(cx));
+                b.push(operand(rhs, rhs.needs_parens_as_operand_of(*op, true)));
+            }
+            ExprData::Unary(op, e) => {
+                b.push(op.pretty(cx));
+                b.glue();
+                b.push(operand(e, e.needs_parens_as_prefix_operand()));
+            }
+            ExprData::Index(base, index) => {
+                b.push(operand(base, base.needs_parens_as_postfix_operand()));
+                b.glue();
+                b.push(Doc::delimited('[', vec![index.pretty(cx)], ']'));
+            }
+            ExprData::Unit => b.text("()"),
+            ExprData::Block(block) => b.push(block.pretty(cx)),
+            ExprData::Unsafe(block) => {
+                b.text("unsafe");
+                b.push(block.pretty(cx));
+            }
+            ExprData::If(c, t, e) => {
+                b.text("if");
+                b.push(c.pretty(cx));
+                b.push(t.pretty(cx));
+                b.text("else");
+                b.push(e.pretty(cx));
+            }
+            ExprData::Match(scrutinee, arms) => {
+                b.text("match");
+                b.push(scrutinee.pretty(cx));
+                b.push(Doc::delimited(
+                    '{',
+                    Doc::comma_separated(arms.iter().map(|a| a.pretty(cx))),
+                    '}',
+                ));
+            }
+            ExprData::Cast(e, ty) => {
+                b.push(operand(e, e.needs_parens_as_cast_operand()));
+                b.text("as");
+                b.push(ty.pretty(cx));
+            }
+            ExprData::Closure(capture_by, params, body) => {
+                if let CaptureBy::Value = capture_by {
+                    b.text("move");
+                }
+                b.text("|");
+                b.glue();
+                b.push(Doc::list(params.iter().map(|p| p.pretty(cx))));
+                b.glue();
+                b.text("|");
+                b.push(body.pretty(cx));
+            }
+            ExprData::Async(capture_by, block) => {
+                b.text("async");
+                if let CaptureBy::Value = capture_by {
+                    b.text("move");
+                }
+                b.push(block.pretty(cx));
+            }
+            ExprData::Await(e) => {
+                b.push(operand(e, e.needs_parens_as_postfix_operand()));
+                b.text(".await");
+            }
+        }
+        b.group()
+    }
+}
+
+impl Generate for Expr {
+    fn generate(g: &mut Generator) -> Self {
+        Expr::new(ExprData::generate(g))
+    }
+}
+
+impl Generate for Block {
+    fn generate(g: &mut Generator) -> Self {
+        g.nested(|g| Block {
+            stmts: Generate::generate(g),
+            tail: Generate::generate(g),
+        })
+    }
+}
+
+/// The statements of a block each on a line of their own, if the block does not fit on one.
+impl Pretty for Block {
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        let mut b = DocBuilder::default();
+        for (stmt, i) in self.stmts.iter().zip(0..) {
+            if i > 0 {
+                b.line();
+            }
+            b.push(stmt.pretty(cx));
+        }
+        if let Some(tail) = &self.tail {
+            if !self.stmts.is_empty() {
+                b.line();
+            }
+            b.push(tail.pretty(cx));
+        }
+        Doc::delimited('{', b.into_docs(), '}')
+    }
+}
+
+impl UpcastFrom<ExprData> for Expr {
+    fn upcast_from(v: ExprData) -> Self {
+        Expr::new(v)
+    }
+}
+
+impl DowncastTo<ExprData> for Expr {
+    fn downcast_to(&self) -> Option<ExprData> {
+        Some(self.data().clone())
+    }
+}
+
+impl Fold for Expr {
+    fn substitute(&self, substitution_fn: SubstitutionFn<'_>) -> Self {
+        Expr::new(self.data().substitute(substitution_fn))
+    }
+}
+
+impl Fold for Block {
+    fn substitute(&self, substitution_fn: SubstitutionFn<'_>) -> Self {
+        Block {
+            stmts: self.stmts.substitute(substitution_fn),
+            tail: self.tail.substitute(substitution_fn),
+        }
+    }
+}
+
+impl std::fmt::Debug for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.data() {
+            ExprData::Literal(c) => write!(f, "{c:?}"),
+            ExprData::Numeric(n) => write!(f, "{n:?}"),
+            ExprData::Var(v) => write!(f, "{v:?}"),
+            ExprData::Call(id, args) => {
+                write!(f, "{id:?}(")?;
+                for (arg, i) in args.iter().zip(0..) {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{arg:?}")?;
+                }
+                write!(f, ")")
+            }
+            ExprData::Field(base, field) => write!(f, "{:?}.{field:?}", PostfixOperand(base)),
+            ExprData::Ref(e) => write!(f, "&{:?}", PrefixOperand(e)),
+            ExprData::RefMut(e) => write!(f, "&mut {:?}", PrefixOperand(e)),
+            ExprData::Deref(e) => write!(f, "*{:?}", PrefixOperand(e)),
+            ExprData::Binary(lhs, op, rhs) => {
+                if lhs.needs_parens_as_operand_of(*op, false) {
+                    write!(f, "({lhs:?})")?;
+                } else {
+                    write!(f, "{lhs:?}")?;
+                }
+                write!(f, " {op:?} ")?;
+                if rhs.needs_parens_as_operand_of(*op, true) {
+                    write!(f, "({rhs:?})")
+                } else {
+                    write!(f, "{rhs:?}")
+                }
+            }
+            ExprData::Unary(op, e) => write!(f, "{op:?}{:?}", PrefixOperand(e)),
+            ExprData::Index(base, index) => write!(f, "{:?}[{index:?}]", PostfixOperand(base)),
+            ExprData::Unit => write!(f, "()"),
+            ExprData::Block(b) => write!(f, "{b:?}"),
+            ExprData::Unsafe(b) => write!(f, "unsafe {b:?}"),
+            ExprData::If(c, t, e) => write!(f, "if {c:?} {t:?} else {e:?}"),
+            ExprData::Match(scrutinee, arms) => {
+                write!(f, "match {scrutinee:?} {{")?;
+                for (Arm { pattern, body }, i) in arms.iter().zip(0..) {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, " {pattern:?} => {body:?}")?;
+                }
+                write!(f, " }}")
+            }
+            ExprData::Cast(e, ty) => {
+                if e.needs_parens_as_cast_operand() {
+                    write!(f, "({e:?}) as {ty:?}")
+                } else {
+                    write!(f, "{e:?} as {ty:?}")
+                }
+            }
+            ExprData::Closure(capture_by, params, body) => {
+                if let CaptureBy::Value = capture_by {
+                    write!(f, "move ")?;
+                }
+                write!(f, "|")?;
+                for (param, i) in params.iter().zip(0..) {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{param:?}")?;
+                }
+                write!(f, "| {body:?}")
+            }
+            ExprData::Async(capture_by, block) => {
+                if let CaptureBy::Value = capture_by {
+                    write!(f, "async move {block:?}")
+                } else {
+                    write!(f, "async {block:?}")
+                }
+            }
+            ExprData::Await(e) => write!(f, "{:?}.await", PostfixOperand(e)),
+        }
+    }
+}
+
+/// Prints the operand of a field access, indexing or `.await`.
+struct PostfixOperand<'e>(&'e Expr);
+
+impl std::fmt::Debug for PostfixOperand<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.needs_parens_as_postfix_operand() {
+            write!(f, "({:?})", self.0)
+        } else {
+            write!(f, "{:?}", self.0)
+        }
+    }
+}
+
+/// Prints the operand of a prefix operator.
+struct PrefixOperand<'e>(&'e Expr);
+
+impl std::fmt::Debug for PrefixOperand<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.needs_parens_as_prefix_operand() {
+            write!(f, "({:?})", self.0)
+        } else {
+            write!(f, "{:?}", self.0)
+        }
+    }
+}
+
+impl std::fmt::Debug for Block {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{")?;
+        for stmt in &self.stmts {
+            write!(f, " {stmt:?}")?;
+        }
+        if let Some(tail) = &self.tail {
+            write!(f, " {tail:?}")?;
+        }
+        write!(f, " }}")
+    }
+}
+
+impl Parse for Expr {
+    #[tracing::instrument(level = "trace", ret)]
+    fn parse<'t>(scope: &Scope, text: &'t str) -> ParseResult<'t, Self> {
+        parse_binary(scope, text, 0)
+    }
+}
+
+/// Parses binary operators of at least the given precedence, which bind less tightly than `as`.
+#[tracing::instrument(level = "trace", ret)]
+fn parse_binary<'t>(scope: &Scope, text: &'t str, precedence: u8) -> ParseResult<'t, Expr> {
+    if precedence > BinOp::MAX_PRECEDENCE {
+        return parse_cast(scope, text);
+    }
+    let (mut expr, mut text) = parse_binary(scope, text, precedence + 1)?;
+    while let Ok((op, text1)) = BinOp::parse(scope, text) {
+        if op.precedence() != precedence {
+            break;
+        }
+        let (rhs, text1) = parse_binary(scope, text1, precedence + 1)?;
+        expr = ExprData::Binary(expr, op, rhs).upcast();
+        text = text1;
+    }
+    Ok((expr, text))
+}
+
+/// Parses casts `e as T`, which bind more tightly than binary operators.
+#[tracing::instrument(level = "trace", ret)]
+fn parse_cast<'t>(scope: &Scope, text: &'t str) -> ParseResult<'t, Expr> {
+    let (mut expr, mut text) = parse_unary(scope, text)?;
+    while let Ok(((), text1)) = expect_keyword("as", text) {
+        let (ty, text1) = Ty::parse(scope, text1)?;
+        expr = ExprData::Cast(expr, ty).upcast();
+        text = text1;
+    }
+    Ok((expr, text))
+}
+
+/// Parses prefix operators, field accesses, indexing and `.await`, which bind more tightly than `as`.
+#[tracing::instrument(level = "trace", ret)]
+fn parse_unary<'t>(scope: &Scope, text: &'t str) -> ParseResult<'t, Expr> {
+    if let Ok(((), text)) = expect_char('&', text) {
+        if let Ok(((), text)) = expect_keyword("mut", text) {
+            let (e, text) = parse_unary(scope, text)?;
+            return Ok((ExprData::RefMut(e).upcast(), text));
+        }
+        let (e, text) = parse_unary(scope, text)?;
+        return Ok((ExprData::Ref(e).upcast(), text));
+    }
+
+    if let Ok(((), text)) = expect_char('*', text) {
+        let (e, text) = parse_unary(scope, text)?;
+        return Ok((ExprData::Deref(e).upcast(), text));
+    }
+
+    if let Ok((op, text)) = UnOp::parse(scope, text) {
+        let (e, text) = parse_unary(scope, text)?;
+        return Ok((ExprData::Unary(op, e).upcast(), text));
+    }
+
+    let (mut expr, mut text) = parse_atom(scope, text)?;
+    loop {
+        if let Ok(((), text1)) = expect_char('[', text) {
+            let (index, text1) = Expr::parse(scope, text1)?;
+            let ((), text1) = expect_char(']', text1)?;
+            expr = ExprData::Index(expr, index).upcast();
+            text = text1;
+            continue;
+        }
+        let Ok(((), text1)) = expect_char('.', text) else {
+            return Ok((expr, text));
+        };
+        if let Ok(((), text1)) = expect_keyword("await", text1) {
+            expr = ExprData::Await(expr).upcast();
+            text = text1;
+            continue;
+        }
+        let (field, text1) = FieldName::parse(scope, text1)?;
+        expr = ExprData::Field(expr, field).upcast();
+        text = text1;
+    }
+}
+
+#[tracing::instrument(level = "trace", ret)]
+fn parse_atom<'t>(scope: &Scope, text0: &'t str) -> ParseResult<'t, Expr> {
+    let text = skip_whitespace(text0);
+
+    if let Ok(((), text1)) = expect_char('(', text) {
+        if let Ok(((), text1)) = expect_char(')', text1) {
+            return Ok((ExprData::Unit.upcast(), text1));
+        }
+        let (e, text1) = Expr::parse(scope, text1)?;
+        let ((), text1) = expect_char(')', text1)?;
+        return Ok((e, text1));
+    }
+
+    if text.starts_with('{') {
+        let (b, text1) = Block::parse(scope, text)?;
+        return Ok((b.upcast(), text1));
+    }
+
+    if let Ok(((), text1)) = expect_keyword("unsafe", text) {
+        let (b, text1) = Block::parse(scope, text1)?;
+        return Ok((ExprData::Unsafe(b).upcast(), text1));
+    }
+
+    if let Ok(((), text1)) = expect_keyword("if", text) {
+        let (c, text1) = Expr::parse(scope, text1)?;
+        let (t, text1) = Block::parse(scope, text1)?;
+        let ((), text1) = expect_keyword("else", text1)?;
+        let (e, text1) = Block::parse(scope, text1)?;
+        return Ok((ExprData::If(c, t, e).upcast(), text1));
+    }
+
+    if let Ok(((), text1)) = expect_keyword("match", text) {
+        let (scrutinee, text1) = Expr::parse(scope, text1)?;
+        let ((), text1) = expect_char('{', text1)?;
+        let (arms, text1) = Arm::parse_comma(scope, text1, '}')?;
+        let ((), text1) = expect_char('}', text1)?;
+        return Ok((ExprData::Match(scrutinee, arms).upcast(), text1));
+    }
+
+    // Numeric literals with a suffix, like `22_u32`, are constants.
+    if text.starts_with(|c: char| c.is_ascii_digit()) {
+        let suffixed = text.trim_start_matches(|c: char| c.is_ascii_digit());
+        if !suffixed.starts_with('_') {
+            let (n, text1) = NumericLit::parse(scope, text)?;
+            return Ok((n.upcast(), text1));
+        }
+    }
+
+    if text.starts_with(|c: char| c.is_ascii_digit())
+        || expect_keyword("true", text).is_ok()
+        || expect_keyword("false", text).is_ok()
+    {
+        let (c, text1) = Const::parse(scope, text)?;
+        return Ok((c.upcast(), text1));
+    }
+
+    if let Ok(((), text1)) = expect_keyword("async", text) {
+        let (capture_by, text1) = match expect_keyword("move", text1) {
+            Ok(((), text1)) => (CaptureBy::Value, text1),
+            Err(_) => (CaptureBy::Ref, text1),
+        };
+        let (block, text1) = Block::parse(scope, text1)?;
+        return Ok((ExprData::Async(capture_by, block).upcast(), text1));
+    }
+
+    let (capture_by, text1) = match expect_keyword("move", text) {
+        Ok(((), text1)) => (CaptureBy::Value, text1),
+        Err(_) => (CaptureBy::Ref, text),
+    };
+    if let Ok(((), text1)) = expect_char('|', text1) {
+        let (params, text1) = ValueId::parse_comma(scope, text1, '|')?;
+        let ((), text1) = expect_char('|', text1)?;
+        let (body, text1) = Expr::parse(scope, text1)?;
+        return Ok((ExprData::Closure(capture_by, params, body).upcast(), text1));
+    }
+
+    let (name, text1) = identifier(text)?;
+    if KEYWORDS.contains(&&name[..]) {
+        return Err(ParseError::at(text, format!("found keyword `{name}`")));
+    }
+
+    // Const generics in scope can be used as values.
+    if let Some(Parameter::Const(c)) = scope.lookup(&name) {
+        return Ok((c.upcast(), text1));
+    }
+
+    // Items in modules are named by paths, like `m::f`.
+    let (name, text1) = path(text)?;
+
+    if let Ok(((), text2)) = expect_char('(', text1) {
+        let (args, text2) = Expr::parse_comma(scope, text2, ')')?;
+        let ((), text2) = expect_char(')', text2)?;
+        return Ok((ExprData::Call(FnId::new(&name), args).upcast(), text2));
+    }
+
+    Ok((ValueId::new(&name).upcast(), text1))
+}
+
+impl Parse for Block {
+    #[tracing::instrument(level = "trace", ret)]
+    fn parse<'t>(scope: &Scope, text: &'t str) -> ParseResult<'t, Self> {
+        let ((), mut text) = expect_char('{', text)?;
+        let mut stmts = vec![];
+        loop {
+            if let Ok(((), text1)) = expect_char('}', text) {
+                return Ok((Block { stmts, tail: None }, text1));
+            }
+
+            if let Ok((stmt, text1)) = Stmt::parse(scope, text) {
+                stmts.push(stmt);
+                text = text1;
+                continue;
+            }
+
+            let (tail, text1) = Expr::parse(scope, text)?;
+            let ((), text1) = expect_char('}', text1)?;
+            return Ok((
+                Block {
+                    stmts,
+                    tail: Some(tail),
+                },
+                text1,
+            ));
+        }
+    }
+}
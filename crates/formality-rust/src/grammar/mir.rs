@@ -1,8 +1,8 @@
-use super::VariantId;
+use super::{FieldName, VariantId};
 use formality_macros::term;
 use formality_types::{
     cast::{Downcast, DowncastFrom, Upcast},
-    grammar::{AdtId, Binder, FieldId, FnId, Lt, Parameter, RefKind, Ty},
+    grammar::{AdtId, Binder, Const, FnId, Lt, Parameter, RefKind, Ty},
 };
 
 #[term(mir($binder))]
@@ -12,7 +12,8 @@ pub struct MirFnBody {
     pub binder: Binder<LocalsAndBlocks>,
 }
 
-#[term]
+/// The first local is the return place; it is followed by one local per fn input.
+#[term($local_decls $basic_block_decls)]
 pub struct LocalsAndBlocks {
     pub local_decls: Vec<LocalDecl>,
     pub basic_block_decls: Vec<BasicBlockDecl>,
@@ -28,7 +29,7 @@ pub struct LocalDecl {
 formality_types::id!(LocalId);
 formality_types::id!(BasicBlockId);
 
-#[term]
+#[term($id : { $*statements } $terminator)]
 pub struct BasicBlockDecl {
     pub id: BasicBlockId,
     pub statements: Vec<Statement>,
@@ -49,6 +50,12 @@ pub enum Rvalue {
     Use(Operand),
     Repeat(Operand, Constant),
     Ref(Lt, RefKind, Place),
+
+    /// A two-phase mutable borrow, as for the receiver of `v.push(v.len())`:
+    /// the borrow is only *reserved* here, acting like a shared borrow, and is
+    /// *activated* at the first use of the local it is assigned to.
+    TwoPhaseRef(Lt, Place),
+
     AddrOf(RefKind, Place),
     Len(Place),
     Apply(Operand, BinaryOp, Operand),
@@ -77,15 +84,16 @@ pub enum BinaryMathOp {
     Divide,
 }
 
+/// Spelled as keywords, as `<` would be a prefix of `<=`.
 #[term]
 pub enum BinaryComparisonOp {
-    #[grammar(<)]
+    #[grammar(lt)]
     LessThan,
-    #[grammar(<=)]
+    #[grammar(le)]
     LessEqual,
-    #[grammar(>)]
+    #[grammar(gt)]
     GreaterThan,
-    #[grammar(>=)]
+    #[grammar(ge)]
     GreaterEqual,
 }
 
@@ -116,9 +124,9 @@ pub enum Operand {
 
 #[term]
 pub enum Constant {
-    Number(usize),
-    True,
-    False,
+    /// Scalar values like `22_u32` or `true`.
+    #[cast]
+    Value(Const),
     FnPtr(FnId, Vec<Parameter>),
     Tuple(Vec<Constant>),
 }
@@ -135,7 +143,7 @@ pub enum Projection {
     Deref,
 
     #[grammar($v0)]
-    Field(FieldId),
+    Field(FieldName),
 
     #[grammar([$v0])]
     Index(LocalId),
@@ -0,0 +1,347 @@
+//! Patterns, as used in the arms of `match` expressions.
+//!
+//! Like expressions, patterns get a handwritten parser, as a binding `x`
+//! and an ADT pattern `Foo { .. }` cannot be told apart by the derived one.
+
+use std::sync::Arc;
+
+use formality_macros::{term, Cast, Visit};
+use serde::{Deserialize, Serialize};
+use formality_types::{
+    cast::{DowncastTo, Upcast, UpcastFrom},
+    fold::{Fold, SubstitutionFn},
+    generate::{Generate, Generator},
+    grammar::{AdtId, Const, Parameter},
+    parse::{
+        expect_char, expect_keyword, identifier, path, skip_whitespace, Parse, ParseError,
+        ParseResult, Scope,
+    },
+    pretty::{Doc, DocBuilder, Pretty, PrettyCx},
+    term::Term,
+};
+
+use super::{
+    expr::{ValueId, KEYWORDS},
+    FieldName, Variant, VariantId,
+};
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast)]
+#[serde(transparent)]
+#[cast(ValueId <: PatternData, Const <: PatternData, AdtPattern <: PatternData)]
+pub struct Pattern {
+    data: Arc<PatternData>,
+}
+
+impl Pattern {
+    pub fn new(data: impl Upcast<PatternData>) -> Self {
+        Pattern {
+            data: Arc::new(data.upcast()),
+        }
+    }
+
+    pub fn data(&self) -> &PatternData {
+        &self.data
+    }
+
+    /// The variables bound by this pattern.
+    pub fn bindings(&self) -> Vec<ValueId> {
+        match self.data() {
+            PatternData::Wildcard | PatternData::Literal(_) => vec![],
+            PatternData::Binding(id) => vec![id.clone()],
+            PatternData::Tuple(pats) => pats.iter().flat_map(|p| p.bindings()).collect(),
+            PatternData::Adt(AdtPattern { fields, .. }) => {
+                fields.iter().flat_map(|f| f.pattern.bindings()).collect()
+            }
+            PatternData::Ref(p) | PatternData::RefMut(p) => p.bindings(),
+        }
+    }
+}
+
+#[term]
+pub enum PatternData {
+    #[grammar(_)]
+    Wildcard,
+
+    /// Constants like `22_u32` or `true`, as well as const generics in scope.
+    #[cast]
+    Literal(Const),
+
+    #[cast]
+    Binding(ValueId),
+
+    #[grammar(($,v0))]
+    Tuple(Vec<Pattern>),
+
+    #[cast]
+    Adt(AdtPattern),
+
+    #[grammar(& $v0)]
+    Ref(Pattern),
+
+    #[grammar(&mut $v0)]
+    RefMut(Pattern),
+}
+
+/// `Enum::Variant { field: pattern, ... }` or, for structs, `Struct { field: pattern, ... }`.
+/// Fields can also be given positionally, as in `Enum::Variant(pattern, ...)`.
+#[term($adt_id :: $variant_id { $,fields })]
+pub struct AdtPattern {
+    pub adt_id: AdtId,
+    pub variant_id: VariantId,
+    pub fields: Vec<FieldPattern>,
+}
+
+impl AdtPattern {
+    /// Returns the field patterns in the order of the fields of `variant`,
+    /// or `None` unless each field of `variant` is matched exactly once.
+    pub fn fields_in_order(&self, variant: &Variant) -> Option<Vec<Pattern>> {
+        if self.fields.len() != variant.fields.len() {
+            return None;
+        }
+        variant
+            .fields
+            .iter()
+            .map(|field| {
+                let mut matching = self.fields.iter().filter(|f| f.name == field.name);
+                match (matching.next(), matching.next()) {
+                    (Some(f), None) => Some(f.pattern.clone()),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+#[term($name : $pattern)]
+pub struct FieldPattern {
+    pub name: FieldName,
+    pub pattern: Pattern,
+}
+
+impl Term for Pattern {}
+
+/// Patterns as in `Debug`, with the variables bound outside of them named.
+impl Pretty for Pattern {
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        let mut b = DocBuilder::default();
+        match self.data() {
+            PatternData::Wildcard => b.text("_"),
+            PatternData::Literal(c) => b.push(c.pretty(cx)),
+            PatternData::Binding(v) => b.push(v.pretty(cx)),
+            PatternData::Tuple(pats) => {
+                let mut docs = Doc::comma_separated(pats.iter().map(|p| p.pretty(cx)));
+                // `(p,)`, as `(p)` is just `p`
+                if pats.len() == 1 {
+                    docs.push(Doc::text(","));
+                }
+                b.push(Doc::delimited('(', docs, ')'));
+            }
+            PatternData::Adt(AdtPattern {
+                adt_id,
+                variant_id,
+                fields,
+            }) => {
+                b.push(adt_id.pretty(cx));
+                if *variant_id != VariantId::for_struct() {
+                    b.text("::");
+                    b.push(variant_id.pretty(cx));
+                }
+                b.push(Doc::delimited(
+                    '{',
+                    Doc::comma_separated(fields.iter().map(|f| f.pretty(cx))),
+                    '}',
+                ));
+            }
+            PatternData::Ref(p) => {
+                b.text("&");
+                b.push(p.pretty(cx));
+            }
+            PatternData::RefMut(p) => {
+                b.text("&mut");
+                b.push(p.pretty(cx));
+            }
+        }
+        b.group()
+    }
+}
+
+impl Generate for Pattern {
+    fn generate(g: &mut Generator) -> Self {
+        Pattern::new(PatternData::generate(g))
+    }
+}
+
+impl UpcastFrom<PatternData> for Pattern {
+    fn upcast_from(v: PatternData) -> Self {
+        Pattern::new(v)
+    }
+}
+
+impl DowncastTo<PatternData> for Pattern {
+    fn downcast_to(&self) -> Option<PatternData> {
+        Some(self.data().clone())
+    }
+}
+
+impl Fold for Pattern {
+    fn substitute(&self, substitution_fn: SubstitutionFn<'_>) -> Self {
+        Pattern::new(self.data().substitute(substitution_fn))
+    }
+}
+
+impl std::fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.data() {
+            PatternData::Wildcard => write!(f, "_"),
+            PatternData::Literal(c) => write!(f, "{c:?}"),
+            PatternData::Binding(v) => write!(f, "{v:?}"),
+            PatternData::Tuple(pats) => {
+                write!(f, "(")?;
+                for (pat, i) in pats.iter().zip(0..) {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{pat:?}")?;
+                }
+                if pats.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            PatternData::Adt(AdtPattern {
+                adt_id,
+                variant_id,
+                fields,
+            }) => {
+                if *variant_id == VariantId::for_struct() {
+                    write!(f, "{adt_id:?} {{")?;
+                } else {
+                    write!(f, "{adt_id:?}::{variant_id:?} {{")?;
+                }
+                for (FieldPattern { name, pattern }, i) in fields.iter().zip(0..) {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, " {name:?}: {pattern:?}")?;
+                }
+                write!(f, " }}")
+            }
+            PatternData::Ref(p) => write!(f, "&{p:?}"),
+            PatternData::RefMut(p) => write!(f, "&mut {p:?}"),
+        }
+    }
+}
+
+impl Parse for Pattern {
+    #[tracing::instrument(level = "trace", ret)]
+    fn parse<'t>(scope: &Scope, text: &'t str) -> ParseResult<'t, Self> {
+        if let Ok(((), text)) = expect_char('&', text) {
+            if let Ok(((), text)) = expect_keyword("mut", text) {
+                let (p, text) = Pattern::parse(scope, text)?;
+                return Ok((PatternData::RefMut(p).upcast(), text));
+            }
+            let (p, text) = Pattern::parse(scope, text)?;
+            return Ok((PatternData::Ref(p).upcast(), text));
+        }
+
+        let text = skip_whitespace(text);
+
+        // `(p)` is just a parenthesized pattern; `()`, `(p,)` and `(p, q)` are tuples.
+        if let Ok(((), text1)) = expect_char('(', text) {
+            let (mut pats, text2) = Pattern::parse_comma(scope, text1, ')')?;
+            let trailing_comma = text1[..text1.len() - text2.len()].trim_end().ends_with(',');
+            let ((), text1) = expect_char(')', text2)?;
+            if pats.len() == 1 && !trailing_comma {
+                return Ok((pats.pop().unwrap(), text1));
+            }
+            return Ok((PatternData::Tuple(pats).upcast(), text1));
+        }
+
+        if text.starts_with(|c: char| c.is_ascii_digit())
+            || expect_keyword("true", text).is_ok()
+            || expect_keyword("false", text).is_ok()
+        {
+            let (c, text1) = Const::parse(scope, text)?;
+            return Ok((c.upcast(), text1));
+        }
+
+        let (name, text1) = identifier(text)?;
+        if name == "_" {
+            return Ok((PatternData::Wildcard.upcast(), text1));
+        }
+        if KEYWORDS.contains(&&name[..]) {
+            return Err(ParseError::at(text, format!("found keyword `{name}`")));
+        }
+
+        // The last segment of a path names the variant; for a struct in a module,
+        // like `m::Point { .. }`, name resolution fixes this up.
+        let (path, text1) = path(text)?;
+        if let Some((adt, variant)) = path.rsplit_once("::") {
+            let (fields, text2) = parse_field_patterns(scope, text1)?;
+            let pat = AdtPattern {
+                adt_id: AdtId::new(adt),
+                variant_id: VariantId::new(variant),
+                fields,
+            };
+            return Ok((pat.upcast(), text2));
+        }
+
+        let text2 = skip_whitespace(text1);
+        if text2.starts_with('{') || text2.starts_with('(') {
+            let (fields, text2) = parse_field_patterns(scope, text2)?;
+            let pat = AdtPattern {
+                adt_id: AdtId::new(&name),
+                variant_id: VariantId::for_struct(),
+                fields,
+            };
+            return Ok((pat.upcast(), text2));
+        }
+
+        // Const generics in scope can be matched against.
+        if let Some(Parameter::Const(c)) = scope.lookup(&name) {
+            return Ok((c.upcast(), text1));
+        }
+
+        Ok((ValueId::new(&name).upcast(), text1))
+    }
+}
+
+/// Parses `{ name: pattern, name, ... }` (where `name` alone binds the field to a variable
+/// of the same name), `(pattern, ...)`, or nothing (for unit variants).
+fn parse_field_patterns<'t>(scope: &Scope, text: &'t str) -> ParseResult<'t, Vec<FieldPattern>> {
+    if let Ok(((), text)) = expect_char('(', text) {
+        let (pats, text) = Pattern::parse_comma(scope, text, ')')?;
+        let ((), text) = expect_char(')', text)?;
+        let fields = pats
+            .into_iter()
+            .enumerate()
+            .map(|(i, pattern)| FieldPattern {
+                name: FieldName::Index(i),
+                pattern,
+            })
+            .collect();
+        return Ok((fields, text));
+    }
+
+    let Ok(((), mut text)) = expect_char('{', text) else {
+        return Ok((vec![], text));
+    };
+    let mut fields = vec![];
+    loop {
+        if let Ok(((), text1)) = expect_char('}', text) {
+            return Ok((fields, text1));
+        }
+        if !fields.is_empty() {
+            let ((), text1) = expect_char(',', text)?;
+            text = text1;
+        }
+        let (name, text1) = FieldName::parse(scope, text)?;
+        let (pattern, text1) = match (expect_char(':', text1), &name) {
+            (Ok(((), text1)), _) => Pattern::parse(scope, text1)?,
+            (Err(_), FieldName::Id(id)) => (ValueId::new(id).upcast(), text1),
+            (Err(e), FieldName::Index(_)) => return Err(e),
+        };
+        fields.push(FieldPattern { name, pattern });
+        text = text1;
+    }
+}
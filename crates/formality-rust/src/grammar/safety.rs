@@ -0,0 +1,60 @@
+//! The `unsafe` marker on fns, traits and impls.
+//!
+//! Like visibility, safety gets a handwritten parser, as items without
+//! an `unsafe` are safe and so the marker may be omitted entirely.
+
+use formality_macros::{Cast, Visit};
+use serde::{Deserialize, Serialize};
+use formality_types::{
+    fold::{Fold, SubstitutionFn},
+    generate::{Generate, Generator},
+    parse::{expect_keyword, Parse, ParseResult, Scope},
+    pretty::Pretty,
+    term::Term,
+};
+
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast)]
+pub enum Safety {
+    /// Items without an `unsafe`.
+    #[default]
+    Safe,
+
+    /// `unsafe fn`s can only be called from unsafe code, `unsafe trait`s have requirements
+    /// that the compiler cannot check and so can only be implemented by an `unsafe impl`.
+    Unsafe,
+}
+
+impl Term for Safety {}
+
+impl Pretty for Safety {}
+
+impl Generate for Safety {
+    fn generate(g: &mut Generator) -> Self {
+        g.choose(&[Safety::Safe, Safety::Unsafe])
+    }
+}
+
+impl Fold for Safety {
+    fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {
+        *self
+    }
+}
+
+impl Parse for Safety {
+    #[tracing::instrument(level = "trace", ret)]
+    fn parse<'t>(_scope: &Scope, text: &'t str) -> ParseResult<'t, Self> {
+        match expect_keyword("unsafe", text) {
+            Ok(((), text)) => Ok((Safety::Unsafe, text)),
+            Err(_) => Ok((Safety::Safe, text)),
+        }
+    }
+}
+
+impl std::fmt::Debug for Safety {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Safety::Safe => Ok(()),
+            Safety::Unsafe => write!(f, "unsafe"),
+        }
+    }
+}
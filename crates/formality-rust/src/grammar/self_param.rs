@@ -0,0 +1,69 @@
+//! The `self` parameter of methods.
+//!
+//! A method declares its receiver by writing `self :` before the type of its
+//! first input, as in `fn len<>(self: &a Self) -> usize`. The receiver can
+//! have any type that derefs to `Self`, like `Rc<Self>` (see `Relation::Receiver`).
+//! The marker gets a handwritten parser as it is omitted for fns without a receiver.
+
+use formality_macros::{Cast, Visit};
+use serde::{Deserialize, Serialize};
+use formality_types::{
+    fold::{Fold, SubstitutionFn},
+    generate::{Generate, Generator},
+    parse::{expect_char, expect_keyword, Parse, ParseResult, Scope},
+    pretty::{Doc, Pretty, PrettyCx},
+    term::Term,
+};
+
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast)]
+pub enum SelfParam {
+    /// Fns whose inputs are all ordinary parameters.
+    #[default]
+    NoSelf,
+
+    /// Methods whose first input is `self`.
+    HasSelf,
+}
+
+impl Term for SelfParam {}
+
+impl Pretty for SelfParam {
+    fn pretty(&self, _cx: &PrettyCx) -> Doc {
+        match self {
+            SelfParam::NoSelf => Doc::text(""),
+            SelfParam::HasSelf => Doc::text("self:"),
+        }
+    }
+}
+
+impl Generate for SelfParam {
+    fn generate(g: &mut Generator) -> Self {
+        g.choose(&[SelfParam::NoSelf, SelfParam::HasSelf])
+    }
+}
+
+impl Fold for SelfParam {
+    fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {
+        *self
+    }
+}
+
+impl Parse for SelfParam {
+    #[tracing::instrument(level = "trace", ret)]
+    fn parse<'t>(_scope: &Scope, text: &'t str) -> ParseResult<'t, Self> {
+        let Ok(((), text1)) = expect_keyword("self", text) else {
+            return Ok((SelfParam::NoSelf, text));
+        };
+        let ((), text1) = expect_char(':', text1)?;
+        Ok((SelfParam::HasSelf, text1))
+    }
+}
+
+impl std::fmt::Debug for SelfParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelfParam::NoSelf => Ok(()),
+            SelfParam::HasSelf => write!(f, "self :"),
+        }
+    }
+}
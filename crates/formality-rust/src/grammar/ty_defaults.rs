@@ -0,0 +1,92 @@
+//! Defaults for the type parameters of fns.
+//!
+//! Rust only accepts defaults in the generics of types and traits; on fns, they are the
+//! subject of the unstable `default_type_parameter_fallback` feature, where type
+//! inference falls back to the default if nothing else determines a type parameter.
+//! Binders have no place for defaults, so a fn lists them in a clause after its
+//! generics, as in `fn make<ty T> default [T = u32] () -> T ...`. The clause gets a
+//! handwritten parser as it is omitted for fns without defaults.
+
+use formality_macros::{term, Cast, Visit};
+use serde::{Deserialize, Serialize};
+use formality_types::{
+    fold::{Fold, SubstitutionFn},
+    generate::{Generate, Generator},
+    grammar::Ty,
+    parse::{expect_keyword, Parse, ParseResult, Scope},
+    pretty::{Doc, DocBuilder, Pretty, PrettyCx},
+    term::Term,
+};
+
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast)]
+pub struct TyDefaults {
+    pub defaults: Vec<TyDefault>,
+}
+
+/// The default `default` of the type parameter `param`.
+#[term($param = $default)]
+pub struct TyDefault {
+    pub param: Ty,
+    pub default: Ty,
+}
+
+impl TyDefaults {
+    /// The default of the type parameter `param`, if it has one.
+    pub fn default_of(&self, param: &Ty) -> Option<&Ty> {
+        self.defaults
+            .iter()
+            .find(|d| d.param == *param)
+            .map(|d| &d.default)
+    }
+}
+
+impl Term for TyDefaults {}
+
+impl Pretty for TyDefaults {
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        if self.defaults.is_empty() {
+            return Doc::text("");
+        }
+        let mut b = DocBuilder::default();
+        b.text("default");
+        b.push(self.defaults.pretty(cx));
+        b.group()
+    }
+}
+
+impl Generate for TyDefaults {
+    fn generate(g: &mut Generator) -> Self {
+        TyDefaults {
+            defaults: Generate::generate(g),
+        }
+    }
+}
+
+impl Fold for TyDefaults {
+    fn substitute(&self, substitution_fn: SubstitutionFn<'_>) -> Self {
+        TyDefaults {
+            defaults: self.defaults.substitute(substitution_fn),
+        }
+    }
+}
+
+impl Parse for TyDefaults {
+    #[tracing::instrument(level = "trace", ret)]
+    fn parse<'t>(scope: &Scope, text: &'t str) -> ParseResult<'t, Self> {
+        let Ok(((), text1)) = expect_keyword("default", text) else {
+            return Ok((TyDefaults::default(), text));
+        };
+        let (defaults, text1) = Vec::<TyDefault>::parse(scope, text1)?;
+        Ok((TyDefaults { defaults }, text1))
+    }
+}
+
+impl std::fmt::Debug for TyDefaults {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.defaults.is_empty() {
+            Ok(())
+        } else {
+            write!(f, "default {:?}", self.defaults)
+        }
+    }
+}
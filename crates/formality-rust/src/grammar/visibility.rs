@@ -0,0 +1,59 @@
+//! Visibility of items and fields.
+//!
+//! Visibility gets a handwritten parser, as items without a `pub`
+//! are private and so the visibility may be omitted entirely.
+
+use formality_macros::{Cast, Visit};
+use serde::{Deserialize, Serialize};
+use formality_types::{
+    fold::{Fold, SubstitutionFn},
+    generate::{Generate, Generator},
+    parse::{expect_keyword, Parse, ParseResult, Scope},
+    pretty::Pretty,
+    term::Term,
+};
+
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast)]
+pub enum Visibility {
+    /// `pub` items can be referred to from any crate.
+    Public,
+
+    /// Items without a `pub` can only be referred to from within their own crate.
+    #[default]
+    Private,
+}
+
+impl Term for Visibility {}
+
+impl Pretty for Visibility {}
+
+impl Generate for Visibility {
+    fn generate(g: &mut Generator) -> Self {
+        g.choose(&[Visibility::Public, Visibility::Private])
+    }
+}
+
+impl Fold for Visibility {
+    fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {
+        *self
+    }
+}
+
+impl Parse for Visibility {
+    #[tracing::instrument(level = "trace", ret)]
+    fn parse<'t>(_scope: &Scope, text: &'t str) -> ParseResult<'t, Self> {
+        match expect_keyword("pub", text) {
+            Ok(((), text)) => Ok((Visibility::Public, text)),
+            Err(_) => Ok((Visibility::Private, text)),
+        }
+    }
+}
+
+impl std::fmt::Debug for Visibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Visibility::Public => write!(f, "pub"),
+            Visibility::Private => Ok(()),
+        }
+    }
+}
@@ -1,6 +1,12 @@
 use formality_types::derive_links;
 
+pub mod cfg;
+mod dyn_compat;
+pub mod effects;
 pub mod grammar;
 pub mod prove;
+pub mod resolve;
+pub mod sized;
+pub mod source_map;
 mod test;
 mod trait_binder;
@@ -1,32 +1,70 @@
 use crate::grammar::{
-    AssociatedTy, AssociatedTyBoundData, AssociatedTyValue, AssociatedTyValueBoundData, Crate,
-    CrateItem, ImplItem, NegTraitImpl, NegTraitImplBoundData, Program, Trait, TraitBoundData,
-    TraitImpl, TraitImplBoundData, TraitItem, WhereBound, WhereBoundData, WhereClause,
-    WhereClauseData,
+    Adt, AssociatedTy, AssociatedTyBoundData, AssociatedTyValue, AssociatedTyValueBoundData, Crate,
+    CrateItem, Fn, FnBoundData, ImplItem, NegTraitImpl, NegTraitImplBoundData, Program, Trait,
+    TraitBoundData, TraitImpl, TraitImplBoundData, TraitItem, TypeAlias, TypeAliasBoundData,
+    WhereBound, WhereBoundData, WhereClause, WhereClauseData,
 };
 use formality_prove as prove;
 use formality_types::{
     cast::{To, Upcast, Upcasted},
     collections::Set,
     grammar::{
-        fresh_bound_var, AdtId, AliasTy, Binder, ParameterKind, Predicate, Relation, TraitId, Ty,
-        Wc, Wcs, PR,
+        fresh_bound_var, Abi, AdtId, AliasTy, Binder, ParameterKind, Predicate, Relation, TraitId,
+        TraitRef, Ty, Wc, Wcs, PR,
     },
     seq,
 };
 
+impl Fn {
+    fn to_fn_decl(&self, abi: Abi) -> prove::FnDecl {
+        let Fn {
+            visibility: _,
+            safety: _,
+            id,
+            binder,
+        } = self;
+        let (
+            vars,
+            FnBoundData {
+                defaults: _,
+                self_param: _,
+                input_tys,
+                output_ty,
+                where_clauses: _,
+                body: _,
+            },
+        ) = binder.open();
+        prove::FnDecl {
+            abi,
+            id: id.clone(),
+            binder: Binder::new(
+                vars,
+                prove::FnDeclBoundData {
+                    input_tys,
+                    output_ty,
+                },
+            ),
+        }
+    }
+}
+
 impl Program {
+    /// The decls that the prover proves goals about this program with. Build them once
+    /// and clone them: the clones share the index of the decls (see [`prove::DeclsIndex`]).
     pub fn to_prove_decls(&self) -> prove::Decls {
-        formality_prove::Decls {
-            max_size: formality_prove::Decls::DEFAULT_MAX_SIZE,
-            trait_decls: self.trait_decls(),
-            impl_decls: self.impl_decls(),
-            neg_impl_decls: self.neg_impl_decls(),
-            alias_eq_decls: self.alias_eq_decls(),
-            alias_bound_decls: self.alias_bound_decls(),
-            local_trait_ids: self.local_trait_ids(),
-            local_adt_ids: self.local_adt_ids(),
-        }
+        let mut decls = formality_prove::Decls::empty()
+            .with_trait_decls(self.trait_decls())
+            .with_impl_decls(self.impl_decls())
+            .with_neg_impl_decls(self.neg_impl_decls())
+            .with_alias_eq_decls(self.alias_eq_decls())
+            .with_alias_bound_decls(self.alias_bound_decls())
+            .with_type_alias_decls(self.type_alias_decls())
+            .with_fn_decls(self.fn_decls())
+            .with_adt_decls(self.adt_decls());
+        decls.local_trait_ids = self.local_trait_ids();
+        decls.local_adt_ids = self.local_adt_ids();
+        decls.dyn_compatible_trait_ids = self.dyn_compatible_trait_ids();
+        decls
     }
 
     fn trait_decls(&self) -> Vec<prove::TraitDecl> {
@@ -58,6 +96,21 @@ impl Program {
             .collect()
     }
 
+    fn type_alias_decls(&self) -> Vec<prove::TypeAliasDecl> {
+        self.crates
+            .iter()
+            .flat_map(|c| c.type_alias_decls())
+            .collect()
+    }
+
+    fn fn_decls(&self) -> Vec<prove::FnDecl> {
+        self.crates.iter().flat_map(|c| c.fn_decls()).collect()
+    }
+
+    fn adt_decls(&self) -> Vec<prove::AdtDecl> {
+        self.crates.iter().flat_map(|c| c.adt_decls()).collect()
+    }
+
     fn local_trait_ids(&self) -> Set<TraitId> {
         self.crates
             .last()
@@ -80,7 +133,13 @@ impl Crate {
         self.items
             .iter()
             .flat_map(|item| match item {
-                CrateItem::Trait(Trait { id, binder }) => {
+                CrateItem::Trait(Trait {
+                    visibility: _,
+                    safety: _,
+                    constness: _,
+                    id,
+                    binder,
+                }) => {
                     let (
                         vars,
                         TraitBoundData {
@@ -106,14 +165,82 @@ impl Crate {
             .collect()
     }
 
+    fn fn_decls(&self) -> Vec<prove::FnDecl> {
+        self.items
+            .iter()
+            .flat_map(|item| match item {
+                CrateItem::Fn(f) => vec![f.to_fn_decl(Abi::Rust)],
+                CrateItem::ExternBlock(b) => b.fns.iter().map(|f| f.to_fn_decl(b.abi)).collect(),
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    fn adt_decls(&self) -> Vec<prove::AdtDecl> {
+        self.items
+            .iter()
+            .flat_map(|item| match item {
+                CrateItem::Struct(s) => Some(s.to_adt()),
+                CrateItem::Enum(e) => Some(e.to_adt()),
+                _ => None,
+            })
+            .map(
+                |Adt {
+                     visibility: _,
+                     id,
+                     binder,
+                 }| {
+                    let (vars, data) = binder.open();
+                    prove::AdtDecl {
+                        id,
+                        binder: Binder::new(
+                            vars,
+                            prove::AdtDeclBoundData {
+                                where_clause: (&data.where_clauses, data.implied_outlives())
+                                    .to_wcs(),
+                            },
+                        ),
+                    }
+                },
+            )
+            .collect()
+    }
+
+    fn type_alias_decls(&self) -> Vec<prove::TypeAliasDecl> {
+        self.items
+            .iter()
+            .flat_map(|item| match item {
+                CrateItem::TypeAlias(TypeAlias {
+                    visibility: _,
+                    id,
+                    binder,
+                }) => {
+                    let (vars, TypeAliasBoundData { ty, where_clauses }) = binder.open();
+                    Some(prove::TypeAliasDecl {
+                        id: id.clone(),
+                        binder: Binder::new(
+                            vars,
+                            prove::TypeAliasDeclBoundData {
+                                ty,
+                                where_clause: where_clauses.to_wcs(),
+                            },
+                        ),
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
     fn impl_decls(&self) -> Vec<prove::ImplDecl> {
         self.items
             .iter()
             .flat_map(|item| match item {
-                CrateItem::TraitImpl(TraitImpl { binder }) => {
+                CrateItem::TraitImpl(TraitImpl { safety: _, binder }) => {
                     let (
                         vars,
                         TraitImplBoundData {
+                            constness: _,
                             trait_id,
                             self_ty,
                             trait_parameters,
@@ -169,10 +296,11 @@ impl Crate {
         self.items
             .iter()
             .flat_map(|item| match item {
-                CrateItem::TraitImpl(TraitImpl { binder }) => {
+                CrateItem::TraitImpl(TraitImpl { safety: _, binder }) => {
                     let (
                         impl_vars,
                         TraitImplBoundData {
+                            constness: _,
                             trait_id,
                             self_ty,
                             trait_parameters,
@@ -225,6 +353,9 @@ impl Crate {
             .iter()
             .flat_map(|item| match item {
                 CrateItem::Trait(Trait {
+                    visibility: _,
+                    safety: _,
+                    constness: _,
                     id: trait_id,
                     binder,
                 }) => {
@@ -255,6 +386,10 @@ impl Crate {
                                 (&trait_vars, &assoc_vars),
                             );
 
+                            // The bounds only hold for aliases whose trait ref holds:
+                            // `<Foo as Iterator>::Item: Debug` is not known unless `Foo: Iterator`.
+                            let trait_ref: Wc = TraitRef::new(trait_id, &trait_vars).upcast();
+
                             ensures
                                 .iter()
                                 .map(|e| {
@@ -267,7 +402,8 @@ impl Crate {
                                             prove::AliasBoundDeclBoundData {
                                                 alias: alias.clone(),
                                                 ensures,
-                                                where_clause: (&trait_wc, &assoc_wc).to_wcs(),
+                                                where_clause: (&trait_ref, &trait_wc, &assoc_wc)
+                                                    .to_wcs(),
                                             },
                                         ),
                                     }
@@ -291,6 +427,12 @@ impl Crate {
                 CrateItem::TraitImpl(_) => None,
                 CrateItem::NegTraitImpl(_) => None,
                 CrateItem::Fn(_) => None,
+                CrateItem::Static(_) => None,
+                CrateItem::Const(_) => None,
+                CrateItem::TypeAlias(_) => None,
+                CrateItem::Module(_) => None,
+                CrateItem::ExternBlock(_) => None,
+                CrateItem::Cfg(_) => None,
             })
             .collect()
     }
@@ -359,6 +501,12 @@ where
         (a, b, c).upcast()
     }
 }
+impl ToWcs for Vec<Relation> {
+    fn to_wcs(&self) -> Wcs {
+        self.iter().upcasted().collect()
+    }
+}
+
 impl ToWcs for Vec<WhereClause> {
     fn to_wcs(&self) -> Wcs {
         self.iter().flat_map(|wc| wc.to_wcs()).collect()
@@ -377,6 +525,11 @@ impl ToWcs for WhereClause {
             WhereClauseData::IsImplemented(self_ty, trait_id, parameters) => {
                 trait_id.with(self_ty, parameters).upcast()
             }
+            // Bounds on const traits are desugared by `Program::desugar_effects`;
+            // before that, they are treated like plain bounds.
+            WhereClauseData::ConstIsImplemented(self_ty, _, trait_id, parameters) => {
+                trait_id.with(self_ty, parameters).upcast()
+            }
             WhereClauseData::AliasEq(alias_ty, ty) => {
                 Predicate::AliasEq(alias_ty.clone(), ty.clone()).upcast()
             }
@@ -391,6 +544,7 @@ impl ToWcs for WhereClause {
             WhereClauseData::TypeOfConst(ct, ty) => {
                 Predicate::ConstHasType(ct.clone(), ty.clone()).upcast()
             }
+            WhereClauseData::MaybeSized(_) => Wcs::t(),
         }
     }
 }
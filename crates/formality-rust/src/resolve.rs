@@ -0,0 +1,989 @@
+//! Name resolution.
+//!
+//! Items can be nested in modules (`mod m { ... }`) and referred to by paths like `m::Foo`.
+//! Resolution flattens the modules of each crate, giving every item its full path from the
+//! crate root as its id, and rewrites each path that refers to an item to that full path.
+//! Everything after resolution thus keeps working with a single flat namespace.
+//!
+//! A path written in module `M` is resolved as follows:
+//!
+//! * `crate::a` starts from the root of the current crate, `self::a` from `M` and
+//!   `super::a` from the parent of `M`.
+//! * Otherwise the first segment is looked up in `M`. The items of `M` shadow the items at
+//!   the root of the current crate and of the crates it depends on directly (see
+//!   [`Dependencies`](`crate::grammar::dependencies::Dependencies`)), which are visible
+//!   from everywhere in the crate. The items of other crates are not visible at all.
+//! * If a segment names more than one item (e.g., two crates define `Foo` at their root and
+//!   it is used from a module that does not define `Foo` itself), the path is ambiguous.
+//!
+//! Paths that resolve to nothing are left unchanged, so that the later checks report them
+//! as before (e.g., "no fn named `f`").
+//! Items in a private module are private themselves.
+
+use std::sync::Arc;
+
+use anyhow::bail;
+use formality_types::{
+    cast::Upcast,
+    collections::Set,
+    grammar::{
+        AdtId, AliasName, AliasTy, AssociatedTyName, Binder, Const, ConstData, CrateId, Fallible,
+        FnId, Parameter, PredicateTy, RigidName, RigidTy, TraitId, Ty, TyData, TypeAliasId,
+    },
+};
+
+use crate::grammar::{
+    expr::{Arm, Block, Expr, ExprData, Stmt, ValueId},
+    pattern::{AdtPattern, FieldPattern, Pattern, PatternData},
+    ty_defaults::{TyDefault, TyDefaults},
+    visibility::Visibility,
+    AdtBoundData, AssociatedTy, AssociatedTyBoundData, AssociatedTyValue,
+    AssociatedTyValueBoundData, ConstItem, Crate, CrateItem, Enum, ExprFnBody, ExternBlock, Field,
+    Fn, FnBody, FnBoundData, ImplItem, MaybeFnBody, Module, NegTraitImpl, NegTraitImplBoundData,
+    Program, Static, Struct, StructBoundData, Trait, TraitBinder, TraitBoundData, TraitImpl,
+    TraitImplBoundData, TraitItem, TypeAlias, TypeAliasBoundData, Variant, VariantId, WhereBound,
+    WhereBoundData, WhereClause, WhereClauseData,
+};
+
+impl Program {
+    /// Resolves the paths in all crates, returning a program without modules
+    /// in which items are named by their full path.
+    pub fn resolve(&self) -> Fallible<Program> {
+        let index = ItemIndex::new(self);
+        let crates = self
+            .crates
+            .iter()
+            .enumerate()
+            .map(|(crate_index, c)| {
+                let visible_crates: Vec<usize> = self
+                    .direct_dependencies(crate_index)?
+                    .into_iter()
+                    .chain(Some(crate_index))
+                    .collect();
+                let resolver = Resolver {
+                    index: &index,
+                    crate_index,
+                    visible_crates: &visible_crates,
+                    module: vec![],
+                };
+                let mut items = vec![];
+                resolver.flatten(&c.items, Visibility::Public, &mut items)?;
+                Ok(Crate {
+                    id: c.id.clone(),
+                    edition: c.edition,
+                    dependencies: c.dependencies.clone(),
+                    items,
+                })
+            })
+            .collect::<Fallible<_>>()?;
+        Ok(Program { crates })
+    }
+}
+
+/// The kinds of items that a path can refer to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Namespace {
+    Module,
+    Adt,
+    Trait,
+    TypeAlias,
+    Fn,
+    /// `static` and `const` items.
+    Value,
+}
+
+/// An item that can be named, along with the crate and module it is defined in.
+#[derive(Debug)]
+struct Entry {
+    crate_index: usize,
+    crate_id: CrateId,
+    module: Vec<String>,
+    name: String,
+    namespace: Namespace,
+}
+
+impl Entry {
+    /// The path of this item from the root of its crate.
+    fn path(&self) -> Vec<String> {
+        self.module
+            .iter()
+            .cloned()
+            .chain(Some(self.name.clone()))
+            .collect()
+    }
+}
+
+/// All items of the program that can be named.
+struct ItemIndex {
+    entries: Vec<Entry>,
+
+    /// The ids of the crates, by index.
+    crate_ids: Vec<CrateId>,
+}
+
+impl ItemIndex {
+    fn new(program: &Program) -> Self {
+        let mut index = ItemIndex {
+            entries: vec![],
+            crate_ids: program.crates.iter().map(|c| c.id.clone()).collect(),
+        };
+        for (crate_index, c) in program.crates.iter().enumerate() {
+            index.add_items(crate_index, &c.id, &[], &c.items);
+        }
+        index
+    }
+
+    fn add_items(
+        &mut self,
+        crate_index: usize,
+        crate_id: &CrateId,
+        module: &[String],
+        items: &[CrateItem],
+    ) {
+        for item in items {
+            let (name, namespace) = match item {
+                CrateItem::Struct(s) => (s.id.to_string(), Namespace::Adt),
+                CrateItem::Enum(e) => (e.id.to_string(), Namespace::Adt),
+                CrateItem::Trait(t) => (t.id.to_string(), Namespace::Trait),
+                CrateItem::Fn(f) => (f.id.to_string(), Namespace::Fn),
+                CrateItem::Static(s) => (s.id.to_string(), Namespace::Value),
+                CrateItem::Const(c) => (c.id.to_string(), Namespace::Value),
+                CrateItem::TypeAlias(t) => (t.id.to_string(), Namespace::TypeAlias),
+                CrateItem::TraitImpl(_) | CrateItem::NegTraitImpl(_) => continue,
+                // The items gated on a feature are disabled, unless configured (see `crate::cfg`).
+                CrateItem::Cfg(_) => continue,
+                // The fns of an extern block are named as if they were declared next to it.
+                CrateItem::ExternBlock(b) => {
+                    for f in &b.fns {
+                        self.entries.push(Entry {
+                            crate_index,
+                            crate_id: crate_id.clone(),
+                            module: module.to_vec(),
+                            name: f.id.to_string(),
+                            namespace: Namespace::Fn,
+                        });
+                    }
+                    continue;
+                }
+                CrateItem::Module(m) => {
+                    let inner: Vec<String> = module
+                        .iter()
+                        .cloned()
+                        .chain(Some(m.id.to_string()))
+                        .collect();
+                    self.add_items(crate_index, crate_id, &inner, &m.items);
+                    (m.id.to_string(), Namespace::Module)
+                }
+            };
+            self.entries.push(Entry {
+                crate_index,
+                crate_id: crate_id.clone(),
+                module: module.to_vec(),
+                name,
+                namespace,
+            });
+        }
+    }
+
+    /// The items named `name` in the module `module` of crate `crate_index`.
+    fn lookup(
+        &self,
+        crate_index: usize,
+        module: &[String],
+        name: &str,
+        namespace: Namespace,
+    ) -> Vec<&Entry> {
+        self.entries
+            .iter()
+            .filter(|e| {
+                e.crate_index == crate_index
+                    && e.module == module
+                    && e.name == name
+                    && e.namespace == namespace
+            })
+            .collect()
+    }
+
+    /// The items named `name` at the root of the crates `crate_indices`.
+    fn lookup_roots(
+        &self,
+        crate_indices: impl std::ops::Fn(usize) -> bool,
+        name: &str,
+        namespace: Namespace,
+    ) -> Vec<&Entry> {
+        self.entries
+            .iter()
+            .filter(|e| {
+                crate_indices(e.crate_index)
+                    && e.module.is_empty()
+                    && e.name == name
+                    && e.namespace == namespace
+            })
+            .collect()
+    }
+}
+
+/// Resolves the paths written in one module of a crate.
+struct Resolver<'i> {
+    index: &'i ItemIndex,
+    crate_index: usize,
+
+    /// The crates whose root items are visible: the crate itself and its dependencies.
+    visible_crates: &'i [usize],
+
+    module: Vec<String>,
+}
+
+impl Resolver<'_> {
+    fn in_module(&self, name: &str) -> Self {
+        Resolver {
+            index: self.index,
+            crate_index: self.crate_index,
+            visible_crates: self.visible_crates,
+            module: self
+                .module
+                .iter()
+                .cloned()
+                .chain(Some(name.to_string()))
+                .collect(),
+        }
+    }
+
+    /// The full path of an item named `name` defined in this module.
+    fn qualify(&self, name: &str) -> String {
+        self.module
+            .iter()
+            .map(|s| &s[..])
+            .chain(Some(name))
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+
+    /// Returns the full path of the item in `namespace` that `path` refers to,
+    /// or `None` if there is no such item.
+    fn resolve(&self, path: &str, namespace: Namespace) -> Fallible<Option<String>> {
+        let segments: Vec<&str> = path.split("::").collect();
+
+        // Where lookup starts: `None` means the current module, falling back to the crate roots.
+        let (mut start, mut segments) = match segments[0] {
+            "crate" => (Some(vec![]), &segments[1..]),
+            "self" => (Some(self.module.clone()), &segments[1..]),
+            "super" => {
+                let mut module = self.module.clone();
+                let mut segments = &segments[..];
+                while let Some((&"super", rest)) = segments.split_first() {
+                    if module.pop().is_none() {
+                        bail!("too many leading `super` in `{path}`")
+                    }
+                    segments = rest;
+                }
+                (Some(module), segments)
+            }
+            _ => (None, &segments[..]),
+        };
+
+        let mut crate_index = self.crate_index;
+        let mut entry = None;
+        while let Some((segment, rest)) = segments.split_first() {
+            let ns = if rest.is_empty() {
+                namespace
+            } else {
+                Namespace::Module
+            };
+            let candidates = match &start {
+                Some(module) => self.index.lookup(crate_index, module, segment, ns),
+                None => {
+                    let local = self.index.lookup(crate_index, &self.module, segment, ns);
+                    if local.is_empty() {
+                        self.lookup_visible_roots(path, segment, ns)?
+                    } else {
+                        local
+                    }
+                }
+            };
+            let found = match &candidates[..] {
+                [] => return Ok(None),
+                [found] => *found,
+                _ => {
+                    let crate_ids: Vec<_> = candidates.iter().map(|e| &e.crate_id).collect();
+                    bail!("`{path}` is ambiguous, `{segment}` is defined in crates {crate_ids:?}")
+                }
+            };
+            crate_index = found.crate_index;
+            start = Some(found.path());
+            entry = Some(found);
+            segments = rest;
+        }
+
+        Ok(entry.map(|e| e.path().join("::")))
+    }
+
+    /// The items named `segment`, the first segment of `path`, at the root of the visible
+    /// crates. It is an error for `segment` to name only items of other crates.
+    fn lookup_visible_roots(
+        &self,
+        path: &str,
+        segment: &str,
+        namespace: Namespace,
+    ) -> Fallible<Vec<&Entry>> {
+        let visible =
+            self.index
+                .lookup_roots(|i| self.visible_crates.contains(&i), segment, namespace);
+        if visible.is_empty() {
+            let invisible = self.index.lookup_roots(|_| true, segment, namespace);
+            if let Some(entry) = invisible.first() {
+                let current = &self.index.crate_ids[self.crate_index];
+                bail!(
+                    "`{path}` refers to `{segment}` of crate `{:?}`, which crate `{current:?}` does not depend on",
+                    entry.crate_id
+                )
+            }
+        }
+        Ok(visible)
+    }
+
+    /// Resolves `path` in `namespace`, keeping it as it is if it refers to nothing.
+    fn resolve_or_keep(&self, path: &str, namespace: Namespace) -> Fallible<String> {
+        Ok(self
+            .resolve(path, namespace)?
+            .unwrap_or_else(|| path.to_string()))
+    }
+
+    fn adt_id(&self, id: &AdtId) -> Fallible<AdtId> {
+        Ok(AdtId::new(&self.resolve_or_keep(id, Namespace::Adt)?))
+    }
+
+    fn trait_id(&self, id: &TraitId) -> Fallible<TraitId> {
+        Ok(TraitId::new(&self.resolve_or_keep(id, Namespace::Trait)?))
+    }
+
+    fn fn_id(&self, id: &FnId) -> Fallible<FnId> {
+        Ok(FnId::new(&self.resolve_or_keep(id, Namespace::Fn)?))
+    }
+
+    fn type_alias_id(&self, id: &TypeAliasId) -> Fallible<TypeAliasId> {
+        Ok(TypeAliasId::new(
+            &self.resolve_or_keep(id, Namespace::TypeAlias)?,
+        ))
+    }
+
+    /// Appends the items of `items`, and of the modules among them, to `out`.
+    fn flatten(
+        &self,
+        items: &[CrateItem],
+        visibility: Visibility,
+        out: &mut Vec<CrateItem>,
+    ) -> Fallible<()> {
+        for item in items {
+            match item {
+                CrateItem::Module(Module {
+                    visibility: module_visibility,
+                    id,
+                    items,
+                }) => self.in_module(id).flatten(
+                    items,
+                    std::cmp::max(visibility, *module_visibility),
+                    out,
+                )?,
+                CrateItem::Cfg(_) => {}
+                _ => out.push(self.crate_item(item, visibility)?),
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolves the paths in `item` and names it by its full path. Its visibility is
+    /// restricted to `visibility`, the visibility of the enclosing modules.
+    fn crate_item(&self, item: &CrateItem, visibility: Visibility) -> Fallible<CrateItem> {
+        let restrict = |v: &Visibility| std::cmp::max(visibility, *v);
+        Ok(match item {
+            CrateItem::Struct(Struct {
+                visibility: v,
+                id,
+                binder,
+            }) => Struct {
+                visibility: restrict(v),
+                id: AdtId::new(&self.qualify(id)),
+                binder: self.binder(
+                    binder,
+                    |StructBoundData {
+                         where_clauses,
+                         fields,
+                     }| {
+                        Ok(StructBoundData {
+                            where_clauses: self.where_clauses(&where_clauses)?,
+                            fields: self.fields(&fields)?,
+                        })
+                    },
+                )?,
+            }
+            .upcast(),
+            CrateItem::Enum(Enum {
+                visibility: v,
+                id,
+                binder,
+            }) => Enum {
+                visibility: restrict(v),
+                id: AdtId::new(&self.qualify(id)),
+                binder: self.binder(
+                    binder,
+                    |AdtBoundData {
+                         where_clauses,
+                         variants,
+                     }| {
+                        Ok(AdtBoundData {
+                            where_clauses: self.where_clauses(&where_clauses)?,
+                            variants: variants
+                                .iter()
+                                .map(|Variant { name, fields }| {
+                                    Ok(Variant {
+                                        name: name.clone(),
+                                        fields: self.fields(fields)?,
+                                    })
+                                })
+                                .collect::<Fallible<_>>()?,
+                        })
+                    },
+                )?,
+            }
+            .upcast(),
+            CrateItem::Trait(Trait {
+                visibility: v,
+                safety,
+                constness,
+                id,
+                binder,
+            }) => Trait {
+                visibility: restrict(v),
+                safety: *safety,
+                constness: *constness,
+                id: TraitId::new(&self.qualify(id)),
+                binder: TraitBinder {
+                    explicit_binder: self.binder(
+                        &binder.explicit_binder,
+                        |TraitBoundData {
+                             where_clauses,
+                             trait_items,
+                         }| {
+                            Ok(TraitBoundData {
+                                where_clauses: self.where_clauses(&where_clauses)?,
+                                trait_items: trait_items
+                                    .iter()
+                                    .map(|trait_item| self.trait_item(trait_item))
+                                    .collect::<Fallible<_>>()?,
+                            })
+                        },
+                    )?,
+                },
+            }
+            .upcast(),
+            CrateItem::TraitImpl(TraitImpl { safety, binder }) => TraitImpl {
+                safety: *safety,
+                binder: self.binder(
+                    binder,
+                    |TraitImplBoundData {
+                         constness,
+                         trait_id,
+                         self_ty,
+                         trait_parameters,
+                         where_clauses,
+                         impl_items,
+                     }| {
+                        Ok(TraitImplBoundData {
+                            constness,
+                            trait_id: self.trait_id(&trait_id)?,
+                            self_ty: self.ty(&self_ty)?,
+                            trait_parameters: self.parameters(&trait_parameters)?,
+                            where_clauses: self.where_clauses(&where_clauses)?,
+                            impl_items: impl_items
+                                .iter()
+                                .map(|impl_item| self.impl_item(impl_item))
+                                .collect::<Fallible<_>>()?,
+                        })
+                    },
+                )?,
+            }
+            .upcast(),
+            CrateItem::NegTraitImpl(NegTraitImpl { binder }) => NegTraitImpl {
+                binder: self.binder(
+                    binder,
+                    |NegTraitImplBoundData {
+                         trait_id,
+                         self_ty,
+                         trait_parameters,
+                         where_clauses,
+                     }| {
+                        Ok(NegTraitImplBoundData {
+                            trait_id: self.trait_id(&trait_id)?,
+                            self_ty: self.ty(&self_ty)?,
+                            trait_parameters: self.parameters(&trait_parameters)?,
+                            where_clauses: self.where_clauses(&where_clauses)?,
+                        })
+                    },
+                )?,
+            }
+            .upcast(),
+            CrateItem::Fn(f) => self.free_fn(f, visibility)?.upcast(),
+            CrateItem::ExternBlock(ExternBlock { abi, fns }) => ExternBlock {
+                abi: *abi,
+                fns: fns
+                    .iter()
+                    .map(|f| self.free_fn(f, visibility))
+                    .collect::<Fallible<_>>()?,
+            }
+            .upcast(),
+            CrateItem::Static(Static {
+                visibility: v,
+                kind,
+                id,
+                ty,
+                value,
+            }) => Static {
+                visibility: restrict(v),
+                kind: kind.clone(),
+                id: ValueId::new(&self.qualify(id)),
+                ty: self.ty(ty)?,
+                value: self.expr(&Set::new(), value)?,
+            }
+            .upcast(),
+            CrateItem::Const(ConstItem {
+                visibility: v,
+                id,
+                ty,
+                value,
+            }) => ConstItem {
+                visibility: restrict(v),
+                id: ValueId::new(&self.qualify(id)),
+                ty: self.ty(ty)?,
+                value: self.expr(&Set::new(), value)?,
+            }
+            .upcast(),
+            CrateItem::TypeAlias(TypeAlias {
+                visibility: v,
+                id,
+                binder,
+            }) => TypeAlias {
+                visibility: restrict(v),
+                id: TypeAliasId::new(&self.qualify(id)),
+                binder: self.binder(binder, |TypeAliasBoundData { ty, where_clauses }| {
+                    Ok(TypeAliasBoundData {
+                        ty: self.ty(&ty)?,
+                        where_clauses: self.where_clauses(&where_clauses)?,
+                    })
+                })?,
+            }
+            .upcast(),
+            CrateItem::Module(_) => unreachable!("modules are flattened by the caller"),
+            CrateItem::Cfg(_) => unreachable!("gated items are dropped by the caller"),
+        })
+    }
+
+    fn trait_item(&self, trait_item: &TraitItem) -> Fallible<TraitItem> {
+        Ok(match trait_item {
+            TraitItem::Fn(f) => self.fn_(f)?.upcast(),
+            TraitItem::AssociatedTy(AssociatedTy { id, binder }) => AssociatedTy {
+                id: id.clone(),
+                binder: self.binder(
+                    binder,
+                    |AssociatedTyBoundData {
+                         ensures,
+                         where_clauses,
+                     }| {
+                        Ok(AssociatedTyBoundData {
+                            ensures: ensures
+                                .iter()
+                                .map(|ensure| self.where_bound(ensure))
+                                .collect::<Fallible<_>>()?,
+                            where_clauses: self.where_clauses(&where_clauses)?,
+                        })
+                    },
+                )?,
+            }
+            .upcast(),
+        })
+    }
+
+    fn impl_item(&self, impl_item: &ImplItem) -> Fallible<ImplItem> {
+        Ok(match impl_item {
+            ImplItem::Fn(f) => self.fn_(f)?.upcast(),
+            ImplItem::AssociatedTyValue(AssociatedTyValue { id, binder }) => AssociatedTyValue {
+                id: id.clone(),
+                binder: self.binder(
+                    binder,
+                    |AssociatedTyValueBoundData { where_clauses, ty }| {
+                        Ok(AssociatedTyValueBoundData {
+                            where_clauses: self.where_clauses(&where_clauses)?,
+                            ty: self.ty(&ty)?,
+                        })
+                    },
+                )?,
+            }
+            .upcast(),
+        })
+    }
+
+    /// Resolves the free fn `f` and names it by its full path, like [`Self::crate_item`].
+    fn free_fn(&self, f: &Fn, visibility: Visibility) -> Fallible<Fn> {
+        Ok(Fn {
+            visibility: std::cmp::max(visibility, f.visibility),
+            id: FnId::new(&self.qualify(&f.id)),
+            ..self.fn_(f)?
+        })
+    }
+
+    /// Resolves the paths in the signature and body of `f`, keeping its id.
+    fn fn_(&self, f: &Fn) -> Fallible<Fn> {
+        let Fn {
+            visibility,
+            safety,
+            id,
+            binder,
+        } = f;
+        Ok(Fn {
+            visibility: *visibility,
+            safety: *safety,
+            id: id.clone(),
+            binder: self.binder(
+                binder,
+                |FnBoundData {
+                     defaults,
+                     self_param,
+                     input_tys,
+                     output_ty,
+                     where_clauses,
+                     body,
+                 }| {
+                    Ok(FnBoundData {
+                        defaults: TyDefaults {
+                            defaults: defaults
+                                .defaults
+                                .iter()
+                                .map(|TyDefault { param, default }| {
+                                    Ok(TyDefault {
+                                        param: param.clone(),
+                                        default: self.ty(default)?,
+                                    })
+                                })
+                                .collect::<Fallible<_>>()?,
+                        },
+                        self_param,
+                        input_tys: input_tys
+                            .iter()
+                            .map(|ty| self.ty(ty))
+                            .collect::<Fallible<_>>()?,
+                        output_ty: self.ty(&output_ty)?,
+                        where_clauses: self.where_clauses(&where_clauses)?,
+                        body: match body {
+                            MaybeFnBody::FnBody(FnBody::ExprFnBody(ExprFnBody {
+                                params,
+                                body,
+                            })) => {
+                                let locals = params.iter().cloned().collect();
+                                MaybeFnBody::FnBody(FnBody::ExprFnBody(ExprFnBody {
+                                    body: self.block(&locals, &body)?,
+                                    params,
+                                }))
+                            }
+                            // FIXME: resolve the paths in MIR bodies
+                            MaybeFnBody::NoFnBody
+                            | MaybeFnBody::FnBody(FnBody::TrustedFnBody)
+                            | MaybeFnBody::FnBody(FnBody::MirFnBody(_)) => body,
+                        },
+                    })
+                },
+            )?,
+        })
+    }
+
+    fn binder<T: formality_types::fold::Fold>(
+        &self,
+        binder: &Binder<T>,
+        op: impl FnOnce(T) -> Fallible<T>,
+    ) -> Fallible<Binder<T>> {
+        let (vars, data) = binder.open();
+        Ok(Binder::new(vars, op(data)?))
+    }
+
+    fn fields(&self, fields: &[Field]) -> Fallible<Vec<Field>> {
+        fields
+            .iter()
+            .map(
+                |Field {
+                     visibility,
+                     name,
+                     ty,
+                 }| {
+                    Ok(Field {
+                        visibility: *visibility,
+                        name: name.clone(),
+                        ty: self.ty(ty)?,
+                    })
+                },
+            )
+            .collect()
+    }
+
+    fn where_clauses(&self, where_clauses: &[WhereClause]) -> Fallible<Vec<WhereClause>> {
+        where_clauses
+            .iter()
+            .map(|where_clause| self.where_clause(where_clause))
+            .collect()
+    }
+
+    fn where_clause(&self, where_clause: &WhereClause) -> Fallible<WhereClause> {
+        let data = match where_clause.data() {
+            WhereClauseData::IsImplemented(self_ty, trait_id, parameters) => {
+                WhereClauseData::IsImplemented(
+                    self.ty(self_ty)?,
+                    self.trait_id(trait_id)?,
+                    self.parameters(parameters)?,
+                )
+            }
+            WhereClauseData::ConstIsImplemented(self_ty, constness, trait_id, parameters) => {
+                WhereClauseData::ConstIsImplemented(
+                    self.ty(self_ty)?,
+                    *constness,
+                    self.trait_id(trait_id)?,
+                    self.parameters(parameters)?,
+                )
+            }
+            WhereClauseData::AliasEq(alias_ty, ty) => {
+                WhereClauseData::AliasEq(self.alias_ty(alias_ty)?, self.ty(ty)?)
+            }
+            WhereClauseData::Outlives(parameter, lt) => {
+                WhereClauseData::Outlives(self.parameter(parameter)?, lt.clone())
+            }
+            WhereClauseData::ForAll(binder) => {
+                WhereClauseData::ForAll(self.binder(binder, |wc| self.where_clause(&wc))?)
+            }
+            WhereClauseData::TypeOfConst(c, ty) => {
+                WhereClauseData::TypeOfConst(self.const_(c)?, self.ty(ty)?)
+            }
+            WhereClauseData::MaybeSized(ty) => WhereClauseData::MaybeSized(self.ty(ty)?),
+        };
+        Ok(WhereClause {
+            data: Arc::new(data),
+        })
+    }
+
+    fn where_bound(&self, where_bound: &WhereBound) -> Fallible<WhereBound> {
+        let data = match where_bound.data() {
+            WhereBoundData::IsImplemented(trait_id, parameters) => WhereBoundData::IsImplemented(
+                self.trait_id(trait_id)?,
+                self.parameters(parameters)?,
+            ),
+            WhereBoundData::Outlives(lt) => WhereBoundData::Outlives(lt.clone()),
+            WhereBoundData::ForAll(binder) => {
+                WhereBoundData::ForAll(self.binder(binder, |wb| self.where_bound(&wb))?)
+            }
+        };
+        Ok(WhereBound {
+            data: Arc::new(data),
+        })
+    }
+
+    fn parameters(&self, parameters: &[Parameter]) -> Fallible<Vec<Parameter>> {
+        parameters.iter().map(|p| self.parameter(p)).collect()
+    }
+
+    fn parameter(&self, parameter: &Parameter) -> Fallible<Parameter> {
+        Ok(match parameter {
+            Parameter::Ty(ty) => Parameter::Ty(self.ty(ty)?),
+            Parameter::Lt(_) => parameter.clone(),
+            Parameter::Const(c) => Parameter::Const(self.const_(c)?),
+        })
+    }
+
+    fn const_(&self, c: &Const) -> Fallible<Const> {
+        Ok(match c.data() {
+            ConstData::Value(value, ty) => Const::valtree(value.clone(), self.ty(ty)?),
+            ConstData::Variable(_) => c.clone(),
+        })
+    }
+
+    fn ty(&self, ty: &Ty) -> Fallible<Ty> {
+        Ok(match ty.data() {
+            TyData::RigidTy(RigidTy { name, parameters }) => {
+                let name = match name {
+                    RigidName::AdtId(id) => RigidName::AdtId(self.adt_id(id)?),
+                    RigidName::FnDef(id) => RigidName::FnDef(self.fn_id(id)?),
+                    RigidName::Dyn(id) => RigidName::Dyn(self.trait_id(id)?),
+                    _ => name.clone(),
+                };
+                Ty::rigid(name, self.parameters(parameters)?)
+            }
+            TyData::AliasTy(alias_ty) => Ty::new(self.alias_ty(alias_ty)?),
+            TyData::PredicateTy(PredicateTy::ForAll(binder)) => {
+                Ty::new(PredicateTy::ForAll(self.binder(binder, |ty| self.ty(&ty))?))
+            }
+            TyData::Variable(_) => ty.clone(),
+        })
+    }
+
+    fn alias_ty(&self, alias_ty: &AliasTy) -> Fallible<AliasTy> {
+        let AliasTy { name, parameters } = alias_ty;
+        let name = match name {
+            AliasName::AssociatedTyId(AssociatedTyName { trait_id, item_id }) => {
+                AliasName::AssociatedTyId(AssociatedTyName {
+                    trait_id: self.trait_id(trait_id)?,
+                    item_id: item_id.clone(),
+                })
+            }
+            AliasName::TypeAliasId(id) => AliasName::TypeAliasId(self.type_alias_id(id)?),
+        };
+        Ok(AliasTy {
+            name,
+            parameters: self.parameters(parameters)?,
+        })
+    }
+
+    /// Resolves the paths in `block`, where the variables `locals` are in scope.
+    fn block(&self, locals: &Set<ValueId>, block: &Block) -> Fallible<Block> {
+        let Block { stmts, tail } = block;
+        let mut locals = locals.clone();
+        let mut resolved_stmts = vec![];
+        for stmt in stmts {
+            resolved_stmts.push(match stmt {
+                Stmt::Let(id, init) => {
+                    let init = self.expr(&locals, init)?;
+                    locals.insert(id.clone());
+                    Stmt::Let(id.clone(), init)
+                }
+                Stmt::LetTy(id, ty, init) => {
+                    let init = self.expr(&locals, init)?;
+                    locals.insert(id.clone());
+                    Stmt::LetTy(id.clone(), self.ty(ty)?, init)
+                }
+                Stmt::Expr(e) => Stmt::Expr(self.expr(&locals, e)?),
+            });
+        }
+        Ok(Block {
+            stmts: resolved_stmts,
+            tail: tail.as_ref().map(|e| self.expr(&locals, e)).transpose()?,
+        })
+    }
+
+    /// Resolves the paths in `expr`, where the variables `locals` are in scope.
+    /// A name that is not a local variable may refer to a `static`, a `const` or a fn.
+    fn expr(&self, locals: &Set<ValueId>, expr: &Expr) -> Fallible<Expr> {
+        let data = match expr.data() {
+            ExprData::Literal(c) => ExprData::Literal(self.const_(c)?),
+            ExprData::Numeric(_) | ExprData::Unit => expr.data().clone(),
+            ExprData::Var(id) => {
+                if locals.contains(id) {
+                    ExprData::Var(id.clone())
+                } else {
+                    let path = match self.resolve(id, Namespace::Value)? {
+                        Some(path) => path,
+                        None => self.resolve_or_keep(id, Namespace::Fn)?,
+                    };
+                    ExprData::Var(ValueId::new(&path))
+                }
+            }
+            ExprData::Call(id, args) => {
+                let id = if locals.contains(&ValueId::new(id)) {
+                    id.clone()
+                } else {
+                    self.fn_id(id)?
+                };
+                ExprData::Call(id, self.exprs(locals, args)?)
+            }
+            ExprData::Field(e, field) => ExprData::Field(self.expr(locals, e)?, field.clone()),
+            ExprData::Ref(e) => ExprData::Ref(self.expr(locals, e)?),
+            ExprData::RefMut(e) => ExprData::RefMut(self.expr(locals, e)?),
+            ExprData::Deref(e) => ExprData::Deref(self.expr(locals, e)?),
+            ExprData::Binary(lhs, op, rhs) => {
+                ExprData::Binary(self.expr(locals, lhs)?, *op, self.expr(locals, rhs)?)
+            }
+            ExprData::Unary(op, e) => ExprData::Unary(*op, self.expr(locals, e)?),
+            ExprData::Index(base, index) => {
+                ExprData::Index(self.expr(locals, base)?, self.expr(locals, index)?)
+            }
+            ExprData::Await(e) => ExprData::Await(self.expr(locals, e)?),
+            ExprData::Block(block) => ExprData::Block(self.block(locals, block)?),
+            ExprData::Unsafe(block) => ExprData::Unsafe(self.block(locals, block)?),
+            ExprData::Async(capture_by, block) => {
+                ExprData::Async(capture_by.clone(), self.block(locals, block)?)
+            }
+            ExprData::If(cond, then_block, else_block) => ExprData::If(
+                self.expr(locals, cond)?,
+                self.block(locals, then_block)?,
+                self.block(locals, else_block)?,
+            ),
+            ExprData::Match(scrutinee, arms) => ExprData::Match(
+                self.expr(locals, scrutinee)?,
+                arms.iter()
+                    .map(|Arm { pattern, body }| {
+                        let mut locals = locals.clone();
+                        locals.extend(pattern.bindings());
+                        Ok(Arm {
+                            pattern: self.pattern(pattern)?,
+                            body: self.expr(&locals, body)?,
+                        })
+                    })
+                    .collect::<Fallible<_>>()?,
+            ),
+            ExprData::Cast(e, ty) => ExprData::Cast(self.expr(locals, e)?, self.ty(ty)?),
+            ExprData::Closure(capture_by, params, body) => {
+                let mut body_locals = locals.clone();
+                body_locals.extend(params.iter().cloned());
+                ExprData::Closure(
+                    capture_by.clone(),
+                    params.clone(),
+                    self.expr(&body_locals, body)?,
+                )
+            }
+        };
+        Ok(Expr::new(data))
+    }
+
+    fn exprs(&self, locals: &Set<ValueId>, exprs: &[Expr]) -> Fallible<Vec<Expr>> {
+        exprs.iter().map(|e| self.expr(locals, e)).collect()
+    }
+
+    fn pattern(&self, pattern: &Pattern) -> Fallible<Pattern> {
+        let data = match pattern.data() {
+            PatternData::Wildcard | PatternData::Binding(_) => pattern.data().clone(),
+            PatternData::Literal(c) => PatternData::Literal(self.const_(c)?),
+            PatternData::Tuple(pats) => PatternData::Tuple(
+                pats.iter()
+                    .map(|p| self.pattern(p))
+                    .collect::<Fallible<_>>()?,
+            ),
+            PatternData::Adt(AdtPattern {
+                adt_id,
+                variant_id,
+                fields,
+            }) => {
+                // `m::Point { .. }` is parsed as variant `Point` of `m`.
+                let struct_path = format!("{adt_id:?}::{variant_id:?}");
+                let (adt_id, variant_id) = match self.resolve(adt_id, Namespace::Adt)? {
+                    Some(path) => (AdtId::new(&path), variant_id.clone()),
+                    None if *variant_id != VariantId::for_struct() => {
+                        match self.resolve(&struct_path, Namespace::Adt)? {
+                            Some(path) => (AdtId::new(&path), VariantId::for_struct()),
+                            None => (adt_id.clone(), variant_id.clone()),
+                        }
+                    }
+                    None => (adt_id.clone(), variant_id.clone()),
+                };
+                PatternData::Adt(AdtPattern {
+                    adt_id,
+                    variant_id,
+                    fields: fields
+                        .iter()
+                        .map(|FieldPattern { name, pattern }| {
+                            Ok(FieldPattern {
+                                name: name.clone(),
+                                pattern: self.pattern(pattern)?,
+                            })
+                        })
+                        .collect::<Fallible<_>>()?,
+                })
+            }
+            PatternData::Ref(p) => PatternData::Ref(self.pattern(p)?),
+            PatternData::RefMut(p) => PatternData::RefMut(self.pattern(p)?),
+        };
+        Ok(Pattern::new(data))
+    }
+}
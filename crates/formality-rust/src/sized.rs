@@ -0,0 +1,295 @@
+//! Implicit `Sized` bounds.
+//!
+//! As in Rust, every type parameter `T` of an item has an implicit bound `T: Sized`,
+//! unless the item relaxes it with `T: ?Sized`. The `Self` parameter of traits has no
+//! such bound. Like the other lang items, the bounds are only added if the program
+//! declares the `Sized` trait.
+//!
+//! FIXME: rustc also adds `Sized` bounds to associated types.
+
+use std::sync::Arc;
+
+use anyhow::bail;
+use formality_types::{
+    cast::Upcast,
+    collections::Set,
+    grammar::{
+        lang_items::sized_trait, Binder, BoundVar, Fallible, ParameterKind, Ty, Variable, Wcs,
+    },
+};
+
+use crate::grammar::{
+    AdtBoundData, AssociatedTy, AssociatedTyBoundData, AssociatedTyValue,
+    AssociatedTyValueBoundData, CfgItem, Crate, CrateItem, Enum, ExternBlock, Fn, FnBoundData,
+    ImplItem, Module, NegTraitImpl, NegTraitImplBoundData, Program, Struct, StructBoundData, Trait,
+    TraitBinder, TraitBoundData, TraitImpl, TraitImplBoundData, TraitItem, TypeAlias,
+    TypeAliasBoundData, WhereClause, WhereClauseData,
+};
+
+impl Program {
+    /// Adds the implicit `T: Sized` bound of each type parameter `T` that is not `?Sized`.
+    pub fn add_implicit_sized_bounds(&self) -> Fallible<Program> {
+        let adder = SizedBounds {
+            sized_declared: self.trait_named(&sized_trait()).is_ok(),
+        };
+        let crates = self
+            .crates
+            .iter()
+            .map(|c| {
+                Ok(Crate {
+                    id: c.id.clone(),
+                    edition: c.edition,
+                    dependencies: c.dependencies.clone(),
+                    items: adder.crate_items(&c.items)?,
+                })
+            })
+            .collect::<Fallible<_>>()?;
+        Ok(Program { crates })
+    }
+
+    /// The goal that `ty` is `Sized`, e.g. for the type of a local variable. It is
+    /// trivially true if the program does not declare the `Sized` trait.
+    pub fn sized_goal(&self, ty: &Ty) -> Wcs {
+        if self.trait_named(&sized_trait()).is_ok() {
+            sized_trait().with(ty, ()).is_implemented().upcast()
+        } else {
+            Wcs::t()
+        }
+    }
+}
+
+struct SizedBounds {
+    sized_declared: bool,
+}
+
+impl SizedBounds {
+    fn crate_items(&self, items: &[CrateItem]) -> Fallible<Vec<CrateItem>> {
+        items.iter().map(|item| self.crate_item(item)).collect()
+    }
+
+    fn crate_item(&self, item: &CrateItem) -> Fallible<CrateItem> {
+        Ok(match item {
+            CrateItem::Struct(s) => Struct {
+                binder: self.binder(
+                    &s.binder,
+                    |params,
+                     StructBoundData {
+                         where_clauses,
+                         fields,
+                     }| {
+                        Ok(StructBoundData {
+                            where_clauses: self.where_clauses(params, &where_clauses)?,
+                            fields,
+                        })
+                    },
+                )?,
+                ..s.clone()
+            }
+            .upcast(),
+            CrateItem::Enum(e) => Enum {
+                binder: self.binder(
+                    &e.binder,
+                    |params,
+                     AdtBoundData {
+                         where_clauses,
+                         variants,
+                     }| {
+                        Ok(AdtBoundData {
+                            where_clauses: self.where_clauses(params, &where_clauses)?,
+                            variants,
+                        })
+                    },
+                )?,
+                ..e.clone()
+            }
+            .upcast(),
+            CrateItem::Trait(t) => self.trait_(t)?.upcast(),
+            CrateItem::TraitImpl(i) => TraitImpl {
+                binder: self.binder(&i.binder, |params, data| {
+                    let TraitImplBoundData {
+                        where_clauses,
+                        impl_items,
+                        ..
+                    } = &data;
+                    Ok(TraitImplBoundData {
+                        where_clauses: self.where_clauses(params, where_clauses)?,
+                        impl_items: impl_items
+                            .iter()
+                            .map(|impl_item| self.impl_item(impl_item))
+                            .collect::<Fallible<_>>()?,
+                        ..data.clone()
+                    })
+                })?,
+                ..i.clone()
+            }
+            .upcast(),
+            CrateItem::NegTraitImpl(i) => NegTraitImpl {
+                binder: self.binder(&i.binder, |params, data| {
+                    Ok(NegTraitImplBoundData {
+                        where_clauses: self.where_clauses(params, &data.where_clauses)?,
+                        ..data
+                    })
+                })?,
+            }
+            .upcast(),
+            CrateItem::Fn(f) => self.fn_(f)?.upcast(),
+            CrateItem::Static(_) | CrateItem::Const(_) => item.clone(),
+            CrateItem::TypeAlias(t) => TypeAlias {
+                binder: self.binder(
+                    &t.binder,
+                    |params, TypeAliasBoundData { ty, where_clauses }| {
+                        Ok(TypeAliasBoundData {
+                            ty,
+                            where_clauses: self.where_clauses(params, &where_clauses)?,
+                        })
+                    },
+                )?,
+                ..t.clone()
+            }
+            .upcast(),
+            CrateItem::Module(m) => Module {
+                items: self.crate_items(&m.items)?,
+                ..m.clone()
+            }
+            .upcast(),
+            CrateItem::ExternBlock(b) => ExternBlock {
+                abi: b.abi,
+                fns: b.fns.iter().map(|f| self.fn_(f)).collect::<Fallible<_>>()?,
+            }
+            .upcast(),
+            CrateItem::Cfg(c) => CfgItem {
+                feature: c.feature.clone(),
+                item: Arc::new(self.crate_item(&c.item)?),
+            }
+            .upcast(),
+        })
+    }
+
+    /// The first parameter of a trait is `Self`, which has no implicit bound.
+    fn trait_(&self, t: &Trait) -> Fallible<Trait> {
+        let (
+            vars,
+            TraitBoundData {
+                where_clauses,
+                trait_items,
+            },
+        ) = t.binder.explicit_binder.open();
+        let data = TraitBoundData {
+            where_clauses: self.where_clauses(&vars[1..], &where_clauses)?,
+            trait_items: trait_items
+                .iter()
+                .map(|trait_item| self.trait_item(trait_item))
+                .collect::<Fallible<_>>()?,
+        };
+        Ok(Trait {
+            binder: TraitBinder {
+                explicit_binder: Binder::new(vars, data),
+            },
+            ..t.clone()
+        })
+    }
+
+    fn trait_item(&self, trait_item: &TraitItem) -> Fallible<TraitItem> {
+        Ok(match trait_item {
+            TraitItem::Fn(f) => self.fn_(f)?.upcast(),
+            TraitItem::AssociatedTy(AssociatedTy { id, binder }) => AssociatedTy {
+                id: id.clone(),
+                binder: self.binder(
+                    binder,
+                    |params,
+                     AssociatedTyBoundData {
+                         ensures,
+                         where_clauses,
+                     }| {
+                        Ok(AssociatedTyBoundData {
+                            ensures,
+                            where_clauses: self.where_clauses(params, &where_clauses)?,
+                        })
+                    },
+                )?,
+            }
+            .upcast(),
+        })
+    }
+
+    fn impl_item(&self, impl_item: &ImplItem) -> Fallible<ImplItem> {
+        Ok(match impl_item {
+            ImplItem::Fn(f) => self.fn_(f)?.upcast(),
+            ImplItem::AssociatedTyValue(AssociatedTyValue { id, binder }) => AssociatedTyValue {
+                id: id.clone(),
+                binder: self.binder(
+                    binder,
+                    |params, AssociatedTyValueBoundData { where_clauses, ty }| {
+                        Ok(AssociatedTyValueBoundData {
+                            where_clauses: self.where_clauses(params, &where_clauses)?,
+                            ty,
+                        })
+                    },
+                )?,
+            }
+            .upcast(),
+        })
+    }
+
+    fn fn_(&self, f: &Fn) -> Fallible<Fn> {
+        Ok(Fn {
+            binder: self.binder(&f.binder, |params, data| {
+                Ok(FnBoundData {
+                    where_clauses: self.where_clauses(params, &data.where_clauses)?,
+                    ..data
+                })
+            })?,
+            ..f.clone()
+        })
+    }
+
+    fn binder<T: formality_types::fold::Fold>(
+        &self,
+        binder: &Binder<T>,
+        op: impl FnOnce(&[BoundVar], T) -> Fallible<T>,
+    ) -> Fallible<Binder<T>> {
+        let (vars, data) = binder.open();
+        let data = op(&vars, data)?;
+        Ok(Binder::new(vars, data))
+    }
+
+    /// Adds the implicit bounds of the type parameters `params` to `where_clauses`,
+    /// which may only relax the bounds of those parameters.
+    fn where_clauses(
+        &self,
+        params: &[BoundVar],
+        where_clauses: &[WhereClause],
+    ) -> Fallible<Vec<WhereClause>> {
+        let param_tys: Vec<Ty> = params
+            .iter()
+            .filter(|var| var.kind == ParameterKind::Ty)
+            .map(|&var| {
+                let v: Variable = var.upcast();
+                Ty::new(v)
+            })
+            .collect();
+
+        let mut relaxed = Set::new();
+        for where_clause in where_clauses {
+            if let WhereClauseData::MaybeSized(ty) = where_clause.data() {
+                if !param_tys.contains(ty) {
+                    bail!("`?Sized` is only allowed on the type parameters of the item itself, not on `{ty:?}`")
+                }
+                relaxed.insert(ty.clone());
+            }
+        }
+
+        let mut where_clauses = where_clauses.to_vec();
+        if self.sized_declared {
+            where_clauses.extend(
+                param_tys
+                    .into_iter()
+                    .filter(|ty| !relaxed.contains(ty))
+                    .map(|ty| WhereClause {
+                        data: Arc::new(WhereClauseData::IsImplemented(ty, sized_trait(), vec![])),
+                    }),
+            );
+        }
+        Ok(where_clauses)
+    }
+}
@@ -0,0 +1,127 @@
+//! Where the items of a program are in the text it was parsed from, so that errors
+//! about an item can point at it.
+
+use std::collections::BTreeMap;
+
+use formality_types::{
+    collections::Set,
+    grammar::CrateId,
+    parse::{Span, Spans},
+};
+
+use crate::grammar::{CrateItem, Feature, FeatureId, Program};
+
+/// The spans of the items of a program parsed with
+/// [`try_term_with_spans`](`formality_types::parse::try_term_with_spans`).
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    /// For each crate, the spans of its items in the order the checks see them:
+    /// with the items of modules in place of the modules, as in [`Program::resolve`],
+    /// and the gated items in place of their `#[cfg]`, as in [`Program::configure`].
+    crates: Vec<(CrateId, Vec<ItemSpans>)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemSpans {
+    pub item: Span,
+
+    /// The where-clauses within the item, including those of its fns, associated types, etc.
+    pub where_clauses: Vec<Span>,
+
+    /// The features of its crate that the item is gated on, if any.
+    pub features: Vec<FeatureId>,
+}
+
+impl SourceMap {
+    pub fn new(program: &Program, spans: &Spans) -> Self {
+        let containers: Vec<Span> = spans.of("Module").chain(spans.of("CfgItem")).collect();
+
+        // Of the items parsed at the same point, e.g., by alternatives that were
+        // abandoned later on, the longest is the one that was kept.
+        let mut items: BTreeMap<usize, Span> = BTreeMap::new();
+        for span in spans.of("CrateItem").filter(|s| !containers.contains(s)) {
+            let kept = items.entry(span.start).or_insert(span);
+            kept.end = kept.end.max(span.end);
+        }
+        let where_clauses: Vec<Span> = spans.of("WhereClause").collect();
+
+        let crates = program
+            .crates
+            .iter()
+            .zip(spans.of("Crate"))
+            .map(|(c, crate_span)| {
+                let mut gates = vec![];
+                item_gates(&c.items, &[], &mut gates);
+                let item_spans = items
+                    .values()
+                    .filter(|s| contains(crate_span, **s))
+                    .zip(gates)
+                    .map(|(&item, features)| ItemSpans {
+                        item,
+                        where_clauses: where_clauses
+                            .iter()
+                            .copied()
+                            .filter(|&wc| contains(item, wc))
+                            .collect(),
+                        features,
+                    })
+                    .collect();
+                (c.id.clone(), item_spans)
+            })
+            .collect();
+        SourceMap { crates }
+    }
+
+    /// The spans of the items that are left with the features `enabled` (see [`Program::configure`]).
+    pub fn configure(&self, enabled: &Set<Feature>) -> SourceMap {
+        let crates = self
+            .crates
+            .iter()
+            .map(|(crate_id, items)| {
+                let items = items
+                    .iter()
+                    .filter(|item| {
+                        item.features.iter().all(|feature_id| {
+                            enabled.contains(&Feature {
+                                crate_id: crate_id.clone(),
+                                feature_id: feature_id.clone(),
+                            })
+                        })
+                    })
+                    .cloned()
+                    .collect();
+                (crate_id.clone(), items)
+            })
+            .collect();
+        SourceMap { crates }
+    }
+
+    /// The spans of the item with the given index in the resolved crate `crate_id`.
+    pub fn item(&self, crate_id: &CrateId, index: usize) -> Option<&ItemSpans> {
+        let (_, items) = self.crates.iter().find(|(id, _)| id == crate_id)?;
+        items.get(index)
+    }
+}
+
+/// Appends the features that each of `items` is gated on to `out`, in the order of the
+/// item spans, given that `items` are within the `#[cfg]`s of `features`.
+fn item_gates(items: &[CrateItem], features: &[FeatureId], out: &mut Vec<Vec<FeatureId>>) {
+    for item in items {
+        match item {
+            CrateItem::Module(m) => item_gates(&m.items, features, out),
+            CrateItem::Cfg(cfg) => {
+                let inner: Vec<FeatureId> = features
+                    .iter()
+                    .cloned()
+                    .chain(Some(cfg.feature.clone()))
+                    .collect();
+                item_gates(std::slice::from_ref(&*cfg.item), &inner, out)
+            }
+            _ => out.push(features.to_vec()),
+        }
+    }
+}
+
+fn contains(outer: Span, inner: Span) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
@@ -4,8 +4,10 @@ use formality_types::{
     cast::To,
     derive_links::{DowncastTo, UpcastFrom},
     fold::Fold,
+    generate::{Generate, Generator},
     grammar::{fresh_bound_var, Binder, BoundVar, ParameterKind},
     parse::{expect_char, Binding, Parse, ParseResult},
+    pretty::{Doc, DocBuilder, Pretty, PrettyCx},
     term::Term,
     visit::Visit,
 };
@@ -49,6 +51,10 @@ where
         self.explicit_binder.free_variables()
     }
 
+    fn var_flags(&self) -> formality_types::visit::VarFlags {
+        self.explicit_binder.var_flags()
+    }
+
     fn size(&self) -> usize {
         self.explicit_binder.size()
     }
@@ -78,6 +84,49 @@ where
     }
 }
 
+/// `<ty T0> term`, where the variable bound implicitly is called `Self`.
+impl<T> Pretty for TraitBinder<T>
+where
+    T: Term,
+{
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        let kinds = self.explicit_binder.kinds();
+        let names = cx.fresh_names(&kinds[1..]);
+        let cx = cx.with_names(
+            std::iter::once("Self".to_string())
+                .chain(names.clone())
+                .zip(kinds.iter().copied())
+                .map(|(name, kind)| (kind, name))
+                .collect(),
+        );
+        let bindings = kinds[1..]
+            .iter()
+            .zip(names)
+            .map(|(kind, name)| Doc::text(format!("{kind:?} {name}")));
+        let mut b = DocBuilder::default();
+        b.glue();
+        b.text("<");
+        b.push(Doc::list(bindings));
+        b.text(">");
+        b.push(self.explicit_binder.peek().pretty(&cx));
+        b.group()
+    }
+}
+
+/// The implicit `Self` comes first, as when parsed.
+impl<T> Generate for TraitBinder<T>
+where
+    T: Term,
+{
+    fn generate(g: &mut Generator) -> Self {
+        let mut kinds = vec![ParameterKind::Ty];
+        kinds.extend(Vec::<ParameterKind>::generate(g));
+        TraitBinder {
+            explicit_binder: Binder::generate_with_kinds(g, kinds),
+        }
+    }
+}
+
 impl<T> Parse for TraitBinder<T>
 where
     T: Term,
@@ -2,6 +2,8 @@ use std::sync::Arc;
 
 use crate::{collections::Set, derive_links::Term};
 
+mod test;
+
 pub trait To {
     fn to<T>(&self) -> T
     where
@@ -264,6 +266,19 @@ where
     }
 }
 
+impl<A, B, C, D, A1, B1, C1, D1> UpcastFrom<(A1, B1, C1, D1)> for (A, B, C, D)
+where
+    A1: Upcast<A>,
+    B1: Upcast<B>,
+    C1: Upcast<C>,
+    D1: Upcast<D>,
+{
+    fn upcast_from(term: (A1, B1, C1, D1)) -> Self {
+        let (a1, b1, c1, d1) = term;
+        (a1.upcast(), b1.upcast(), c1.upcast(), d1.upcast())
+    }
+}
+
 impl<A, As, B, Bs, Z> UpcastFrom<(A, B)> for Vec<Z>
 where
     A: IntoIterator<Item = As> + Clone,
@@ -411,3 +426,27 @@ where
         Box::new(self.into_iter().filter_map(|e| T::downcast_from(&e)))
     }
 }
+
+/// Like [`Downcasted`], for pairs of an item and something about where it comes from,
+/// e.g., the items of a program with the crates that define them: keeps the pairs whose
+/// item downcasts to `T`, with the item downcast.
+pub trait DowncastedWith<'a, K, V>: IntoIterator<Item = (K, V)> {
+    fn downcasted_with<T>(self) -> Box<dyn Iterator<Item = (K, T)> + 'a>
+    where
+        T: DowncastFrom<V>;
+}
+
+impl<'a, I, K, V> DowncastedWith<'a, K, V> for I
+where
+    I: IntoIterator<Item = (K, V)> + 'a,
+{
+    fn downcasted_with<T>(self) -> Box<dyn Iterator<Item = (K, T)> + 'a>
+    where
+        T: DowncastFrom<V>,
+    {
+        Box::new(
+            self.into_iter()
+                .filter_map(|(k, v)| Some((k, T::downcast_from(&v)?))),
+        )
+    }
+}
@@ -0,0 +1,97 @@
+#![cfg(test)]
+
+use formality_macros::Cast;
+
+use crate::{
+    cast::{Downcast, DowncastTo, Downcasted, DowncastedWith, Upcast, UpcastFrom},
+    grammar::{Lt, LtData, Parameter, ParameterKind, UniversalVar, VarIndex},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, Cast)]
+enum Leaf {
+    #[cast]
+    Number(u32),
+    #[cast]
+    Text(String),
+    Empty,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Cast)]
+#[cast(u32 <: Leaf, String <: Leaf)]
+struct Node {
+    leaf: Leaf,
+}
+
+impl UpcastFrom<Leaf> for Node {
+    fn upcast_from(leaf: Leaf) -> Self {
+        Node { leaf }
+    }
+}
+
+impl DowncastTo<Leaf> for Node {
+    fn downcast_to(&self) -> Option<Leaf> {
+        Some(self.leaf.clone())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Cast)]
+#[cast(Leaf <: Node, u32 <: Leaf <: Node)]
+enum Tree {
+    #[cast]
+    Node(Node),
+    Empty,
+}
+
+#[test]
+fn variants() {
+    let leaf: Leaf = 22_u32.upcast();
+    assert_eq!(leaf, Leaf::Number(22));
+    assert_eq!(leaf.downcast::<u32>(), Some(22));
+    assert_eq!(leaf.downcast::<String>(), None);
+    assert_eq!(Leaf::Empty.downcast::<Leaf>(), Some(Leaf::Empty));
+}
+
+#[test]
+fn chains() {
+    let node: Node = "a".to_string().upcast();
+    assert_eq!(node.downcast::<String>(), Some("a".to_string()));
+    assert_eq!(node.downcast::<u32>(), None);
+
+    let tree: Tree = 22_u32.upcast();
+    assert_eq!(
+        tree,
+        Tree::Node(Node {
+            leaf: Leaf::Number(22)
+        })
+    );
+    assert_eq!(tree.downcast::<Leaf>(), Some(Leaf::Number(22)));
+    assert_eq!(tree.downcast::<u32>(), Some(22));
+    assert_eq!(Tree::Empty.downcast::<u32>(), None);
+}
+
+#[test]
+fn downcasted_with_keys() {
+    let leaves = vec![
+        ("a", Leaf::Number(1)),
+        ("b", Leaf::Text("two".to_string())),
+        ("c", Leaf::Empty),
+        ("d", Leaf::Number(4)),
+    ];
+    let numbers: Vec<u32> = leaves.iter().map(|(_, leaf)| leaf).downcasted().collect();
+    assert_eq!(numbers, vec![1, 4]);
+    let numbers: Vec<(&str, u32)> = leaves.iter().cloned().downcasted_with().collect();
+    assert_eq!(numbers, vec![("a", 1), ("d", 4)]);
+}
+
+#[test]
+fn lifetime_parameters() {
+    let var = UniversalVar {
+        kind: ParameterKind::Lt,
+        var_index: VarIndex::ZERO,
+    };
+    let lt: Lt = var.upcast();
+    let parameter: Parameter = lt.data().clone().upcast();
+    assert_eq!(parameter, lt.clone().upcast());
+    assert_eq!(parameter.downcast::<LtData>(), Some(lt.data().clone()));
+    assert_eq!(parameter.downcast::<UniversalVar>(), Some(var));
+}
@@ -3,14 +3,20 @@
 //! references to the Fold trait that work both in this crate and others.
 //! Other crates that wish to use the Fold macro must re-export this module.
 
+pub use crate::cast::DowncastFrom;
 pub use crate::cast::DowncastTo;
+pub use crate::cast::Upcast;
 pub use crate::cast::UpcastFrom;
 pub use crate::fixed_point;
 pub use crate::fold::Fold;
 pub use crate::fold::SubstitutionFn;
+pub use crate::generate;
 pub use crate::grammar::Parameter;
 pub use crate::grammar::ParameterKind;
 pub use crate::grammar::Variable;
 pub use crate::parse;
+pub use crate::pretty;
+pub use serde;
 pub use crate::term::Term;
+pub use crate::visit::VarFlags;
 pub use crate::visit::Visit;
@@ -1,11 +1,73 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::fmt::Debug;
 use std::hash::Hash;
-use std::thread::LocalKey;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::{LocalKey, ThreadId};
+
+use crate::judgment::{cancel, proof_tree, stats};
 
 mod stack;
 pub use stack::FixedPointStack;
 
+thread_local! {
+    /// For each fixed-point computation in progress, of any judgment, from the outermost
+    /// to the innermost: the depth of the outermost computation whose provisional
+    /// outputs it observed, directly or through the computations it started. Starts
+    /// out as the depth of the computation itself.
+    ///
+    /// A computation that observed the outputs of an enclosing computation is part
+    /// of a cycle whose fixed point is not reached yet, so its outputs must not be
+    /// memoized. Judgments call each other, so this is tracked across all of them.
+    static DEPENDENCIES: RefCell<Vec<usize>> = RefCell::new(vec![]);
+
+    /// For each fixed-point computation in progress, from the outermost to the innermost:
+    /// the stack of its judgment and the index of its entry there, so that the inputs
+    /// of a cycle that does not converge can be reported.
+    static PROVENANCE: RefCell<Vec<(&'static dyn DescribeEntry, usize)>> = RefCell::new(vec![]);
+
+    /// For each negated premise being evaluated, from the outermost to the innermost:
+    /// the premise and the number of fixed-point computations in progress when its
+    /// evaluation started, see [`negation`].
+    static NEGATIONS: RefCell<Vec<(&'static str, usize)>> = RefCell::new(vec![]);
+
+    /// The number of times the computations in progress were marked as depending on
+    /// their context, see [`mark_context_dependent`].
+    static CONTEXT_DEPENDENT: Cell<usize> = Cell::new(0);
+
+    /// The depths of the fixed-point computations in progress that are hidden from those
+    /// started now, as they are not their ancestors (see [`Spawn::run`]).
+    static HIDDEN: RefCell<Vec<Range<usize>>> = RefCell::new(vec![]);
+
+    /// The generation of the memo tables that the computations on this thread use: the
+    /// tables drop the outputs of other generations when they are next used, see
+    /// [`clear_memo_tables`].
+    static MEMO_GENERATION: Cell<usize> = Cell::new(0);
+}
+
+/// The last generation of the memo tables handed out by [`clear_memo_tables`].
+static LAST_MEMO_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// After this many iterations without reaching a fixed point, a computation is taken
+/// not to converge. The next time the cycle through it comes around, the inputs of
+/// the cycle are reported with a panic rather than iterating forever.
+const MAX_ITERATIONS: usize = 100;
+
+/// Describes the input of an entry of a [`FixedPointStack`], of any judgment.
+trait DescribeEntry {
+    fn describe_entry(&'static self, index: usize) -> String;
+}
+
+impl<Input, Output> DescribeEntry for LocalKey<RefCell<FixedPointStack<Input, Output>>>
+where
+    Input: Value,
+    Output: Value,
+{
+    fn describe_entry(&'static self, index: usize) -> String {
+        self.with(|stack| format!("{:?}", stack.borrow().input(index)))
+    }
+}
+
 pub fn fixed_point<Input, Output>(
     tracing_span: impl Fn(&Input) -> tracing::Span,
     storage: &'static LocalKey<RefCell<FixedPointStack<Input, Output>>>,
@@ -52,32 +114,238 @@ where
     TracingSpan: Fn(&Input) -> tracing::Span,
 {
     fn apply(&self, input: Input) -> Output {
-        if let Some(r) = self.with_stack(|stack| stack.search(&input)) {
+        // Once cancelled, judgments no longer evaluate their rules, so that the
+        // proof in progress unwinds quickly; its outputs are discarded anyway.
+        if cancel::is_cancelled() {
+            return (self.default_value)(&input);
+        }
+
+        // The memo table is bypassed while recording proof trees, which need
+        // every judgment to be evaluated.
+        let memoize = !proof_tree::is_recording();
+
+        if memoize {
+            let generation = MEMO_GENERATION.with(Cell::get);
+            if let Some(r) = self.with_stack(|stack| stack.memoized(&input, generation)) {
+                tracing::debug!("memoized call to {:?}, yielding {:?}", input, r);
+                stats::memo_hit();
+                return r;
+            }
+        }
+
+        if let Some((r, depth, converging)) =
+            self.with_stack(|stack| stack.search(&input, is_visible))
+        {
             tracing::debug!("recursive call to {:?}, yielding {:?}", input, r);
+            stats::cycle_hit();
+            if !converging {
+                panic!("{}", cycle_report(depth));
+            }
+            if let Some((premise, start)) = NEGATIONS.with(|n| n.borrow().last().copied()) {
+                if depth < start {
+                    panic!("{}", unstratified_report(premise, depth));
+                }
+            }
+            DEPENDENCIES.with(|dependencies| {
+                let mut dependencies = dependencies.borrow_mut();
+                let top = dependencies.last_mut().unwrap();
+                *top = (*top).min(depth);
+            });
             return r;
         }
 
-        self.with_stack(|stack| {
+        let depth = DEPENDENCIES.with(|dependencies| {
+            let mut dependencies = dependencies.borrow_mut();
+            let depth = dependencies.len();
+            dependencies.push(depth);
+            depth
+        });
+
+        let context_dependent = CONTEXT_DEPENDENT.with(Cell::get);
+
+        let index = self.with_stack(|stack| {
             let default_value = (self.default_value)(&input);
-            stack.push(&input, default_value);
+            stack.push(&input, default_value, depth, is_visible)
         });
+        PROVENANCE.with(|provenance| provenance.borrow_mut().push((self.storage, index)));
 
+        let mut iterations = 0;
         loop {
+            iterations += 1;
+            stats::iteration();
+            if iterations > MAX_ITERATIONS {
+                self.with_stack(|stack| stack.mark_not_converging(&input));
+            }
+
             let span = (self.tracing_span)(&input);
             let _guard = span.enter();
             let output = (self.next_value)(input.clone());
             tracing::debug!(?output);
-            if !self.with_stack(|stack| stack.update_output(&input, output)) {
+            if !self.with_stack(|stack| stack.update_output(&input, output))
+                || cancel::is_cancelled()
+            {
                 break;
             } else {
                 tracing::debug!("output is different from previous iteration, re-executing until fixed point is reached");
             }
         }
 
-        self.with_stack(|stack| stack.pop(&input))
+        let output = self.with_stack(|stack| stack.pop(&input));
+        PROVENANCE.with(|provenance| provenance.borrow_mut().pop());
+
+        let min_dependency = DEPENDENCIES.with(|dependencies| {
+            let mut dependencies = dependencies.borrow_mut();
+            let min_dependency = dependencies.pop().unwrap();
+            if let Some(parent) = dependencies.last_mut() {
+                *parent = (*parent).min(min_dependency);
+            }
+            min_dependency
+        });
+
+        if memoize
+            && min_dependency == depth
+            && CONTEXT_DEPENDENT.with(Cell::get) == context_dependent
+            && !cancel::is_cancelled()
+        {
+            let generation = MEMO_GENERATION.with(Cell::get);
+            self.with_stack(|stack| stack.memoize(&input, &output, generation));
+        }
+
+        output
     }
 
     fn with_stack<R>(&self, f: impl FnOnce(&mut FixedPointStack<Input, Output>) -> R) -> R {
         self.storage.with(|v| f(&mut *v.borrow_mut()))
     }
 }
+
+/// Drops the memoized outputs of the judgments that this thread, and the jobs it
+/// [spawns](`Spawn`), computed so far. The memo tables only hold final outputs, so this
+/// never changes results, but they grow with every goal whose outputs are memoized:
+/// long-running hosts, like the REPL or the language server, call this between
+/// unrelated top-level operations so that the tables don't grow without bound.
+pub fn clear_memo_tables() {
+    let generation = LAST_MEMO_GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+    MEMO_GENERATION.with(|g| g.set(generation));
+}
+
+/// Marks the outputs of the fixed-point computations in progress as depending on more
+/// than their inputs, e.g., on how deeply they are nested in a search with a depth
+/// limit, so that they are not memoized. The computations they start later are not
+/// affected.
+pub fn mark_context_dependent() {
+    CONTEXT_DEPENDENT.with(|count| count.set(count.get() + 1));
+}
+
+/// Where a job that may run on another thread, or later on this one, was started from:
+/// the fixed-point computations and negated premises in progress at that point. Jobs
+/// are [run](`Spawn::run`) isolated from the computations that are in progress on their
+/// thread without being the job's ancestors (see [`crate::judgment::parallel`]).
+pub struct Spawn {
+    thread: ThreadId,
+    depth: usize,
+    negations: usize,
+    memo_generation: usize,
+}
+
+impl Spawn {
+    /// The current point of this thread.
+    pub fn here() -> Spawn {
+        Spawn {
+            thread: std::thread::current().id(),
+            depth: DEPENDENCIES.with(|dependencies| dependencies.borrow().len()),
+            negations: NEGATIONS.with(|negations| negations.borrow().len()),
+            memo_generation: MEMO_GENERATION.with(Cell::get),
+        }
+    }
+
+    /// Runs the job `op`, which observes the computations in progress at this spawn
+    /// point if it runs on the same thread, and no other computation in progress: a
+    /// thread that waits for the jobs it started may meanwhile run unrelated ones.
+    /// The computations that `op` starts are memoized as usual, as the memo tables only
+    /// hold final outputs, in the generation of the spawn point.
+    ///
+    /// Also returns whether the computations of `op` were [marked as depending on their
+    /// context](`mark_context_dependent`), which the computations in progress at the
+    /// spawn point then are as well.
+    pub fn run<R>(&self, op: impl FnOnce() -> R) -> (R, bool) {
+        struct Restore(Vec<(&'static str, usize)>, usize);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                HIDDEN.with(|hidden| hidden.borrow_mut().pop());
+                NEGATIONS.with(|negations| negations.borrow_mut().append(&mut self.0));
+                MEMO_GENERATION.with(|generation| generation.set(self.1));
+            }
+        }
+
+        let same_thread = self.thread == std::thread::current().id();
+        let depth = DEPENDENCIES.with(|dependencies| dependencies.borrow().len());
+        let (hidden, negations) = if same_thread {
+            (self.depth..depth, self.negations)
+        } else {
+            (0..depth, 0)
+        };
+        HIDDEN.with(|h| h.borrow_mut().push(hidden));
+        let _restore = Restore(
+            NEGATIONS.with(|n| n.borrow_mut().split_off(negations)),
+            MEMO_GENERATION.with(|g| g.replace(self.memo_generation)),
+        );
+
+        let context_dependent = CONTEXT_DEPENDENT.with(Cell::get);
+        let result = op();
+        let marked = CONTEXT_DEPENDENT.with(Cell::get) != context_dependent;
+        (result, marked)
+    }
+}
+
+/// Whether the fixed-point computation at `depth` is visible to those started now,
+/// see [`Spawn::run`].
+fn is_visible(depth: usize) -> bool {
+    HIDDEN.with(|hidden| !hidden.borrow().iter().any(|range| range.contains(&depth)))
+}
+
+/// Evaluates the negated premise `premise` with `op`, which holds if `op` yields no
+/// outputs. The outputs of a computation that is in progress are provisional, and may
+/// grow until its fixed point is reached, so a negated premise must not depend on them:
+/// if evaluating `premise` calls back into a computation that was in progress before,
+/// the rules are not stratified, and this panics.
+pub fn negation<R>(premise: &'static str, op: impl FnOnce() -> R) -> R {
+    let start = DEPENDENCIES.with(|dependencies| dependencies.borrow().len());
+    NEGATIONS.with(|negations| negations.borrow_mut().push((premise, start)));
+    let result = op();
+    NEGATIONS.with(|negations| negations.borrow_mut().pop());
+    result
+}
+
+/// The inputs of the fixed-point computations in progress from the one at `depth`.
+fn cycle_inputs(depth: usize) -> Vec<String> {
+    PROVENANCE.with(|provenance| {
+        provenance.borrow()[depth..]
+            .iter()
+            .map(|(storage, index)| storage.describe_entry(*index))
+            .collect()
+    })
+}
+
+/// Describes the cycle through the fixed-point computation at `depth`, which the
+/// innermost computation in progress has just called back into.
+fn cycle_report(depth: usize) -> String {
+    let inputs = cycle_inputs(depth);
+    let mut report =
+        format!("no fixed point reached after {MAX_ITERATIONS} iterations of the cycle:");
+    for input in inputs.iter().chain(&inputs[..1]) {
+        report.push_str(&format!("\n  {input}"));
+    }
+    report
+}
+
+/// Describes the cycle through the negated premise `premise` and the fixed-point
+/// computation at `depth`, which the innermost computation has just called back into.
+fn unstratified_report(premise: &str, depth: usize) -> String {
+    let inputs = cycle_inputs(depth);
+    let mut report = format!("unstratified negation: `{premise}` depends on the cycle:");
+    for input in inputs.iter().chain(&inputs[..1]) {
+        report.push_str(&format!("\n  {input}"));
+    }
+    report
+}
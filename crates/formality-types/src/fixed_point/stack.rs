@@ -1,13 +1,23 @@
+use std::collections::HashMap;
+
 use super::Value;
 
 pub struct FixedPointStack<Input, Output> {
     entries: Vec<StackEntry<Input, Output>>,
+
+    /// Memo table of final outputs, see [`Self::memoize`].
+    memo: HashMap<Input, Output>,
+
+    /// The generation of the memo table, see [`super::clear_memo_tables`].
+    memo_generation: usize,
 }
 
 impl<Input, Output> Default for FixedPointStack<Input, Output> {
     fn default() -> Self {
         Self {
             entries: Default::default(),
+            memo: Default::default(),
+            memo_generation: 0,
         }
     }
 }
@@ -22,6 +32,14 @@ struct StackEntry<Input, Output> {
     /// Initially false; set to true when the outputs of this rule
     /// are observed while it is being evaluated.
     has_dependents: bool,
+
+    /// The depth of this entry among all active fixed-point computations,
+    /// of this judgment or any other.
+    depth: usize,
+
+    /// Initially true; set to false once this entry has iterated too often
+    /// without reaching a fixed point.
+    converging: bool,
 }
 
 impl<Input, Output> FixedPointStack<Input, Output>
@@ -36,36 +54,82 @@ where
         top
     }
 
-    /// Search backwards through the stack, looking for the given input.
+    /// Search backwards through the stack, looking for the given input among the
+    /// entries whose depth is visible (see [`super::Spawn::run`]).
     ///
-    /// If it is found, return `Some` with the current outputs, and mark it
+    /// If it is found, return `Some` with the current outputs, the depth of the entry
+    /// and whether it is still [converging](`Self::mark_not_converging`), and mark it
     /// as needing fixed point iteration.
     ///
     /// If not, return `None`.
     ///
     /// The fixed-point mark is returned when the stack is [popped](`Self::pop`) and is used
     /// as part of the fixed point algorithm.
-    pub fn search(&mut self, input: &Input) -> Option<Output> {
+    pub fn search(
+        &mut self,
+        input: &Input,
+        is_visible: impl Fn(usize) -> bool,
+    ) -> Option<(Output, usize, bool)> {
         for entry in &mut self.entries {
-            if entry.input == *input {
+            if entry.input == *input && is_visible(entry.depth) {
                 entry.has_dependents = true;
-                return Some(entry.output.clone());
+                return Some((entry.output.clone(), entry.depth, entry.converging));
             }
         }
 
         None
     }
 
+    /// Look up the final outputs for `input` in the memo table of the current `generation`.
+    pub fn memoized(&mut self, input: &Input, generation: usize) -> Option<Output> {
+        self.memo(generation).get(input).cloned()
+    }
+
+    /// Record the final outputs for `input`. Outputs are final if their computation
+    /// did not observe the (provisional) outputs of any computation still in progress.
+    pub fn memoize(&mut self, input: &Input, output: &Output, generation: usize) {
+        self.memo(generation).insert(input.clone(), output.clone());
+    }
+
+    /// The memo table of `generation`, dropping the outputs of earlier generations.
+    fn memo(&mut self, generation: usize) -> &mut HashMap<Input, Output> {
+        if self.memo_generation != generation {
+            self.memo = HashMap::new();
+            self.memo_generation = generation;
+        }
+        &mut self.memo
+    }
+
     /// Push an entry onto the stack, indicating it is currently being evaluated.
-    /// There must not already be an entry for `input`.
-    pub fn push(&mut self, input: &Input, output: Output) {
-        assert!(self.search(input).is_none());
+    /// There must not already be a visible entry for `input`. Returns the index of the entry.
+    pub fn push(
+        &mut self,
+        input: &Input,
+        output: Output,
+        depth: usize,
+        is_visible: impl Fn(usize) -> bool,
+    ) -> usize {
+        assert!(self.search(input, is_visible).is_none());
 
         self.entries.push(StackEntry {
             input: input.clone(),
             output,
             has_dependents: false,
+            depth,
+            converging: true,
         });
+        self.entries.len() - 1
+    }
+
+    /// The input of the entry at `index`.
+    pub fn input(&self, index: usize) -> &Input {
+        &self.entries[index].input
+    }
+
+    /// Marks the top-most stack entry, which must be for `input`, as not reaching a
+    /// fixed point, so that the next [search](`Self::search`) for it reports the cycle.
+    pub fn mark_not_converging(&mut self, input: &Input) {
+        self.top_frame(input).converging = false;
     }
 
     /// Add outputs to the top-most stack entry, which must be for `input`.
@@ -3,10 +3,15 @@ use std::sync::Arc;
 use crate::{
     cast::Upcast,
     collections::Set,
-    grammar::{Const, ConstData, Lt, LtData, Parameter, Ty, TyData, ValTree, Variable},
+    grammar::{
+        Abi, AssociatedTyName, Const, ConstData, Lt, LtData, Parameter, Ty, TyData, ValTree,
+        Variable,
+    },
     visit::Visit,
 };
 
+mod test;
+
 /// Invoked for each variable that we find when folding, ignoring variables bound by binders
 /// that we traverse. The arguments are as follows:
 ///
@@ -15,7 +20,10 @@ use crate::{
 pub type SubstitutionFn<'a> = &'a mut dyn FnMut(Variable) -> Option<Parameter>;
 
 pub trait Fold: Sized + Visit {
-    /// Replace uses of variables with values from the substitution.
+    /// Replace uses of variables with values from the substitution. Types and consts
+    /// without any variables (see [`Visit::var_flags`]) are returned as they are,
+    /// without walking them, and those that the substitution leaves unchanged keep
+    /// their allocation (see [`Ty::with_data`]).
     fn substitute(&self, substitution_fn: SubstitutionFn<'_>) -> Self;
 
     /// Produce a version of this term where any debruijn indices which appear free are incremented by one.
@@ -58,28 +66,47 @@ impl<T: Fold> Fold for Arc<T> {
     }
 }
 
+impl Fold for AssociatedTyName {
+    fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {
+        self.clone()
+    }
+}
+
+impl Fold for Abi {
+    fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {
+        *self
+    }
+}
+
 impl Fold for Ty {
     fn substitute(&self, substitution_fn: SubstitutionFn<'_>) -> Self {
-        match self.data() {
-            TyData::RigidTy(v) => v.substitute(substitution_fn).upcast(),
-            TyData::AliasTy(v) => v.substitute(substitution_fn).upcast(),
-            TyData::PredicateTy(v) => v.substitute(substitution_fn).upcast(),
+        if self.var_flags().is_empty() {
+            return self.clone();
+        }
+        let data = match self.data() {
+            TyData::RigidTy(v) => TyData::RigidTy(v.substitute(substitution_fn)),
+            TyData::AliasTy(v) => TyData::AliasTy(v.substitute(substitution_fn)),
+            TyData::PredicateTy(v) => TyData::PredicateTy(v.substitute(substitution_fn)),
             TyData::Variable(v) => match substitution_fn(*v) {
-                None => self.clone(),
-                Some(Parameter::Ty(t)) => t,
+                None => return self.clone(),
+                Some(Parameter::Ty(t)) => return t,
                 Some(param) => panic!("ill-kinded substitute: expected type, got {param:?}"),
             },
-        }
+        };
+        self.with_data(data)
     }
 }
 
 impl Fold for Const {
     fn substitute(&self, substitution_fn: SubstitutionFn<'_>) -> Self {
+        if self.var_flags().is_empty() {
+            return self.clone();
+        }
         match self.data() {
-            ConstData::Value(v, ty) => Self::valtree(
+            ConstData::Value(v, ty) => self.with_data(ConstData::Value(
                 v.substitute(substitution_fn),
                 ty.substitute(substitution_fn),
-            ),
+            )),
             ConstData::Variable(v) => match substitution_fn(*v) {
                 None => self.clone(),
                 Some(Parameter::Const(c)) => c,
@@ -120,6 +147,24 @@ impl Fold for u32 {
     }
 }
 
+impl Fold for u64 {
+    fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {
+        *self
+    }
+}
+
+impl Fold for bool {
+    fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {
+        *self
+    }
+}
+
+impl Fold for u128 {
+    fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {
+        *self
+    }
+}
+
 impl Fold for () {
     fn substitute(&self, _substitution_fn: SubstitutionFn<'_>) -> Self {}
 }
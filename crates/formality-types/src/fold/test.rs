@@ -0,0 +1,86 @@
+#![cfg(test)]
+
+use crate::{
+    fold::Fold,
+    grammar::{
+        Binder, ExistentialVar, Parameter, ParameterKind, RigidTy, Ty, TyData, UniversalVar,
+        VarIndex, Variable,
+    },
+    parse::term,
+    visit::{VarFlags, Visit},
+};
+
+fn existential_var() -> ExistentialVar {
+    ExistentialVar {
+        kind: ParameterKind::Ty,
+        var_index: VarIndex::ZERO,
+    }
+}
+
+fn existential() -> Ty {
+    Ty::new(existential_var())
+}
+
+fn universal() -> Ty {
+    Ty::new(UniversalVar {
+        kind: ParameterKind::Ty,
+        var_index: VarIndex::ZERO,
+    })
+}
+
+#[test]
+fn var_flags() {
+    let ground: Ty = term("Vec<(u32, String)>");
+    assert_eq!(ground.var_flags(), VarFlags::NONE);
+
+    let mixed = Ty::tuple(&[ground.clone(), existential(), universal()]);
+    assert_eq!(
+        mixed.var_flags(),
+        VarFlags::EXISTENTIAL | VarFlags::UNIVERSAL
+    );
+    assert!(!mixed.var_flags().contains(VarFlags::BOUND));
+
+    // The variables bound within a term count too, as substituting shifts them.
+    let binder: Binder<Ty> = Binder::new(vec![existential_var()], mixed);
+    assert_eq!(binder.var_flags(), VarFlags::BOUND | VarFlags::UNIVERSAL);
+}
+
+#[test]
+fn substitute_skips_ground_types() {
+    let ground: Ty = term("Vec<(u32, String)>");
+    let substituted = ground.substitute(&mut |v| panic!("visited {v:?}"));
+    assert_eq!(substituted, ground);
+
+    let mixed = Ty::tuple(&[ground.clone(), existential()]);
+    let mut visited = vec![];
+    let substituted = mixed.substitute(&mut |v| {
+        visited.push(v);
+        Some(ground.to_parameter())
+    });
+    assert_eq!(visited.len(), 1);
+    assert_eq!(substituted, Ty::tuple(&[ground.clone(), ground]));
+}
+
+#[test]
+fn substitute_keeps_unchanged_types() {
+    let unchanged = Ty::tuple(&[term("Vec<u32>"), universal()]);
+    let ty = Ty::tuple(&[unchanged.clone(), existential()]);
+
+    // Replacing only the existential variable rebuilds the outer tuple alone.
+    let substituted = ty.substitute(&mut |v| match v {
+        Variable::ExistentialVar(_) => Some(term::<Ty>("u32").to_parameter()),
+        _ => None,
+    });
+    let TyData::RigidTy(RigidTy { parameters, .. }) = substituted.data() else {
+        panic!("not a tuple: {substituted:?}")
+    };
+    let Parameter::Ty(first) = &parameters[0] else {
+        panic!("not a type: {:?}", parameters[0])
+    };
+    assert!(first.ptr_eq(&unchanged));
+    assert!(!substituted.ptr_eq(&ty));
+
+    // Replacing nothing rebuilds nothing.
+    let substituted = ty.substitute(&mut |_| None);
+    assert!(substituted.ptr_eq(&ty));
+}
@@ -0,0 +1,230 @@
+//! Generating arbitrary terms, to check properties that should hold of all terms of a type.
+//!
+//! The `#[term]` macro derives [`Generate`] by picking a random variant and generating
+//! its fields. Terms are generated up to a depth, below which enums take their first
+//! variant and collections are empty, so the first variant of an enum should not be
+//! recursive. Generated terms only contain variables bound by binders within them,
+//! so that they can be printed and parsed back (see [`check_round_trip`]), and
+//! [`check_for_all`] checks other properties, e.g., of the prover, against them.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::collections::Set;
+use crate::grammar::{BoundVar, DebruijnIndex, ParameterKind, VarIndex};
+use crate::parse::try_term;
+use crate::pretty::to_string;
+use crate::term::Term;
+
+pub trait Generate: Sized {
+    fn generate(g: &mut Generator) -> Self;
+}
+
+/// The depth that [`Generator::new`] generates terms up to.
+const DEPTH: usize = 6;
+
+/// Names for the ids of generated terms, none of which is a keyword or the name that
+/// the pretty-printer gives to a bound variable.
+const NAMES: &[&str] = &["foo", "bar", "Baz"];
+
+/// A source of random choices, seeded so that a failing term can be generated again.
+#[derive(Clone, Debug)]
+pub struct Generator {
+    /// The state of a SplitMix64 generator.
+    state: u64,
+
+    /// The remaining depth; see [`Generator::nested`].
+    depth: usize,
+
+    /// The kinds of the variables of the binders that the generated term is within,
+    /// the innermost one last.
+    binders: Vec<Vec<ParameterKind>>,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        Generator {
+            state: seed,
+            depth: DEPTH,
+            binders: vec![],
+        }
+    }
+
+    /// A random number in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0);
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        (z % n as u64) as usize
+    }
+
+    /// A random element of `items`.
+    pub fn choose<T: Clone>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())].clone()
+    }
+
+    /// Which of the `n` variants of an enum to generate: the first one once the
+    /// depth is exhausted.
+    pub fn variant(&mut self, n: usize) -> usize {
+        if self.depth == 0 {
+            0
+        } else {
+            self.below(n)
+        }
+    }
+
+    /// The number of elements of a collection: none once the depth is exhausted.
+    pub fn collection_len(&mut self) -> usize {
+        if self.depth == 0 {
+            0
+        } else {
+            self.below(3)
+        }
+    }
+
+    /// A name for an id.
+    pub fn name(&mut self) -> String {
+        self.choose(NAMES).to_string()
+    }
+
+    /// Generates the parts of a term one level deeper.
+    pub fn nested<T>(&mut self, op: impl FnOnce(&mut Self) -> T) -> T {
+        let depth = self.depth;
+        self.depth = depth.saturating_sub(1);
+        let result = op(self);
+        self.depth = depth;
+        result
+    }
+
+    /// Generates a term within a binder of variables of the given kinds.
+    pub fn with_binder<T>(
+        &mut self,
+        kinds: &[ParameterKind],
+        op: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        self.binders.push(kinds.to_vec());
+        let result = op(self);
+        self.binders.pop();
+        result
+    }
+
+    /// A random variable of the given kind bound by a binder in scope, if there is one.
+    pub fn bound_var(&mut self, kind: ParameterKind) -> Option<BoundVar> {
+        let vars: Vec<BoundVar> = self
+            .binders
+            .iter()
+            .rev()
+            .zip(0..)
+            .flat_map(|(kinds, debruijn)| {
+                kinds
+                    .iter()
+                    .zip(0..)
+                    .filter(|(k, _)| **k == kind)
+                    .map(move |(&kind, index)| BoundVar {
+                        debruijn: Some(DebruijnIndex { index: debruijn }),
+                        var_index: VarIndex { index },
+                        kind,
+                    })
+            })
+            .collect();
+        if vars.is_empty() {
+            None
+        } else {
+            Some(self.choose(&vars))
+        }
+    }
+}
+
+/// Checks that `property` holds of the terms of type `T` generated from the first
+/// `count` seeds, e.g., that proving `a = b` succeeds just when proving `b = a` does.
+/// The property returns an error, or panics, to report a term it does not hold of.
+#[track_caller]
+pub fn check_for_all<T: Generate + Debug>(
+    count: u64,
+    mut property: impl FnMut(&T) -> Result<(), String>,
+) {
+    for seed in 0..count {
+        let t = T::generate(&mut Generator::new(seed));
+        if let Err(message) = property(&t) {
+            panic!("seed {seed}: {message}\ngenerated: {t:?}");
+        }
+    }
+}
+
+/// Checks that the terms of type `T` generated from the first `count` seeds
+/// print as text that parses back to the same term.
+#[track_caller]
+pub fn check_round_trip<T: Term + Generate>(count: u64) {
+    check_for_all(count, |t: &T| {
+        let text = to_string(t);
+        let parsed = std::panic::catch_unwind(|| try_term::<T>(&text))
+            .map_err(|_| format!("parsing `{text}` panicked"))?;
+        match parsed {
+            Ok(parsed) if parsed == *t => Ok(()),
+            Ok(parsed) => Err(format!(
+                "`{text}` parses as a different term\nparsed:    {parsed:?}"
+            )),
+            Err(err) => Err(format!("`{text}` does not parse: {err}")),
+        }
+    })
+}
+
+impl<T: Generate> Generate for Vec<T> {
+    fn generate(g: &mut Generator) -> Self {
+        let len = g.collection_len();
+        (0..len).map(|_| T::generate(g)).collect()
+    }
+}
+
+impl<T: Generate + Ord> Generate for Set<T> {
+    fn generate(g: &mut Generator) -> Self {
+        let len = g.collection_len();
+        (0..len).map(|_| T::generate(g)).collect()
+    }
+}
+
+impl<T: Generate> Generate for Option<T> {
+    fn generate(g: &mut Generator) -> Self {
+        match g.variant(2) {
+            0 => None,
+            _ => Some(T::generate(g)),
+        }
+    }
+}
+
+impl<T: Generate> Generate for Arc<T> {
+    fn generate(g: &mut Generator) -> Self {
+        Arc::new(T::generate(g))
+    }
+}
+
+impl<A: Generate, B: Generate> Generate for (A, B) {
+    fn generate(g: &mut Generator) -> Self {
+        (A::generate(g), B::generate(g))
+    }
+}
+
+impl Generate for () {
+    fn generate(_g: &mut Generator) -> Self {}
+}
+
+impl Generate for usize {
+    fn generate(g: &mut Generator) -> Self {
+        g.below(3)
+    }
+}
+
+impl Generate for u32 {
+    fn generate(g: &mut Generator) -> Self {
+        g.below(3) as u32
+    }
+}
+
+impl Generate for bool {
+    fn generate(g: &mut Generator) -> Self {
+        g.below(2) == 1
+    }
+}
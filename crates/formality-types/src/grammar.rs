@@ -3,6 +3,7 @@ mod consts;
 mod formulas;
 mod ids;
 mod kinded;
+pub mod lang_items;
 mod ty;
 mod wc;
 
@@ -4,18 +4,23 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 
 use anyhow::bail;
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     cast::{Downcast, DowncastFrom, DowncastTo, To, Upcast, UpcastFrom},
     fold::Fold,
     fold::SubstitutionFn,
+    generate::{Generate, Generator},
     grammar::VarIndex,
-    visit::Visit,
+    pretty::{Doc, DocBuilder, Pretty, PrettyCx},
+    visit::{VarFlags, Visit},
 };
 
 use super::{BoundVar, DebruijnIndex, Fallible, Parameter, ParameterKind, Substitution, Variable};
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+mod test;
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
 pub struct Binder<T> {
     kinds: Vec<ParameterKind>,
     term: T,
@@ -73,6 +78,15 @@ impl<T: Fold> Binder<T> {
             .unzip();
 
         let term = substitution.apply(&term);
+        if cfg!(debug_assertions) {
+            // The term has no dangling indices but those of the variables bound here.
+            for v in escaping_bound_vars(&term) {
+                let bound = v.debruijn.is_none()
+                    || (v.debruijn == Some(DebruijnIndex::INNERMOST)
+                        && v.var_index.index < kinds.len());
+                assert!(bound, "Binder::new: {}", DebruijnError::Dangling(v));
+            }
+        }
         Binder { kinds, term }
     }
 
@@ -135,6 +149,10 @@ impl<T: Fold> Binder<T> {
             .map(|(&kind, index)| op(kind, VarIndex { index }))
             .collect();
 
+        if cfg!(debug_assertions) {
+            assert_no_dangling_indices(&substitution, "Binder::instantiate");
+        }
+
         self.term.substitute(&mut |var| match var {
             Variable::BoundVar(BoundVar {
                 debruijn: Some(DebruijnIndex::INNERMOST),
@@ -162,6 +180,46 @@ impl<T: Fold> Binder<T> {
         let u = op(t);
         Binder::new(vars, u)
     }
+
+    /// Combines this binder with `other`, which binds variables of the same kinds, into
+    /// one binder over both terms, whose variables are shared. Returns an err if the
+    /// kinds differ.
+    pub fn zip<U: Fold>(&self, other: &Binder<U>) -> Fallible<Binder<(T, U)>> {
+        if self.kinds != other.kinds {
+            bail!(
+                "distinct binder kinds: {:?} vs {:?}",
+                self.kinds,
+                other.kinds
+            );
+        }
+        let (vars, t) = self.open();
+        let u = other.instantiate_with(&vars)?;
+        Ok(Binder::new(vars, (t, u)))
+    }
+
+    /// Splits this binder in two: an outer binder for its first `len` variables, and
+    /// within it an inner binder for the rest. [`Binder::fuse`] is the inverse.
+    pub fn split(&self, len: usize) -> Binder<Binder<T>> {
+        assert!(
+            len <= self.len(),
+            "cannot split {len} of {} variables",
+            self.len()
+        );
+        let (vars, t) = self.open();
+        let (outer, inner) = vars.split_at(len);
+        Binder::new(outer, Binder::new(inner, t))
+    }
+}
+
+impl<T: Fold> Binder<Binder<T>> {
+    /// Fuses nested binders into one, binding the variables of the outer binder followed
+    /// by those of the inner one.
+    pub fn fuse(&self) -> Binder<T> {
+        let (mut vars, inner) = self.open();
+        let (inner_vars, t) = inner.open();
+        vars.extend(inner_vars);
+        Binder::new(vars, t)
+    }
 }
 
 /// Creates a fresh bound var of the given kind that is not yet part of a binder.
@@ -185,6 +243,10 @@ impl<T: Visit> Visit for Binder<T> {
         self.term.free_variables()
     }
 
+    fn var_flags(&self) -> VarFlags {
+        self.term.var_flags()
+    }
+
     fn size(&self) -> usize {
         self.term.size()
     }
@@ -214,16 +276,70 @@ impl<T: Fold> Fold for Binder<T> {
             term,
         }
     }
+}
 
-    fn shift_in(&self) -> Self {
-        let term = self.term.shift_in();
-        Binder {
-            kinds: self.kinds.clone(),
-            term,
+/// A bound variable that escapes a term, see [`validate_debruijn`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DebruijnError {
+    /// A variable whose debruijn index refers past the binders of the term, e.g. one
+    /// taken out of a binder with [`Binder::peek`]. Substituted into a binder, it would
+    /// be captured by that binder.
+    Dangling(BoundVar),
+
+    /// A variable that [`Binder::open`] created and that was not closed again with
+    /// [`Binder::new`].
+    Unclosed(BoundVar),
+}
+
+impl std::fmt::Display for DebruijnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DebruijnError::Dangling(v) => write!(f, "dangling bound variable {v:?}"),
+            DebruijnError::Unclosed(v) => write!(f, "bound variable {v:?} was never closed"),
         }
     }
 }
 
+impl std::error::Error for DebruijnError {}
+
+/// Checks that no bound variable escapes `term`, a term outside of any binder: each
+/// variable with a debruijn index must be bound by a binder within `term`, and there
+/// must be no variable with `debruijn: None`, i.e., every variable that [`Binder::open`]
+/// created must have been closed with [`Binder::new`]. Bugs that break this otherwise
+/// show up as silently wrong results.
+pub fn validate_debruijn(term: &impl Fold) -> Result<(), DebruijnError> {
+    match escaping_bound_vars(term).first() {
+        None => Ok(()),
+        Some(&v @ BoundVar { debruijn: None, .. }) => Err(DebruijnError::Unclosed(v)),
+        Some(&v) => Err(DebruijnError::Dangling(v)),
+    }
+}
+
+/// The bound variables that escape `term`, with their debruijn indices relative to
+/// the outside of `term`.
+fn escaping_bound_vars(term: &impl Fold) -> Vec<BoundVar> {
+    let mut escaping = vec![];
+    term.substitute(&mut |v| {
+        if let Variable::BoundVar(v) = v {
+            escaping.push(v);
+        }
+        None
+    });
+    escaping
+}
+
+/// For the debug builds: panics if `term`, which may be within other binders and so
+/// contain open variables, has dangling indices.
+fn assert_no_dangling_indices(term: &impl Fold, context: &str) {
+    for v in escaping_bound_vars(term) {
+        assert!(
+            v.debruijn.is_none(),
+            "{context}: {}",
+            DebruijnError::Dangling(v)
+        );
+    }
+}
+
 impl<T, U> UpcastFrom<Binder<T>> for Binder<U>
 where
     T: Clone,
@@ -270,3 +386,49 @@ where
         Ok(())
     }
 }
+
+impl<T: Generate> Binder<T> {
+    /// Generates a binder of variables of the given kinds, whose term can refer to them.
+    pub fn generate_with_kinds(g: &mut Generator, kinds: Vec<ParameterKind>) -> Self {
+        let term = g.with_binder(&kinds, T::generate);
+        Binder { kinds, term }
+    }
+}
+
+impl<T: Generate> Generate for Binder<T> {
+    fn generate(g: &mut Generator) -> Self {
+        let kinds = Generate::generate(g);
+        Binder::generate_with_kinds(g, kinds)
+    }
+}
+
+/// `<ty T0, lt a0> term`, naming the bound variables.
+impl<T> Pretty for Binder<T>
+where
+    T: Pretty,
+{
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        let (cx, names) = cx.with_binder(&self.kinds);
+        let bindings = self
+            .kinds
+            .iter()
+            .zip(names)
+            .map(|(kind, name)| Doc::text(format!("{kind:?} {name}")));
+        let mut b = DocBuilder::default();
+        b.glue();
+        b.text("<");
+        b.push(Doc::list(bindings));
+        b.text(">");
+        b.push(self.term.pretty(&cx));
+        b.group()
+    }
+}
+
+impl<T> std::fmt::Display for Binder<T>
+where
+    T: Pretty,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&crate::pretty::to_string(self))
+    }
+}
@@ -0,0 +1,82 @@
+#![cfg(test)]
+
+use crate::{
+    cast::Upcast,
+    fold::Fold,
+    grammar::{AdtId, Binder, DebruijnError, Parameter, ParameterKind, Ty},
+};
+
+use super::{fresh_bound_var, validate_debruijn};
+
+/// `<ty T> Vec<T>`
+fn vec_binder() -> Binder<Ty> {
+    let t = fresh_bound_var(ParameterKind::Ty);
+    Binder::new(vec![t], Ty::rigid(AdtId::new("Vec"), vec![t.ty()]))
+}
+
+#[test]
+fn validate_debruijn_errors() {
+    let binder = vec_binder();
+    assert_eq!(validate_debruijn(&binder), Ok(()));
+
+    let (vars, ty) = binder.open();
+    assert_eq!(
+        validate_debruijn(&ty),
+        Err(DebruijnError::Unclosed(vars[0]))
+    );
+    assert_eq!(validate_debruijn(&Binder::new(vars, ty)), Ok(()));
+
+    let Err(DebruijnError::Dangling(v)) = validate_debruijn(binder.peek()) else {
+        panic!("`peek` should expose a dangling variable")
+    };
+    assert_eq!(v.var_index.index, 0);
+}
+
+#[test]
+fn shift_in_binder() {
+    // Shifting in only affects the indices that escape the binder, of which there are
+    // none, not those of its own variables.
+    let binder = vec_binder();
+    assert_eq!(binder.shift_in(), binder);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "Binder::instantiate: dangling bound variable")]
+fn instantiate_with_dangling() {
+    let binder = vec_binder();
+    let dangling = binder.peek().clone();
+    let _ = binder.instantiate_with(&[dangling]);
+}
+
+/// `<ty T, lt L> Ref<L, T>`
+fn ref_binder() -> Binder<Ty> {
+    let t = fresh_bound_var(ParameterKind::Ty);
+    let l = fresh_bound_var(ParameterKind::Lt);
+    let parameters: Vec<Parameter> = vec![l.lt().upcast(), t.ty().upcast()];
+    Binder::new(vec![t, l], Ty::rigid(AdtId::new("Ref"), parameters))
+}
+
+#[test]
+fn split_and_fuse() {
+    let binder = ref_binder();
+    let split = binder.split(1);
+    assert_eq!(split.kinds(), &[ParameterKind::Ty]);
+    assert_eq!(split.peek().kinds(), &[ParameterKind::Lt]);
+    assert_eq!(validate_debruijn(&split), Ok(()));
+    assert_eq!(split.fuse(), binder);
+
+    assert_eq!(binder.split(0).fuse(), binder);
+    assert_eq!(binder.split(2).fuse(), binder);
+}
+
+#[test]
+fn zip() {
+    let zipped = vec_binder().zip(&vec_binder()).unwrap();
+    let (vars, (a, b)) = zipped.open();
+    assert_eq!(a, b);
+    assert_eq!(a, Ty::rigid(AdtId::new("Vec"), vec![vars[0].ty()]));
+
+    let err = vec_binder().zip(&ref_binder()).unwrap_err();
+    assert_eq!(err.to_string(), "distinct binder kinds: [ty] vs [ty, lt]");
+}
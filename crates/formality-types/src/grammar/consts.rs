@@ -4,10 +4,12 @@ use crate::cast::{DowncastTo, Upcast, UpcastFrom};
 
 use super::{Parameter, Ty, Variable};
 use formality_macros::{term, Visit};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 pub use valtree::*;
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Visit)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Const {
     data: Arc<ConstData>,
 }
@@ -16,6 +18,16 @@ impl Const {
         &self.data
     }
 
+    /// The const with `data`, which is `self` (sharing its allocation) if `data` is the
+    /// same as its own, see [`Ty::with_data`].
+    pub fn with_data(&self, data: ConstData) -> Const {
+        if *self.data == data {
+            self.clone()
+        } else {
+            Const::new(data)
+        }
+    }
+
     pub fn new(data: impl Upcast<ConstData>) -> Self {
         Self {
             data: Arc::new(data.upcast()),
@@ -41,7 +53,7 @@ impl Const {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Visit)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize)]
 pub enum ConstData {
     Value(ValTree, Ty),
     Variable(Variable),
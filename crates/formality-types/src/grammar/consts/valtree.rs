@@ -1,10 +1,11 @@
 use formality_macros::Visit;
+use serde::{Deserialize, Serialize};
 
 use crate::cast::{Upcast, UpcastFrom};
 
 use super::Bool;
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Visit)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize)]
 pub enum ValTree {
     Leaf(Scalar),
     Branches(Vec<ValTree>),
@@ -19,7 +20,7 @@ impl std::fmt::Debug for ValTree {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Visit)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize)]
 pub struct Scalar {
     bits: u128,
 }
@@ -56,6 +57,10 @@ impl UpcastFrom<Self> for ValTree {
 
 impl UpcastFrom<Bool> for Scalar {
     fn upcast_from(term: Bool) -> Self {
-        Scalar { bits: term as u128 }
+        let bits = match term {
+            Bool::True => 1,
+            Bool::False => 0,
+        };
+        Scalar { bits }
     }
 }
@@ -2,15 +2,17 @@ use formality_macros::term;
 
 use crate::cast::To;
 use crate::cast::Upcast;
-use crate::cast_impl;
 
 use super::AliasName;
 use super::AliasTy;
 use super::Const;
 use super::Parameter;
 use super::Parameters;
+use super::RigidName;
+use super::RigidTy;
 use super::TraitId;
 use super::Ty;
+use super::TyData;
 
 pub type Fallible<T> = anyhow::Result<T>;
 
@@ -65,6 +67,31 @@ impl Ty {
     pub fn well_formed(&self) -> Relation {
         Relation::WellFormed(self.upcast())
     }
+
+    /// The outlives bounds that this type being well formed implies: `T: 'a` for each
+    /// reference `&'a T` in it, as in rustc's implied bounds. Types under binders are skipped.
+    pub fn implied_outlives(&self) -> Vec<Relation> {
+        let mut bounds = vec![];
+        let parameters = match self.data() {
+            TyData::RigidTy(RigidTy { name, parameters }) => {
+                if let (RigidName::Ref(_), [lt, ty]) = (name, &parameters[..]) {
+                    bounds.push(ty.outlives(lt));
+                }
+                parameters
+            }
+            TyData::AliasTy(AliasTy {
+                name: _,
+                parameters,
+            }) => parameters,
+            TyData::PredicateTy(_) | TyData::Variable(_) => return bounds,
+        };
+        for p in parameters {
+            if let Parameter::Ty(ty) = p {
+                bounds.extend(ty.implied_outlives());
+            }
+        }
+        bounds
+    }
 }
 
 impl Parameter {
@@ -94,6 +121,8 @@ pub enum Skeleton {
     Equals,
     Sub,
     Outlives,
+    Coerce,
+    Receiver,
 }
 
 impl Predicate {
@@ -172,6 +201,15 @@ pub enum Relation {
 
     #[grammar(@wf($v0))]
     WellFormed(Parameter),
+
+    /// A value of the first type can be coerced to the second type.
+    #[grammar($v0 ~> $v1)]
+    Coerce(Parameter, Parameter),
+
+    /// The first type can be the type of `self` in a method whose `Self` is the second type:
+    /// they are equal, or the first type derefs to a valid receiver.
+    #[grammar(@receiver($v0, $v1))]
+    Receiver(Parameter, Parameter),
 }
 
 impl Relation {
@@ -187,6 +225,14 @@ impl Relation {
         Self::Sub(p1.upcast(), p2.upcast())
     }
 
+    pub fn coerce(p1: impl Upcast<Parameter>, p2: impl Upcast<Parameter>) -> Self {
+        Self::Coerce(p1.upcast(), p2.upcast())
+    }
+
+    pub fn receiver(p1: impl Upcast<Parameter>, p2: impl Upcast<Parameter>) -> Self {
+        Self::Receiver(p1.upcast(), p2.upcast())
+    }
+
     #[tracing::instrument(level = "trace", ret)]
     pub fn debone(&self) -> (Skeleton, Vec<Parameter>) {
         match self {
@@ -194,6 +240,8 @@ impl Relation {
             Relation::Sub(a, b) => (Skeleton::Sub, vec![a.clone(), b.clone()]),
             Relation::Outlives(a, b) => (Skeleton::Outlives, vec![a.clone(), b.clone()]),
             Relation::WellFormed(p) => (Skeleton::WellFormed, vec![p.clone()]),
+            Relation::Coerce(a, b) => (Skeleton::Coerce, vec![a.clone(), b.clone()]),
+            Relation::Receiver(a, b) => (Skeleton::Receiver, vec![a.clone(), b.clone()]),
         }
     }
 }
@@ -229,6 +277,7 @@ impl TraitId {
 ///
 /// We need a better name for this lol.
 #[term]
+#[cast(TraitRef <: Predicate)]
 pub enum PR {
     #[cast]
     Predicate(Predicate),
@@ -262,7 +311,3 @@ macro_rules! debone_impl {
 debone_impl!(PR);
 debone_impl!(Predicate);
 debone_impl!(Relation);
-
-// Transitive casting impls:
-
-cast_impl!((TraitRef) <: (Predicate) <: (PR));
@@ -1,6 +1,12 @@
 #[macro_export]
 macro_rules! id {
     ($n:ident) => {
+        $crate::id!($n, identifier);
+    };
+
+    // Ids parsed with `$parse` instead of as a plain identifier,
+    // e.g. `path` for the names of items that can be defined in modules.
+    ($n:ident, $parse:ident) => {
         #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
         pub struct $n {
             data: std::sync::Arc<String>,
@@ -10,7 +16,7 @@ macro_rules! id {
             use $crate::fold::{self, Fold};
             use $crate::grammar::Variable;
             use $crate::parse::{self, Parse};
-            use $crate::visit::Visit;
+            use $crate::visit::{VarFlags, Visit};
 
             $crate::cast_impl!($n);
 
@@ -35,6 +41,10 @@ macro_rules! id {
                     vec![]
                 }
 
+                fn var_flags(&self) -> VarFlags {
+                    VarFlags::NONE
+                }
+
                 fn size(&self) -> usize {
                     1
                 }
@@ -50,7 +60,7 @@ macro_rules! id {
 
             impl Parse for $n {
                 fn parse<'t>(_scope: &parse::Scope, text: &'t str) -> parse::ParseResult<'t, Self> {
-                    let (string, text) = parse::identifier(text)?;
+                    let (string, text) = parse::$parse(text)?;
                     let n = $n::new(&string);
                     Ok((n, text))
                 }
@@ -61,13 +71,44 @@ macro_rules! id {
                     write!(f, "{}", &self.data)
                 }
             }
+
+            impl $crate::pretty::Pretty for $n {}
+
+            /// Ids are serialized as their strings.
+            impl $crate::derive_links::serde::Serialize for $n {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: $crate::derive_links::serde::Serializer,
+                {
+                    serializer.serialize_str(&self.data)
+                }
+            }
+
+            impl<'de> $crate::derive_links::serde::Deserialize<'de> for $n {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: $crate::derive_links::serde::Deserializer<'de>,
+                {
+                    let s = <String as $crate::derive_links::serde::Deserialize>::deserialize(
+                        deserializer,
+                    )?;
+                    Ok($n::new(&s))
+                }
+            }
+
+            impl $crate::generate::Generate for $n {
+                fn generate(g: &mut $crate::generate::Generator) -> Self {
+                    $n::new(&g.name())
+                }
+            }
         };
     };
 }
 
-id!(FnId);
-id!(AdtId);
-id!(TraitId);
+id!(FnId, path);
+id!(AdtId, path);
+id!(TraitId, path);
 id!(AssociatedItemId);
 id!(CrateId);
 id!(FieldId);
+id!(TypeAliasId, path);
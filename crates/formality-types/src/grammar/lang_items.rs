@@ -0,0 +1,169 @@
+//! Items that the type system knows about by name.
+//!
+//! Rust identifies these via `#[lang = "..."]` attributes; we just
+//! use well-known names. A program that wants the built-in behavior
+//! has to declare the corresponding trait itself.
+
+use super::{AdtId, AssociatedItemId, ClosureKind, ScalarId, TraitId};
+
+/// The `Deref` trait, used for autoderef and deref coercions.
+pub fn deref_trait() -> TraitId {
+    TraitId::new("Deref")
+}
+
+/// The `DerefMut` trait, used when coercing through `&mut` references.
+pub fn deref_mut_trait() -> TraitId {
+    TraitId::new("DerefMut")
+}
+
+/// The `Target` associated type of [`deref_trait`].
+pub fn deref_target() -> AssociatedItemId {
+    AssociatedItemId::new("Target")
+}
+
+/// The `Receiver` trait: a type `R: Receiver` can be the type of `self` in methods
+/// of `<R as Receiver>::Target` (see [`receiver_target`]), like `Rc<Self>`. Unlike rustc,
+/// we don't have a blanket impl for `Deref` types; receivers may go through either trait.
+pub fn receiver_trait() -> TraitId {
+    TraitId::new("Receiver")
+}
+
+/// The `Target` associated type of [`receiver_trait`].
+pub fn receiver_target() -> AssociatedItemId {
+    AssociatedItemId::new("Target")
+}
+
+/// The `DispatchFromDyn` trait: a receiver `P<Self>` whose ADT `P` implements it, like
+/// `Rc<Self>`, can be called through the vtable of `dyn Trait` (see `formality_rust::dyn_compat`).
+pub fn dispatch_from_dyn_trait() -> TraitId {
+    TraitId::new("DispatchFromDyn")
+}
+
+/// The `Unsize` trait: `A: Unsize<B>` means that `&A` can be coerced to `&B`.
+pub fn unsize_trait() -> TraitId {
+    TraitId::new("Unsize")
+}
+
+/// The `Copy` trait; moving out of a place whose type is `Copy` leaves it initialized.
+pub fn copy_trait() -> TraitId {
+    TraitId::new("Copy")
+}
+
+/// The `Sized` trait, which holds for every type whose size is known at compile time.
+pub fn sized_trait() -> TraitId {
+    TraitId::new("Sized")
+}
+
+/// The `Sync` trait; the type of a `static` shared between threads must be `Sync`.
+pub fn sync_trait() -> TraitId {
+    TraitId::new("Sync")
+}
+
+/// The `PhantomData<T>` struct, which has no fields but behaves as if it owned a `T`:
+/// it is covariant in `T` and implements the auto traits exactly when `T` does.
+pub fn phantom_data() -> AdtId {
+    AdtId::new("PhantomData")
+}
+
+/// The `Add` trait for `a + b`, whose `Self` type is the type of `a` and whose parameter
+/// is the type of `b`. Like for the other operator traits, the type of the expression
+/// is the `Output` associated type (see [`op_output`]).
+pub fn add_trait() -> TraitId {
+    TraitId::new("Add")
+}
+
+/// The `Sub` trait for `a - b`, see [`add_trait`].
+pub fn sub_trait() -> TraitId {
+    TraitId::new("Sub")
+}
+
+/// The `Mul` trait for `a * b`, see [`add_trait`].
+pub fn mul_trait() -> TraitId {
+    TraitId::new("Mul")
+}
+
+/// The `Div` trait for `a / b`, see [`add_trait`].
+pub fn div_trait() -> TraitId {
+    TraitId::new("Div")
+}
+
+/// The `Rem` trait for `a % b`, see [`add_trait`].
+pub fn rem_trait() -> TraitId {
+    TraitId::new("Rem")
+}
+
+/// The `Neg` trait for `-a`, which has no parameters.
+pub fn neg_trait() -> TraitId {
+    TraitId::new("Neg")
+}
+
+/// The `Not` trait for `!a`, which has no parameters.
+pub fn not_trait() -> TraitId {
+    TraitId::new("Not")
+}
+
+/// The `Index` trait for `a[i]`, whose parameter is the type of `i`.
+pub fn index_trait() -> TraitId {
+    TraitId::new("Index")
+}
+
+/// The `Output` associated type of the operator traits and [`index_trait`].
+pub fn op_output() -> AssociatedItemId {
+    AssociatedItemId::new("Output")
+}
+
+/// The scalar types that implement the operator trait `trait_id` built in, with
+/// every parameter and the `Output` being the scalar type itself, e.g. `i32: Add<i32>`.
+/// Unlike in the standard library, there are no impls for references to scalars.
+pub fn builtin_operator_impls(trait_id: &TraitId) -> Vec<ScalarId> {
+    let arithmetic = [
+        add_trait(),
+        sub_trait(),
+        mul_trait(),
+        div_trait(),
+        rem_trait(),
+    ];
+    ScalarId::ALL
+        .into_iter()
+        .filter(|s| {
+            if arithmetic.contains(trait_id) {
+                s.is_integral() || s.is_float()
+            } else if *trait_id == neg_trait() {
+                s.is_signed() || s.is_float()
+            } else if *trait_id == not_trait() {
+                s.is_integral() || *s == ScalarId::Bool
+            } else {
+                false
+            }
+        })
+        .collect()
+}
+
+/// The closure kind of the `Fn`, `FnMut` and `FnOnce` traits, which are implemented by
+/// closures of that kind or a kind before it. They take the tuple of the argument types
+/// as a parameter, e.g. `F: Fn<(u32,)>`.
+pub fn fn_trait_kind(trait_id: &TraitId) -> Option<ClosureKind> {
+    match trait_id.as_str() {
+        "Fn" => Some(ClosureKind::Fn),
+        "FnMut" => Some(ClosureKind::FnMut),
+        "FnOnce" => Some(ClosureKind::FnOnce),
+        _ => None,
+    }
+}
+
+/// The `Send` and `Sync` traits, which hold for a closure if they hold for
+/// the types of all of its captured variables, and likewise for a coroutine
+/// if they also hold for its witness types.
+pub fn auto_traits() -> [TraitId; 2] {
+    [TraitId::new("Send"), sync_trait()]
+}
+
+/// The `Future` trait, which coroutines implement and `.await` requires.
+pub fn future_trait() -> TraitId {
+    TraitId::new("Future")
+}
+
+/// The `Output` associated type of [`future_trait`], which is the type of `.await`.
+pub fn future_output() -> AssociatedItemId {
+    AssociatedItemId::new("Output")
+}
@@ -1,29 +1,53 @@
 use contracts::requires;
-use formality_macros::{term, Visit};
+use formality_macros::{term, Cast, Visit};
+use serde::{Deserialize, Serialize};
 use std::{collections::BTreeSet, sync::Arc};
 
 mod debug_impls;
+mod generate_impls;
 mod parse_impls;
+mod pretty_impls;
+mod test_substitution;
 
 use crate::{
-    cast::{DowncastTo, To, Upcast, UpcastFrom},
-    cast_impl,
+    cast::{Downcast, DowncastTo, To, Upcast, UpcastFrom},
     collections::Map,
     derive_links::Visit,
     fold::Fold,
+    visit::VarFlags,
 };
 
-use super::{consts::Const, AdtId, AssociatedItemId, Binder, FnId, TraitId};
+use super::{
+    consts::Const, lang_items::phantom_data, AdtId, AssociatedItemId, Binder, FnId, TraitId,
+    TypeAliasId,
+};
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Cast)]
+#[cast(
+    RigidTy <: TyData,
+    AliasTy <: TyData,
+    ScalarId <: TyData,
+    PredicateTy <: TyData,
+    Variable <: TyData,
+    UniversalVar <: Variable,
+    ExistentialVar <: Variable,
+    BoundVar <: Variable,
+)]
 pub struct Ty {
     data: Arc<TyData>,
+
+    /// The [`Visit::var_flags`] of `data`.
+    var_flags: VarFlags,
 }
 
 impl Ty {
+    /// Computing the flags of a type only looks at its immediate contents, as the types
+    /// within have theirs already.
     pub fn new(data: impl Upcast<TyData>) -> Self {
+        let data: TyData = data.upcast();
         Ty {
-            data: Arc::new(data.upcast()),
+            var_flags: data.var_flags(),
+            data: Arc::new(data),
         }
     }
 
@@ -31,6 +55,24 @@ impl Ty {
         &self.data
     }
 
+    /// The type with `data`, which is `self` (sharing its allocation) if `data` is the
+    /// same as its own. Folding uses this, so that the types that a substitution leaves
+    /// unchanged are not rebuilt. Comparing is cheap then, as the types within `data`
+    /// are unchanged too and so compare by pointer first.
+    pub fn with_data(&self, data: TyData) -> Ty {
+        if *self.data == data {
+            self.clone()
+        } else {
+            Ty::new(data)
+        }
+    }
+
+    /// Whether `self` and `other` share their allocation, which implies that they are
+    /// equal.
+    pub fn ptr_eq(&self, other: &Ty) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+
     pub fn to_parameter(&self) -> Parameter {
         Parameter::Ty(self.clone())
     }
@@ -46,6 +88,40 @@ impl Ty {
         matches!(self.data(), TyData::RigidTy(_))
     }
 
+    /// If this is `PhantomData<T>` (see [`phantom_data`]), returns `T`.
+    pub fn as_phantom_data_ty(&self) -> Option<Ty> {
+        let RigidTy {
+            name: RigidName::AdtId(adt_id),
+            parameters,
+        } = self.downcast()?
+        else {
+            return None;
+        };
+        if adt_id != phantom_data() {
+            return None;
+        }
+        let [Parameter::Ty(ty)] = &parameters[..] else {
+            return None;
+        };
+        Some(ty.clone())
+    }
+
+    /// If this is a reference type `&'a T` or `&'a mut T`, returns its kind, lifetime and referent.
+    pub fn as_ref_ty(&self) -> Option<(RefKind, Lt, Ty)> {
+        match self.data() {
+            TyData::RigidTy(RigidTy {
+                name: RigidName::Ref(kind),
+                parameters,
+            }) => match &parameters[..] {
+                [Parameter::Lt(lt), Parameter::Ty(ty)] => {
+                    Some((kind.clone(), lt.clone(), ty.clone()))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     pub fn rigid(name: impl Upcast<RigidName>, parameters: impl Upcast<Vec<Parameter>>) -> Self {
         RigidTy {
             name: name.upcast(),
@@ -78,6 +154,147 @@ impl Ty {
         )
     }
 
+    pub fn raw_ptr_ty(&self, kind: RefKind) -> Self {
+        Self::rigid(RigidName::RawPtr(kind), vec![self.to::<Parameter>()])
+    }
+
+    pub fn as_raw_ptr_ty(&self) -> Option<(RefKind, Ty)> {
+        match self.data() {
+            TyData::RigidTy(RigidTy {
+                name: RigidName::RawPtr(kind),
+                parameters,
+            }) => match &parameters[..] {
+                [Parameter::Ty(ty)] => Some((kind.clone(), ty.clone())),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn fn_ptr(input_tys: &[Ty], output_ty: &Ty) -> Self {
+        Self::abi_fn_ptr(Abi::Rust, input_tys, output_ty)
+    }
+
+    /// The fn pointer type `extern "abi" fn(A, B) -> C`.
+    pub fn abi_fn_ptr(abi: Abi, input_tys: &[Ty], output_ty: &Ty) -> Self {
+        let parameters: Vec<Parameter> = input_tys
+            .iter()
+            .chain(Some(output_ty))
+            .map(|ty| ty.to::<Parameter>())
+            .collect();
+        Self::rigid(RigidName::FnPtr(abi, input_tys.len()), parameters)
+    }
+
+    pub fn tuple(tys: &[Ty]) -> Self {
+        Self::rigid(RigidName::Tuple(tys.len()), tys.to_vec())
+    }
+
+    /// If this is a fn pointer type `fn(A, B) -> C`, returns its input and output types.
+    pub fn as_fn_ptr_ty(&self) -> Option<(Vec<Ty>, Ty)> {
+        let RigidTy {
+            name: RigidName::FnPtr(..),
+            parameters,
+        } = self.downcast()?
+        else {
+            return None;
+        };
+        let mut tys = parameters
+            .iter()
+            .map(|p| p.downcast())
+            .collect::<Option<Vec<Ty>>>()?;
+        let output_ty = tys.pop()?;
+        Some((tys, output_ty))
+    }
+
+    /// If this is a trait object type `dyn Trait<..>`, returns the trait and its parameters
+    /// (without the self type).
+    pub fn as_dyn_ty(&self) -> Option<(TraitId, Parameters)> {
+        let RigidTy {
+            name: RigidName::Dyn(trait_id),
+            parameters,
+        } = self.downcast()?
+        else {
+            return None;
+        };
+        Some((trait_id, parameters))
+    }
+
+    pub fn closure(kind: ClosureKind, sig: &Ty, upvar_tys: &[Ty]) -> Self {
+        Self::rigid(
+            RigidName::Closure(kind),
+            vec![sig.clone(), Ty::tuple(upvar_tys)],
+        )
+    }
+
+    /// If this is a closure type, returns its kind, its signature (a fn pointer type)
+    /// and the types of its captured variables.
+    pub fn as_closure_ty(&self) -> Option<(ClosureKind, Ty, Vec<Ty>)> {
+        let RigidTy {
+            name: RigidName::Closure(kind),
+            parameters,
+        } = self.downcast()?
+        else {
+            return None;
+        };
+        let [Parameter::Ty(sig), Parameter::Ty(upvars)] = &parameters[..] else {
+            return None;
+        };
+        Some((kind, sig.clone(), upvars.as_tuple_elems()?))
+    }
+
+    pub fn coroutine(output_ty: &Ty, upvar_tys: &[Ty], witness_tys: &[Ty]) -> Self {
+        Self::rigid(
+            RigidName::Coroutine,
+            vec![
+                output_ty.clone(),
+                Ty::tuple(upvar_tys),
+                Ty::tuple(witness_tys),
+            ],
+        )
+    }
+
+    /// If this is a coroutine type, returns its output type, the types of its captured
+    /// variables and its witness types.
+    pub fn as_coroutine_ty(&self) -> Option<(Ty, Vec<Ty>, Vec<Ty>)> {
+        let RigidTy {
+            name: RigidName::Coroutine,
+            parameters,
+        } = self.downcast()?
+        else {
+            return None;
+        };
+        let [Parameter::Ty(output_ty), Parameter::Ty(upvars), Parameter::Ty(witness)] =
+            &parameters[..]
+        else {
+            return None;
+        };
+        Some((
+            output_ty.clone(),
+            upvars.as_tuple_elems()?,
+            witness.as_tuple_elems()?,
+        ))
+    }
+
+    /// If this is a tuple type, returns the types of its elements.
+    fn as_tuple_elems(&self) -> Option<Vec<Ty>> {
+        let RigidTy {
+            name: RigidName::Tuple(_),
+            parameters,
+        } = self.downcast()?
+        else {
+            return None;
+        };
+        parameters.iter().map(|p| p.downcast()).collect()
+    }
+
+    pub fn unit() -> Ty {
+        Self::rigid(RigidName::Tuple(0), Vec::<Parameter>::new())
+    }
+
+    pub fn never() -> Ty {
+        Self::rigid(RigidName::Never, Vec::<Parameter>::new())
+    }
+
     pub fn bool() -> Ty {
         RigidTy {
             name: RigidName::ScalarId(ScalarId::Bool),
@@ -101,39 +318,81 @@ impl DowncastTo<TyData> for Ty {
 
 // NB: TyData doesn't implement Fold; you fold types, not TyData,
 // because variables might not map to the same variant.
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Visit)]
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast,
+)]
+#[cast(
+    UniversalVar <: Variable,
+    ExistentialVar <: Variable,
+    BoundVar <: Variable,
+    ScalarId <: RigidTy,
+)]
 pub enum TyData {
+    #[cast]
     RigidTy(RigidTy),
+    #[cast]
     AliasTy(AliasTy),
+    #[cast]
     PredicateTy(PredicateTy),
+    #[cast]
     Variable(Variable),
 }
 
-impl UpcastFrom<TyData> for TyData {
-    fn upcast_from(term: TyData) -> Self {
-        term
-    }
-}
-
 impl UpcastFrom<Ty> for TyData {
     fn upcast_from(term: Ty) -> Self {
         term.data().clone()
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+impl Visit for Ty {
+    fn free_variables(&self) -> Vec<Variable> {
+        if self.var_flags.is_empty() {
+            return vec![];
+        }
+        self.data().free_variables()
+    }
+
+    fn var_flags(&self) -> VarFlags {
+        self.var_flags
+    }
+
+    fn size(&self) -> usize {
+        self.data().size()
+    }
+
+    fn assert_valid(&self) {
+        self.data().assert_valid()
+    }
+}
+
+/// Serialized as its data; the flags are recomputed when deserialized.
+impl Serialize for Ty {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.data().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Ty {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Ty::new(TyData::deserialize(deserializer)?))
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Cast)]
 pub struct ExistentialVar {
     pub kind: ParameterKind,
     pub var_index: VarIndex,
 }
 
-cast_impl!(ExistentialVar);
-
 impl Visit for ExistentialVar {
     fn free_variables(&self) -> Vec<Variable> {
         vec![self.upcast()]
     }
 
+    fn var_flags(&self) -> VarFlags {
+        VarFlags::EXISTENTIAL
+    }
+
     fn size(&self) -> usize {
         1
     }
@@ -173,9 +432,53 @@ pub enum RigidName {
     #[cast]
     #[grammar(&($v0))]
     Ref(RefKind),
+    /// Raw pointers `*const T` and `*mut T`, with the pointee type as the only parameter.
+    #[grammar(*($v0))]
+    RawPtr(RefKind),
     Tuple(usize),
-    FnPtr(usize),
+    /// Fn pointers `fn(A, B) -> C` with the given ABI; the parameters are the input types,
+    /// followed by the output type.
+    FnPtr(Abi, usize),
     FnDef(FnId),
+    /// The never type `!`, which has no values.
+    #[grammar(!)]
+    Never,
+    /// Closures, whose calls need the access of the given kind to their captured variables.
+    /// The parameters are the signature, as a fn pointer, and the tuple of the types of the
+    /// captured variables. Unlike in rustc, closures with the same kind, signature and
+    /// captures have the same type.
+    #[grammar((closure $v0))]
+    Closure(ClosureKind),
+    /// Trait objects `dyn Trait<..>`, whose parameters are those of the trait without
+    /// the self type. They are well formed only if the trait is dyn compatible.
+    #[grammar((dyn $v0))]
+    Dyn(TraitId),
+    /// Coroutines, the state machines that async blocks evaluate to. The parameters are
+    /// the output type, the tuple of the types of the captured variables, and the witness:
+    /// the tuple of the types that the coroutine holds across its suspension points.
+    Coroutine,
+}
+
+/// The calling convention of a fn, written `"Rust"` or `"C"`.
+/// Parsed by hand (see `parse_impls`) because the grammar has no string literals.
+#[derive(
+    Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast,
+)]
+pub enum Abi {
+    Rust,
+    C,
+}
+
+/// The kinds of closures, ordered such that a closure of one kind can also be
+/// called as any of the later ones.
+#[term]
+pub enum ClosureKind {
+    /// Calls only read the captured variables.
+    Fn,
+    /// Calls may mutate the captured variables.
+    FnMut,
+    /// Calls may move out of the captured variables, so the closure can only be called once.
+    FnOnce,
 }
 
 #[term]
@@ -208,6 +511,43 @@ pub enum ScalarId {
     Usize,
     #[grammar(isize)]
     Isize,
+    #[grammar(f32)]
+    F32,
+    #[grammar(f64)]
+    F64,
+}
+
+impl ScalarId {
+    pub const ALL: [ScalarId; 13] = [
+        ScalarId::U8,
+        ScalarId::U16,
+        ScalarId::U32,
+        ScalarId::U64,
+        ScalarId::I8,
+        ScalarId::I16,
+        ScalarId::I32,
+        ScalarId::I64,
+        ScalarId::Bool,
+        ScalarId::Usize,
+        ScalarId::Isize,
+        ScalarId::F32,
+        ScalarId::F64,
+    ];
+
+    pub fn is_integral(&self) -> bool {
+        !matches!(self, ScalarId::Bool | ScalarId::F32 | ScalarId::F64)
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, ScalarId::F32 | ScalarId::F64)
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            ScalarId::I8 | ScalarId::I16 | ScalarId::I32 | ScalarId::I64 | ScalarId::Isize
+        )
+    }
 }
 
 #[term((alias $name $*parameters))]
@@ -231,15 +571,33 @@ impl AliasTy {
             parameters: parameters.upcast(),
         }
     }
+
+    pub fn type_alias(
+        id: impl Upcast<TypeAliasId>,
+        parameters: impl Upcast<Vec<Parameter>>,
+    ) -> Self {
+        AliasTy {
+            name: AliasName::TypeAliasId(id.upcast()),
+            parameters: parameters.upcast(),
+        }
+    }
 }
 
 #[term]
 pub enum AliasName {
     #[cast]
     AssociatedTyId(AssociatedTyName),
+
+    /// Free type aliases `type Foo<T> = ...`, which are not expanded when they are written
+    /// but only lazily when they are equated with another type (like the `lazy_type_alias`
+    /// feature of rustc).
+    #[cast]
+    TypeAliasId(TypeAliasId),
 }
 
-#[term(($trait_id :: $item_id))]
+/// The name of an associated type, written `(Trait :: Item)`.
+/// Parsed by hand (see `parse_impls`) because the trait id can be a path.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Visit, Serialize, Deserialize, Cast)]
 pub struct AssociatedTyName {
     pub trait_id: TraitId,
     pub item_id: AssociatedItemId,
@@ -253,7 +611,7 @@ pub enum PredicateTy {
 /// A *universal variable* is a dummy variable about which nothing is known except
 /// that which we see in the environment. When we want to prove something
 /// is true for all `T` (`∀T`), we replace `T` with a universal variable.
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct UniversalVar {
     pub kind: ParameterKind,
     pub var_index: VarIndex,
@@ -266,6 +624,17 @@ pub enum QuantifierKind {
 }
 
 #[term]
+#[cast(
+    UniversalVar <: Variable,
+    ExistentialVar <: Variable,
+    BoundVar <: Variable,
+    RigidTy <: Ty,
+    AliasTy <: Ty,
+    ScalarId <: Ty,
+    PredicateTy <: Ty,
+    TyData <: Ty,
+    LtData <: Lt,
+)]
 pub enum Parameter {
     #[cast]
     Ty(Ty),
@@ -318,7 +687,9 @@ pub enum Variance {
     Invariant,
 }
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Cast)]
+#[serde(transparent)]
+#[cast(UniversalVar <: LtData, ExistentialVar <: LtData, BoundVar <: LtData)]
 pub struct Lt {
     data: Arc<LtData>,
 }
@@ -358,24 +729,14 @@ impl DowncastTo<LtData> for Lt {
     }
 }
 
-impl UpcastFrom<LtData> for Parameter {
-    fn upcast_from(v: LtData) -> Self {
-        Lt::new(v).upcast()
-    }
-}
-
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Cast)]
+#[cast(ExistentialVar <: Variable, UniversalVar <: Variable, BoundVar <: Variable)]
 pub enum LtData {
     Static,
+    #[cast]
     Variable(Variable),
 }
 
-impl UpcastFrom<LtData> for LtData {
-    fn upcast_from(term: LtData) -> Self {
-        term
-    }
-}
-
 impl Visit for LtData {
     fn free_variables(&self) -> Vec<Variable> {
         match self {
@@ -390,6 +751,13 @@ impl Visit for LtData {
         }
     }
 
+    fn var_flags(&self) -> VarFlags {
+        match self {
+            LtData::Variable(v) => v.var_flags(),
+            LtData::Static => VarFlags::NONE,
+        }
+    }
+
     fn size(&self) -> usize {
         match self {
             LtData::Variable(v) => v.size(),
@@ -405,15 +773,16 @@ impl Visit for LtData {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Cast)]
 pub enum Variable {
+    #[cast]
     UniversalVar(UniversalVar),
+    #[cast]
     ExistentialVar(ExistentialVar),
+    #[cast]
     BoundVar(BoundVar),
 }
 
-cast_impl!(Variable);
-
 impl Variable {
     pub fn kind(&self) -> ParameterKind {
         match self {
@@ -506,6 +875,10 @@ impl Visit for Variable {
         }
     }
 
+    fn var_flags(&self) -> VarFlags {
+        VarFlags::of(self)
+    }
+
     fn size(&self) -> usize {
         1
     }
@@ -529,13 +902,8 @@ impl DowncastTo<Variable> for Parameter {
     }
 }
 
-cast_impl!(BoundVar);
-cast_impl!((ExistentialVar) <: (Variable) <: (Parameter));
-cast_impl!((BoundVar) <: (Variable) <: (Parameter));
-cast_impl!((UniversalVar) <: (Variable) <: (Parameter));
-
 /// Identifies a bound variable.
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Cast)]
 pub struct BoundVar {
     /// Identifies the binder that contained this variable, counting "outwards".
     /// When you create a binder with `Binder::new`,
@@ -563,7 +931,7 @@ impl BoundVar {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct DebruijnIndex {
     pub index: usize,
 }
@@ -590,7 +958,7 @@ impl DebruijnIndex {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct VarIndex {
     pub index: usize,
 }
@@ -724,6 +1092,34 @@ impl Substitution {
     pub fn get(&self, v: Variable) -> Option<Parameter> {
         self.map.get(&v).cloned()
     }
+
+    /// The substitution that applies `self` and then `other`, i.e., `other` applied to
+    /// the values of `self`, together with the mappings of `other` for the variables
+    /// not in the domain of `self`.
+    pub fn compose(&self, other: &Substitution) -> Substitution {
+        let mut composed: Substitution = self.iter().map(|(v, p)| (v, other.apply(&p))).collect();
+        for (v, p) in other.iter() {
+            composed.map.entry(v).or_insert(p);
+        }
+        composed
+    }
+
+    /// This substitution without mappings for `vars`.
+    pub fn remove_vars(mut self, vars: impl Upcast<Vec<Variable>>) -> Substitution {
+        self -= vars;
+        self
+    }
+
+    /// True if applying this substitution twice is the same as applying it once, i.e.,
+    /// no variable of its domain occurs in its range. The substitutions of the prover
+    /// are always idempotent, having been applied to themselves.
+    pub fn is_idempotent(&self) -> bool {
+        let domain = self.domain();
+        self.range()
+            .free_variables()
+            .iter()
+            .all(|v| !domain.contains(v))
+    }
 }
 
 impl Fold for Substitution {
@@ -741,6 +1137,10 @@ impl Visit for Substitution {
         v
     }
 
+    fn var_flags(&self) -> VarFlags {
+        self.domain().iter().map(VarFlags::of).collect::<VarFlags>() | self.range().var_flags()
+    }
+
     fn size(&self) -> usize {
         self.range().iter().map(|r| r.size()).sum()
     }
@@ -758,6 +1158,20 @@ impl std::ops::Index<Variable> for Substitution {
     }
 }
 
+/// Serialized as a list of pairs, since the variables cannot be map keys in JSON.
+impl Serialize for Substitution {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.map)
+    }
+}
+
+impl<'de> Deserialize<'de> for Substitution {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pairs: Vec<(Variable, Parameter)> = Vec::deserialize(deserializer)?;
+        Ok(pairs.into_iter().collect())
+    }
+}
+
 /// A substitution that is only between variables.
 /// These are reversible.
 #[derive(Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -810,37 +1224,3 @@ impl VarSubstitution {
         assert!(x.is_none());
     }
 }
-
-cast_impl!(Ty);
-cast_impl!((RigidTy) <: (TyData) <: (Ty));
-cast_impl!((AliasTy) <: (TyData) <: (Ty));
-cast_impl!((ScalarId) <: (TyData) <: (Ty));
-cast_impl!((PredicateTy) <: (TyData) <: (Ty));
-cast_impl!((RigidTy) <: (Ty) <: (Parameter));
-cast_impl!((AliasTy) <: (Ty) <: (Parameter));
-cast_impl!((ScalarId) <: (Ty) <: (Parameter));
-cast_impl!((PredicateTy) <: (Ty) <: (Parameter));
-cast_impl!((TyData) <: (Ty) <: (Parameter));
-cast_impl!(TyData::RigidTy(RigidTy));
-cast_impl!(TyData::AliasTy(AliasTy));
-cast_impl!(TyData::PredicateTy(PredicateTy));
-cast_impl!(TyData::Variable(Variable));
-cast_impl!((Variable) <: (TyData) <: (Ty));
-cast_impl!((UniversalVar) <: (Variable) <: (TyData));
-cast_impl!((ExistentialVar) <: (Variable) <: (TyData));
-cast_impl!((BoundVar) <: (Variable) <: (TyData));
-cast_impl!((ScalarId) <: (RigidTy) <: (TyData));
-cast_impl!((UniversalVar) <: (Variable) <: (Ty));
-cast_impl!((ExistentialVar) <: (Variable) <: (Ty));
-cast_impl!((BoundVar) <: (Variable) <: (Ty));
-cast_impl!(Lt);
-cast_impl!(LtData::Variable(Variable));
-cast_impl!((ExistentialVar) <: (Variable) <: (LtData));
-cast_impl!((UniversalVar) <: (Variable) <: (LtData));
-cast_impl!((BoundVar) <: (Variable) <: (LtData));
-cast_impl!((UniversalVar) <: (LtData) <: (Lt));
-cast_impl!((ExistentialVar) <: (LtData) <: (Lt));
-cast_impl!((BoundVar) <: (LtData) <: (Lt));
-cast_impl!(Variable::UniversalVar(UniversalVar));
-cast_impl!(Variable::ExistentialVar(ExistentialVar));
-cast_impl!(Variable::BoundVar(BoundVar));
@@ -20,6 +20,21 @@ impl std::fmt::Debug for Const {
     }
 }
 
+impl std::fmt::Debug for super::AssociatedTyName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({:?} :: {:?})", self.trait_id, self.item_id)
+    }
+}
+
+impl std::fmt::Debug for super::Abi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            super::Abi::Rust => write!(f, "\"Rust\""),
+            super::Abi::C => write!(f, "\"C\""),
+        }
+    }
+}
+
 impl std::fmt::Debug for super::Lt {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.data() {
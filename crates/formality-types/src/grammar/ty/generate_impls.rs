@@ -0,0 +1,113 @@
+//! Handwritten generator impls, generating types in the surface syntax more often
+//! than the raw `(rigid ...)` and `(alias ...)` forms that other parameters print as.
+
+use crate::{
+    cast::{To, Upcast},
+    generate::{Generate, Generator},
+    grammar::{AssociatedItemId, Bool, Const, Scalar, TraitId},
+};
+
+use super::{
+    Abi, AliasTy, AssociatedTyName, Lt, LtData, Parameter, ParameterKind, PredicateTy, RigidName,
+    RigidTy, ScalarId, Ty, Variable,
+};
+
+impl Generate for Ty {
+    fn generate(g: &mut Generator) -> Self {
+        g.nested(|g| match g.variant(7) {
+            0 => g.choose(&ScalarId::ALL).upcast(),
+            1 | 2 => {
+                let name = RigidName::generate(g);
+                let parameters = rigid_parameters(&name, g);
+                RigidTy { name, parameters }.upcast()
+            }
+            3 => RigidTy::generate(g).upcast(),
+            4 => {
+                let name = AssociatedTyName::generate(g);
+                let parameters = std::iter::once(Ty::generate(g).upcast())
+                    .chain(Vec::<Parameter>::generate(g))
+                    .collect::<Vec<_>>();
+                Ty::alias(name, parameters)
+            }
+            5 => match g.variant(2) {
+                0 => AliasTy::generate(g).upcast(),
+                _ => PredicateTy::generate(g).upcast(),
+            },
+            _ => match g.bound_var(ParameterKind::Ty) {
+                Some(v) => v.upcast(),
+                None => g.choose(&ScalarId::ALL).upcast(),
+            },
+        })
+    }
+}
+
+/// Parameters that fit `name`, so that the type prints in the surface syntax.
+fn rigid_parameters(name: &RigidName, g: &mut Generator) -> Vec<Parameter> {
+    let tys = |n: usize, g: &mut Generator| -> Vec<Parameter> {
+        (0..n).map(|_| Ty::generate(g).upcast()).collect()
+    };
+    match name {
+        RigidName::ScalarId(_) | RigidName::Never => vec![],
+        RigidName::Ref(_) => vec![Lt::generate(g).upcast(), Ty::generate(g).upcast()],
+        RigidName::RawPtr(_) => tys(1, g),
+        RigidName::Tuple(n) => tys(*n, g),
+        RigidName::FnPtr(_, n) => tys(n + 1, g),
+        RigidName::AdtId(_)
+        | RigidName::FnDef(_)
+        | RigidName::Closure(_)
+        | RigidName::Coroutine
+        | RigidName::Dyn(_) => Generate::generate(g),
+    }
+}
+
+impl Generate for Lt {
+    fn generate(g: &mut Generator) -> Self {
+        match g.variant(2) {
+            0 => Lt::new(LtData::Static),
+            _ => match g.bound_var(ParameterKind::Lt) {
+                Some(v) => v.upcast(),
+                None => Lt::new(LtData::Static),
+            },
+        }
+    }
+}
+
+/// Booleans, integers of integral types, and bound variables.
+impl Generate for Const {
+    fn generate(g: &mut Generator) -> Self {
+        let bool = |g: &mut Generator| match g.below(2) {
+            0 => Bool::False.upcast(),
+            _ => Bool::True.upcast(),
+        };
+        match g.variant(3) {
+            0 => bool(g),
+            1 => {
+                let integral: Vec<ScalarId> = ScalarId::ALL
+                    .into_iter()
+                    .filter(|s| s.is_integral())
+                    .collect();
+                let ty: Ty = g.choose(&integral).upcast();
+                Const::valtree(Scalar::new(g.below(100) as u128), ty)
+            }
+            _ => match g.bound_var(ParameterKind::Const) {
+                Some(v) => Const::new(v.to::<Variable>()),
+                None => bool(g),
+            },
+        }
+    }
+}
+
+impl Generate for AssociatedTyName {
+    fn generate(g: &mut Generator) -> Self {
+        AssociatedTyName {
+            trait_id: TraitId::generate(g),
+            item_id: AssociatedItemId::generate(g),
+        }
+    }
+}
+
+impl Generate for Abi {
+    fn generate(g: &mut Generator) -> Self {
+        g.choose(&[Abi::Rust, Abi::C])
+    }
+}
@@ -7,7 +7,10 @@ use crate::{
     seq,
 };
 
-use super::{AliasTy, AssociatedTyName, Lt, LtData, Parameter, PredicateTy, RigidTy, ScalarId, Ty};
+use super::{
+    Abi, AliasTy, AssociatedTyName, Lt, LtData, Parameter, PredicateTy, RefKind, RigidTy, ScalarId,
+    Ty,
+};
 
 // For types, we invest some effort into parsing them decently because it makes
 // writing tests so much more pleasant.
@@ -18,6 +21,10 @@ impl Parse for Ty {
             return Ok((scalar_ty.upcast(), text1));
         }
 
+        if let Ok(((), text1)) = expect_char('!', text0) {
+            return Ok((Ty::never(), text1));
+        }
+
         // Support naming variables in scope and give that preference
         if let Ok((p, text1)) = parse_variable(scope, text0) {
             return match p {
@@ -37,6 +44,9 @@ impl Parse for Ty {
                 parse::try_parse(|| parse_ref_ty(scope, text0)),
                 parse::try_parse(|| parse_ref_mut_ty(scope, text0)),
                 parse::try_parse(|| parse_tuple_ty(scope, text0)),
+                parse::try_parse(|| parse_raw_ptr_ty(scope, text0)),
+                parse::try_parse(|| parse_fn_ptr_ty(scope, text0)),
+                parse::try_parse(|| parse_dyn_ty(scope, text0)),
                 parse::try_parse(|| {
                     let (ty, text) = RigidTy::parse(scope, text0)?;
                     Ok((Ty::new(ty), text))
@@ -60,6 +70,10 @@ fn parse_adt_ty<'t>(scope: &crate::parse::Scope, text: &'t str) -> ParseResult<'
     // Treat plain identifiers as adt ids, with or without parameters.
     let ((), text) = reject_keyword("static", text)?;
     let ((), text) = reject_keyword("const", text)?;
+    let ((), text) = reject_keyword("fn", text)?;
+    let ((), text) = reject_keyword("extern", text)?;
+    let ((), text) = reject_keyword("for_all", text)?;
+    let ((), text) = reject_keyword("dyn", text)?;
     let (name, text) = AdtId::parse(scope, text)?;
     let (parameters, text) = parse_parameters(scope, text)?;
     Ok((Ty::rigid(name, parameters), text))
@@ -98,6 +112,44 @@ fn parse_ref_mut_ty<'t>(scope: &crate::parse::Scope, text: &'t str) -> ParseResu
     ))
 }
 
+#[tracing::instrument(level = "trace", ret)]
+fn parse_raw_ptr_ty<'t>(scope: &crate::parse::Scope, text: &'t str) -> ParseResult<'t, Ty> {
+    let ((), text) = expect_char('*', text)?;
+    let (kind, text) = match expect_keyword("const", text) {
+        Ok(((), text)) => (RefKind::Shared, text),
+        Err(_) => {
+            let ((), text) = expect_keyword("mut", text)?;
+            (RefKind::Mut, text)
+        }
+    };
+    let (ty, text) = Ty::parse(scope, text)?;
+    Ok((ty.raw_ptr_ty(kind), text))
+}
+
+#[tracing::instrument(level = "trace", ret)]
+fn parse_fn_ptr_ty<'t>(scope: &crate::parse::Scope, text: &'t str) -> ParseResult<'t, Ty> {
+    let (abi, text) = match expect_keyword("extern", text) {
+        Ok(((), text)) => Abi::parse(scope, text)?,
+        Err(_) => (Abi::Rust, text),
+    };
+    let ((), text) = expect_keyword("fn", text)?;
+    let ((), text) = expect_char('(', text)?;
+    let (input_tys, text) = Ty::parse_comma(scope, text, ')')?;
+    let ((), text) = expect_char(')', text)?;
+    let ((), text) = expect_char('-', text)?;
+    let ((), text) = expect_char('>', text)?;
+    let (output_ty, text) = Ty::parse(scope, text)?;
+    Ok((Ty::abi_fn_ptr(abi, &input_tys, &output_ty), text))
+}
+
+#[tracing::instrument(level = "trace", ret)]
+fn parse_dyn_ty<'t>(scope: &crate::parse::Scope, text: &'t str) -> ParseResult<'t, Ty> {
+    let ((), text) = expect_keyword("dyn", text)?;
+    let (trait_id, text) = TraitId::parse(scope, text)?;
+    let (parameters, text) = parse_parameters(scope, text)?;
+    Ok((Ty::rigid(RigidName::Dyn(trait_id), parameters), text))
+}
+
 #[tracing::instrument(level = "trace", ret)]
 fn parse_tuple_ty<'t>(scope: &crate::parse::Scope, text: &'t str) -> ParseResult<'t, Ty> {
     let ((), text) = expect_char('(', text)?;
@@ -144,13 +196,60 @@ fn parse_parameters<'t>(
     scope: &crate::parse::Scope,
     text: &'t str,
 ) -> ParseResult<'t, Vec<Parameter>> {
-    let text = match expect_char('<', text) {
+    // In `Foo <: Bar`, the `<` is part of the subtyping relation.
+    if parse::skip_whitespace(text).starts_with("<:") {
+        return Ok((vec![], text));
+    }
+    let text1 = match expect_char('<', text) {
         Err(_) => return Ok((vec![], text)),
-        Ok(((), text)) => text,
+        Ok(((), text1)) => text1,
     };
-    let (parameters, text) = Parameter::parse_comma(scope, text, '>')?;
-    let ((), text) = expect_char('>', text)?;
-    Ok((parameters, text))
+    let parameters = Parameter::parse_comma(scope, text1, '>').and_then(|(parameters, text1)| {
+        let ((), text1) = expect_char('>', text1)?;
+        Ok((parameters, text1))
+    });
+    match parameters {
+        Ok(result) => Ok(result),
+
+        // In a list of parameters like `(rigid coroutine Foo <T as Trait>::Item)`,
+        // the `<` starts the next parameter.
+        Err(_) if parse_assoc_ty(scope, text).is_ok() => Ok((vec![], text)),
+
+        Err(errs) => Err(errs),
+    }
+}
+
+// The trait id can be a path, so `(a::Trait::Item)` is split at its last `::`.
+impl Parse for AssociatedTyName {
+    fn parse<'t>(_scope: &crate::parse::Scope, text0: &'t str) -> ParseResult<'t, Self> {
+        let ((), text) = expect_char('(', text0)?;
+        let (path, text) = parse::path(text)?;
+        let Some((trait_id, item_id)) = path.rsplit_once("::") else {
+            return Err(ParseError::at(text0, "expected `Trait::Item`".to_string()));
+        };
+        let ((), text) = expect_char(')', text)?;
+        Ok((
+            AssociatedTyName {
+                trait_id: TraitId::new(trait_id),
+                item_id: AssociatedItemId::new(item_id),
+            },
+            text,
+        ))
+    }
+}
+
+impl Parse for Abi {
+    fn parse<'t>(_scope: &crate::parse::Scope, text0: &'t str) -> ParseResult<'t, Self> {
+        let ((), text) = expect_char('"', text0)?;
+        let (name, text) = parse::identifier(text)?;
+        let abi = match &name[..] {
+            "Rust" => Abi::Rust,
+            "C" => Abi::C,
+            _ => return Err(ParseError::at(text0, format!("unknown ABI `{name}`"))),
+        };
+        let ((), text) = expect_char('"', text)?;
+        Ok((abi, text))
+    }
 }
 
 impl Parse for Lt {
@@ -183,7 +282,7 @@ fn parse_variable<'t>(scope: &crate::parse::Scope, text0: &'t str) -> ParseResul
     let (id, text1) = parse::identifier(text0)?;
     match scope.lookup(&id) {
         Some(parameter) => Ok((parameter, text1)),
-        None => Err(ParseError::at(text0, format!("unrecognized variable"))),
+        None => Err(ParseError::at(text0, "unrecognized variable".to_string())),
     }
 }
 
@@ -214,16 +313,16 @@ impl Parse for Const {
 }
 
 #[tracing::instrument(level = "trace", ret)]
-fn parse_int<'t>(scope: &crate::parse::Scope, text: &'t str) -> ParseResult<'t, Const> {
-    let (num, text) = text.split_once('_').ok_or_else(|| {
+fn parse_int<'t>(scope: &crate::parse::Scope, text0: &'t str) -> ParseResult<'t, Const> {
+    let (num, text) = text0.split_once('_').ok_or_else(|| {
         ParseError::at(
-            text,
-            format!("numeric constants must be followed by an `_` and their type"),
+            text0,
+            "numeric constants must be followed by an `_` and their type".to_string(),
         )
     })?;
     let n: u128 = num
         .parse()
-        .map_err(|err| ParseError::at(num, format!("could not parse number: {err}")))?;
+        .map_err(|err| ParseError::at(text0, format!("could not parse number: {err}")))?;
     let (ty, text) = Ty::parse(scope, text)?;
     Ok((Const::valtree(Scalar::new(n), ty), text))
 }
@@ -0,0 +1,194 @@
+//! Handwritten pretty-printer impls, printing types in the surface syntax
+//! that `parse_impls` accepts where there is one.
+
+use crate::{
+    grammar::{Const, ConstData, ValTree},
+    pretty::{Doc, DocBuilder, Pretty, PrettyCx},
+};
+
+use super::{
+    Abi, AliasName, AliasTy, AssociatedTyName, Lt, LtData, Parameter, RefKind, RigidName, RigidTy,
+    Ty, TyData, Variable,
+};
+
+impl Pretty for Ty {
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        match self.data() {
+            TyData::RigidTy(r) => pretty_rigid_ty(r, cx).unwrap_or_else(|| r.pretty(cx)),
+            TyData::AliasTy(a) => pretty_alias_ty(a, cx).unwrap_or_else(|| a.pretty(cx)),
+            TyData::PredicateTy(p) => p.pretty(cx),
+            TyData::Variable(v) => v.pretty(cx),
+        }
+    }
+}
+
+/// `Foo<..>`, `u32`, `&a T`, `(A, B)`, etc., if the parameters fit the name.
+fn pretty_rigid_ty(ty: &RigidTy, cx: &PrettyCx) -> Option<Doc> {
+    let RigidTy { name, parameters } = ty;
+    let tys = || -> Option<Vec<Doc>> {
+        parameters
+            .iter()
+            .map(|p| match p {
+                Parameter::Ty(ty) => Some(ty.pretty(cx)),
+                _ => None,
+            })
+            .collect()
+    };
+
+    let mut b = DocBuilder::default();
+    match (name, &parameters[..]) {
+        (RigidName::AdtId(id), _) => {
+            b.push(id.pretty(cx));
+            if !parameters.is_empty() {
+                b.push(pretty_parameters(parameters, cx));
+            }
+        }
+        (RigidName::ScalarId(id), []) => b.push(id.pretty(cx)),
+        (RigidName::Ref(kind), [Parameter::Lt(lt), Parameter::Ty(ty)]) => {
+            b.text("&");
+            if let RefKind::Mut = kind {
+                b.text("mut");
+            }
+            b.push(lt.pretty(cx));
+            b.push(ty.pretty(cx));
+        }
+        (RigidName::RawPtr(kind), [Parameter::Ty(ty)]) => {
+            b.text("*");
+            b.text(match kind {
+                RefKind::Shared => "const",
+                RefKind::Mut => "mut",
+            });
+            b.push(ty.pretty(cx));
+        }
+        (RigidName::Tuple(n), _) if *n == parameters.len() => {
+            b.push(Doc::delimited('(', Doc::comma_separated(tys()?), ')'));
+        }
+        (RigidName::FnPtr(abi, n), _) if *n + 1 == parameters.len() => {
+            let mut tys = tys()?;
+            let output_ty = tys.pop()?;
+            if let Abi::C = abi {
+                b.text("extern");
+                b.push(abi.pretty(cx));
+            }
+            b.text("fn");
+            b.push(Doc::delimited('(', Doc::comma_separated(tys), ')'));
+            b.text("->");
+            b.push(output_ty);
+        }
+        (RigidName::Never, []) => b.text("!"),
+        (RigidName::Dyn(id), _) => {
+            b.text("dyn");
+            b.push(id.pretty(cx));
+            if !parameters.is_empty() {
+                b.push(pretty_parameters(parameters, cx));
+            }
+        }
+        _ => return None,
+    }
+    Some(b.group())
+}
+
+/// `<T as Trait<..>>::Item<..>`, if the self type is a type. The parameters of the trait
+/// and of the item are not told apart, so they are all printed as those of the trait.
+fn pretty_alias_ty(ty: &AliasTy, cx: &PrettyCx) -> Option<Doc> {
+    let AliasTy { name, parameters } = ty;
+    let (
+        AliasName::AssociatedTyId(AssociatedTyName { trait_id, item_id }),
+        [Parameter::Ty(self_ty), rest @ ..],
+    ) = (name, &parameters[..])
+    else {
+        return None;
+    };
+    let mut b = DocBuilder::default();
+    b.text("<");
+    b.push(self_ty.pretty(cx));
+    b.text("as");
+    b.push(trait_id.pretty(cx));
+    if !rest.is_empty() {
+        b.push(pretty_parameters(rest, cx));
+    }
+    b.text(">::");
+    b.push(item_id.pretty(cx));
+    Some(b.group())
+}
+
+/// `<P0, .., Pn>`, following the name they are the parameters of.
+fn pretty_parameters(parameters: &[Parameter], cx: &PrettyCx) -> Doc {
+    let mut b = DocBuilder::default();
+    b.glue();
+    b.text("<");
+    b.push(Doc::list(parameters.iter().map(|p| p.pretty(cx))));
+    b.text(">");
+    b.group()
+}
+
+impl Pretty for Lt {
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        match self.data() {
+            LtData::Static => Doc::text("static"),
+            LtData::Variable(v) => v.pretty(cx),
+        }
+    }
+}
+
+impl Pretty for Const {
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        match self.data() {
+            ConstData::Value(ValTree::Leaf(scalar), ty) if *ty == Ty::bool() => {
+                match format!("{scalar:?}").as_str() {
+                    "0" => Doc::text("false"),
+                    "1" => Doc::text("true"),
+                    _ => Doc::text(format!("{self:?}")),
+                }
+            }
+            // `3_u32`: the type follows the value without a space.
+            ConstData::Value(valtree, ty) => {
+                Doc::text(format!("{valtree:?}_{}", ty.pretty(cx).render(usize::MAX)))
+            }
+            ConstData::Variable(v) => v.pretty(cx),
+        }
+    }
+}
+
+/// Bound variables by the names given by their binders; free variables as in `Debug`.
+impl Pretty for Variable {
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        match self {
+            Variable::BoundVar(v) => match cx.bound_name(v) {
+                Some(name) => Doc::text(name),
+                None => Doc::text(format!("{v:?}")),
+            },
+            Variable::UniversalVar(_) | Variable::ExistentialVar(_) => {
+                Doc::text(format!("{self:?}"))
+            }
+        }
+    }
+}
+
+/// `(Trait::Item)`, as `parse_impls` splits the path at its last `::`.
+impl Pretty for AssociatedTyName {
+    fn pretty(&self, _cx: &PrettyCx) -> Doc {
+        let AssociatedTyName { trait_id, item_id } = self;
+        Doc::text(format!("({trait_id:?}::{item_id:?})"))
+    }
+}
+
+impl Pretty for Abi {}
+
+impl std::fmt::Display for Ty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&crate::pretty::to_string(self))
+    }
+}
+
+impl std::fmt::Display for Lt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&crate::pretty::to_string(self))
+    }
+}
+
+impl std::fmt::Display for Const {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&crate::pretty::to_string(self))
+    }
+}
@@ -0,0 +1,60 @@
+#![cfg(test)]
+
+use expect_test::expect;
+
+use crate::{
+    cast::Upcast,
+    grammar::{AdtId, ExistentialVar, ParameterKind, Substitution, Ty, VarIndex, Variable},
+    parse::term,
+};
+
+fn var(index: usize) -> Variable {
+    ExistentialVar {
+        kind: ParameterKind::Ty,
+        var_index: VarIndex { index },
+    }
+    .upcast()
+}
+
+fn vec_of(v: Variable) -> Ty {
+    Ty::rigid(AdtId::new("Vec"), vec![Ty::new(v)])
+}
+
+#[test]
+fn compose() {
+    let (x, y, z) = (var(0), var(1), var(2));
+    let first: Substitution = [(x, vec_of(y))].into_iter().collect();
+    let second: Substitution = [(y, term::<Ty>("u32")), (z, term::<Ty>("bool"))]
+        .into_iter()
+        .collect();
+
+    let composed = first.compose(&second);
+    expect![[r#"
+        {
+            ?ty_0 => (rigid (adt Vec) (rigid (scalar u32))),
+            ?ty_1 => (rigid (scalar u32)),
+            ?ty_2 => (rigid (scalar bool)),
+        }
+    "#]]
+    .assert_debug_eq(&composed);
+
+    let ty = Ty::tuple(&[Ty::new(x), Ty::new(y), Ty::new(z)]);
+    assert_eq!(composed.apply(&ty), second.apply(&first.apply(&ty)));
+}
+
+#[test]
+fn is_idempotent() {
+    let (x, y) = (var(0), var(1));
+    let chained: Substitution = [(x, vec_of(y)), (y, term::<Ty>("u32"))]
+        .into_iter()
+        .collect();
+    assert!(!chained.is_idempotent());
+
+    let applied = chained.compose(&chained);
+    assert!(applied.is_idempotent());
+    assert_eq!(applied.domain(), chained.domain());
+
+    let removed = applied.remove_vars(vec![y]);
+    assert_eq!(removed.domain(), [x].into_iter().collect());
+    assert!(removed.is_idempotent());
+}
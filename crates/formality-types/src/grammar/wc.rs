@@ -4,7 +4,6 @@ use formality_macros::term;
 
 use crate::{
     cast::{DowncastFrom, DowncastTo, Upcast, UpcastFrom, Upcasted},
-    cast_impl,
     collections::{Set, SetExt},
     grammar::PR,
     set,
@@ -13,6 +12,7 @@ use crate::{
 use super::{Binder, BoundVar, Parameter, Predicate, Relation, TraitRef};
 
 #[term($set)]
+#[cast(PR <: Wc, Relation <: Wc, Predicate <: Wc, TraitRef <: Wc)]
 pub struct Wcs {
     set: Set<Wc>,
 }
@@ -114,6 +114,7 @@ impl DowncastTo<()> for Wcs {
 }
 
 #[term($data)]
+#[cast(PR <: WcData, Relation <: PR, Predicate <: PR, TraitRef <: PR)]
 pub struct Wc {
     data: Arc<WcData>,
 }
@@ -160,13 +161,6 @@ impl DowncastFrom<Wc> for WcData {
     }
 }
 
-// ---
-
-cast_impl!((PR) <: (WcData) <: (Wc));
-cast_impl!((Relation) <: (PR) <: (Wc));
-cast_impl!((Predicate) <: (PR) <: (Wc));
-cast_impl!((TraitRef) <: (PR) <: (Wc));
-
 impl UpcastFrom<Wc> for Wcs {
     fn upcast_from(term: Wc) -> Self {
         Wcs { set: set![term] }
@@ -182,8 +176,3 @@ impl DowncastTo<Wc> for Wcs {
         }
     }
 }
-
-cast_impl!((PR) <: (Wc) <: (Wcs));
-cast_impl!((Relation) <: (Wc) <: (Wcs));
-cast_impl!((Predicate) <: (Wc) <: (Wcs));
-cast_impl!((TraitRef) <: (Wc) <: (Wcs));
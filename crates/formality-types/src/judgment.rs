@@ -2,8 +2,23 @@ use std::{cell::RefCell, collections::BTreeSet, sync::Arc};
 
 use crate::fixed_point::FixedPointStack;
 
+pub mod cancel;
+pub mod coverage;
+pub mod parallel;
+pub mod proof_tree;
+pub mod rules;
+pub mod stats;
+mod test_cancel;
+mod test_coinductive;
+mod test_coverage;
+mod test_cycle;
 mod test_filtered;
+mod test_negation;
+mod test_parallel;
+mod test_premises;
 mod test_reachable;
+mod test_rules;
+mod test_trace_filter;
 
 pub type JudgmentStack<J, O> = RefCell<FixedPointStack<J, BTreeSet<O>>>;
 
@@ -14,13 +29,36 @@ struct InferenceRule<I, O> {
     closure: InferenceRuleClosure<I, O>,
 }
 
+/// Declares a judgment, whose outputs are derived by inference rules (see [`push_rules!`]).
+///
+/// Judgments are inductive: the outputs of a recursive call with the same inputs, i.e.,
+/// of a cycle, start out empty, and only grow to what the rules can derive otherwise
+/// (the least fixed point). A `coinductive(<expr>)` clause makes the judgment
+/// coinductive instead: the outputs of a cycle start out as `<expr>`, which may refer
+/// to the inputs, so that the cycle can justify itself (the greatest fixed point,
+/// starting from `<expr>`). E.g., an auto trait holds for a type that contains itself.
 #[macro_export]
 macro_rules! judgment_fn {
+    // The outputs that a cycle starts out with.
+    (@provisional $input:ident ($($input_name:ident),*) => $output:ty) => {
+        Default::default()
+    };
+
+    (@provisional $input:ident ($($input_name:ident),*) => $output:ty, $coinductive:expr) => {
+        {
+            #[allow(unused_variables)]
+            let __JudgmentStruct($($input_name),*) = $input;
+            let output: $output = $crate::cast::Upcast::upcast($coinductive);
+            std::iter::once(output).collect()
+        }
+    };
+
     (
         $(#[$attr:meta])*
         $v:vis fn $name:ident($($input_name:ident : $input_ty:ty),* $(,)?) => $output:ty {
             debug($($debug_input_name:ident),*)
             $(assert($assert_expr:expr))*
+            $(coinductive($coinductive:expr))?
             $(trivial($trivial_expr:expr => $trivial_result:expr))*
             $(($($rule:tt)*))*
         }
@@ -45,16 +83,41 @@ macro_rules! judgment_fn {
 
             $(let $input_name: $input_ty = $crate::cast::Upcast::upcast($input_name);)*
 
+            let _stats_call = $crate::judgment::stats::enter_judgment(stringify!($name));
+
+            // Registered, and referenced by the rules when they fire: this keeps the
+            // registration linked into any program that can call the judgment.
+            static __JUDGMENT_DECL: $crate::judgment::rules::JudgmentDecl =
+                $crate::judgment::rules::JudgmentDecl {
+                    name: stringify!($name),
+                    module: module_path!(),
+                    file: file!(),
+                    line: line!(),
+                    inputs: &[$((stringify!($input_name), stringify!($input_ty))),*],
+                    output: stringify!($output),
+                    rules: &[$($crate::rule_decl!($($rule)*)),*],
+                };
+            $crate::inventory::submit! {
+                $crate::judgment::rules::Registered(&__JUDGMENT_DECL)
+            }
+
             $(
                 // Assertions are preconditions
                 assert!($assert_expr);
             )*
 
+            let _trivial_inputs = || vec![$((stringify!($debug_input_name), format!("{:?}", $debug_input_name))),*];
+
             $(
                 // Trivial cases are an (important) optimization that lets
                 // you cut out all the normal rules.
                 if $trivial_expr {
-                    return std::iter::once($trivial_result).collect();
+                    let inputs = $crate::judgment::proof_tree::is_recording().then(&_trivial_inputs);
+                    let result: $output = $trivial_result;
+                    tracing::debug!(judgment = stringify!($name), rule = "trivial", ?result, "produced");
+                    $crate::judgment::proof_tree::trivial(stringify!($name), inputs, &result);
+                    $crate::judgment::stats::fired("trivial");
+                    return std::iter::once(result).collect();
                 }
             )*
 
@@ -83,12 +146,18 @@ macro_rules! judgment_fn {
                 __JudgmentStruct($($input_name),*),
 
                 // Default value:
-                |_| Default::default(),
+                |_input| $crate::judgment_fn!(@provisional _input ($($input_name),*) => $output $(, $coinductive)?),
 
                 // Next value:
                 |input: __JudgmentStruct| {
                     let mut output = $crate::collections::Set::new();
 
+                    $crate::judgment::proof_tree::enter_judgment(stringify!($name), || {
+                        #[allow(unused_variables)]
+                        let __JudgmentStruct($($input_name),*) = &input;
+                        vec![$((stringify!($debug_input_name), format!("{:?}", $debug_input_name))),*]
+                    });
+
                     $crate::push_rules!(
                         $name,
                         &input,
@@ -97,6 +166,8 @@ macro_rules! judgment_fn {
                         $(($($rule)*))*
                     );
 
+                    $crate::judgment::proof_tree::exit_judgment();
+
                     output
                 },
             )
@@ -122,8 +193,14 @@ macro_rules! judgment_fn {
 ///
 /// * `(<expr> => <binding>)` -- used to apply judgments, but really `<expr>` can be anything with an `into_iter` method.
 /// * `(if <expr>)`
-/// * `(if let <pat> = <expr>)`
-/// * `(let <binding> = <expr>)`
+/// * `(if let <pat> = <expr>)` -- holds if `<expr>` matches `<pat>`.
+/// * `(let <binding> = <expr>)` or `(let <ident>: <type> = <expr>)` -- binds an intermediate result.
+/// * `(ok <expr> => <binding>)` -- for side conditions that may fail with an error: `<expr>`
+///   is a `Result`, and its error is recorded in the [`proof_tree::FailureTrace`] (when
+///   recording proof trees) rather than silently failing the rule.
+/// * `(! (<expr> => _))` -- holds if `<expr>` yields nothing, e.g., if a judgment has no
+///   outputs. The judgments called by `<expr>` must not call back into a judgment call
+///   in progress, as its outputs are not final yet: such unstratified negation panics.
 ///
 /// The conclusions can be the following
 ///
@@ -179,7 +256,9 @@ macro_rules! push_rules {
     // extracted from the input. For anything that is not an identity pattern, invoke `downcast`.
 
     (@match inputs() patterns() args($judgment_name:ident; $n:literal; $v:expr; $output:expr; $($m:tt)*)) => {
-        tracing::trace_span!("matched rule", rule = $n, judgment = stringify!($judgment_name)).in_scope(|| {
+        $crate::judgment::proof_tree::attempt($n);
+        // Rule spans are named after the rule, so that `TraceFilter` can select them.
+        tracing::debug_span!($n, judgment = stringify!($judgment_name)).in_scope(|| {
             $crate::push_rules!(@body ($judgment_name, $n, $v, $output) $($m)*);
         });
     };
@@ -241,6 +320,32 @@ macro_rules! push_rules {
     // expression `v` is carried in from the conclusion and forms the final
     // output of this rule, once all the conditions are evaluated.
 
+    (@body ($judgment_name:ident, $rule_name:literal, $v:expr, $output:expr) (ok $i:expr => $p:pat) $($m:tt)*) => {
+        match $i {
+            Ok(value) => {
+                let $p = value;
+                $crate::push_rules!(@body ($judgment_name, $rule_name, $v, $output) $($m)*);
+            }
+            Err(error) => {
+                tracing::trace!("failed side condition {:?}: {:#}", stringify!($i), error);
+                $crate::judgment::proof_tree::premise_failed($rule_name, &error);
+            }
+        }
+    };
+
+    (@body $args:tt (! ($i:expr => _)) $($m:tt)*) => {
+        $crate::judgment::proof_tree::take_last_call();
+        let holds = $crate::fixed_point::negation(stringify!($i), || {
+            IntoIterator::into_iter($i).next().is_none()
+        });
+        $crate::judgment::proof_tree::take_last_call();
+        if holds {
+            $crate::push_rules!(@body $args $($m)*);
+        } else {
+            tracing::trace!("failed to refute {:?}", stringify!($i))
+        }
+    };
+
     (@body $args:tt (if $c:expr) $($m:tt)*) => {
         if $c {
             $crate::push_rules!(@body $args $($m)*);
@@ -262,9 +367,26 @@ macro_rules! push_rules {
         }
     };
 
+    // Before the next arm, which would fail to parse the type annotation as part of
+    // an expression.
+    (@body $args:tt (let $p:ident : $t:ty = $i:expr) $($m:tt)*) => {
+        {
+            let $p: $t = $i;
+            $crate::push_rules!(@body $args $($m)*);
+        }
+    };
+
     (@body $args:tt ($i:expr => $p:pat) $($m:tt)*) => {
-        for $p in $i {
+        // When recording proof trees, each value produced by a judgment call
+        // brings the derivation of that value along as a premise.
+        $crate::judgment::proof_tree::take_last_call();
+        let values = $i;
+        let derivations = $crate::judgment::proof_tree::take_last_call();
+        for value in values {
+            let premises = $crate::judgment::proof_tree::push_premises(&derivations, &value);
+            let $p = value;
             $crate::push_rules!(@body $args $($m)*);
+            $crate::judgment::proof_tree::pop_premises(premises);
         }
     };
 
@@ -278,7 +400,10 @@ macro_rules! push_rules {
     (@body ($judgment_name:ident, $rule_name:literal, $v:expr, $output:expr)) => {
         {
             let result = $crate::cast::Upcast::upcast($v);
-            tracing::debug!("produced {:?} from rule {:?} in judgment {:?}", result, $rule_name, stringify!($judgment_name));
+            tracing::debug!(judgment = stringify!($judgment_name), rule = $rule_name, ?result, "produced");
+            $crate::judgment::proof_tree::conclude($rule_name, &result);
+            $crate::judgment::coverage::fired(&__JUDGMENT_DECL, $rule_name);
+            $crate::judgment::stats::fired($rule_name);
             $output.insert(result)
         }
     };
@@ -0,0 +1,109 @@
+//! Cooperative cancellation of judgments, so that a proof that takes too long can be
+//! given up on rather than hang.
+//!
+//! [`with_cancellation`] runs an operation with judgments evaluated under a
+//! [`CancellationToken`], which is cancelled explicitly or when its deadline passes.
+//! Every judgment call checks the token: once it is cancelled, the calls return their
+//! default output (usually no outputs at all) without evaluating their rules, so that
+//! the operation finishes promptly. What it returns is then meaningless, which is why
+//! [`with_cancellation`] reports [`Cancelled`] instead. The outputs computed after the
+//! cancellation are not memoized.
+
+use std::{
+    cell::RefCell,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Cancels the judgments evaluated under it, see the module documentation. Clones
+/// share whether they are cancelled.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// A token that is only cancelled by [`CancellationToken::cancel`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that is also cancelled once `time_limit` has passed from now.
+    pub fn with_time_limit(time_limit: Duration) -> Self {
+        CancellationToken {
+            cancelled: Default::default(),
+            deadline: Some(Instant::now() + time_limit),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+/// The operation run by [`with_cancellation`] was cancelled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+thread_local! {
+    /// The tokens that the judgments on this thread are evaluated under, from the
+    /// outermost [`with_cancellation`].
+    static ACTIVE: RefCell<Vec<CancellationToken>> = RefCell::new(vec![])
+}
+
+/// Runs `op` with the judgments it evaluates on this thread (and, through
+/// [`super::parallel`], on others) under `token`, in addition to the tokens they are
+/// already under. Returns [`Cancelled`] if any of those was cancelled by the end.
+pub fn with_cancellation<R>(
+    token: &CancellationToken,
+    op: impl FnOnce() -> R,
+) -> Result<R, Cancelled> {
+    let mut tokens = active();
+    tokens.push(token.clone());
+    with_active(tokens, || {
+        let result = op();
+        if is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(result)
+        }
+    })
+}
+
+/// Whether the judgments on this thread are to stop.
+pub fn is_cancelled() -> bool {
+    ACTIVE.with(|active| active.borrow().iter().any(|t| t.is_cancelled()))
+}
+
+/// The tokens that the judgments on this thread are evaluated under.
+pub fn active() -> Vec<CancellationToken> {
+    ACTIVE.with(|active| active.borrow().clone())
+}
+
+/// Runs `op` with the judgments on this thread evaluated under `tokens` (only), e.g.
+/// under the [`active`] tokens of the thread that handed `op` over.
+pub fn with_active<R>(tokens: Vec<CancellationToken>, op: impl FnOnce() -> R) -> R {
+    let previous = ACTIVE.with(|active| active.replace(tokens));
+    let result = op();
+    ACTIVE.with(|active| active.replace(previous));
+    result
+}
@@ -0,0 +1,138 @@
+//! Optional recording of which rules of the judgments fire, to find the rules that
+//! no test exercises.
+//!
+//! Recording is enabled by setting the environment variable `FORMALITY_RULE_COVERAGE`
+//! to the (absolute) path of a file. Each process then appends to that file every rule
+//! that produces an output; this way the test binaries, and the processes they spawn,
+//! all contribute to the same file. It is read back with [`Coverage::read`], which
+//! compares it with the [complete rule set](`super::rules::judgments`).
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+
+use super::rules::{judgments, JudgmentDecl};
+
+/// The rules of the judgments, and whether they fired.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// For each judgment, its rules and whether they fired.
+    pub judgments: BTreeMap<String, BTreeMap<String, bool>>,
+}
+
+impl Coverage {
+    /// Reads the file written by the recording, see the module documentation.
+    pub fn read(path: impl AsRef<Path>) -> std::io::Result<Coverage> {
+        Ok(Coverage::parse(
+            &std::fs::read_to_string(path)?,
+            &judgments(),
+        ))
+    }
+
+    /// The coverage of the rules of `judgments` recorded in `text`, the lines written by
+    /// the recording. Malformed lines (e.g., from interleaved writes) are ignored.
+    pub fn parse(text: &str, judgments: &[&JudgmentDecl]) -> Coverage {
+        let mut coverage = Coverage::default();
+        for judgment in judgments {
+            let rules = coverage
+                .judgments
+                .entry(judgment.name.to_string())
+                .or_default();
+            for rule in judgment.rules {
+                rules.insert(rule.name.to_string(), false);
+            }
+        }
+        for line in text.lines() {
+            let mut parts = line.split('\t');
+            let (Some("fired"), Some(judgment), Some(rule), None) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+            let rules = coverage.judgments.entry(judgment.to_string()).or_default();
+            rules.insert(rule.to_string(), true);
+        }
+        coverage
+    }
+
+    /// The rules that never fired, as pairs of a judgment and a rule.
+    pub fn unexercised(&self) -> BTreeSet<(&str, &str)> {
+        self.judgments
+            .iter()
+            .flat_map(|(judgment, rules)| {
+                rules
+                    .iter()
+                    .filter(|(_, fired)| !**fired)
+                    .map(move |(rule, _)| (&judgment[..], &rule[..]))
+            })
+            .collect()
+    }
+}
+
+/// A report of the rules that never fired, judgment by judgment.
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rules: usize = self.judgments.values().map(BTreeMap::len).sum();
+        let unexercised = self.unexercised();
+        writeln!(
+            f,
+            "{} of {rules} rules in {} judgments fired",
+            rules - unexercised.len(),
+            self.judgments.len(),
+        )?;
+        for (judgment, rules) in &self.judgments {
+            let never: Vec<&str> = rules
+                .iter()
+                .filter(|(_, fired)| !**fired)
+                .map(|(rule, _)| &rule[..])
+                .collect();
+            if !never.is_empty() {
+                writeln!(f, "{judgment}: never fired {never:?}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Recorder {
+    file: File,
+    /// What this process has already written, so that each line is written once.
+    written: HashSet<(&'static str, &'static str)>,
+}
+
+fn recorder() -> Option<&'static Mutex<Recorder>> {
+    static RECORDER: OnceLock<Option<Mutex<Recorder>>> = OnceLock::new();
+    RECORDER
+        .get_or_init(|| {
+            let path = std::env::var_os("FORMALITY_RULE_COVERAGE")?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .unwrap_or_else(|e| panic!("cannot record rule coverage to {path:?}: {e}"));
+            Some(Mutex::new(Recorder {
+                file,
+                written: HashSet::new(),
+            }))
+        })
+        .as_ref()
+}
+
+/// Records that the rule `rule` of the judgment `judgment` produced an output.
+pub fn fired(judgment: &'static JudgmentDecl, rule: &'static str) {
+    let judgment = judgment.name;
+    let Some(recorder) = recorder() else {
+        return;
+    };
+    let mut recorder = recorder.lock().unwrap();
+    if recorder.written.insert((judgment, rule)) {
+        // One write per line, so that the lines of concurrent processes do not interleave.
+        let line = format!("fired\t{judgment}\t{rule}\n");
+        recorder.file.write_all(line.as_bytes()).unwrap();
+    }
+}
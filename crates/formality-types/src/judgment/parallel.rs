@@ -0,0 +1,47 @@
+//! Opt-in parallel evaluation of independent judgment calls, e.g., of the candidates
+//! for one goal.
+//!
+//! The state of the judgments in progress is thread-local, so a call evaluated on
+//! another thread does not see the computations in progress on the calling thread.
+//! A cycle back into one of them is computed anew there, which yields the same fixed
+//! point. A thread that waits for the calls it started may meanwhile run unrelated
+//! calls, so each call only sees the computations in progress on its thread that were
+//! so when it was started (see [`fixed_point::Spawn`]). Each thread also has memo
+//! tables of its own, which only hold final outputs. The outputs of the computations
+//! in progress on the calling thread are not memoized if those of a call depended on
+//! their context, e.g., on the depth of the call. Proof trees are recorded
+//! thread-locally as well, so while recording, everything is evaluated in order on
+//! the calling thread. Cancellation (see [`cancel`]) carries over to the other
+//! threads.
+
+use rayon::prelude::*;
+
+use crate::fixed_point;
+
+use super::{cancel, proof_tree};
+
+/// Maps `op` over `items`, on rayon's thread pool if `parallel` is set. The results
+/// are in the order of `items` either way, so merging them is deterministic.
+pub fn map<T, R>(parallel: bool, items: &[T], op: impl Fn(&T) -> R + Sync + Send) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    if parallel && !proof_tree::is_recording() {
+        let active = cancel::active();
+        let spawn = fixed_point::Spawn::here();
+        let results: Vec<(R, bool)> = items
+            .par_iter()
+            .map(|item| cancel::with_active(active.clone(), || spawn.run(|| op(item))))
+            .collect();
+        if results
+            .iter()
+            .any(|(_, context_dependent)| *context_dependent)
+        {
+            fixed_point::mark_context_dependent();
+        }
+        results.into_iter().map(|(result, _)| result).collect()
+    } else {
+        items.iter().map(op).collect()
+    }
+}
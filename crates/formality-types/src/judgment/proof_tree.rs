@@ -0,0 +1,417 @@
+//! Optional recording of proof trees.
+//!
+//! Judgments only return their outputs, not *why* they hold. When recording is enabled
+//! (see [`record`]), each judgment evaluation also records, for every output, the rule
+//! that produced it and the derivations of the premises (the `=>` conditions) it used.
+//!
+//! The derivations of a judgment call are handed to the caller through the "last call"
+//! of the current frame, keyed by the `Debug` form of the outputs. Plain functions that
+//! combine judgments (like `prove` and its combinators) forward derivations the same way
+//! via [`take_last_call`] and [`set_last_call`]. A recursive call that is answered from
+//! the fixed-point stack has no derivation, so the cycle simply ends the tree there; such
+//! incomplete derivations are replaced as soon as a complete one is found.
+//!
+//! Failures are recorded too: every judgment call without outputs is kept as a
+//! [`FailedGoal`], together with the rules that were attempted for it and the errors
+//! of their fallible (`ok`) premises. A [`FailureTrace`] summarizes them to explain why
+//! a goal could not be proven.
+//!
+//! Both can be rendered graphically with [`to_dot`] and [`to_mermaid`].
+
+use std::{
+    cell::RefCell,
+    fmt::{Debug, Display},
+};
+
+use serde::Serialize;
+
+mod graph;
+pub use graph::{to_dot, to_mermaid, Graph};
+
+/// A derivation of a judgment: the rule that produced `output` from `inputs`,
+/// together with the derivations of its premises.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize)]
+pub struct ProofTree {
+    pub judgment: &'static str,
+    /// The `Debug` form of the judgment inputs. The program's declarations are the same
+    /// throughout a proof and are omitted.
+    pub inputs: Vec<(&'static str, String)>,
+    pub rule: &'static str,
+    pub output: String,
+    pub children: Vec<ProofTree>,
+}
+
+impl ProofTree {
+    fn fmt_indented(&self, f: &mut std::fmt::Formatter<'_>, indent: usize) -> std::fmt::Result {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            f,
+            "{:indent$}{}({}) => {} by {:?}",
+            "", self.judgment, inputs, self.output, self.rule,
+        )?;
+        for child in &self.children {
+            child.fmt_indented(f, indent + 2)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ProofTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// The derivations of the outputs of a judgment call: for each output, the proof trees
+/// of its premises.
+#[derive(Clone, Default, Debug)]
+pub struct Derivations {
+    entries: Vec<Derivation>,
+}
+
+#[derive(Clone, Debug)]
+struct Derivation {
+    output: String,
+    premises: Vec<ProofTree>,
+    /// False if some premise was answered by a cycle and has no derivation.
+    complete: bool,
+}
+
+impl Derivations {
+    /// The premises of `output`, if it has a recorded derivation.
+    pub fn premises(&self, output: &impl Debug) -> Option<&[ProofTree]> {
+        let output = format!("{output:?}");
+        self.entries
+            .iter()
+            .find(|d| d.output == output)
+            .map(|d| &d.premises[..])
+    }
+
+    /// Records the premises of `output`. The first complete derivation wins.
+    pub fn insert(&mut self, output: &impl Debug, premises: Vec<ProofTree>, complete: bool) {
+        let derivation = Derivation {
+            output: format!("{output:?}"),
+            premises,
+            complete,
+        };
+        match self
+            .entries
+            .iter_mut()
+            .find(|d| d.output == derivation.output)
+        {
+            Some(d) => {
+                if !d.complete && complete {
+                    *d = derivation;
+                }
+            }
+            None => self.entries.push(derivation),
+        }
+    }
+
+    /// Whether the derivation of `output` is complete.
+    pub fn is_complete(&self, output: &impl Debug) -> bool {
+        let output = format!("{output:?}");
+        self.entries
+            .iter()
+            .any(|d| d.output == output && d.complete)
+    }
+}
+
+/// A judgment call that produced no outputs.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct FailedGoal {
+    pub judgment: &'static str,
+    pub inputs: Vec<(&'static str, String)>,
+    /// The rules whose conclusion matched the inputs, all of which failed.
+    pub rules: Vec<&'static str>,
+    /// The errors of the fallible premises that failed, with their rules.
+    pub errors: Vec<(&'static str, String)>,
+    /// The number of enclosing judgment calls.
+    pub depth: usize,
+}
+
+impl std::fmt::Display for FailedGoal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}({}), rules tried: {:?}",
+            self.judgment,
+            brief_inputs(&self.inputs),
+            self.rules
+        )?;
+        for (rule, error) in &self.errors {
+            write!(f, ", {rule:?} failed: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The inputs except `env` and `assumptions`, for brevity.
+fn brief_inputs(inputs: &[(&'static str, String)]) -> String {
+    inputs
+        .iter()
+        .filter(|(name, _)| !matches!(*name, "env" | "assumptions"))
+        .map(|(name, value)| format!("{name}: {value}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Explains why a goal could not be proven: the candidate rules attempted for the
+/// root goals, and the deepest subgoals that failed.
+#[derive(Clone, Default, PartialEq, Eq, Hash, Debug)]
+pub struct FailureTrace {
+    pub root: Vec<FailedGoal>,
+    pub deepest: Vec<FailedGoal>,
+}
+
+impl FailureTrace {
+    /// Summarizes the failed calls of `judgments`: the root goals are the outermost
+    /// ones, the deepest are the innermost ones. Only the given judgments are
+    /// considered, as the failures of auxiliary judgments are mostly noise.
+    pub fn new(failures: &[FailedGoal], judgments: &[&str]) -> Self {
+        let failures: Vec<&FailedGoal> = failures
+            .iter()
+            .filter(|g| judgments.contains(&g.judgment))
+            .collect();
+        let at_depth = |depth: Option<usize>| {
+            failures
+                .iter()
+                .filter(|g| Some(g.depth) == depth)
+                .map(|g| (*g).clone())
+                .collect::<Vec<_>>()
+        };
+        let root = at_depth(failures.iter().map(|g| g.depth).min());
+        let deepest = at_depth(failures.iter().map(|g| g.depth).max());
+        FailureTrace {
+            deepest: if deepest == root { vec![] } else { deepest },
+            root,
+        }
+    }
+}
+
+impl std::fmt::Display for FailureTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.root.is_empty() {
+            writeln!(f, "failed goals:")?;
+            for goal in &self.root {
+                writeln!(f, "  {goal}")?;
+            }
+        }
+        if !self.deepest.is_empty() {
+            writeln!(f, "deepest failed subgoals:")?;
+            for goal in &self.deepest {
+                writeln!(f, "  {goal}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What was recorded by [`record`].
+#[derive(Clone, Default, Debug)]
+pub struct Recording {
+    /// The derivations of the last judgment call, if any.
+    pub derivations: Option<Derivations>,
+    pub failures: Vec<FailedGoal>,
+}
+
+#[derive(Default)]
+struct Recorder {
+    frames: Vec<Frame>,
+    failures: Vec<FailedGoal>,
+}
+
+#[derive(Default)]
+struct Frame {
+    judgment: &'static str,
+    inputs: Vec<(&'static str, String)>,
+    /// The rules whose conclusion matched the inputs.
+    attempted: Vec<&'static str>,
+    /// The errors of the fallible premises that failed, with their rules.
+    errors: Vec<(&'static str, String)>,
+    /// The premises chosen by the `=>` conditions of the rule being evaluated.
+    path: Vec<ProofTree>,
+    /// The number of `=>` conditions on the path whose value has no derivation.
+    missing: usize,
+    /// The derivations of the outputs of this judgment found so far.
+    derivations: Derivations,
+    /// The derivations of the last judgment call made from this frame.
+    last_call: Option<Derivations>,
+}
+
+thread_local! {
+    static RECORDER: RefCell<Option<Recorder>> = RefCell::new(None)
+}
+
+fn with_recorder<R>(op: impl FnOnce(&mut Recorder) -> R) -> Option<R> {
+    RECORDER.with(|recorder| recorder.borrow_mut().as_mut().map(op))
+}
+
+fn with_frame<R>(op: impl FnOnce(&mut Frame) -> R) -> Option<R> {
+    with_recorder(|recorder| op(recorder.frames.last_mut().unwrap()))
+}
+
+/// Runs `op` while recording proof trees and failures.
+pub fn record<R>(op: impl FnOnce() -> R) -> (R, Recording) {
+    let previous = RECORDER.with(|recorder| {
+        recorder.replace(Some(Recorder {
+            frames: vec![Frame::default()],
+            failures: vec![],
+        }))
+    });
+    let result = op();
+    let mut recorder = RECORDER
+        .with(|recorder| recorder.replace(previous))
+        .unwrap();
+    let recording = Recording {
+        derivations: recorder.frames.pop().unwrap().last_call,
+        failures: recorder.failures,
+    };
+    (result, recording)
+}
+
+pub fn is_recording() -> bool {
+    RECORDER.with(|recorder| recorder.borrow().is_some())
+}
+
+/// Takes the derivations of the last judgment call made from the current frame.
+pub fn take_last_call() -> Option<Derivations> {
+    with_frame(|frame| frame.last_call.take()).flatten()
+}
+
+/// Sets the derivations of the last judgment call of the current frame, for plain
+/// functions that wrap judgment calls.
+pub fn set_last_call(derivations: Option<Derivations>) {
+    with_frame(|frame| frame.last_call = derivations);
+}
+
+#[doc(hidden)]
+pub fn enter_judgment(
+    judgment: &'static str,
+    inputs: impl FnOnce() -> Vec<(&'static str, String)>,
+) {
+    with_recorder(|recorder| {
+        let inputs = inputs()
+            .into_iter()
+            .filter(|(name, _)| *name != "decls")
+            .collect();
+        recorder.frames.push(Frame {
+            judgment,
+            inputs,
+            ..Frame::default()
+        });
+    });
+}
+
+/// Pops the frame of a judgment call. A call is evaluated again until it reaches a
+/// fixed point, so a failure of an earlier evaluation is forgotten once it succeeds.
+#[doc(hidden)]
+pub fn exit_judgment() {
+    with_recorder(|recorder| {
+        let frame = recorder.frames.pop().unwrap();
+        if frame.derivations.entries.is_empty() {
+            let failure = FailedGoal {
+                judgment: frame.judgment,
+                inputs: frame.inputs,
+                rules: frame.attempted,
+                errors: frame.errors,
+                depth: recorder.frames.len() - 1,
+            };
+            if !recorder.failures.contains(&failure) {
+                recorder.failures.push(failure);
+            }
+        } else {
+            recorder
+                .failures
+                .retain(|f| f.judgment != frame.judgment || f.inputs != frame.inputs);
+        }
+        recorder.frames.last_mut().unwrap().last_call = Some(frame.derivations);
+    });
+}
+
+/// Records that the conclusion of `rule` matched the inputs.
+#[doc(hidden)]
+pub fn attempt(rule: &'static str) {
+    with_frame(|frame| frame.attempted.push(rule));
+}
+
+/// Records that a fallible premise of `rule` failed with `error`.
+#[doc(hidden)]
+pub fn premise_failed(rule: &'static str, error: &impl Display) {
+    with_frame(|frame| {
+        let error = (rule, format!("{error:#}"));
+        if !frame.errors.contains(&error) {
+            frame.errors.push(error);
+        }
+    });
+}
+
+/// Records the output of a judgment that was decided by a `trivial` clause.
+#[doc(hidden)]
+pub fn trivial(
+    judgment: &'static str,
+    inputs: Option<Vec<(&'static str, String)>>,
+    output: &impl Debug,
+) {
+    if let Some(inputs) = inputs {
+        enter_judgment(judgment, || inputs);
+        conclude("trivial", output);
+        exit_judgment();
+    }
+}
+
+/// The premises pushed for one `=>` condition, see [`push_premises`].
+#[doc(hidden)]
+pub struct Pushed {
+    len: usize,
+    missing: bool,
+}
+
+/// Pushes the premises for `value`, produced by a `=>` condition, onto the path.
+/// The result must be passed to [`pop_premises`] to remove them again.
+#[doc(hidden)]
+pub fn push_premises(derivations: &Option<Derivations>, value: &impl Debug) -> Option<Pushed> {
+    let derivations = derivations.as_ref()?;
+    with_frame(|frame| {
+        let premises = derivations.premises(value).unwrap_or_default();
+        let pushed = Pushed {
+            len: premises.len(),
+            missing: !derivations.is_complete(value),
+        };
+        frame.path.extend(premises.iter().cloned());
+        frame.missing += pushed.missing as usize;
+        pushed
+    })
+}
+
+#[doc(hidden)]
+pub fn pop_premises(pushed: Option<Pushed>) {
+    if let Some(pushed) = pushed {
+        with_frame(|frame| {
+            frame.path.truncate(frame.path.len() - pushed.len);
+            frame.missing -= pushed.missing as usize;
+        });
+    }
+}
+
+/// Records that `rule` produced `output` from the premises currently on the path.
+#[doc(hidden)]
+pub fn conclude(rule: &'static str, output: &impl Debug) {
+    with_frame(|frame| {
+        let tree = ProofTree {
+            judgment: frame.judgment,
+            inputs: frame.inputs.clone(),
+            rule,
+            output: format!("{output:?}"),
+            children: frame.path.clone(),
+        };
+        frame
+            .derivations
+            .insert(output, vec![tree], frame.missing == 0);
+    });
+}
@@ -0,0 +1,176 @@
+//! Rendering of proof trees and failure traces as graphs, for Graphviz or Mermaid.
+
+use std::fmt::Write;
+
+use super::{brief_inputs, FailedGoal, FailureTrace, ProofTree};
+
+/// A graph of judgment calls, built from a [`ProofTree`] or a [`FailureTrace`].
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Graph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Node {
+    /// One entry per line.
+    label: Vec<String>,
+    /// All inputs, which the label abbreviates.
+    tooltip: String,
+    failed: bool,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Edge {
+    from: usize,
+    to: usize,
+    /// Whether the edge stands for a path of judgment calls rather than a single premise.
+    indirect: bool,
+}
+
+impl Graph {
+    fn add_node(&mut self, node: Node) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    fn add_tree(&mut self, tree: &ProofTree) -> usize {
+        let node = self.add_node(Node {
+            label: vec![
+                format!("{}({})", tree.judgment, brief_inputs(&tree.inputs)),
+                format!("by {:?}", tree.rule),
+                format!("=> {}", tree.output),
+            ],
+            tooltip: all_inputs(&tree.inputs),
+            failed: false,
+        });
+        for child in &tree.children {
+            let child = self.add_tree(child);
+            self.edges.push(Edge {
+                from: node,
+                to: child,
+                indirect: false,
+            });
+        }
+        node
+    }
+
+    fn add_failed_goal(&mut self, goal: &FailedGoal) -> usize {
+        let errors = goal
+            .errors
+            .iter()
+            .map(|(rule, error)| format!("{rule:?} failed: {error}"));
+        self.add_node(Node {
+            label: [
+                format!("{}({})", goal.judgment, brief_inputs(&goal.inputs)),
+                format!("rules tried: {:?}", goal.rules),
+            ]
+            .into_iter()
+            .chain(errors)
+            .collect(),
+            tooltip: all_inputs(&goal.inputs),
+            failed: true,
+        })
+    }
+}
+
+fn all_inputs(inputs: &[(&'static str, String)]) -> String {
+    inputs
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl From<&ProofTree> for Graph {
+    fn from(tree: &ProofTree) -> Self {
+        let mut graph = Graph::default();
+        graph.add_tree(tree);
+        graph
+    }
+}
+
+/// The root goals point to the deepest failed subgoals through indirect edges, as the
+/// trace does not record the calls in between.
+impl From<&FailureTrace> for Graph {
+    fn from(trace: &FailureTrace) -> Self {
+        let mut graph = Graph::default();
+        let roots: Vec<usize> = trace
+            .root
+            .iter()
+            .map(|goal| graph.add_failed_goal(goal))
+            .collect();
+        let deepest: Vec<usize> = trace
+            .deepest
+            .iter()
+            .map(|goal| graph.add_failed_goal(goal))
+            .collect();
+        for &from in &roots {
+            for &to in &deepest {
+                graph.edges.push(Edge {
+                    from,
+                    to,
+                    indirect: true,
+                });
+            }
+        }
+        graph
+    }
+}
+
+/// Renders `graph` in the DOT language of Graphviz.
+pub fn to_dot(graph: impl Into<Graph>) -> String {
+    fn escape(s: &str) -> String {
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    }
+
+    let graph = graph.into();
+    let mut dot = String::new();
+    writeln!(dot, "digraph proof {{").unwrap();
+    writeln!(dot, "    node [shape=box];").unwrap();
+    for (i, node) in graph.nodes.iter().enumerate() {
+        let color = if node.failed { ", color=red" } else { "" };
+        writeln!(
+            dot,
+            "    n{i} [label=\"{}\", tooltip=\"{}\"{color}];",
+            escape(&node.label.join("\n")),
+            escape(&node.tooltip),
+        )
+        .unwrap();
+    }
+    for edge in &graph.edges {
+        let style = if edge.indirect { " [style=dashed]" } else { "" };
+        writeln!(dot, "    n{} -> n{}{style};", edge.from, edge.to).unwrap();
+    }
+    writeln!(dot, "}}").unwrap();
+    dot
+}
+
+/// Renders `graph` as a Mermaid flowchart. Mermaid has no tooltips without
+/// interaction, so the labels are all there is.
+pub fn to_mermaid(graph: impl Into<Graph>) -> String {
+    fn escape(s: &str) -> String {
+        s.replace('"', "#quot;")
+            .replace('<', "#lt;")
+            .replace('>', "#gt;")
+    }
+
+    let graph = graph.into();
+    let mut mermaid = String::new();
+    writeln!(mermaid, "graph TD").unwrap();
+    for (i, node) in graph.nodes.iter().enumerate() {
+        let label: Vec<String> = node.label.iter().map(|l| escape(l)).collect();
+        let class = if node.failed { ":::failed" } else { "" };
+        writeln!(mermaid, "    n{i}[\"{}\"]{class}", label.join("<br/>")).unwrap();
+    }
+    for edge in &graph.edges {
+        let arrow = if edge.indirect { "-.->" } else { "-->" };
+        writeln!(mermaid, "    n{} {arrow} n{}", edge.from, edge.to).unwrap();
+    }
+    if graph.nodes.iter().any(|node| node.failed) {
+        writeln!(mermaid, "    classDef failed stroke:red").unwrap();
+    }
+    mermaid
+}
@@ -0,0 +1,76 @@
+//! The judgments and their inference rules, as written in their `judgment_fn!`s.
+//!
+//! Each `judgment_fn!` registers a [`JudgmentDecl`] in a global inventory, so that
+//! tools (documentation, [coverage](`super::coverage`), ...) can iterate over the
+//! complete rule set of the crates linked into the program with [`judgments`].
+//! The rules can be rendered as inference rules with [`to_latex`] and [`to_markdown`].
+
+use serde::Serialize;
+
+mod render;
+pub use render::{to_latex, to_markdown};
+
+/// A judgment declared with `judgment_fn!`.
+#[derive(Debug, Serialize)]
+pub struct JudgmentDecl {
+    pub name: &'static str,
+
+    /// The module that declares the judgment; names are only unique within a module.
+    pub module: &'static str,
+
+    /// The source file and line of the declaration.
+    pub file: &'static str,
+    pub line: u32,
+
+    /// The names and types of the inputs.
+    pub inputs: &'static [(&'static str, &'static str)],
+    pub output: &'static str,
+
+    /// The rules, in the order they are written.
+    pub rules: &'static [RuleDecl],
+}
+
+/// An inference rule of a judgment. The premises and the conclusion are as written,
+/// e.g. `prove_wc(decls, env, assumptions, goal) => c` or `if let Some(x) = y`.
+#[derive(Debug, Serialize)]
+pub struct RuleDecl {
+    pub name: &'static str,
+    pub premises: &'static [&'static str],
+    pub conclusion: &'static str,
+}
+
+/// The entry of a judgment in the inventory.
+#[doc(hidden)]
+pub struct Registered(pub &'static JudgmentDecl);
+
+inventory::collect!(Registered);
+
+/// All judgments declared in the program, ordered by module and name.
+///
+/// Only the judgments of the crates that are linked into the program are registered;
+/// a crate whose judgments the program never calls may be left out by the linker.
+pub fn judgments() -> Vec<&'static JudgmentDecl> {
+    let mut judgments: Vec<_> = inventory::iter::<Registered>().map(|r| r.0).collect();
+    judgments.sort_by_key(|j| (j.module, j.name));
+    judgments
+}
+
+/// Builds the [`RuleDecl`] of a rule written as in `push_rules!`.
+#[macro_export]
+macro_rules! rule_decl {
+    (@accum premises($($p:expr,)*) ---$(-)* ($n:literal) ($($c:tt)*)) => {
+        $crate::judgment::rules::RuleDecl {
+            name: $n,
+            premises: &[$($p),*],
+            conclusion: stringify!($($c)*),
+        }
+    };
+
+    (@accum premises($($p:expr,)*) ($($premise:tt)*) $($rest:tt)*) => {
+        $crate::rule_decl!(@accum premises($($p,)* stringify!($($premise)*),) $($rest)*)
+    };
+
+    ($($rule:tt)*) => {
+        $crate::rule_decl!(@accum premises() $($rule)*)
+    };
+}
@@ -0,0 +1,132 @@
+//! Rendering of the rules as inference rules (premises over conclusion), for LaTeX
+//! or Markdown with MathJax.
+
+use std::fmt::Write;
+
+use super::{JudgmentDecl, RuleDecl};
+
+/// Renders the rules of `judgments` in LaTeX, one displayed formula per rule. There is
+/// no preamble, so that the output can be `\input` into a document; it uses `\dfrac`
+/// from `amsmath`.
+pub fn to_latex(judgments: &[&JudgmentDecl]) -> String {
+    let mut latex = String::new();
+    for judgment in judgments {
+        writeln!(latex, "\\subsection*{{${}$}}", mathtt(judgment.name)).unwrap();
+        writeln!(
+            latex,
+            "% {} ({}:{})",
+            judgment.module, judgment.file, judgment.line
+        )
+        .unwrap();
+        writeln!(latex, "${}$", mathtt(&signature(judgment))).unwrap();
+        for rule in judgment.rules {
+            writeln!(latex, "\\[\n{}\n\\]", inference_rule(rule)).unwrap();
+        }
+        writeln!(latex).unwrap();
+    }
+    latex
+}
+
+/// Renders the rules of `judgments` in Markdown, one `$$` formula per rule, as
+/// MathJax (e.g. on GitHub or in mdbook) displays it.
+pub fn to_markdown(judgments: &[&JudgmentDecl]) -> String {
+    let mut markdown = String::new();
+    for judgment in judgments {
+        writeln!(markdown, "## `{}`\n", judgment.name).unwrap();
+        writeln!(
+            markdown,
+            "`{}`, declared in `{}` (`{}:{}`).\n",
+            signature(judgment),
+            judgment.module,
+            judgment.file,
+            judgment.line,
+        )
+        .unwrap();
+        for rule in judgment.rules {
+            writeln!(markdown, "$$\n{}\n$$\n", inference_rule(rule)).unwrap();
+        }
+    }
+    markdown
+}
+
+/// The judgment as it is called, e.g. `prove_wc(decls: Decls, ...) => Constraints`.
+fn signature(judgment: &JudgmentDecl) -> String {
+    let inputs: Vec<String> = judgment
+        .inputs
+        .iter()
+        .map(|(name, ty)| format!("{name}: {}", tidy(ty)))
+        .collect();
+    format!(
+        "{}({}) => {}",
+        judgment.name,
+        inputs.join(", "),
+        tidy(judgment.output)
+    )
+}
+
+/// The rule with its premises stacked over the conclusion, and its name on the right.
+fn inference_rule(rule: &RuleDecl) -> String {
+    let premises: Vec<String> = rule.premises.iter().map(|p| mathtt(p)).collect();
+    format!(
+        "\\dfrac{{\\begin{{array}}{{c}}{}\\end{{array}}}}{{{}}}\\;(\\text{{{}}})",
+        premises.join(" \\\\ "),
+        mathtt(rule.conclusion),
+        rule.name,
+    )
+}
+
+/// Undoes the spacing of `stringify!`, which writes e.g. `ExprData :: Unit`,
+/// `f(& x)`, `vec! [x]`, `let(a, b) = ..` and `if! x`, and wraps long expressions
+/// over several lines.
+fn tidy(s: &str) -> String {
+    let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+    let s = s
+        .replace(" :: ", "::")
+        .replace(":: ", "::")
+        .replace(" ::", "::")
+        .replace(" : ", ": ")
+        .replace("! [", "![")
+        .replace("! (", "!(")
+        .replace("let(", "let (")
+        .replace("if! ", "if !");
+
+    // `& x` is a reference, but the second `&` of `a && b` is not.
+    let mut tidy = String::new();
+    let mut chars = s.chars().peekable();
+    let mut previous = None;
+    while let Some(c) = chars.next() {
+        tidy.push(c);
+        if c == '&'
+            && chars.peek() == Some(&' ')
+            && matches!(previous, None | Some(' ' | '(' | '[' | ','))
+        {
+            chars.next();
+        }
+        previous = Some(c);
+    }
+    tidy
+}
+
+/// `s` in a typewriter font, in math mode; this works the same in LaTeX and MathJax.
+fn mathtt(s: &str) -> String {
+    format!("\\mathtt{{{}}}", escape(&tidy(s)))
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\backslash{}"),
+            '{' | '}' | '_' | '&' | '#' | '$' | '%' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '^' => escaped.push_str("\\hat{}"),
+            '~' => escaped.push_str("\\sim{}"),
+            // Math mode ignores spaces.
+            ' ' => escaped.push_str("\\ "),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
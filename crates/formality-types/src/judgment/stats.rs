@@ -0,0 +1,171 @@
+//! Optional statistics of the judgments, to measure the cost of the rules rather than
+//! guess it: how often each judgment is called and its calls are answered from the
+//! memo tables or from a cycle, how many iterations its fixed points take, how often
+//! each rule fires, and how long the calls take.
+//!
+//! Statistics are collected while running an operation with [`record`]. Like proof
+//! trees, they are recorded thread-locally, so the judgments that [`super::parallel`]
+//! evaluates on other threads are not counted.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// The statistics recorded by [`record`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub judgments: BTreeMap<&'static str, JudgmentStats>,
+
+    /// The deepest nesting of judgment calls.
+    pub max_depth: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JudgmentStats {
+    /// The calls of the judgment, i.e., the goals attempted.
+    pub calls: usize,
+
+    /// The calls answered from the memo table.
+    pub memo_hits: usize,
+
+    /// The recursive calls answered with the provisional output of a call in progress.
+    pub cycle_hits: usize,
+
+    /// The iterations of the fixed-point computations, each applying all the rules.
+    pub iterations: usize,
+
+    /// How often each rule produced an output; `trivial` for the trivial cases.
+    pub rule_firings: BTreeMap<&'static str, usize>,
+
+    /// The time spent in the calls of the judgment, including the judgments they call.
+    /// Calls within a call of the same judgment are not counted again.
+    pub time: Duration,
+}
+
+/// A table of the judgments, the most time-consuming first, with the firings of
+/// their rules.
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.judgments.keys().map(|j| j.len()).max().unwrap_or(0);
+        writeln!(f, "max depth: {}", self.max_depth)?;
+        writeln!(
+            f,
+            "{:<width$} {:>8} {:>8} {:>8} {:>10} {:>12}",
+            "judgment", "calls", "memo", "cycle", "iterations", "time"
+        )?;
+        let mut judgments: Vec<_> = self.judgments.iter().collect();
+        judgments.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.time));
+        for (judgment, stats) in judgments {
+            writeln!(
+                f,
+                "{:<width$} {:>8} {:>8} {:>8} {:>10} {:>12}",
+                judgment,
+                stats.calls,
+                stats.memo_hits,
+                stats.cycle_hits,
+                stats.iterations,
+                format!("{:.3?}", stats.time),
+            )?;
+            for (rule, firings) in &stats.rule_firings {
+                writeln!(f, "    {rule:?} fired {firings} times")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Recorder {
+    stats: Stats,
+
+    /// The judgment calls in progress, from the outermost.
+    calls: Vec<&'static str>,
+}
+
+thread_local! {
+    static RECORDER: RefCell<Option<Recorder>> = RefCell::new(None)
+}
+
+fn with_recorder<R>(op: impl FnOnce(&mut Recorder) -> R) -> Option<R> {
+    RECORDER.with(|recorder| recorder.borrow_mut().as_mut().map(op))
+}
+
+/// The statistics of the innermost judgment call in progress.
+fn with_current<R>(op: impl FnOnce(&mut JudgmentStats) -> R) -> Option<R> {
+    with_recorder(|recorder| {
+        let judgment = *recorder.calls.last()?;
+        Some(op(recorder.stats.judgments.entry(judgment).or_default()))
+    })
+    .flatten()
+}
+
+/// Runs `op` while recording statistics.
+pub fn record<R>(op: impl FnOnce() -> R) -> (R, Stats) {
+    let previous = RECORDER.with(|recorder| {
+        recorder.replace(Some(Recorder {
+            stats: Stats::default(),
+            calls: vec![],
+        }))
+    });
+    let result = op();
+    let recorder = RECORDER
+        .with(|recorder| recorder.replace(previous))
+        .unwrap();
+    (result, recorder.stats)
+}
+
+/// A call of a judgment in progress, see [`enter_judgment`].
+#[doc(hidden)]
+pub struct Call {
+    /// When the call started, unless it is within a call of the same judgment.
+    start: Option<Instant>,
+}
+
+/// Records a call of `judgment`, which lasts until the returned value is dropped.
+#[doc(hidden)]
+pub fn enter_judgment(judgment: &'static str) -> Option<Call> {
+    with_recorder(|recorder| {
+        let outermost = !recorder.calls.contains(&judgment);
+        recorder.calls.push(judgment);
+        recorder.stats.max_depth = recorder.stats.max_depth.max(recorder.calls.len());
+        recorder.stats.judgments.entry(judgment).or_default().calls += 1;
+        Call {
+            start: outermost.then(Instant::now),
+        }
+    })
+}
+
+impl Drop for Call {
+    fn drop(&mut self) {
+        let elapsed = self.start.map(|start| start.elapsed());
+        with_recorder(|recorder| {
+            let judgment = recorder.calls.pop().unwrap();
+            if let Some(elapsed) = elapsed {
+                recorder.stats.judgments.get_mut(judgment).unwrap().time += elapsed;
+            }
+        });
+    }
+}
+
+/// Records that the current call was answered from the memo table.
+pub(crate) fn memo_hit() {
+    with_current(|stats| stats.memo_hits += 1);
+}
+
+/// Records that the current call was answered from a call in progress.
+pub(crate) fn cycle_hit() {
+    with_current(|stats| stats.cycle_hits += 1);
+}
+
+/// Records an iteration of the fixed-point computation of the current call.
+pub(crate) fn iteration() {
+    with_current(|stats| stats.iterations += 1);
+}
+
+/// Records that the rule `rule` of the current call produced an output.
+#[doc(hidden)]
+pub fn fired(rule: &'static str) {
+    with_current(|stats| *stats.rule_firings.entry(rule).or_default() += 1);
+}
@@ -0,0 +1,70 @@
+#![cfg(test)]
+
+use std::cell::RefCell;
+
+use super::cancel::{with_cancellation, CancellationToken, Cancelled};
+use crate::judgment_fn;
+
+thread_local! {
+    /// Cancelled when `count_down` reaches zero, if set.
+    static CANCEL_AT_ZERO: RefCell<Option<CancellationToken>> = RefCell::new(None)
+}
+
+/// Always holds, as a premise.
+fn cancel_at_zero() -> bool {
+    CANCEL_AT_ZERO.with(|token| {
+        if let Some(token) = &*token.borrow() {
+            token.cancel();
+        }
+    });
+    true
+}
+
+judgment_fn! {
+    /// Yields `n`, the long way: each step calls the judgment again on its own output.
+    fn count_down(n: u32) => u32 {
+        debug(n)
+
+        (
+            (if n == 0)
+            (if cancel_at_zero())
+            --------------------------------------- ("zero")
+            (count_down(n) => 0)
+        )
+
+        (
+            (if n > 0)
+            (count_down(n - 1) => m)
+            (count_down(m) => k)
+            --------------------------------------- ("step")
+            (count_down(n) => k + 1)
+        )
+    }
+}
+
+#[test]
+fn not_cancelled() {
+    let token = CancellationToken::new();
+    let result = with_cancellation(&token, || count_down(3));
+    assert_eq!(result, Ok(crate::set![3]));
+}
+
+/// Once cancelled, judgments yield no outputs; those are not memoized, so that the
+/// judgments still have their outputs when called again.
+#[test]
+fn cancelled_midway() {
+    let token = CancellationToken::new();
+    CANCEL_AT_ZERO.with(|t| *t.borrow_mut() = Some(token.clone()));
+    let result = with_cancellation(&token, || count_down(3));
+    CANCEL_AT_ZERO.with(|t| *t.borrow_mut() = None);
+    assert_eq!(result, Err(Cancelled));
+
+    assert_eq!(count_down(3), crate::set![3]);
+}
+
+#[test]
+fn time_limit() {
+    let token = CancellationToken::with_time_limit(std::time::Duration::ZERO);
+    assert!(token.is_cancelled());
+    assert_eq!(with_cancellation(&token, || count_down(3)), Err(Cancelled));
+}
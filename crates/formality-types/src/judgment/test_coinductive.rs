@@ -0,0 +1,110 @@
+#![cfg(test)]
+
+use std::sync::Arc;
+
+use formality_macros::term;
+
+use crate::judgment_fn;
+
+#[term($edges)]
+struct Graph {
+    edges: Vec<(u32, u32)>,
+}
+
+impl Graph {
+    fn successors(&self, n: u32) -> Vec<u32> {
+        self.edges
+            .iter()
+            .flat_map(|(a, b)| if *a == n { Some(*b) } else { None })
+            .collect()
+    }
+}
+
+judgment_fn! {
+    /// There is an infinite path from `n`, i.e., a path to a cycle: the cycle justifies
+    /// itself.
+    fn diverges(
+        graph: Arc<Graph>,
+        n: u32,
+    ) => () {
+        debug(graph, n)
+        coinductive(())
+
+        (
+            (graph.successors(n) => m)
+            (diverges(&graph, m) => ())
+            --------------------------------------- ("step")
+            (diverges(graph, n) => ())
+        )
+    }
+}
+
+judgment_fn! {
+    /// The same rule as [`diverges`], but inductive: it never holds, as there are no
+    /// infinite derivations.
+    fn diverges_inductively(
+        graph: Arc<Graph>,
+        n: u32,
+    ) => () {
+        debug(graph, n)
+
+        (
+            (graph.successors(n) => m)
+            (diverges_inductively(&graph, m) => ())
+            --------------------------------------- ("step")
+            (diverges_inductively(graph, n) => ())
+        )
+    }
+}
+
+judgment_fn! {
+    /// The smallest node on the paths from `n` that end in `bound`, or `bound` if there
+    /// are none. A cycle is first assumed to yield `bound`, which is then refined.
+    fn smallest(
+        graph: Arc<Graph>,
+        n: u32,
+        bound: u32,
+    ) => u32 {
+        debug(graph, n, bound)
+        coinductive(*bound)
+
+        (
+            (if n == bound)
+            --------------------------------------- ("bound")
+            (smallest(_graph, n, bound) => n)
+        )
+
+        (
+            (if n != bound)
+            (graph.successors(n) => m)
+            (smallest(&graph, m, bound) => s)
+            --------------------------------------- ("step")
+            (smallest(graph, n, bound) => s.min(n))
+        )
+    }
+}
+
+#[test]
+fn greatest_fixed_point() {
+    let graph = Arc::new(Graph {
+        edges: vec![(0, 1), (1, 2), (2, 1), (3, 4)],
+    });
+    let diverging: Vec<u32> = (0..5)
+        .filter(|&n| !diverges(&graph, n).is_empty())
+        .collect();
+    assert_eq!(diverging, vec![0, 1, 2]);
+    assert!((0..5).all(|n| diverges_inductively(&graph, n).is_empty()));
+}
+
+#[test]
+fn refined_assumption() {
+    let graph = Arc::new(Graph {
+        edges: vec![(3, 1), (1, 3), (1, 5)],
+    });
+    expect_test::expect![[r#"
+        {
+            1,
+        }
+    "#]]
+    .assert_debug_eq(&smallest(&graph, 3, 5_u32));
+}
@@ -0,0 +1,23 @@
+#![cfg(test)]
+
+use super::{coverage::Coverage, rules::judgments};
+
+#[test]
+fn report() {
+    let judgments: Vec<_> = judgments()
+        .into_iter()
+        .filter(|j| j.module.ends_with("::test_reachable"))
+        .collect();
+    let coverage = Coverage::parse(
+        "fired\ttransitive_reachable\tbase\n\
+         fired\ttransitive_reachable\tbase\n\
+         fired\ttransitive_reachable\n",
+        &judgments,
+    );
+    expect_test::expect![[r#"
+        1 of 4 rules in 2 judgments fired
+        right_reachable: never fired ["base", "step"]
+        transitive_reachable: never fired ["transitive"]
+    "#]]
+    .assert_eq(&coverage.to_string());
+}
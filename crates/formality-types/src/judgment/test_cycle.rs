@@ -0,0 +1,42 @@
+#![cfg(test)]
+
+use crate::judgment_fn;
+
+judgment_fn! {
+    /// Each iteration of the cycle through `up` and `down` yields a larger number,
+    /// so it never reaches a fixed point.
+    fn up(n: u32) => u32 {
+        debug(n)
+
+        (
+            (down(n) => m)
+            --------------------------------------- ("up")
+            (up(n) => m + 1)
+        )
+    }
+}
+
+judgment_fn! {
+    fn down(n: u32) => u32 {
+        debug(n)
+
+        (
+            --------------------------------------- ("zero")
+            (down(_n) => 0)
+        )
+
+        (
+            (up(n) => m)
+            --------------------------------------- ("down")
+            (down(n) => m)
+        )
+    }
+}
+
+#[test]
+#[should_panic(
+    expected = "no fixed point reached after 100 iterations of the cycle:\n  up { n: 0 }\n  down { n: 0 }\n  up { n: 0 }"
+)]
+fn cycle_without_fixed_point() {
+    up(0_u32);
+}
@@ -0,0 +1,122 @@
+#![cfg(test)]
+
+use std::sync::Arc;
+
+use formality_macros::term;
+
+use crate::{collections::Set, judgment_fn};
+
+#[term($edges)]
+struct Graph {
+    edges: Vec<(u32, u32)>,
+}
+
+impl Graph {
+    fn nodes(&self) -> Set<u32> {
+        self.edges.iter().flat_map(|&(a, b)| [a, b]).collect()
+    }
+
+    fn successors(&self, n: u32) -> Vec<u32> {
+        self.edges
+            .iter()
+            .flat_map(|(a, b)| if *a == n { Some(*b) } else { None })
+            .collect()
+    }
+}
+
+judgment_fn! {
+    fn path(
+        graph: Arc<Graph>,
+        from: u32,
+        to: u32,
+    ) => () {
+        debug(graph, from, to)
+
+        (
+            (if graph.edges.contains(&(from, to)))
+            --------------------------------------- ("edge")
+            (path(graph, from, to) => ())
+        )
+
+        (
+            (graph.successors(from) => mid)
+            (path(&graph, mid, to) => ())
+            --------------------------------------- ("step")
+            (path(graph, from, to) => ())
+        )
+    }
+}
+
+judgment_fn! {
+    /// The nodes that there is no path to from `from`. The negated premise calls
+    /// `path`, which never calls back into `unreachable`: this is stratified.
+    fn unreachable(
+        graph: Arc<Graph>,
+        from: u32,
+    ) => u32 {
+        debug(graph, from)
+
+        (
+            (graph.nodes() => to)
+            (! (path(&graph, from, to) => _))
+            --------------------------------------- ("unreachable")
+            (unreachable(graph, from) => to)
+        )
+    }
+}
+
+judgment_fn! {
+    /// In a game where the players take turns to move along an edge, and the player
+    /// that cannot move loses, the player to move from `n` wins. This is only
+    /// stratified if the moves from `n` cannot lead back to `n`.
+    fn wins(
+        graph: Arc<Graph>,
+        n: u32,
+    ) => () {
+        debug(graph, n)
+
+        (
+            (graph.successors(n) => m)
+            (! (wins(&graph, m) => _))
+            --------------------------------------- ("move")
+            (wins(graph, n) => ())
+        )
+    }
+}
+
+#[test]
+fn stratified() {
+    let graph = Arc::new(Graph {
+        edges: vec![(0, 1), (1, 2), (2, 0), (2, 3), (4, 0)],
+    });
+    expect_test::expect![[r#"
+        {
+            4,
+        }
+    "#]]
+    .assert_debug_eq(&unreachable(&graph, 0));
+}
+
+#[test]
+fn locally_stratified() {
+    let graph = Arc::new(Graph {
+        edges: vec![(0, 1), (1, 2), (3, 2)],
+    });
+    let winning: Vec<u32> = graph
+        .nodes()
+        .into_iter()
+        .filter(|&n| !wins(&graph, n).is_empty())
+        .collect();
+    assert_eq!(winning, vec![1, 3]);
+}
+
+#[test]
+#[should_panic(
+    expected = "unstratified negation: `wins(&graph, m)` depends on the cycle:\n  wins { graph: [(0, 1), (1, 0)], n: 0 }\n  wins { graph: [(0, 1), (1, 0)], n: 1 }\n  wins { graph: [(0, 1), (1, 0)], n: 0 }"
+)]
+fn unstratified() {
+    let graph = Arc::new(Graph {
+        edges: vec![(0, 1), (1, 0)],
+    });
+    wins(&graph, 0);
+}
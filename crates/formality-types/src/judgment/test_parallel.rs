@@ -0,0 +1,145 @@
+#![cfg(test)]
+
+use std::{
+    cell::{Cell, RefCell},
+    sync::Arc,
+};
+
+use formality_macros::term;
+
+use crate::{collections::Set, fixed_point::Spawn, judgment::parallel, judgment_fn};
+
+#[term($edges)]
+struct Graph {
+    edges: Vec<(u32, u32)>,
+}
+
+impl Graph {
+    fn successors(&self, n: u32) -> Vec<u32> {
+        self.edges
+            .iter()
+            .flat_map(|(a, b)| if *a == n { Some(*b) } else { None })
+            .collect()
+    }
+}
+
+judgment_fn! {
+    /// The nodes reachable from `from`, going through the successors of each node in
+    /// parallel.
+    fn parallel_reachable(
+        graph: Arc<Graph>,
+        from: u32,
+    ) => u32 {
+        debug(from, graph)
+
+        (
+            (graph.successors(start) => s)
+            --------------------------------------- ("base")
+            (parallel_reachable(graph, start) => s)
+        )
+
+        (
+            (let successors = graph.successors(a))
+            (let reachable: Vec<Set<u32>> = parallel::map(true, &successors, |b| parallel_reachable(&graph, *b)))
+            (reachable.into_iter().flatten() => c)
+            --------------------------------------- ("step")
+            (parallel_reachable(graph, a) => c)
+        )
+    }
+}
+
+/// The nodes reachable from `from`, computed directly.
+fn reachable(graph: &Graph, from: u32) -> Set<u32> {
+    let mut reachable = Set::new();
+    let mut queue = graph.successors(from);
+    while let Some(n) = queue.pop() {
+        if reachable.insert(n) {
+            queue.extend(graph.successors(n));
+        }
+    }
+    reachable
+}
+
+/// Calls evaluated on other threads, or on this one while it waits for them, do not
+/// see the cycles in progress of unrelated calls, and reach the same fixed points.
+#[test]
+fn cycles_across_threads() {
+    let graph = Arc::new(Graph {
+        edges: (0..12)
+            .flat_map(|n| [(n, (n * 5 + 1) % 12), (n, (n + 7) % 12)])
+            .filter(|&(a, _)| a != 11)
+            .collect(),
+    });
+    let nodes: Vec<u32> = (0..12).collect();
+    let results = parallel::map(true, &nodes, |n| parallel_reachable(&graph, *n));
+    for (n, result) in nodes.iter().zip(results) {
+        assert_eq!(result, reachable(&graph, *n), "from {n}");
+    }
+}
+
+thread_local! {
+    /// A spawn point of another thread, at which the next job is run, see `probe`.
+    static UNRELATED: RefCell<Option<Spawn>> = RefCell::new(None);
+
+    /// Whether a job run at `UNRELATED` is in progress.
+    static IN_JOB: Cell<bool> = Cell::new(false);
+
+    /// Whether `probe(0)` was evaluated in that job.
+    static EVALUATED_IN_JOB: Cell<bool> = Cell::new(false);
+}
+
+/// Runs `op` as a job spawned on another thread, the first time only.
+fn run_unrelated(op: impl FnOnce() -> Set<u32>) -> Set<u32> {
+    match UNRELATED.with(|u| u.borrow_mut().take()) {
+        Some(spawn) => {
+            IN_JOB.with(|j| j.set(true));
+            let (result, _) = spawn.run(op);
+            IN_JOB.with(|j| j.set(false));
+            result
+        }
+        None => op(),
+    }
+}
+
+judgment_fn! {
+    /// `probe(0)` starts a job that cycles back into it.
+    fn probe(n: u32) => u32 {
+        debug(n)
+
+        (
+            (if n == 0)
+            (let () = EVALUATED_IN_JOB.with(|e| e.set(e.get() || IN_JOB.with(Cell::get))))
+            (run_unrelated(|| probe(1)) => m)
+            --------------------------------------- ("zero")
+            (probe(n) => m)
+        )
+
+        (
+            (if n == 1)
+            (probe(0) => m)
+            --------------------------------------- ("one, cycle")
+            (probe(n) => m)
+        )
+
+        (
+            (if n == 1)
+            --------------------------------------- ("one")
+            (probe(n) => 1)
+        )
+    }
+}
+
+/// A job spawned on another thread does not observe the computations in progress on
+/// the thread it runs on, here `probe(0)`, which it thus evaluates anew.
+#[test]
+fn job_isolated_from_unrelated_computations() {
+    let spawn = std::thread::spawn(Spawn::here).join().unwrap();
+    UNRELATED.with(|u| *u.borrow_mut() = Some(spawn));
+    expect_test::expect![[r#"
+        {
+            1,
+        }
+    "#]]
+    .assert_debug_eq(&probe(0_u32));
+    assert!(EVALUATED_IN_JOB.with(Cell::get));
+}
@@ -0,0 +1,108 @@
+#![cfg(test)]
+
+use crate::{
+    judgment::proof_tree::{self, FailureTrace},
+    judgment_fn,
+};
+
+judgment_fn! {
+    /// The halves of the even numbers in `ns`, and the numbers after the odd ones.
+    fn halves(
+        ns: Vec<u32>,
+    ) => u32 {
+        debug(ns)
+
+        (
+            (ns.iter().copied() => n)
+            (if let 0 = n % 2)
+            (let half: u32 = n / 2)
+            --------------------------------------- ("even")
+            (halves(ns) => half)
+        )
+
+        (
+            (ns.iter().copied() => n)
+            (if let 1 = n % 2)
+            (let next = n + 1)
+            --------------------------------------- ("odd")
+            (halves(ns) => next)
+        )
+    }
+}
+
+judgment_fn! {
+    /// The bindings of the premises do not interfere with the names that the macro
+    /// uses internally, such as `values`, `result` and `output`.
+    fn hygiene(
+        ns: Vec<u32>,
+    ) => u32 {
+        debug(ns)
+
+        (
+            (let values: Vec<u32> = ns.iter().map(|n| n * 10).collect())
+            (values => result)
+            (let output = result + 1)
+            (if let Some(ns) = output.checked_sub(1))
+            --------------------------------------- ("shadowing")
+            (hygiene(_ns) => ns)
+        )
+    }
+}
+
+judgment_fn! {
+    /// The numbers written in `s`, separated by commas.
+    fn numbers(
+        s: String,
+    ) => u32 {
+        debug(s)
+
+        (
+            (s.split(',') => n)
+            (ok n.trim().parse::<u32>() => n)
+            --------------------------------------- ("parse")
+            (numbers(s) => n)
+        )
+    }
+}
+
+#[test]
+fn let_and_if_let() {
+    expect_test::expect![[r#"
+        {
+            2,
+            3,
+        }
+    "#]]
+    .assert_debug_eq(&halves(vec![1_u32, 4, 6]));
+}
+
+#[test]
+fn hygienic() {
+    expect_test::expect![[r#"
+        {
+            10,
+            20,
+        }
+    "#]]
+    .assert_debug_eq(&hygiene(vec![1_u32, 2]));
+}
+
+/// The errors of fallible premises end up in the failure trace.
+#[test]
+fn ok() {
+    expect_test::expect![[r#"
+        {
+            1,
+            2,
+        }
+    "#]]
+    .assert_debug_eq(&numbers("1, 2"));
+
+    let (outputs, recording) = proof_tree::record(|| numbers("x, 99999999999"));
+    assert!(outputs.is_empty());
+    expect_test::expect![[r#"
+        failed goals:
+          numbers(s: "x, 99999999999"), rules tried: ["parse"], "parse" failed: invalid digit found in string, "parse" failed: number too large to fit in target type
+    "#]]
+        .assert_eq(&FailureTrace::new(&recording.failures, &["numbers"]).to_string());
+}
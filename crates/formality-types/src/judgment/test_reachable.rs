@@ -61,3 +61,102 @@ fn judgment() {
     "#]]
     .assert_debug_eq(&transitive_reachable(graph, 0));
 }
+
+judgment_fn! {
+    /// Like `transitive_reachable`, but only recursing after a step along an edge.
+    fn right_reachable(
+        graph: Arc<Graph>,
+        from: u32,
+    ) => u32 {
+        debug(from, graph)
+
+        (
+            (graph.successors(start) => s)
+            --------------------------------------- ("base")
+            (right_reachable(graph, start) => s)
+        )
+
+        (
+            (graph.successors(a) => b)
+            (right_reachable(&graph, b) => c)
+            --------------------------------------- ("step")
+            (right_reachable(graph, a) => c)
+        )
+    }
+}
+
+/// While computing the outputs for 1, those for 4 depend on the provisional
+/// outputs for 1 through the cycle, so they must not be memoized then.
+#[test]
+fn judgment_memoized_in_cycle() {
+    let graph = Arc::new(Graph {
+        edges: vec![(1, 4), (4, 1), (4, 0)],
+    });
+
+    expect_test::expect![[r#"
+        {
+            0,
+            1,
+            4,
+        }
+    "#]]
+    .assert_debug_eq(&right_reachable(&graph, 1));
+    expect_test::expect![[r#"
+        {
+            0,
+            1,
+            4,
+        }
+    "#]]
+    .assert_debug_eq(&right_reachable(&graph, 4));
+}
+
+/// The second call is answered from the memo table.
+#[test]
+fn stats() {
+    let graph = Arc::new(Graph {
+        edges: vec![(0, 1), (1, 2), (2, 0)],
+    });
+
+    let (_, mut stats) = super::stats::record(|| {
+        transitive_reachable(&graph, 0);
+        transitive_reachable(&graph, 0)
+    });
+    for judgment in stats.judgments.values_mut() {
+        judgment.time = Default::default();
+    }
+    expect_test::expect![[r#"
+        Stats {
+            judgments: {
+                "transitive_reachable": JudgmentStats {
+                    calls: 71,
+                    memo_hits: 1,
+                    cycle_hits: 61,
+                    iterations: 29,
+                    rule_firings: {
+                        "base": 29,
+                        "transitive": 100,
+                    },
+                    time: 0ns,
+                },
+            },
+            max_depth: 4,
+        }
+    "#]].assert_debug_eq(&stats);
+}
+
+/// After the memo tables are cleared, the second call is computed afresh.
+#[test]
+fn memo_tables_cleared() {
+    let graph = Arc::new(Graph {
+        edges: vec![(0, 1), (1, 2), (2, 0)],
+    });
+
+    let (_, stats) = super::stats::record(|| {
+        transitive_reachable(&graph, 0);
+        crate::fixed_point::clear_memo_tables();
+        transitive_reachable(&graph, 0)
+    });
+    let stats = &stats.judgments["transitive_reachable"];
+    assert_eq!((stats.calls, stats.memo_hits), (140, 0));
+}
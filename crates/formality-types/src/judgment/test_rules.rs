@@ -0,0 +1,128 @@
+#![cfg(test)]
+
+use super::rules::{judgments, to_latex, to_markdown, JudgmentDecl};
+
+#[test]
+fn registered() {
+    let judgment = judgments()
+        .into_iter()
+        .find(|j| j.module.ends_with("::test_reachable") && j.name == "transitive_reachable")
+        .unwrap();
+    expect_test::expect![[r#"
+        JudgmentDecl {
+            name: "transitive_reachable",
+            module: "formality_types::judgment::test_reachable",
+            file: "crates/formality-types/src/judgment/test_reachable.rs",
+            line: 26,
+            inputs: [
+                (
+                    "graph",
+                    "Arc<Graph>",
+                ),
+                (
+                    "from",
+                    "u32",
+                ),
+            ],
+            output: "u32",
+            rules: [
+                RuleDecl {
+                    name: "base",
+                    premises: [
+                        "graph.successors(start) => s",
+                    ],
+                    conclusion: "transitive_reachable(graph, start) => s",
+                },
+                RuleDecl {
+                    name: "transitive",
+                    premises: [
+                        "transitive_reachable(& graph, a) => b",
+                        "transitive_reachable(& graph, b) => c",
+                    ],
+                    conclusion: "transitive_reachable(graph, a) => c",
+                },
+            ],
+        }
+    "#]]
+    .assert_debug_eq(&judgment);
+}
+
+#[test]
+fn ordered() {
+    let names: Vec<_> = judgments()
+        .into_iter()
+        .filter(|j| j.module.ends_with("::test_filtered") || j.module.ends_with("::test_cycle"))
+        .map(|j| format!("{}::{}", j.module, j.name))
+        .collect();
+    expect_test::expect![[r#"
+        [
+            "formality_types::judgment::test_cycle::down",
+            "formality_types::judgment::test_cycle::up",
+            "formality_types::judgment::test_filtered::transitive_reachable",
+        ]
+    "#]]
+    .assert_debug_eq(&names);
+}
+
+fn test_reachable() -> Vec<&'static JudgmentDecl> {
+    judgments()
+        .into_iter()
+        .filter(|j| j.module.ends_with("::test_reachable"))
+        .collect()
+}
+
+#[test]
+fn latex() {
+    expect_test::expect![[r#"
+        \subsection*{$\mathtt{right\_reachable}$}
+        % formality_types::judgment::test_reachable (crates/formality-types/src/judgment/test_reachable.rs:65)
+        $\mathtt{right\_reachable(graph:\ Arc<Graph>,\ from:\ u32)\ =>\ u32}$
+        \[
+        \dfrac{\begin{array}{c}\mathtt{graph.successors(start)\ =>\ s}\end{array}}{\mathtt{right\_reachable(graph,\ start)\ =>\ s}}\;(\text{base})
+        \]
+        \[
+        \dfrac{\begin{array}{c}\mathtt{graph.successors(a)\ =>\ b} \\ \mathtt{right\_reachable(\&graph,\ b)\ =>\ c}\end{array}}{\mathtt{right\_reachable(graph,\ a)\ =>\ c}}\;(\text{step})
+        \]
+
+        \subsection*{$\mathtt{transitive\_reachable}$}
+        % formality_types::judgment::test_reachable (crates/formality-types/src/judgment/test_reachable.rs:26)
+        $\mathtt{transitive\_reachable(graph:\ Arc<Graph>,\ from:\ u32)\ =>\ u32}$
+        \[
+        \dfrac{\begin{array}{c}\mathtt{graph.successors(start)\ =>\ s}\end{array}}{\mathtt{transitive\_reachable(graph,\ start)\ =>\ s}}\;(\text{base})
+        \]
+        \[
+        \dfrac{\begin{array}{c}\mathtt{transitive\_reachable(\&graph,\ a)\ =>\ b} \\ \mathtt{transitive\_reachable(\&graph,\ b)\ =>\ c}\end{array}}{\mathtt{transitive\_reachable(graph,\ a)\ =>\ c}}\;(\text{transitive})
+        \]
+
+    "#]].assert_eq(&to_latex(&test_reachable()));
+}
+
+#[test]
+fn markdown() {
+    expect_test::expect![[r#"
+        ## `right_reachable`
+
+        `right_reachable(graph: Arc<Graph>, from: u32) => u32`, declared in `formality_types::judgment::test_reachable` (`crates/formality-types/src/judgment/test_reachable.rs:65`).
+
+        $$
+        \dfrac{\begin{array}{c}\mathtt{graph.successors(start)\ =>\ s}\end{array}}{\mathtt{right\_reachable(graph,\ start)\ =>\ s}}\;(\text{base})
+        $$
+
+        $$
+        \dfrac{\begin{array}{c}\mathtt{graph.successors(a)\ =>\ b} \\ \mathtt{right\_reachable(\&graph,\ b)\ =>\ c}\end{array}}{\mathtt{right\_reachable(graph,\ a)\ =>\ c}}\;(\text{step})
+        $$
+
+        ## `transitive_reachable`
+
+        `transitive_reachable(graph: Arc<Graph>, from: u32) => u32`, declared in `formality_types::judgment::test_reachable` (`crates/formality-types/src/judgment/test_reachable.rs:26`).
+
+        $$
+        \dfrac{\begin{array}{c}\mathtt{graph.successors(start)\ =>\ s}\end{array}}{\mathtt{transitive\_reachable(graph,\ start)\ =>\ s}}\;(\text{base})
+        $$
+
+        $$
+        \dfrac{\begin{array}{c}\mathtt{transitive\_reachable(\&graph,\ a)\ =>\ b} \\ \mathtt{transitive\_reachable(\&graph,\ b)\ =>\ c}\end{array}}{\mathtt{transitive\_reachable(graph,\ a)\ =>\ c}}\;(\text{transitive})
+        $$
+
+    "#]].assert_eq(&to_markdown(&test_reachable()));
+}
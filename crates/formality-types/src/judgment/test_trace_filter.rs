@@ -0,0 +1,80 @@
+#![cfg(test)]
+
+use std::sync::{Arc, Mutex};
+
+use formality_core::TraceFilter;
+
+use crate::judgment_fn;
+
+judgment_fn!(
+    fn round_up_half(n: u32) => u32 {
+        debug(n)
+
+        (
+            (if n % 2 == 0)
+            --------------------------------------- ("even")
+            (round_up_half(n) => n / 2)
+        )
+
+        (
+            (if n % 2 == 1)
+            (round_up_half(n + 1) => m)
+            --------------------------------------- ("odd")
+            (round_up_half(n) => m)
+        )
+    }
+);
+
+/// The tracing output of `round_up_half(3)` with `filter`.
+fn trace(filter: TraceFilter) -> String {
+    let buffer = Buffer::default();
+    let writer = buffer.clone();
+    let subscriber = filter.subscriber("debug", move || writer.clone());
+    tracing::subscriber::with_default(subscriber, || round_up_half(3));
+    let output = buffer.0.lock().unwrap();
+    String::from_utf8(output.clone()).unwrap()
+}
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn unfiltered() {
+    let output = trace(TraceFilter::default());
+    assert!(output.contains("round_up_half n=3"));
+    assert!(output.contains("rule=\"odd\""));
+    assert!(output.contains("rule=\"even\""));
+}
+
+#[test]
+fn judgment() {
+    let output = trace("round_up_half".parse().unwrap());
+    assert!(output.contains("round_up_half n=3"));
+    assert!(output.contains("rule=\"odd\""));
+}
+
+#[test]
+fn rule() {
+    // The rule "even" only applies to the nested judgment `round_up_half(4)`.
+    let output = trace(TraceFilter::default().rule("round_up_half", "even"));
+    assert!(output.contains("rule=\"even\", result=2"));
+    assert!(!output.contains("odd"));
+    assert!(!output.contains("n=3"));
+}
+
+#[test]
+fn nothing_selected() {
+    let output = trace("other_judgment, round_up_half:none".parse().unwrap());
+    assert_eq!(output, "");
+}
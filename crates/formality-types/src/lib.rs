@@ -3,13 +3,19 @@
 pub mod collections;
 pub mod fixed_point;
 pub mod fold;
+pub mod generate;
 pub mod grammar;
 pub mod judgment;
 pub mod matcher;
 pub mod parse;
+pub mod pretty;
 pub mod term;
 pub mod visit;
 
 pub mod derive_links;
 
 pub mod cast;
+
+// Used by `judgment_fn!` to register the judgments, see `judgment::rules`.
+#[doc(hidden)]
+pub use inventory;
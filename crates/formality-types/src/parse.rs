@@ -1,4 +1,7 @@
-use std::{str::FromStr, sync::Arc};
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 use crate::{
     cast::{To, Upcast},
@@ -10,6 +13,8 @@ use crate::{
 use std::fmt::Debug;
 
 mod test;
+mod test_comments;
+mod test_precedence;
 
 /// Parses `text` as a term with no bindings in scope.
 #[track_caller]
@@ -29,6 +34,46 @@ where
     term_with(None::<(String, Parameter)>, text)
 }
 
+/// Like [`try_term`], but also returns the spans of the terms parsed from `text`.
+/// On failure, returns all syntax errors: at the recovery points (see
+/// [`Parse::RECOVERY_POINT`]), the parser skips what it cannot parse and goes on.
+#[track_caller]
+pub fn try_term_with_spans<T>(text: &str) -> Result<(T, Spans), Vec<SyntaxError>>
+where
+    T: Parse,
+{
+    let recorder = Arc::new(Mutex::new(Recorder {
+        spans: Spans {
+            len: text.len(),
+            spans: Set::new(),
+        },
+        errors: vec![],
+    }));
+    let scope = Scope {
+        bindings: vec![],
+        recorder: Some(recorder.clone()),
+    };
+    let result = T::parse(&scope, text);
+    let Recorder { spans, mut errors } = recorder.lock().unwrap().clone();
+    match result {
+        Ok((t, remainder)) => {
+            let remainder = skip_whitespace(remainder);
+            if !remainder.is_empty() {
+                errors.push(SyntaxError::at(
+                    text.len(),
+                    remainder,
+                    extra_tokens_message(remainder),
+                ));
+            }
+            if errors.is_empty() {
+                return Ok((t, spans));
+            }
+        }
+        Err(e) => errors.push(SyntaxError::furthest(text.len(), &e)),
+    }
+    Err(errors)
+}
+
 /// Parses `text` as a term with the given bindings in scope.
 ///
 /// References to the given string will be replaced with the given parameter
@@ -40,7 +85,15 @@ where
     B: Upcast<(String, Parameter)>,
 {
     let scope = Scope::new(bindings.into_iter().map(|b| b.upcast()));
-    let (t, remainder) = match T::parse(&scope, text) {
+    parse_all(&scope, text)
+}
+
+/// Parses all of `text` as a term in `scope`.
+fn parse_all<T>(scope: &Scope, text: &str) -> anyhow::Result<T>
+where
+    T: Parse,
+{
+    let (t, remainder) = match T::parse(scope, text) {
         Ok(v) => v,
         Err(errors) => {
             let mut err = anyhow::anyhow!("failed to parse {text}");
@@ -50,8 +103,10 @@ where
             return Err(err);
         }
     };
-    if !skip_whitespace(remainder).is_empty() {
-        anyhow::bail!("extra tokens after parsing {text:?} to {t:?}: {remainder:?}");
+    let remainder = skip_whitespace(remainder);
+    if !remainder.is_empty() {
+        let message = extra_tokens_message(remainder);
+        anyhow::bail!("{message} after parsing {text:?} to {t:?}: {remainder:?}");
     }
     Ok(t)
 }
@@ -62,6 +117,25 @@ pub trait Parse: Sized + Debug {
     /// instance is present.
     fn parse<'t>(scope: &Scope, text: &'t str) -> ParseResult<'t, Self>;
 
+    /// Parse a single instance of this type that binds at least as tightly as the
+    /// precedence `level`, as the operands of the variants with a `#[precedence]` on a
+    /// `#[term]` must. Only the types with such variants have levels; by default, this
+    /// is [`Parse::parse`].
+    fn parse_with_precedence<'t>(
+        scope: &Scope,
+        text: &'t str,
+        level: usize,
+    ) -> ParseResult<'t, Self> {
+        let _ = level;
+        Self::parse(scope, text)
+    }
+
+    /// Whether the lists of this type are points at which the parser recovers from syntax
+    /// errors, when parsing with [`try_term_with_spans`]: an instance that fails to parse
+    /// is skipped, recording the error, so that the errors after it are found as well.
+    /// Set with `#[recovery_point]` on a `#[term]`.
+    const RECOVERY_POINT: bool = false;
+
     /// Parse many instances of self, expecting `close_char` to appear after the last instance
     /// (`close_char` is not consumed).
     fn parse_many<'t>(
@@ -71,9 +145,13 @@ pub trait Parse: Sized + Debug {
     ) -> ParseResult<'t, Vec<Self>> {
         let mut result = vec![];
         while !skip_whitespace(text).starts_with(close_char) {
-            let (e, t) = Self::parse(scope, text)?;
-            result.push(e);
-            text = t;
+            match Self::parse(scope, text) {
+                Ok((e, t)) => {
+                    result.push(e);
+                    text = t;
+                }
+                Err(errors) => text = scope.recover::<Self>(text, errors, skip_item)?,
+            }
         }
         Ok((result, text))
     }
@@ -86,9 +164,13 @@ pub trait Parse: Sized + Debug {
     ) -> ParseResult<'t, Vec<Self>> {
         let mut result = vec![];
         while !skip_whitespace(text).starts_with(close_char) {
-            let (e, t) = Self::parse(scope, text)?;
-            result.push(e);
-            text = t;
+            match Self::parse(scope, text) {
+                Ok((e, t)) => {
+                    result.push(e);
+                    text = t;
+                }
+                Err(errors) => text = scope.recover::<Self>(text, errors, skip_element)?,
+            }
 
             if let Ok(((), t)) = expect_char(',', text) {
                 text = t;
@@ -142,10 +224,101 @@ impl<'t> ParseError<'t> {
 
 pub type ParseResult<'t, T> = Result<(T, &'t str), Set<ParseError<'t>>>;
 
+/// A range of bytes in the text being parsed, excluding leading whitespace and comments.
+#[derive(Copy, Clone, Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// The line and column, both starting at 1, at which this span starts in `text`.
+    pub fn line_column(&self, text: &str) -> (usize, usize) {
+        let before = &text[..self.start];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+        (line, column)
+    }
+}
+
+/// What [`try_term_with_spans`] records while parsing.
+#[derive(Clone, Debug)]
+struct Recorder {
+    spans: Spans,
+
+    /// The errors the parser recovered from.
+    errors: Vec<SyntaxError>,
+}
+
+/// A syntax error found by [`try_term_with_spans`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// Where the error occurred, as an empty span.
+    pub span: Span,
+
+    pub message: String,
+}
+
+impl SyntaxError {
+    /// An error at `remainder`, a suffix of the parsed text of length `len`.
+    fn at(len: usize, remainder: &str, message: String) -> Self {
+        let offset = len - remainder.len();
+        SyntaxError {
+            span: Span {
+                start: offset,
+                end: offset,
+            },
+            message,
+        }
+    }
+
+    /// The error at the furthest point of `errors`, the one that the parse that got
+    /// furthest ran into. `len` is the length of the parsed text.
+    fn furthest(len: usize, errors: &Set<ParseError<'_>>) -> Self {
+        let remainder = errors
+            .iter()
+            .map(|e| e.text)
+            .min_by_key(|t| t.len())
+            .unwrap();
+        let messages: Vec<&str> = errors
+            .iter()
+            .filter(|e| e.text.len() == remainder.len())
+            .map(|e| &e.message[..])
+            .collect();
+        SyntaxError::at(len, skip_whitespace(remainder), messages.join("; "))
+    }
+}
+
+/// The spans of the terms parsed by [`try_term_with_spans`], along with the name of
+/// their type (e.g., `"WhereClause"`). Recorded are the terms with a derived [`Parse`] impl.
+///
+/// As the parser explores alternatives, the spans may include terms of a failed
+/// alternative that was abandoned later on.
+#[derive(Clone, Debug, Default)]
+pub struct Spans {
+    /// Length of the parsed text.
+    len: usize,
+
+    spans: Set<(&'static str, Span)>,
+}
+
+impl Spans {
+    /// The spans of the terms whose type is named `type_name`, in order.
+    pub fn of<'s>(&'s self, type_name: &'s str) -> impl Iterator<Item = Span> + 's {
+        self.spans
+            .iter()
+            .filter(move |(n, _)| *n == type_name)
+            .map(|(_, span)| *span)
+    }
+}
+
 /// Tracks the variables in scope at this point in parsing.
 #[derive(Clone, Debug)]
 pub struct Scope {
     bindings: Vec<(String, Parameter)>,
+
+    /// Where to record the spans of the parsed terms and the errors recovered from, if anywhere.
+    recorder: Option<Arc<Mutex<Recorder>>>,
 }
 
 impl Scope {
@@ -153,9 +326,60 @@ impl Scope {
     pub fn new(bindings: impl IntoIterator<Item = (String, Parameter)>) -> Self {
         Self {
             bindings: bindings.into_iter().collect(),
+            recorder: None,
         }
     }
 
+    /// Records that a term of type `type_name` was parsed from `text`, leaving `remainder`.
+    pub fn record_span(&self, type_name: &'static str, text: &str, remainder: &str) {
+        if let Some(recorder) = &self.recorder {
+            let spans = &mut recorder.lock().unwrap().spans;
+            // A term that consumed nothing but whitespace gets an empty span.
+            let end = spans.len - remainder.len();
+            let start = (spans.len - skip_whitespace(text).len()).min(end);
+            let span = Span { start, end };
+            spans.spans.insert((type_name, span));
+        }
+    }
+
+    /// The number of errors recovered from so far, to pass to [`Self::discard_recovered`].
+    pub fn recovered(&self) -> usize {
+        match &self.recorder {
+            Some(recorder) => recorder.lock().unwrap().errors.len(),
+            None => 0,
+        }
+    }
+
+    /// Forgets the errors recovered from since [`Self::recovered`] returned `mark`: they
+    /// were found by an alternative that failed after all.
+    pub fn discard_recovered(&self, mark: usize) {
+        if let Some(recorder) = &self.recorder {
+            recorder.lock().unwrap().errors.truncate(mark);
+        }
+    }
+
+    /// Recovers from `errors`, found parsing a `T` from `text`, if `T` is a recovery point:
+    /// records the error and returns the text after the `T`, as found by `skip`.
+    fn recover<'t, T: Parse>(
+        &self,
+        text: &'t str,
+        errors: Set<ParseError<'t>>,
+        skip: fn(&str) -> &str,
+    ) -> Result<&'t str, Set<ParseError<'t>>> {
+        let Some(recorder) = self.recorder.as_ref().filter(|_| T::RECOVERY_POINT) else {
+            return Err(errors);
+        };
+        let rest = skip(text);
+        if rest.len() == text.len() {
+            // Nothing to skip, e.g., at the end of the input: there is no recovering.
+            return Err(errors);
+        }
+        let mut recorder = recorder.lock().unwrap();
+        let error = SyntaxError::furthest(recorder.spans.len, &errors);
+        recorder.errors.push(error);
+        Ok(rest)
+    }
+
     /// Look for a variable with the given name.
     pub fn lookup(&self, name: &str) -> Option<Parameter> {
         self.bindings
@@ -264,6 +488,15 @@ where
         let (data, text) = T::parse(scope, text)?;
         Ok((Arc::new(data), text))
     }
+
+    fn parse_with_precedence<'t>(
+        scope: &Scope,
+        text: &'t str,
+        level: usize,
+    ) -> ParseResult<'t, Self> {
+        let (data, text) = T::parse_with_precedence(scope, text, level)?;
+        Ok((Arc::new(data), text))
+    }
 }
 
 impl Parse for usize {
@@ -287,10 +520,27 @@ impl Parse for u64 {
     }
 }
 
+impl Parse for bool {
+    #[tracing::instrument(level = "trace", ret)]
+    fn parse<'t>(_scope: &Scope, text: &'t str) -> ParseResult<'t, Self> {
+        match identifier(text) {
+            Ok((ident, text1)) if ident == "true" => Ok((true, text1)),
+            Ok((ident, text1)) if ident == "false" => Ok((false, text1)),
+            _ => Err(ParseError::at(
+                text,
+                "expected `true` or `false`".to_string(),
+            )),
+        }
+    }
+}
+
 /// Extract the next character from input, returning an error if we've reached the input.
 ///
 /// Warning: does not skip whitespace.
 fn char(text: &str) -> ParseResult<'_, char> {
+    if text.starts_with("/*") {
+        return Err(ParseError::at(text, UNTERMINATED_COMMENT.to_string()));
+    }
     let ch = match text.chars().next() {
         Some(c) => c,
         None => return Err(ParseError::at(text, "unexpected end of input".to_string())),
@@ -307,7 +557,7 @@ where
     let (id, text1) = accumulate(text0, char::is_numeric, char::is_numeric, "number")?;
     match T::from_str(&id) {
         Ok(t) => Ok((t, text1)),
-        Err(_) => Err(ParseError::at(text0, format!("invalid number"))),
+        Err(_) => Err(ParseError::at(text0, "invalid number".to_string())),
     }
 }
 
@@ -326,7 +576,7 @@ pub fn expect_char(ch: char, text0: &str) -> ParseResult<'_, ()> {
 /// Consume a comma if one is present.
 #[tracing::instrument(level = "trace", ret)]
 pub fn skip_trailing_comma(text: &str) -> &str {
-    text.strip_prefix(',').unwrap_or(text)
+    skip_whitespace(text).strip_prefix(',').unwrap_or(text)
 }
 
 /// Extracts a maximal identifier from the start of text,
@@ -341,6 +591,21 @@ pub fn identifier(text: &str) -> ParseResult<'_, String> {
     )
 }
 
+/// Extracts a path of one or more identifiers separated by `::`, like `a::b::C`.
+#[tracing::instrument(level = "trace", ret)]
+pub fn path(text: &str) -> ParseResult<'_, String> {
+    let (mut path, mut text) = identifier(text)?;
+    while let Some(text1) = skip_whitespace(text).strip_prefix("::") {
+        let Ok((segment, text1)) = identifier(text1) else {
+            break;
+        };
+        path.push_str("::");
+        path.push_str(&segment);
+        text = text1;
+    }
+    Ok((path, text))
+}
+
 /// Consume next identifier, requiring that it be equal to `expected`.
 #[tracing::instrument(level = "trace", ret)]
 pub fn expect_keyword<'t>(expected: &str, text0: &'t str) -> ParseResult<'t, ()> {
@@ -405,6 +670,8 @@ where
         panic!("parsing ambiguity: {results:?}");
     } else if results.len() == 1 {
         Ok(results.pop().unwrap())
+    } else if errors.is_empty() && skip_whitespace(text).starts_with("/*") {
+        Err(ParseError::at(text, UNTERMINATED_COMMENT.to_string()))
     } else if errors.is_empty() {
         Err(ParseError::at(text, format!("{} expected", expected)))
     } else {
@@ -479,7 +746,48 @@ impl<A: Parse, B: Parse, C: Parse> Parse for (A, B, C) {
     }
 }
 
-/// Skips leading whitespace and comments.
+/// Skips an item that failed to parse: up to and including its first `{ ... }` block or
+/// the `;` that ends it, or else up to the `}` that closes the list of items.
+fn skip_item(text: &str) -> &str {
+    skip_balanced(text, |ch, depth| match (ch, depth) {
+        (';', 0) | ('}', 1) => Some(true),
+        (_, _) => None,
+    })
+}
+
+/// Skips an element of a comma-separated list that failed to parse: up to the next
+/// comma, or the delimiter that closes the list.
+fn skip_element(text: &str) -> &str {
+    skip_balanced(text, |ch, depth| match (ch, depth) {
+        (',', 0) => Some(false),
+        (_, _) => None,
+    })
+}
+
+/// Skips `text` up to a character for which `stop` returns `Some(inclusive)`, given
+/// the nesting depth of the brackets before it, or to a closing bracket at depth 0.
+fn skip_balanced(text: &str, stop: impl Fn(char, usize) -> Option<bool>) -> &str {
+    let mut depth = 0;
+    let mut rest = skip_whitespace(text);
+    while let Some(ch) = rest.chars().next() {
+        let after = &rest[ch.len_utf8()..];
+        if let Some(inclusive) = stop(ch, depth) {
+            return if inclusive { after } else { rest };
+        }
+        match ch {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth == 0 => return rest,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+        rest = skip_whitespace(after);
+    }
+    ""
+}
+
+/// Skips leading whitespace and comments: `//` line comments and `/* */` block
+/// comments, which nest as in Rust. An unterminated block comment is not skipped, so
+/// that the parser reports it where it starts, see [`UNTERMINATED_COMMENT`].
 pub fn skip_whitespace(mut text: &str) -> &str {
     loop {
         let len = text.len();
@@ -495,6 +803,8 @@ pub fn skip_whitespace(mut text: &str) -> &str {
                     text = "";
                 }
             }
+        } else if text.starts_with("/*") {
+            text = skip_block_comment(text);
         }
 
         if text.len() == len {
@@ -502,3 +812,39 @@ pub fn skip_whitespace(mut text: &str) -> &str {
         }
     }
 }
+
+/// Skips the block comment at the start of `text`, along with the comments nested in it.
+/// An unterminated comment is left as it is.
+fn skip_block_comment(text: &str) -> &str {
+    let mut depth = 0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        if let Some(r) = rest.strip_prefix("/*") {
+            depth += 1;
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("*/") {
+            depth -= 1;
+            rest = r;
+            if depth == 0 {
+                return rest;
+            }
+        } else {
+            let ch = rest.chars().next().unwrap();
+            rest = &rest[ch.len_utf8()..];
+        }
+    }
+    text
+}
+
+/// The error at a block comment that the input ends in, which is all that
+/// [`skip_whitespace`] leaves starting with `/*`.
+const UNTERMINATED_COMMENT: &str = "unterminated block comment";
+
+/// The error for the `remainder` of a term that could be parsed from its start.
+fn extra_tokens_message(remainder: &str) -> String {
+    if remainder.starts_with("/*") {
+        UNTERMINATED_COMMENT.to_string()
+    } else {
+        "extra tokens".to_string()
+    }
+}
@@ -0,0 +1,45 @@
+#![cfg(test)]
+
+use expect_test::expect;
+
+use crate::grammar::Ty;
+use crate::parse::{try_term, try_term_with_spans};
+
+#[test]
+fn nested_block_comments() {
+    let ty: Ty = try_term("Vec</* one /* two */ still one */ u32> /* trailing */").unwrap();
+    expect!["(rigid (adt Vec) (rigid (scalar u32)))"].assert_eq(&format!("{ty:?}"));
+}
+
+/// A block comment that the input ends in is an error where it starts, rather than
+/// the rest of the input.
+#[test]
+fn unterminated_block_comment() {
+    let errors = try_term_with_spans::<Ty>("Vec<u32 /* open /* nested */").unwrap_err();
+    expect![[r#"
+        [
+            SyntaxError {
+                span: Span {
+                    start: 8,
+                    end: 8,
+                },
+                message: "unterminated block comment",
+            },
+        ]
+    "#]]
+    .assert_debug_eq(&errors);
+
+    let errors = try_term_with_spans::<Ty>("Vec<u32> /* trailing").unwrap_err();
+    expect![[r#"
+        [
+            SyntaxError {
+                span: Span {
+                    start: 9,
+                    end: 9,
+                },
+                message: "unterminated block comment",
+            },
+        ]
+    "#]]
+    .assert_debug_eq(&errors);
+}
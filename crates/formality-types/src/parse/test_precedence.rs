@@ -0,0 +1,114 @@
+#![cfg(test)]
+
+use std::sync::Arc;
+
+use expect_test::expect;
+use formality_macros::term;
+
+use crate::parse::{term, try_term};
+
+/// Types with operators of all kinds, from the loosest to the tightest.
+#[term]
+enum TestTy {
+    #[grammar($v0 => $v1)]
+    #[precedence(0, right)]
+    Implies(Arc<TestTy>, Arc<TestTy>),
+
+    #[grammar($v0 -> $v1)]
+    #[precedence(1, right)]
+    Fn(Arc<TestTy>, Arc<TestTy>),
+
+    #[grammar($v0 + $v1)]
+    #[precedence(2)]
+    Sum(Arc<TestTy>, Arc<TestTy>),
+
+    #[grammar(& $v0)]
+    #[precedence(3)]
+    Ref(Arc<TestTy>),
+
+    #[grammar($v0 ?)]
+    #[precedence(4)]
+    Option(Arc<TestTy>),
+
+    #[grammar(u32)]
+    U32,
+
+    #[grammar(bool)]
+    Bool,
+
+    #[grammar(tuple($,v0))]
+    Tuple(Vec<TestTy>),
+}
+
+/// Each of `inputs` and the term it parses to, printed with as few parentheses as
+/// possible. Both `Display` and `Debug` print it so that it parses back to itself.
+fn reprint(inputs: &[&str]) -> String {
+    let mut out = String::new();
+    for input in inputs {
+        let ty: TestTy = term(input);
+        assert_eq!(ty, term(&ty.to_string()));
+        assert_eq!(ty, term(&format!("{ty:?}")));
+        out.push_str(&format!("{input:<32} ~~> {ty}\n"));
+    }
+    out
+}
+
+#[test]
+fn levels() {
+    let ty: TestTy = term("&u32 -> u32 + bool => bool?");
+    let parenthesized: TestTy = term("((&u32) -> (u32 + bool)) => (bool?)");
+    assert_eq!(ty, parenthesized);
+}
+
+#[test]
+fn associativity() {
+    let left: TestTy = term("u32 + bool + u32");
+    assert_eq!(left, term("(u32 + bool) + u32"));
+
+    let right: TestTy = term("u32 -> bool -> u32");
+    assert_eq!(right, term("u32 -> (bool -> u32)"));
+
+    let prefix_and_postfix: TestTy = term("& & u32 ? ?");
+    assert_eq!(prefix_and_postfix, term("&(&((u32?)?))"));
+}
+
+#[test]
+fn parentheses_only_where_needed() {
+    expect![[r#"
+        u32 -> bool -> u32               ~~> u32 -> bool -> u32
+        (u32 -> bool) -> u32             ~~> (u32 -> bool) -> u32
+        ((u32) -> (bool)) => ((u32))     ~~> u32 -> bool => u32
+        u32 => (bool => u32)             ~~> u32 => bool => u32
+        (u32 + bool) + u32               ~~> u32 + bool + u32
+        u32 + (bool + u32)               ~~> u32 + (bool + u32)
+        &(u32 -> u32)                    ~~> &(u32 -> u32)
+        (&u32) -> u32                    ~~> &u32 -> u32
+        (&u32)?                          ~~> (&u32) ?
+        &(u32?)                          ~~> &u32 ?
+        tuple((u32 -> u32), u32 => bool) ~~> tuple(u32 -> u32, u32 => bool)
+    "#]]
+    .assert_eq(&reprint(&[
+        "u32 -> bool -> u32",
+        "(u32 -> bool) -> u32",
+        "((u32) -> (bool)) => ((u32))",
+        "u32 => (bool => u32)",
+        "(u32 + bool) + u32",
+        "u32 + (bool + u32)",
+        "&(u32 -> u32)",
+        "(&u32) -> u32",
+        "(&u32)?",
+        "&(u32?)",
+        "tuple((u32 -> u32), u32 => bool)",
+    ]));
+}
+
+#[test]
+fn unbalanced() {
+    expect![[r#"
+        extra tokens after parsing "(u32 -> bool))" to u32 -> bool: ")"
+    "#]]
+    .assert_eq(&format!(
+        "{}\n",
+        try_term::<TestTy>("(u32 -> bool))").unwrap_err()
+    ));
+}
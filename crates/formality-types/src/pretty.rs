@@ -0,0 +1,372 @@
+//! Pretty-printing of terms in the syntax they are parsed from.
+//!
+//! Where `Debug` prints a term on one line and shows bound variables by their
+//! de Bruijn indices (`^ty0_0`), [`Pretty`] gives the variables of binders names,
+//! prefers the surface syntax of types (`&a Vec<T0>` rather than `(rigid & (shared) ...)`),
+//! and breaks terms that do not fit in [`WIDTH`] columns over several indented lines.
+//! The `#[term]` macro derives it from the grammar of the term along with `Display`.
+//!
+//! The layout follows Wadler's "prettier printer": a term is turned into a [`Doc`]
+//! whose [groups](`Doc::Group`) are printed on one line if they fit and with their
+//! [line breaks](`Doc::Line`) taken otherwise.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::collections::Set;
+use crate::grammar::{BoundVar, ParameterKind};
+
+/// The number of columns that terms are printed in.
+pub const WIDTH: usize = 80;
+
+/// The number of columns by which the contents of delimiters are indented.
+const INDENT: usize = 4;
+
+pub trait Pretty: Debug {
+    /// The document to print `self` as, with the variables bound in `cx` in scope.
+    ///
+    /// By default, the `Debug` output on one line.
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        let _ = cx;
+        Doc::text(format!("{self:?}"))
+    }
+}
+
+/// Prints `term` with the layout for [`WIDTH`] columns.
+pub fn to_string(term: &(impl Pretty + ?Sized)) -> String {
+    term.pretty(&PrettyCx::default()).render(WIDTH)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Doc {
+    Text(String),
+
+    /// A space, or a line break if the enclosing group does not fit.
+    Line,
+
+    /// Nothing, or a line break if the enclosing group does not fit.
+    Break,
+
+    /// No space between the texts on either side, e.g., before the `<` of the binder
+    /// in `impl<ty T0>`, which is not set off from the `impl` like the `<` of `for <T0 as Foo>::X`.
+    Glue,
+
+    /// Indents the line breaks within.
+    Nest(Vec<Doc>),
+
+    /// Printed flat if it fits on the rest of the line, or else with its line breaks taken.
+    Group(Vec<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: impl ToString) -> Doc {
+        Doc::Text(text.to_string())
+    }
+
+    /// `docs` between `open` and `close`, indented on lines of their own if they don't fit.
+    /// The contents of braces are set off by spaces, those of parentheses and brackets are not.
+    pub fn delimited(open: char, docs: Vec<Doc>, close: char) -> Doc {
+        if docs.iter().all(Doc::is_empty) {
+            return Doc::text(format!("{open}{close}"));
+        }
+        let line = if open == '{' { Doc::Line } else { Doc::Break };
+        let mut contents = vec![line.clone()];
+        contents.extend(docs);
+        Doc::Group(vec![
+            Doc::text(open),
+            Doc::Nest(contents),
+            line,
+            Doc::text(close),
+        ])
+    }
+
+    /// `docs` separated by `, `, grouped so that the list breaks independently of what
+    /// encloses it, e.g., `<ty T0, ty T1>` in a binder.
+    pub fn list(docs: impl IntoIterator<Item = Doc>) -> Doc {
+        Doc::Group(vec![Doc::Nest(Doc::comma_separated(docs))])
+    }
+
+    /// `docs` separated by `, `.
+    pub fn comma_separated(docs: impl IntoIterator<Item = Doc>) -> Vec<Doc> {
+        let mut result = vec![];
+        for doc in docs {
+            if !result.is_empty() {
+                result.push(Doc::text(","));
+                result.push(Doc::Line);
+            }
+            result.push(doc);
+        }
+        result
+    }
+
+    /// Prints the document, breaking lines of groups that don't fit in `width` columns.
+    pub fn render(&self, width: usize) -> String {
+        let mut renderer = Renderer {
+            out: String::new(),
+            column: 0,
+            width,
+        };
+        renderer.render(self, 0, false);
+        renderer.out
+    }
+
+    /// The width of the document when printed on one line.
+    fn flat_width(&self) -> usize {
+        match self {
+            Doc::Text(text) => text.chars().count(),
+            Doc::Line => 1,
+            Doc::Break | Doc::Glue => 0,
+            Doc::Nest(docs) | Doc::Group(docs) => docs.iter().map(Doc::flat_width).sum(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Doc::Text(text) => text.is_empty(),
+            Doc::Line | Doc::Break | Doc::Glue => false,
+            Doc::Nest(docs) | Doc::Group(docs) => docs.iter().all(Doc::is_empty),
+        }
+    }
+
+    /// The text that the document starts with, if it starts with text at all.
+    fn first_text(&self) -> Option<&str> {
+        match self {
+            Doc::Text(text) => Some(text).filter(|t| !t.is_empty()).map(|t| &t[..]),
+            Doc::Line | Doc::Break | Doc::Glue => None,
+            Doc::Nest(docs) | Doc::Group(docs) => docs
+                .iter()
+                .find(|d| !d.is_empty())
+                .and_then(Doc::first_text),
+        }
+    }
+
+    /// The text that the document ends with, if it ends with text at all.
+    fn last_text(&self) -> Option<&str> {
+        match self {
+            Doc::Text(text) => Some(text).filter(|t| !t.is_empty()).map(|t| &t[..]),
+            Doc::Line | Doc::Break | Doc::Glue => None,
+            Doc::Nest(docs) | Doc::Group(docs) => docs
+                .iter()
+                .rev()
+                .find(|d| !d.is_empty())
+                .and_then(Doc::last_text),
+        }
+    }
+}
+
+struct Renderer {
+    out: String,
+    column: usize,
+    width: usize,
+}
+
+impl Renderer {
+    fn render(&mut self, doc: &Doc, indent: usize, flat: bool) {
+        match doc {
+            Doc::Text(text) => self.text(text),
+            Doc::Line if flat => self.text(" "),
+            Doc::Break if flat => {}
+            Doc::Glue => {}
+            Doc::Line | Doc::Break => {
+                self.out.push('\n');
+                self.out.extend(std::iter::repeat(' ').take(indent));
+                self.column = indent;
+            }
+            Doc::Nest(docs) => {
+                for doc in docs {
+                    self.render(doc, indent + INDENT, flat);
+                }
+            }
+            Doc::Group(docs) => {
+                let flat = flat || self.column + doc.flat_width() <= self.width;
+                for doc in docs {
+                    self.render(doc, indent, flat);
+                }
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        self.out.push_str(text);
+        self.column += text.chars().count();
+    }
+}
+
+/// Concatenates documents, with spaces between tokens that would otherwise run together
+/// or look cramped, e.g., between two words but not before a `,` or after a `(`.
+#[derive(Default)]
+pub struct DocBuilder {
+    docs: Vec<Doc>,
+}
+
+impl DocBuilder {
+    pub fn push(&mut self, doc: Doc) {
+        if doc.is_empty() {
+            return;
+        }
+        let previous = self.docs.iter().rev().find(|d| !d.is_empty());
+        if let (Some(previous), Some(next)) = (previous.and_then(Doc::last_text), doc.first_text())
+        {
+            if needs_space(previous, next) {
+                self.docs.push(Doc::text(" "));
+            }
+        }
+        self.docs.push(doc);
+    }
+
+    pub fn text(&mut self, text: impl ToString) {
+        self.push(Doc::text(text));
+    }
+
+    /// The next doc follows the last one without a space, as `x` does `|` in `|x, y|`.
+    pub fn glue(&mut self) {
+        self.docs.push(Doc::Glue);
+    }
+
+    /// Pushes a space, or a line break if the group does not fit.
+    pub fn line(&mut self) {
+        self.docs.push(Doc::Line);
+    }
+
+    pub fn into_docs(self) -> Vec<Doc> {
+        self.docs
+    }
+
+    pub fn group(self) -> Doc {
+        Doc::Group(self.docs)
+    }
+}
+
+fn needs_space(previous: &str, next: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let (Some(p), Some(n)) = (previous.chars().last(), next.chars().next()) else {
+        return false;
+    };
+    if is_word(p) && is_word(n) {
+        return true;
+    }
+    if previous.ends_with("::") || next.starts_with("::") {
+        return false;
+    }
+    match (p, n) {
+        (_, ',' | ';' | ')' | ']' | '>' | '.' | ':') => false,
+        ('(' | '[' | '<' | '&' | '*' | '?' | '.' | '#', _) => false,
+        // Calls and parameter lists, as in `foo(x)` or `fn foo<ty T0>(x: T0)`,
+        // but not parenthesized operands, as in `&mut (x)` or `-> ()`.
+        (_, '(') => {
+            let keyword = ["as", "else", "if", "match", "mut", "move"]
+                .iter()
+                .any(|k| {
+                    previous.ends_with(k)
+                        && !previous[..previous.len() - k.len()].ends_with(is_word)
+                });
+            let arrow = previous.ends_with("->") || previous.ends_with("=>");
+            keyword || arrow || !(is_word(p) || p == '>')
+        }
+        _ => true,
+    }
+}
+
+/// The names of the variables bound by the binders that the printed term is within.
+#[derive(Clone, Debug, Default)]
+pub struct PrettyCx {
+    /// The names of the variables of each binder, the innermost one last.
+    binders: Vec<Vec<(ParameterKind, String)>>,
+}
+
+impl PrettyCx {
+    /// The context within a binder of variables of the given kinds, along with the names
+    /// given to those variables.
+    pub fn with_binder(&self, kinds: &[ParameterKind]) -> (PrettyCx, Vec<String>) {
+        let names = self.fresh_names(kinds);
+        let cx = self.with_names(kinds.iter().copied().zip(names.clone()).collect());
+        (cx, names)
+    }
+
+    /// Names for variables of the given kinds that differ from those in scope, e.g., `T0`.
+    pub fn fresh_names(&self, kinds: &[ParameterKind]) -> Vec<String> {
+        let mut names: Vec<(ParameterKind, String)> = vec![];
+        for &kind in kinds {
+            let prefix = match kind {
+                ParameterKind::Ty => "T",
+                ParameterKind::Lt => "a",
+                ParameterKind::Const => "C",
+            };
+            let index = self
+                .binders
+                .iter()
+                .flatten()
+                .chain(&names)
+                .filter(|(k, _)| *k == kind)
+                .count();
+            names.push((kind, format!("{prefix}{index}")));
+        }
+        names.into_iter().map(|(_, name)| name).collect()
+    }
+
+    /// The context within a binder whose variables have the given names.
+    pub fn with_names(&self, names: Vec<(ParameterKind, String)>) -> PrettyCx {
+        let mut cx = self.clone();
+        cx.binders.push(names);
+        cx
+    }
+
+    /// The name of a variable bound within the printed term.
+    pub fn bound_name(&self, var: &BoundVar) -> Option<&str> {
+        let debruijn = var.debruijn?;
+        let binder = self.binders.iter().rev().nth(debruijn.index)?;
+        let (kind, name) = binder.get(var.var_index.index)?;
+        (*kind == var.kind).then_some(&name[..])
+    }
+}
+
+impl<T: Pretty> Pretty for Vec<T> {
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        Doc::delimited(
+            '[',
+            Doc::comma_separated(self.iter().map(|e| e.pretty(cx))),
+            ']',
+        )
+    }
+}
+
+impl<T: Pretty> Pretty for Set<T> {
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        Doc::delimited(
+            '{',
+            Doc::comma_separated(self.iter().map(|e| e.pretty(cx))),
+            '}',
+        )
+    }
+}
+
+/// Optional parts of the grammar are printed if present.
+impl<T: Pretty> Pretty for Option<T> {
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        match self {
+            Some(v) => v.pretty(cx),
+            None => Doc::text(""),
+        }
+    }
+}
+
+impl<T: Pretty> Pretty for Arc<T> {
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        T::pretty(self, cx)
+    }
+}
+
+impl<A: Pretty, B: Pretty> Pretty for (A, B) {
+    fn pretty(&self, cx: &PrettyCx) -> Doc {
+        let (a, b) = self;
+        Doc::delimited('(', Doc::comma_separated([a.pretty(cx), b.pretty(cx)]), ')')
+    }
+}
+
+impl Pretty for () {}
+
+impl Pretty for usize {}
+
+impl Pretty for u32 {}
+
+impl Pretty for bool {}
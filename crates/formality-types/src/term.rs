@@ -1,15 +1,33 @@
 use std::{fmt::Debug, hash::Hash, sync::Arc};
 
+use serde::{de::DeserializeOwned, Serialize};
+
 use crate::{
     cast::{DowncastFrom, Upcast},
     collections::Set,
     fold::Fold,
-    grammar::{Binder, Lt, Ty},
+    generate::Generate,
+    grammar::{Abi, AssociatedTyName, Binder, Lt, Ty},
     parse::Parse,
+    pretty::Pretty,
 };
 
 pub trait Term:
-    Clone + Fold + Parse + Ord + Eq + Hash + Debug + Upcast<Self> + DowncastFrom<Self> + 'static + Sized
+    Clone
+    + Fold
+    + Parse
+    + Pretty
+    + Generate
+    + Serialize
+    + DeserializeOwned
+    + Ord
+    + Eq
+    + Hash
+    + Debug
+    + Upcast<Self>
+    + DowncastFrom<Self>
+    + 'static
+    + Sized
 {
 }
 
@@ -25,6 +43,10 @@ impl Term for Ty {}
 
 impl Term for Lt {}
 
+impl Term for AssociatedTyName {}
+
+impl Term for Abi {}
+
 impl Term for usize {}
 
 impl Term for u32 {}
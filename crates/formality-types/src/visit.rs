@@ -1,8 +1,11 @@
-use std::sync::Arc;
+use std::{
+    ops::{BitOr, BitOrAssign},
+    sync::Arc,
+};
 
 use crate::{
     collections::Set,
-    grammar::{Lt, Parameter, Ty, Variable},
+    grammar::{Lt, Parameter, Variable},
 };
 
 /// Invoked for each variable that we find when Visiting, ignoring variables bound by binders
@@ -17,6 +20,11 @@ pub trait Visit {
     /// The list may contain duplicates and must be in a determinstic order (though the order itself isn't important).
     fn free_variables(&self) -> Vec<Variable>;
 
+    /// The kinds of variables that appear in the term, bound or free. Types cache theirs
+    /// (see [`crate::grammar::Ty::new`]), so that this is cheap and folding can skip terms without any
+    /// variables (see [`crate::fold::Fold::substitute`]).
+    fn var_flags(&self) -> VarFlags;
+
     /// Measures the overall size of the term by counting constructors etc.
     /// Used to determine overflow.
     fn size(&self) -> usize;
@@ -41,11 +49,70 @@ pub trait Visit {
     }
 }
 
+/// A set of kinds of variables, see [`Visit::var_flags`].
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VarFlags(u8);
+
+impl VarFlags {
+    pub const NONE: VarFlags = VarFlags(0);
+
+    /// Universal variables, i.e., placeholders.
+    pub const UNIVERSAL: VarFlags = VarFlags(1 << 0);
+
+    /// Existential variables, i.e., inference variables.
+    pub const EXISTENTIAL: VarFlags = VarFlags(1 << 1);
+
+    /// Bound variables, whether bound within the term or not.
+    pub const BOUND: VarFlags = VarFlags(1 << 2);
+
+    /// The kind of `v`.
+    pub fn of(v: &Variable) -> VarFlags {
+        match v {
+            Variable::UniversalVar(_) => VarFlags::UNIVERSAL,
+            Variable::ExistentialVar(_) => VarFlags::EXISTENTIAL,
+            Variable::BoundVar(_) => VarFlags::BOUND,
+        }
+    }
+
+    pub fn is_empty(self) -> bool {
+        self == VarFlags::NONE
+    }
+
+    /// Whether all the kinds in `other` are in `self`.
+    pub fn contains(self, other: VarFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for VarFlags {
+    type Output = VarFlags;
+
+    fn bitor(self, other: VarFlags) -> VarFlags {
+        VarFlags(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for VarFlags {
+    fn bitor_assign(&mut self, other: VarFlags) {
+        self.0 |= other.0;
+    }
+}
+
+impl FromIterator<VarFlags> for VarFlags {
+    fn from_iter<I: IntoIterator<Item = VarFlags>>(iter: I) -> VarFlags {
+        iter.into_iter().fold(VarFlags::NONE, BitOr::bitor)
+    }
+}
+
 impl<T: Visit> Visit for Vec<T> {
     fn free_variables(&self) -> Vec<Variable> {
         self.iter().flat_map(|e| e.free_variables()).collect()
     }
 
+    fn var_flags(&self) -> VarFlags {
+        self.iter().map(|e| e.var_flags()).collect()
+    }
+
     fn size(&self) -> usize {
         self.iter().map(|e| e.size()).sum()
     }
@@ -60,6 +127,10 @@ impl<T: Visit + Ord> Visit for Set<T> {
         self.iter().flat_map(|e| e.free_variables()).collect()
     }
 
+    fn var_flags(&self) -> VarFlags {
+        self.iter().map(|e| e.var_flags()).collect()
+    }
+
     fn size(&self) -> usize {
         self.iter().map(|e| e.size()).sum()
     }
@@ -74,6 +145,10 @@ impl<T: Visit> Visit for Option<T> {
         self.iter().flat_map(|e| e.free_variables()).collect()
     }
 
+    fn var_flags(&self) -> VarFlags {
+        self.iter().map(|e| e.var_flags()).collect()
+    }
+
     fn size(&self) -> usize {
         self.as_ref().map(|e| e.size()).unwrap_or(0)
     }
@@ -88,6 +163,10 @@ impl<T: Visit + ?Sized> Visit for Arc<T> {
         T::free_variables(self)
     }
 
+    fn var_flags(&self) -> VarFlags {
+        T::var_flags(self)
+    }
+
     fn size(&self) -> usize {
         T::size(self)
     }
@@ -97,11 +176,15 @@ impl<T: Visit + ?Sized> Visit for Arc<T> {
     }
 }
 
-impl Visit for Ty {
+impl Visit for Lt {
     fn free_variables(&self) -> Vec<Variable> {
         self.data().free_variables()
     }
 
+    fn var_flags(&self) -> VarFlags {
+        self.data().var_flags()
+    }
+
     fn size(&self) -> usize {
         self.data().size()
     }
@@ -111,25 +194,47 @@ impl Visit for Ty {
     }
 }
 
-impl Visit for Lt {
+impl Visit for usize {
     fn free_variables(&self) -> Vec<Variable> {
-        self.data().free_variables()
+        vec![]
+    }
+
+    fn var_flags(&self) -> VarFlags {
+        VarFlags::NONE
     }
 
     fn size(&self) -> usize {
-        self.data().size()
+        1
     }
 
-    fn assert_valid(&self) {
-        self.data().assert_valid()
+    fn assert_valid(&self) {}
+}
+
+impl Visit for u32 {
+    fn free_variables(&self) -> Vec<Variable> {
+        vec![]
+    }
+
+    fn var_flags(&self) -> VarFlags {
+        VarFlags::NONE
+    }
+
+    fn size(&self) -> usize {
+        1
     }
+
+    fn assert_valid(&self) {}
 }
 
-impl Visit for usize {
+impl Visit for u64 {
     fn free_variables(&self) -> Vec<Variable> {
         vec![]
     }
 
+    fn var_flags(&self) -> VarFlags {
+        VarFlags::NONE
+    }
+
     fn size(&self) -> usize {
         1
     }
@@ -137,11 +242,15 @@ impl Visit for usize {
     fn assert_valid(&self) {}
 }
 
-impl Visit for u32 {
+impl Visit for bool {
     fn free_variables(&self) -> Vec<Variable> {
         vec![]
     }
 
+    fn var_flags(&self) -> VarFlags {
+        VarFlags::NONE
+    }
+
     fn size(&self) -> usize {
         1
     }
@@ -154,6 +263,10 @@ impl Visit for u128 {
         vec![]
     }
 
+    fn var_flags(&self) -> VarFlags {
+        VarFlags::NONE
+    }
+
     fn size(&self) -> usize {
         std::mem::size_of::<Self>()
     }
@@ -166,6 +279,10 @@ impl Visit for () {
         vec![]
     }
 
+    fn var_flags(&self) -> VarFlags {
+        VarFlags::NONE
+    }
+
     fn size(&self) -> usize {
         0
     }
@@ -182,6 +299,11 @@ impl<A: Visit, B: Visit> Visit for (A, B) {
         fv
     }
 
+    fn var_flags(&self) -> VarFlags {
+        let (a, b) = self;
+        a.var_flags() | b.var_flags()
+    }
+
     fn size(&self) -> usize {
         let (a, b) = self;
         a.size() + b.size()
@@ -204,6 +326,11 @@ impl<A: Visit, B: Visit, C: Visit> Visit for (A, B, C) {
         fv
     }
 
+    fn var_flags(&self) -> VarFlags {
+        let (a, b, c) = self;
+        a.var_flags() | b.var_flags() | c.var_flags()
+    }
+
     fn size(&self) -> usize {
         let (a, b, c) = self;
         a.size() + b.size() + c.size()
@@ -222,6 +349,10 @@ impl<A: Visit + ?Sized> Visit for &A {
         A::free_variables(self)
     }
 
+    fn var_flags(&self) -> VarFlags {
+        A::var_flags(self)
+    }
+
     fn size(&self) -> usize {
         A::size(self)
     }
@@ -236,6 +367,10 @@ impl<A: Visit> Visit for [A] {
         self.iter().flat_map(|e| A::free_variables(e)).collect()
     }
 
+    fn var_flags(&self) -> VarFlags {
+        self.iter().map(|e| A::var_flags(e)).collect()
+    }
+
     fn size(&self) -> usize {
         self.iter().map(|e| A::size(e)).sum()
     }
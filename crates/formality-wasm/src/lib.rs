@@ -0,0 +1,115 @@
+//! The interface of the model for JavaScript, e.g., for a playground in the browser.
+//! Build it with
+//!
+//! ```text
+//! cargo build --release --target wasm32-unknown-unknown -p formality-wasm
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/formality_wasm.wasm
+//! ```
+//!
+//! The functions take and return strings; the results are JSON, which is what the
+//! playground renders.
+
+use std::sync::Arc;
+
+use formality_check::{check_all_crates_report, Options};
+use formality_prove::test_util::{test_prove_with_proof_trees, TestAssertion};
+use formality_rust::{grammar::Program, source_map::SourceMap};
+use formality_types::{
+    fixed_point,
+    parse::{try_term, try_term_with_spans, Span},
+};
+use serde_json::json;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Checks the program `source`, returning its syntax errors or, if it parses, the report
+/// of the checks (see [`formality_check::Report::to_json`]) with the spans of the items
+/// and errors:
+///
+/// ```json
+/// { "syntax_errors": [{ "message": ..., "span": ... }], "report": { "items": ..., "errors": ... } }
+/// ```
+#[wasm_bindgen]
+pub fn check_program(source: &str) -> String {
+    // The module lives as long as the page, so don't keep the outputs of earlier calls.
+    fixed_point::clear_memo_tables();
+    let (program, spans): (Program, _) = match try_term_with_spans(source) {
+        Ok(v) => v,
+        Err(errors) => {
+            let syntax_errors: Vec<serde_json::Value> = errors
+                .iter()
+                .map(|e| json!({ "message": e.message, "span": span_json(source, Some(e.span)) }))
+                .collect();
+            return json!({ "syntax_errors": syntax_errors, "report": null }).to_string();
+        }
+    };
+    let report = check_all_crates_report(&program, &Options::default(), false);
+    let source_map = SourceMap::new(&program, &spans).configure(&report.features);
+    let mut report_json = report.to_json();
+    for (item, item_json) in report
+        .items
+        .iter()
+        .zip(report_json["items"].as_array_mut().unwrap())
+    {
+        let span = source_map.item(&item.crate_id, item.index).map(|s| s.item);
+        item_json["span"] = span_json(source, span);
+    }
+    for (error, error_json) in report
+        .errors
+        .iter()
+        .zip(report_json["errors"].as_array_mut().unwrap())
+    {
+        let span = error
+            .item
+            .and_then(|i| source_map.item(&error.crate_id, i))
+            .map(|s| s.item);
+        error_json["span"] = span_json(source, span);
+    }
+    json!({ "syntax_errors": [], "report": report_json }).to_string()
+}
+
+/// Proves `goal`, written like `exists<ty T> {assumptions} => {goals}` (see [`TestAssertion`]),
+/// against the program `source`, returning the solutions with their proof trees:
+///
+/// ```json
+/// { "error": null, "solutions": [{ "constraints": ..., "proof_tree": ... }] }
+/// ```
+///
+/// If the program or the goal does not parse, `error` says why.
+#[wasm_bindgen]
+pub fn prove(source: &str, goal: &str) -> String {
+    fixed_point::clear_memo_tables();
+    let parsed = try_term::<Program>(source).and_then(|program| {
+        let assertion: Arc<TestAssertion> = try_term(goal)?;
+        Ok((program, assertion))
+    });
+    let (program, assertion) = match parsed {
+        Ok(v) => v,
+        Err(e) => return json!({ "error": format!("{e:?}"), "solutions": [] }).to_string(),
+    };
+    let solutions: Vec<serde_json::Value> = test_prove_with_proof_trees(
+        program.to_prove_decls(),
+        assertion,
+    )
+    .into_iter()
+    .map(
+        |(constraints, proof_tree)| json!({ "constraints": constraints, "proof_tree": proof_tree }),
+    )
+    .collect();
+    json!({ "error": null, "solutions": solutions }).to_string()
+}
+
+/// The span as JSON, with the line and column that it starts at in `source`.
+fn span_json(source: &str, span: Option<Span>) -> serde_json::Value {
+    match span {
+        Some(span) => {
+            let (line, column) = span.line_column(source);
+            json!({
+                "start": span.start,
+                "end": span.end,
+                "line": line,
+                "column": column,
+            })
+        }
+        None => json!(null),
+    }
+}
@@ -1,10 +1,18 @@
 use std::{path::PathBuf, sync::Arc};
 
+use anyhow::{anyhow, bail};
 use clap::Parser;
-use formality_check::check_all_crates;
+use formality_check::{
+    check_all_crates, check_all_crates_errors, check_all_crates_report, combine_errors, CheckError,
+};
+use formality_cli::CheckOptions;
 use formality_prove::{test_util::TestAssertion, Constraints};
-use formality_rust::grammar::Program;
-use formality_types::{collections::Set, parse::try_term};
+use formality_rust::{grammar::Program, source_map::SourceMap};
+use formality_types::{
+    collections::Set,
+    parse::{try_term, try_term_with_spans, Span, SyntaxError},
+};
+use serde_json::json;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -12,6 +20,8 @@ struct Args {
     #[arg(long)]
     print_rust: bool,
 
+    /// With `json`, print each error as a rustc-style JSON diagnostic, which is what
+    /// the ui tests match their `//~ ERROR` annotations against.
     #[arg(long, default_value = "")]
     error_format: String,
 
@@ -24,19 +34,125 @@ struct Args {
     #[arg(long)]
     out_dir: Option<PathBuf>,
 
+    #[command(flatten)]
+    check_options: CheckOptions,
+
+    /// Read the input as Rust code, importing the subset of it that formality models
+    /// as a crate named after the file.
+    #[arg(long)]
+    import_rust: bool,
+
+    /// Print a JSON report of the verdict on each item and the errors to stdout,
+    /// in addition to printing the errors.
+    #[arg(long)]
+    report: bool,
+
+    /// With `--report`, include the proof trees of the goals proven for each item that passes.
+    #[arg(long)]
+    proof_trees: bool,
+
     input_path: String,
 }
 
 pub fn main() -> anyhow::Result<()> {
     let args = Args::parse();
     let input: String = std::fs::read_to_string(&args.input_path)?;
-    let program: Program = try_term(&input)?;
+    let (program, source_map) = if args.import_rust {
+        (import_rust(&args.input_path, &input)?, SourceMap::default())
+    } else {
+        match parse_program(&input) {
+            Ok(v) => v,
+            Err(errors) => return syntax_errors(&args, &input, &errors),
+        }
+    };
 
     if args.print_rust {
         eprintln!("{:#?}", program);
     }
 
-    check_all_crates(&program)
+    let options = args.check_options.to_options()?;
+    let span = |error: &CheckError| -> Option<Span> {
+        let source_map = source_map.configure(&error.features);
+        let item = source_map.item(&error.crate_id, error.item?)?;
+        Some(item.item)
+    };
+    let span_json = |span: Option<Span>| match span {
+        Some(span) => {
+            let (line, column) = span.line_column(&input);
+            json!({
+                "start": span.start,
+                "end": span.end,
+                "line": line,
+                "column": column,
+            })
+        }
+        None => json!(null),
+    };
+    let errors = if args.report {
+        let report = check_all_crates_report(&program, &options, args.proof_trees);
+        let source_map = source_map.configure(&report.features);
+        let mut json = report.to_json();
+        for (item, item_json) in report
+            .items
+            .iter()
+            .zip(json["items"].as_array_mut().unwrap())
+        {
+            let span = source_map.item(&item.crate_id, item.index);
+            item_json["span"] = span_json(span.map(|s| s.item));
+        }
+        for (error, error_json) in report
+            .errors
+            .iter()
+            .zip(json["errors"].as_array_mut().unwrap())
+        {
+            error_json["span"] = span_json(span(error));
+        }
+        println!("{json:#}");
+        report.errors
+    } else {
+        check_all_crates_errors(&program, &options)
+    };
+    if args.error_format == "json" {
+        for error in &errors {
+            let diagnostic = error.diagnostic();
+            let notes: Vec<String> = error
+                .error
+                .chain()
+                .map(|e| e.to_string())
+                .filter(|m| *m != diagnostic.message)
+                .collect();
+            let json = rustc_diagnostic(
+                &args.input_path,
+                &input,
+                span(error),
+                diagnostic.code,
+                &diagnostic.message,
+                &notes,
+                &format!("{:?}", error.error),
+            );
+            eprintln!("{json}");
+        }
+        return aborting(errors.len());
+    }
+    combine_errors(
+        errors
+            .into_iter()
+            .map(|error| match span(&error) {
+                Some(span) => {
+                    let (line, column) = span.line_column(&input);
+                    CheckError {
+                        error: anyhow!(
+                            "{:?}\n  --> {}:{line}:{column}",
+                            error.error,
+                            args.input_path
+                        ),
+                        ..error
+                    }
+                }
+                None => error,
+            })
+            .collect(),
+    )
 }
 
 pub fn test_program_ok(input: &str) -> anyhow::Result<()> {
@@ -53,3 +169,125 @@ pub fn test_where_clause(program: &str, assertion: &str) -> anyhow::Result<Set<C
         Ok(formality_prove::test_util::test_prove(decls, assertion))
     })
 }
+
+/// Parses `input` as a program.
+fn parse_program(input: &str) -> Result<(Program, SourceMap), Vec<SyntaxError>> {
+    let (program, spans): (Program, _) = try_term_with_spans(input)?;
+    let source_map = SourceMap::new(&program, &spans);
+    Ok((program, source_map))
+}
+
+/// Reports the syntax errors of `input`, the text of the input file.
+fn syntax_errors(args: &Args, input: &str, errors: &[SyntaxError]) -> anyhow::Result<()> {
+    if args.error_format == "json" {
+        for e in errors {
+            let json = rustc_diagnostic(
+                &args.input_path,
+                input,
+                Some(e.span),
+                None,
+                &e.message,
+                &[],
+                &e.message,
+            );
+            eprintln!("{json}");
+        }
+        return aborting(errors.len());
+    }
+    let errors: Vec<String> = errors
+        .iter()
+        .map(|e| {
+            let (line, column) = e.span.line_column(input);
+            format!("{}\n  --> {}:{line}:{column}", e.message, args.input_path)
+        })
+        .collect();
+    match &errors[..] {
+        [error] => bail!("{error}"),
+        _ => bail!("{} syntax errors:\n\n{}", errors.len(), errors.join("\n\n")),
+    }
+}
+
+fn aborting(errors: usize) -> anyhow::Result<()> {
+    match errors {
+        0 => Ok(()),
+        n => bail!("aborting due to {n} previous error(s)"),
+    }
+}
+
+/// An error as rustc prints it with `--error-format=json`, so that tools made for rustc,
+/// like `ui_test`, understand it. The error is at `span` of `input`, the text of the file
+/// `input_path`, and `notes` are its causes. `rendered` is the human-readable form.
+fn rustc_diagnostic(
+    input_path: &str,
+    input: &str,
+    span: Option<Span>,
+    code: Option<&str>,
+    message: &str,
+    notes: &[String],
+    rendered: &str,
+) -> serde_json::Value {
+    let (spans, location) = match span {
+        Some(span) => {
+            let (line_start, column_start) = span.line_column(input);
+            let end = Span {
+                start: span.end,
+                end: span.end,
+            };
+            let (line_end, column_end) = end.line_column(input);
+            let span_json = json!({
+                "file_name": input_path,
+                "byte_start": span.start,
+                "byte_end": span.end,
+                "line_start": line_start,
+                "line_end": line_end,
+                "column_start": column_start,
+                "column_end": column_end,
+                "is_primary": true,
+                "text": [],
+                "label": null,
+                "suggested_replacement": null,
+                "suggestion_applicability": null,
+                "expansion": null,
+            });
+            (
+                vec![span_json],
+                format!("\n  --> {input_path}:{line_start}:{column_start}"),
+            )
+        }
+        None => (vec![], String::new()),
+    };
+    let children: Vec<serde_json::Value> = notes
+        .iter()
+        .map(|note| {
+            json!({
+                "message": note,
+                "code": null,
+                "level": "note",
+                "spans": [],
+                "children": [],
+                "rendered": null,
+            })
+        })
+        .collect();
+    let code_suffix = code.map(|c| format!("[{c}]")).unwrap_or_default();
+    json!({
+        "$message_type": "diagnostic",
+        "message": message,
+        "code": code.map(|c| json!({ "code": c, "explanation": null })),
+        "level": "error",
+        "spans": spans,
+        "children": children,
+        "rendered": format!("error{code_suffix}: {rendered}{location}\n\n"),
+    })
+}
+
+/// Imports `input`, the Rust code in the file `input_path`, as a program of one crate
+/// named after the file. There are no spans to point errors at.
+fn import_rust(input_path: &str, input: &str) -> anyhow::Result<Program> {
+    let crate_id = std::path::Path::new(input_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("main")
+        .replace('-', "_");
+    formality_import::import_program(&crate_id, input)
+}
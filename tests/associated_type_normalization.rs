@@ -20,26 +20,30 @@ fn test_mirror_normalizes_u32_to_u32() {
                 Constraints {
                     env: Env {
                         variables: [
-                            ?ty_1,
+                            ?ty_0,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {
-                        ?ty_1 => (rigid (scalar u32)),
+                        ?ty_0 => (rigid (scalar u32)),
                     },
+                    region_constraints: {},
                 },
                 Constraints {
                     env: Env {
                         variables: [
-                            ?ty_1,
+                            ?ty_0,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {
-                        ?ty_1 => (alias (Mirror :: Assoc) (rigid (scalar u32))),
+                        ?ty_0 => (alias (Mirror :: Assoc) (rigid (scalar u32))),
                     },
+                    region_constraints: {},
                 },
             },
         )
@@ -0,0 +1,314 @@
+//! The command-line driver, run on the programs in `tests/cli`.
+
+use clap::Parser;
+use formality_cli::{repl::Repl, run, Args};
+use formality_macros::test;
+
+/// Runs the driver with `args`, returning what it prints and its error, if any.
+fn cli(args: &[&str]) -> String {
+    let args =
+        Args::try_parse_from(std::iter::once("formality-cli").chain(args.iter().copied())).unwrap();
+    let mut out = vec![];
+    let result = run(&args, &mut out);
+    let mut text = String::from_utf8(out).unwrap();
+    if let Err(e) = result {
+        text.push_str(&format!("error: {e:?}\n"));
+    }
+    text
+}
+
+#[test]
+fn check() {
+    expect_test::expect![[""]].assert_eq(&cli(&["tests/cli/core.formality"]));
+    expect_test::expect![[r#"
+        error: 4 errors:
+
+        orphan_check(impl<ty T0> Debug<> for T0 where [] {})
+
+        Caused by:
+            failed to prove {@ IsLocal(Debug(!ty_1))} given {}, got {}
+            failed goals:
+              prove_wc(goal: @ IsLocal(Debug(!ty_0))), rules tried: ["assumption", "trait ref is local"]
+
+        impls may overlap:
+        impl<ty T0> Debug<> for T0 where [] {}
+        impl<ty T0> Debug<> for Vec<T0> where [T0: Debug<>] {}
+        note: downstream crates may implement trait `Debug` for type `?ty_0`
+
+        impls may overlap:
+        impl<ty T0> Debug<> for T0 where [] {}
+        impl<> Debug<> for u32 where [] {}
+
+        trait `Debug` is private to crate `core`
+    "#]].assert_eq(&cli(&[
+        "--check",
+        "tests/cli/core.formality",
+        "tests/cli/overlap.formality",
+    ]));
+}
+
+#[test]
+fn coherence_only() {
+    expect_test::expect![[r#"
+        error: 3 errors:
+
+        orphan_check(impl<ty T0> Debug<> for T0 where [] {})
+
+        Caused by:
+            failed to prove {@ IsLocal(Debug(!ty_1))} given {}, got {}
+            failed goals:
+              prove_wc(goal: @ IsLocal(Debug(!ty_0))), rules tried: ["assumption", "trait ref is local"]
+
+        impls may overlap:
+        impl<ty T0> Debug<> for T0 where [] {}
+        impl<ty T0> Debug<> for Vec<T0> where [T0: Debug<>] {}
+        note: downstream crates may implement trait `Debug` for type `?ty_0`
+
+        impls may overlap:
+        impl<ty T0> Debug<> for T0 where [] {}
+        impl<> Debug<> for u32 where [] {}
+    "#]].assert_eq(&cli(&[
+        "--coherence-only",
+        "tests/cli/core.formality",
+        "tests/cli/overlap.formality",
+    ]));
+}
+
+#[test]
+fn check_json() {
+    expect_test::expect![[r#"
+        {
+          "errors": [],
+          "items": [
+            {
+              "crate": "core",
+              "errors": [],
+              "index": 0,
+              "item": "trait Debug<> where [] {}",
+              "proof_trees": [],
+              "verdict": "pass"
+            },
+            {
+              "crate": "core",
+              "errors": [],
+              "index": 1,
+              "item": "struct Vec<ty T0> where [] {}",
+              "proof_trees": [],
+              "verdict": "pass"
+            },
+            {
+              "crate": "core",
+              "errors": [],
+              "index": 2,
+              "item": "impl<ty T0> Debug<> for Vec<T0> where [T0: Debug<>] {}",
+              "proof_trees": [],
+              "verdict": "pass"
+            },
+            {
+              "crate": "core",
+              "errors": [],
+              "index": 3,
+              "item": "impl<> Debug<> for u32 where [] {}",
+              "proof_trees": [],
+              "verdict": "pass"
+            }
+          ]
+        }
+    "#]]
+    .assert_eq(&cli(&["--emit=json", "tests/cli/core.formality"]));
+}
+
+#[test]
+fn prove_goal() {
+    expect_test::expect![[r#"
+        Constraints { env: Env { variables: [?ty_0], coherence_mode: false }, known_true: true, overflow: false, substitution: {?ty_0 => (rigid (scalar u32))}, region_constraints: {} }
+    "#]].assert_eq(&cli(&[
+        "--prove-goal",
+        "exists<ty T> {} => {T = u32, Debug(T)}",
+        "tests/cli/core.formality",
+    ]));
+    expect_test::expect![[r#"
+        [
+          {
+            "env": {
+              "variables": [],
+              "coherence_mode": false
+            },
+            "known_true": true,
+            "overflow": false,
+            "substitution": [],
+            "region_constraints": []
+          }
+        ]
+    "#]]
+    .assert_eq(&cli(&[
+        "--prove-goal",
+        "{} => {Debug(Vec<u32>)}",
+        "--emit=json",
+        "tests/cli/core.formality",
+    ]));
+    expect_test::expect![[r#"
+        error: failed to prove `{} => {Debug(i32)}`
+    "#]]
+    .assert_eq(&cli(&[
+        "--prove-goal",
+        "{} => {Debug(i32)}",
+        "tests/cli/core.formality",
+    ]));
+}
+
+/// The statistics follow the results. Their times vary, and with them the order of
+/// the judgments, so the test drops the times and sorts the judgments.
+#[test]
+fn stats() {
+    let output = cli(&[
+        "--stats",
+        "--prove-goal",
+        "{} => {Debug(Vec<u32>)}",
+        "tests/cli/core.formality",
+    ]);
+    let mut blocks: Vec<String> = vec![];
+    for line in output.lines() {
+        match line.rsplit_once(' ') {
+            Some((row, time)) if time.ends_with('s') && !line.starts_with(' ') => {
+                blocks.push(row.trim_end().to_string())
+            }
+            _ if line.starts_with(' ') => {
+                let block = blocks.last_mut().unwrap();
+                block.push('\n');
+                block.push_str(line);
+            }
+            _ => blocks.push(line.to_string()),
+        }
+    }
+    let (results, judgments) = blocks.split_at_mut(3);
+    judgments.sort();
+    expect_test::expect![[r#"
+        Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} }
+        max depth: 13
+        judgment                    calls     memo    cycle iterations         time
+        prove_after                    10        4        0          6
+            "prove_after" fired 6 times
+        prove_eq                       15        1        5         11
+            "existential" fired 2 times
+            "rigid" fired 4 times
+            "symmetric" fired 6 times
+        prove_existential_var_eq        2        1        0          1
+            "existential-nonvar" fired 1 times
+        prove_impl                      2        0        0          2
+            "impl" fired 2 times
+        prove_normalize                11        5        0          6
+        prove_normalize_via             6        0        0          6
+        prove_via                       5        0        0          5
+        prove_wc                        6        0        0          6
+            "eq" fired 3 times
+            "positive impl" fired 2 times
+        prove_wc_list                  15        6        0          9
+            "none" fired 3 times
+            "some" fired 5 times"#]].assert_eq(&format!(
+        "{}\n{}",
+        results.join("\n"),
+        judgments.join("\n")
+    ));
+}
+
+#[test]
+fn proof_trees() {
+    let text = cli(&[
+        "--prove-goal",
+        "{} => {Debug(Vec<u32>)}",
+        "--emit=proof-tree",
+        "tests/cli/core.formality",
+    ]);
+    assert!(
+        text.starts_with("prove(goal: {Debug((rigid (adt Vec) (rigid (scalar u32))))}"),
+        "{text}"
+    );
+
+    // The items that pass, with the proof trees of the goals proven to check them.
+    let text = cli(&["--emit=proof-tree", "tests/cli/core.formality"]);
+    assert!(
+        text.starts_with("impl<ty T0> Debug<> for Vec<T0> where [T0: Debug<>] {}\n"),
+        "{text}"
+    );
+    assert!(text.contains("by \"positive impl\""), "{text}");
+}
+
+#[test]
+fn parse_error() {
+    expect_test::expect![[r#"
+        error: parsing the goal `Debug(`
+
+        Caused by:
+            0: `TestAssertion` expected
+            1: Debug(
+            2: failed to parse Debug(
+    "#]]
+    .assert_eq(&cli(&[
+        "--prove-goal",
+        "Debug(",
+        "tests/cli/core.formality",
+    ]));
+}
+
+#[test]
+fn repl() {
+    let program =
+        formality_types::parse::term(&std::fs::read_to_string("tests/cli/core.formality").unwrap());
+    let mut repl = Repl::new(&program);
+    let mut eval = |line: &str| repl.eval(line).unwrap();
+    expect_test::expect!["Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} }"].assert_eq(&eval("Vec<u32>: Debug<>"));
+    expect_test::expect!["no solutions"].assert_eq(&eval("Vec<i32>: Debug<>"));
+    expect_test::expect!["Constraints { env: Env { variables: [!ty_0], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} }"].assert_eq(&eval("forall<ty T> {Debug(T)} => {Debug(Vec<T>)}"));
+
+    // Assumptions apply to the goals written as where-clauses.
+    expect_test::expect![[""]].assert_eq(&eval(":assume i32: Debug<>"));
+    expect_test::expect!["Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} }"].assert_eq(&eval("Vec<i32>: Debug<>"));
+
+    expect_test::expect!["coherence mode: on"].assert_eq(&eval(":coherence"));
+    expect_test::expect![[r#"
+        coherence mode: on
+        assumptions: [i32: Debug<>]"#]]
+    .assert_eq(&eval(":env"));
+    expect_test::expect![[""]].assert_eq(&eval(":reset"));
+    expect_test::expect![[r#"
+        coherence mode: on
+        assumptions: []"#]]
+    .assert_eq(&eval(":env"));
+    expect_test::expect!["error: unknown command `:frobnicate`, see `:help`"]
+        .assert_eq(&eval(":frobnicate"));
+    assert_eq!(repl.eval(":quit"), None);
+}
+
+/// The loop reads lines until the input ends or the user quits.
+#[test]
+fn repl_run() {
+    let program =
+        formality_types::parse::term(&std::fs::read_to_string("tests/cli/core.formality").unwrap());
+    let mut out = vec![];
+    Repl::new(&program)
+        .run(&b"u32: Debug<>\n:quit\nu32: Debug<>\n"[..], &mut out)
+        .unwrap();
+    expect_test::expect![[r#"
+        Type a goal, or `:help` for the commands.
+        > Constraints { env: Env { variables: [], coherence_mode: false }, known_true: true, overflow: false, substitution: {}, region_constraints: {} }
+        > "#]].assert_eq(&String::from_utf8(out).unwrap());
+}
+
+/// A second run with the query cache that the first one saved reports the same errors.
+#[test]
+fn query_cache() {
+    let path = std::env::temp_dir().join(format!("cli-query-cache-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let args = [
+        "--query-cache",
+        path.to_str().unwrap(),
+        "tests/cli/core.formality",
+        "tests/cli/overlap.formality",
+    ];
+    let uncached = cli(&args[2..]);
+    assert_eq!(cli(&args), uncached);
+    assert!(path.exists());
+    assert_eq!(cli(&args), uncached);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,153 @@
+use formality::test_where_clause;
+
+const DEREF_BOX: &str = "[
+    crate test {
+        trait Deref<> where [] {
+            type Target<> : [] where [];
+        }
+
+        trait DerefMut<> where [Self: Deref<>] {}
+
+        struct Box<ty T> where [] {}
+
+        struct Rc<ty T> where [] {}
+
+        impl<ty T> Deref<> for Box<T> where [] {
+            type Target<> = T where [];
+        }
+
+        impl<ty T> DerefMut<> for Box<T> where [] {}
+
+        impl<ty T> Deref<> for Rc<T> where [] {
+            type Target<> = T where [];
+        }
+    }
+]";
+
+#[test]
+fn deref_coercion_through_impl() {
+    expect_test::expect![[r#"
+        Ok(
+            {
+                Constraints {
+                    env: Env {
+                        variables: [
+                            !ty_0,
+                            !lt_1,
+                        ],
+                        coherence_mode: false,
+                    },
+                    known_true: true,
+                    overflow: false,
+                    substitution: {},
+                    region_constraints: {},
+                },
+            },
+        )
+    "#]]
+    .assert_debug_eq(&test_where_clause(
+        DEREF_BOX,
+        "forall<ty T, lt a> {} => { &a Box<T> ~> &a T }",
+    ));
+}
+
+#[test]
+fn deref_coercion_chain() {
+    expect_test::expect![[r#"
+        Ok(
+            {
+                Constraints {
+                    env: Env {
+                        variables: [
+                            !ty_0,
+                            !lt_1,
+                        ],
+                        coherence_mode: false,
+                    },
+                    known_true: true,
+                    overflow: false,
+                    substitution: {},
+                    region_constraints: {},
+                },
+            },
+        )
+    "#]]
+    .assert_debug_eq(&test_where_clause(
+        DEREF_BOX,
+        "forall<ty T, lt a> {} => { &a &a Rc<Box<T>> ~> &a T }",
+    ));
+}
+
+#[test]
+fn deref_mut_coercion_requires_deref_mut() {
+    expect_test::expect![[r#"
+        Ok(
+            {
+                Constraints {
+                    env: Env {
+                        variables: [
+                            !ty_0,
+                            !lt_1,
+                        ],
+                        coherence_mode: false,
+                    },
+                    known_true: true,
+                    overflow: false,
+                    substitution: {},
+                    region_constraints: {},
+                },
+            },
+        )
+    "#]]
+    .assert_debug_eq(&test_where_clause(
+        DEREF_BOX,
+        "forall<ty T, lt a> {} => { &mut a Box<T> ~> &mut a T }",
+    ));
+
+    expect_test::expect![[r#"
+        Ok(
+            {},
+        )
+    "#]]
+    .assert_debug_eq(&test_where_clause(
+        DEREF_BOX,
+        "forall<ty T, lt a> {} => { &mut a Rc<T> ~> &mut a T }",
+    ));
+
+    expect_test::expect![[r#"
+        Ok(
+            {
+                Constraints {
+                    env: Env {
+                        variables: [
+                            !ty_0,
+                            !lt_1,
+                        ],
+                        coherence_mode: false,
+                    },
+                    known_true: true,
+                    overflow: false,
+                    substitution: {},
+                    region_constraints: {},
+                },
+            },
+        )
+    "#]]
+    .assert_debug_eq(&test_where_clause(
+        DEREF_BOX,
+        "forall<ty T, lt a> {} => { &mut a Rc<T> ~> &a T }",
+    ));
+}
+
+#[test]
+fn no_deref_coercion_to_unrelated_type() {
+    expect_test::expect![[r#"
+        Ok(
+            {},
+        )
+    "#]]
+    .assert_debug_eq(&test_where_clause(
+        DEREF_BOX,
+        "forall<ty T, ty U, lt a> {} => { &a Box<T> ~> &a U }",
+    ));
+}
@@ -0,0 +1,114 @@
+use formality_check::{
+    check_all_crates_errors, check_all_crates_report, Diagnostic, Options, Verdict,
+};
+use formality_macros::test;
+use formality_rust::grammar::Program;
+use formality_types::parse::term;
+
+/// `foo` has two overlapping impls and a non-exhaustive match.
+fn program() -> Program {
+    term(
+        "[
+            crate foo {
+                trait Foo<> where [] {}
+                impl<ty T> Foo<> for T where [] {}
+                impl<> Foo<> for u32 where [] {}
+
+                enum Option<ty T> where [] {
+                    Some { 0: T },
+                    None { },
+                }
+
+                fn unwrap<>(Option<u32>) -> u32 where [] |o| {
+                    match o { Option::Some(x) => x }
+                }
+            }
+        ]",
+    )
+}
+
+#[test]
+fn codes() {
+    let errors = check_all_crates_errors(&program(), &Options::default());
+    let codes: Vec<_> = errors.iter().map(|e| e.diagnostic().code).collect();
+    expect_test::expect![[r#"
+        [
+            Some(
+                "E0004",
+            ),
+            Some(
+                "E0119",
+            ),
+        ]
+    "#]]
+    .assert_debug_eq(&codes);
+}
+
+#[test]
+fn overlap_json() {
+    let errors = check_all_crates_errors(&program(), &Options::default());
+    let overlap = errors
+        .iter()
+        .find(|e| e.diagnostic().code == Some("E0119"))
+        .unwrap();
+    expect_test::expect![[r#"
+        {
+          "code": "E0119",
+          "crate": "foo",
+          "message": "impls may overlap:\nimpl<ty T0> Foo<> for T0 where [] {}\nimpl<> Foo<> for u32 where [] {}",
+          "primary": " impl <ty>  Foo < > for ^ty0_0 where [] { }",
+          "related": [
+            " impl <>  Foo < > for (rigid (scalar u32)) where [] { }"
+          ],
+          "rendered": "impls may overlap:\nimpl<ty T0> Foo<> for T0 where [] {}\nimpl<> Foo<> for u32 where [] {}"
+        }
+    "#]]
+    .assert_eq(&format!("{:#}\n", overlap.to_json()));
+}
+
+/// Errors the checks have no code for are reported without one.
+#[test]
+fn no_code() {
+    let error = anyhow::anyhow!("something went wrong");
+    assert_eq!(Diagnostic::of(&error).code, None);
+    assert_eq!(Diagnostic::of(&error).message, "something went wrong");
+}
+
+/// The outermost diagnostic describes the error, e.g., an orphan check failing to prove a goal.
+#[test]
+fn outermost_wins() {
+    let inner = anyhow::Error::new(Diagnostic::new("E0277", "failed to prove"));
+    let error = inner.context(Diagnostic::new("E0117", "orphan_check"));
+    assert_eq!(Diagnostic::of(&error).code, Some("E0117"));
+}
+
+/// The report has a verdict on each item; the errors that span items are about their primary one.
+#[test]
+fn report_verdicts() {
+    let report = check_all_crates_report(&program(), &Options::default(), false);
+    let verdicts: Vec<(usize, Verdict, Vec<usize>)> = report
+        .items
+        .iter()
+        .map(|item| (item.index, item.verdict, item.errors.clone()))
+        .collect();
+    expect_test::expect![
+        "[(0, Pass, []), (1, Fail, [1]), (2, Pass, []), (3, Pass, []), (4, Fail, [0])]"
+    ]
+    .assert_eq(&format!("{verdicts:?}"));
+    assert_eq!(report.errors.len(), 2);
+}
+
+/// Proof trees are only recorded on request, and only for the items that pass.
+#[test]
+fn report_proof_trees() {
+    let report = check_all_crates_report(&program(), &Options::default(), false);
+    assert!(report.items.iter().all(|item| item.proof_trees.is_empty()));
+
+    let report = check_all_crates_report(&program(), &Options::default(), true);
+    for item in &report.items {
+        if item.verdict != Verdict::Pass {
+            assert!(item.proof_trees.is_empty(), "{item:?}");
+        }
+    }
+    assert!(report.items.iter().any(|item| !item.proof_trees.is_empty()));
+}
@@ -0,0 +1,40 @@
+//! Differential fuzzing of the checks on random well-formed programs
+//! (see `formality_check::fuzz`).
+
+use formality_check::fuzz::{fuzz, generate_program};
+use formality_macros::test;
+use formality_types::{generate::Generator, pretty::to_string};
+
+/// The number of programs to check.
+const COUNT: u64 = 50;
+
+#[test]
+fn generated_programs() {
+    for seed in 0..COUNT {
+        if let Err(message) = fuzz(seed) {
+            panic!("seed {seed}: {message}");
+        }
+    }
+}
+
+/// Generated programs only name the items they declare.
+#[test]
+fn generated_program() {
+    let program = generate_program(&mut Generator::new(3));
+    expect_test::expect![[r#"
+        [
+            crate crate0 {
+                trait Trait0<> where [Self: Trait0<>] {}
+                trait Trait1<ty T0, ty T1> where [] {}
+            },
+            crate crate1 {
+                trait Trait2<ty T0> where [] {}
+                struct Struct0<ty T0> where [Struct0<T0>: Trait2<bool>] { f0: T0 }
+                impl<ty T0> !Trait0<> for &static T0 where [T0: Trait1<T0, T0>] {}
+                impl<> !Trait1<&static bool, Struct0<u32>> for Struct0<u32> where [
+                    (): Trait0<>
+                ] {}
+            }
+        ]"#]]
+    .assert_eq(&to_string(&program));
+}
@@ -0,0 +1,151 @@
+use formality_check::check_all_crates;
+use formality_import::import_program;
+use formality_macros::test;
+use formality_types::pretty::to_string;
+
+fn import(text: &str) -> String {
+    match import_program("foo", text) {
+        Ok(program) => to_string(&program),
+        Err(e) => format!("{e:?}"),
+    }
+}
+
+#[test]
+fn structs_and_enums() {
+    expect_test::expect![[r#"
+        [
+            crate foo {
+                pub struct Point<> where [] { pub x: u32, pub y: u32 }
+                struct Wrapper<ty T0> where [T0: Clone<>] { 0: T0 }
+                struct Unit<> where [] {}
+                pub enum Either<ty T0, ty T1> where [] {
+                    Left { 0: T0 },
+                    Right { 0: T1 }
+                }
+                struct Ref<lt a0, ty T0> where [T0: a0] { r: &a0 T0, p: *mut T0 }
+            }
+        ]"#]]
+    .assert_eq(&import(
+        "
+        use std::fmt::Debug;
+
+        #[derive(Clone)]
+        pub struct Point { pub x: u32, pub y: u32 }
+        struct Wrapper<T: Clone>(T);
+        struct Unit;
+        pub enum Either<L, R> { Left(L), Right(R) }
+        struct Ref<'a, T: 'a> { r: &'a T, p: *mut T }
+        ",
+    ));
+}
+
+#[test]
+fn traits_and_impls() {
+    expect_test::expect![[r#"
+        [
+            crate foo {
+                pub trait Iterator<> where [] {
+                    type Item<>: [] where [];
+                    fn next<lt a0>(self: &mut a0 Self) -> Option<<Self as Iterator>::Item> where [
+                        Self: a0
+                    ];
+                }
+                trait Collect<ty T0> where [Self: Iterator<>, T0:?Sized] {
+                    fn collect<ty T2>(T2) -> Self where [
+                        T2: Iterator<>,
+                        (alias(Iterator::Item) T2) => T0
+                    ];
+                }
+                unsafe trait Marker<> where [for<lt a0> Self: Collect<&a0 u32>] {}
+                impl<lt a0, ty T0> Iterator<> for Iter<a0, T0> where [T0: Clone<>] {
+                    type Item<> = &a0 T0 where [];
+                    fn next<lt a1>(self: &mut a1 Iter<a0, T0>) -> Option<<Iter<a0, T0> as Iterator>::Item> where [
+                        Iter<a0, T0>: a1
+                    ] { trusted }
+                }
+                impl<> !Marker<> for u32 where [] {}
+                unsafe impl<ty T0, lt a0> Marker<> for &a0 T0 where [T0: a0] {}
+                fn first<ty T0, lt a0>(&a0 Vec<T0>) -> &a0 T0 where [
+                    Vec<T0>: a0,
+                    T0: a0
+                ] { trusted }
+            }
+        ]"#]]
+    .assert_eq(&import(
+        "
+        pub trait Iterator {
+            type Item;
+            fn next(&mut self) -> Option<Self::Item>;
+        }
+        trait Collect<T: ?Sized>: Iterator {
+            fn collect<I: Iterator<Item = T>>(iter: I) -> Self;
+        }
+        unsafe trait Marker where for<'a> Self: Collect<&'a u32> {}
+        impl<'a, T> Iterator for Iter<'a, T> where T: Clone {
+            type Item = &'a T;
+            fn next(&mut self) -> Option<Self::Item> { None }
+        }
+        impl !Marker for u32 {}
+        unsafe impl<T> Marker for &T {}
+        fn first<T>(v: &Vec<T>) -> &T { &v[0] }
+        ",
+    ));
+}
+
+#[test]
+fn unsupported() {
+    expect_test::expect![[r#"
+        importing `impl Foo`
+
+        Caused by:
+            inherent impls are not supported"#]]
+    .assert_eq(&import("impl Foo { fn new() -> Self { Foo } }"));
+
+    expect_test::expect![[r#"
+        importing fn `pick`
+
+        Caused by:
+            missing lifetime in the return type `& u32`"#]]
+    .assert_eq(&import("fn pick(a: &u32, b: &u32) -> &u32 { a }"));
+
+    expect_test::expect![[r#"
+        importing trait `Get`
+
+        Caused by:
+            0: importing fn `get`
+            1: ambiguous associated type `T :: Output`, write `<T as Trait>::Output` instead"#]]
+    .assert_eq(&import("trait Get { fn get<T>(t: T) -> T::Output; }"));
+}
+
+/// Imported programs can be checked like any other.
+#[test]
+fn check_imported() {
+    let program = import_program(
+        "foo",
+        "
+        trait Sized {}
+        trait Animal { fn name() -> u32 { 0 } }
+        struct Dog;
+        impl Sized for Dog {}
+        impl Animal for Dog {}
+        fn speak<A: Animal>(a: A) -> u32 { A::name() }
+        ",
+    )
+    .unwrap();
+    check_all_crates(&program).unwrap();
+
+    // A pet has to be an animal.
+    let program = import_program(
+        "foo",
+        "
+        trait Animal {}
+        trait Pet: Animal {}
+        struct Dog;
+        impl Pet for Dog {}
+        ",
+    )
+    .unwrap();
+    let error = format!("{:?}", check_all_crates(&program).unwrap_err());
+    expect_test::expect!["check_trait_impl(impl<> Pet<> for Dog where [] {})"]
+        .assert_eq(error.lines().next().unwrap());
+}
@@ -0,0 +1,155 @@
+//! Checking programs again after they change (see `formality_check::IncrementalCheck`).
+
+use formality_check::{check_all_crates_errors, fuzz::generate_program, IncrementalCheck, Options};
+use formality_macros::test;
+use formality_rust::grammar::{CrateItem, Program};
+use formality_types::{generate::Generator, parse::term};
+
+fn program() -> Program {
+    term(
+        "[
+            crate core {
+                trait Foo<> where [] {}
+                trait Bar<> where [] {}
+                struct S<> where [] {}
+                impl<> Foo<> for u32 where [] {}
+                impl<> Bar<> for S<> where [] {}
+                fn needs_foo<ty T>(T) -> () where [T: Foo<>] {trusted}
+                fn uses_foo<>(u32) -> () where [] |x| { needs_foo(x) }
+            }
+        ]",
+    )
+}
+
+fn errors(errors: Vec<formality_check::CheckError>) -> Vec<String> {
+    errors.iter().map(|e| e.to_string()).collect()
+}
+
+/// `program` without the item at `index` of its crate `crate_index`.
+fn without_item(program: &Program, crate_index: usize, index: usize) -> Program {
+    let mut program = program.clone();
+    program.crates[crate_index].items.remove(index);
+    program
+}
+
+/// The checks that the change does not affect are not re-run: removing the impl of
+/// `Foo` re-runs the checks of `uses_foo` but not those of the impl of `Bar`.
+#[test]
+fn only_affected_checks_rerun() {
+    let mut check = IncrementalCheck::new(Options::default());
+    assert!(check.check(&program()).is_empty());
+    assert_eq!(check.reused(), 0);
+
+    assert!(check.check(&program()).is_empty());
+    let all = check.reused();
+    assert!(all > 0);
+
+    let without_foo_impl = without_item(&program(), 0, 3);
+    let errors = errors(check.check(&without_foo_impl));
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert!(errors[0].contains("needs_foo(x)"), "{errors:?}");
+    assert!(0 < check.reused() && check.reused() < all);
+
+    let with_bar_impl = without_foo_impl.with_items(
+        &term("core"),
+        [term::<CrateItem>("impl<> Bar<> for u32 where [] {}")],
+    );
+    let errors_after = check.check(&with_bar_impl.unwrap());
+    assert_eq!(errors_after.len(), 1);
+}
+
+/// Removing any one impl of a generated program, and putting it back, reports the
+/// same errors as checking the changed program from scratch.
+#[test]
+fn agrees_with_checking_from_scratch() {
+    // The prover recurses deeply on generated programs, see `formality_check::fuzz`.
+    std::thread::Builder::new()
+        .stack_size(64 << 20)
+        .spawn(check_generated_programs)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+fn check_generated_programs() {
+    for seed in 0..10 {
+        let program = generate_program(&mut Generator::new(seed));
+        let mut check = IncrementalCheck::new(Options::default());
+        let original = errors(check.check(&program));
+        for (crate_index, c) in program.crates.iter().enumerate() {
+            for (index, item) in c.items.iter().enumerate() {
+                if !matches!(item, CrateItem::TraitImpl(_) | CrateItem::NegTraitImpl(_)) {
+                    continue;
+                }
+                let changed = without_item(&program, crate_index, index);
+                assert_eq!(
+                    errors(check.check(&changed)),
+                    errors(check_all_crates_errors(&changed, &Options::default())),
+                    "seed {seed}, without impl {index} of crate {crate_index}",
+                );
+                assert_eq!(errors(check.check(&program)), original, "seed {seed}");
+            }
+        }
+    }
+}
+
+/// Changing the dependencies of a crate re-runs its checks: here `Tr` names the trait
+/// of `b` rather than that of `a`, which `u32` does not implement.
+#[test]
+fn dependencies_changed() {
+    let program = |dependency: &str| -> Program {
+        term(&format!(
+            "[
+                crate a depends [] {{
+                    pub trait Tr<> where [] {{}}
+                    impl<> Tr<> for u32 where [] {{}}
+                }},
+                crate b depends [] {{
+                    pub trait Tr<> where [] {{}}
+                }},
+                crate c depends [{dependency}] {{
+                    fn needs_tr<ty T>(T) -> () where [T: Tr<>] {{trusted}}
+                    fn uses_tr<>(u32) -> () where [] |x| {{ needs_tr(x) }}
+                }}
+            ]"
+        ))
+    };
+
+    let mut check = IncrementalCheck::new(Options::default());
+    assert!(check.check(&program("a")).is_empty());
+
+    let errors = errors(check.check(&program("b")));
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert!(errors[0].contains("needs_tr(x)"), "{errors:?}");
+    assert!(check.reused() > 0);
+}
+
+/// Changing the edition of a crate re-runs its checks: from Rust 2024, the diverging
+/// argument of `debug` falls back to `!`, which does not implement `Debug`.
+#[test]
+fn edition_changed() {
+    let program = |edition: &str| -> Program {
+        term(&format!(
+            "[
+                crate core edition {edition} {{
+                    trait Debug<> where [] {{}}
+                    impl<> Debug<> for () where [] {{}}
+                    impl<> Debug<> for u32 where [] {{}}
+                    enum Void<> where [] {{}}
+                    fn debug<ty T>(T) -> () where [T: Debug<>] {{trusted}}
+                    fn unit_fallback<>(Void) -> () where [] |v| {{ debug(match v {{}}) }}
+                }}
+            ]"
+        ))
+    };
+
+    let mut check = IncrementalCheck::new(Options::default());
+    assert!(check.check(&program("2015")).is_empty());
+
+    let errors = errors(check.check(&program("2024")));
+    assert_eq!(
+        errors,
+        self::errors(check_all_crates_errors(&program("2024"), &Options::default()))
+    );
+    assert_eq!(errors.len(), 1, "{errors:?}");
+}
@@ -0,0 +1,139 @@
+//! The language server, both its analysis of documents and the protocol.
+
+use formality_lsp::analysis::Analysis;
+use formality_macros::test;
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::Url;
+use serde_json::json;
+
+const TEXT: &str = "[
+    crate core {
+        trait Bar<> where [] {}
+        trait Foo<> where [Self: Bar<>] {}
+        struct Wrapper<ty T> where [T: Foo<>] { value: T }
+    }
+]";
+
+/// The byte offset of the `n`th occurrence of `word` in `TEXT`.
+fn offset_of(word: &str, n: usize) -> usize {
+    TEXT.match_indices(word).nth(n).unwrap().0
+}
+
+#[test]
+fn definition() {
+    let analysis = Analysis::new(TEXT);
+    let spans = analysis.definition(offset_of("Foo", 1) + 1);
+    assert_eq!(spans.len(), 1);
+    assert_eq!(spans[0].start, offset_of("Foo", 0));
+    assert_eq!(&TEXT[spans[0].start..spans[0].end], "Foo");
+
+    // Only ADTs and traits have definitions.
+    assert!(analysis.definition(offset_of("value", 0)).is_empty());
+}
+
+#[test]
+fn hover() {
+    let analysis = Analysis::new(TEXT);
+    expect_test::expect![[r#"
+        ```
+        struct Wrapper
+        ```
+        where-clauses, elaborated: `<ty T0> { Bar(T0), Foo(T0) }`"#]].assert_eq(&analysis.hover(offset_of("Wrapper", 0)).unwrap());
+    expect_test::expect![[r#"
+        ```
+        trait Foo
+        ```
+        where-clauses, elaborated: `<ty T0> { Bar(T0) }`"#]].assert_eq(&analysis.hover(offset_of("Foo", 0)).unwrap());
+    assert_eq!(analysis.hover(offset_of("value", 0)), None);
+}
+
+#[test]
+fn syntax_errors() {
+    let text = "[crate core { trait Foo<> where [] {} struct }]";
+    let diagnostics = formality_lsp::diagnostics(text);
+    expect_test::expect![[r#"
+        [
+          {
+            "range": {
+              "start": {
+                "line": 0,
+                "character": 45
+              },
+              "end": {
+                "line": 0,
+                "character": 45
+              }
+            },
+            "severity": 1,
+            "source": "formality",
+            "message": "identifier expected"
+          }
+        ]"#]].assert_eq(&serde_json::to_string_pretty(&diagnostics).unwrap());
+    assert!(formality_lsp::diagnostics(TEXT).is_empty());
+}
+
+/// A session of a client with the server over the protocol.
+#[test]
+fn protocol() {
+    let (client, server) = Connection::memory();
+    let server = std::thread::spawn(move || formality_lsp::run(&server));
+    let uri = Url::parse("file:///core.formality").unwrap();
+    let request = |id: i32, method: &str, params: serde_json::Value| -> Response {
+        client
+            .sender
+            .send(Request::new(RequestId::from(id), method.to_string(), params).into())
+            .unwrap();
+        loop {
+            match client.receiver.recv().unwrap() {
+                Message::Response(response) => return response,
+                _ => continue,
+            }
+        }
+    };
+    let notify = |method: &str, params: serde_json::Value| {
+        client
+            .sender
+            .send(Notification::new(method.to_string(), params).into())
+            .unwrap();
+    };
+
+    let response = request(1, "initialize", json!({ "capabilities": {} }));
+    assert_eq!(
+        response.result.unwrap()["capabilities"]["hoverProvider"],
+        json!(true)
+    );
+    notify("initialized", json!({}));
+
+    notify(
+        "textDocument/didOpen",
+        json!({ "textDocument": { "uri": uri, "languageId": "formality", "version": 1, "text": TEXT } }),
+    );
+    let Message::Notification(diagnostics) = client.receiver.recv().unwrap() else {
+        panic!("expected diagnostics");
+    };
+    assert_eq!(diagnostics.method, "textDocument/publishDiagnostics");
+    assert_eq!(diagnostics.params["diagnostics"], json!([]));
+
+    // `Foo` in the where-clause of `Wrapper`, on line 4.
+    let position = json!({ "line": 4, "character": 39 });
+    let response = request(
+        2,
+        "textDocument/definition",
+        json!({ "textDocument": { "uri": uri }, "position": position }),
+    );
+    expect_test::expect![[r#"[{"range":{"end":{"character":17,"line":3},"start":{"character":14,"line":3}},"uri":"file:///core.formality"}]"#]].assert_eq(&response.result.unwrap().to_string());
+
+    let response = request(
+        3,
+        "textDocument/hover",
+        json!({ "textDocument": { "uri": uri }, "position": position }),
+    );
+    assert!(response.result.unwrap()["contents"]["value"]
+        .as_str()
+        .unwrap()
+        .starts_with("```\ntrait Foo\n```"));
+
+    request(4, "shutdown", json!(null));
+    notify("exit", json!(null));
+    server.join().unwrap().unwrap();
+}
@@ -0,0 +1,64 @@
+//! Minimizing programs that the checks go wrong on (see `formality_check::minimize`).
+
+use formality_check::minimize::{minimize, Failure};
+use formality_macros::test;
+use formality_rust::grammar::Program;
+use formality_types::{parse::term, pretty::to_string};
+
+/// Of the overlapping impls, only what makes them overlap remains.
+#[test]
+fn overlapping_impls() {
+    let program: Program = term(
+        "[
+            crate core {
+                trait Foo<ty X> where [] {}
+                trait Is<> where [] {}
+                trait Unused<> where [] {}
+                struct S<ty T, lt a> where [T: Is<>] {}
+                impl<> Is<> for u32 where [] {}
+                impl<> Foo<u32> for u32 where [u32: Is<>] {}
+                impl<ty T, ty U> Foo<U> for T where [T: Is<>] {}
+            },
+            crate other {
+                trait Bar<> where [] {}
+            }
+        ]",
+    );
+    let failure = Failure::Error("impls may overlap".to_string());
+    assert!(failure.reproduces(&program));
+    let minimized = minimize(&program, |p| failure.reproduces(p));
+    expect_test::expect![[r#"
+        [
+            crate core {
+                trait Foo<ty T0> where [] {}
+                impl<> Foo<u32> for u32 where [] {}
+                impl<ty T0, ty T1> Foo<T1> for T0 where [] {}
+            }
+        ]"#]]
+    .assert_eq(&to_string(&minimized));
+}
+
+/// Parameters that the failure does not depend on are replaced, here by `static`.
+#[test]
+fn parameters() {
+    let program: Program = term(
+        "[
+            crate core {
+                trait Foo<> where [] {}
+                struct S<ty T, lt a> where [] {}
+                impl<> Foo<> for S<u32, static> where [] {}
+                impl<ty T, lt a> Foo<> for S<T, a> where [] {}
+            }
+        ]",
+    );
+    let failure = Failure::Error("impls may overlap".to_string());
+    let minimized = minimize(&program, |p| failure.reproduces(p));
+    expect_test::expect![[r#"
+        [
+            crate core {
+                impl<> Foo<> for S<u32, static> where [] {}
+                impl<ty T0> Foo<> for S<T0, static> where [] {}
+            }
+        ]"#]]
+    .assert_eq(&to_string(&minimized));
+}
@@ -0,0 +1,59 @@
+use formality_macros::test;
+use formality_rust::grammar::Program;
+use formality_types::{grammar::Ty, parse::term};
+
+/// Types print in the syntax they are written in, binders give their variables names.
+#[test]
+fn surface_syntax() {
+    let program: Program = term(
+        "[
+            crate core {
+                trait Foo<ty T> where [for<lt a> &a T: Bar<>] {}
+                impl<ty T, lt a> Foo<(u32, *const T)> for &mut a <T as Bar<>>::Item where [] {}
+            }
+        ]",
+    );
+    let items: Vec<_> = program.crates[0]
+        .items
+        .iter()
+        .map(|i| i.to_string())
+        .collect();
+    expect_test::expect![[r#"
+        [
+            "trait Foo<ty T0> where [for<lt a0> &a0 T0: Bar<>] {}",
+            "impl<ty T0, lt a0> Foo<(u32, *const T0)> for &mut a0 <T0 as Bar>::Item where [] {}",
+        ]
+    "#]]
+    .assert_debug_eq(&items);
+}
+
+/// Terms that don't fit on a line are broken at their delimiters.
+#[test]
+fn line_breaks() {
+    let program: Program = term(
+        "[
+            crate core {
+                struct Pair<ty T, ty U> where [T: Copy<>, U: Copy<>] { first: T, second: U, sum: fn(T, U) -> u32 }
+            }
+        ]",
+    );
+    expect_test::expect![[r#"
+        [
+            crate core {
+                struct Pair<ty T0, ty T1> where [T0: Copy<>, T1: Copy<>] {
+                    first: T0,
+                    second: T1,
+                    sum: fn(T0, T1) -> u32
+                }
+            }
+        ]"#]]
+    .assert_eq(&program.to_string());
+}
+
+/// The printed term parses back to the same term.
+#[test]
+fn parses_back() {
+    let ty: Ty = term("extern \"C\" fn(&static u32, (bool,)) -> !");
+    assert_eq!(ty.to_string(), "extern \"C\" fn(&static u32, (bool)) -> !");
+    assert_eq!(term::<Ty>(&ty.to_string()), ty);
+}
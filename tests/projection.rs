@@ -24,28 +24,32 @@ fn normalize_basic() {
                 Constraints {
                     env: Env {
                         variables: [
-                            !ty_1,
-                            ?ty_2,
+                            !ty_0,
+                            ?ty_1,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {
-                        ?ty_2 => (alias (Iterator :: Item) (rigid (adt Vec) !ty_1)),
+                        ?ty_1 => (alias (Iterator :: Item) (rigid (adt Vec) !ty_0)),
                     },
+                    region_constraints: {},
                 },
                 Constraints {
                     env: Env {
                         variables: [
-                            !ty_1,
-                            ?ty_2,
+                            !ty_0,
+                            ?ty_1,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {
-                        ?ty_2 => !ty_1,
+                        ?ty_1 => !ty_0,
                     },
+                    region_constraints: {},
                 },
             },
         )
@@ -61,12 +65,14 @@ fn normalize_basic() {
                 Constraints {
                     env: Env {
                         variables: [
-                            !ty_1,
+                            !ty_0,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {},
+                    region_constraints: {},
                 },
             },
         )
@@ -82,12 +88,14 @@ fn normalize_basic() {
                 Constraints {
                     env: Env {
                         variables: [
-                            !ty_1,
+                            !ty_0,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {},
+                    region_constraints: {},
                 },
             },
         )
@@ -103,15 +111,17 @@ fn normalize_basic() {
                 Constraints {
                     env: Env {
                         variables: [
-                            !ty_1,
-                            ?ty_2,
+                            !ty_0,
+                            ?ty_1,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {
-                        ?ty_2 => (alias (Iterator :: Item) !ty_1),
+                        ?ty_1 => (alias (Iterator :: Item) !ty_0),
                     },
+                    region_constraints: {},
                 },
             },
         )
@@ -127,12 +137,14 @@ fn normalize_basic() {
                 Constraints {
                     env: Env {
                         variables: [
-                            !ty_1,
+                            !ty_0,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {},
+                    region_constraints: {},
                 },
             },
         )
@@ -148,30 +160,32 @@ fn normalize_basic() {
                 Constraints {
                     env: Env {
                         variables: [
-                            !ty_1,
-                            ?ty_2,
+                            !ty_0,
+                            ?ty_1,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {
-                        ?ty_2 => !ty_1,
+                        ?ty_1 => (rigid (adt Vec) (alias (Iterator :: Item) !ty_0)),
                     },
+                    region_constraints: {},
                 },
                 Constraints {
                     env: Env {
                         variables: [
-                            !ty_1,
-                            ?ty_3,
-                            ?ty_2,
+                            !ty_0,
+                            ?ty_1,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {
-                        ?ty_2 => (rigid (adt Vec) (alias (Iterator :: Item) !ty_1)),
-                        ?ty_3 => (alias (Iterator :: Item) !ty_1),
+                        ?ty_1 => !ty_0,
                     },
+                    region_constraints: {},
                 },
             },
         )
@@ -214,28 +228,32 @@ fn normalize_into_iterator() {
                 Constraints {
                     env: Env {
                         variables: [
-                            !ty_1,
-                            ?ty_2,
+                            !ty_0,
+                            ?ty_1,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {
-                        ?ty_2 => (alias (IntoIterator :: Item) (rigid (adt Vec) !ty_1)),
+                        ?ty_1 => (alias (IntoIterator :: Item) (rigid (adt Vec) !ty_0)),
                     },
+                    region_constraints: {},
                 },
                 Constraints {
                     env: Env {
                         variables: [
-                            !ty_1,
-                            ?ty_2,
+                            !ty_0,
+                            ?ty_1,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {
-                        ?ty_2 => !ty_1,
+                        ?ty_1 => !ty_0,
                     },
+                    region_constraints: {},
                 },
             },
         )
@@ -269,26 +287,30 @@ fn projection_equality() {
                 Constraints {
                     env: Env {
                         variables: [
-                            ?ty_1,
+                            ?ty_0,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {
-                        ?ty_1 => (rigid (scalar u32)),
+                        ?ty_0 => (rigid (scalar u32)),
                     },
+                    region_constraints: {},
                 },
                 Constraints {
                     env: Env {
                         variables: [
-                            ?ty_1,
+                            ?ty_0,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {
-                        ?ty_1 => (alias (Trait1 :: Type) (rigid (adt S))),
+                        ?ty_0 => (alias (Trait1 :: Type) (rigid (adt S))),
                     },
+                    region_constraints: {},
                 },
             },
         )
@@ -304,26 +326,30 @@ fn projection_equality() {
                 Constraints {
                     env: Env {
                         variables: [
-                            ?ty_1,
+                            ?ty_0,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {
-                        ?ty_1 => (rigid (scalar u32)),
+                        ?ty_0 => (rigid (scalar u32)),
                     },
+                    region_constraints: {},
                 },
                 Constraints {
                     env: Env {
                         variables: [
-                            ?ty_1,
+                            ?ty_0,
                         ],
                         coherence_mode: false,
                     },
                     known_true: true,
+                    overflow: false,
                     substitution: {
-                        ?ty_1 => (alias (Trait1 :: Type) (rigid (adt S))),
+                        ?ty_0 => (alias (Trait1 :: Type) (rigid (adt S))),
                     },
+                    region_constraints: {},
                 },
             },
         )
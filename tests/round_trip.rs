@@ -0,0 +1,63 @@
+//! Terms print as text that parses back to the same term, for terms generated
+//! from many seeds (see `formality_types::generate`).
+
+use formality_macros::test;
+use formality_prove::Decls;
+use formality_rust::grammar::{expr::Expr, pattern::Pattern, Crate, Program};
+use formality_types::{
+    generate::check_round_trip,
+    grammar::{Binder, Parameter, Relation, Ty, Wcs},
+};
+
+/// The number of terms of each type to check.
+const COUNT: u64 = 500;
+
+#[test]
+fn ty() {
+    check_round_trip::<Ty>(COUNT);
+}
+
+#[test]
+fn parameter() {
+    check_round_trip::<Parameter>(COUNT);
+}
+
+#[test]
+fn binder() {
+    check_round_trip::<Binder<Ty>>(COUNT);
+}
+
+#[test]
+fn relation() {
+    check_round_trip::<Relation>(COUNT);
+}
+
+#[test]
+fn wcs() {
+    check_round_trip::<Wcs>(COUNT);
+}
+
+#[test]
+fn decls() {
+    check_round_trip::<Decls>(COUNT);
+}
+
+#[test]
+fn expr() {
+    check_round_trip::<Expr>(COUNT);
+}
+
+#[test]
+fn pattern() {
+    check_round_trip::<Pattern>(COUNT);
+}
+
+#[test]
+fn krate() {
+    check_round_trip::<Crate>(COUNT);
+}
+
+#[test]
+fn program() {
+    check_round_trip::<Program>(COUNT);
+}
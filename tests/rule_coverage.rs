@@ -0,0 +1,29 @@
+//! Recording which rules fire, across processes.
+
+use std::process::Command;
+
+use formality_macros::test;
+use formality_types::judgment::coverage::Coverage;
+
+#[test]
+fn record() {
+    let path = std::env::temp_dir().join(format!("rule-coverage-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    // Recording happens in the process that checks the program.
+    let status = Command::new(env!("CARGO_BIN_EXE_formality"))
+        .arg("tests/ui/hello_world.🔬")
+        .env("FORMALITY_RULE_COVERAGE", &path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // Link in the judgments, so that those that never fired are reported too.
+    std::hint::black_box(formality::main as fn() -> _);
+    let coverage = Coverage::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let prove_wc = &coverage.judgments["prove_wc"];
+    assert_eq!(prove_wc.get("positive impl"), Some(&true));
+    assert_eq!(prove_wc.get("closure"), Some(&false));
+    assert!(coverage.unexercised().contains(&("prove_wc", "closure")));
+}
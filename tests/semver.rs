@@ -0,0 +1,153 @@
+use formality_check::{check_semver, BreakingChange, Options};
+use formality_macros::test;
+use formality_rust::grammar::{Crate, Program};
+use formality_types::parse::term;
+
+/// `foo` depends on `core`.
+fn dependencies() -> Program {
+    term(
+        "[
+            crate core {
+                pub trait CoreTrait<> where [] {}
+            }
+        ]",
+    )
+}
+
+fn breaking_changes(old: &str, new: &str) -> Vec<String> {
+    let old: Crate = term(old);
+    let new: Crate = term(new);
+    check_semver(&dependencies(), &old, &new, &Options::default())
+        .unwrap()
+        .iter()
+        .map(BreakingChange::to_string)
+        .collect()
+}
+
+#[test]
+fn unchanged() {
+    let krate = "crate foo {
+        pub trait FooTrait<> where [] {}
+        pub struct FooStruct<> where [] {}
+        impl<> FooTrait<> for FooStruct<> where [] {}
+        pub fn f<ty T>(T) -> () where [T: FooTrait<>] { trusted }
+    }";
+    assert!(breaking_changes(krate, krate).is_empty());
+}
+
+#[test]
+fn removed_impl() {
+    expect_test::expect![[r#"
+        [
+            "impl removed: impl<> FooTrait<> for FooStruct where [] {}",
+        ]
+    "#]]
+    .assert_debug_eq(&breaking_changes(
+        "crate foo {
+            pub trait FooTrait<> where [] {}
+            pub struct FooStruct<> where [] {}
+            impl<> FooTrait<> for FooStruct<> where [] {}
+        }",
+        "crate foo {
+            pub trait FooTrait<> where [] {}
+            pub struct FooStruct<> where [] {}
+        }",
+    ));
+}
+
+/// Replacing an impl with a blanket impl that covers it removes nothing, but the
+/// blanket impl overlaps with the impls of downstream crates.
+#[test]
+fn impl_replaced_by_blanket_impl() {
+    expect_test::expect![[r#"
+        [
+            "impl may overlap with downstream impls: impl<ty T0> FooTrait<> for T0 where [] {}",
+        ]
+    "#]]
+    .assert_debug_eq(&breaking_changes(
+        "crate foo {
+            pub trait FooTrait<> where [] {}
+            pub struct FooStruct<> where [] {}
+            impl<> FooTrait<> for FooStruct<> where [] {}
+        }",
+        "crate foo {
+            pub trait FooTrait<> where [] {}
+            pub struct FooStruct<> where [] {}
+            impl<ty T> FooTrait<> for T where [] {}
+        }",
+    ));
+}
+
+/// A new impl for a local type cannot overlap with downstream impls.
+#[test]
+fn new_local_impl() {
+    assert!(breaking_changes(
+        "crate foo {
+            pub trait FooTrait<> where [] {}
+            pub struct FooStruct<> where [] {}
+        }",
+        "crate foo {
+            pub trait FooTrait<> where [] {}
+            pub struct FooStruct<> where [] {}
+            impl<> FooTrait<> for FooStruct<> where [] {}
+        }",
+    )
+    .is_empty());
+}
+
+/// A blanket impl of a new trait breaks nothing, as no downstream crate implements it yet.
+#[test]
+fn blanket_impl_of_new_trait() {
+    assert!(breaking_changes(
+        "crate foo {}",
+        "crate foo {
+            pub trait FooTrait<> where [] {}
+            impl<ty T> FooTrait<> for T where [] {}
+        }",
+    )
+    .is_empty());
+}
+
+#[test]
+fn strengthened_where_clauses() {
+    expect_test::expect![[r#"
+        [
+            "where-clauses of fn `f` strengthened",
+        ]
+    "#]]
+    .assert_debug_eq(&breaking_changes(
+        "crate foo {
+            pub fn f<ty T>(T) -> () where [] { trusted }
+        }",
+        "crate foo {
+            pub fn f<ty T>(T) -> () where [T: CoreTrait<>] { trusted }
+        }",
+    ));
+}
+
+#[test]
+fn weakened_where_clauses() {
+    assert!(breaking_changes(
+        "crate foo {
+            pub fn f<ty T>(T) -> () where [T: CoreTrait<>] { trusted }
+        }",
+        "crate foo {
+            pub fn f<ty T>(T) -> () where [] { trusted }
+        }",
+    )
+    .is_empty());
+}
+
+/// The where-clauses of private fns are not part of the API.
+#[test]
+fn strengthened_where_clauses_of_private_fn() {
+    assert!(breaking_changes(
+        "crate foo {
+            fn f<ty T>(T) -> () where [] { trusted }
+        }",
+        "crate foo {
+            fn f<ty T>(T) -> () where [T: CoreTrait<>] { trusted }
+        }",
+    )
+    .is_empty());
+}
@@ -0,0 +1,118 @@
+//! Terms and proof results can be stored as JSON and read back.
+
+use formality_macros::test;
+use formality_prove::{test_util::test_prove, Constraints, Decls};
+use formality_rust::grammar::Program;
+use formality_types::{
+    generate::{check_for_all, Generate},
+    grammar::{Ty, Wcs},
+    parse::term,
+    term::Term,
+};
+
+/// The number of terms of each type to check.
+const COUNT: u64 = 200;
+
+#[track_caller]
+fn check_json_round_trip<T: Term + Generate>(count: u64) {
+    check_for_all(count, |t: &T| {
+        let json = serde_json::to_string(t).map_err(|e| e.to_string())?;
+        match serde_json::from_str::<T>(&json) {
+            Ok(read) if read == *t => Ok(()),
+            Ok(read) => Err(format!(
+                "`{json}` reads as a different term\nread: {read:?}"
+            )),
+            Err(err) => Err(format!("`{json}` does not read: {err}")),
+        }
+    })
+}
+
+#[test]
+fn ty() {
+    check_json_round_trip::<Ty>(COUNT);
+}
+
+#[test]
+fn wcs() {
+    check_json_round_trip::<Wcs>(COUNT);
+}
+
+#[test]
+fn decls() {
+    check_json_round_trip::<Decls>(COUNT);
+}
+
+#[test]
+fn program() {
+    check_json_round_trip::<Program>(COUNT);
+}
+
+#[test]
+fn ty_json() {
+    let ty: Ty = term("Vec<u32>");
+    expect_test::expect![[r#"{"RigidTy":{"name":{"AdtId":"Vec"},"parameters":[{"Ty":{"RigidTy":{"name":{"ScalarId":"U32"},"parameters":[]}}}]}}"#]].assert_eq(&serde_json::to_string(&ty).unwrap());
+}
+
+/// Ids are stored as strings and substitutions as lists of pairs.
+#[test]
+fn constraints() {
+    let constraints = test_prove(Decls::empty(), term("exists<ty T> {} => {T = Vec<u32>}"));
+    let json = serde_json::to_string_pretty(&constraints).unwrap();
+    expect_test::expect![[r#"
+        [
+          {
+            "env": {
+              "variables": [
+                {
+                  "ExistentialVar": {
+                    "kind": "Ty",
+                    "var_index": {
+                      "index": 0
+                    }
+                  }
+                }
+              ],
+              "coherence_mode": false
+            },
+            "known_true": true,
+            "overflow": false,
+            "substitution": [
+              [
+                {
+                  "ExistentialVar": {
+                    "kind": "Ty",
+                    "var_index": {
+                      "index": 0
+                    }
+                  }
+                },
+                {
+                  "Ty": {
+                    "RigidTy": {
+                      "name": {
+                        "AdtId": "Vec"
+                      },
+                      "parameters": [
+                        {
+                          "Ty": {
+                            "RigidTy": {
+                              "name": {
+                                "ScalarId": "U32"
+                              },
+                              "parameters": []
+                            }
+                          }
+                        }
+                      ]
+                    }
+                  }
+                }
+              ]
+            ],
+            "region_constraints": []
+          }
+        ]"#]]
+    .assert_eq(&json);
+    let read: Vec<Constraints> = serde_json::from_str(&json).unwrap();
+    assert_eq!(read, constraints.into_iter().collect::<Vec<_>>());
+}
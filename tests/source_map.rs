@@ -0,0 +1,80 @@
+use formality_check::{check_all_crates_errors, Options};
+use formality_macros::test;
+use formality_rust::{grammar::Program, source_map::SourceMap};
+use formality_types::{grammar::CrateId, parse::try_term_with_spans};
+
+const PROGRAM: &str = "[
+    crate core {
+        trait Foo<> where [] {}
+
+        mod m {
+            struct S<ty T> where [T: Foo<>] {}
+        }
+
+        impl<ty T> Foo<> for T where [] {}
+        impl<> Foo<> for u32 where [] {}
+    }
+]";
+
+fn parse() -> (Program, SourceMap) {
+    let (program, spans) = try_term_with_spans(PROGRAM).unwrap();
+    let source_map = SourceMap::new(&program, &spans);
+    (program, source_map)
+}
+
+/// The items of modules are in place of the modules, as after resolution.
+#[test]
+fn item_spans() {
+    let (_, source_map) = parse();
+    let krate = CrateId::new("core");
+    let texts: Vec<_> = (0..)
+        .map_while(|i| source_map.item(&krate, i))
+        .map(|spans| {
+            let wcs: Vec<_> = spans
+                .where_clauses
+                .iter()
+                .map(|wc| &PROGRAM[wc.start..wc.end])
+                .collect();
+            (&PROGRAM[spans.item.start..spans.item.end], wcs)
+        })
+        .collect();
+    expect_test::expect![[r#"
+        [
+            (
+                "trait Foo<> where [] {}",
+                [],
+            ),
+            (
+                "struct S<ty T> where [T: Foo<>] {}",
+                [
+                    "T: Foo<>",
+                ],
+            ),
+            (
+                "impl<ty T> Foo<> for T where [] {}",
+                [],
+            ),
+            (
+                "impl<> Foo<> for u32 where [] {}",
+                [],
+            ),
+        ]
+    "#]]
+    .assert_debug_eq(&texts);
+}
+
+#[test]
+fn line_column() {
+    let (_, source_map) = parse();
+    let span = source_map.item(&CrateId::new("core"), 1).unwrap().item;
+    assert_eq!(span.line_column(PROGRAM), (6, 13));
+}
+
+/// Errors of coherence are about the first impl of the overlapping ones.
+#[test]
+fn error_items() {
+    let (program, _) = parse();
+    let errors = check_all_crates_errors(&program, &Options::default());
+    let items: Vec<_> = errors.iter().map(|e| e.item).collect();
+    assert_eq!(items, vec![Some(2)]);
+}
@@ -0,0 +1,156 @@
+//! The functions exposed to JavaScript, called natively.
+
+use formality_macros::test;
+use formality_wasm::{check_program, prove};
+
+const PROGRAM: &str = "[
+    crate core {
+        trait Debug<> where [] {}
+        struct Foo<> where [] {}
+        impl<> Debug<> for Foo<> where [] {}
+        impl<> Debug<> for Foo<> where [] {}
+    }
+]";
+
+fn pretty(json: String) -> String {
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    format!("{value:#}")
+}
+
+#[test]
+fn check_program_report() {
+    expect_test::expect![[r#"
+        {
+          "report": {
+            "errors": [
+              {
+                "code": "E0119",
+                "crate": "core",
+                "item": 2,
+                "message": "duplicate impl in current crate: impl<> Debug<> for Foo where [] {}",
+                "primary": " impl <>  Debug < > for (rigid (adt Foo)) where [] { }",
+                "related": [],
+                "rendered": "duplicate impl in current crate: impl<> Debug<> for Foo where [] {}",
+                "span": {
+                  "column": 9,
+                  "end": 130,
+                  "line": 5,
+                  "start": 94
+                }
+              }
+            ],
+            "items": [
+              {
+                "crate": "core",
+                "errors": [],
+                "index": 0,
+                "item": "trait Debug<> where [] {}",
+                "proof_trees": [],
+                "span": {
+                  "column": 9,
+                  "end": 52,
+                  "line": 3,
+                  "start": 27
+                },
+                "verdict": "pass"
+              },
+              {
+                "crate": "core",
+                "errors": [],
+                "index": 1,
+                "item": "struct Foo<> where [] {}",
+                "proof_trees": [],
+                "span": {
+                  "column": 9,
+                  "end": 85,
+                  "line": 4,
+                  "start": 61
+                },
+                "verdict": "pass"
+              },
+              {
+                "crate": "core",
+                "errors": [
+                  0
+                ],
+                "index": 2,
+                "item": "impl<> Debug<> for Foo where [] {}",
+                "proof_trees": [],
+                "span": {
+                  "column": 9,
+                  "end": 130,
+                  "line": 5,
+                  "start": 94
+                },
+                "verdict": "fail"
+              },
+              {
+                "crate": "core",
+                "errors": [],
+                "index": 3,
+                "item": "impl<> Debug<> for Foo where [] {}",
+                "proof_trees": [],
+                "span": {
+                  "column": 9,
+                  "end": 175,
+                  "line": 6,
+                  "start": 139
+                },
+                "verdict": "pass"
+              }
+            ]
+          },
+          "syntax_errors": []
+        }"#]]
+    .assert_eq(&pretty(check_program(PROGRAM)));
+}
+
+#[test]
+fn check_program_syntax_errors() {
+    expect_test::expect![[r#"
+        {
+          "report": null,
+          "syntax_errors": [
+            {
+              "message": "identifier expected",
+              "span": {
+                "column": 22,
+                "end": 21,
+                "line": 1,
+                "start": 21
+              }
+            }
+          ]
+        }"#]]
+    .assert_eq(&pretty(check_program("[crate core { struct }]")));
+}
+
+#[test]
+fn prove_goal() {
+    let value: serde_json::Value =
+        serde_json::from_str(&prove(PROGRAM, "{} => {Debug(Foo)}")).unwrap();
+    assert!(value["error"].is_null());
+    assert_eq!(value["solutions"].as_array().unwrap().len(), 1);
+    expect_test::expect![[r#"
+        {
+          "env": {
+            "coherence_mode": false,
+            "variables": []
+          },
+          "known_true": true,
+          "overflow": false,
+          "region_constraints": [],
+          "substitution": []
+        }"#]]
+    .assert_eq(&format!("{:#}", value["solutions"][0]["constraints"]));
+}
+
+#[test]
+fn prove_syntax_error() {
+    expect_test::expect![[r#"
+        {
+          "error": "`WcData` expected\n\nCaused by:\n    0: \n    1: failed to parse {} => {",
+          "solutions": []
+        }"#]]
+    .assert_eq(&pretty(prove(PROGRAM, "{} => {")));
+}
@@ -0,0 +1,123 @@
+use formality::test_where_clause;
+
+const PROGRAM: &str = "[
+    crate test {
+        trait Copy<> where [] {}
+
+        struct Foo<ty T> where [T: Copy<>] {}
+
+        struct Wrapper<lt a, ty T> where [] { r: &a T }
+
+        impl<> Copy<> for u32 where [] {}
+    }
+]";
+
+#[test]
+fn adt_where_clauses_hold() {
+    expect_test::expect![[r#"
+        Ok(
+            {
+                Constraints {
+                    env: Env {
+                        variables: [],
+                        coherence_mode: false,
+                    },
+                    known_true: true,
+                    overflow: false,
+                    substitution: {},
+                    region_constraints: {},
+                },
+            },
+        )
+    "#]]
+    .assert_debug_eq(&test_where_clause(PROGRAM, "{} => { @wf(Foo<u32>) }"));
+}
+
+#[test]
+fn adt_where_clauses_do_not_hold() {
+    expect_test::expect![[r#"
+        Ok(
+            {},
+        )
+    "#]]
+    .assert_debug_eq(&test_where_clause(
+        PROGRAM,
+        "forall<ty T> {} => { @wf(Foo<T>) }",
+    ));
+}
+
+#[test]
+fn adt_where_clauses_assumed() {
+    expect_test::expect![[r#"
+        Ok(
+            {
+                Constraints {
+                    env: Env {
+                        variables: [
+                            !ty_0,
+                        ],
+                        coherence_mode: false,
+                    },
+                    known_true: true,
+                    overflow: false,
+                    substitution: {},
+                    region_constraints: {},
+                },
+            },
+        )
+    "#]]
+    .assert_debug_eq(&test_where_clause(
+        PROGRAM,
+        "forall<ty T> { Copy(T) } => { @wf(Foo<T>) }",
+    ));
+}
+
+/// `&'a T` needs `T: 'a`, as does `Wrapper<'a, T>` for its field of that type.
+#[test]
+fn reference_needs_outlives() {
+    expect_test::expect![[r#"
+        Ok(
+            {},
+        )
+    "#]]
+    .assert_debug_eq(&test_where_clause(
+        PROGRAM,
+        "forall<lt a, ty T> {} => { @wf(&a T) }",
+    ));
+    expect_test::expect![[r#"
+        Ok(
+            {},
+        )
+    "#]]
+    .assert_debug_eq(&test_where_clause(
+        PROGRAM,
+        "forall<lt a, ty T> {} => { @wf(Wrapper<a, T>) }",
+    ));
+}
+
+#[test]
+fn reference_given_outlives() {
+    expect_test::expect![[r#"
+        Ok(
+            {
+                Constraints {
+                    env: Env {
+                        variables: [
+                            !lt_0,
+                            !ty_1,
+                        ],
+                        coherence_mode: false,
+                    },
+                    known_true: true,
+                    overflow: false,
+                    substitution: {},
+                    region_constraints: {},
+                },
+            },
+        )
+    "#]]
+    .assert_debug_eq(&test_where_clause(
+        PROGRAM,
+        "forall<lt a, ty T> { T: a } => { @wf(Wrapper<a, &a T>) }",
+    ));
+}
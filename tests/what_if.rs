@@ -0,0 +1,72 @@
+use formality_check::{check_all_crates, check_what_if, Options, WhatIf};
+use formality_macros::test;
+use formality_rust::grammar::{CrateItem, Program};
+use formality_types::parse::term;
+
+/// `foo` implements `FooTrait` for all `T: CoreTrait` and for its own `FooStruct`,
+/// which `core` cannot implement `CoreTrait` for.
+fn program() -> Program {
+    term(
+        "[
+            crate core {
+                pub trait CoreTrait<> where [] {}
+            },
+            crate foo {
+                trait FooTrait<> where [] {}
+                struct FooStruct<> where [] {}
+                impl<ty T> FooTrait<> for T where [T: CoreTrait<>] {}
+                impl<> FooTrait<> for FooStruct<> where [] {}
+            }
+        ]",
+    )
+}
+
+#[test]
+fn base_program_ok() {
+    check_all_crates(&program()).unwrap();
+}
+
+/// Adding a blanket impl to `core` breaks `foo`: its impls would overlap.
+#[test]
+fn blanket_impl_upstream_breaks_downstream() {
+    let item: CrateItem = term("impl<ty T> CoreTrait<> for T where [] {}");
+    let what_if = WhatIf {
+        items: vec![(term("core"), item)],
+        ..WhatIf::default()
+    };
+    expect_test::expect![[r#"
+        Err(
+            "impls may overlap:\nimpl<ty T0> FooTrait<> for T0 where [T0: CoreTrait<>] {}\nimpl<> FooTrait<> for FooStruct where [] {}",
+        )
+    "#]]
+    .assert_debug_eq(&check_what_if(&program(), &what_if, &Options::default()).map_err(|e| e.to_string()));
+}
+
+/// Adding an impl for a type of `core` itself does not break `foo`.
+#[test]
+fn local_impl_upstream_ok() {
+    let items: Vec<CrateItem> = vec![
+        term("struct CoreStruct<> where [] {}"),
+        term("impl<> CoreTrait<> for CoreStruct<> where [] {}"),
+    ];
+    let what_if = WhatIf {
+        items: items.into_iter().map(|item| (term("core"), item)).collect(),
+        ..WhatIf::default()
+    };
+    check_what_if(&program(), &what_if, &Options::default()).unwrap();
+}
+
+/// A downstream crate that implements `CoreTrait` for its own type breaks nothing.
+#[test]
+fn downstream_crate_ok() {
+    let what_if = WhatIf {
+        crates: vec![term(
+            "crate bar {
+                struct BarStruct<> where [] {}
+                impl<> CoreTrait<> for BarStruct<> where [] {}
+            }",
+        )],
+        ..WhatIf::default()
+    };
+    check_what_if(&program(), &what_if, &Options::default()).unwrap();
+}